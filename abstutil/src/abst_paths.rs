@@ -147,6 +147,12 @@ pub fn path_scenario(name: &MapName, scenario_name: &str) -> String {
     }
     bin
 }
+pub fn path_scenario_generator(name: &MapName, scenario_name: &str) -> String {
+    path(format!(
+        "system/{}/scenario_generators/{}/{}.json",
+        name.city, name.map, scenario_name
+    ))
+}
 pub fn path_all_scenarios(name: &MapName) -> String {
     path(format!("system/{}/scenarios/{}", name.city, name.map))
 }
@@ -185,6 +191,39 @@ pub fn path_all_edits(name: &MapName) -> String {
     path(format!("player/edits/{}/{}", name.city, name.map))
 }
 
+pub fn path_shared_proposal(name: &MapName, proposal_name: &str) -> String {
+    path(format!(
+        "player/shared_proposals/{}/{}/{}.json.gz",
+        name.city, name.map, proposal_name
+    ))
+}
+pub fn path_all_shared_proposals(name: &MapName) -> String {
+    path(format!(
+        "player/shared_proposals/{}/{}",
+        name.city, name.map
+    ))
+}
+
+pub fn path_neighborhood(name: &MapName, neighborhood_name: &str) -> String {
+    path(format!(
+        "player/neighborhoods/{}/{}/{}.json",
+        name.city, name.map, neighborhood_name
+    ))
+}
+pub fn path_all_neighborhoods(name: &MapName) -> String {
+    path(format!("player/neighborhoods/{}/{}", name.city, name.map))
+}
+
+pub fn path_trace(name: &MapName, trace_name: &str) -> String {
+    path(format!(
+        "player/traces/{}/{}/{}.bin",
+        name.city, name.map, trace_name
+    ))
+}
+pub fn path_all_traces(name: &MapName) -> String {
+    path(format!("player/traces/{}/{}", name.city, name.map))
+}
+
 pub fn path_save(name: &MapName, edits_name: &str, run_name: &str, time: String) -> String {
     path(format!(
         "player/saves/{}/{}/{}_{}/{}.bin",
@@ -207,3 +246,10 @@ pub fn path_popdat() -> String {
 pub fn path_raw_map(name: &MapName) -> String {
     path(format!("input/{}/raw_maps/{}.bin", name.city, name.map))
 }
+
+pub fn path_consolidation_hints(name: &MapName) -> String {
+    path(format!(
+        "input/{}/consolidation_hints/{}.json",
+        name.city, name.map
+    ))
+}
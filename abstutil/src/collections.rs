@@ -3,7 +3,8 @@ use std::collections::{BTreeMap, BTreeSet};
 use std::marker::PhantomData;
 
 use itertools::Itertools;
-use serde::{Deserialize, Serialize};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 // TODO Ideally derive Serialize and Deserialize, but I can't seem to express the lifetimes
 // correctly.
@@ -272,16 +273,28 @@ pub trait IndexableKey {
 /// A drop-in replacement for `BTreeMap`, where the keys have the property of being array indices.
 /// Some values may be missing. Much more efficient at operations on individual objects, because
 /// it just becomes a simple array lookup.
-#[derive(Serialize, Deserialize, Clone)]
+///
+/// Values live in a pool, and each key just points to its slot there. A removed value's slot is
+/// reused by the next insertion, so the big values only take space for the most that are present
+/// at once, not for every key ever used.
+#[derive(Clone)]
 pub struct FixedMap<K: IndexableKey, V> {
-    inner: Vec<Option<V>>,
+    /// For each key, the slot in `pool` holding its value, or `EMPTY_SLOT`
+    slots: Vec<u32>,
+    pool: Vec<Option<V>>,
+    /// Slots in `pool` that can be reused
+    free: Vec<u32>,
     key_type: PhantomData<K>,
 }
 
+const EMPTY_SLOT: u32 = u32::MAX;
+
 impl<K: IndexableKey, V> FixedMap<K, V> {
     pub fn new() -> FixedMap<K, V> {
         FixedMap {
-            inner: Vec::new(),
+            slots: Vec::new(),
+            pool: Vec::new(),
+            free: Vec::new(),
             key_type: PhantomData,
         }
     }
@@ -291,33 +304,61 @@ impl<K: IndexableKey, V> FixedMap<K, V> {
         // Depending on the order of calls, this could wind up pushing one value at a time. It may
         // be more efficient to resize less times and allocate more, but it'll require the caller
         // to know about how many values it'll need.
-        if idx >= self.inner.len() {
-            self.inner.resize_with(idx + 1, || None);
+        if idx >= self.slots.len() {
+            self.slots.resize(idx + 1, EMPTY_SLOT);
+        }
+        if self.slots[idx] != EMPTY_SLOT {
+            self.pool[self.slots[idx] as usize] = Some(value);
+            return;
         }
-        self.inner[idx] = Some(value);
+        self.slots[idx] = if let Some(slot) = self.free.pop() {
+            self.pool[slot as usize] = Some(value);
+            slot
+        } else {
+            self.pool.push(Some(value));
+            (self.pool.len() - 1) as u32
+        };
     }
 
     pub fn get(&self, key: &K) -> Option<&V> {
-        self.inner.get(key.index())?.as_ref()
+        let slot = self.slot(key)?;
+        self.pool[slot].as_ref()
     }
 
     pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
-        self.inner.get_mut(key.index())?.as_mut()
+        let slot = self.slot(key)?;
+        self.pool[slot].as_mut()
     }
 
     pub fn contains_key(&self, key: &K) -> bool {
-        self.inner
-            .get(key.index())
-            .map(|x| x.is_some())
-            .unwrap_or(false)
+        self.slot(key).is_some()
     }
 
     pub fn remove(&mut self, key: &K) -> Option<V> {
-        self.inner.get_mut(key.index())?.take()
+        let slot = self.slot(key)?;
+        self.slots[key.index()] = EMPTY_SLOT;
+        self.free.push(slot as u32);
+        self.pool[slot].take()
     }
 
-    pub fn values(&self) -> std::iter::Flatten<std::slice::Iter<'_, std::option::Option<V>>> {
-        self.inner.iter().flatten()
+    /// Iterates in the order of the keys.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.slots
+            .iter()
+            .filter(|slot| **slot != EMPTY_SLOT)
+            .map(move |slot| self.pool[*slot as usize].as_ref().unwrap())
+    }
+
+    /// How many values can be stored before the pool has to grow.
+    pub fn capacity(&self) -> usize {
+        self.pool.len()
+    }
+
+    fn slot(&self, key: &K) -> Option<usize> {
+        match self.slots.get(key.index()) {
+            Some(slot) if *slot != EMPTY_SLOT => Some(*slot as usize),
+            _ => None,
+        }
     }
 }
 
@@ -325,6 +366,51 @@ impl<K: IndexableKey, V> std::ops::Index<&K> for FixedMap<K, V> {
     type Output = V;
 
     fn index(&self, key: &K) -> &Self::Output {
-        self.inner[key.index()].as_ref().unwrap()
+        self.get(key).unwrap()
+    }
+}
+
+// Serialized as if every key had its own slot, like before the values were pooled, so old
+// savestates still load.
+impl<K: IndexableKey, V: Serialize> Serialize for FixedMap<K, V> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let inner: Vec<Option<&V>> = self
+            .slots
+            .iter()
+            .map(|slot| {
+                if *slot == EMPTY_SLOT {
+                    None
+                } else {
+                    self.pool[*slot as usize].as_ref()
+                }
+            })
+            .collect();
+        let mut state = s.serialize_struct("FixedMap", 2)?;
+        state.serialize_field("inner", &inner)?;
+        state.serialize_field("key_type", &PhantomData::<()>)?;
+        state.end()
+    }
+}
+
+impl<'de, K: IndexableKey, V: Deserialize<'de>> Deserialize<'de> for FixedMap<K, V> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<FixedMap<K, V>, D::Error> {
+        #[derive(Deserialize)]
+        struct RawFixedMap<V> {
+            inner: Vec<Option<V>>,
+            #[serde(rename = "key_type")]
+            _key_type: PhantomData<()>,
+        }
+
+        let raw = RawFixedMap::<V>::deserialize(d)?;
+        let mut map = FixedMap::new();
+        for value in raw.inner {
+            if value.is_some() {
+                map.slots.push(map.pool.len() as u32);
+                map.pool.push(value);
+            } else {
+                map.slots.push(EMPTY_SLOT);
+            }
+        }
+        Ok(map)
     }
 }
@@ -2,7 +2,10 @@ use std::collections::BTreeMap;
 
 use serde::de::DeserializeOwned;
 
-use crate::{basename, list_dir, maybe_read_binary, parent_path, slurp_file, Timer};
+use crate::{
+    basename, list_dir, maybe_read_binary, maybe_read_versioned_binary, parent_path, slurp_file,
+    Timer, Versioned,
+};
 
 pub fn maybe_read_json<T: DeserializeOwned>(path: String, timer: &mut Timer) -> Result<T, String> {
     if !path.ends_with(".json") && !path.ends_with(".geojson") {
@@ -31,6 +34,13 @@ pub fn read_binary<T: DeserializeOwned>(path: String, timer: &mut Timer) -> T {
     }
 }
 
+pub fn read_versioned_binary<T: Versioned>(path: String, timer: &mut Timer) -> T {
+    match maybe_read_versioned_binary(path.clone(), timer) {
+        Ok(obj) => obj,
+        Err(err) => panic!("Couldn't read_versioned_binary({}): {}", path, err),
+    }
+}
+
 /// May be a JSON or binary file
 pub fn read_object<T: DeserializeOwned>(path: String, timer: &mut Timer) -> Result<T, String> {
     if path.ends_with(".bin") {
@@ -48,6 +58,23 @@ pub fn must_read_object<T: DeserializeOwned>(path: String, timer: &mut Timer) ->
     }
 }
 
+/// Like `read_object`, but binary files start with a version header.
+pub fn read_versioned_object<T: Versioned>(path: String, timer: &mut Timer) -> Result<T, String> {
+    if path.ends_with(".bin") {
+        maybe_read_versioned_binary(path, timer)
+    } else {
+        maybe_read_json(path, timer)
+    }
+}
+
+/// Like `must_read_object`, but binary files start with a version header.
+pub fn must_read_versioned_object<T: Versioned>(path: String, timer: &mut Timer) -> T {
+    match read_versioned_object(path.clone(), timer) {
+        Ok(obj) => obj,
+        Err(err) => panic!("Couldn't read_versioned_object({}): {}", path, err),
+    }
+}
+
 /// Keeps file extensions
 pub fn find_prev_file(orig: String) -> Option<String> {
     let mut files = list_dir(parent_path(&orig));
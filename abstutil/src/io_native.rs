@@ -11,7 +11,10 @@ use serde::Serialize;
 
 pub use crate::io::*;
 use crate::time::{clear_current_line, prettyprint_time};
-use crate::{elapsed_seconds, prettyprint_usize, to_json, Timer, PROGRESS_FREQUENCY_SECONDS};
+use crate::{
+    elapsed_seconds, from_versioned_binary_reader, prettyprint_usize, to_json, to_versioned_binary,
+    Timer, Versioned, PROGRESS_FREQUENCY_SECONDS,
+};
 
 pub fn file_exists<I: Into<String>>(path: I) -> bool {
     Path::new(&path.into()).exists()
@@ -55,6 +58,18 @@ pub fn maybe_read_binary<T: DeserializeOwned>(
     bincode::deserialize_from(timer).map_err(|x| x.to_string())
 }
 
+pub fn maybe_read_versioned_binary<T: Versioned>(
+    path: String,
+    timer: &mut Timer,
+) -> Result<T, String> {
+    if !path.ends_with(".bin") {
+        panic!("read_versioned_binary needs {} to end with .bin", path);
+    }
+
+    timer.read_file(&path)?;
+    from_versioned_binary_reader(timer)
+}
+
 // TODO Idea: Have a wrapper type DotJSON(...) and DotBin(...) to distinguish raw path strings
 fn maybe_write_json<T: Serialize>(path: &str, obj: &T) -> Result<(), Box<dyn Error>> {
     if !path.ends_with(".json") {
@@ -94,6 +109,19 @@ pub fn write_binary<T: Serialize>(path: String, obj: &T) {
     println!("Wrote {}", path);
 }
 
+pub fn write_versioned_binary<T: Versioned>(path: String, obj: &T) {
+    if !path.ends_with(".bin") {
+        panic!("write_versioned_binary needs {} to end with .bin", path);
+    }
+    std::fs::create_dir_all(std::path::Path::new(&path).parent().unwrap())
+        .expect("Creating parent dir failed");
+
+    if let Err(err) = std::fs::write(&path, to_versioned_binary(obj)) {
+        panic!("Can't write_versioned_binary({}): {}", path, err);
+    }
+    println!("Wrote {}", path);
+}
+
 /// Idempotent
 pub fn delete_file<I: Into<String>>(path: I) {
     let path = path.into();
@@ -7,7 +7,7 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 pub use crate::io::*;
-use crate::{path_player, to_json, Manifest, Timer};
+use crate::{from_versioned_binary, path_player, to_json, Manifest, Timer, Versioned};
 
 // Bring in everything from data/system/ matching one of the prefixes -- aka, no scenarios, and
 // only the smallest map. Everything else has to be dynamically loaded over HTTP.
@@ -99,6 +99,17 @@ pub fn maybe_read_binary<T: DeserializeOwned>(path: String, _: &mut Timer) -> Re
     }
 }
 
+pub fn maybe_read_versioned_binary<T: Versioned>(path: String, _: &mut Timer) -> Result<T, String> {
+    if let Some(raw) = SYSTEM_DATA.get_file(path.trim_start_matches("../data/system/")) {
+        from_versioned_binary(raw.contents())
+    } else {
+        Err(format!(
+            "Can't maybe_read_versioned_binary {}, it doesn't exist",
+            path
+        ))
+    }
+}
+
 pub fn write_json<T: Serialize>(path: String, obj: &T) {
     // Only save for data/player, for now
     if !path.starts_with(&path_player("")) {
@@ -116,6 +127,11 @@ pub fn write_binary<T: Serialize>(path: String, _obj: &T) {
     warn!("Not saving {}", path);
 }
 
+pub fn write_versioned_binary<T: Versioned>(path: String, _obj: &T) {
+    // TODO
+    warn!("Not saving {}", path);
+}
+
 pub fn delete_file<I: Into<String>>(path: I) {
     // TODO
     warn!("Not deleting {}", path.into());
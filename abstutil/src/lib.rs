@@ -27,6 +27,7 @@ pub use logger::*;
 pub use process::*;
 pub use time::*;
 pub use utils::*;
+pub use versioning::*;
 
 mod abst_data;
 mod abst_paths;
@@ -38,5 +39,6 @@ mod process;
 mod serde;
 mod time;
 mod utils;
+mod versioning;
 
 const PROGRESS_FREQUENCY_SECONDS: f64 = 0.2;
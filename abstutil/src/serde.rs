@@ -25,6 +25,11 @@ pub fn from_json_reader<R: std::io::Read, T: DeserializeOwned>(reader: R) -> Res
     serde_json::from_reader(reader).map_err(|x| x.to_string())
 }
 
+/// Serializes an object to the bincode format.
+pub fn to_binary<T: Serialize>(obj: &T) -> Vec<u8> {
+    bincode::serialize(obj).unwrap()
+}
+
 /// Deserializes an object from the bincode format.
 pub fn from_binary<T: DeserializeOwned>(raw: &Vec<u8>) -> Result<T, String> {
     bincode::deserialize(raw).map_err(|x| x.to_string())
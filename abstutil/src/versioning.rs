@@ -0,0 +1,325 @@
+//! Some binary files, like maps and simulation savestates, start with a short header recording the
+//! version of the format they were written with. bincode isn't self-describing, so without this,
+//! any change to a struct makes old files silently fail to deserialize. When a change like that
+//! happens, bump the type's version and register a migration that upgrades the older bytes.
+//!
+//! Adding a field somewhere deep inside a type is the common case. Mark the field with
+//! `added_in_format!` and register `upgrade_added_fields` as the migration.
+
+use std::cell::Cell;
+use std::io::{Cursor, Read};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Marks a file with a version header. Files written before versioning start with something else.
+const MAGIC: &[u8; 8] = b"ABSTVERS";
+const HEADER_BYTES: usize = 12;
+
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Names the kind of file in error messages, like "map"
+    const FORMAT: &'static str;
+    /// Increase this whenever the serialized format changes, and add a migration from the old
+    /// version.
+    const VERSION: u32;
+
+    /// Upgrades older files, one version at a time. Files written before versioning existed are
+    /// version 0. If the format changed before versioning was added, those files could have any of
+    /// several layouts, so leave out a migration from 0; loading them fails with a message to
+    /// regenerate them.
+    fn migrations() -> Vec<Migration>;
+}
+
+pub struct Migration {
+    /// Files of this version are upgraded to the next version
+    pub from: u32,
+    pub description: &'static str,
+    /// Transforms the serialized payload, without the header
+    pub upgrade: fn(Vec<u8>) -> Result<Vec<u8>, String>,
+}
+
+impl Migration {
+    /// Only for formats that didn't change between before versioning existed and version 1. Then
+    /// unversioned files have the same payload as version 1, just without the header.
+    pub fn add_header() -> Migration {
+        Migration {
+            from: 0,
+            description: "add a version header",
+            upgrade: Ok,
+        }
+    }
+}
+
+thread_local! {
+    /// While a migration runs, the format being read or written. Fields added after this format
+    /// are left out.
+    static MIGRATING_FORMAT: Cell<Option<u32>> = Cell::new(None);
+}
+
+/// Upgrades a payload that's only missing fields marked with `added_in_format!`, by reading it as
+/// format `from` and writing it back out as `from + 1`. New fields start with their default value.
+pub fn upgrade_added_fields<T: Serialize + DeserializeOwned>(
+    bytes: Vec<u8>,
+    from: u32,
+) -> Result<Vec<u8>, String> {
+    upgrade_added_fields_with::<T, _>(bytes, from, |_| {})
+}
+
+/// Like `upgrade_added_fields`, but for new fields that can't just start with a default value.
+/// `fill_in` is called after reading the old format, before writing the new one.
+pub fn upgrade_added_fields_with<T: Serialize + DeserializeOwned, F: FnOnce(&mut T)>(
+    bytes: Vec<u8>,
+    from: u32,
+    fill_in: F,
+) -> Result<Vec<u8>, String> {
+    MIGRATING_FORMAT.with(|format| format.set(Some(from)));
+    let result = bincode::deserialize::<T>(&bytes)
+        .and_then(|mut obj| {
+            MIGRATING_FORMAT.with(|format| format.set(None));
+            fill_in(&mut obj);
+            MIGRATING_FORMAT.with(|format| format.set(Some(from + 1)));
+            bincode::serialize(&obj)
+        })
+        .map_err(|err| err.to_string());
+    MIGRATING_FORMAT.with(|format| format.set(None));
+    result
+}
+
+fn missing_before(since: u32) -> bool {
+    MIGRATING_FORMAT.with(|format| format.get().map(|v| v < since).unwrap_or(false))
+}
+
+/// Use `added_in_format!` instead of calling this directly.
+pub fn serialize_added_field<T: Serialize, S: Serializer>(
+    since: u32,
+    value: &T,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    if missing_before(since) {
+        // bincode writes nothing for this
+        s.serialize_unit()
+    } else {
+        value.serialize(s)
+    }
+}
+
+/// Use `added_in_format!` instead of calling this directly.
+pub fn deserialize_added_field<'de, T: Deserialize<'de> + Default, D: Deserializer<'de>>(
+    since: u32,
+    d: D,
+) -> Result<T, D::Error> {
+    deserialize_added_field_or(since, d, T::default)
+}
+
+/// For fields without a sensible default, use this from a custom `#[serde(with = "...")]`
+/// module. Older formats get `missing()`, which `upgrade_added_fields_with` should replace.
+pub fn deserialize_added_field_or<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+    since: u32,
+    d: D,
+    missing: impl FnOnce() -> T,
+) -> Result<T, D::Error> {
+    if missing_before(since) {
+        Ok(missing())
+    } else {
+        T::deserialize(d)
+    }
+}
+
+/// Declares a module for `#[serde(with = "...")]`, marking fields that older formats don't have.
+/// `added_in_format!(added_in_v3, 3)` is for fields first written in format 3. The field's type
+/// must implement `Default`.
+#[macro_export]
+macro_rules! added_in_format {
+    ($name:ident, $version:expr) => {
+        pub(crate) mod $name {
+            pub fn serialize<T: serde::Serialize, S: serde::Serializer>(
+                value: &T,
+                s: S,
+            ) -> Result<S::Ok, S::Error> {
+                $crate::serialize_added_field($version, value, s)
+            }
+
+            pub fn deserialize<
+                'de,
+                T: serde::Deserialize<'de> + Default,
+                D: serde::Deserializer<'de>,
+            >(
+                d: D,
+            ) -> Result<T, D::Error> {
+                $crate::deserialize_added_field($version, d)
+            }
+        }
+    };
+}
+
+/// Serializes an object to bincode, with a version header.
+pub fn to_versioned_binary<T: Versioned>(obj: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&T::VERSION.to_le_bytes());
+    bytes.extend(bincode::serialize(obj).unwrap());
+    bytes
+}
+
+/// Deserializes an object from bincode, upgrading it from an older version if needed.
+pub fn from_versioned_binary<T: Versioned>(raw: &[u8]) -> Result<T, String> {
+    from_versioned_binary_reader(raw)
+}
+
+/// Deserializes an object from bincode, from a reader, upgrading it from an older version if
+/// needed. If the file is already the latest version, it's streamed directly from the reader.
+pub fn from_versioned_binary_reader<R: Read, T: Versioned>(mut reader: R) -> Result<T, String> {
+    let mut header = Vec::new();
+    (&mut reader)
+        .take(HEADER_BYTES as u64)
+        .read_to_end(&mut header)
+        .map_err(|err| err.to_string())?;
+    let version = if header.len() == HEADER_BYTES && &header[0..MAGIC.len()] == MAGIC {
+        let mut version = [0; 4];
+        version.copy_from_slice(&header[MAGIC.len()..]);
+        u32::from_le_bytes(version)
+    } else {
+        0
+    };
+
+    if version == T::VERSION {
+        return bincode::deserialize_from(reader).map_err(|err| err.to_string());
+    }
+    if version > T::VERSION {
+        return Err(format!(
+            "this {} was written by a newer version (format {}, but only {} is understood). \
+             You may need to update your build.",
+            T::FORMAT,
+            version,
+            T::VERSION
+        ));
+    }
+
+    // An unversioned file has no header, so the bytes already read are part of the payload
+    let mut payload = if version == 0 { header } else { Vec::new() };
+    reader
+        .read_to_end(&mut payload)
+        .map_err(|err| err.to_string())?;
+    let migrations = T::migrations();
+    for from in version..T::VERSION {
+        let migration = migrations.iter().find(|m| m.from == from).ok_or_else(|| {
+            if from == 0 {
+                format!(
+                    "this {} is from before files were versioned, so it can't be upgraded. \
+                     Regenerate it with this version.",
+                    T::FORMAT
+                )
+            } else {
+                format!(
+                    "this {} is format {}, and there's no way to upgrade it to {}",
+                    T::FORMAT,
+                    from,
+                    from + 1
+                )
+            }
+        })?;
+        info!(
+            "Upgrading {} from format {} to {}: {}",
+            T::FORMAT,
+            from,
+            from + 1,
+            migration.description
+        );
+        payload = (migration.upgrade)(payload).map_err(|err| {
+            format!(
+                "couldn't upgrade {} from format {}: {}",
+                T::FORMAT,
+                from,
+                err
+            )
+        })?;
+    }
+    bincode::deserialize_from(Cursor::new(payload)).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Thing {
+        name: String,
+        count: usize,
+    }
+
+    impl Versioned for Thing {
+        const FORMAT: &'static str = "thing";
+        const VERSION: u32 = 1;
+
+        fn migrations() -> Vec<Migration> {
+            vec![Migration::add_header()]
+        }
+    }
+
+    #[test]
+    fn versions() {
+        let thing = Thing {
+            name: "x".to_string(),
+            count: 42,
+        };
+        let versioned = to_versioned_binary(&thing);
+        assert_eq!(Ok(&thing), from_versioned_binary(&versioned).as_ref());
+
+        // Files from before versioning existed still load
+        let unversioned = bincode::serialize(&thing).unwrap();
+        assert_eq!(Ok(&thing), from_versioned_binary(&unversioned).as_ref());
+
+        let mut newer = versioned;
+        newer[MAGIC.len()] = 2;
+        assert!(from_versioned_binary::<Thing>(&newer).is_err());
+    }
+
+    added_in_format!(added_in_v2, 2);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Widget {
+        name: String,
+        #[serde(with = "added_in_v2")]
+        parts: Vec<usize>,
+    }
+
+    impl Versioned for Widget {
+        const FORMAT: &'static str = "widget";
+        const VERSION: u32 = 2;
+
+        fn migrations() -> Vec<Migration> {
+            vec![Migration {
+                from: 1,
+                description: "add parts",
+                upgrade: |bytes| upgrade_added_fields::<Widget>(bytes, 1),
+            }]
+        }
+    }
+
+    #[test]
+    fn added_fields() {
+        // Format 1 didn't have parts
+        let mut old = Vec::new();
+        old.extend_from_slice(MAGIC);
+        old.extend_from_slice(&1u32.to_le_bytes());
+        old.extend(bincode::serialize(&"x".to_string()).unwrap());
+        assert_eq!(
+            Ok(Widget {
+                name: "x".to_string(),
+                parts: Vec::new(),
+            }),
+            from_versioned_binary(&old)
+        );
+
+        let widget = Widget {
+            name: "y".to_string(),
+            parts: vec![1, 2],
+        };
+        assert_eq!(
+            Ok(&widget),
+            from_versioned_binary(&to_versioned_binary(&widget)).as_ref()
+        );
+    }
+}
@@ -16,6 +16,8 @@ pub struct OsmExtract {
     pub roads: Vec<(WayID, RawRoad)>,
     /// Traffic signals to the direction they apply (or just true if unspecified)
     pub traffic_signals: HashMap<HashablePt2D, bool>,
+    /// Crossing nodes to their kerb tag, describing curb ramps
+    pub crossing_kerbs: HashMap<HashablePt2D, String>,
     pub osm_node_ids: HashMap<HashablePt2D, NodeID>,
     /// (ID, restriction type, from way ID, via node ID, to way ID)
     pub simple_turn_restrictions: Vec<(RestrictionType, WayID, NodeID, WayID)>,
@@ -36,6 +38,7 @@ pub fn extract_osm(map: &mut RawMap, opts: &Options, timer: &mut Timer) -> OsmEx
     let mut out = OsmExtract {
         roads: Vec::new(),
         traffic_signals: HashMap::new(),
+        crossing_kerbs: HashMap::new(),
         osm_node_ids: HashMap::new(),
         simple_turn_restrictions: Vec::new(),
         complicated_turn_restrictions: Vec::new(),
@@ -52,6 +55,12 @@ pub fn extract_osm(map: &mut RawMap, opts: &Options, timer: &mut Timer) -> OsmEx
             out.traffic_signals
                 .insert(node.pt.to_hashable(), !backwards);
         }
+        if node.tags.is(osm::HIGHWAY, "crossing") {
+            if let Some(kerb) = node.tags.get("kerb") {
+                out.crossing_kerbs
+                    .insert(node.pt.to_hashable(), kerb.clone());
+            }
+        }
         for amenity in get_bldg_amenities(&node.tags) {
             out.amenities.push((node.pt, amenity));
         }
@@ -434,13 +443,16 @@ fn is_road(tags: &mut Tags, opts: &Options) -> bool {
         return false;
     }
 
-    if (highway == "cycleway" || highway == "footway" || highway == "path" || highway == "steps")
+    // Cycle tracks are always kept. Separately mapped footways would duplicate inferred
+    // sidewalks.
+    if (highway == "footway" || highway == "path" || highway == "steps")
         && opts.map_config.inferred_sidewalks
     {
         return false;
     }
-    if (highway == "cycleway" || highway == "path")
+    if highway == "path"
         && !tags.is_any("foot", vec!["yes", "designated"])
+        && !tags.is_any("bicycle", vec!["yes", "designated"])
     {
         return false;
     }
@@ -518,6 +530,11 @@ fn get_bldg_amenities(tags: &Tags) -> Vec<Amenity> {
 }
 
 fn get_area_type(tags: &Tags) -> Option<AreaType> {
+    if (tags.is_any(osm::HIGHWAY, vec!["pedestrian", "footway"]) && tags.is("area", "yes"))
+        || tags.is("place", "square")
+    {
+        return Some(AreaType::PedestrianPlaza);
+    }
     if tags.is_any("leisure", vec!["park", "golf_course"]) {
         return Some(AreaType::Park);
     }
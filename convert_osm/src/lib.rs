@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 
 mod clip;
 mod extract;
+mod open_spaces;
 pub mod osm_geom;
 mod parking;
 pub mod reader;
@@ -60,6 +61,10 @@ pub enum PublicOffstreetParking {
     /// <https://data-seattlecitygis.opendata.arcgis.com/datasets/public-garages-or-parking-lots>, a
     /// Seattle-specific data source.
     GIS(String),
+    /// Buildings tagged as `building=parking`, `parking=multi-storey`, or `amenity=parking` in OSM
+    /// become public garages, unless their `access` is restricted. Capacity comes from the
+    /// `capacity` tag or is estimated from the area.
+    OSM,
 }
 
 /// If a building doesn't have anything from public_offstreet_parking and isn't tagged as a garage
@@ -87,6 +92,8 @@ pub fn convert(opts: Options, timer: &mut abstutil::Timer) -> RawMap {
     // doing the parking hint matching.
     abstutil::retain_btreemap(&mut map.roads, |r, _| r.i1 != r.i2);
 
+    open_spaces::connect_open_spaces(&mut map, timer);
+
     let all_routes = map.bus_routes.drain(..).collect::<Vec<_>>();
     let mut routes = Vec::new();
     for route in all_routes {
@@ -0,0 +1,330 @@
+//! Pedestrians can walk straight across plazas and parks, instead of following the sidewalks
+//! around them. Each open space becomes a few footpaths connecting the roads at its edge, routed
+//! through a visibility graph of the polygon so they never leave it. The paths are normal roads
+//! afterwards, so pathfinding and the simulation don't need to know about open spaces at all.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+use abstutil::{Tags, Timer};
+use geom::{Distance, Line, PolyLine, Polygon, Pt2D, Ring};
+use map_model::raw::{OriginalRoad, RawArea, RawMap, RawRoad};
+use map_model::{osm, AreaType, IntersectionType};
+
+/// Intersections this close to the edge of an open space can enter it
+const ENTRANCE_THRESHOLD: Distance = Distance::const_meters(10.0);
+/// Only add a path if it's this much shorter than the existing way between two entrances
+const MAX_DETOUR_RATIO: f64 = 1.25;
+/// Skip areas with a more detailed outline, since the visibility graph is cubic
+const MAX_REFLEX_VERTICES: usize = 300;
+
+pub fn connect_open_spaces(map: &mut RawMap, timer: &mut Timer) {
+    let areas: Vec<(osm::OsmID, Polygon, Tags)> = map
+        .areas
+        .iter()
+        .filter(|a| is_walkable_area(a))
+        .map(|a| (a.osm_id, a.polygon.clone(), a.osm_tags.clone()))
+        .collect();
+    let mut network = Network::new(map);
+
+    let mut paths = 0;
+    timer.start_iter("connect open spaces", areas.len());
+    for (osm_id, polygon, area_tags) in areas {
+        timer.next();
+        let ring = match Ring::new(polygon.points().clone()) {
+            Ok(ring) => ring,
+            Err(_) => {
+                continue;
+            }
+        };
+        let space = match OpenSpace::new(map, polygon, ring) {
+            Some(space) => space,
+            None => {
+                timer.warn(format!(
+                    "Not connecting {}; its outline is too complex",
+                    osm_id
+                ));
+                continue;
+            }
+        };
+
+        // Greedily add the shortest paths first, so longer ones can reuse them
+        let mut candidates = space.candidate_paths();
+        candidates.sort_by_key(|(_, _, pts)| PolyLine::unchecked_new(pts.clone()).length());
+        for (i1, i2, pts) in candidates {
+            let pl = match PolyLine::new(pts) {
+                Ok(pl) => pl,
+                Err(_) => {
+                    continue;
+                }
+            };
+            let limit = pl.length() * MAX_DETOUR_RATIO;
+            if network.dist(i1, i2, limit).is_some() {
+                continue;
+            }
+
+            let id = OriginalRoad {
+                osm_way_id: map.new_osm_way_id(-1),
+                i1,
+                i2,
+            };
+            let mut tags = Tags::new(BTreeMap::new());
+            tags.insert(osm::HIGHWAY, "footway");
+            tags.insert(osm::OSM_WAY_ID, id.osm_way_id.0.to_string());
+            tags.insert(osm::OPEN_SPACE, osm_id.to_string());
+            if let Some(name) = area_tags.get(osm::NAME) {
+                tags.insert(osm::NAME, name.clone());
+            }
+            network.add(i1, i2, pl.length());
+            map.roads.insert(
+                id,
+                RawRoad {
+                    center_points: pl.into_points(),
+                    osm_tags: tags,
+                    turn_restrictions: Vec::new(),
+                    complicated_turn_restrictions: Vec::new(),
+                },
+            );
+            paths += 1;
+        }
+    }
+    timer.note(format!("Added {} footpaths across open spaces", paths));
+}
+
+fn is_walkable_area(area: &RawArea) -> bool {
+    if area.osm_tags.is_any("access", vec!["no", "private"]) {
+        return false;
+    }
+    area.area_type == AreaType::PedestrianPlaza || area.osm_tags.is("leisure", "park")
+}
+
+fn is_walkable_road(r: &RawRoad) -> bool {
+    if r.osm_tags.is(osm::HIGHWAY, "cycleway") {
+        return r.osm_tags.is_any("foot", vec!["yes", "designated"]);
+    }
+    r.is_footway()
+        || r.osm_tags
+            .is_any(osm::SIDEWALK, vec!["both", "left", "right"])
+}
+
+struct OpenSpace {
+    polygon: Polygon,
+    outline: PolyLine,
+    edges: Vec<Line>,
+    /// Each intersection at the edge, and the point where a path from it enters the polygon
+    entrances: Vec<(osm::NodeID, Pt2D, Pt2D)>,
+    /// The only places a shortest path through the polygon can bend
+    reflex_vertices: Vec<Pt2D>,
+}
+
+impl OpenSpace {
+    fn new(map: &RawMap, polygon: Polygon, ring: Ring) -> Option<OpenSpace> {
+        let pts = ring.into_points();
+        let outline = PolyLine::unchecked_new(pts.clone());
+        let edges: Vec<Line> = pts
+            .windows(2)
+            .filter_map(|pair| Line::new(pair[0], pair[1]))
+            .collect();
+
+        // The ring repeats its first point at the end
+        let n = pts.len() - 1;
+        let winding: f64 = (0..n)
+            .map(|i| pts[i].x() * pts[i + 1].y() - pts[i + 1].x() * pts[i].y())
+            .sum();
+        let mut reflex_vertices = Vec::new();
+        for i in 0..n {
+            let (prev, pt, next) = (pts[(i + n - 1) % n], pts[i], pts[i + 1]);
+            let turn = (pt.x() - prev.x()) * (next.y() - pt.y())
+                - (pt.y() - prev.y()) * (next.x() - pt.x());
+            if turn * winding < 0.0 {
+                reflex_vertices.push(pt);
+            }
+        }
+        if reflex_vertices.len() > MAX_REFLEX_VERTICES {
+            return None;
+        }
+
+        let bounds = polygon.get_bounds();
+        let mut entrances = Vec::new();
+        for (id, i) in &map.intersections {
+            if i.intersection_type == IntersectionType::Border {
+                continue;
+            }
+            let entry = if polygon.contains_pt(i.point) {
+                i.point
+            } else {
+                if i.point.x() < bounds.min_x - ENTRANCE_THRESHOLD.inner_meters()
+                    || i.point.x() > bounds.max_x + ENTRANCE_THRESHOLD.inner_meters()
+                    || i.point.y() < bounds.min_y - ENTRANCE_THRESHOLD.inner_meters()
+                    || i.point.y() > bounds.max_y + ENTRANCE_THRESHOLD.inner_meters()
+                {
+                    continue;
+                }
+                let pt = outline.project_pt(i.point);
+                if pt.dist_to(i.point) > ENTRANCE_THRESHOLD {
+                    continue;
+                }
+                pt
+            };
+            if map
+                .roads_per_intersection(*id)
+                .into_iter()
+                .any(|r| is_walkable_road(&map.roads[&r]))
+            {
+                entrances.push((*id, i.point, entry));
+            }
+        }
+
+        Some(OpenSpace {
+            polygon,
+            outline,
+            edges,
+            entrances,
+            reflex_vertices,
+        })
+    }
+
+    /// Can someone walk in a straight line between two points without leaving the polygon?
+    fn visible(&self, pt1: Pt2D, pt2: Pt2D) -> bool {
+        let line = match Line::new(pt1, pt2) {
+            Some(line) => line,
+            None => {
+                return true;
+            }
+        };
+        if self.edges.iter().any(|e| line.crosses(e)) {
+            return false;
+        }
+        // Points on the outline count as inside
+        let middle = line.unbounded_percent_along(0.5);
+        self.polygon.contains_pt(middle)
+            || self.outline.project_pt(middle).dist_to(middle) < Distance::meters(0.1)
+    }
+
+    /// For every pair of entrances with a way through, returns the intersections and the points
+    /// of a path between them.
+    fn candidate_paths(&self) -> Vec<(osm::NodeID, osm::NodeID, Vec<Pt2D>)> {
+        // Nodes in the visibility graph are the entry points, then the reflex vertices
+        let nodes: Vec<Pt2D> = self
+            .entrances
+            .iter()
+            .map(|(_, _, entry)| *entry)
+            .chain(self.reflex_vertices.iter().cloned())
+            .collect();
+        let mut neighbors: Vec<Vec<(usize, Distance)>> = vec![Vec::new(); nodes.len()];
+        for idx1 in 0..nodes.len() {
+            for idx2 in (idx1 + 1)..nodes.len() {
+                if self.visible(nodes[idx1], nodes[idx2]) {
+                    let dist = nodes[idx1].dist_to(nodes[idx2]);
+                    neighbors[idx1].push((idx2, dist));
+                    neighbors[idx2].push((idx1, dist));
+                }
+            }
+        }
+
+        let mut results = Vec::new();
+        for (src, (i1, pt1, _)) in self.entrances.iter().enumerate() {
+            let prev = shortest_paths(&neighbors, src);
+            for (dst, (i2, pt2, _)) in self.entrances.iter().enumerate().skip(src + 1) {
+                if i1 == i2 {
+                    continue;
+                }
+                let mut path = vec![*pt2];
+                let mut current = dst;
+                path.push(nodes[current]);
+                while current != src {
+                    current = match prev[current] {
+                        Some(idx) => idx,
+                        None => break,
+                    };
+                    path.push(nodes[current]);
+                }
+                if current != src {
+                    continue;
+                }
+                path.push(*pt1);
+                path.reverse();
+                path.dedup();
+                results.push((*i1, *i2, path));
+            }
+        }
+        results
+    }
+}
+
+/// Dijkstra's over the visibility graph. Returns the previous node on the shortest path to every
+/// node.
+fn shortest_paths(neighbors: &[Vec<(usize, Distance)>], src: usize) -> Vec<Option<usize>> {
+    let mut prev = vec![None; neighbors.len()];
+    let mut best = vec![None; neighbors.len()];
+    best[src] = Some(Distance::ZERO);
+    let mut queue = BinaryHeap::new();
+    queue.push(Reverse((Distance::ZERO, src)));
+    while let Some(Reverse((dist, current))) = queue.pop() {
+        if best[current].map(|d| dist > d).unwrap_or(false) {
+            continue;
+        }
+        for (next, step) in &neighbors[current] {
+            let total = dist + *step;
+            if best[*next].map(|d| total < d).unwrap_or(true) {
+                best[*next] = Some(total);
+                prev[*next] = Some(current);
+                queue.push(Reverse((total, *next)));
+            }
+        }
+    }
+    prev
+}
+
+/// How pedestrians can already get between intersections. Direction doesn't matter on foot.
+struct Network {
+    neighbors: BTreeMap<osm::NodeID, Vec<(osm::NodeID, Distance)>>,
+}
+
+impl Network {
+    fn new(map: &RawMap) -> Network {
+        let mut network = Network {
+            neighbors: BTreeMap::new(),
+        };
+        for (id, r) in &map.roads {
+            if is_walkable_road(r) {
+                let length = PolyLine::unchecked_new(r.center_points.clone()).length();
+                network.add(id.i1, id.i2, length);
+            }
+        }
+        network
+    }
+
+    fn add(&mut self, i1: osm::NodeID, i2: osm::NodeID, length: Distance) {
+        self.neighbors
+            .entry(i1)
+            .or_insert_with(Vec::new)
+            .push((i2, length));
+        self.neighbors
+            .entry(i2)
+            .or_insert_with(Vec::new)
+            .push((i1, length));
+    }
+
+    /// The walking distance between two intersections, if it's at most `limit`
+    fn dist(&self, from: osm::NodeID, to: osm::NodeID, limit: Distance) -> Option<Distance> {
+        let mut visited = BTreeSet::new();
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((Distance::ZERO, from)));
+        while let Some(Reverse((dist, current))) = queue.pop() {
+            if current == to {
+                return Some(dist);
+            }
+            if !visited.insert(current) {
+                continue;
+            }
+            for (next, length) in self.neighbors.get(&current).into_iter().flatten() {
+                let total = dist + *length;
+                if total <= limit && !visited.contains(next) {
+                    queue.push(Reverse((total, *next)));
+                }
+            }
+        }
+        None
+    }
+}
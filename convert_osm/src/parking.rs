@@ -2,7 +2,7 @@ use abstutil::Timer;
 use geom::{Distance, FindClosest, PolyLine};
 use kml::ExtraShapes;
 use map_model::osm;
-use map_model::raw::{OriginalRoad, RawMap};
+use map_model::raw::{OriginalRoad, RawBuilding, RawMap};
 
 use crate::{OnstreetParking, Options, PrivateOffstreetParking, PublicOffstreetParking};
 
@@ -43,6 +43,9 @@ pub fn apply_parking(map: &mut RawMap, opts: &Options, timer: &mut Timer) {
         PublicOffstreetParking::GIS(ref path) => {
             use_offstreet_parking(map, path.clone(), timer);
         }
+        PublicOffstreetParking::OSM => {
+            use_osm_public_garages(map, timer);
+        }
     }
     apply_private_offstreet_parking(map, &opts.private_offstreet_parking);
 }
@@ -190,6 +193,58 @@ fn use_offstreet_parking(map: &mut RawMap, path: String, timer: &mut Timer) {
     timer.stop("match offstreet parking points");
 }
 
+/// Garages are usually tagged `building=parking` or `parking=multi-storey`, but sometimes just
+/// `amenity=parking` on the building itself.
+fn use_osm_public_garages(map: &mut RawMap, timer: &mut Timer) {
+    let mut cnt = 0;
+    for b in map.buildings.values_mut() {
+        if b.public_garage_name.is_some() {
+            continue;
+        }
+        if !b.osm_tags.is("amenity", "parking")
+            && !b.osm_tags.is("building", "parking")
+            && !b.osm_tags.is("parking", "multi-storey")
+        {
+            continue;
+        }
+        if b.osm_tags.is_any(
+            "access",
+            vec!["private", "no", "customers", "permit", "residents"],
+        ) {
+            continue;
+        }
+        b.public_garage_name = Some(
+            b.osm_tags
+                .get("name")
+                .cloned()
+                .unwrap_or_else(|| "parking garage".to_string()),
+        );
+        b.num_parking_spots = garage_capacity(b);
+        b.amenities.retain(|a| a.amenity_type != "parking");
+        cnt += 1;
+    }
+    timer.note(format!("Found {} public parking garages in OSM", cnt));
+}
+
+/// Use the capacity tag if it's there. Otherwise for multi-story garages, assume every floor has
+/// the same capacity, and guess 1 spot per 30m^2.
+fn garage_capacity(b: &RawBuilding) -> usize {
+    if let Some(n) = b
+        .osm_tags
+        .get("capacity")
+        .and_then(|x| x.parse::<usize>().ok())
+    {
+        return n;
+    }
+    let levels = b
+        .osm_tags
+        .get("parking:levels")
+        .or_else(|| b.osm_tags.get("building:levels"))
+        .and_then(|x| x.parse::<usize>().ok())
+        .unwrap_or(1);
+    ((b.polygon.area() / 30.0) as usize) * levels
+}
+
 fn apply_private_offstreet_parking(map: &mut RawMap, policy: &PrivateOffstreetParking) {
     match policy {
         PrivateOffstreetParking::FixedPerBldg(n) => {
@@ -199,15 +254,7 @@ fn apply_private_offstreet_parking(map: &mut RawMap, policy: &PrivateOffstreetPa
 
                     // Is it a parking garage?
                     if b.osm_tags.is("building", "parking") || b.osm_tags.is("amenity", "parking") {
-                        let levels = b
-                            .osm_tags
-                            .get("parking:levels")
-                            .or_else(|| b.osm_tags.get("building:levels"))
-                            .and_then(|x| x.parse::<usize>().ok())
-                            .unwrap_or(1);
-                        // For multi-story garages, assume every floor has the same capacity. Guess
-                        // 1 spot per 30m^2.
-                        b.num_parking_spots = ((b.polygon.area() / 30.0) as usize) * levels;
+                        b.num_parking_spots = garage_capacity(b);
                         // Not useful to list this
                         b.amenities.retain(|a| a.amenity_type != "parking");
                     } else {
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use abstutil::{Counter, Timer};
 use geom::{Distance, HashablePt2D, Pt2D};
@@ -17,14 +17,22 @@ pub fn split_up_roads(
     timer.start("splitting up roads");
 
     let mut pt_to_intersection: HashMap<HashablePt2D, osm::NodeID> = HashMap::new();
+    // Ways only meet in the middle if they're at the same level. A bridge passing over a road
+    // might share a node with it, but they don't connect.
     let mut counts_per_pt = Counter::new();
+    let mut endpts = HashSet::new();
     for (_, r) in &input.roads {
+        let zorder = r.get_zorder();
         for (idx, raw_pt) in r.center_points.iter().enumerate() {
             let pt = raw_pt.to_hashable();
-            let count = counts_per_pt.inc(pt);
+            let count = counts_per_pt.inc((pt, zorder));
 
             // All start and endpoints of ways are also intersections.
-            if count == 2 || idx == 0 || idx == r.center_points.len() - 1 {
+            let is_endpt = idx == 0 || idx == r.center_points.len() - 1;
+            if is_endpt {
+                endpts.insert((pt, zorder));
+            }
+            if count == 2 || is_endpt {
                 if !pt_to_intersection.contains_key(&pt) {
                     let id = input.osm_node_ids[&pt];
                     pt_to_intersection.insert(pt, id);
@@ -56,6 +64,7 @@ pub fn split_up_roads(
     for (osm_way_id, orig_road) in &input.roads {
         timer.next();
         let mut r = orig_road.clone();
+        let zorder = r.get_zorder();
         let mut pts = Vec::new();
         let endpt1 = pt_to_intersection[&orig_road.center_points[0].to_hashable()];
         let endpt2 = pt_to_intersection[&orig_road.center_points.last().unwrap().to_hashable()];
@@ -66,7 +75,12 @@ pub fn split_up_roads(
             if pts.len() == 1 {
                 continue;
             }
-            if let Some(i2) = pt_to_intersection.get(&pt.to_hashable()) {
+            let hashed = pt.to_hashable();
+            // Don't split at an intersection between other roads at a different level
+            if !endpts.contains(&(hashed, zorder)) && counts_per_pt.get((hashed, zorder)) < 2 {
+                continue;
+            }
+            if let Some(i2) = pt_to_intersection.get(&hashed) {
                 if i1 == endpt1 {
                     r.osm_tags
                         .insert(osm::ENDPT_BACK.to_string(), "true".to_string());
@@ -190,6 +204,22 @@ pub fn split_up_roads(
     }
     timer.stop("match traffic signals to intersections");
 
+    // Remember curb ramps at crossings in the middle of a road, so crosswalks at the nearer end can
+    // use them
+    for (pt, kerb) in input.crossing_kerbs {
+        if let Some(id) = pt_to_road.get(&pt) {
+            let r = map.roads.get_mut(id).unwrap();
+            let pt = pt.to_pt2d();
+            let key =
+                if pt.dist_to(r.center_points[0]) <= pt.dist_to(*r.center_points.last().unwrap()) {
+                    osm::CROSSING_KERB_START
+                } else {
+                    osm::CROSSING_KERB_END
+                };
+            r.osm_tags.insert(key, kerb);
+        }
+    }
+
     timer.stop("splitting up roads");
     (input.amenities, pt_to_road)
 }
@@ -1,89 +1,231 @@
 use std::collections::HashMap;
 
+use geojson::{Feature, FeatureCollection, GeoJson};
+
 use abstutil::MultiMap;
-use geom::{Duration, Polygon};
+use geom::{Duration, Polygon, Pt2D};
 use map_gui::tools::{amenity_type, Grid};
-use map_model::{connectivity, BuildingID, BuildingType, Map, Path, PathConstraints, PathRequest};
+use map_model::{
+    connectivity, Building, BuildingID, BuildingType, Map, Path, PathConstraints, PathRequest,
+};
 use widgetry::{Color, Drawable, EventCtx, GeomBatch};
 
 use crate::App;
 
+/// Isochrones show everything reachable within each of these times. The first is the "15-minute
+/// neighborhood" that amenities and population are summarized for.
+const BANDS: [usize; 3] = [15, 30, 45];
+
+/// How somebody gets around
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TravelMode {
+    Walk,
+    Bike,
+    Transit,
+    Drive,
+}
+
+impl TravelMode {
+    pub fn all() -> Vec<TravelMode> {
+        vec![
+            TravelMode::Walk,
+            TravelMode::Bike,
+            TravelMode::Transit,
+            TravelMode::Drive,
+        ]
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            TravelMode::Walk => "walking",
+            TravelMode::Bike => "biking",
+            TravelMode::Transit => "transit",
+            TravelMode::Drive => "driving",
+        }
+    }
+}
+
 /// Represents the area reachable from a single building.
 pub struct Isochrone {
     /// The center of the isochrone
     pub start: BuildingID,
     /// What mode of travel we're using
-    pub constraints: PathConstraints,
+    pub mode: TravelMode,
     /// Colored polygon contours, uploaded to the GPU and ready for drawing
     pub draw: Drawable,
-    /// How far away is each building from the start?
+    /// How far away is each building from the start? Only buildings within the largest band are
+    /// included.
     pub time_to_reach_building: HashMap<BuildingID, Duration>,
-    /// Per category of amenity (defined by helpers::amenity_type), what buildings have that?
+    /// Per category of amenity (defined by helpers::amenity_type), what buildings within the first
+    /// band have that?
     pub amenities_reachable: MultiMap<&'static str, BuildingID>,
-    /// How many people live in the returned area, according to estimates included in the map (from
-    /// city-specific parcel data, guesses from census, or a guess based on OSM tags)
+    /// How many people live within the first band, according to estimates included in the map
+    /// (from city-specific parcel data, guesses from census, or a guess based on OSM tags)
     pub population: usize,
+    /// For each band, the polygons covering everything reachable within that time. These're in
+    /// map-space, shaped like the coordinates of a GeoJSON MultiPolygon.
+    contours: Vec<(Duration, Vec<Vec<Vec<Vec<f64>>>>)>,
 }
 
 impl Isochrone {
-    pub fn new(
-        ctx: &mut EventCtx,
-        app: &App,
-        start: BuildingID,
-        constraints: PathConstraints,
-    ) -> Isochrone {
-        let time_to_reach_building =
-            connectivity::all_costs_from(&app.map, start, Duration::minutes(15), constraints);
-        let draw = draw_isochrone(app, &time_to_reach_building).upload(ctx);
+    pub fn new(ctx: &mut EventCtx, app: &App, start: BuildingID, mode: TravelMode) -> Isochrone {
+        let time_limit = Duration::minutes(BANDS[BANDS.len() - 1]);
+        let time_to_reach_building = match mode {
+            TravelMode::Walk => connectivity::all_costs_from(
+                &app.map,
+                start,
+                time_limit,
+                PathConstraints::Pedestrian,
+            ),
+            TravelMode::Bike => {
+                connectivity::all_costs_from(&app.map, start, time_limit, PathConstraints::Bike)
+            }
+            TravelMode::Transit => {
+                connectivity::all_transit_costs_from(&app.map, start, time_limit)
+            }
+            TravelMode::Drive => {
+                connectivity::all_costs_from(&app.map, start, time_limit, PathConstraints::Car)
+            }
+        };
+        let contours = calculate_contours(app, &time_to_reach_building, time_limit);
+        let draw = draw_isochrone(&contours).upload(ctx);
 
         let mut amenities_reachable = MultiMap::new();
         let mut population = 0;
-        for b in time_to_reach_building.keys() {
+        for (b, time) in &time_to_reach_building {
+            if *time > Duration::minutes(BANDS[0]) {
+                continue;
+            }
             let bldg = app.map.get_b(*b);
             for amenity in &bldg.amenities {
                 if let Some(category) = amenity_type(&amenity.amenity_type) {
                     amenities_reachable.insert(category, bldg.id);
                 }
             }
-            match bldg.bldg_type {
-                BuildingType::Residential { num_residents, .. }
-                | BuildingType::ResidentialCommercial(num_residents, _) => {
-                    population += num_residents;
-                }
-                _ => {}
-            }
+            population += residents(bldg);
         }
 
         Isochrone {
             start,
-            constraints,
+            mode,
             draw,
             time_to_reach_building,
             amenities_reachable,
             population,
+            contours,
         }
     }
 
     pub fn path_to(&self, map: &Map, to: BuildingID) -> Option<Path> {
-        let req = PathRequest::between_buildings(map, self.start, to, self.constraints)?;
+        let constraints = match self.mode {
+            TravelMode::Walk => PathConstraints::Pedestrian,
+            TravelMode::Bike => PathConstraints::Bike,
+            // TODO The costs come from a simplified transit graph that regular pathfinding doesn't
+            // know about, so don't draw a misleading route.
+            TravelMode::Transit => {
+                return None;
+            }
+            TravelMode::Drive => PathConstraints::Car,
+        };
+        let req = PathRequest::between_buildings(map, self.start, to, constraints)?;
         map.pathfind(req)
     }
+
+    /// Writes each band as a GeoJSON MultiPolygon in lon/lat coordinates, returns the filename.
+    pub fn export_geojson(&self, map: &Map) -> String {
+        let gps_bounds = map.get_gps_bounds();
+        let mut features = Vec::new();
+        for (time, polygons) in &self.contours {
+            let population: usize = self
+                .time_to_reach_building
+                .iter()
+                .filter(|(_, cost)| *cost <= time)
+                .map(|(b, _)| residents(map.get_b(*b)))
+                .sum();
+
+            let mut properties = serde_json::Map::new();
+            properties.insert(
+                "from".to_string(),
+                map.get_b(self.start).address.clone().into(),
+            );
+            properties.insert("mode".to_string(), self.mode.describe().into());
+            properties.insert(
+                "minutes".to_string(),
+                ((time.inner_seconds() / 60.0) as usize).into(),
+            );
+            properties.insert("population".to_string(), population.into());
+
+            let polygons = polygons
+                .iter()
+                .map(|rings| {
+                    rings
+                        .iter()
+                        .map(|ring| {
+                            ring.iter()
+                                .map(|pt| {
+                                    let gps = Pt2D::new(pt[0], pt[1]).to_gps(gps_bounds);
+                                    vec![gps.x(), gps.y()]
+                                })
+                                .collect()
+                        })
+                        .collect()
+                })
+                .collect();
+            features.push(Feature {
+                bbox: None,
+                geometry: Some(geojson::Geometry::new(geojson::Value::MultiPolygon(
+                    polygons,
+                ))),
+                id: None,
+                properties: Some(properties),
+                foreign_members: None,
+            });
+        }
+
+        let path = format!(
+            "isochrone_{}_{}_{}.geojson",
+            map.get_name().as_filename(),
+            self.start.0,
+            self.mode.describe()
+        );
+        abstutil::write_json(
+            path.clone(),
+            &GeoJson::from(FeatureCollection {
+                bbox: None,
+                features,
+                foreign_members: None,
+            }),
+        );
+        path
+    }
 }
 
-fn draw_isochrone(app: &App, time_to_reach_building: &HashMap<BuildingID, Duration>) -> GeomBatch {
-    // To generate the polygons covering areas between 0-5 mins, 5-10 mins, etc, we have to feed
-    // in a 2D grid of costs. Use a 100x100 meter resolution.
+fn residents(bldg: &Building) -> usize {
+    match bldg.bldg_type {
+        BuildingType::Residential { num_residents, .. }
+        | BuildingType::ResidentialCommercial(num_residents, _) => num_residents,
+        _ => 0,
+    }
+}
+
+fn calculate_contours(
+    app: &App,
+    time_to_reach_building: &HashMap<BuildingID, Duration>,
+    time_limit: Duration,
+) -> Vec<(Duration, Vec<Vec<Vec<Vec<f64>>>>)> {
+    // To generate the polygons covering areas within 15 mins, 30 mins, etc, we have to feed in a
+    // 2D grid of costs. Use a 100x100 meter resolution.
     let bounds = app.map.get_bounds();
     let resolution_m = 100.0;
-    // The costs we're storing are currenly durations, but the contour crate needs f64, so
-    // just store the number of seconds.
+    // The contour crate finds the area with a value above some threshold, but we want the area
+    // below some cost. So store how much time would be left over after reaching each building,
+    // plus one second so that unreachable cells stay at 0.
     let mut grid: Grid<f64> = Grid::new(
         (bounds.width() / resolution_m).ceil() as usize,
         (bounds.height() / resolution_m).ceil() as usize,
         0.0,
     );
 
-    // Calculate the cost from the start building to every other building in the map
     for (b, cost) in time_to_reach_building {
         // What grid cell does the building belong to?
         let pt = app.map.get_b(*b).polygon.center();
@@ -92,47 +234,64 @@ fn draw_isochrone(app: &App, time_to_reach_building: &HashMap<BuildingID, Durati
             ((pt.y() - bounds.min_y) / resolution_m) as usize,
         );
         // Don't add! If two buildings map to the same cell, we should pick a finer resolution.
-        grid.data[idx] = cost.inner_seconds();
+        grid.data[idx] = (time_limit - *cost).inner_seconds() + 1.0;
     }
 
-    // Generate polygons covering the contour line where the cost in the grid crosses these
-    // threshold values.
-    let thresholds = vec![
-        0.1,
-        Duration::minutes(5).inner_seconds(),
-        Duration::minutes(10).inner_seconds(),
-        Duration::minutes(15).inner_seconds(),
-    ];
-    // And color the polygon for each threshold
-    let colors = vec![
-        Color::GREEN.alpha(0.5),
-        Color::ORANGE.alpha(0.5),
-        Color::RED.alpha(0.5),
-    ];
+    // Generate polygons covering the contour line where the grid crosses each band. The largest
+    // band comes first.
+    let times: Vec<Duration> = BANDS
+        .iter()
+        .rev()
+        .map(|mins| Duration::minutes(*mins))
+        .collect();
+    let thresholds: Vec<f64> = times
+        .iter()
+        .map(|time| (time_limit - *time).inner_seconds() + 0.5)
+        .collect();
     let smooth = false;
     let c = contour::ContourBuilder::new(grid.width as u32, grid.height as u32, smooth);
-    let mut batch = GeomBatch::new();
-    // The last feature returned will be larger than the last threshold value. We don't want to
-    // display that at all. zip() will omit this last pair, since colors.len() ==
-    // thresholds.len() - 1.
-    //
-    // TODO Actually, this still isn't working. I think each polygon is everything > the
-    // threshold, not everything between two thresholds?
-    for (feature, color) in c
+    let mut results = Vec::new();
+    for (feature, time) in c
         .contours(&grid.data, &thresholds)
         .unwrap()
         .into_iter()
-        .zip(colors)
+        .zip(times)
     {
         match feature.geometry.unwrap().value {
             geojson::Value::MultiPolygon(polygons) => {
-                for p in polygons {
-                    batch.push(color, Polygon::from_geojson(&p).scale(resolution_m));
-                }
+                let scaled = polygons
+                    .into_iter()
+                    .map(|rings| {
+                        rings
+                            .into_iter()
+                            .map(|ring| {
+                                ring.into_iter()
+                                    .map(|pt| vec![pt[0] * resolution_m, pt[1] * resolution_m])
+                                    .collect()
+                            })
+                            .collect()
+                    })
+                    .collect();
+                results.push((time, scaled));
             }
             _ => unreachable!(),
         }
     }
+    results
+}
 
+fn draw_isochrone(contours: &Vec<(Duration, Vec<Vec<Vec<Vec<f64>>>>)>) -> GeomBatch {
+    // The largest band is drawn first, so the smaller ones get layered on top.
+    let colors = vec![
+        Color::RED.alpha(0.3),
+        Color::ORANGE.alpha(0.3),
+        Color::GREEN.alpha(0.3),
+    ];
+    let mut batch = GeomBatch::new();
+    for ((_, polygons), color) in contours.iter().zip(colors) {
+        for p in polygons {
+            batch.push(color, Polygon::from_geojson(p));
+        }
+    }
     batch
 }
@@ -8,14 +8,14 @@ use abstutil::prettyprint_usize;
 use geom::{Distance, Pt2D};
 use map_gui::tools::{amenity_type, nice_map_name, CityPicker, PopupMsg};
 use map_gui::{Cached, ID};
-use map_model::{Building, BuildingID, PathConstraints};
+use map_model::{Building, BuildingID};
 use widgetry::{
-    lctrl, Btn, Checkbox, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key,
+    lctrl, Btn, Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key,
     Line, Outcome, Panel, RewriteColor, State, Text, TextExt, Transition, VerticalAlignment,
     Widget,
 };
 
-use crate::isochrone::Isochrone;
+use crate::isochrone::{Isochrone, TravelMode};
 use crate::App;
 
 /// This is the UI state for exploring the isochrone/walkshed from a single building.
@@ -36,9 +36,8 @@ impl Viewer {
     }
 
     pub fn new(ctx: &mut EventCtx, app: &App, start: BuildingID) -> Box<dyn State<App>> {
-        let constraints = PathConstraints::Pedestrian;
         let start = app.map.get_b(start);
-        let isochrone = Isochrone::new(ctx, app, start.id, constraints);
+        let isochrone = Isochrone::new(ctx, app, start.id, TravelMode::Walk);
         let highlight_start = draw_star(ctx, start.polygon.center());
         let panel = build_panel(ctx, app, start, &isochrone);
 
@@ -72,7 +71,7 @@ impl State<App> for Viewer {
         if let Some((hover_id, _)) = self.hovering_on_bldg.key() {
             if ctx.normal_left_click() {
                 let start = app.map.get_b(hover_id);
-                self.isochrone = Isochrone::new(ctx, app, start.id, self.isochrone.constraints);
+                self.isochrone = Isochrone::new(ctx, app, start.id, self.isochrone.mode);
                 self.highlight_start = draw_star(ctx, start.polygon.center());
                 self.panel = build_panel(ctx, app, start, &self.isochrone);
                 // Any previous hover is from the perspective of the old `highlight_start`.
@@ -98,6 +97,14 @@ impl State<App> for Viewer {
                 "close" => {
                     return Transition::Pop;
                 }
+                "export to GeoJSON" => {
+                    let path = self.isochrone.export_geojson(&app.map);
+                    return Transition::Push(PopupMsg::new(
+                        ctx,
+                        "Isochrone exported",
+                        vec![format!("Isochrone exported to {}", path)],
+                    ));
+                }
                 "About" => {
                     return Transition::Push(PopupMsg::new(
                         ctx,
@@ -111,6 +118,9 @@ impl State<App> for Viewer {
                              policy changes could lead to 15 minute neighborhoods.",
                             "This tool lets you see what commercial amenities are near you right \
                              now, using data from OpenStreetMap.",
+                            "The shaded areas show everything reachable within 15, 30, and 45 \
+                             minutes by each way of getting around. You can export them to \
+                             GeoJSON to compare in other tools.",
                         ],
                     ));
                 }
@@ -135,12 +145,8 @@ impl State<App> for Viewer {
                 }
             },
             Outcome::Changed => {
-                let constraints = if self.panel.is_checked("walking / biking") {
-                    PathConstraints::Pedestrian
-                } else {
-                    PathConstraints::Bike
-                };
-                self.isochrone = Isochrone::new(ctx, app, self.isochrone.start, constraints);
+                let mode = self.panel.dropdown_value("mode");
+                self.isochrone = Isochrone::new(ctx, app, self.isochrone.start, mode);
                 self.panel = build_panel(
                     ctx,
                     app,
@@ -203,7 +209,7 @@ fn build_panel(ctx: &mut EventCtx, app: &App, start: &Building, isochrone: &Isoc
 
     rows.push(
         Text::from_all(vec![
-            Line("Estimated population: ").secondary(),
+            Line("Estimated population within 15 minutes: ").secondary(),
             Line(prettyprint_usize(isochrone.population)),
         ])
         .draw(ctx),
@@ -218,14 +224,30 @@ fn build_panel(ctx: &mut EventCtx, app: &App, start: &Building, isochrone: &Isoc
     // Start of toolbar
     rows.push(Widget::horiz_separator(ctx, 0.3).margin_above(10));
 
-    rows.push(Checkbox::toggle(
-        ctx,
-        "walking / biking",
-        "walking",
-        "biking",
-        None,
-        isochrone.constraints == PathConstraints::Pedestrian,
-    ));
+    rows.push(Widget::row(vec![
+        "Travel by:".draw_text(ctx),
+        Widget::dropdown(
+            ctx,
+            "mode",
+            isochrone.mode,
+            TravelMode::all()
+                .into_iter()
+                .map(|mode| Choice::new(mode.describe(), mode))
+                .collect(),
+        ),
+    ]));
+    rows.push(
+        Text::from_all(vec![
+            Line("15").fg(Color::GREEN),
+            Line(" / "),
+            Line("30").fg(Color::ORANGE),
+            Line(" / "),
+            Line("45").fg(Color::RED),
+            Line(" minutes away"),
+        ])
+        .draw(ctx),
+    );
+    rows.push(Btn::text_fg("export to GeoJSON").build_def(ctx, None));
     rows.push(Btn::plaintext("About").build_def(ctx, None));
 
     Panel::new(Widget::col(rows))
@@ -281,7 +303,7 @@ impl HoverOnBuilding {
             tooltip: if let Some(time) = isochrone.time_to_reach_building.get(&hover_id) {
                 Text::from(Line(format!("{} away", time)))
             } else {
-                Text::from(Line("This is more than 15 minutes away"))
+                Text::from(Line("This is more than 45 minutes away"))
             },
             drawn_route: ctx.upload(batch),
         }
@@ -18,7 +18,7 @@ use crate::challenges::HighScore;
 use crate::common::Warping;
 use crate::edit::apply_map_edits;
 use crate::layer::Layer;
-use crate::sandbox::{GameplayMode, TutorialState};
+use crate::sandbox::{GameplayMode, Snapshots, TutorialState};
 
 // Convenient typedef
 pub type Transition = widgetry::Transition<App>;
@@ -612,6 +612,8 @@ pub struct PerMap {
     pub layer: Option<Box<dyn Layer>>,
     /// Only filled out in edit mode. Stored here once to avoid lots of clones. Used for preview.
     pub suspended_sim: Option<Sim>,
+    /// Recent copies of the sim, for rewinding a few minutes. Cleared whenever the sim is replaced.
+    pub snapshots: Snapshots,
     /// Only exists in some gameplay modes. Must be carefully reset otherwise. Has the map and
     /// scenario name too.
     // TODO Embed that in Analytics directly instead.
@@ -653,6 +655,7 @@ impl PerMap {
             unedited_map: RefCell::new(None),
             layer: None,
             suspended_sim: None,
+            snapshots: Snapshots::default(),
             prebaked: None,
             scenario: None,
         };
@@ -687,6 +690,7 @@ impl PerMap {
     /// Returns whatever was there
     pub fn clear_sim(&mut self) -> Sim {
         self.dirty_from_edits = false;
+        self.snapshots.clear();
         std::mem::replace(
             &mut self.sim,
             Sim::new(
@@ -12,7 +12,7 @@ pub fn prebake_all() {
 
     {
         let map = map_model::Map::new(MapName::seattle("montlake").path(), &mut timer);
-        let scenario: Scenario = abstutil::read_binary(
+        let scenario: Scenario = abstutil::read_versioned_binary(
             abstutil::path_scenario(map.get_name(), "weekday"),
             &mut timer,
         );
@@ -30,7 +30,7 @@ pub fn prebake_all() {
 
     for name in vec![MapName::seattle("lakeslice")] {
         let map = map_model::Map::new(name.path(), &mut timer);
-        let scenario: Scenario = abstutil::read_binary(
+        let scenario: Scenario = abstutil::read_versioned_binary(
             abstutil::path_scenario(map.get_name(), "weekday"),
             &mut timer,
         );
@@ -62,7 +62,7 @@ fn prebake(map: &Map, scenario: Scenario, time_limit: Option<Duration>, timer: &
         );
     }
 
-    abstutil::write_binary(
+    abstutil::write_versioned_binary(
         abstutil::path_prebaked_results(&scenario.map_name, &scenario.scenario_name),
         sim.get_analytics(),
     );
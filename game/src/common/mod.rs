@@ -341,6 +341,7 @@ pub fn color_for_mode(app: &App, m: TripMode) -> Color {
         TripMode::Bike => app.cs.unzoomed_bike,
         TripMode::Transit => app.cs.unzoomed_bus,
         TripMode::Drive => app.cs.unzoomed_car,
+        TripMode::RideHail => app.cs.unzoomed_ride_hail,
     }
 }
 
@@ -364,6 +365,10 @@ pub fn color_for_trip_phase(app: &App, tpt: TripPhaseType) -> Color {
         TripPhaseType::RidingBus(_, _, _) => app.cs.bus_trip,
         TripPhaseType::Cancelled | TripPhaseType::Finished => unreachable!(),
         TripPhaseType::DelayedStart => Color::YELLOW,
+        TripPhaseType::WaitingForRideHail => app.cs.unzoomed_ride_hail.alpha(0.5),
+        TripPhaseType::RidingRideHail(_) => app.cs.unzoomed_ride_hail,
+        TripPhaseType::WaitingForGuideway(_) => app.cs.bus_layer,
+        TripPhaseType::RidingGuideway(_) => app.cs.bus_trip,
     }
 }
 
@@ -0,0 +1,261 @@
+use geom::{Circle, Distance, Duration, Pt2D};
+use map_gui::ID;
+use map_model::{PathStep, RoadID};
+use sim::{AgentID, TripMode, TripPurpose};
+use widgetry::{
+    Btn, Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Panel, Spinner, State, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::{CommonState, Warping};
+
+/// Only this many matches are listed, worst delay first. All of them are highlighted.
+const MAX_LISTED: usize = 20;
+
+#[derive(Clone, PartialEq)]
+struct Filter {
+    mode: Option<TripMode>,
+    purpose: Option<TripPurpose>,
+    /// Total time spent waiting during the current leg of the trip
+    min_delay: Duration,
+    /// The agent's remaining route has to pass through this road
+    road: Option<RoadID>,
+}
+
+/// Find agents currently in the simulation matching some filters, then warp to them.
+pub struct AgentSearch {
+    panel: Panel,
+    filter: Filter,
+    /// Sorted by delay, worst first
+    matches: Vec<(AgentID, Pt2D, Duration)>,
+    draw: Drawable,
+}
+
+impl AgentSearch {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        AgentSearch::with_filter(
+            ctx,
+            app,
+            Filter {
+                mode: None,
+                purpose: None,
+                min_delay: Duration::ZERO,
+                road: None,
+            },
+        )
+    }
+
+    fn with_filter(ctx: &mut EventCtx, app: &App, filter: Filter) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let matches = find_matches(app, &filter);
+
+        let mut batch = GeomBatch::new();
+        if let Some(r) = filter.road {
+            batch.push(Color::CYAN.alpha(0.5), map.get_r(r).get_thick_polygon(map));
+        }
+        for (_, pt, _) in &matches {
+            batch.push(
+                Color::YELLOW,
+                Circle::new(*pt, Distance::meters(5.0))
+                    .to_outline(Distance::meters(1.0))
+                    .unwrap(),
+            );
+        }
+
+        let mut mode_choices = vec![Choice::new("any mode", None)];
+        for mode in TripMode::all() {
+            mode_choices.push(Choice::new(mode.ongoing_verb(), Some(mode)));
+        }
+        let mut purpose_choices = vec![Choice::new("any purpose", None)];
+        for purpose in TripPurpose::all() {
+            purpose_choices.push(Choice::new(purpose.to_string(), Some(purpose)));
+        }
+
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Search for agents").small_heading().draw(ctx),
+                Btn::close(ctx),
+            ]),
+            Widget::dropdown(ctx, "mode", filter.mode, mode_choices),
+            Widget::dropdown(ctx, "purpose", filter.purpose, purpose_choices),
+            Widget::row(vec![
+                "Delayed at least".draw_text(ctx).centered_vert(),
+                Spinner::new(
+                    ctx,
+                    (0, 240),
+                    (filter.min_delay.inner_seconds() / 60.0) as isize,
+                )
+                .named("min delay"),
+                "minutes".draw_text(ctx).centered_vert(),
+            ]),
+        ];
+        if let Some(r) = filter.road {
+            col.push(Widget::row(vec![
+                format!("Route passes through {}", r).draw_text(ctx),
+                Btn::text_fg("any road").build_def(ctx, None),
+            ]));
+        } else {
+            col.push(
+                "Click a road to only find agents whose route passes through it".draw_text(ctx),
+            );
+        }
+        col.push(Btn::text_bg2("refresh").build_def(ctx, Key::R));
+
+        col.push(
+            Line(format!("{} agents match", matches.len()))
+                .small_heading()
+                .draw(ctx),
+        );
+        for (idx, (id, _, delay)) in matches.iter().take(MAX_LISTED).enumerate() {
+            col.push(Widget::row(vec![
+                format!("{}, delayed {}", id, delay).draw_text(ctx),
+                Btn::text_fg("warp")
+                    .build(ctx, format!("warp to #{}", idx + 1), None)
+                    .align_right(),
+            ]));
+        }
+        if matches.len() > MAX_LISTED {
+            col.push(
+                format!(
+                    "{} more are highlighted on the map",
+                    matches.len() - MAX_LISTED
+                )
+                .draw_text(ctx),
+            );
+        }
+
+        Box::new(AgentSearch {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+                .build(ctx),
+            filter,
+            matches,
+            draw: ctx.upload(batch),
+        })
+    }
+
+    fn filter_from_panel(&self) -> Filter {
+        Filter {
+            mode: self.panel.dropdown_value("mode"),
+            purpose: self.panel.dropdown_value("purpose"),
+            min_delay: Duration::minutes(self.panel.spinner("min delay") as usize),
+            road: self.filter.road,
+        }
+    }
+}
+
+impl State<App> for AgentSearch {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.primary.current_selection = app.mouseover_unzoomed_roads_and_intersections(ctx);
+        }
+        let road = match app.primary.current_selection {
+            Some(ID::Road(r)) => Some(r),
+            Some(ID::Lane(l)) => Some(app.primary.map.get_l(l).parent),
+            _ => None,
+        };
+        if let Some(r) = road {
+            if Some(r) != self.filter.road
+                && app
+                    .per_obj
+                    .left_click(ctx, "find agents passing through here")
+            {
+                let mut filter = self.filter_from_panel();
+                filter.road = Some(r);
+                return Transition::Replace(AgentSearch::with_filter(ctx, app, filter));
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    app.primary.current_selection = None;
+                    Transition::Pop
+                }
+                "any road" => {
+                    let mut filter = self.filter_from_panel();
+                    filter.road = None;
+                    Transition::Replace(AgentSearch::with_filter(ctx, app, filter))
+                }
+                "refresh" => {
+                    let filter = self.filter_from_panel();
+                    Transition::Replace(AgentSearch::with_filter(ctx, app, filter))
+                }
+                x => {
+                    let idx = x["warp to #".len()..].parse::<usize>().unwrap();
+                    let (agent, pt, _) = self.matches[idx - 1];
+                    Transition::Push(Warping::new(
+                        ctx,
+                        pt,
+                        Some(10.0),
+                        Some(ID::from_agent(agent)),
+                        &mut app.primary,
+                    ))
+                }
+            },
+            Outcome::Changed => {
+                let filter = self.filter_from_panel();
+                if filter != self.filter {
+                    Transition::Replace(AgentSearch::with_filter(ctx, app, filter))
+                } else {
+                    Transition::Keep
+                }
+            }
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn find_matches(app: &App, filter: &Filter) -> Vec<(AgentID, Pt2D, Duration)> {
+    let sim = &app.primary.sim;
+    let map = &app.primary.map;
+    let mut matches = Vec::new();
+    for agent in sim.get_unzoomed_agents(map) {
+        if filter.mode.is_some() || filter.purpose.is_some() {
+            // Buses don't have a trip
+            let trip = match sim.agent_to_trip(agent.id) {
+                Some(t) => sim.trip_info(t),
+                None => continue,
+            };
+            if filter.mode.map(|m| m != trip.mode).unwrap_or(false)
+                || filter.purpose.map(|p| p != trip.purpose).unwrap_or(false)
+            {
+                continue;
+            }
+        }
+
+        let delay = sim.agent_properties(agent.id).total_waiting;
+        if delay < filter.min_delay {
+            continue;
+        }
+
+        if let Some(r) = filter.road {
+            let passes = sim
+                .get_path(agent.id)
+                .map(|path| {
+                    path.get_steps().iter().any(|step| match step {
+                        PathStep::Lane(l) | PathStep::ContraflowLane(l) => {
+                            map.get_l(*l).parent == r
+                        }
+                        PathStep::Turn(_) => false,
+                    })
+                })
+                .unwrap_or(false);
+            if !passes {
+                continue;
+            }
+        }
+
+        matches.push((agent.id, agent.pos, delay));
+    }
+    matches.sort_by_key(|(_, _, delay)| std::cmp::Reverse(*delay));
+    matches
+}
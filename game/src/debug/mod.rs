@@ -20,11 +20,13 @@ use crate::common::{tool_panel, CommonState};
 use crate::info::ContextualActions;
 use crate::sandbox::GameplayMode;
 
+mod agent_search;
 mod blocked_by;
 mod floodfill;
 mod objects;
 pub mod path_counter;
 mod polygons;
+mod route_export;
 pub mod shared_row;
 pub mod streetmix;
 
@@ -71,6 +73,7 @@ impl DebugMode {
                     Btn::text_fg("find large intersections").build_def(ctx, None),
                     Btn::text_fg("sim internal stats").build_def(ctx, None),
                     Btn::text_fg("blocked-by graph").build_def(ctx, Key::B),
+                    Btn::text_fg("search for agents").build_def(ctx, None),
                 ]),
                 Text::from_all(vec![
                     Line("Hold "),
@@ -83,7 +86,7 @@ impl DebugMode {
             .build(ctx),
             common: CommonState::new(),
             tool_panel: tool_panel(ctx),
-            objects: objects::ObjectDebugger,
+            objects: objects::ObjectDebugger::new(),
             hidden: HashSet::new(),
             layers: ShowLayers::new(),
             search_results: None,
@@ -146,6 +149,7 @@ impl State<App> for DebugMode {
                             {
                                 Some(new_sim) => {
                                     app.primary.sim = new_sim;
+                                    app.primary.snapshots.clear();
                                     app.recalculate_current_selection(ctx);
                                     None
                                 }
@@ -173,6 +177,7 @@ impl State<App> for DebugMode {
                         {
                             Some(new_sim) => {
                                 app.primary.sim = new_sim;
+                                app.primary.snapshots.clear();
                                 app.recalculate_current_selection(ctx);
                                 None
                             }
@@ -198,6 +203,7 @@ impl State<App> for DebugMode {
                             ctx.loading_screen("load savestate", |ctx, mut timer| {
                                 app.primary.sim = Sim::load_savestate(ss_path, &mut timer)
                                     .expect("Can't load savestate");
+                                app.primary.snapshots.clear();
                                 app.recalculate_current_selection(ctx);
                             });
                             Transition::Pop
@@ -257,6 +263,9 @@ impl State<App> for DebugMode {
                 "blocked-by graph" => {
                     return Transition::Push(blocked_by::Viewer::new(ctx, app));
                 }
+                "search for agents" => {
+                    return Transition::Push(agent_search::AgentSearch::new(ctx, app));
+                }
                 _ => unreachable!(),
             },
             Outcome::Changed => {
@@ -281,6 +290,7 @@ impl State<App> for DebugMode {
             _ => {}
         }
 
+        self.objects.event(ctx, app);
         self.highlighted_agents.update(
             match app.primary.current_selection {
                 Some(ID::Intersection(i)) => Some(i),
@@ -483,6 +493,10 @@ impl ContextualActions for Actions {
             }
             _ => {}
         }
+        if id.agent_id().is_some() {
+            actions.push((Key::G, "export route to GeoJSON".to_string()));
+            actions.push((Key::P, "export route to GPX".to_string()));
+        }
         actions
     }
 
@@ -515,6 +529,26 @@ impl ContextualActions for Actions {
                 app.primary.current_selection = None;
                 Transition::Keep
             }
+            (id, "export route to GeoJSON") => Transition::Push(
+                match route_export::export_geojson(app, id.agent_id().unwrap()) {
+                    Ok(path) => PopupMsg::new(
+                        ctx,
+                        "Route exported",
+                        vec![format!("Route exported to {}", path)],
+                    ),
+                    Err(err) => PopupMsg::new(ctx, "Export failed", vec![err.to_string()]),
+                },
+            ),
+            (id, "export route to GPX") => Transition::Push(
+                match route_export::export_gpx(app, id.agent_id().unwrap()) {
+                    Ok(path) => PopupMsg::new(
+                        ctx,
+                        "Route exported",
+                        vec![format!("Route exported to {}", path)],
+                    ),
+                    Err(err) => PopupMsg::new(ctx, "Export failed", vec![err.to_string()]),
+                },
+            ),
             (ID::Lane(l), "floodfill from this lane") => {
                 Transition::Push(floodfill::Floodfiller::floodfill(ctx, app, l))
             }
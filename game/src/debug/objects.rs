@@ -1,14 +1,43 @@
-use map_gui::ID;
-use map_model::{Map, PathConstraints};
-use sim::{AgentID, Sim};
-use widgetry::{GfxCtx, Key, Line, Text};
+use geom::{Circle, Distance, Polygon, Speed, Time};
+use map_gui::{Cached, ID};
+use map_model::{Map, PathConstraints, PathStep};
+use sim::{AgentID, CarConstraint, CarID, Sim};
+use widgetry::{
+    Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Panel, Text,
+    VerticalAlignment, Widget,
+};
 
 use crate::app::App;
 
-pub struct ObjectDebugger;
+pub struct ObjectDebugger {
+    /// Explains what the selected car is doing and why. The map-space drawable shows the rest of
+    /// its route.
+    car_intent: Cached<(CarID, Time), Option<(Panel, Drawable)>>,
+}
 
 impl ObjectDebugger {
+    pub fn new() -> ObjectDebugger {
+        ObjectDebugger {
+            car_intent: Cached::new(),
+        }
+    }
+
+    pub fn event(&mut self, ctx: &mut EventCtx, app: &App) {
+        self.car_intent.update(
+            match app.primary.current_selection {
+                Some(ID::Car(c)) => Some((c, app.primary.sim.time())),
+                _ => None,
+            },
+            |(car, _)| explain_car(ctx, app, car),
+        );
+    }
+
     pub fn draw(&self, g: &mut GfxCtx, app: &App) {
+        if let Some(Some((panel, draw))) = self.car_intent.value() {
+            g.redraw(draw);
+            panel.draw(g);
+        }
+
         if g.is_key_down(Key::LeftControl) {
             if let Some(pt) = g.canvas.get_cursor_in_map_space() {
                 let mut txt = Text::new();
@@ -104,3 +133,119 @@ impl ObjectDebugger {
         }
     }
 }
+
+/// How many of the upcoming turns to list
+const MAX_TURNS_LISTED: usize = 5;
+const SPARKLINE_WIDTH: f64 = 300.0;
+const SPARKLINE_HEIGHT: f64 = 50.0;
+
+fn explain_car(ctx: &mut EventCtx, app: &App, id: CarID) -> Option<(Panel, Drawable)> {
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    let intent = sim.car_intent(id, map)?;
+
+    let mut txt = Text::new();
+    txt.add(Line(format!("Decision: {}", intent.decision)));
+    txt.add(Line(format!(
+        "Limited by: {}",
+        match intent.constraint {
+            Some(CarConstraint::SpeedLimit(speed)) => {
+                format!("the speed limit ({})", speed.to_string(&app.opts.units))
+            }
+            Some(CarConstraint::LeadVehicle(a)) => format!("the vehicle ahead, {}", a),
+            Some(CarConstraint::Intersection(i)) => format!("the signal or stop sign at {}", i),
+            Some(CarConstraint::EndOfLane) => "the end of the lane".to_string(),
+            None => "nothing, it's not driving".to_string(),
+        }
+    )));
+    let turns: Vec<String> = intent
+        .remaining_path
+        .iter()
+        .filter_map(|step| match step {
+            PathStep::Turn(t) => Some(t.to_string()),
+            _ => None,
+        })
+        .collect();
+    txt.add(Line(format!(
+        "{} steps left, with {} turns",
+        intent.remaining_path.len(),
+        turns.len()
+    )));
+    for t in turns.iter().take(MAX_TURNS_LISTED) {
+        txt.add(Line(format!("- {}", t)).secondary());
+    }
+    if turns.len() > MAX_TURNS_LISTED {
+        txt.add(Line(format!("- {} more", turns.len() - MAX_TURNS_LISTED)).secondary());
+    }
+
+    let sparkline = if let Some((start, _)) = intent.speed_history.first() {
+        txt.add(Line(format!("Speed since {}:", start)));
+        speed_sparkline(&intent.speed_history, sim.time())
+    } else {
+        txt.add(Line("No speed history yet").secondary());
+        GeomBatch::new()
+    };
+
+    let panel = Panel::new(Widget::col(vec![
+        Line(format!("What is {} doing?", id))
+            .small_heading()
+            .draw(ctx),
+        txt.draw(ctx),
+        Widget::draw_batch(ctx, sparkline),
+    ]))
+    .aligned(HorizontalAlignment::Left, VerticalAlignment::Top)
+    .build(ctx);
+
+    let mut batch = GeomBatch::new();
+    if let Some(trace) = sim.trace_route(AgentID::Car(id), map, None) {
+        batch.push(
+            Color::CYAN.alpha(0.8),
+            trace.make_polygons(Distance::meters(2.0)),
+        );
+    }
+    match intent.constraint {
+        Some(CarConstraint::Intersection(i)) => {
+            batch.push(Color::RED.alpha(0.5), map.get_i(i).polygon.clone());
+        }
+        Some(CarConstraint::LeadVehicle(a)) => {
+            if let Some(pt) = sim.canonical_pt_for_agent(a, map) {
+                batch.push(
+                    Color::RED.alpha(0.5),
+                    Circle::new(pt, Distance::meters(5.0)).to_polygon(),
+                );
+            }
+        }
+        _ => {}
+    }
+
+    Some((panel, ctx.upload(batch)))
+}
+
+/// A bar for each speed the car has moved at, as wide as the time spent at that speed.
+fn speed_sparkline(history: &Vec<(Time, Speed)>, now: Time) -> GeomBatch {
+    let start = history[0].0;
+    let total = (now - start).inner_seconds().max(1.0);
+    let max_speed = history
+        .iter()
+        .map(|(_, speed)| speed.inner_meters_per_second())
+        .fold(1.0, f64::max);
+
+    let mut batch = GeomBatch::new();
+    batch.push(
+        Color::grey(0.3),
+        Polygon::rectangle(SPARKLINE_WIDTH, SPARKLINE_HEIGHT),
+    );
+    for (idx, (time, speed)) in history.iter().enumerate() {
+        let end = history.get(idx + 1).map(|(t, _)| *t).unwrap_or(now);
+        let x1 = SPARKLINE_WIDTH * (*time - start).inner_seconds() / total;
+        let x2 = SPARKLINE_WIDTH * (end - start).inner_seconds() / total;
+        let height = SPARKLINE_HEIGHT * speed.inner_meters_per_second() / max_speed;
+        if x2 > x1 && height > 0.0 {
+            batch.push(
+                Color::GREEN,
+                Polygon::rectangle(x2 - x1, height).translate(x1, SPARKLINE_HEIGHT - height),
+            );
+        }
+    }
+    batch
+}
@@ -0,0 +1,89 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+
+use geojson::{Feature, GeoJson};
+
+use geom::PolyLine;
+use sim::AgentID;
+
+use crate::app::App;
+
+/// Exports the rest of an agent's current route as a GeoJSON LineString in lon/lat coordinates,
+/// returns the filename.
+pub fn export_geojson(app: &App, agent: AgentID) -> Result<String, Box<dyn Error>> {
+    let map = &app.primary.map;
+    let trace = current_route(app, agent)?;
+
+    let mut properties = serde_json::Map::new();
+    properties.insert("agent".to_string(), agent.to_string().into());
+    properties.insert(
+        "time".to_string(),
+        app.primary.sim.time().to_string().into(),
+    );
+    let geojson = GeoJson::from(Feature {
+        bbox: None,
+        geometry: Some(trace.to_geojson(Some(map.get_gps_bounds()))),
+        id: None,
+        properties: Some(properties),
+        foreign_members: None,
+    });
+
+    let path = format!("{}.geojson", filename(app, agent));
+    abstutil::write_json(path.clone(), &geojson);
+    Ok(path)
+}
+
+/// Exports the rest of an agent's current route as a GPX track, returns the filename.
+pub fn export_gpx(app: &App, agent: AgentID) -> Result<String, Box<dyn Error>> {
+    let trace = current_route(app, agent)?;
+
+    let path = format!("{}.gpx", filename(app, agent));
+    let mut f = File::create(&path)?;
+    writeln!(f, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        f,
+        r#"<gpx version="1.1" creator="A/B Street" xmlns="http://www.topografix.com/GPX/1/1">"#
+    )?;
+    writeln!(f, "  <trk>")?;
+    writeln!(
+        f,
+        "    <name>{} at {}</name>",
+        agent,
+        app.primary.sim.time()
+    )?;
+    writeln!(f, "    <trkseg>")?;
+    for pt in app
+        .primary
+        .map
+        .get_gps_bounds()
+        .convert_back(trace.points())
+    {
+        writeln!(f, r#"      <trkpt lat="{}" lon="{}"/>"#, pt.y(), pt.x())?;
+    }
+    writeln!(f, "    </trkseg>")?;
+    writeln!(f, "  </trk>")?;
+    writeln!(f, "</gpx>")?;
+    Ok(path)
+}
+
+fn current_route(app: &App, agent: AgentID) -> Result<PolyLine, String> {
+    app.primary
+        .sim
+        .trace_route(agent, &app.primary.map, None)
+        .ok_or_else(|| format!("{} doesn't have a route right now", agent))
+}
+
+fn filename(app: &App, agent: AgentID) -> String {
+    let agent = match agent {
+        AgentID::Car(c) => format!("car_{}", c.0),
+        AgentID::Pedestrian(p) => format!("ped_{}", p.0),
+        AgentID::BusPassenger(p, _) => format!("person_{}", p.0),
+    };
+    format!(
+        "route_{}_{}_{}",
+        app.primary.map.get_name().as_filename(),
+        agent,
+        app.primary.sim.time().as_filename()
+    )
+}
@@ -68,6 +68,7 @@ fn lane(lane: &Lane) -> Option<serde_json::Map<String, serde_json::value::Value>
             LaneType::Sidewalk => "sidewalk".into(),
             // TODO Nope
             LaneType::Shoulder => "sidewalk".into(),
+            LaneType::Footway => "sidewalk".into(),
             LaneType::Biking => "bike_lane".into(),
             LaneType::Bus => "bus_lane".into(),
             LaneType::SharedLeftTurn => "turn_lane".into(),
@@ -44,6 +44,7 @@ fn lane(lane: &Lane, dir: Direction) -> serde_json::Map<String, serde_json::valu
         },
         LaneType::Sidewalk => ("sidewalk", "dense"),
         LaneType::Shoulder => ("sidewalk", "dense"),
+        LaneType::Footway => ("sidewalk", "normal"),
         LaneType::Biking => match dir {
             Direction::Fwd => ("bike-lane", "inbound|green|road"),
             Direction::Back => ("bike-lane", "outbound|green|road"),
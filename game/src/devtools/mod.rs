@@ -111,7 +111,7 @@ impl State<App> for DevToolsMode {
                             app.primary.map.get_name(),
                         ))),
                         Box::new(|s, ctx, app| {
-                            let scenario = abstutil::read_binary(
+                            let scenario = abstutil::read_versioned_binary(
                                 abstutil::path_scenario(app.primary.map.get_name(), &s),
                                 &mut Timer::throwaway(),
                             );
@@ -0,0 +1,116 @@
+use geom::{Circle, Distance};
+use map_model::{BuildingID, EditCmd};
+use widgetry::{
+    Btn, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome,
+    Panel, Spinner, State, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::edit::apply_map_edits;
+use map_gui::ID;
+
+/// Shows where cyclists can lock up their bikes, and lets the player add or remove racks.
+pub struct BikeRackEditor {
+    panel: Panel,
+    draw: Drawable,
+    selected: Option<BuildingID>,
+}
+
+impl BikeRackEditor {
+    pub fn new(ctx: &mut EventCtx, app: &App, selected: Option<BuildingID>) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut batch = GeomBatch::new();
+        let racks = map.all_bike_racks();
+        for (b, capacity) in &racks {
+            batch.push(
+                Color::CYAN.alpha(0.8),
+                Circle::new(
+                    map.get_b(*b).label_center,
+                    Distance::meters(3.0 + (*capacity as f64).sqrt()),
+                )
+                .to_polygon(),
+            );
+        }
+        if let Some(b) = selected {
+            batch.push(Color::YELLOW.alpha(0.5), map.get_b(b).polygon.clone());
+        }
+
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Bike racks").small_heading().draw(ctx),
+                Btn::close(ctx),
+            ]),
+            format!(
+                "{} buildings have room for {} bikes",
+                racks.len(),
+                racks.iter().map(|(_, cap)| cap).sum::<usize>()
+            )
+            .draw_text(ctx),
+            "Cyclists without a free rack take longer to lock up".draw_text(ctx),
+        ];
+        if let Some(b) = selected {
+            col.push(map.get_b(b).address.clone().draw_text(ctx));
+            col.push(Widget::row(vec![
+                "Room for bikes:".draw_text(ctx).centered_vert(),
+                Spinner::new(ctx, (0, 200), map.get_bike_rack_capacity(b) as isize)
+                    .named("capacity"),
+            ]));
+            col.push(Btn::text_bg2("apply").build_def(ctx, Key::Enter));
+        } else {
+            col.push("Click a building to change its racks".draw_text(ctx));
+        }
+
+        Box::new(BikeRackEditor {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+                .build(ctx),
+            draw: ctx.upload(batch),
+            selected,
+        })
+    }
+}
+
+impl State<App> for BikeRackEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.primary.current_selection = app.mouseover_unzoomed_buildings(ctx);
+        }
+        if let Some(ID::Building(b)) = app.primary.current_selection {
+            if Some(b) != self.selected && app.per_obj.left_click(ctx, "change bike racks here") {
+                return Transition::Replace(BikeRackEditor::new(ctx, app, Some(b)));
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    app.primary.current_selection = None;
+                    Transition::Pop
+                }
+                "apply" => {
+                    let b = self.selected.unwrap();
+                    let old = app.primary.map.get_bike_rack_capacity(b);
+                    let new = self.panel.spinner("capacity") as usize;
+                    if old != new {
+                        let mut edits = app.primary.map.get_edits().clone();
+                        edits
+                            .commands
+                            .push(EditCmd::ChangeBikeRacks { b, old, new });
+                        apply_map_edits(ctx, app, edits);
+                    }
+                    Transition::Replace(BikeRackEditor::new(ctx, app, None))
+                }
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
@@ -0,0 +1,138 @@
+use map_model::{BikeTreatments, EditCmd, IntersectionID};
+use widgetry::{
+    Btn, Checkbox, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Panel, State, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::edit::apply_map_edits;
+use map_gui::ID;
+
+/// Shows which intersections have been redesigned for cyclists, and lets the player change them.
+pub struct BikeTreatmentEditor {
+    panel: Panel,
+    draw: Drawable,
+    selected: Option<IntersectionID>,
+}
+
+impl BikeTreatmentEditor {
+    pub fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        selected: Option<IntersectionID>,
+    ) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut batch = GeomBatch::new();
+        for i in map.all_bike_treatments().keys() {
+            batch.push(Color::CYAN.alpha(0.8), map.get_i(*i).polygon.clone());
+        }
+        if let Some(i) = selected {
+            batch.push(Color::YELLOW.alpha(0.5), map.get_i(i).polygon.clone());
+        }
+
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Bike treatments").small_heading().draw(ctx),
+                Btn::close(ctx),
+            ]),
+            format!(
+                "{} intersections have been redesigned for cyclists",
+                map.all_bike_treatments().len()
+            )
+            .draw_text(ctx),
+        ];
+        if let Some(i) = selected {
+            let treatments = map.get_bike_treatments(i);
+            let is_signal = map.maybe_get_traffic_signal(i).is_some();
+            col.push(format!("{}", i).draw_text(ctx));
+            col.push(Checkbox::switch(
+                ctx,
+                "protected intersection",
+                None,
+                treatments.protected,
+            ));
+            col.push("Drivers turning across cyclists yield to them".draw_text(ctx));
+            if is_signal {
+                col.push(Checkbox::switch(ctx, "bike box", None, treatments.bike_box));
+                col.push("Cyclists waiting at a red start first".draw_text(ctx));
+                col.push(Checkbox::switch(
+                    ctx,
+                    "two-stage left turns",
+                    None,
+                    treatments.two_stage_left,
+                ));
+                col.push("Cyclists turn left with through traffic, in two steps".draw_text(ctx));
+            } else {
+                col.push(
+                    "Bike boxes and two-stage left turns need a traffic signal".draw_text(ctx),
+                );
+            }
+            col.push(Btn::text_bg2("apply").build_def(ctx, Key::Enter));
+        } else {
+            col.push("Click an intersection to change it".draw_text(ctx));
+        }
+
+        Box::new(BikeTreatmentEditor {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+                .build(ctx),
+            draw: ctx.upload(batch),
+            selected,
+        })
+    }
+}
+
+impl State<App> for BikeTreatmentEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.primary.current_selection = app.mouseover_unzoomed_intersections(ctx);
+        }
+        if let Some(ID::Intersection(i)) = app.primary.current_selection {
+            if Some(i) != self.selected
+                && !app.primary.map.get_i(i).is_border()
+                && app.per_obj.left_click(ctx, "change bike treatments here")
+            {
+                return Transition::Replace(BikeTreatmentEditor::new(ctx, app, Some(i)));
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    app.primary.current_selection = None;
+                    Transition::Pop
+                }
+                "apply" => {
+                    let i = self.selected.unwrap();
+                    let old = app.primary.map.get_bike_treatments(i);
+                    let new = BikeTreatments {
+                        protected: self.panel.is_checked("protected intersection"),
+                        bike_box: self.panel.maybe_is_checked("bike box").unwrap_or(false),
+                        two_stage_left: self
+                            .panel
+                            .maybe_is_checked("two-stage left turns")
+                            .unwrap_or(false),
+                    };
+                    if old != new {
+                        let mut edits = app.primary.map.get_edits().clone();
+                        edits
+                            .commands
+                            .push(EditCmd::ChangeBikeTreatments { i, old, new });
+                        apply_map_edits(ctx, app, edits);
+                    }
+                    Transition::Replace(BikeTreatmentEditor::new(ctx, app, None))
+                }
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
@@ -282,6 +282,7 @@ fn make_bulk_edits(
     let mut speed_changes = 0;
     let mut lt_changes = 0;
     let mut errors = Vec::new();
+    let first_cmd = app.primary.map.get_edits().commands.len();
     ctx.loading_screen("change lane types", |ctx, timer| {
         if let Some(speed) = speed_limit {
             let mut edits = app.primary.map.get_edits().clone();
@@ -331,6 +332,11 @@ fn make_bulk_edits(
                 }
             }
         }
+
+        // Undo and redo everything as one step
+        let mut edits = app.primary.map.get_edits().clone();
+        edits.group_commands_since(first_cmd);
+        apply_map_edits(ctx, app, edits);
     });
 
     // TODO Need to express the errors in some form that we can union here.
@@ -0,0 +1,285 @@
+use abstutil::prettyprint_usize;
+use geom::{Circle, Distance, Duration, LonLat, Pt2D, Speed};
+use map_gui::tools::PopupMsg;
+use map_model::{EditCmd, Guideway, GuidewayKind, Map};
+use widgetry::{
+    Btn, Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Panel, Spinner, State, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::edit::apply_map_edits;
+
+const STATION_RADIUS: Distance = Distance::const_meters(10.0);
+
+/// Lists the guideways in the current proposal, with their ridership so far.
+pub struct GuidewayEditor {
+    panel: Panel,
+    draw: Drawable,
+}
+
+impl GuidewayEditor {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut batch = GeomBatch::new();
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Guideways").small_heading().draw(ctx),
+                Btn::close(ctx),
+            ]),
+            "Gondolas, pods, or monorails running straight between stations. People taking \
+             transit ride one when it's faster than walking."
+                .draw_text(ctx),
+        ];
+        let ridership = app.primary.sim.get_guideway_ridership();
+        for (idx, guideway) in map.get_guideways().iter().enumerate() {
+            draw_stations(&mut batch, map, &guideway.stations);
+            if let Some(pt) = guideway.station_pts(map).get(0) {
+                batch.append(
+                    Text::from(Line(&guideway.name))
+                        .render_autocropped(ctx)
+                        .scale(2.0)
+                        .centered_on(*pt),
+                );
+            }
+
+            let mut txt = Text::from_multiline(vec![
+                Line(format!("{} ({})", guideway.name, guideway.kind.describe())),
+                Line(guideway.describe_service()).secondary(),
+            ]);
+            if let Some(stats) = ridership.get(&idx) {
+                txt.add(
+                    Line(format!(
+                        "{} riders so far, {} left behind by a full {}",
+                        prettyprint_usize(stats.riders),
+                        prettyprint_usize(stats.left_behind),
+                        guideway.kind.vehicle_noun()
+                    ))
+                    .secondary(),
+                );
+                if stats.riders > 0 {
+                    txt.add(
+                        Line(format!(
+                            "Average wait: {}",
+                            stats.total_wait / (stats.riders as f64)
+                        ))
+                        .secondary(),
+                    );
+                }
+            } else {
+                txt.add(Line("No riders yet").secondary());
+            }
+            col.push(Widget::row(vec![
+                txt.draw(ctx),
+                Btn::svg_def("system/assets/tools/delete.svg")
+                    .build(ctx, format!("delete guideway {}", idx + 1), None)
+                    .align_right(),
+            ]));
+        }
+        if map.get_guideways().is_empty() {
+            col.push("No guideways yet".draw_text(ctx));
+        }
+        col.push(Btn::text_fg("draw a new guideway").build_def(ctx, Key::N));
+
+        Box::new(GuidewayEditor {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+                .build(ctx),
+            draw: ctx.upload(batch),
+        })
+    }
+}
+
+impl State<App> for GuidewayEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "draw a new guideway" => Transition::Push(DrawGuideway::new(ctx)),
+                x => {
+                    let idx = x["delete guideway ".len()..].parse::<usize>().unwrap() - 1;
+                    let old = app.primary.map.get_guideways().clone();
+                    let mut new = old.clone();
+                    new.remove(idx);
+                    change_guideways(ctx, app, old, new);
+                    Transition::Replace(GuidewayEditor::new(ctx, app))
+                }
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn change_guideways(ctx: &mut EventCtx, app: &mut App, old: Vec<Guideway>, new: Vec<Guideway>) {
+    let mut edits = app.primary.map.get_edits().clone();
+    edits.commands.push(EditCmd::ChangeGuideways { old, new });
+    apply_map_edits(ctx, app, edits);
+}
+
+fn draw_stations(batch: &mut GeomBatch, map: &Map, stations: &[LonLat]) {
+    let pts: Vec<Pt2D> = map.get_gps_bounds().convert(stations);
+    for pair in pts.windows(2) {
+        if let Some(line) = geom::Line::new(pair[0], pair[1]) {
+            batch.push(Color::PURPLE, line.make_polygons(STATION_RADIUS / 2.0));
+        }
+    }
+    for pt in pts {
+        batch.push(Color::PURPLE, Circle::new(pt, STATION_RADIUS).to_polygon());
+    }
+}
+
+struct DrawGuideway {
+    panel: Panel,
+    kind: GuidewayKind,
+    stations: Vec<LonLat>,
+}
+
+impl DrawGuideway {
+    fn new(ctx: &mut EventCtx) -> Box<dyn State<App>> {
+        let kind = GuidewayKind::Gondola;
+        Box::new(DrawGuideway {
+            panel: DrawGuideway::make_panel(ctx, kind, String::new()),
+            kind,
+            stations: Vec::new(),
+        })
+    }
+
+    /// Starts with the typical service for this kind of guideway.
+    fn make_panel(ctx: &mut EventCtx, kind: GuidewayKind, name: String) -> Panel {
+        let (capacity, speed, headway) = kind.typical_service();
+        Panel::new(Widget::col(vec![
+            Widget::row(vec![
+                Line("Draw a guideway").small_heading().draw(ctx),
+                Btn::close(ctx),
+            ]),
+            "Click to add stations, in order along the line".draw_text(ctx),
+            Widget::row(vec![
+                "Name:".draw_text(ctx).centered_vert(),
+                Widget::text_entry(ctx, name, true).named("name"),
+            ]),
+            Widget::row(vec![
+                "Kind:".draw_text(ctx).centered_vert(),
+                Widget::dropdown(
+                    ctx,
+                    "kind",
+                    kind,
+                    GuidewayKind::all()
+                        .into_iter()
+                        .map(|k| Choice::new(k.describe(), k))
+                        .collect(),
+                ),
+            ]),
+            Widget::row(vec![
+                format!("People per {}:", kind.vehicle_noun())
+                    .draw_text(ctx)
+                    .centered_vert(),
+                Spinner::new(ctx, (1, 500), capacity as isize).named("capacity"),
+            ]),
+            Widget::row(vec![
+                "Speed in mph:".draw_text(ctx).centered_vert(),
+                Spinner::new(
+                    ctx,
+                    (1, 100),
+                    (speed.inner_meters_per_second()
+                        / Speed::miles_per_hour(1.0).inner_meters_per_second())
+                    .round() as isize,
+                )
+                .named("speed"),
+            ]),
+            Widget::row(vec![
+                format!("Seconds between {}s:", kind.vehicle_noun())
+                    .draw_text(ctx)
+                    .centered_vert(),
+                Spinner::new(ctx, (5, 3600), headway.inner_seconds() as isize).named("headway"),
+            ]),
+            Widget::row(vec![
+                Btn::text_fg("undo last station").build_def(ctx, Key::Backspace),
+                Btn::text_bg2("save").build_def(ctx, Key::Enter),
+            ]),
+        ]))
+        .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+        .build(ctx)
+    }
+}
+
+impl State<App> for DrawGuideway {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "undo last station" => {
+                    self.stations.pop();
+                }
+                "save" => {
+                    let name = self.panel.text_box("name");
+                    if name.is_empty() {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["Name the guideway first"],
+                        ));
+                    }
+                    let guideway = Guideway {
+                        name,
+                        kind: self.kind,
+                        stations: self.stations.clone(),
+                        capacity: self.panel.spinner("capacity") as usize,
+                        speed: Speed::miles_per_hour(self.panel.spinner("speed") as f64),
+                        headway: Duration::seconds(self.panel.spinner("headway") as f64),
+                    };
+                    if let Err(err) = guideway.find_station_positions(&app.primary.map) {
+                        return Transition::Push(PopupMsg::new(ctx, "Error", vec![err]));
+                    }
+                    let old = app.primary.map.get_guideways().clone();
+                    let mut new = old.clone();
+                    new.push(guideway);
+                    change_guideways(ctx, app, old, new);
+                    return Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::Replace(GuidewayEditor::new(ctx, app)),
+                    ]);
+                }
+                _ => unreachable!(),
+            },
+            Outcome::Changed => {
+                let kind: GuidewayKind = self.panel.dropdown_value("kind");
+                if kind != self.kind {
+                    self.kind = kind;
+                    let name = self.panel.text_box("name");
+                    self.panel = DrawGuideway::make_panel(ctx, kind, name);
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(pt) = ctx.canvas.get_cursor_in_map_space() {
+            if app.per_obj.left_click(ctx, "add a new station") {
+                self.stations
+                    .push(pt.to_gps(app.primary.map.get_gps_bounds()));
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let mut batch = GeomBatch::new();
+        draw_stations(&mut batch, &app.primary.map, &self.stations);
+        batch.draw(g);
+
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
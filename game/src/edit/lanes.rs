@@ -1,3 +1,4 @@
+use geom::Distance;
 use map_gui::render::Renderable;
 use map_gui::ID;
 use map_model::{EditCmd, LaneID, LaneType, Map};
@@ -9,6 +10,8 @@ use widgetry::{
 use crate::app::App;
 use crate::app::Transition;
 use crate::common::{CommonState, SimpleState};
+use crate::edit::timed_speed_limits::TimedSpeedLimitEditor;
+use crate::edit::turn_restrictions::TurnRestrictionEditor;
 use crate::edit::zones::ZoneEditor;
 use crate::edit::{
     apply_map_edits, can_edit_lane, maybe_edit_intersection, speed_limit_choices, try_change_lt,
@@ -97,7 +100,44 @@ impl LaneEditor {
                     Widget::dropdown(ctx, "speed limit", parent.speed_limit, choices),
                 ])
             },
+            {
+                let width = app.primary.map.get_l(l).width;
+                let mut choices = lane_width_choices(app);
+                if !choices.iter().any(|c| c.data == width) {
+                    choices.push(Choice::new(width.to_string(&app.opts.units), width));
+                }
+                Widget::row(vec![
+                    "Change lane width:".draw_text(ctx).centered_vert(),
+                    Widget::dropdown(ctx, "width", width, choices),
+                ])
+            },
+            {
+                let mut choices: Vec<Choice<usize>> = vec![0, 1, 2, 4, 6, 10, 15, 20, 30]
+                    .into_iter()
+                    .map(|n| Choice::new(n.to_string(), n))
+                    .collect();
+                if !choices.iter().any(|c| c.data == parent.deliveries_per_hour) {
+                    choices.push(Choice::new(
+                        parent.deliveries_per_hour.to_string(),
+                        parent.deliveries_per_hour,
+                    ));
+                }
+                Widget::row(vec![
+                    "Deliveries stopping in the road per hour:"
+                        .draw_text(ctx)
+                        .centered_vert(),
+                    Widget::dropdown(ctx, "deliveries", parent.deliveries_per_hour, choices),
+                ])
+            },
             Btn::text_fg("Change access restrictions").build_def(ctx, Key::A),
+            Btn::text_fg("Change turn restrictions").build_def(ctx, Key::R),
+            Btn::text_fg("Change speed limits by time of day").build_def(ctx, Key::H),
+            Btn::text_fg(if parent.modal_filter {
+                "remove modal filter"
+            } else {
+                "add modal filter"
+            })
+            .build_def(ctx, Key::L),
             Btn::text_bg2("Finish").build_def(ctx, Key::Escape),
         ];
         let panel = Panel::new(Widget::col(col))
@@ -121,11 +161,24 @@ impl SimpleState for LaneEditor {
                 app,
                 app.primary.map.get_l(self.l).parent,
             )),
+            "Change turn restrictions" => Transition::Push(TurnRestrictionEditor::new(
+                ctx,
+                app,
+                app.primary.map.get_l(self.l).parent,
+            )),
+            "Change speed limits by time of day" => Transition::Push(TimedSpeedLimitEditor::new(
+                ctx,
+                app,
+                app.primary.map.get_l(self.l).parent,
+            )),
             "Finish" => Transition::Pop,
             x => {
                 let map = &mut app.primary.map;
                 let result = match x {
                     "reverse direction" => Ok(reverse_lane(map, self.l)),
+                    "add modal filter" | "remove modal filter" => {
+                        Ok(toggle_modal_filter(map, self.l))
+                    }
                     "convert to a driving lane" => {
                         try_change_lt(ctx, map, self.l, LaneType::Driving)
                     }
@@ -161,13 +214,15 @@ impl SimpleState for LaneEditor {
         app: &mut App,
         panel: &Panel,
     ) -> Option<Transition> {
-        let mut edits = app.primary.map.get_edits().clone();
-        edits.commands.push(app.primary.map.edit_road_cmd(
-            app.primary.map.get_l(self.l).parent,
-            |new| {
-                new.speed_limit = panel.dropdown_value("speed limit");
-            },
-        ));
+        let map = &app.primary.map;
+        let parent = map.get_parent(self.l);
+        let idx = parent.offset(self.l);
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(map.edit_road_cmd(parent.id, |new| {
+            new.speed_limit = panel.dropdown_value("speed limit");
+            new.lane_widths[idx] = panel.dropdown_value("width");
+            new.deliveries_per_hour = panel.dropdown_value("deliveries");
+        }));
         apply_map_edits(ctx, app, edits);
         Some(Transition::Replace(LaneEditor::new(
             ctx,
@@ -226,3 +281,21 @@ fn reverse_lane(map: &Map, l: LaneID) -> EditCmd {
         new.lanes_ltr[idx].1 = new.lanes_ltr[idx].1.opposite();
     })
 }
+
+// A modal filter stops cars and trucks from driving through the middle of the road, but still
+// lets pedestrians, bikes, and buses pass.
+fn toggle_modal_filter(map: &Map, l: LaneID) -> EditCmd {
+    map.edit_road_cmd(map.get_l(l).parent, |new| {
+        new.modal_filter = !new.modal_filter;
+    })
+}
+
+fn lane_width_choices(app: &App) -> Vec<Choice<Distance>> {
+    // From a narrow bike lane to a wide truck lane, every quarter meter
+    (6..=20)
+        .map(|quarters| {
+            let width = Distance::meters(0.25 * (quarters as f64));
+            Choice::new(width.to_string(&app.opts.units), width)
+        })
+        .collect()
+}
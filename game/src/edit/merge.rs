@@ -0,0 +1,216 @@
+use std::collections::BTreeSet;
+
+use abstutil::Timer;
+use map_gui::tools::{grey_out_map, ChooseSomething, PopupMsg};
+use map_model::{EditConflict, EditTarget, MapEdits};
+use widgetry::{
+    Btn, Checkbox, Choice, EventCtx, GfxCtx, Key, Line, Outcome, Panel, State, Text, TextExt,
+    Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::Warping;
+use crate::edit::{apply_map_edits, cmd_to_id};
+use crate::sandbox::GameplayMode;
+
+/// Pick another saved proposal to merge into the current one.
+pub fn choose_proposal(ctx: &mut EventCtx, app: &App, mode: GameplayMode) -> Box<dyn State<App>> {
+    let current = app.primary.map.get_edits().edits_name.clone();
+    let choices: Vec<Choice<String>> =
+        abstutil::list_all_objects(abstutil::path_all_edits(app.primary.map.get_name()))
+            .into_iter()
+            .filter(|name| name != &current)
+            .map(|name| Choice::new(name.clone(), name))
+            .collect();
+    if choices.is_empty() {
+        return PopupMsg::new(
+            ctx,
+            "No proposals",
+            vec!["There's no other proposal to merge"],
+        );
+    }
+
+    ChooseSomething::new(
+        ctx,
+        "Merge which proposal into this one?",
+        choices,
+        Box::new(move |name, ctx, app| {
+            let path = abstutil::path_edits(app.primary.map.get_name(), &name);
+            let theirs = match MapEdits::load(&app.primary.map, path, &mut Timer::throwaway()) {
+                Ok(edits) => edits,
+                Err(err) => {
+                    return Transition::Replace(PopupMsg::new(
+                        ctx,
+                        "Error",
+                        vec![format!("Can't load {}", name), err],
+                    ));
+                }
+            };
+            if !mode.allows(&theirs) {
+                return Transition::Replace(PopupMsg::new(
+                    ctx,
+                    "Error",
+                    vec![
+                        "The current gameplay mode restricts edits. That proposal has a banned \
+                         command.",
+                    ],
+                ));
+            }
+            Transition::Replace(merge_proposal(ctx, app, &name, theirs))
+        }),
+    )
+}
+
+/// Merge another proposal into the current one, first asking which side wins each conflict, if
+/// there are any. The caller checks that the gameplay mode allows the other proposal.
+pub fn merge_proposal(
+    ctx: &mut EventCtx,
+    app: &mut App,
+    name: &str,
+    theirs: MapEdits,
+) -> Box<dyn State<App>> {
+    let conflicts = app.primary.map.get_edits().find_conflicts(&theirs);
+    if conflicts.is_empty() {
+        let merged = app.primary.map.get_edits().merge(&theirs, &BTreeSet::new());
+        apply_map_edits(ctx, app, merged);
+        return PopupMsg::new(
+            ctx,
+            "Merged",
+            vec![format!("Nothing in {} conflicts with this proposal", name)],
+        );
+    }
+    MergeEdits::new(ctx, app, theirs, conflicts)
+}
+
+/// Both proposals change some of the same roads or intersections. Decide which side wins each one.
+struct MergeEdits {
+    panel: Panel,
+    theirs: MapEdits,
+    conflicts: Vec<EditConflict>,
+}
+
+impl MergeEdits {
+    fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        theirs: MapEdits,
+        conflicts: Vec<EditConflict>,
+    ) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let ours_name = &map.get_edits().edits_name;
+
+        let mut col = vec![
+            Widget::row(vec![
+                Line(format!("Merge {} into {}", theirs.edits_name, ours_name))
+                    .small_heading()
+                    .draw(ctx),
+                Btn::close(ctx),
+            ]),
+            format!(
+                "{} changes conflict. Everything else from both proposals is kept.",
+                conflicts.len()
+            )
+            .draw_text(ctx),
+        ];
+        for (idx, conflict) in conflicts.iter().enumerate() {
+            let mut txt = Text::new();
+            let (summary, ours_details) = conflict.ours.describe(map);
+            if let EditTarget::Road(r) = conflict.target {
+                txt.add(Line(format!(
+                    "{} ({})",
+                    summary,
+                    map.get_r(r).get_name(app.opts.language.as_ref())
+                )));
+            } else {
+                txt.add(Line(summary));
+            }
+            let (_, theirs_details) = conflict.theirs.describe(map);
+            for (name, details) in vec![
+                (ours_name, ours_details),
+                (&theirs.edits_name, theirs_details),
+            ] {
+                let details = if details.is_empty() {
+                    "changed".to_string()
+                } else {
+                    details.join(", ")
+                };
+                txt.add(Line(format!("{}: {}", name, details)).secondary());
+            }
+
+            col.push(Widget::row(vec![
+                txt.draw(ctx),
+                Checkbox::new(
+                    false,
+                    Btn::text_bg2(format!("keep {}", ours_name)).build(
+                        ctx,
+                        format!("keep ours for #{}", idx + 1),
+                        None,
+                    ),
+                    Btn::text_bg2(format!("keep {}", theirs.edits_name)).build(
+                        ctx,
+                        format!("keep theirs for #{}", idx + 1),
+                        None,
+                    ),
+                )
+                .named(format!("conflict #{}", idx + 1))
+                .align_right(),
+                if cmd_to_id(&conflict.ours).is_some() {
+                    Btn::text_fg("show").build(ctx, format!("show #{}", idx + 1), None)
+                } else {
+                    Widget::nothing()
+                },
+            ]));
+        }
+        col.push(Btn::text_bg2("Merge").build_def(ctx, Key::Enter));
+
+        Box::new(MergeEdits {
+            panel: Panel::new(Widget::col(col))
+                .exact_size_percent(60, 70)
+                .build(ctx),
+            theirs,
+            conflicts,
+        })
+    }
+}
+
+impl State<App> for MergeEdits {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "Merge" => {
+                    let mut theirs_wins = BTreeSet::new();
+                    for (idx, conflict) in self.conflicts.iter().enumerate() {
+                        if self.panel.is_checked(&format!("conflict #{}", idx + 1)) {
+                            theirs_wins.insert(conflict.target);
+                        }
+                    }
+                    let merged = app
+                        .primary
+                        .map
+                        .get_edits()
+                        .merge(&self.theirs, &theirs_wins);
+                    apply_map_edits(ctx, app, merged);
+                    Transition::Pop
+                }
+                x => {
+                    let idx = x["show #".len()..].parse::<usize>().unwrap();
+                    let id = cmd_to_id(&self.conflicts[idx - 1].ours).unwrap();
+                    Transition::Push(Warping::new(
+                        ctx,
+                        app.primary.canonical_point(id.clone()).unwrap(),
+                        Some(10.0),
+                        Some(id),
+                        &mut app.primary,
+                    ))
+                }
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
@@ -11,13 +11,18 @@ use map_gui::ID;
 use map_model::{EditCmd, IntersectionID, LaneID, LaneType, MapEdits};
 use widgetry::{
     lctrl, Btn, Choice, Color, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Menu,
-    Outcome, Panel, State, Text, TextExt, VerticalAlignment, Widget,
+    Outcome, Panel, RewriteColor, State, Text, TextExt, VerticalAlignment, Widget,
 };
 
+pub use self::bike_racks::BikeRackEditor;
+pub use self::bike_treatments::BikeTreatmentEditor;
 pub use self::cluster_traffic_signals::ClusterTrafficSignalEditor;
+pub use self::guideways::GuidewayEditor;
 pub use self::lanes::LaneEditor;
-pub use self::routes::RouteEditor;
+pub use self::parking_zones::ParkingZoneEditor;
+pub use self::routes::{NewBusRouteEditor, RouteEditor};
 pub use self::stop_signs::StopSignEditor;
+pub use self::tolls::TollZoneEditor;
 pub use self::traffic_signals::TrafficSignalEditor;
 pub use self::validate::{check_blackholes, check_sidewalk_connectivity, try_change_lt};
 use crate::app::{App, Transition};
@@ -25,13 +30,22 @@ use crate::common::{tool_panel, CommonState, Warping};
 use crate::debug::DebugMode;
 use crate::sandbox::{GameplayMode, SandboxMode, TimeWarpScreen};
 
+mod bike_racks;
+mod bike_treatments;
 mod bulk;
 mod cluster_traffic_signals;
+mod guideways;
 mod lanes;
+mod merge;
+mod parking_zones;
+mod proposals;
 mod routes;
 mod select;
 mod stop_signs;
+mod timed_speed_limits;
+mod tolls;
 mod traffic_signals;
+mod turn_restrictions;
 mod validate;
 mod zones;
 
@@ -45,8 +59,8 @@ pub struct EditMode {
     // Retained state from the SandboxMode that spawned us
     mode: GameplayMode,
 
-    // edits name, number of commands
-    changelist_key: (String, usize),
+    // edits name, number of commands, number of steps to redo
+    changelist_key: (String, usize, usize),
 
     unzoomed: Drawable,
     zoomed: Drawable,
@@ -66,7 +80,11 @@ impl EditMode {
             orig_edits: edits.clone(),
             orig_dirty,
             mode,
-            changelist_key: (edits.edits_name.clone(), edits.commands.len()),
+            changelist_key: (
+                edits.edits_name.clone(),
+                edits.commands.len(),
+                edits.redo_stack.len(),
+            ),
             unzoomed: layer.unzoomed,
             zoomed: layer.zoomed,
         })
@@ -95,6 +113,7 @@ impl EditMode {
             if app.primary.current_flags.live_map_edits {
                 app.primary.sim = old_sim;
                 app.primary.dirty_from_edits = true;
+                app.primary.snapshots.clear();
                 app.primary
                     .sim
                     .handle_live_edited_traffic_signals(&app.primary.map);
@@ -143,7 +162,11 @@ impl State<App> for EditMode {
     fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
         {
             let edits = app.primary.map.get_edits();
-            let changelist_key = (edits.edits_name.clone(), edits.commands.len());
+            let changelist_key = (
+                edits.edits_name.clone(),
+                edits.commands.len(),
+                edits.redo_stack.len(),
+            );
             if self.changelist_key != changelist_key {
                 self.changelist_key = changelist_key;
                 self.changelist = make_changelist(ctx, app);
@@ -194,7 +217,10 @@ impl State<App> for EditMode {
                             Choice::string("rename current proposal"),
                             Choice::string("open a saved proposal").multikey(lctrl(Key::L)),
                             Choice::string("create a blank proposal"),
+                            Choice::string("merge in another proposal"),
                             Choice::string("save this proposal as..."),
+                            Choice::string("share this proposal"),
+                            Choice::string("browse shared proposals"),
                             Choice::string("delete this proposal and remove all edits")
                                 .fg(Color::hex("#EB3223")),
                         ],
@@ -252,6 +278,9 @@ impl State<App> for EditMode {
                                     Transition::Pop
                                 }
                             }
+                            "merge in another proposal" => {
+                                Transition::Replace(merge::choose_proposal(ctx, app, mode.clone()))
+                            }
                             "save this proposal as..." => Transition::Replace(SaveEdits::new(
                                 ctx,
                                 app,
@@ -260,6 +289,12 @@ impl State<App> for EditMode {
                                 Some(Transition::Pop),
                                 Box::new(|_, _| {}),
                             )),
+                            "share this proposal" => {
+                                Transition::Replace(proposals::ShareProposal::new(ctx, app))
+                            }
+                            "browse shared proposals" => Transition::Replace(
+                                proposals::BrowseProposals::new(ctx, app, mode.clone()),
+                            ),
                             "delete this proposal and remove all edits" => {
                                 abstutil::delete_file(abstutil::path_edits(
                                     app.primary.map.get_name(),
@@ -273,9 +308,37 @@ impl State<App> for EditMode {
                     ));
                 }
                 "load proposal" => {}
-                "undo" => {
+                "toll zones" => {
+                    return Transition::Push(TollZoneEditor::new(ctx, app));
+                }
+                "bike racks" => {
+                    return Transition::Push(BikeRackEditor::new(ctx, app, None));
+                }
+                "bike treatments" => {
+                    return Transition::Push(BikeTreatmentEditor::new(ctx, app, None));
+                }
+                "parking zones" => {
+                    return Transition::Push(ParkingZoneEditor::new(ctx, app));
+                }
+                "new bus routes" => {
+                    return Transition::Push(NewBusRouteEditor::new(ctx, app));
+                }
+                "guideways" => {
+                    return Transition::Push(GuidewayEditor::new(ctx, app));
+                }
+                "undo" | "redo" => {
                     let mut edits = app.primary.map.get_edits().clone();
-                    let maybe_id = cmd_to_id(&edits.commands.pop().unwrap());
+                    if x == "undo" {
+                        edits.undo();
+                    } else {
+                        edits.redo();
+                    }
+                    // Show where the first command of the step happened
+                    let maybe_id = if x == "undo" {
+                        edits.redo_stack.last().and_then(|step| cmd_to_id(&step[0]))
+                    } else {
+                        edits.commands.last().and_then(cmd_to_id)
+                    };
                     apply_map_edits(ctx, app, edits);
                     if let Some(id) = maybe_id {
                         return Transition::Push(Warping::new(
@@ -746,8 +809,6 @@ pub fn maybe_edit_intersection(
 }
 
 fn make_changelist(ctx: &mut EventCtx, app: &App) -> Panel {
-    // TODO Support redo. Bit harder here to reset the redo_stack when the edits
-    // change, because nested other places modify it too.
     let edits = app.primary.map.get_edits();
     let mut col = vec![
         Widget::row(vec![
@@ -757,6 +818,26 @@ fn make_changelist(ctx: &mut EventCtx, app: &App) -> Panel {
                 .container()
                 .padding(10)
                 .bg(Color::hex("#5D9630")),
+            (if edits.commands.is_empty() {
+                Widget::draw_svg_transform(
+                    ctx,
+                    "system/assets/tools/undo.svg",
+                    RewriteColor::ChangeAll(Color::WHITE.alpha(0.5)),
+                )
+            } else {
+                Btn::svg_def("system/assets/tools/undo.svg").build(ctx, "undo", lctrl(Key::Z))
+            })
+            .centered_vert(),
+            (if edits.redo_stack.is_empty() {
+                Widget::draw_svg_transform(
+                    ctx,
+                    "system/assets/tools/redo.svg",
+                    RewriteColor::ChangeAll(Color::WHITE.alpha(0.5)),
+                )
+            } else {
+                Btn::svg_def("system/assets/tools/redo.svg").build(ctx, "redo", lctrl(Key::Y))
+            })
+            .centered_vert(),
         ]),
         ColorLegend::row(
             ctx,
@@ -767,6 +848,36 @@ fn make_changelist(ctx: &mut EventCtx, app: &App) -> Panel {
                 edits.original_intersections.len()
             ),
         ),
+        Btn::text_fg(format!(
+            "{} toll zones",
+            app.primary.map.get_toll_zones().len()
+        ))
+        .build(ctx, "toll zones", None),
+        Btn::text_fg(format!(
+            "{} bike racks",
+            app.primary.map.all_bike_racks().len()
+        ))
+        .build(ctx, "bike racks", None),
+        Btn::text_fg(format!(
+            "{} intersections with bike treatments",
+            app.primary.map.all_bike_treatments().len()
+        ))
+        .build(ctx, "bike treatments", None),
+        Btn::text_fg(format!(
+            "{} parking zones",
+            app.primary.map.get_parking_zones().len()
+        ))
+        .build(ctx, "parking zones", None),
+        Btn::text_fg(format!(
+            "{} new bus routes",
+            app.primary.map.get_new_bus_routes().len()
+        ))
+        .build(ctx, "new bus routes", None),
+        Btn::text_fg(format!(
+            "{} guideways",
+            app.primary.map.get_guideways().len()
+        ))
+        .build(ctx, "guideways", None),
     ];
 
     if edits.commands.len() > 5 {
@@ -780,16 +891,7 @@ fn make_changelist(ctx: &mut EventCtx, app: &App) -> Panel {
         }
         let btn = Btn::plaintext_custom(format!("change #{}", idx + 1), txt).build_def(ctx, None);
         if idx == edits.commands.len() - 1 {
-            col.push(
-                Widget::row(vec![
-                    btn,
-                    Btn::plaintext("X")
-                        .build(ctx, "undo", lctrl(Key::Z))
-                        .align_right(),
-                ])
-                .padding(16)
-                .outline(2.0, Color::WHITE),
-            );
+            col.push(btn.padding(16).outline(2.0, Color::WHITE));
         } else {
             col.push(btn);
         }
@@ -804,8 +906,16 @@ fn make_changelist(ctx: &mut EventCtx, app: &App) -> Panel {
 fn cmd_to_id(cmd: &EditCmd) -> Option<ID> {
     match cmd {
         EditCmd::ChangeRoad { r, .. } => Some(ID::Road(*r)),
-        EditCmd::ChangeIntersection { i, .. } => Some(ID::Intersection(*i)),
-        EditCmd::ChangeRouteSchedule { .. } => None,
+        EditCmd::ChangeIntersection { i, .. } | EditCmd::ChangeBikeTreatments { i, .. } => {
+            Some(ID::Intersection(*i))
+        }
+        EditCmd::ChangeBikeRacks { b, .. } => Some(ID::Building(*b)),
+        EditCmd::ChangeRouteSchedule { .. }
+        | EditCmd::ChangeRouteStops { .. }
+        | EditCmd::ChangeNewBusRoutes { .. }
+        | EditCmd::ChangeTollZones { .. }
+        | EditCmd::ChangeParkingZones { .. }
+        | EditCmd::ChangeGuideways { .. } => None,
     }
 }
 
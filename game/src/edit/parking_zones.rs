@@ -0,0 +1,244 @@
+use geom::{Circle, Distance, Duration, LonLat, Pt2D, Time};
+use map_gui::tools::PopupMsg;
+use map_model::{EditCmd, ParkingRestriction, ParkingZone};
+use widgetry::{
+    Btn, Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Panel, State, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::edit::apply_map_edits;
+
+const POINT_RADIUS: Distance = Distance::const_meters(10.0);
+
+/// Lists the parking zones in the current proposal.
+pub struct ParkingZoneEditor {
+    panel: Panel,
+    draw: Drawable,
+}
+
+impl ParkingZoneEditor {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut batch = GeomBatch::new();
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Parking zones").small_heading().draw(ctx),
+                Btn::close(ctx),
+            ]),
+            "Restrict who can park on the street in a zone, and for how long.".draw_text(ctx),
+        ];
+        for (idx, zone) in map.get_parking_zones().iter().enumerate() {
+            if let Some(poly) = zone.polygon(map) {
+                batch.push(Color::BLUE.alpha(0.4), poly.clone());
+                if let Ok(outline) = poly.to_outline(Distance::meters(3.0)) {
+                    batch.push(Color::BLUE, outline);
+                }
+                batch.append(
+                    Text::from(Line(&zone.name))
+                        .render_autocropped(ctx)
+                        .scale(2.0)
+                        .centered_on(poly.center()),
+                );
+            }
+            col.push(Widget::row(vec![
+                Text::from_multiline(vec![
+                    Line(&zone.name),
+                    Line(zone.describe_restriction()).secondary(),
+                ])
+                .draw(ctx),
+                Btn::svg_def("system/assets/tools/delete.svg")
+                    .build(ctx, format!("delete parking zone {}", idx + 1), None)
+                    .align_right(),
+            ]));
+        }
+        if map.get_parking_zones().is_empty() {
+            col.push("No parking zones yet".draw_text(ctx));
+        }
+        col.push(Btn::text_fg("draw a new parking zone").build_def(ctx, Key::N));
+
+        Box::new(ParkingZoneEditor {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+                .build(ctx),
+            draw: ctx.upload(batch),
+        })
+    }
+}
+
+impl State<App> for ParkingZoneEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "draw a new parking zone" => Transition::Push(DrawParkingZone::new(ctx)),
+                x => {
+                    let idx = x["delete parking zone ".len()..].parse::<usize>().unwrap() - 1;
+                    let old = app.primary.map.get_parking_zones().clone();
+                    let mut new = old.clone();
+                    new.remove(idx);
+                    change_parking_zones(ctx, app, old, new);
+                    Transition::Replace(ParkingZoneEditor::new(ctx, app))
+                }
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn change_parking_zones(
+    ctx: &mut EventCtx,
+    app: &mut App,
+    old: Vec<ParkingZone>,
+    new: Vec<ParkingZone>,
+) {
+    let mut edits = app.primary.map.get_edits().clone();
+    edits
+        .commands
+        .push(EditCmd::ChangeParkingZones { old, new });
+    apply_map_edits(ctx, app, edits);
+}
+
+struct DrawParkingZone {
+    panel: Panel,
+    points: Vec<LonLat>,
+}
+
+impl DrawParkingZone {
+    fn new(ctx: &mut EventCtx) -> Box<dyn State<App>> {
+        let paid = ParkingRestriction::Paid {
+            start: Time::START_OF_DAY + Duration::hours(8),
+            end: Time::START_OF_DAY + Duration::hours(18),
+            dollars_per_hour: 2.0,
+        };
+        Box::new(DrawParkingZone {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Draw a parking zone").small_heading().draw(ctx),
+                    Btn::close(ctx),
+                ]),
+                "Click to add points to the boundary".draw_text(ctx),
+                Widget::row(vec![
+                    "Name:".draw_text(ctx).centered_vert(),
+                    Widget::text_entry(ctx, String::new(), true).named("name"),
+                ]),
+                Widget::row(vec![
+                    "Restriction:".draw_text(ctx).centered_vert(),
+                    Widget::dropdown(
+                        ctx,
+                        "restriction",
+                        ParkingRestriction::ResidentPermit,
+                        vec![
+                            Choice::new("residents only", ParkingRestriction::ResidentPermit),
+                            Choice::new(
+                                "2 hour limit",
+                                ParkingRestriction::TimeLimit(Duration::hours(2)),
+                            ),
+                            Choice::new("$2/hour from 8am to 6pm", paid),
+                        ],
+                    ),
+                ]),
+                Widget::row(vec![
+                    Btn::text_fg("undo last point").build_def(ctx, Key::Backspace),
+                    Btn::text_bg2("save").build_def(ctx, Key::Enter),
+                ]),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            points: Vec::new(),
+        })
+    }
+}
+
+impl State<App> for DrawParkingZone {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "undo last point" => {
+                    self.points.pop();
+                }
+                "save" => {
+                    let name = self.panel.text_box("name");
+                    if name.is_empty() {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["Name the parking zone first"],
+                        ));
+                    }
+                    let zone = ParkingZone {
+                        name,
+                        boundary: self.points.clone(),
+                        restriction: self.panel.dropdown_value("restriction"),
+                    };
+                    if zone.polygon(&app.primary.map).is_none() {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["The boundary needs at least 3 points and can't cross itself"],
+                        ));
+                    }
+                    let old = app.primary.map.get_parking_zones().clone();
+                    let mut new = old.clone();
+                    new.push(zone);
+                    change_parking_zones(ctx, app, old, new);
+                    return Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::Replace(ParkingZoneEditor::new(ctx, app)),
+                    ]);
+                }
+                _ => unreachable!(),
+            },
+            _ => {}
+        }
+
+        if let Some(pt) = ctx.canvas.get_cursor_in_map_space() {
+            if app.per_obj.left_click(ctx, "add a new point") {
+                self.points
+                    .push(pt.to_gps(app.primary.map.get_gps_bounds()));
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let pts: Vec<Pt2D> = app.primary.map.get_gps_bounds().convert(&self.points);
+        if pts.len() == 2 {
+            g.draw_polygon(
+                Color::RED,
+                geom::Line::must_new(pts[0], pts[1]).make_polygons(POINT_RADIUS / 2.0),
+            );
+        }
+        let zone = ParkingZone {
+            name: String::new(),
+            boundary: self.points.clone(),
+            restriction: ParkingRestriction::ResidentPermit,
+        };
+        if let Some(poly) = zone.polygon(&app.primary.map) {
+            g.draw_polygon(Color::BLUE.alpha(0.6), poly);
+        }
+        for pt in pts {
+            g.draw_polygon(
+                Color::RED,
+                Circle::new(pt, POINT_RADIUS / g.canvas.cam_zoom).to_polygon(),
+            );
+        }
+
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
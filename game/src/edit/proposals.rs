@@ -0,0 +1,304 @@
+//! Shared proposals bundle a set of map edits with an author, a description, and a thumbnail of
+//! the changed roads into one gzipped file, so people can exchange street redesigns. They're
+//! stored in data/player/shared_proposals/<city>/<map>/.
+
+use std::error::Error;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use geom::Pt2D;
+use map_gui::tools::{grey_out_map, PopupMsg};
+use map_model::{Map, PermanentMapEdits};
+use widgetry::{
+    Btn, EventCtx, GeomBatch, GfxCtx, Key, Line, Outcome, Panel, State, Text, TextExt, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::edit::apply_map_edits;
+use crate::edit::merge::merge_proposal;
+use crate::sandbox::GameplayMode;
+
+const THUMBNAIL_WIDTH: f64 = 300.0;
+
+#[derive(Serialize, Deserialize)]
+pub struct Proposal {
+    pub edits: PermanentMapEdits,
+    pub author: String,
+    pub description: Vec<String>,
+    /// An SVG of the map, highlighting the edited roads and intersections
+    pub thumbnail: String,
+}
+
+impl Proposal {
+    /// Bundles up the map's current edits.
+    pub fn new(map: &Map, author: String, description: Vec<String>) -> Proposal {
+        Proposal {
+            edits: map.get_edits().to_permanent(map),
+            author,
+            description,
+            thumbnail: make_thumbnail(map),
+        }
+    }
+
+    /// Returns the path written.
+    pub fn save(&self, map: &Map) -> Result<String, Box<dyn Error>> {
+        let path = abstutil::path_shared_proposal(map.get_name(), &self.edits.edits_name);
+        std::fs::create_dir_all(abstutil::path_all_shared_proposals(map.get_name()))?;
+        let mut encoder = GzEncoder::new(BufWriter::new(File::create(&path)?), Compression::best());
+        serde_json::to_writer(&mut encoder, self)?;
+        // Dropping the encoder and writer would silently ignore errors writing the end of the file
+        encoder.finish()?.into_inner()?;
+        Ok(path)
+    }
+
+    pub fn load(path: &str) -> Result<Proposal, Box<dyn Error>> {
+        let decoder = GzDecoder::new(BufReader::new(File::open(path)?));
+        Ok(serde_json::from_reader(decoder)?)
+    }
+
+    /// All of the shared proposals for this map, along with their path.
+    fn load_all(map: &Map) -> Vec<(String, Proposal)> {
+        let mut results = Vec::new();
+        for path in abstutil::list_dir(abstutil::path_all_shared_proposals(map.get_name())) {
+            match Proposal::load(&path) {
+                Ok(proposal) => {
+                    results.push((path, proposal));
+                }
+                Err(err) => {
+                    warn!("Skipping shared proposal {}: {}", path, err);
+                }
+            }
+        }
+        results
+    }
+}
+
+fn make_thumbnail(map: &Map) -> String {
+    let bounds = map.get_bounds();
+    let scale = THUMBNAIL_WIDTH / bounds.width();
+    let height = bounds.height() * scale;
+    let to_svg = |pt: &Pt2D| format!("{:.1},{:.1}", pt.x() * scale, pt.y() * scale);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg width="{}" height="{}" xmlns="http://www.w3.org/2000/svg">"#,
+        THUMBNAIL_WIDTH, height
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r##"<rect width="{}" height="{}" fill="#2B2B2B"/>"##,
+        THUMBNAIL_WIDTH, height
+    )
+    .unwrap();
+
+    let edits = map.get_edits();
+    for r in map.all_roads() {
+        let (color, width) = if edits.changed_roads.contains(&r.id) {
+            ("#F4DA22", 3.0)
+        } else {
+            ("#7F7F7F", 0.5)
+        };
+        let pts: Vec<String> = r.center_pts.points().iter().map(to_svg).collect();
+        writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+            pts.join(" "),
+            color,
+            width
+        )
+        .unwrap();
+    }
+    for i in edits.original_intersections.keys() {
+        let pt = map.get_i(*i).polygon.center();
+        writeln!(
+            svg,
+            r##"<circle cx="{:.1}" cy="{:.1}" r="3" fill="#F4DA22"/>"##,
+            pt.x() * scale,
+            pt.y() * scale
+        )
+        .unwrap();
+    }
+    writeln!(svg, "</svg>").unwrap();
+    svg
+}
+
+pub struct ShareProposal {
+    panel: Panel,
+}
+
+impl ShareProposal {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let edits = app.primary.map.get_edits();
+        Box::new(ShareProposal {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line(format!("Share \"{}\"", edits.edits_name))
+                        .small_heading()
+                        .draw(ctx),
+                    Btn::close(ctx),
+                ]),
+                Widget::row(vec![
+                    "Author:".draw_text(ctx).centered_vert(),
+                    Widget::text_entry(ctx, String::new(), true).named("author"),
+                ]),
+                Widget::row(vec![
+                    "Description:".draw_text(ctx).centered_vert(),
+                    Widget::text_entry(
+                        ctx,
+                        edits
+                            .proposal_description
+                            .get(0)
+                            .cloned()
+                            .unwrap_or_default(),
+                        false,
+                    )
+                    .named("description"),
+                ]),
+                Btn::text_bg2("Share").build_def(ctx, Key::Enter),
+            ]))
+            .build(ctx),
+        })
+    }
+}
+
+impl State<App> for ShareProposal {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "Share" => {
+                    let proposal = Proposal::new(
+                        &app.primary.map,
+                        self.panel.text_box("author"),
+                        vec![self.panel.text_box("description")],
+                    );
+                    let msg = match proposal.save(&app.primary.map) {
+                        Ok(path) => format!("Wrote {}. Send this file to anybody!", path),
+                        Err(err) => format!("Couldn't share this proposal: {}", err),
+                    };
+                    Transition::Replace(PopupMsg::new(ctx, "Share proposal", vec![msg]))
+                }
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
+
+pub struct BrowseProposals {
+    panel: Panel,
+    mode: GameplayMode,
+    proposals: Vec<(String, Proposal)>,
+}
+
+impl BrowseProposals {
+    pub fn new(ctx: &mut EventCtx, app: &App, mode: GameplayMode) -> Box<dyn State<App>> {
+        let proposals = Proposal::load_all(&app.primary.map);
+
+        let mut col = vec![Widget::row(vec![
+            Line("Shared proposals").small_heading().draw(ctx),
+            Btn::close(ctx),
+        ])];
+        if proposals.is_empty() {
+            col.push(
+                format!(
+                    "Nobody has shared any proposals for this map yet. Put files in {}",
+                    abstutil::path_all_shared_proposals(app.primary.map.get_name())
+                )
+                .draw_text(ctx),
+            );
+        }
+        for (idx, (_, proposal)) in proposals.iter().enumerate() {
+            let mut txt = Text::from(Line(&proposal.edits.edits_name).small_heading());
+            txt.add(Line(format!("by {}", proposal.author)).secondary());
+            for l in &proposal.description {
+                txt.add(Line(l));
+            }
+            col.push(Widget::row(vec![
+                Widget::draw_batch(
+                    ctx,
+                    GeomBatch::from_svg_contents(proposal.thumbnail.as_bytes().to_vec()),
+                ),
+                Widget::col(vec![
+                    txt.wrap_to_pct(ctx, 30).draw(ctx),
+                    Widget::row(vec![
+                        Btn::text_bg2("Load").build(ctx, format!("load {}", idx), None),
+                        Btn::text_bg2("Merge into current proposal").build(
+                            ctx,
+                            format!("merge {}", idx),
+                            None,
+                        ),
+                    ]),
+                ]),
+            ]));
+        }
+
+        Box::new(BrowseProposals {
+            panel: Panel::new(Widget::col(col))
+                .exact_size_percent(60, 70)
+                .build(ctx),
+            mode,
+            proposals,
+        })
+    }
+}
+
+impl State<App> for BrowseProposals {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => {
+                if x == "close" {
+                    return Transition::Pop;
+                }
+                let merge = x.starts_with("merge ");
+                let idx = x.split(' ').nth(1).unwrap().parse::<usize>().unwrap();
+                let (path, proposal) = &self.proposals[idx];
+                let edits = match proposal.edits.clone().to_edits(&app.primary.map) {
+                    Ok(edits) => edits,
+                    Err(err) => {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec![format!("Can't load {}", path), err],
+                        ));
+                    }
+                };
+                if !self.mode.allows(&edits) {
+                    return Transition::Push(PopupMsg::new(
+                        ctx,
+                        "Error",
+                        vec![
+                            "The current gameplay mode restricts edits. This proposal has a \
+                             banned command.",
+                        ],
+                    ));
+                }
+                if merge {
+                    let name = proposal.edits.edits_name.clone();
+                    return Transition::Replace(merge_proposal(ctx, app, &name, edits));
+                }
+                apply_map_edits(ctx, app, edits);
+                Transition::Pop
+            }
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        grey_out_map(g, app);
+        self.panel.draw(g);
+    }
+}
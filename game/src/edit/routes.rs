@@ -1,14 +1,18 @@
-use geom::{Duration, Time};
-use map_model::{BusRouteID, EditCmd};
+use geom::{Circle, Distance, Duration, PolyLine, Pt2D, Time};
+use map_gui::tools::PopupMsg;
+use map_model::{BusRouteID, BusStopID, EditCmd, NewBusRoute};
 use widgetry::{
-    Btn, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, Spinner, State, TextExt,
-    VerticalAlignment, Widget,
+    Btn, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome,
+    Panel, Spinner, State, Text, TextExt, VerticalAlignment, Widget,
 };
 
 use crate::app::App;
 use crate::app::Transition;
+use crate::common::CommonState;
 use crate::edit::apply_map_edits;
 
+const STOP_RADIUS: Distance = Distance::const_meters(5.0);
+
 pub struct RouteEditor {
     panel: Panel,
     route: BusRouteID,
@@ -32,6 +36,7 @@ impl RouteEditor {
                     Spinner::new(ctx, (1, 120), 60).named("freq_mins"),
                 ]),
                 Btn::text_bg2("Apply").build_def(ctx, Key::Enter),
+                Btn::text_fg("change stops").build_def(ctx, None),
             ]))
             .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
             .build(ctx),
@@ -51,23 +56,19 @@ impl State<App> for RouteEditor {
                 }
                 "Apply" => {
                     let freq = Duration::minutes(self.panel.spinner("freq_mins") as usize);
-                    let mut now = Time::START_OF_DAY;
-                    let mut hourly_times = Vec::new();
-                    while now <= Time::START_OF_DAY + Duration::hours(24) {
-                        hourly_times.push(now);
-                        now += freq;
-                    }
-
                     let mut edits = app.primary.map.get_edits().clone();
                     edits.commands.push(EditCmd::ChangeRouteSchedule {
                         id: self.route,
                         old: app.primary.map.get_br(self.route).spawn_times.clone(),
-                        new: hourly_times,
+                        new: spawn_times(freq),
                     });
                     apply_map_edits(ctx, app, edits);
 
                     return Transition::Pop;
                 }
+                "change stops" => {
+                    return Transition::Replace(PickStops::new(ctx, app, Some(self.route)));
+                }
                 _ => unreachable!(),
             },
             _ => {}
@@ -80,3 +81,302 @@ impl State<App> for RouteEditor {
         self.panel.draw(g);
     }
 }
+
+/// Lists the bus routes created by the current proposal.
+pub struct NewBusRouteEditor {
+    panel: Panel,
+}
+
+impl NewBusRouteEditor {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let routes = app.primary.map.get_new_bus_routes();
+        let mut col = vec![
+            Widget::row(vec![
+                Line("New bus routes").small_heading().draw(ctx),
+                Btn::close(ctx),
+            ]),
+            "Routes can only use bus stops that already exist.".draw_text(ctx),
+        ];
+        for (idx, r) in routes.iter().enumerate() {
+            col.push(Widget::row(vec![
+                Text::from_multiline(vec![
+                    Line(&r.name),
+                    Line(format!(
+                        "{} stops, {} buses a day",
+                        r.stops.len(),
+                        r.spawn_times.len()
+                    ))
+                    .secondary(),
+                ])
+                .draw(ctx),
+                Btn::svg_def("system/assets/tools/delete.svg")
+                    .build(ctx, format!("delete route {}", idx + 1), None)
+                    .align_right(),
+            ]));
+        }
+        if routes.is_empty() {
+            col.push("No new routes yet".draw_text(ctx));
+        }
+        col.push(Btn::text_fg("create a new bus route").build_def(ctx, Key::N));
+
+        Box::new(NewBusRouteEditor {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+                .build(ctx),
+        })
+    }
+}
+
+impl State<App> for NewBusRouteEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "create a new bus route" => Transition::Push(PickStops::new(ctx, app, None)),
+                x => {
+                    let idx = x["delete route ".len()..].parse::<usize>().unwrap() - 1;
+                    let old = app.primary.map.get_new_bus_routes();
+                    let mut new = old.clone();
+                    new.remove(idx);
+                    change_new_routes(ctx, app, old, new);
+                    Transition::Replace(NewBusRouteEditor::new(ctx, app))
+                }
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn change_new_routes(
+    ctx: &mut EventCtx,
+    app: &mut App,
+    old: Vec<NewBusRoute>,
+    new: Vec<NewBusRoute>,
+) {
+    let mut edits = app.primary.map.get_edits().clone();
+    edits
+        .commands
+        .push(EditCmd::ChangeNewBusRoutes { old, new });
+    apply_map_edits(ctx, app, edits);
+}
+
+fn spawn_times(freq: Duration) -> Vec<Time> {
+    let mut now = Time::START_OF_DAY;
+    let mut times = Vec::new();
+    while now <= Time::START_OF_DAY + Duration::hours(24) {
+        times.push(now);
+        now += freq;
+    }
+    times
+}
+
+/// Click bus stops in order to change an existing route, or to create a new one.
+struct PickStops {
+    panel: Panel,
+    route: Option<BusRouteID>,
+    stops: Vec<BusStopID>,
+    hovering: Option<BusStopID>,
+    draw_all_stops: Drawable,
+    draw_picked: Drawable,
+}
+
+impl PickStops {
+    fn new(ctx: &mut EventCtx, app: &App, route: Option<BusRouteID>) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut col = vec![Widget::row(vec![
+            Line(if let Some(id) = route {
+                format!("Change stops of {}", map.get_br(id).short_name)
+            } else {
+                "Create a bus route".to_string()
+            })
+            .small_heading()
+            .draw(ctx),
+            Btn::close(ctx),
+        ])];
+        col.push("Click bus stops in the order buses should visit them".draw_text(ctx));
+        if route.is_none() {
+            col.push(Widget::row(vec![
+                "Name:".draw_text(ctx).centered_vert(),
+                Widget::text_entry(ctx, String::new(), true).named("name"),
+            ]));
+            col.push(Widget::row(vec![
+                "Frequency in minutes:".draw_text(ctx).centered_vert(),
+                Spinner::new(ctx, (1, 120), 15).named("freq_mins"),
+            ]));
+        }
+        col.push(Widget::nothing().named("num stops"));
+        col.push(Widget::row(vec![
+            Btn::text_fg("undo last stop").build_def(ctx, Key::Backspace),
+            Btn::text_fg("clear").build_def(ctx, None),
+            Btn::text_bg2("save").build_def(ctx, Key::Enter),
+        ]));
+
+        let mut batch = GeomBatch::new();
+        for bs in map.all_bus_stops().values() {
+            if !bs.is_train_stop {
+                batch.push(
+                    Color::BLUE.alpha(0.5),
+                    Circle::new(bs.sidewalk_pos.pt(map), STOP_RADIUS).to_polygon(),
+                );
+            }
+        }
+
+        let mut state = PickStops {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+                .build(ctx),
+            route,
+            stops: route
+                .map(|id| map.get_br(id).stops.clone())
+                .unwrap_or_else(Vec::new),
+            hovering: None,
+            draw_all_stops: ctx.upload(batch),
+            draw_picked: Drawable::empty(ctx),
+        };
+        state.stops_changed(ctx, app);
+        Box::new(state)
+    }
+
+    fn stops_changed(&mut self, ctx: &mut EventCtx, app: &App) {
+        let map = &app.primary.map;
+        let pts: Vec<Pt2D> = self
+            .stops
+            .iter()
+            .map(|bs| map.get_bs(*bs).sidewalk_pos.pt(map))
+            .collect();
+        let mut batch = GeomBatch::new();
+        if let Ok(pl) = PolyLine::new(pts.clone()) {
+            batch.push(
+                Color::RED.alpha(0.5),
+                pl.make_polygons(Distance::meters(2.0)),
+            );
+        }
+        for (idx, pt) in pts.into_iter().enumerate() {
+            batch.push(Color::RED, Circle::new(pt, STOP_RADIUS).to_polygon());
+            batch.append(
+                Text::from(Line(format!("{}", idx + 1)))
+                    .render_autocropped(ctx)
+                    .scale(0.5)
+                    .centered_on(pt),
+            );
+        }
+        self.draw_picked = ctx.upload(batch);
+
+        let txt = format!("{} stops", self.stops.len()).draw_text(ctx);
+        self.panel.replace(ctx, "num stops", txt);
+    }
+
+    fn save(&self, ctx: &mut EventCtx, app: &mut App) -> Result<(), String> {
+        app.primary
+            .map
+            .check_route_stops(self.route, self.stops.clone())?;
+        if let Some(id) = self.route {
+            let mut edits = app.primary.map.get_edits().clone();
+            edits.commands.push(EditCmd::ChangeRouteStops {
+                id,
+                old: app.primary.map.get_br(id).stops.clone(),
+                new: self.stops.clone(),
+            });
+            apply_map_edits(ctx, app, edits);
+        } else {
+            let name = self.panel.text_box("name");
+            if name.is_empty() {
+                return Err("Name the route first".to_string());
+            }
+            let old = app.primary.map.get_new_bus_routes();
+            let mut new = old.clone();
+            new.push(NewBusRoute {
+                name,
+                stops: self.stops.clone(),
+                spawn_times: spawn_times(Duration::minutes(
+                    self.panel.spinner("freq_mins") as usize
+                )),
+            });
+            change_new_routes(ctx, app, old, new);
+        }
+        Ok(())
+    }
+}
+
+impl State<App> for PickStops {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "undo last stop" => {
+                    self.stops.pop();
+                    self.stops_changed(ctx, app);
+                }
+                "clear" => {
+                    self.stops.clear();
+                    self.stops_changed(ctx, app);
+                }
+                "save" => {
+                    return match self.save(ctx, app) {
+                        Ok(()) => {
+                            if self.route.is_some() {
+                                Transition::Pop
+                            } else {
+                                Transition::Multi(vec![
+                                    Transition::Pop,
+                                    Transition::Replace(NewBusRouteEditor::new(ctx, app)),
+                                ])
+                            }
+                        }
+                        Err(err) => Transition::Push(PopupMsg::new(ctx, "Error", vec![err])),
+                    };
+                }
+                _ => unreachable!(),
+            },
+            _ => {}
+        }
+
+        if ctx.redo_mouseover() {
+            self.hovering = None;
+            if let Some(pt) = ctx.canvas.get_cursor_in_map_space() {
+                let map = &app.primary.map;
+                self.hovering = map
+                    .all_bus_stops()
+                    .values()
+                    .filter(|bs| !bs.is_train_stop)
+                    .map(|bs| (bs.id, bs.sidewalk_pos.pt(map).dist_to(pt)))
+                    .filter(|(_, dist)| *dist <= STOP_RADIUS * 2.0)
+                    .min_by_key(|(_, dist)| *dist)
+                    .map(|(id, _)| id);
+            }
+        }
+        if let Some(bs) = self.hovering {
+            if self.stops.last() != Some(&bs) && app.per_obj.left_click(ctx, "add this stop") {
+                self.stops.push(bs);
+                self.stops_changed(ctx, app);
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw_all_stops);
+        g.redraw(&self.draw_picked);
+        if let Some(bs) = self.hovering {
+            let map = &app.primary.map;
+            g.draw_polygon(
+                Color::YELLOW,
+                Circle::new(map.get_bs(bs).sidewalk_pos.pt(map), STOP_RADIUS).to_polygon(),
+            );
+            g.draw_mouse_tooltip(Text::from(Line(&map.get_bs(bs).name)));
+        }
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
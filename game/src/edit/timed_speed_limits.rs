@@ -0,0 +1,165 @@
+use geom::{Duration, Speed, Time};
+use map_gui::render::Renderable;
+use map_model::{RoadID, TimedSpeedLimit};
+use widgetry::{
+    Btn, Choice, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, State, TextExt,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::edit::{apply_map_edits, speed_limit_choices};
+
+/// Change a road's speed limit during part of the day, like a school zone or a lower limit at
+/// night.
+pub struct TimedSpeedLimitEditor {
+    panel: Panel,
+    r: RoadID,
+    limits: Vec<TimedSpeedLimit>,
+}
+
+impl TimedSpeedLimitEditor {
+    pub fn new(ctx: &mut EventCtx, app: &App, r: RoadID) -> Box<dyn State<App>> {
+        let limits = app.primary.map.get_r(r).timed_speed_limits.clone();
+        Box::new(TimedSpeedLimitEditor {
+            panel: make_panel(ctx, app, r, &limits),
+            r,
+            limits,
+        })
+    }
+}
+
+impl State<App> for TimedSpeedLimitEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "Apply" => {
+                    let map = &app.primary.map;
+                    if self.limits != map.get_r(self.r).timed_speed_limits {
+                        let mut edits = map.get_edits().clone();
+                        edits.commands.push(map.edit_road_cmd(self.r, |new| {
+                            new.timed_speed_limits = self.limits.clone();
+                        }));
+                        apply_map_edits(ctx, app, edits);
+                    }
+                    Transition::Pop
+                }
+                "Cancel" => Transition::Pop,
+                "add school zone" => {
+                    let hours = |h| Time::START_OF_DAY + Duration::hours(h);
+                    for (start, end) in vec![(7, 9), (14, 16)] {
+                        self.limits.push(TimedSpeedLimit {
+                            speed_limit: Speed::miles_per_hour(20.0),
+                            window: (hours(start), hours(end)),
+                            school_zone: true,
+                        });
+                    }
+                    self.panel = make_panel(ctx, app, self.r, &self.limits);
+                    Transition::Keep
+                }
+                "add limit" => {
+                    self.limits.push(TimedSpeedLimit {
+                        speed_limit: self.panel.dropdown_value("speed limit"),
+                        window: self.panel.dropdown_value("window"),
+                        school_zone: false,
+                    });
+                    self.panel = make_panel(ctx, app, self.r, &self.limits);
+                    Transition::Keep
+                }
+                x => {
+                    let idx = x["delete limit ".len()..].parse::<usize>().unwrap() - 1;
+                    self.limits.remove(idx);
+                    self.panel = make_panel(ctx, app, self.r, &self.limits);
+                    Transition::Keep
+                }
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.draw_polygon(
+            app.cs.perma_selected_object,
+            app.primary
+                .draw_map
+                .get_r(self.r)
+                .get_outline(&app.primary.map),
+        );
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App, r: RoadID, limits: &[TimedSpeedLimit]) -> Panel {
+    let road = app.primary.map.get_r(r);
+    let mut col = vec![
+        Line(format!(
+            "Speed limits on {} by time of day",
+            road.get_name(app.opts.language.as_ref())
+        ))
+        .small_heading()
+        .draw(ctx),
+        format!(
+            "The usual limit of {} applies outside of these hours. If hours overlap, the first \
+             limit listed wins.",
+            road.speed_limit.to_string(&app.opts.units)
+        )
+        .draw_text(ctx),
+    ];
+    for (idx, limit) in limits.iter().enumerate() {
+        col.push(Widget::row(vec![
+            limit
+                .describe(&app.opts.units)
+                .draw_text(ctx)
+                .centered_vert(),
+            Btn::svg_def("system/assets/tools/delete.svg")
+                .build(ctx, format!("delete limit {}", idx + 1), None)
+                .align_right(),
+        ]));
+    }
+    if limits.is_empty() {
+        col.push("No limits by time of day yet".draw_text(ctx));
+    }
+
+    let hours = |h| Time::START_OF_DAY + Duration::hours(h);
+    col.push(Widget::row(vec![
+        Widget::dropdown(
+            ctx,
+            "speed limit",
+            Speed::miles_per_hour(25.0),
+            speed_limit_choices(app),
+        ),
+        Widget::dropdown(
+            ctx,
+            "window",
+            (hours(22), hours(6)),
+            vec![(22, 6), (0, 5), (7, 9), (16, 19), (7, 19)]
+                .into_iter()
+                .map(|(start, end)| {
+                    Choice::new(
+                        format!(
+                            "from {} to {}",
+                            hours(start).ampm_tostring(),
+                            hours(end).ampm_tostring()
+                        ),
+                        (hours(start), hours(end)),
+                    )
+                })
+                .collect(),
+        ),
+        Btn::text_fg("add limit").build_def(ctx, None),
+    ]));
+    col.push(Btn::text_fg("add school zone").build_def(ctx, Key::S));
+    col.push(
+        Widget::custom_row(vec![
+            Btn::text_fg("Apply").build_def(ctx, Key::Enter),
+            Btn::text_fg("Cancel").build_def(ctx, Key::Escape),
+        ])
+        .evenly_spaced(),
+    );
+
+    Panel::new(Widget::col(col))
+        .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+        .build(ctx)
+}
@@ -0,0 +1,249 @@
+use geom::{Circle, Distance, Duration, LonLat, Pt2D, Time};
+use map_gui::tools::PopupMsg;
+use map_model::{EditCmd, TollZone};
+use widgetry::{
+    Btn, Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Panel, Spinner, State, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::edit::apply_map_edits;
+
+const POINT_RADIUS: Distance = Distance::const_meters(10.0);
+
+/// Lists the toll zones in the current proposal.
+pub struct TollZoneEditor {
+    panel: Panel,
+    draw: Drawable,
+}
+
+impl TollZoneEditor {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut batch = GeomBatch::new();
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Toll zones").small_heading().draw(ctx),
+                Btn::close(ctx),
+            ]),
+            "Cars and trucks pay each time they drive into a zone.".draw_text(ctx),
+        ];
+        for (idx, zone) in map.get_toll_zones().iter().enumerate() {
+            if let Some(poly) = zone.polygon(map) {
+                batch.push(Color::ORANGE.alpha(0.4), poly.clone());
+                if let Ok(outline) = poly.to_outline(Distance::meters(3.0)) {
+                    batch.push(Color::ORANGE, outline);
+                }
+                batch.append(
+                    Text::from(Line(&zone.name))
+                        .render_autocropped(ctx)
+                        .scale(2.0)
+                        .centered_on(poly.center()),
+                );
+            }
+            col.push(Widget::row(vec![
+                Text::from_multiline(vec![
+                    Line(&zone.name),
+                    Line(zone.describe_schedule()).secondary(),
+                ])
+                .draw(ctx),
+                Btn::svg_def("system/assets/tools/delete.svg")
+                    .build(ctx, format!("delete toll zone {}", idx + 1), None)
+                    .align_right(),
+            ]));
+        }
+        if map.get_toll_zones().is_empty() {
+            col.push("No toll zones yet".draw_text(ctx));
+        }
+        col.push(Btn::text_fg("draw a new toll zone").build_def(ctx, Key::N));
+
+        Box::new(TollZoneEditor {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+                .build(ctx),
+            draw: ctx.upload(batch),
+        })
+    }
+}
+
+impl State<App> for TollZoneEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "draw a new toll zone" => Transition::Push(DrawTollZone::new(ctx)),
+                x => {
+                    let idx = x["delete toll zone ".len()..].parse::<usize>().unwrap() - 1;
+                    let old = app.primary.map.get_toll_zones().clone();
+                    let mut new = old.clone();
+                    new.remove(idx);
+                    change_toll_zones(ctx, app, old, new);
+                    Transition::Replace(TollZoneEditor::new(ctx, app))
+                }
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn change_toll_zones(ctx: &mut EventCtx, app: &mut App, old: Vec<TollZone>, new: Vec<TollZone>) {
+    let mut edits = app.primary.map.get_edits().clone();
+    edits.commands.push(EditCmd::ChangeTollZones { old, new });
+    apply_map_edits(ctx, app, edits);
+}
+
+/// When the toll applies, as pairs of start and end hours
+type Window = Vec<(usize, usize)>;
+
+struct DrawTollZone {
+    panel: Panel,
+    points: Vec<LonLat>,
+}
+
+impl DrawTollZone {
+    fn new(ctx: &mut EventCtx) -> Box<dyn State<App>> {
+        Box::new(DrawTollZone {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Draw a toll zone").small_heading().draw(ctx),
+                    Btn::close(ctx),
+                ]),
+                "Click to add points to the boundary".draw_text(ctx),
+                Widget::row(vec![
+                    "Name:".draw_text(ctx).centered_vert(),
+                    Widget::text_entry(ctx, String::new(), true).named("name"),
+                ]),
+                Widget::row(vec![
+                    "Price in dollars:".draw_text(ctx).centered_vert(),
+                    Spinner::new(ctx, (1, 50), 5).named("price"),
+                ]),
+                Widget::row(vec![
+                    "Charged:".draw_text(ctx).centered_vert(),
+                    Widget::dropdown(
+                        ctx,
+                        "window",
+                        vec![(7, 9), (16, 19)],
+                        vec![
+                            Choice::new("all day", vec![(0, 24)]),
+                            Choice::new("7-9am and 4-7pm", vec![(7, 9), (16, 19)]),
+                            Choice::new("7am-7pm", vec![(7, 19)]),
+                        ],
+                    ),
+                ]),
+                Widget::row(vec![
+                    Btn::text_fg("undo last point").build_def(ctx, Key::Backspace),
+                    Btn::text_bg2("save").build_def(ctx, Key::Enter),
+                ]),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            points: Vec::new(),
+        })
+    }
+}
+
+impl State<App> for DrawTollZone {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "undo last point" => {
+                    self.points.pop();
+                }
+                "save" => {
+                    let name = self.panel.text_box("name");
+                    if name.is_empty() {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["Name the toll zone first"],
+                        ));
+                    }
+                    let price = self.panel.spinner("price") as f64;
+                    let window: Window = self.panel.dropdown_value("window");
+                    let zone = TollZone {
+                        name,
+                        boundary: self.points.clone(),
+                        schedule: make_schedule(price, window),
+                    };
+                    if zone.polygon(&app.primary.map).is_none() {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["The boundary needs at least 3 points and can't cross itself"],
+                        ));
+                    }
+                    let old = app.primary.map.get_toll_zones().clone();
+                    let mut new = old.clone();
+                    new.push(zone);
+                    change_toll_zones(ctx, app, old, new);
+                    return Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::Replace(TollZoneEditor::new(ctx, app)),
+                    ]);
+                }
+                _ => unreachable!(),
+            },
+            _ => {}
+        }
+
+        if let Some(pt) = ctx.canvas.get_cursor_in_map_space() {
+            if app.per_obj.left_click(ctx, "add a new point") {
+                self.points
+                    .push(pt.to_gps(app.primary.map.get_gps_bounds()));
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let pts: Vec<Pt2D> = app.primary.map.get_gps_bounds().convert(&self.points);
+        if pts.len() == 2 {
+            g.draw_polygon(
+                Color::RED,
+                geom::Line::must_new(pts[0], pts[1]).make_polygons(POINT_RADIUS / 2.0),
+            );
+        }
+        let zone = TollZone {
+            name: String::new(),
+            boundary: self.points.clone(),
+            schedule: Vec::new(),
+        };
+        if let Some(poly) = zone.polygon(&app.primary.map) {
+            g.draw_polygon(Color::ORANGE.alpha(0.6), poly);
+        }
+        for pt in pts {
+            g.draw_polygon(
+                Color::RED,
+                Circle::new(pt, POINT_RADIUS / g.canvas.cam_zoom).to_polygon(),
+            );
+        }
+
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn make_schedule(price: f64, window: Window) -> Vec<(Time, f64)> {
+    let mut schedule = Vec::new();
+    for (start, end) in window {
+        schedule.push((Time::START_OF_DAY + Duration::hours(start), price));
+        if end < 24 {
+            schedule.push((Time::START_OF_DAY + Duration::hours(end), 0.0));
+        }
+    }
+    schedule
+}
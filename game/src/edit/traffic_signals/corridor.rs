@@ -0,0 +1,444 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use abstutil::Timer;
+use geom::{Distance, Duration, PolyLine, Polygon, Pt2D, Time};
+use map_gui::tools::PopupMsg;
+use map_model::{
+    ControlTrafficSignal, DirectedRoadID, Direction, IntersectionID, LaneID, Map, Traversable,
+    TurnPriority,
+};
+use sim::{CarID, CarStatus};
+use widgetry::{
+    Btn, Color, Drawable, EventCtx, GeomBatch, GfxCtx, Key, Line, Panel, Spinner, State, Text,
+    TextExt, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::SimpleState;
+use crate::edit::traffic_signals::fade_irrelevant;
+use crate::edit::traffic_signals::offsets::TuneRelative;
+
+const PLOT_WIDTH: f64 = 800.0;
+const PLOT_HEIGHT: f64 = 400.0;
+/// Room on the left for signal labels and below for times
+const MARGIN: f64 = 60.0;
+/// How many cycles of the longest signal along the corridor to show
+const NUM_CYCLES: f64 = 3.0;
+
+/// A time-space diagram of the signals along a corridor. Distance along the corridor goes up,
+/// time goes right. Each signal is a bar showing when traffic can continue along the corridor,
+/// and vehicles simulated from the current time are drawn over it, so offsets can be tuned until
+/// a platoon gets a green wave.
+pub struct TimeSpaceDiagram {
+    i1: IntersectionID,
+    i2: IntersectionID,
+    members: BTreeSet<IntersectionID>,
+    corridor: Corridor,
+    draw_corridor: Drawable,
+}
+
+struct Corridor {
+    /// Every intersection along the corridor, with its distance from the start
+    intersections: Vec<(IntersectionID, Distance)>,
+    /// Every road along the corridor in the direction of travel, with the distance where it starts
+    roads: Vec<(DirectedRoadID, Distance)>,
+    length: Distance,
+}
+
+impl TimeSpaceDiagram {
+    pub fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        i1: IntersectionID,
+        i2: IntersectionID,
+        members: BTreeSet<IntersectionID>,
+    ) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let corridor = match Corridor::new(map, i1, i2) {
+            Some(c) => c,
+            None => {
+                return PopupMsg::new(
+                    ctx,
+                    "Error",
+                    vec![format!("There's no path from {} to {}", i1, i2)],
+                );
+            }
+        };
+
+        let start = app
+            .primary
+            .suspended_sim
+            .as_ref()
+            .map(|sim| sim.time())
+            .unwrap_or_else(|| app.primary.sim.time());
+        let longest_cycle = corridor
+            .signals(map)
+            .map(|ts| ts.simple_cycle_duration())
+            .max()
+            .unwrap_or(Duration::ZERO);
+        let window = (NUM_CYCLES * longest_cycle).max(Duration::minutes(2));
+        let trajectories = ctx.loading_screen("simulate traffic along the corridor", |_, timer| {
+            corridor.simulate(app, start, window, timer)
+        });
+
+        let mut col = vec![
+            Widget::row(vec![
+                Line(format!("Time-space diagram from {} to {}", i1, i2))
+                    .small_heading()
+                    .draw(ctx),
+                Btn::close(ctx),
+            ]),
+            Text::from_multiline(vec![
+                Line("Bars show when each signal lets traffic continue along the corridor."),
+                Line("Lines are vehicles driving along it, simulated from the current time."),
+            ])
+            .draw(ctx),
+        ];
+        if app.primary.suspended_sim.is_none() {
+            col.push(
+                "Start the simulation before editing signals to see vehicles"
+                    .draw_text(ctx)
+                    .margin_below(10),
+            );
+        }
+        col.push(Widget::draw_batch(
+            ctx,
+            corridor.render(ctx, app, start, window, trajectories),
+        ));
+
+        let mut offsets = Vec::new();
+        for (i, _) in &corridor.intersections {
+            if !members.contains(i) {
+                continue;
+            }
+            let ts = map.get_traffic_signal(*i);
+            offsets.push(Widget::row(vec![
+                format!("Offset of {} (seconds):", i)
+                    .draw_text(ctx)
+                    .centered_vert(),
+                Spinner::new(
+                    ctx,
+                    (0, ts.simple_cycle_duration().inner_seconds() as isize),
+                    ts.offset.inner_seconds() as isize,
+                )
+                .named(format!("offset {}", i.0)),
+            ]));
+        }
+        if !offsets.is_empty() {
+            col.extend(offsets);
+            col.push(Btn::text_bg2("Update offsets").build_def(ctx, Key::Enter));
+        }
+
+        let mut batch = fade_irrelevant(
+            app,
+            &corridor.intersections.iter().map(|(i, _)| *i).collect(),
+        );
+        for (dr, _) in &corridor.roads {
+            batch.push(app.cs.route, map.get_r(dr.id).get_thick_polygon(map));
+        }
+
+        SimpleState::new(
+            Panel::new(Widget::col(col)).build(ctx),
+            Box::new(TimeSpaceDiagram {
+                i1,
+                i2,
+                members,
+                corridor,
+                draw_corridor: ctx.upload(batch),
+            }),
+        )
+    }
+}
+
+impl SimpleState for TimeSpaceDiagram {
+    fn on_click(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        x: &str,
+        panel: &Panel,
+    ) -> Transition {
+        match x {
+            "close" => Transition::Replace(TuneRelative::new(
+                ctx,
+                app,
+                self.i1,
+                self.i2,
+                self.members.clone(),
+            )),
+            "Update offsets" => {
+                for (i, _) in &self.corridor.intersections {
+                    if !self.members.contains(i) {
+                        continue;
+                    }
+                    let offset =
+                        Duration::seconds(panel.spinner(&format!("offset {}", i.0)) as f64);
+                    let mut ts = app.primary.map.get_traffic_signal(*i).clone();
+                    if ts.offset != offset {
+                        ts.offset = offset;
+                        app.primary.map.incremental_edit_traffic_signal(ts);
+                    }
+                }
+                Transition::Replace(TimeSpaceDiagram::new(
+                    ctx,
+                    app,
+                    self.i1,
+                    self.i2,
+                    self.members.clone(),
+                ))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn other_event(&mut self, ctx: &mut EventCtx, _: &mut App) -> Transition {
+        ctx.canvas_movement();
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        g.redraw(&self.draw_corridor);
+    }
+}
+
+impl Corridor {
+    fn new(map: &Map, i1: IntersectionID, i2: IntersectionID) -> Option<Corridor> {
+        let mut intersections = Vec::new();
+        let mut roads = Vec::new();
+        let mut at = i1;
+        let mut dist = Distance::ZERO;
+        for r in map.simple_path_btwn(i1, i2)? {
+            let road = map.get_r(r);
+            let dir = if road.src_i == at {
+                Direction::Fwd
+            } else {
+                Direction::Back
+            };
+            intersections.push((at, dist));
+            roads.push((DirectedRoadID { id: r, dir }, dist));
+            dist += road.center_pts.length();
+            at = if road.src_i == at {
+                road.dst_i
+            } else {
+                road.src_i
+            };
+        }
+        intersections.push((at, dist));
+        Some(Corridor {
+            intersections,
+            roads,
+            length: dist,
+        })
+    }
+
+    fn signals<'a>(&'a self, map: &'a Map) -> impl Iterator<Item = &'a ControlTrafficSignal> + 'a {
+        self.intersections
+            .iter()
+            .filter_map(move |(i, _)| map.maybe_get_traffic_signal(*i))
+    }
+
+    /// How the signal at some intersection along the corridor treats traffic continuing along it
+    /// during one stage.
+    fn priority(&self, idx: usize, ts: &ControlTrafficSignal, stage: usize) -> TurnPriority {
+        let from = if idx == 0 {
+            None
+        } else {
+            self.roads.get(idx - 1).map(|(dr, _)| *dr)
+        };
+        let to = self.roads.get(idx).map(|(dr, _)| *dr);
+        let mut result = TurnPriority::Banned;
+        for m in ts.movements.keys() {
+            if m.crosswalk
+                || from.map(|dr| dr != m.from).unwrap_or(false)
+                || to.map(|dr| dr != m.to).unwrap_or(false)
+            {
+                continue;
+            }
+            match ts.stages[stage].get_priority_of_movement(*m) {
+                TurnPriority::Protected => {
+                    return TurnPriority::Protected;
+                }
+                TurnPriority::Yield => {
+                    result = TurnPriority::Yield;
+                }
+                TurnPriority::Banned => {}
+            }
+        }
+        result
+    }
+
+    /// Runs a copy of the simulation from the moment edit mode was entered, recording where
+    /// vehicles are along the corridor every second.
+    fn simulate(
+        &self,
+        app: &App,
+        start: Time,
+        window: Duration,
+        timer: &mut Timer,
+    ) -> Vec<Vec<(Time, Distance)>> {
+        let map = &app.primary.map;
+        let mut sim = match app.primary.suspended_sim {
+            Some(ref sim) => sim.clone(),
+            None => {
+                return Vec::new();
+            }
+        };
+        sim.handle_live_edited_traffic_signals(map);
+        sim.restart_traffic_signals(&self.signals(map).map(|ts| ts.id).collect(), map);
+
+        let mut lanes: Vec<(LaneID, Distance)> = Vec::new();
+        for (dr, dist) in &self.roads {
+            for (l, dir, lt) in map.get_r(dr.id).lanes_ltr() {
+                if dir == dr.dir && lt.is_for_moving_vehicles() {
+                    lanes.push((l, *dist));
+                }
+            }
+        }
+
+        let step = Duration::seconds(1.0);
+        let mut trajectories: Vec<Vec<(Time, Distance)>> = Vec::new();
+        // Each vehicle's latest trajectory
+        let mut current: BTreeMap<CarID, usize> = BTreeMap::new();
+        let num_steps = (window / step) as usize;
+        timer.start_iter("simulate traffic along the corridor", num_steps);
+        for _ in 0..num_steps {
+            timer.next();
+            let now = sim.time();
+            for (l, road_start) in &lanes {
+                let lane = map.get_l(*l);
+                for car in sim.get_draw_cars(Traversable::Lane(*l), map) {
+                    if car.status == CarStatus::Parked {
+                        continue;
+                    }
+                    let dist = match lane.lane_center_pts.dist_along_of_point(car.body.last_pt()) {
+                        Some((dist, _)) => *road_start + dist,
+                        None => {
+                            continue;
+                        }
+                    };
+                    // A vehicle might leave the corridor and come back later
+                    match current.get(&car.id) {
+                        Some(idx) if trajectories[*idx].last().unwrap().0 + step >= now => {
+                            trajectories[*idx].push((now, dist));
+                        }
+                        _ => {
+                            current.insert(car.id, trajectories.len());
+                            trajectories.push(vec![(now, dist)]);
+                        }
+                    }
+                }
+            }
+            sim.timed_step(map, step, &mut None, &mut Timer::throwaway());
+        }
+        trajectories
+    }
+
+    fn render(
+        &self,
+        ctx: &EventCtx,
+        app: &App,
+        start: Time,
+        window: Duration,
+        trajectories: Vec<Vec<(Time, Distance)>>,
+    ) -> GeomBatch {
+        let map = &app.primary.map;
+        let x = |t: Time| MARGIN + (t - start) / window * PLOT_WIDTH;
+        let y = |d: Distance| {
+            if self.length == Distance::ZERO {
+                PLOT_HEIGHT
+            } else {
+                PLOT_HEIGHT - d / self.length * PLOT_HEIGHT
+            }
+        };
+        let end = start + window;
+
+        let mut batch = GeomBatch::new();
+        batch.push(
+            Color::WHITE.alpha(0.1),
+            Polygon::rectangle(PLOT_WIDTH, PLOT_HEIGHT).translate(MARGIN, 0.0),
+        );
+
+        for (idx, (i, dist)) in self.intersections.iter().enumerate() {
+            let ts = match map.maybe_get_traffic_signal(*i) {
+                Some(ts) => ts,
+                None => {
+                    continue;
+                }
+            };
+            for (t1, t2, stage) in stages_between(ts, start, end) {
+                let color = match self.priority(idx, ts, stage) {
+                    TurnPriority::Protected => app.cs.signal_protected_turn,
+                    TurnPriority::Yield => app.cs.signal_permitted_turn,
+                    TurnPriority::Banned => Color::RED,
+                };
+                if let Some(poly) = Polygon::rectangle_two_corners(
+                    Pt2D::new(x(t1), y(*dist) - 3.0),
+                    Pt2D::new(x(t2), y(*dist) + 3.0),
+                ) {
+                    batch.push(color, poly);
+                }
+            }
+            batch.append(
+                Text::from(Line(i.to_string()))
+                    .render_autocropped(ctx)
+                    .scale(0.5)
+                    .centered_on(Pt2D::new(MARGIN / 2.0, y(*dist))),
+            );
+        }
+
+        for (idx, pts) in trajectories.into_iter().enumerate() {
+            let pts: Vec<Pt2D> = pts
+                .into_iter()
+                .map(|(t, d)| Pt2D::new(x(t), y(d)))
+                .collect();
+            if let Ok(pl) = PolyLine::deduping_new(pts) {
+                batch.push(
+                    app.cs.rotating_color_agents(idx),
+                    pl.make_polygons(Distance::meters(1.0)),
+                );
+            }
+        }
+
+        for (t, label) in vec![(start, start.ampm_tostring()), (end, end.ampm_tostring())] {
+            batch.append(
+                Text::from(Line(label))
+                    .render_autocropped(ctx)
+                    .scale(0.5)
+                    .centered_on(Pt2D::new(x(t), PLOT_HEIGHT + MARGIN / 2.0)),
+            );
+        }
+        batch
+    }
+}
+
+/// Following a signal's fixed schedule, returns each stage active between two times.
+fn stages_between(ts: &ControlTrafficSignal, start: Time, end: Time) -> Vec<(Time, Time, usize)> {
+    let mut results = Vec::new();
+    let cycle = ts.simple_cycle_duration();
+    if cycle == Duration::ZERO {
+        return results;
+    }
+
+    // Find the stage at the start, just like the simulation does
+    let mut offset = ((start - Time::START_OF_DAY) + ts.offset) % cycle;
+    let mut idx = 0;
+    loop {
+        let dt = ts.stages[idx].phase_type.simple_duration();
+        if offset < dt {
+            break;
+        }
+        offset -= dt;
+        idx = (idx + 1) % ts.stages.len();
+    }
+
+    let mut t = start;
+    let mut remaining = ts.stages[idx].phase_type.simple_duration() - offset;
+    while t < end {
+        let next = (t + remaining).min(end);
+        results.push((t, next, idx));
+        t = next;
+        idx = (idx + 1) % ts.stages.len();
+        remaining = ts.stages[idx].phase_type.simple_duration();
+    }
+    results
+}
@@ -20,6 +20,7 @@ use crate::common::{CommonState, Warping};
 use crate::edit::{apply_map_edits, ConfirmDiscard};
 use crate::sandbox::GameplayMode;
 
+mod corridor;
 mod edits;
 mod offsets;
 mod picker;
@@ -337,6 +338,31 @@ impl State<App> for TrafficSignalEditor {
                         );
                     }
                 }
+                "Export timing plan" => {
+                    let map = &app.primary.map;
+                    let first = *self.members.iter().next().unwrap();
+                    let groups = if self.members.len() > 1 {
+                        vec![(
+                            format!("{} signals", self.members.len()),
+                            self.members.clone(),
+                        )]
+                    } else {
+                        Vec::new()
+                    };
+                    let plans = map_model::signal_plans::export(map, &self.members, groups);
+                    let path = abstutil::path_player(format!(
+                        "signal_plans/{}/{}/{}.json",
+                        map.get_name().city,
+                        map.get_name().map,
+                        map.get_i(first).orig_id.0
+                    ));
+                    abstutil::write_json(path.clone(), &plans);
+                    return Transition::Push(PopupMsg::new(
+                        ctx,
+                        "Timing plan exported",
+                        vec![format!("Timing plan exported to {}", path)],
+                    ));
+                }
                 "Preview" => {
                     // Might have to do this first!
                     app.primary
@@ -538,6 +564,11 @@ fn make_top_panel(ctx: &mut EventCtx, app: &App, can_undo: bool, can_redo: bool)
             .build_def(ctx, Key::M),
         ]),
         Widget::row(row),
+        Btn::text_fg("Export timing plan")
+            .tooltip(Text::from(
+                Line("Save these signals in a format other tools can read").small(),
+            ))
+            .build_def(ctx, None),
         if app.opts.dev {
             Btn::text_fg("Export")
                 .tooltip(Text::from_multiline(vec![
@@ -697,7 +728,7 @@ impl BundleEdits {
         }
 
         let mut edits = app.primary.map.get_edits().clone();
-        // TODO Can we batch these commands somehow, so undo/redo in edit mode behaves properly?
+        let first = edits.commands.len();
         for signal in self.signals {
             edits.commands.push(EditCmd::ChangeIntersection {
                 i: signal.id,
@@ -705,6 +736,8 @@ impl BundleEdits {
                 new: EditIntersection::TrafficSignal(signal.export(&app.primary.map)),
             });
         }
+        // Undo and redo the whole bundle as one step
+        edits.group_commands_since(first);
         apply_map_edits(ctx, app, edits);
     }
 
@@ -12,6 +12,7 @@ use widgetry::{
 
 use crate::app::{App, Transition};
 use crate::common::{CommonState, SimpleState};
+use crate::edit::traffic_signals::corridor::TimeSpaceDiagram;
 use crate::edit::traffic_signals::fade_irrelevant;
 
 pub struct ShowAbsolute {
@@ -191,7 +192,7 @@ impl SimpleState for ShowRelative {
     }
 }
 
-struct TuneRelative {
+pub struct TuneRelative {
     i1: IntersectionID,
     i2: IntersectionID,
     members: BTreeSet<IntersectionID>,
@@ -253,6 +254,7 @@ impl TuneRelative {
                     .named("offset"),
             ]),
             Btn::text_bg2("Update offset").build_def(ctx, Key::Enter),
+            Btn::text_fg("Time-space diagram").build_def(ctx, Key::T),
         ]))
         .build(ctx);
         SimpleState::new(
@@ -288,6 +290,13 @@ impl SimpleState for TuneRelative {
                     Transition::Replace(ShowRelative::new(ctx, app, self.i1, self.members.clone())),
                 ])
             }
+            "Time-space diagram" => Transition::Replace(TimeSpaceDiagram::new(
+                ctx,
+                app,
+                self.i1,
+                self.i2,
+                self.members.clone(),
+            )),
             _ => unreachable!(),
         }
     }
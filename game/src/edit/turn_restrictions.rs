@@ -0,0 +1,192 @@
+use std::collections::BTreeSet;
+
+use geom::{Duration, Time};
+use map_gui::render::Renderable;
+use map_model::{BannedTurn, RoadID};
+use widgetry::{
+    Btn, Choice, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel, State, TextExt,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::edit::apply_map_edits;
+
+/// Ban vehicles from turning from one road onto others, either all day or only during some hours.
+pub struct TurnRestrictionEditor {
+    panel: Panel,
+    r: RoadID,
+    targets: Vec<RoadID>,
+}
+
+/// None means the turn is allowed. Otherwise, the window of the ban; None for all day.
+type BanChoice = Option<Option<(Time, Time)>>;
+
+impl TurnRestrictionEditor {
+    pub fn new(ctx: &mut EventCtx, app: &App, r: RoadID) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let road = map.get_r(r);
+
+        let mut targets = Vec::new();
+        let mut col = vec![
+            Line(format!(
+                "Turn restrictions from {}",
+                road.get_name(app.opts.language.as_ref())
+            ))
+            .small_heading()
+            .draw(ctx),
+            "Bans for part of the day only affect trips starting during those hours."
+                .draw_text(ctx),
+        ];
+        for i in vec![road.src_i, road.dst_i] {
+            // Can vehicles leave this road here?
+            if !road
+                .lanes_ltr()
+                .into_iter()
+                .any(|(l, _, lt)| lt.is_for_moving_vehicles() && map.get_l(l).dst_i == i)
+            {
+                continue;
+            }
+            let to_roads: BTreeSet<RoadID> = map
+                .get_i(i)
+                .outgoing_lanes
+                .iter()
+                .map(|l| map.get_l(*l))
+                .filter(|l| l.lane_type.is_for_moving_vehicles() && l.parent != r)
+                .map(|l| l.parent)
+                .collect();
+            for to in to_roads {
+                let to_road = map.get_r(to);
+                let current: BanChoice = road
+                    .banned_turns
+                    .iter()
+                    .find(|ban| ban.to == to_road.orig_id)
+                    .map(|ban| ban.window);
+                // If the turn is banned all day, it doesn't exist anymore
+                let turn_type = map
+                    .get_i(i)
+                    .turns
+                    .iter()
+                    .find(|t| map.get_l(t.src).parent == r && map.get_l(t.dst).parent == to)
+                    .map(|t| format!("{:?} onto ", map.get_t(*t).turn_type))
+                    .unwrap_or_else(|| "Onto ".to_string());
+                col.push(Widget::row(vec![
+                    format!(
+                        "{}{}",
+                        turn_type,
+                        to_road.get_name(app.opts.language.as_ref())
+                    )
+                    .draw_text(ctx)
+                    .centered_vert(),
+                    Widget::dropdown(ctx, to.to_string(), current, ban_choices(current)),
+                ]));
+                targets.push(to);
+            }
+        }
+        if targets.is_empty() {
+            col.push("Vehicles can't turn anywhere from this road".draw_text(ctx));
+        }
+        col.push(
+            Widget::custom_row(vec![
+                Btn::text_fg("Apply").build_def(ctx, Key::Enter),
+                Btn::text_fg("Cancel").build_def(ctx, Key::Escape),
+            ])
+            .evenly_spaced(),
+        );
+
+        Box::new(TurnRestrictionEditor {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+                .build(ctx),
+            r,
+            targets,
+        })
+    }
+}
+
+impl State<App> for TurnRestrictionEditor {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "Apply" => {
+                    let map = &app.primary.map;
+                    let target_ids: BTreeSet<_> =
+                        self.targets.iter().map(|r| map.get_r(*r).orig_id).collect();
+                    // Keep bans onto roads not listed here
+                    let mut banned_turns: Vec<BannedTurn> = map
+                        .get_r(self.r)
+                        .banned_turns
+                        .iter()
+                        .filter(|ban| !target_ids.contains(&ban.to))
+                        .cloned()
+                        .collect();
+                    for to in &self.targets {
+                        let choice: BanChoice = self.panel.dropdown_value(to.to_string());
+                        if let Some(window) = choice {
+                            banned_turns.push(BannedTurn {
+                                to: map.get_r(*to).orig_id,
+                                window,
+                            });
+                        }
+                    }
+
+                    if banned_turns != map.get_r(self.r).banned_turns {
+                        let mut edits = map.get_edits().clone();
+                        edits.commands.push(map.edit_road_cmd(self.r, |new| {
+                            new.banned_turns = banned_turns.clone();
+                        }));
+                        apply_map_edits(ctx, app, edits);
+                    }
+                    Transition::Pop
+                }
+                "Cancel" => Transition::Pop,
+                _ => unreachable!(),
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.draw_polygon(
+            app.cs.perma_selected_object,
+            app.primary
+                .draw_map
+                .get_r(self.r)
+                .get_outline(&app.primary.map),
+        );
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn ban_choices(current: BanChoice) -> Vec<Choice<BanChoice>> {
+    let hours = |h| Time::START_OF_DAY + Duration::hours(h);
+    let mut choices = vec![
+        Choice::new("allowed", None),
+        Choice::new("banned all day", Some(None)),
+    ];
+    for (start, end) in vec![(7, 9), (16, 19), (7, 19), (22, 6)] {
+        choices.push(ban_choice(Some((hours(start), hours(end)))));
+    }
+    if !choices.iter().any(|c| c.data == current) {
+        if let Some(window) = current {
+            choices.push(ban_choice(window));
+        }
+    }
+    choices
+}
+
+fn ban_choice(window: Option<(Time, Time)>) -> Choice<BanChoice> {
+    match window {
+        Some((start, end)) => Choice::new(
+            format!(
+                "banned from {} to {}",
+                start.ampm_tostring(),
+                end.ampm_tostring()
+            ),
+            Some(window),
+        ),
+        None => Choice::new("banned all day", Some(None)),
+    }
+}
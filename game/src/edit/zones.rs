@@ -104,6 +104,10 @@ impl State<App> for ZoneEditor {
                     // The original allow_through_traffic always includes this, and there's no way
                     // to exclude it, so stay consistent.
                     allow_through_traffic.insert(PathConstraints::Train);
+                    // Trucks follow the same rules as cars here.
+                    if allow_through_traffic.contains(PathConstraints::Car) {
+                        allow_through_traffic.insert(PathConstraints::Truck);
+                    }
                     let new_access_restrictions = AccessRestrictions {
                         allow_through_traffic,
                         cap_vehicles_per_hour: {
@@ -49,6 +49,27 @@ pub fn info(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BuildingID
         kv.push(("Parking", "None".to_string()));
     }
 
+    let racks = app.primary.map.get_bike_rack_capacity(b.id);
+    let bikes = app.primary.sim.get_bike_rack_occupancy(b.id);
+    if racks > 0 || bikes.total_improvised > 0 {
+        kv.push((
+            "Bike racks",
+            format!(
+                "{} / {} in use, at most {} so far",
+                bikes.at_racks, racks, bikes.peak_at_racks
+            ),
+        ));
+    }
+    if bikes.total_improvised > 0 {
+        kv.push((
+            "Bikes without a rack",
+            format!(
+                "{} now, {} so far",
+                bikes.improvised, bikes.total_improvised
+            ),
+        ));
+    }
+
     rows.extend(make_table(ctx, kv));
 
     let mut txt = Text::new();
@@ -235,6 +235,21 @@ pub fn route(ctx: &mut EventCtx, app: &App, details: &mut Details, id: BusRouteI
         .draw(ctx),
     );
 
+    if let Some(headways) = app.primary.sim.get_headway_regularity(id) {
+        rows.push(
+            Text::from_all(vec![
+                Line("Headways"),
+                Line(format!(
+                    ": {} on average, varying by {} (coefficient of variation {:.2})",
+                    headways.mean, headways.std_dev, headways.coefficient_of_variation
+                ))
+                .secondary(),
+            ])
+            .wrap_to_pct(ctx, 20)
+            .draw(ctx),
+        );
+    }
+
     rows.push(format!("{} stops", route.stops.len()).draw_text(ctx));
     {
         let i = map.get_i(map.get_l(route.start).src_i);
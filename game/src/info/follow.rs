@@ -0,0 +1,346 @@
+//! While the simulation runs with a person's info panel open, the camera chases whoever the panel
+//! is about. Instead of jumping to the agent every step, the camera eases toward a point a little
+//! ahead of them. For vehicles, a strip view shows what the driver sees ahead: the road along
+//! their path, other vehicles queued in front, their next turn, and the signal controlling it.
+//!
+//! The map canvas itself can't rotate, so heading-up rotation only applies to the strip view.
+
+use geom::{Angle, Circle, Distance, Duration, Polygon, Pt2D};
+use map_model::{PathStep, Traversable, TurnPriority, TurnType};
+use sim::{AgentID, CarStatus, DrawCarInput};
+use widgetry::{
+    Checkbox, Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, Text,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+
+/// How long the camera takes to close most of the gap to the agent
+const SMOOTHING: Duration = Duration::const_seconds(0.5);
+/// The camera looks this far ahead of a moving vehicle
+const CAMERA_LEAD: Distance = Distance::const_meters(15.0);
+/// How much of the path the strip view shows, in every direction around the vehicle
+const STRIP_RADIUS: Distance = Distance::const_meters(50.0);
+/// Pixels per meter in the strip view
+const STRIP_SCALE: f64 = 2.5;
+const CAR_WIDTH: Distance = Distance::const_meters(1.75);
+
+pub struct FollowState {
+    agent: AgentID,
+    /// Where the camera is looking, lagging behind the agent
+    camera: Option<Pt2D>,
+    heading_up: bool,
+    panel: Panel,
+}
+
+impl FollowState {
+    pub fn new(ctx: &mut EventCtx, app: &App, agent: AgentID) -> FollowState {
+        let heading_up = true;
+        FollowState {
+            agent,
+            camera: None,
+            heading_up,
+            panel: make_panel(ctx, app, agent, heading_up),
+        }
+    }
+
+    pub fn agent(&self) -> AgentID {
+        self.agent
+    }
+
+    pub fn event(&mut self, ctx: &mut EventCtx, app: &App) {
+        if let Outcome::Changed = self.panel.event(ctx) {
+            self.heading_up = self.panel.is_checked("heading up");
+            self.panel = make_panel(ctx, app, self.agent, self.heading_up);
+        }
+
+        if let Some(dt) = ctx.input.nonblocking_is_update_event() {
+            let target = match chase_point(app, self.agent) {
+                Some(pt) => pt,
+                None => {
+                    return;
+                }
+            };
+            let pt = match self.camera {
+                Some(camera) => {
+                    // Exponential smoothing, so the camera moves the same way regardless of the
+                    // frame rate
+                    let pct = 1.0 - (-dt.inner_seconds() / SMOOTHING.inner_seconds()).exp();
+                    Pt2D::new(
+                        camera.x() + (target.x() - camera.x()) * pct,
+                        camera.y() + (target.y() - camera.y()) * pct,
+                    )
+                }
+                None => target,
+            };
+            ctx.canvas.center_on_map_pt(pt);
+            self.camera = Some(pt);
+
+            let mut new = make_panel(ctx, app, self.agent, self.heading_up);
+            new.restore(ctx, &self.panel);
+            self.panel = new;
+        }
+    }
+
+    pub fn draw(&self, g: &mut GfxCtx) {
+        self.panel.draw(g);
+    }
+}
+
+/// Where the camera should be looking
+fn chase_point(app: &App, agent: AgentID) -> Option<Pt2D> {
+    if let AgentID::Car(c) = agent {
+        if let Some(input) = app.primary.sim.get_draw_car(c, &app.primary.map) {
+            let front = input.body.last_pt();
+            return Some(front.project_away(CAMERA_LEAD, input.body.last_line().angle()));
+        }
+    }
+    app.primary
+        .sim
+        .canonical_pt_for_agent(agent, &app.primary.map)
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App, agent: AgentID, heading_up: bool) -> Panel {
+    let mut col = vec![Line(format!("Following {}", agent))
+        .small_heading()
+        .draw(ctx)];
+    let input = match agent {
+        AgentID::Car(c) => app.primary.sim.get_draw_car(c, &app.primary.map),
+        _ => None,
+    };
+    if let Some(input) = input {
+        col.push(Checkbox::switch(ctx, "heading up", None, heading_up));
+        col.push(draw_strip(app, &input, heading_up).batch());
+        col.push(describe_ahead(app, &input).draw(ctx));
+    }
+    Panel::new(Widget::col(col))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx)
+}
+
+/// The steps of the vehicle's path within the strip view, and how far along the first step the
+/// front of the vehicle is.
+fn steps_ahead(app: &App, input: &DrawCarInput) -> (Vec<Traversable>, Distance) {
+    let map = &app.primary.map;
+    let front = input.body.last_pt();
+    let current = input
+        .on
+        .exact_slice(Distance::ZERO, input.on.length(map), map)
+        .dist_along_of_point(front)
+        .map(|(dist, _)| dist)
+        .unwrap_or(Distance::ZERO);
+
+    let mut steps = vec![input.on];
+    let mut dist_left = STRIP_RADIUS - (input.on.length(map) - current);
+    if let Some(path) = app.primary.sim.get_path(AgentID::Car(input.id)) {
+        // The path starts with the step the vehicle is currently on
+        for step in path.get_steps().iter().skip(1) {
+            if dist_left <= Distance::ZERO {
+                break;
+            }
+            let t = step.as_traversable();
+            steps.push(t);
+            dist_left -= t.length(map);
+        }
+    }
+    (steps, current)
+}
+
+fn draw_strip(app: &App, input: &DrawCarInput, heading_up: bool) -> GeomBatch {
+    let map = &app.primary.map;
+    let front = input.body.last_pt();
+    let (steps, current) = steps_ahead(app, input);
+
+    let mut world = GeomBatch::new();
+    let mut dist_left = STRIP_RADIUS;
+    for (idx, t) in steps.iter().enumerate() {
+        let length = t.length(map);
+        // Only show a little bit of the current step behind the vehicle, and cut off the last step
+        let start = if idx == 0 {
+            (current - STRIP_RADIUS / 2.0).max(Distance::ZERO)
+        } else {
+            Distance::ZERO
+        };
+        let end = if idx == 0 {
+            (current + dist_left).min(length)
+        } else {
+            dist_left.min(length)
+        };
+        dist_left -= if idx == 0 { end - current } else { end };
+        if end <= start {
+            continue;
+        }
+        let (width, color) = match t {
+            Traversable::Lane(l) => (
+                map.get_l(*l).width,
+                app.cs
+                    .zoomed_road_surface(map.get_l(*l).lane_type, map.get_parent(*l).get_rank()),
+            ),
+            Traversable::Turn(turn) => (
+                map.get_l(turn.src).width,
+                app.cs
+                    .zoomed_intersection_surface(map.get_parent(turn.src).get_rank()),
+            ),
+        };
+        world.push(color, t.exact_slice(start, end, map).make_polygons(width));
+
+        for car in app.primary.sim.get_draw_cars(*t, map) {
+            if car.status != CarStatus::Moving {
+                continue;
+            }
+            if car
+                .body
+                .points()
+                .iter()
+                .any(|pt| pt.dist_to(front) > STRIP_RADIUS)
+            {
+                continue;
+            }
+            let color = if car.id == input.id {
+                app.cs.selected
+            } else {
+                Color::WHITE
+            };
+            world.push(color, car.body.make_polygons(CAR_WIDTH));
+        }
+
+        // The color of the signal at the next turn
+        if let Traversable::Turn(turn) = t {
+            if let Some(signal) = map.maybe_get_traffic_signal(turn.parent) {
+                let (stage, _) = app
+                    .primary
+                    .sim
+                    .current_stage_and_remaining_time(turn.parent);
+                let color = match signal.stages[stage].get_priority_of_turn(*turn, signal) {
+                    TurnPriority::Protected => Color::GREEN,
+                    TurnPriority::Yield => Color::YELLOW,
+                    TurnPriority::Banned => Color::RED,
+                };
+                world.push(
+                    color,
+                    Circle::new(map.get_t(*turn).geom.first_pt(), Distance::meters(1.5))
+                        .to_polygon(),
+                );
+            }
+        }
+    }
+
+    // Move the vehicle to the center of the strip, optionally rotating so it faces up
+    // Leave a little room for the width of lanes at the edge
+    let size = 2.0 * (STRIP_RADIUS + Distance::meters(5.0)).inner_meters() * STRIP_SCALE;
+    let rotate = if heading_up {
+        Angle::degrees(-90.0) + -input.body.last_line().angle()
+    } else {
+        Angle::degrees(0.0)
+    };
+    let origin = Pt2D::new(0.0, 0.0);
+    let mut batch = GeomBatch::new();
+    batch.push(Color::BLACK, Polygon::rectangle(size, size));
+    for (color, poly, _) in world.consume() {
+        let poly = poly
+            .translate(-front.x(), -front.y())
+            .rotate_around(rotate, origin)
+            .scale(STRIP_SCALE)
+            .translate(size / 2.0, size / 2.0);
+        let bounds = poly.get_bounds();
+        if bounds.min_x >= 0.0
+            && bounds.min_y >= 0.0
+            && bounds.max_x <= size
+            && bounds.max_y <= size
+        {
+            batch.push(color, poly);
+        }
+    }
+    batch
+}
+
+fn describe_ahead(app: &App, input: &DrawCarInput) -> Text {
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+    let lang = app.opts.language.as_ref();
+    let mut txt = Text::new();
+
+    // Vehicles in front on the same lane or turn
+    let front = |car: &DrawCarInput| {
+        input
+            .on
+            .exact_slice(Distance::ZERO, input.on.length(map), map)
+            .dist_along_of_point(car.body.last_pt())
+            .map(|(dist, _)| dist)
+    };
+    if let Some(ours) = front(input) {
+        let ahead: Vec<Distance> = sim
+            .get_draw_cars(input.on, map)
+            .iter()
+            .filter(|car| car.status == CarStatus::Moving && car.id != input.id)
+            .filter_map(|car| front(car))
+            .filter(|dist| *dist > ours)
+            .collect();
+        if let Some(nearest) = ahead.iter().min() {
+            txt.add(Line(format!(
+                "{} vehicles ahead, the nearest {} away",
+                ahead.len(),
+                *nearest - ours
+            )));
+        } else {
+            txt.add(Line("Nobody ahead"));
+        }
+    }
+
+    // The next turn, and what controls it
+    let path = match sim.get_path(AgentID::Car(input.id)) {
+        Some(path) => path,
+        None => {
+            return txt;
+        }
+    };
+    let (_, current) = steps_ahead(app, input);
+    let mut dist = input.on.length(map) - current;
+    let mut next_turn = None;
+    for (idx, step) in path.get_steps().iter().enumerate() {
+        if let PathStep::Turn(t) = step {
+            next_turn = Some((*t, if idx == 0 { Distance::ZERO } else { dist }));
+            break;
+        }
+        if idx > 0 {
+            dist += step.as_traversable().length(map);
+        }
+    }
+    let (turn, dist) = match next_turn {
+        Some(x) => x,
+        None => {
+            txt.add(Line("Almost there"));
+            return txt;
+        }
+    };
+    let onto = map.get_parent(turn.dst).get_name(lang);
+    let verb = match map.get_t(turn).turn_type {
+        TurnType::Straight => "Continue straight",
+        TurnType::Left => "Turn left",
+        TurnType::Right => "Turn right",
+        TurnType::UTurn => "Make a U-turn",
+        TurnType::Crosswalk | TurnType::SharedSidewalkCorner => "Cross",
+    };
+    if dist == Distance::ZERO {
+        txt.add(Line(format!("{} onto {} now", verb, onto)));
+    } else {
+        txt.add(Line(format!("{} onto {} in {}", verb, onto, dist)));
+    }
+
+    if let Some(signal) = map.maybe_get_traffic_signal(turn.parent) {
+        let (stage, remaining) = sim.current_stage_and_remaining_time(turn.parent);
+        let (state, color) = match signal.stages[stage].get_priority_of_turn(turn, signal) {
+            TurnPriority::Protected => ("green", Color::GREEN),
+            TurnPriority::Yield => ("green, but yielding", Color::YELLOW),
+            TurnPriority::Banned => ("red", Color::RED),
+        };
+        txt.add(Line(format!("The signal is {} for another {}", state, remaining)).fg(color));
+    } else if let Some(sign) = map.maybe_get_stop_sign(turn.parent) {
+        if sign.get_priority(turn, map) == TurnPriority::Protected {
+            txt.add(Line("Has the right-of-way"));
+        } else {
+            txt.add(Line("Stop sign ahead").fg(Color::RED));
+        }
+    }
+    txt
+}
@@ -1,19 +1,21 @@
 use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
 
 use abstutil::prettyprint_usize;
 use geom::{ArrowCap, Distance, Duration, PolyLine, Polygon, Time};
 use map_gui::options::TrafficSignalStyle;
 use map_gui::render::traffic_signal::draw_signal_stage;
 use map_model::{IntersectionID, IntersectionType, PhaseType};
-use sim::AgentType;
+use sim::{AgentType, SafetyLocation, TURN_COUNT_INTERVAL};
 use widgetry::{
     Btn, Checkbox, Color, DrawWithTooltips, EventCtx, FanChart, GeomBatch, Line, PlotOptions,
-    ScatterPlot, Series, Text, Widget,
+    ScatterPlot, Series, Text, TextExt, Widget,
 };
 
 use crate::app::App;
 use crate::common::color_for_agent_type;
-use crate::info::{header_btns, make_tabs, throughput, DataOptions, Details, Tab};
+use crate::info::{header_btns, make_table, make_tabs, throughput, DataOptions, Details, Tab};
 
 pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: IntersectionID) -> Vec<Widget> {
     let mut rows = header(ctx, app, details, id, Tab::IntersectionInfo(id));
@@ -34,6 +36,59 @@ pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: IntersectionID
     }
     rows.push(txt.draw(ctx));
 
+    if !i.crosswalks.is_empty() {
+        let mut txt = Text::from(Line("Crosswalks"));
+        for c in &i.crosswalks {
+            txt.add(Line(format!(
+                "  {}: {}",
+                app.primary
+                    .map
+                    .get_r(c.road)
+                    .get_name(app.opts.language.as_ref()),
+                c.describe()
+            )));
+        }
+        rows.push(txt.draw(ctx));
+    }
+
+    let treatments = app.primary.map.get_bike_treatments(id);
+    if !treatments.is_empty() {
+        let mut txt = Text::from(Line("Bike treatments"));
+        for x in treatments.describe() {
+            txt.add(Line(format!("  {}", x)));
+        }
+        rows.push(txt.draw(ctx));
+    }
+    let mut txt = Text::from(Line("Cyclists so far"));
+    let near_misses = app
+        .primary
+        .sim
+        .get_safety_stats()
+        .events
+        .iter()
+        .filter(|(_, ev)| {
+            ev.involves_bike() && ev.location(&app.primary.map) == SafetyLocation::Intersection(id)
+        })
+        .count();
+    txt.add(Line(format!(
+        "  {} near misses",
+        prettyprint_usize(near_misses)
+    )));
+    if let Some(list) = app.primary.sim.get_analytics().intersection_delays.get(&id) {
+        let delays: Vec<Duration> = list
+            .iter()
+            .filter(|(_, _, _, agent_type)| *agent_type == AgentType::Bike)
+            .map(|(_, _, dt, _)| *dt)
+            .collect();
+        if !delays.is_empty() {
+            txt.add(Line(format!(
+                "  waited {} on average",
+                delays.iter().cloned().sum::<Duration>() / (delays.len() as f64)
+            )));
+        }
+    }
+    rows.push(txt.draw(ctx));
+
     if app.opts.dev {
         rows.push(Btn::text_bg1("Open OSM node").build(ctx, format!("open {}", i.orig_id), None));
     }
@@ -123,6 +178,7 @@ pub fn delay(
     ));
 
     rows.push(delay_plot(ctx, app, id, opts, fan_chart));
+    rows.push(delay_percentiles(ctx, app, id, opts));
 
     rows
 }
@@ -316,6 +372,102 @@ pub fn traffic_signal(
     rows
 }
 
+pub fn counts(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: IntersectionID,
+) -> Vec<Widget> {
+    let mut rows = header(ctx, app, details, id, Tab::IntersectionCounts(id));
+
+    let table = app
+        .primary
+        .sim
+        .get_turn_counts()
+        .table(id, &app.primary.map);
+    let peak = match table.peak_hour() {
+        Some(peak) => peak,
+        None => {
+            rows.push("Nobody has crossed here yet".draw_text(ctx));
+            return rows;
+        }
+    };
+
+    let mut txt = Text::new();
+    txt.add(Line(format!(
+        "Turning movement counts, in {} intervals",
+        TURN_COUNT_INTERVAL
+    )));
+    txt.add(Line(format!(
+        "Peak hour: {} - {}, with {} vehicles",
+        peak.0.ampm_tostring(),
+        (peak.0 + Duration::hours(1)).ampm_tostring(),
+        prettyprint_usize(peak.1)
+    )));
+    rows.push(txt.draw(ctx));
+
+    // Per column, the total for the peak hour and for the whole day so far
+    let mut peak_totals = vec![0; table.columns.len()];
+    let mut day_totals = vec![0; table.columns.len()];
+    for (time, counts) in &table.rows {
+        let in_peak = *time >= peak.0 && *time < peak.0 + Duration::hours(1);
+        for (idx, count) in counts.iter().enumerate() {
+            day_totals[idx] += count;
+            if in_peak {
+                peak_totals[idx] += count;
+            }
+        }
+    }
+    rows.push(Line("Movement: peak hour / all day").secondary().draw(ctx));
+    rows.extend(make_table(
+        ctx,
+        table
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(idx, col)| {
+                (
+                    col.describe(),
+                    format!(
+                        "{} / {}",
+                        prettyprint_usize(peak_totals[idx]),
+                        prettyprint_usize(day_totals[idx])
+                    ),
+                )
+            })
+            .collect(),
+    ));
+
+    rows.push(Btn::text_fg("Export to CSV").build(
+        ctx,
+        format!("export turning movement counts for {}", id),
+        None,
+    ));
+
+    rows
+}
+
+/// Writes every interval of turning movement counts so far, returning the path.
+pub fn export_counts(app: &App, id: IntersectionID) -> Result<String, std::io::Error> {
+    let path = format!(
+        "turning_movement_counts_{}_{}_{}.csv",
+        app.primary.map.get_name().as_filename(),
+        id.0,
+        app.primary.sim.time().as_filename()
+    );
+    let mut f = File::create(&path)?;
+    write!(
+        f,
+        "{}",
+        app.primary
+            .sim
+            .get_turn_counts()
+            .table(id, &app.primary.map)
+            .to_csv()
+    )?;
+    Ok(path)
+}
+
 fn delay_plot(
     ctx: &EventCtx,
     app: &App,
@@ -372,6 +524,42 @@ fn delay_plot(
     .outline(2.0, Color::WHITE)
 }
 
+/// The mean delay can hide problems with signal timing, so show how bad it gets for the unluckiest
+/// agents, per hour.
+fn delay_percentiles(ctx: &EventCtx, app: &App, i: IntersectionID, opts: &DataOptions) -> Widget {
+    let data = if opts.show_before {
+        app.prebaked()
+    } else {
+        app.primary.sim.get_analytics()
+    };
+    let limit = if opts.show_end_of_day {
+        app.primary.sim.get_end_of_day()
+    } else {
+        app.primary.sim.time()
+    };
+
+    let mut txt = Text::from(Line("Delay percentiles per hour").small_heading());
+    let stats = data.intersection_delay_percentiles(i, Duration::hours(1), limit);
+    if stats.is_empty() {
+        txt.add(Line("No delays measured yet").secondary());
+    }
+    for s in stats {
+        txt.add(Line(format!(
+            "{}: {} agents, 50%ile {}, 90%ile {}, 99%ile {}, max {}",
+            s.start.ampm_tostring(),
+            prettyprint_usize(s.count),
+            s.p50,
+            s.p90,
+            s.p99,
+            s.max
+        )));
+    }
+    txt.draw(ctx)
+        .padding(10)
+        .bg(app.cs.inner_panel)
+        .outline(2.0, Color::WHITE)
+}
+
 fn header(
     ctx: &EventCtx,
     app: &App,
@@ -407,6 +595,9 @@ fn header(
             tabs.push(("Current demand", Tab::IntersectionDemand(id)));
             tabs.push(("Signal", Tab::IntersectionTrafficSignal(id)));
         }
+        if !i.is_border() {
+            tabs.push(("Counts", Tab::IntersectionCounts(id)));
+        }
         if i.is_incoming_border() {
             tabs.push((
                 "Arrivals",
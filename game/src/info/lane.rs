@@ -51,9 +51,31 @@ pub fn info(ctx: &EventCtx, app: &App, details: &mut Details, id: LaneID) -> Vec
         ));
     } else {
         kv.push(("Speed limit", r.speed_limit.to_string(&app.opts.units)));
+        for limit in &r.timed_speed_limits {
+            kv.push(("Speed limit", limit.describe(&app.opts.units)));
+        }
     }
 
     kv.push(("Length", l.length().to_string(&app.opts.units)));
+    if let Some(stress) = l.traffic_stress(&app.primary.map) {
+        kv.push(("Biking", stress.describe().to_string()));
+    }
+    if l.is_walkable() {
+        let map = &app.primary.map;
+        if let Some(width) = l.sidewalk_width(map) {
+            kv.push(("Sidewalk width", width.to_string(&app.opts.units)));
+        }
+        if let Some(surface) = l.sidewalk_surface(map) {
+            kv.push(("Surface", surface));
+        }
+        kv.push((
+            "Wheelchair access",
+            match l.wheelchair_barrier(map) {
+                Some(barrier) => barrier.describe(),
+                None => "no known barriers".to_string(),
+            },
+        ));
+    }
 
     rows.extend(make_table(ctx, kv));
 
@@ -3,7 +3,7 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 pub use trip::OpenTrip;
 
 use geom::{Circle, Distance, Time};
-use map_gui::tools::open_browser;
+use map_gui::tools::{open_browser, PopupMsg};
 use map_gui::ID;
 use map_model::{AreaID, BuildingID, BusRouteID, BusStopID, IntersectionID, LaneID, ParkingLotID};
 use sim::{
@@ -19,11 +19,13 @@ use crate::app::{App, Transition};
 use crate::common::{color_for_agent_type, hotkey_btn, Warping};
 use crate::debug::path_counter::PathCounter;
 use crate::edit::{EditMode, RouteEditor};
+use crate::info::follow::FollowState;
 use crate::sandbox::{dashboards, GameplayMode, SandboxMode, TimeWarpScreen};
 
 mod building;
 mod bus;
 mod debug;
+mod follow;
 mod intersection;
 mod lane;
 mod parking_lot;
@@ -45,6 +47,9 @@ pub struct InfoPanel {
 
     // For drawing the OSD only
     cached_actions: Vec<Key>,
+
+    // While the simulation runs, chase the person this panel is about
+    follow: Option<FollowState>,
 }
 
 #[derive(Clone)]
@@ -54,6 +59,7 @@ pub enum Tab {
     PersonTrips(PersonID, BTreeMap<TripID, OpenTrip>),
     PersonBio(PersonID),
     PersonSchedule(PersonID),
+    PersonDiary(PersonID),
 
     BusStatus(CarID),
     BusStop(BusStopID),
@@ -78,6 +84,7 @@ pub enum Tab {
     IntersectionDemand(IntersectionID),
     IntersectionArrivals(IntersectionID, DataOptions),
     IntersectionTrafficSignal(IntersectionID),
+    IntersectionCounts(IntersectionID),
 
     LaneInfo(LaneID),
     LaneDebug(LaneID),
@@ -125,6 +132,13 @@ impl Tab {
                         Tab::IntersectionInfo(i)
                     }
                 }
+                "counts" => {
+                    if app.primary.map.get_i(i).is_border() {
+                        Tab::IntersectionInfo(i)
+                    } else {
+                        Tab::IntersectionCounts(i)
+                    }
+                }
                 _ => unreachable!(),
             },
             ID::Building(b) => match app.session.info_panel_tab["bldg"] {
@@ -144,6 +158,7 @@ impl Tab {
                         ),
                         "bio" => Tab::PersonBio(p),
                         "schedule" => Tab::PersonSchedule(p),
+                        "diary" => Tab::PersonDiary(p),
                         _ => unreachable!(),
                     }
                 } else if c.1 == VehicleType::Bus || c.1 == VehicleType::Train {
@@ -173,6 +188,7 @@ impl Tab {
                     ),
                     "bio" => Tab::PersonBio(person),
                     "schedule" => Tab::PersonSchedule(person),
+                    "diary" => Tab::PersonDiary(person),
                     _ => unreachable!(),
                 }
             }
@@ -184,18 +200,19 @@ impl Tab {
 
     fn to_id(&self, app: &App) -> Option<ID> {
         match self {
-            Tab::PersonTrips(p, _) | Tab::PersonBio(p) | Tab::PersonSchedule(p) => {
-                match app.primary.sim.get_person(*p).state {
-                    PersonState::Inside(b) => Some(ID::Building(b)),
-                    PersonState::Trip(t) => app
-                        .primary
-                        .sim
-                        .trip_to_agent(t)
-                        .ok()
-                        .map(|a| ID::from_agent(a)),
-                    _ => None,
-                }
-            }
+            Tab::PersonTrips(p, _)
+            | Tab::PersonBio(p)
+            | Tab::PersonSchedule(p)
+            | Tab::PersonDiary(p) => match app.primary.sim.get_person(*p).state {
+                PersonState::Inside(b) => Some(ID::Building(b)),
+                PersonState::Trip(t) => app
+                    .primary
+                    .sim
+                    .trip_to_agent(t)
+                    .ok()
+                    .map(|a| ID::from_agent(a)),
+                _ => None,
+            },
             Tab::BusStatus(c) => Some(ID::Car(*c)),
             Tab::BusStop(bs) => Some(ID::BusStop(*bs)),
             Tab::BusRoute(_) => None,
@@ -215,11 +232,26 @@ impl Tab {
             | Tab::IntersectionDelay(i, _, _)
             | Tab::IntersectionDemand(i)
             | Tab::IntersectionArrivals(i, _)
-            | Tab::IntersectionTrafficSignal(i) => Some(ID::Intersection(*i)),
+            | Tab::IntersectionTrafficSignal(i)
+            | Tab::IntersectionCounts(i) => Some(ID::Intersection(*i)),
             Tab::LaneInfo(l) | Tab::LaneDebug(l) | Tab::LaneTraffic(l, _) => Some(ID::Lane(*l)),
         }
     }
 
+    /// The agent of the person this tab is about, if they're currently on a trip
+    fn following(&self, app: &App) -> Option<AgentID> {
+        match self {
+            Tab::PersonTrips(p, _)
+            | Tab::PersonBio(p)
+            | Tab::PersonSchedule(p)
+            | Tab::PersonDiary(p) => match app.primary.sim.get_person(*p).state {
+                PersonState::Trip(t) => app.primary.sim.trip_to_agent(t).ok(),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
     fn changed_settings(&self, c: &Panel) -> Option<Tab> {
         // Avoid an occasionally expensive clone.
         match self {
@@ -262,6 +294,7 @@ impl Tab {
             Tab::PersonTrips(_, _) => ("person", "trips"),
             Tab::PersonBio(_) => ("person", "bio"),
             Tab::PersonSchedule(_) => ("person", "schedule"),
+            Tab::PersonDiary(_) => ("person", "diary"),
             Tab::BusStatus(_) => ("bus", "status"),
             Tab::BusStop(_) => ("bus stop", "info"),
             Tab::BusRoute(_) => ("bus route", "info"),
@@ -277,6 +310,7 @@ impl Tab {
             Tab::IntersectionDemand(_) => ("intersection", "demand"),
             Tab::IntersectionArrivals(_, _) => ("intersection", "arrivals"),
             Tab::IntersectionTrafficSignal(_) => ("intersection", "traffic signal"),
+            Tab::IntersectionCounts(_) => ("intersection", "counts"),
             Tab::LaneInfo(_) => ("lane", "info"),
             Tab::LaneDebug(_) => ("lane", "debug"),
             Tab::LaneTraffic(_, _) => ("lane", "traffic"),
@@ -327,6 +361,10 @@ impl InfoPanel {
                 person::schedule(ctx, app, &mut details, p, ctx_actions.is_paused()),
                 false,
             ),
+            Tab::PersonDiary(p) => (
+                person::diary(ctx, app, &mut details, p, ctx_actions.is_paused()),
+                false,
+            ),
             Tab::BusStatus(c) => (bus::bus_status(ctx, app, &mut details, c), true),
             Tab::BusStop(bs) => (bus::stop(ctx, app, &mut details, bs), true),
             Tab::BusRoute(br) => (bus::route(ctx, app, &mut details, br), true),
@@ -360,6 +398,7 @@ impl InfoPanel {
                 intersection::traffic_signal(ctx, app, &mut details, i),
                 false,
             ),
+            Tab::IntersectionCounts(i) => (intersection::counts(ctx, app, &mut details, i), false),
             Tab::LaneInfo(l) => (lane::info(ctx, app, &mut details, l), true),
             Tab::LaneDebug(l) => (lane::debug(ctx, app, &mut details, l), false),
             Tab::LaneTraffic(l, ref opts) => {
@@ -447,6 +486,7 @@ impl InfoPanel {
             warpers: details.warpers,
             time_warpers: details.time_warpers,
             cached_actions,
+            follow: None,
         }
     }
 
@@ -465,10 +505,30 @@ impl InfoPanel {
             return (true, None);
         }
 
+        let following = if ctx_actions.is_paused() {
+            None
+        } else {
+            self.tab.following(app)
+        };
+        if let Some(agent) = following {
+            if self
+                .follow
+                .as_ref()
+                .map(|f| f.agent() != agent)
+                .unwrap_or(true)
+            {
+                self.follow = Some(FollowState::new(ctx, app, agent));
+            }
+            self.follow.as_mut().unwrap().event(ctx, app);
+        } else {
+            self.follow = None;
+        }
+
         // Live update?
         if app.primary.sim.time() != self.time || ctx_actions.is_paused() != self.is_paused {
             let mut new = InfoPanel::new(ctx, app, self.tab.clone(), ctx_actions);
             new.panel.restore(ctx, &self.panel);
+            new.follow = self.follow.take();
             *self = new;
             return (false, None);
         }
@@ -575,6 +635,25 @@ impl InfoPanel {
                             ctx, app,
                         ))),
                     )
+                } else if let Some(x) =
+                    action.strip_prefix("export turning movement counts for Intersection #")
+                {
+                    let i = IntersectionID(x.parse::<usize>().unwrap());
+                    (
+                        false,
+                        Some(Transition::Push(
+                            match intersection::export_counts(app, i) {
+                                Ok(path) => PopupMsg::new(
+                                    ctx,
+                                    "Data exported",
+                                    vec![format!("Data exported to {}", path)],
+                                ),
+                                Err(err) => {
+                                    PopupMsg::new(ctx, "Export failed", vec![err.to_string()])
+                                }
+                            },
+                        )),
+                    )
                 } else if let Some(x) = action.strip_prefix("routes across Intersection #") {
                     (
                         false,
@@ -621,6 +700,9 @@ impl InfoPanel {
         } else {
             g.redraw(&self.zoomed);
         }
+        if let Some(ref follow) = self.follow {
+            follow.draw(g);
+        }
     }
 
     pub fn active_keys(&self) -> &Vec<Key> {
@@ -5,10 +5,11 @@ use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
 
 use geom::{Angle, Duration, Time};
-use map_model::Map;
+use map_gui::ID;
+use map_model::{Map, Traversable};
 use sim::{
     AgentID, CarID, ParkingSpot, PedestrianID, Person, PersonID, PersonState, TripEndpoint, TripID,
-    TripMode, TripResult, VehicleType,
+    TripInfo, TripMode, TripResult, VehicleType,
 };
 use widgetry::{
     Btn, Color, EdgeInsets, EventCtx, GeomBatch, Key, Line, RewriteColor, Text, TextExt, TextSpan,
@@ -136,6 +137,7 @@ pub fn trips(
                         TripMode::Bike => "system/assets/meters/bike.svg",
                         TripMode::Drive => "system/assets/meters/car.svg",
                         TripMode::Transit => "system/assets/meters/bus.svg",
+                        TripMode::RideHail => "system/assets/meters/car.svg",
                     },
                 )
                 // we want the icon to be about the same height as the text
@@ -427,6 +429,133 @@ pub fn schedule(
     rows
 }
 
+/// Everything a person does during the day, comparing each trip to how long it'd take on empty
+/// roads.
+pub fn diary(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    id: PersonID,
+    is_paused: bool,
+) -> Vec<Widget> {
+    let mut rows = header(ctx, app, details, id, Tab::PersonDiary(id), is_paused);
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+
+    for (idx, t) in sim.get_person(id).trips.iter().enumerate() {
+        let trip = sim.trip_info(*t);
+        rows.push(
+            Line(format!(
+                "Trip {}: {} at {}",
+                idx + 1,
+                trip.mode.ongoing_verb(),
+                trip.departure.ampm_tostring()
+            ))
+            .small_heading()
+            .draw(ctx)
+            .margin_above(10),
+        );
+
+        let actual = match sim.finished_trip_details(*t) {
+            Some((total, waiting, dist)) => format!(
+                "{} ({} waiting), {}",
+                total.to_string(&app.opts.units),
+                waiting.to_string(&app.opts.units),
+                dist.to_string(&app.opts.units)
+            ),
+            None => match sim.trip_to_agent(*t) {
+                TripResult::TripNotStarted => "hasn't started".to_string(),
+                TripResult::TripCancelled => "cancelled".to_string(),
+                _ => "ongoing".to_string(),
+            },
+        };
+        let free_flow = match free_flow_time(&trip, map) {
+            Some(dt) => dt.to_string(&app.opts.units),
+            None => "unknown".to_string(),
+        };
+        rows.extend(make_table(
+            ctx,
+            vec![("Actual", actual), ("Free-flow", free_flow)],
+        ));
+
+        let (start_id, _, start_name) = trip::endpoint(&trip.start, app);
+        let (end_id, _, end_name) = trip::endpoint(&trip.end, app);
+        rows.push(diary_warper(
+            ctx,
+            app,
+            details,
+            format!("Trip {}: from {}", idx + 1, start_name),
+            start_id,
+        ));
+        for (leg, phase) in sim
+            .get_analytics()
+            .get_trip_phases(*t, map)
+            .into_iter()
+            .enumerate()
+        {
+            let id = match phase.path {
+                Some((_, ref path)) => match path.get_steps()[0].as_traversable() {
+                    Traversable::Lane(l) => ID::Lane(l),
+                    Traversable::Turn(t) => ID::Intersection(t.parent),
+                },
+                None => {
+                    continue;
+                }
+            };
+            rows.push(diary_warper(
+                ctx,
+                app,
+                details,
+                format!(
+                    "Trip {}, leg {}: {}",
+                    idx + 1,
+                    leg + 1,
+                    phase.phase_type.describe(map)
+                ),
+                id,
+            ));
+        }
+        rows.push(diary_warper(
+            ctx,
+            app,
+            details,
+            format!("Trip {}: to {}", idx + 1, end_name),
+            end_id,
+        ));
+    }
+
+    rows
+}
+
+fn diary_warper(
+    ctx: &mut EventCtx,
+    app: &App,
+    details: &mut Details,
+    name: String,
+    id: ID,
+) -> Widget {
+    let row = Widget::row(vec![
+        Btn::svg(
+            "system/assets/tools/pin.svg",
+            RewriteColor::Change(Color::hex("#CC4121"), app.cs.hovering),
+        )
+        .build(ctx, &name, None),
+        name.clone().draw_text(ctx),
+    ]);
+    details.warpers.insert(name, id);
+    row
+}
+
+/// For transit trips, this is the time to drive instead.
+fn free_flow_time(trip: &TripInfo, map: &Map) -> Option<Duration> {
+    let mode = if trip.mode == TripMode::Transit {
+        TripMode::Drive
+    } else {
+        trip.mode
+    };
+    TripEndpoint::free_flow_time(trip.start, trip.end, mode, map)
+}
+
 pub fn crowd(
     ctx: &EventCtx,
     app: &App,
@@ -476,11 +605,22 @@ pub fn parked_car(
     is_paused: bool,
 ) -> Vec<Widget> {
     let mut rows = vec![];
+    let ride_hail = app.primary.sim.describe_ride_hail_vehicle(id);
+    let shuttle = app.primary.sim.describe_shuttle(id);
+    let delivery = app.primary.sim.describe_delivery_vehicle(id);
 
     rows.push(Widget::row(vec![
-        Line(format!("Parked car #{}", id.0))
-            .small_heading()
-            .draw(ctx),
+        Line(if ride_hail.is_some() {
+            format!("Ride-hail vehicle #{}", id.0)
+        } else if shuttle.is_some() {
+            format!("Microtransit shuttle #{}", id.0)
+        } else if delivery.is_some() {
+            format!("Delivery vehicle #{}", id.0)
+        } else {
+            format!("Parked car #{}", id.0)
+        })
+        .small_heading()
+        .draw(ctx),
         Widget::row(vec![
             // Little indirect, but the handler of this action is actually the ContextualActions
             // for SandboxMode.
@@ -504,6 +644,12 @@ pub fn parked_car(
 
     // TODO prev trips, next trips, etc
 
+    // Nobody owns these
+    if let Some(status) = ride_hail.or(shuttle).or(delivery) {
+        rows.push(status.draw_text(ctx));
+        return rows;
+    }
+
     let p = app.primary.sim.get_owner_of_car(id).unwrap();
     rows.push(Btn::text_bg2(format!("Owned by {}", p)).build_def(ctx, None));
     details.hyperlinks.insert(
@@ -564,18 +710,27 @@ fn header(
         PersonState::Trip(t) => (
             Some(t),
             if let Some(a) = app.primary.sim.trip_to_agent(t).ok() {
-                if let Some(pt) = app.primary.sim.canonical_pt_for_agent(a, &app.primary.map) {
-                    ctx.canvas.center_on_map_pt(pt);
+                // While the simulation runs, the info panel's FollowState moves the camera
+                if is_paused {
+                    if let Some(pt) = app.primary.sim.canonical_pt_for_agent(a, &app.primary.map) {
+                        ctx.canvas.center_on_map_pt(pt);
+                    }
                 }
                 match a {
                     AgentID::Pedestrian(_) => {
                         ("walking", Some("system/assets/meters/pedestrian.svg"))
                     }
                     AgentID::Car(c) => match c.1 {
-                        VehicleType::Car => ("driving", Some("system/assets/meters/car.svg")),
+                        VehicleType::Car | VehicleType::Truck | VehicleType::Emergency => {
+                            ("driving", Some("system/assets/meters/car.svg"))
+                        }
                         VehicleType::Bike => ("biking", Some("system/assets/meters/bike.svg")),
                         VehicleType::Bus | VehicleType::Train => unreachable!(),
                     },
+                    AgentID::BusPassenger(_, c) if c.1 == VehicleType::Car => (
+                        "riding in a ride-hail vehicle",
+                        Some("system/assets/meters/car.svg"),
+                    ),
                     AgentID::BusPassenger(_, _) => {
                         ("riding a bus", Some("system/assets/meters/bus.svg"))
                     }
@@ -634,6 +789,7 @@ fn header(
     if app.opts.dev {
         tabs.push(("Schedule", Tab::PersonSchedule(id)));
     }
+    tabs.push(("Diary", Tab::PersonDiary(id)));
     rows.push(make_tabs(ctx, &mut details.hyperlinks, tab, tabs));
 
     rows
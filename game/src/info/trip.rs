@@ -306,6 +306,28 @@ pub fn finished(
             waiting.to_string(&app.opts.units).draw_text(ctx),
         ]));
 
+        if let Some(cruising) = app.primary.sim.get_analytics().parking_cruising.get(&id) {
+            col.push(Widget::custom_row(vec![
+                Widget::custom_row(vec![Line("Cruising for parking").secondary().draw(ctx)])
+                    .force_width_pct(ctx, col_width),
+                cruising.to_string(&app.opts.units).draw_text(ctx),
+            ]));
+        }
+
+        if let Some(emissions) = app.primary.sim.get_analytics().trip_emissions.get(&id) {
+            col.push(Widget::custom_row(vec![
+                Widget::custom_row(vec![Line("Emissions").secondary().draw(ctx)])
+                    .force_width_pct(ctx, col_width),
+                format!(
+                    "{:.1} kg CO2, {:.1} g NOx ({:.2} L fuel)",
+                    emissions.co2_grams / 1000.0,
+                    emissions.nox_grams,
+                    emissions.fuel_liters
+                )
+                .draw_text(ctx),
+            ]));
+        }
+
         col.push(Widget::custom_row(vec![
             Widget::custom_row(vec![Line("Purpose").secondary().draw(ctx)])
                 .force_width_pct(ctx, col_width),
@@ -549,6 +571,14 @@ fn make_timeline(
                     TripPhaseType::RidingBus(_, _, _) => "system/assets/timeline/riding_bus.svg",
                     TripPhaseType::Cancelled | TripPhaseType::Finished => unreachable!(),
                     TripPhaseType::DelayedStart => "system/assets/timeline/delayed_start.svg",
+                    TripPhaseType::WaitingForRideHail => {
+                        "system/assets/timeline/waiting_for_bus.svg"
+                    }
+                    TripPhaseType::RidingRideHail(_) => "system/assets/timeline/driving.svg",
+                    TripPhaseType::WaitingForGuideway(_) => {
+                        "system/assets/timeline/waiting_for_bus.svg"
+                    }
+                    TripPhaseType::RidingGuideway(_) => "system/assets/timeline/riding_bus.svg",
                 },
             )
             .centered_on(Pt2D::new(x1 + phase_width / 2.0, icon_height / 2.0)),
@@ -881,7 +911,7 @@ fn make_elevation(ctx: &EventCtx, color: Color, walking: bool, path: &Path, map:
 }
 
 // (ID, center, name)
-fn endpoint(endpt: &TripEndpoint, app: &App) -> (ID, Pt2D, String) {
+pub fn endpoint(endpt: &TripEndpoint, app: &App) -> (ID, Pt2D, String) {
     match endpt {
         TripEndpoint::Bldg(b) => {
             let bldg = app.primary.map.get_b(*b);
@@ -0,0 +1,254 @@
+use abstutil::Timer;
+use geom::{Circle, Duration, Time};
+use map_gui::render::unzoomed_agent_radius;
+use sim::{AgentTraces, AgentType, VehicleType};
+use widgetry::{
+    Btn, Choice, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome, Panel,
+    Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::App;
+use crate::layer::{Layer, LayerOutcome};
+
+/// How often to sample everybody's position when recording the whole run
+const RECORD_EVERYBODY_EVERY: Duration = Duration::const_seconds(30.0);
+
+/// Shows where people went in a recorded run as translucent ghosts, to compare against the live
+/// simulation. Also starts and saves recordings.
+pub struct Ghosts {
+    time: Time,
+    /// What's being recorded when the panel was made: None if nothing, otherwise how many people
+    /// (None for everybody).
+    recording: Option<Option<usize>>,
+    /// The name of the recording being played back
+    playback: Option<(String, AgentTraces)>,
+    draw: Drawable,
+    panel: Panel,
+}
+
+impl Layer for Ghosts {
+    fn name(&self) -> Option<&'static str> {
+        Some("ghosts")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            self.time = app.primary.sim.time();
+            self.draw = draw_ghosts(ctx, app, &self.playback);
+        }
+        if recording_status(app) != self.recording {
+            self.recording = recording_status(app);
+            self.panel = make_panel(ctx, app, &self.playback);
+        }
+
+        self.panel.align_above(ctx, minimap);
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                "record everybody" => {
+                    app.primary
+                        .sim
+                        .record_traces(&app.primary.map, None, RECORD_EVERYBODY_EVERY);
+                }
+                "stop and save" => {
+                    let name = self.panel.text_box("name");
+                    let traces = app.primary.sim.stop_recording_traces().unwrap();
+                    println!("Saved {}", traces.save(&name));
+                    return Some(LayerOutcome::Replace(Box::new(Ghosts::new(
+                        ctx,
+                        app,
+                        Some((name, traces)),
+                    ))));
+                }
+                "stop without saving" => {
+                    app.primary.sim.stop_recording_traces();
+                }
+                _ => unreachable!(),
+            },
+            Outcome::Changed => {
+                let choice: Option<String> = self.panel.dropdown_value("recording");
+                let playback = choice.and_then(|name| {
+                    let path = abstutil::path_trace(app.primary.map.get_name(), &name);
+                    match AgentTraces::load(path, &mut Timer::new("load recording")) {
+                        Ok(traces) => Some((name, traces)),
+                        Err(err) => {
+                            warn!("Couldn't load recording {}: {}", name, err);
+                            None
+                        }
+                    }
+                });
+                return Some(LayerOutcome::Replace(Box::new(Ghosts::new(
+                    ctx, app, playback,
+                ))));
+            }
+            _ => {}
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+        g.redraw(&self.draw);
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.draw);
+    }
+}
+
+impl Ghosts {
+    pub fn new(ctx: &mut EventCtx, app: &App, playback: Option<(String, AgentTraces)>) -> Ghosts {
+        Ghosts {
+            time: app.primary.sim.time(),
+            recording: recording_status(app),
+            draw: draw_ghosts(ctx, app, &playback),
+            panel: make_panel(ctx, app, &playback),
+            playback,
+        }
+    }
+}
+
+fn recording_status(app: &App) -> Option<Option<usize>> {
+    app.primary
+        .sim
+        .get_trace_recording()
+        .map(|traces| traces.people.as_ref().map(|people| people.len()))
+}
+
+fn draw_ghosts(
+    ctx: &mut EventCtx,
+    app: &App,
+    playback: &Option<(String, AgentTraces)>,
+) -> Drawable {
+    let mut batch = GeomBatch::new();
+    if let Some((_, ref traces)) = playback {
+        for ghost in traces.ghosts_at(app.primary.sim.time()) {
+            let (color, radius) = match ghost.agent_type {
+                AgentType::Car => (
+                    app.cs.unzoomed_car,
+                    unzoomed_agent_radius(Some(VehicleType::Car)),
+                ),
+                AgentType::Bike => (
+                    app.cs.unzoomed_bike,
+                    unzoomed_agent_radius(Some(VehicleType::Bike)),
+                ),
+                AgentType::Bus | AgentType::Train => (
+                    app.cs.unzoomed_bus,
+                    unzoomed_agent_radius(Some(VehicleType::Bus)),
+                ),
+                AgentType::Pedestrian | AgentType::TransitRider => {
+                    (app.cs.unzoomed_pedestrian, unzoomed_agent_radius(None))
+                }
+            };
+            batch.push(
+                color.alpha(0.4),
+                Circle::new(ghost.pos, radius).to_polygon(),
+            );
+        }
+    }
+    ctx.upload(batch)
+}
+
+fn make_panel(ctx: &mut EventCtx, app: &App, playback: &Option<(String, AgentTraces)>) -> Panel {
+    let mut col = vec![
+        Widget::row(vec![
+            Widget::draw_svg(ctx, "system/assets/tools/layers.svg"),
+            "Ghosts".draw_text(ctx),
+            Btn::close(ctx),
+        ]),
+        Text::from(Line(
+            "Record where people go in one run, then watch them as ghosts in another, like after \
+             editing the map.",
+        ))
+        .wrap_to_pct(ctx, 15)
+        .draw(ctx),
+    ];
+
+    match app.primary.sim.get_trace_recording() {
+        Some(traces) => {
+            col.push(
+                Text::from_multiline(vec![
+                    Line(format!(
+                        "Recording {} every {}",
+                        match traces.people {
+                            Some(ref people) => format!("{} people", people.len()),
+                            None => "everybody".to_string(),
+                        },
+                        traces.every
+                    )),
+                    Line(match traces.time_range() {
+                        Some((start, _)) => format!("Started at {}", start.ampm_tostring()),
+                        None => "Starting now".to_string(),
+                    })
+                    .secondary(),
+                ])
+                .draw(ctx),
+            );
+            col.push(Widget::row(vec![
+                "Name:".draw_text(ctx).centered_vert(),
+                Widget::text_entry(
+                    ctx,
+                    format!(
+                        "{}_{}",
+                        traces.edits_name,
+                        app.primary.sim.time().as_filename()
+                    ),
+                    false,
+                )
+                .named("name"),
+            ]));
+            col.push(Widget::row(vec![
+                Btn::text_bg2("stop and save").build_def(ctx, None),
+                Btn::text_fg("stop without saving").build_def(ctx, None),
+            ]));
+        }
+        None => {
+            col.push(
+                "To record somebody, select them and choose \"record this person's trace\""
+                    .draw_text(ctx),
+            );
+            col.push(Btn::text_fg("record everybody").build_def(ctx, None));
+        }
+    }
+
+    let mut choices = vec![Choice::new("none", None)];
+    for path in abstutil::list_dir(abstutil::path_all_traces(app.primary.map.get_name())) {
+        let name = abstutil::basename(path);
+        choices.push(Choice::new(name.clone(), Some(name)));
+    }
+    col.push(Widget::row(vec![
+        "Show ghosts from:".draw_text(ctx).centered_vert(),
+        Widget::dropdown(
+            ctx,
+            "recording",
+            playback.as_ref().map(|(name, _)| name.clone()),
+            choices,
+        ),
+    ]));
+    if let Some((_, ref traces)) = playback {
+        let mut txt = Text::new();
+        txt.add(Line(format!(
+            "Recorded with {}, every {}",
+            traces.edits_name, traces.every
+        )));
+        if let Some((start, end)) = traces.time_range() {
+            txt.add(
+                Line(format!(
+                    "From {} to {}",
+                    start.ampm_tostring(),
+                    end.ampm_tostring()
+                ))
+                .secondary(),
+            );
+        }
+        col.push(txt.draw(ctx));
+    }
+
+    Panel::new(Widget::col(col))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx)
+}
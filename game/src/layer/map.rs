@@ -2,7 +2,8 @@ use abstutil::{prettyprint_usize, Counter};
 use geom::{Distance, Time};
 use map_gui::tools::{amenity_type, ColorDiscrete, ColorLegend, ColorNetwork};
 use map_gui::ID;
-use map_model::{LaneType, PathConstraints};
+use map_model::connectivity::find_wheelchair_gaps;
+use map_model::{LaneType, PathConstraints, TrafficStress};
 use sim::AgentType;
 use widgetry::{
     Btn, Color, Drawable, EventCtx, GfxCtx, HorizontalAlignment, Line, Panel, Text, TextExt,
@@ -299,6 +300,86 @@ impl Static {
         )
     }
 
+    pub fn traffic_stress(ctx: &mut EventCtx, app: &App) -> Static {
+        let mut colorer = ColorDiscrete::new(
+            app,
+            vec![
+                (TrafficStress::Low.describe(), Color::hex("#1A9641")),
+                (TrafficStress::Moderate.describe(), Color::hex("#A6D96A")),
+                (TrafficStress::High.describe(), Color::hex("#FDAE61")),
+                (TrafficStress::Extreme.describe(), Color::hex("#D7191C")),
+            ],
+        );
+        for l in app.primary.map.all_lanes() {
+            if let Some(stress) = l.traffic_stress(&app.primary.map) {
+                colorer.add_l(l.id, stress.describe());
+            }
+        }
+        Static::new(
+            ctx,
+            colorer,
+            "bike traffic stress",
+            "Level of traffic stress for biking".to_string(),
+            Widget::nothing(),
+        )
+    }
+
+    pub fn wheelchair_access(ctx: &mut EventCtx, app: &App) -> Static {
+        let map = &app.primary.map;
+        let mut colorer = ColorDiscrete::new(
+            app,
+            vec![
+                ("too narrow", Color::RED),
+                ("rough surface", Color::ORANGE),
+                ("too steep", Color::PURPLE),
+                ("steps", Color::BLACK),
+                ("tagged inaccessible", Color::PINK),
+                ("no curb ramps", Color::YELLOW),
+                ("cut off", Color::CYAN),
+            ],
+        );
+        let mut num_barriers = 0;
+        for l in map.all_lanes() {
+            if let Some(barrier) = l.wheelchair_barrier(map) {
+                colorer.add_l(l.id, barrier.category());
+                num_barriers += 1;
+            }
+        }
+        let mut num_crossings = 0;
+        for i in map.all_intersections() {
+            if i.crosswalks.iter().any(|c| c.curb_ramps == Some(false)) {
+                colorer.add_i(i.id, "no curb ramps");
+                num_crossings += 1;
+            }
+        }
+        let gaps = find_wheelchair_gaps(map);
+        for l in &gaps {
+            colorer.add_l(*l, "cut off");
+        }
+
+        Static::new(
+            ctx,
+            colorer,
+            "wheelchair access",
+            "Wheelchair access".to_string(),
+            Text::from_multiline(vec![
+                Line(format!(
+                    "{} sidewalks and paths are inaccessible",
+                    prettyprint_usize(num_barriers)
+                )),
+                Line(format!(
+                    "{} intersections have crossings without curb ramps",
+                    prettyprint_usize(num_crossings)
+                )),
+                Line(format!(
+                    "{} more are cut off from the rest",
+                    prettyprint_usize(gaps.len())
+                )),
+            ])
+            .draw(ctx),
+        )
+    }
+
     pub fn blackholes(ctx: &mut EventCtx, app: &App) -> Static {
         let mut colorer = ColorDiscrete::new(
             app,
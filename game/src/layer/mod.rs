@@ -8,6 +8,7 @@ use crate::common::hotkey_btn;
 use crate::sandbox::dashboards;
 
 mod elevation;
+mod ghosts;
 pub mod map;
 mod pandemic;
 mod parking;
@@ -107,15 +108,21 @@ impl PickLayer {
                     btn("delay", Key::D),
                     btn("throughput", Key::T),
                     btn("traffic jams", Key::J),
+                    btn("queue spillback", Key::Q),
+                    btn("sidewalk crowding", Key::W),
+                    btn("emissions", Key::G),
+                    btn("safety", Key::K),
                 ]),
                 Widget::col(vec![
                     "Map".draw_text(ctx),
                     btn("map edits", Key::E),
                     btn("parking occupancy", Key::P),
                     btn("bike network", Key::B),
+                    btn("bike traffic stress", Key::F),
                     btn("transit network", Key::U),
                     btn("population map", Key::X),
                     btn("no sidewalks", Key::S),
+                    btn("wheelchair access", Key::H),
                 ]),
             ])
             .evenly_spaced(),
@@ -131,6 +138,7 @@ impl PickLayer {
                     btn("parking efficiency", Key::O),
                     btn("blackholes", Key::L),
                     btn("congestion caps", Key::C),
+                    btn("ghosts", Key::I),
                     if app.primary.sim.get_pandemic_model().is_some() {
                         btn("pandemic model", Key::Y)
                     } else {
@@ -141,6 +149,7 @@ impl PickLayer {
                     "Data".draw_text(ctx),
                     btn("traffic signal demand", Key::M),
                     btn("commuter patterns", Key::R),
+                    btn("parking over time", Key::Num1),
                 ]),
             ])
             .evenly_spaced(),
@@ -168,12 +177,18 @@ impl State<App> for PickLayer {
                 "backpressure" => {
                     app.primary.layer = Some(Box::new(traffic::Backpressure::new(ctx, app)));
                 }
+                "bike traffic stress" => {
+                    app.primary.layer = Some(Box::new(map::Static::traffic_stress(ctx, app)));
+                }
                 "bike network" => {
                     app.primary.layer = Some(Box::new(map::BikeNetwork::new(ctx, app)));
                 }
                 "delay" => {
                     app.primary.layer = Some(Box::new(traffic::Delay::new(ctx, app)));
                 }
+                "emissions" => {
+                    app.primary.layer = Some(Box::new(traffic::Emissions::new(ctx, app)));
+                }
                 "elevation" => {
                     app.primary.layer = Some(Box::new(elevation::Elevation::new(ctx, app)));
                 }
@@ -183,6 +198,9 @@ impl State<App> for PickLayer {
                 "no sidewalks" => {
                     app.primary.layer = Some(Box::new(map::Static::no_sidewalks(ctx, app)));
                 }
+                "wheelchair access" => {
+                    app.primary.layer = Some(Box::new(map::Static::wheelchair_access(ctx, app)));
+                }
                 "pandemic model" => {
                     app.primary.layer = Some(Box::new(pandemic::Pandemic::new(
                         ctx,
@@ -199,11 +217,18 @@ impl State<App> for PickLayer {
                 "congestion caps" => {
                     app.primary.layer = Some(Box::new(map::CongestionCaps::new(ctx, app)));
                 }
+                "ghosts" => {
+                    app.primary.layer = Some(Box::new(ghosts::Ghosts::new(ctx, app, None)));
+                }
                 "parking occupancy" => {
                     app.primary.layer = Some(Box::new(parking::Occupancy::new(
                         ctx, app, true, true, true, false, true,
                     )));
                 }
+                "parking over time" => {
+                    app.primary.layer =
+                        Some(Box::new(parking::OccupancyOverTime::new(ctx, app, None)));
+                }
                 "parking efficiency" => {
                     app.primary.layer = Some(Box::new(parking::Efficiency::new(ctx, app)));
                 }
@@ -217,11 +242,20 @@ impl State<App> for PickLayer {
                     )));
                 }
                 "throughput" => {
-                    app.primary.layer = Some(Box::new(traffic::Throughput::new(ctx, app)));
+                    app.primary.layer = Some(Box::new(traffic::Throughput::new(ctx, app, (0, 24))));
+                }
+                "queue spillback" => {
+                    app.primary.layer = Some(Box::new(traffic::QueueSpillback::new(ctx, app)));
                 }
                 "traffic jams" => {
                     app.primary.layer = Some(Box::new(traffic::TrafficJams::new(ctx, app)));
                 }
+                "safety" => {
+                    app.primary.layer = Some(Box::new(traffic::Safety::new(ctx, app)));
+                }
+                "sidewalk crowding" => {
+                    app.primary.layer = Some(Box::new(traffic::SidewalkCrowding::new(ctx, app)));
+                }
                 "transit network" => {
                     app.primary.layer = Some(Box::new(transit::TransitNetwork::new(
                         ctx, app, false, true, true,
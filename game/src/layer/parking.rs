@@ -1,4 +1,6 @@
 use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Write;
 
 use abstutil::{prettyprint_usize, Counter, Parallelism};
 use geom::{Circle, Distance, Duration, Pt2D, Time};
@@ -7,10 +9,10 @@ use map_gui::tools::{ColorLegend, ColorNetwork};
 use map_model::{
     BuildingID, Map, OffstreetParking, ParkingLotID, PathConstraints, PathRequest, RoadID,
 };
-use sim::{ParkingSpot, Scenario, VehicleType};
+use sim::{ParkingCounts, ParkingSpot, Scenario, VehicleType, PARKING_OCCUPANCY_EVERY};
 use widgetry::{
     Btn, Checkbox, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line, Outcome,
-    Panel, Text, TextExt, VerticalAlignment, Widget,
+    Panel, Slider, Text, TextExt, VerticalAlignment, Widget,
 };
 
 use crate::app::App;
@@ -319,6 +321,200 @@ impl Loc {
     }
 }
 
+/// Shows how full parking was at each count taken so far, to scrub through the day and compare
+/// with parking studies.
+pub struct OccupancyOverTime {
+    /// How many counts had been taken when the panel was made
+    num_counts: usize,
+    /// Which count is shown
+    current: usize,
+    capacity: ParkingCounts,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    panel: Panel,
+}
+
+impl Layer for OccupancyOverTime {
+    fn name(&self) -> Option<&'static str> {
+        Some("parking over time")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        let num_counts = app.primary.sim.get_analytics().parking_occupancy.len();
+        if num_counts != self.num_counts {
+            // Keep following the latest count while the simulation runs
+            let current = if self.current + 1 >= self.num_counts {
+                None
+            } else {
+                Some(self.current)
+            };
+            *self = OccupancyOverTime::new(ctx, app, current);
+        }
+
+        self.panel.align_above(ctx, minimap);
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Some(LayerOutcome::Close);
+                }
+                "export to CSV" => match export_occupancy(app) {
+                    Ok(path) => println!("Exported parking occupancy to {}", path),
+                    Err(err) => warn!("Couldn't export parking occupancy: {}", err),
+                },
+                _ => unreachable!(),
+            },
+            _ => {}
+        }
+
+        if self.num_counts > 1 {
+            let percent = self.panel.slider("time slider").get_percent();
+            let idx = (percent * ((self.num_counts - 1) as f64)).round() as usize;
+            if idx != self.current {
+                self.current = idx;
+                let (time, ref filled) = app.primary.sim.get_analytics().parking_occupancy[idx];
+                let (unzoomed, zoomed) = draw_occupancy(ctx, app, &self.capacity, filled);
+                self.unzoomed = unzoomed;
+                self.zoomed = zoomed;
+                self.panel.replace(
+                    ctx,
+                    "time",
+                    Line(time.ampm_tostring()).draw(ctx).named("time"),
+                );
+            }
+        }
+        None
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl OccupancyOverTime {
+    /// Shows the `current` count, or the latest if None.
+    pub fn new(ctx: &mut EventCtx, app: &App, current: Option<usize>) -> OccupancyOverTime {
+        let counts = &app.primary.sim.get_analytics().parking_occupancy;
+        let num_counts = counts.len();
+        let current = current
+            .unwrap_or(num_counts.saturating_sub(1))
+            .min(num_counts.saturating_sub(1));
+        let capacity = app.primary.sim.get_parking_capacity();
+
+        let mut col = vec![
+            Widget::row(vec![
+                Widget::draw_svg(ctx, "system/assets/tools/layers.svg"),
+                "Parking over time".draw_text(ctx),
+                Btn::close(ctx),
+            ]),
+            Text::from(
+                Line(format!(
+                    "Percent of spots filled on each side of the street, in each building, and in \
+                     each lot. Spots are counted every {}.",
+                    PARKING_OCCUPANCY_EVERY
+                ))
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .draw(ctx),
+        ];
+        let (unzoomed, zoomed) = if app.primary.sim.infinite_parking() {
+            col.push("Parking simulation disabled.".draw_text(ctx));
+            (Drawable::empty(ctx), Drawable::empty(ctx))
+        } else if num_counts == 0 {
+            col.push("Nothing counted yet. Run the simulation first.".draw_text(ctx));
+            (Drawable::empty(ctx), Drawable::empty(ctx))
+        } else {
+            if num_counts > 1 {
+                col.push(
+                    Slider::area(
+                        ctx,
+                        0.15 * ctx.canvas.window_width,
+                        (current as f64) / ((num_counts - 1) as f64),
+                    )
+                    .named("time slider"),
+                );
+            }
+            col.push(
+                Line(counts[current].0.ampm_tostring())
+                    .draw(ctx)
+                    .named("time"),
+            );
+            col.push(ColorLegend::gradient(
+                ctx,
+                &app.cs.good_to_bad_red,
+                vec!["0%", "100%"],
+            ));
+            col.push(Btn::text_fg("export to CSV").build_def(ctx, None));
+            draw_occupancy(ctx, app, &capacity, &counts[current].1)
+        };
+
+        OccupancyOverTime {
+            num_counts,
+            current,
+            capacity,
+            unzoomed,
+            zoomed,
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+                .build(ctx),
+        }
+    }
+}
+
+fn draw_occupancy(
+    ctx: &mut EventCtx,
+    app: &App,
+    capacity: &ParkingCounts,
+    filled: &ParkingCounts,
+) -> (Drawable, Drawable) {
+    let color = |filled: Option<&usize>, capacity: &usize| {
+        app.cs
+            .good_to_bad_red
+            .eval((filled.cloned().unwrap_or(0) as f64) / (*capacity as f64))
+    };
+    let mut colorer = ColorNetwork::new(app);
+    for (l, cap) in &capacity.onstreet {
+        colorer.add_l(*l, color(filled.onstreet.get(l), cap));
+    }
+    for (b, cap) in &capacity.offstreet {
+        colorer.add_b(*b, color(filled.offstreet.get(b), cap));
+    }
+    for (pl, cap) in &capacity.lots {
+        colorer.add_pl(*pl, color(filled.lots.get(pl), cap));
+    }
+    colorer.build(ctx)
+}
+
+/// Writes every count of filled parking spots so far, returning the path.
+fn export_occupancy(app: &App) -> Result<String, std::io::Error> {
+    let path = format!(
+        "parking_occupancy_{}_{}.csv",
+        app.primary.map.get_name().as_filename(),
+        app.primary.sim.time().as_filename()
+    );
+    let mut f = File::create(&path)?;
+    write!(
+        f,
+        "{}",
+        app.primary
+            .sim
+            .get_analytics()
+            .parking_occupancy_csv(&app.primary.sim.get_parking_capacity(), &app.primary.map)
+    )?;
+    Ok(path)
+}
+
 pub struct Efficiency {
     time: Time,
     unzoomed: Drawable,
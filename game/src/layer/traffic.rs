@@ -1,17 +1,18 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
+use instant::Instant;
 use maplit::btreeset;
 
 use abstutil::{prettyprint_usize, Counter};
-use geom::{Circle, Distance, Duration, Polygon, Pt2D, Time};
+use geom::{Circle, Distance, Duration, PolyLine, Polygon, Pt2D, Time};
 use map_gui::render::unzoomed_agent_radius;
 use map_gui::tools::{ColorLegend, ColorNetwork, DivergingScale};
 use map_gui::ID;
-use map_model::{IntersectionID, Map, Traversable};
+use map_model::{IntersectionID, LaneID, Map, RoadID, Traversable, TurnID};
 use sim::VehicleType;
 use widgetry::{
     Btn, Checkbox, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Line,
-    Outcome, Panel, Text, TextExt, VerticalAlignment, Widget,
+    Outcome, Panel, Spinner, Text, TextExt, UpdateType, VerticalAlignment, Widget,
 };
 
 use crate::app::App;
@@ -105,9 +106,449 @@ impl Backpressure {
     }
 }
 
+pub struct Emissions {
+    time: Time,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    panel: Panel,
+}
+
+impl Layer for Emissions {
+    fn name(&self) -> Option<&'static str> {
+        Some("emissions")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            *self = Emissions::new(ctx, app);
+        }
+
+        Layer::simple_event(ctx, minimap, &mut self.panel)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl Emissions {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Emissions {
+        let mut co2_per_road = Counter::new();
+        let mut total = sim::Emissions::ZERO;
+        for (r, emissions) in &app.primary.sim.get_analytics().road_emissions {
+            co2_per_road.add(*r, emissions.co2_grams as usize);
+            total += *emissions;
+        }
+
+        let panel = Panel::new(Widget::col(vec![
+            Widget::row(vec![
+                Widget::draw_svg(ctx, "system/assets/tools/layers.svg"),
+                "Emissions".draw_text(ctx),
+                Btn::close(ctx),
+            ]),
+            Text::from(
+                Line("This estimates the CO2 emitted by vehicles on each road so far").secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .draw(ctx),
+            format!(
+                "{} kg CO2 and {} kg NOx total",
+                prettyprint_usize((total.co2_grams / 1000.0) as usize),
+                prettyprint_usize((total.nox_grams / 1000.0) as usize)
+            )
+            .draw_text(ctx),
+            ColorLegend::gradient(ctx, &app.cs.good_to_bad_red, vec!["lowest", "highest"]),
+        ]))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        let mut colorer = ColorNetwork::new(app);
+        colorer.pct_roads(co2_per_road, &app.cs.good_to_bad_red);
+        let (unzoomed, zoomed) = colorer.build(ctx);
+
+        Emissions {
+            time: app.primary.sim.time(),
+            unzoomed,
+            zoomed,
+            panel,
+        }
+    }
+}
+
+pub struct Safety {
+    time: Time,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    panel: Panel,
+}
+
+impl Layer for Safety {
+    fn name(&self) -> Option<&'static str> {
+        Some("safety")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            *self = Safety::new(ctx, app);
+        }
+
+        Layer::simple_event(ctx, minimap, &mut self.panel)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl Safety {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Safety {
+        let stats = app.primary.sim.get_safety_stats();
+        let (roads, intersections) = stats.per_location(&app.primary.map);
+
+        let mut col = vec![
+            Widget::row(vec![
+                Widget::draw_svg(ctx, "system/assets/tools/layers.svg"),
+                "Safety".draw_text(ctx),
+                Btn::close(ctx),
+            ]),
+            Text::from(
+                Line(
+                    "Near misses so far: hard braking, conflicting turns close together, and \
+                     close passes of cyclists",
+                )
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .draw(ctx),
+        ];
+        for (kind, count) in stats.per_kind() {
+            col.push(format!("{}: {}", kind, prettyprint_usize(count)).draw_text(ctx));
+        }
+        col.push(ColorLegend::gradient(
+            ctx,
+            &app.cs.good_to_bad_red,
+            vec!["fewest", "most"],
+        ));
+        let panel = Panel::new(Widget::col(col))
+            .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+            .build(ctx);
+
+        let mut colorer = ColorNetwork::new(app);
+        colorer.pct_roads(roads, &app.cs.good_to_bad_red);
+        colorer.pct_intersections(intersections, &app.cs.good_to_bad_red);
+        let (unzoomed, zoomed) = colorer.build(ctx);
+
+        Safety {
+            time: app.primary.sim.time(),
+            unzoomed,
+            zoomed,
+            panel,
+        }
+    }
+}
+
+pub struct SidewalkCrowding {
+    time: Time,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    panel: Panel,
+}
+
+impl Layer for SidewalkCrowding {
+    fn name(&self) -> Option<&'static str> {
+        Some("sidewalk crowding")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            *self = SidewalkCrowding::new(ctx, app);
+        }
+
+        Layer::simple_event(ctx, minimap, &mut self.panel)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+        } else {
+            g.redraw(&self.zoomed);
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl SidewalkCrowding {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> SidewalkCrowding {
+        // Past 2 people per square meter, walking is noticeably slower
+        let max_density = 2.0;
+
+        let mut colorer = ColorNetwork::new(app);
+        for (l, density) in app.primary.sim.get_sidewalk_densities(&app.primary.map) {
+            colorer.add_l(
+                l,
+                app.cs
+                    .good_to_bad_red
+                    .eval((density / max_density).min(1.0)),
+            );
+        }
+        let (unzoomed, zoomed) = colorer.build(ctx);
+
+        let panel = Panel::new(Widget::col(vec![
+            Widget::row(vec![
+                Widget::draw_svg(ctx, "system/assets/tools/layers.svg"),
+                "Sidewalk crowding".draw_text(ctx),
+                Btn::close(ctx),
+            ]),
+            Text::from(Line("People per square meter of sidewalk").secondary())
+                .wrap_to_pct(ctx, 15)
+                .draw(ctx),
+            ColorLegend::gradient(ctx, &app.cs.good_to_bad_red, vec!["0", "1", "2+"]),
+        ]))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        SidewalkCrowding {
+            time: app.primary.sim.time(),
+            unzoomed,
+            zoomed,
+            panel,
+        }
+    }
+}
+
+/// Lanes at least this full count as full, since another vehicle usually won't fit
+const FULL_QUEUE: f64 = 0.9;
+const SPILLBACK_FRAMES: usize = 8;
+const SPILLBACK_PERIOD_SECONDS: f64 = 1.0;
+const SPILLBACK_DOT_SPACING: Distance = Distance::const_meters(3.0);
+/// How far back along the blocked lane to animate
+const SPILLBACK_TAIL: Distance = Distance::const_meters(20.0);
+
+// Colors lanes by how full their queue is. Where vehicles wait to turn from one full lane onto
+// another full lane, the queue has spilled back across the intersection; this is animated.
+pub struct QueueSpillback {
+    time: Time,
+    unzoomed: Drawable,
+    zoomed: Drawable,
+    // (unzoomed, zoomed) for each frame of the animation
+    frames: Vec<(Drawable, Drawable)>,
+    started: Instant,
+    panel: Panel,
+}
+
+impl Layer for QueueSpillback {
+    fn name(&self) -> Option<&'static str> {
+        Some("queue spillback")
+    }
+    fn event(
+        &mut self,
+        ctx: &mut EventCtx,
+        app: &mut App,
+        minimap: &Panel,
+    ) -> Option<LayerOutcome> {
+        if app.primary.sim.time() != self.time {
+            let started = self.started;
+            *self = QueueSpillback::new(ctx, app);
+            self.started = started;
+        }
+        if !self.frames.is_empty() {
+            ctx.request_update(UpdateType::Game);
+        }
+
+        Layer::simple_event(ctx, minimap, &mut self.panel)
+    }
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        self.panel.draw(g);
+        let frame = self.current_frame();
+        if g.canvas.cam_zoom < app.opts.min_zoom_for_detail {
+            g.redraw(&self.unzoomed);
+            if let Some((unzoomed, _)) = frame {
+                g.redraw(unzoomed);
+            }
+        } else {
+            g.redraw(&self.zoomed);
+            if let Some((_, zoomed)) = frame {
+                g.redraw(zoomed);
+            }
+        }
+    }
+    fn draw_minimap(&self, g: &mut GfxCtx) {
+        g.redraw(&self.unzoomed);
+    }
+}
+
+impl QueueSpillback {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> QueueSpillback {
+        let map = &app.primary.map;
+        let occupancy = app.primary.sim.get_queue_occupancy();
+
+        // Unzoomed, show the fullest lane of each road
+        let mut per_road: BTreeMap<RoadID, f64> = BTreeMap::new();
+        for (l, pct) in &occupancy {
+            let max = per_road.entry(map.get_l(*l).parent).or_insert(0.0);
+            *max = max.max(*pct);
+        }
+        let mut colorer = ColorNetwork::new(app);
+        for (r, pct) in per_road {
+            colorer.unzoomed.push(
+                app.cs.good_to_bad_red.eval(pct),
+                map.get_r(r).get_thick_polygon(map),
+            );
+        }
+        for (l, pct) in &occupancy {
+            let lane = map.get_l(*l);
+            colorer.zoomed.push(
+                app.cs.good_to_bad_red.eval(*pct).alpha(0.4),
+                lane.lane_center_pts.make_polygons(lane.width),
+            );
+        }
+        let (unzoomed, zoomed) = colorer.build(ctx);
+
+        let full = |l: LaneID| {
+            occupancy
+                .get(&l)
+                .map(|pct| *pct >= FULL_QUEUE)
+                .unwrap_or(false)
+        };
+        let num_full = occupancy.values().filter(|pct| **pct >= FULL_QUEUE).count();
+        let mut spillback: BTreeSet<TurnID> = BTreeSet::new();
+        for i in map.all_intersections() {
+            for (_, t, _) in app.primary.sim.get_waiting_agents(i.id) {
+                if full(t.src) && full(t.dst) {
+                    spillback.insert(t);
+                }
+            }
+        }
+        let intersections: BTreeSet<IntersectionID> = spillback.iter().map(|t| t.parent).collect();
+
+        // Animate against the direction of traffic, from the full lane back through the turn and
+        // onto the end of the lane it's blocking.
+        let mut paths: Vec<PolyLine> = Vec::new();
+        for t in &spillback {
+            paths.push(map.get_t(*t).geom.reversed());
+            let src = map.get_l(t.src);
+            let len = src.length();
+            let start = if len > SPILLBACK_TAIL {
+                len - SPILLBACK_TAIL
+            } else {
+                Distance::ZERO
+            };
+            paths.push(src.lane_center_pts.exact_slice(start, len).reversed());
+        }
+        let mut frames = Vec::new();
+        if !spillback.is_empty() {
+            for frame in 0..SPILLBACK_FRAMES {
+                let phase = (frame as f64) / (SPILLBACK_FRAMES as f64);
+                let mut unzoomed = GeomBatch::new();
+                let mut zoomed = GeomBatch::new();
+                // Ripples spreading out from each intersection
+                for i in &intersections {
+                    if let Ok(ripple) = Circle::new(
+                        map.get_i(*i).polygon.center(),
+                        Distance::meters(10.0 + 30.0 * phase),
+                    )
+                    .to_outline(Distance::meters(3.0))
+                    {
+                        unzoomed.push(Color::RED.alpha(1.0 - phase), ripple);
+                    }
+                }
+                for pl in &paths {
+                    let mut dist = SPILLBACK_DOT_SPACING * phase;
+                    while dist < pl.length() {
+                        if let Ok((pt, _)) = pl.dist_along(dist) {
+                            zoomed.push(
+                                Color::RED,
+                                Circle::new(pt, Distance::meters(0.6)).to_polygon(),
+                            );
+                        }
+                        dist += SPILLBACK_DOT_SPACING;
+                    }
+                }
+                frames.push((ctx.upload(unzoomed), ctx.upload(zoomed)));
+            }
+        }
+
+        let panel = Panel::new(Widget::col(vec![
+            Widget::row(vec![
+                Widget::draw_svg(ctx, "system/assets/tools/layers.svg"),
+                "Queue spillback".draw_text(ctx),
+                Btn::close(ctx),
+            ]),
+            Text::from(
+                Line(
+                    "How much of each lane is claimed by queued vehicles. When a full lane blocks \
+                     vehicles waiting on another full lane, the queue spills back across the \
+                     intersection.",
+                )
+                .secondary(),
+            )
+            .wrap_to_pct(ctx, 15)
+            .draw(ctx),
+            ColorLegend::gradient(ctx, &app.cs.good_to_bad_red, vec!["empty", "half", "full"]),
+            format!(
+                "{} full lanes, spilling back across {} intersections",
+                prettyprint_usize(num_full),
+                prettyprint_usize(intersections.len())
+            )
+            .draw_text(ctx),
+        ]))
+        .aligned(HorizontalAlignment::Right, VerticalAlignment::Center)
+        .build(ctx);
+
+        QueueSpillback {
+            time: app.primary.sim.time(),
+            unzoomed,
+            zoomed,
+            frames,
+            started: Instant::now(),
+            panel,
+        }
+    }
+
+    fn current_frame(&self) -> Option<&(Drawable, Drawable)> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let cycles =
+            Duration::realtime_elapsed(self.started).inner_seconds() / SPILLBACK_PERIOD_SECONDS;
+        let idx = (cycles.fract() * (self.frames.len() as f64)) as usize;
+        self.frames.get(idx)
+    }
+}
+
 // TODO Filter by mode
 pub struct Throughput {
     time: Time,
+    // Only count hours in [start, end)
+    hours: (usize, usize),
     tooltip: Option<Text>,
     unzoomed: Drawable,
     zoomed: Drawable,
@@ -126,7 +567,7 @@ impl Layer for Throughput {
     ) -> Option<LayerOutcome> {
         let mut recalc_tooltip = false;
         if app.primary.sim.time() != self.time {
-            *self = Throughput::new(ctx, app);
+            *self = Throughput::new(ctx, app, self.hours);
             recalc_tooltip = true;
         }
 
@@ -134,25 +575,24 @@ impl Layer for Throughput {
         if ctx.canvas.cam_zoom < app.opts.min_zoom_for_detail {
             if ctx.redo_mouseover() || recalc_tooltip {
                 self.tooltip = None;
-                match app.mouseover_unzoomed_roads_and_intersections(ctx) {
-                    Some(ID::Road(r)) => {
-                        let cnt = app.primary.sim.get_analytics().road_thruput.total_for(r);
-                        if cnt > 0 {
-                            self.tooltip = Some(Text::from(Line(prettyprint_usize(cnt))));
-                        }
-                    }
-                    Some(ID::Intersection(i)) => {
-                        let cnt = app
-                            .primary
-                            .sim
-                            .get_analytics()
-                            .intersection_thruput
-                            .total_for(i);
-                        if cnt > 0 {
-                            self.tooltip = Some(Text::from(Line(prettyprint_usize(cnt))));
-                        }
-                    }
-                    _ => {}
+                let (start, end) = self.hours;
+                let cnt = match app.mouseover_unzoomed_roads_and_intersections(ctx) {
+                    Some(ID::Road(r)) => app
+                        .primary
+                        .sim
+                        .get_analytics()
+                        .road_thruput
+                        .total_for_between(r, start, end),
+                    Some(ID::Intersection(i)) => app
+                        .primary
+                        .sim
+                        .get_analytics()
+                        .intersection_thruput
+                        .total_for_between(i, start, end),
+                    _ => 0,
+                };
+                if cnt > 0 {
+                    self.tooltip = Some(Text::from(Line(prettyprint_usize(cnt))));
                 }
             }
         } else {
@@ -168,9 +608,14 @@ impl Layer for Throughput {
                 _ => unreachable!(),
             },
             Outcome::Changed => {
-                return Some(LayerOutcome::Replace(Box::new(CompareThroughput::new(
-                    ctx, app,
-                ))));
+                let hours = hours_from_controls(&self.panel);
+                if self.panel.maybe_is_checked("Compare before proposal") == Some(true) {
+                    return Some(LayerOutcome::Replace(Box::new(CompareThroughput::new(
+                        ctx, app, hours,
+                    ))));
+                }
+                *self = Throughput::new(ctx, app, hours);
+                self.panel.align_above(ctx, minimap);
             }
             _ => {}
         }
@@ -193,19 +638,24 @@ impl Layer for Throughput {
 }
 
 impl Throughput {
-    pub fn new(ctx: &mut EventCtx, app: &App) -> Throughput {
+    /// Counts everybody crossing each road and intersection during the hours [start, end).
+    /// (0, 24) covers the whole day.
+    pub fn new(ctx: &mut EventCtx, app: &App, hours: (usize, usize)) -> Throughput {
         let stats = &app.primary.sim.get_analytics();
-        let road_counter = stats.road_thruput.all_total_counts();
-        let intersection_counter = stats.intersection_thruput.all_total_counts();
+        let road_counter = stats.road_thruput.total_counts_between(hours.0, hours.1);
+        let intersection_counter = stats
+            .intersection_thruput
+            .total_counts_between(hours.0, hours.1);
         let panel = Panel::new(Widget::col(vec![
             Widget::row(vec![
                 Widget::draw_svg(ctx, "system/assets/tools/layers.svg"),
                 "Throughput".draw_text(ctx),
                 Btn::close(ctx),
             ]),
-            Text::from(Line("This counts all people crossing since midnight").secondary())
+            Text::from(Line(describe_hours("This counts all people crossing", hours)).secondary())
                 .wrap_to_pct(ctx, 15)
                 .draw(ctx),
+            hour_controls(ctx, hours),
             if app.has_prebaked().is_some() {
                 Checkbox::switch(ctx, "Compare before proposal", None, false)
             } else {
@@ -223,6 +673,7 @@ impl Throughput {
 
         Throughput {
             time: app.primary.sim.time(),
+            hours,
             tooltip: None,
             unzoomed,
             zoomed,
@@ -233,6 +684,7 @@ impl Throughput {
 
 pub struct CompareThroughput {
     time: Time,
+    hours: (usize, usize),
     unzoomed: Drawable,
     zoomed: Drawable,
     panel: Panel,
@@ -249,7 +701,7 @@ impl Layer for CompareThroughput {
         minimap: &Panel,
     ) -> Option<LayerOutcome> {
         if app.primary.sim.time() != self.time {
-            *self = CompareThroughput::new(ctx, app);
+            *self = CompareThroughput::new(ctx, app, self.hours);
         }
 
         self.panel.align_above(ctx, minimap);
@@ -261,7 +713,14 @@ impl Layer for CompareThroughput {
                 _ => unreachable!(),
             },
             Outcome::Changed => {
-                return Some(LayerOutcome::Replace(Box::new(Throughput::new(ctx, app))));
+                let hours = hours_from_controls(&self.panel);
+                if !self.panel.is_checked("Compare before proposal") {
+                    return Some(LayerOutcome::Replace(Box::new(Throughput::new(
+                        ctx, app, hours,
+                    ))));
+                }
+                *self = CompareThroughput::new(ctx, app, hours);
+                self.panel.align_above(ctx, minimap);
             }
             _ => {}
         }
@@ -281,37 +740,24 @@ impl Layer for CompareThroughput {
 }
 
 impl CompareThroughput {
-    pub fn new(ctx: &mut EventCtx, app: &App) -> CompareThroughput {
+    /// Compares throughput during the hours [start, end) against the baseline run.
+    pub fn new(ctx: &mut EventCtx, app: &App, hours: (usize, usize)) -> CompareThroughput {
         let after = app.primary.sim.get_analytics();
         let before = app.prebaked();
-        let hour = app.primary.sim.time().get_hours();
+        // The baseline has the whole day, but only compare against the part simulated so far.
+        // TODO ew. lerp?
+        let before_end = hours.1.min(app.primary.sim.time().get_hours() + 1);
 
-        let mut after_road = Counter::new();
-        let mut before_road = Counter::new();
-        {
-            for ((r, _, _), count) in &after.road_thruput.counts {
-                after_road.add(*r, *count);
-            }
-            // TODO ew. lerp?
-            for ((r, _, hr), count) in &before.road_thruput.counts {
-                if *hr <= hour {
-                    before_road.add(*r, *count);
-                }
-            }
-        }
-        let mut after_intersection = Counter::new();
-        let mut before_intersection = Counter::new();
-        {
-            for ((i, _, _), count) in &after.intersection_thruput.counts {
-                after_intersection.add(*i, *count);
-            }
-            // TODO ew. lerp?
-            for ((i, _, hr), count) in &before.intersection_thruput.counts {
-                if *hr <= hour {
-                    before_intersection.add(*i, *count);
-                }
-            }
-        }
+        let after_road = after.road_thruput.total_counts_between(hours.0, hours.1);
+        let before_road = before
+            .road_thruput
+            .total_counts_between(hours.0, before_end);
+        let after_intersection = after
+            .intersection_thruput
+            .total_counts_between(hours.0, hours.1);
+        let before_intersection = before
+            .intersection_thruput
+            .total_counts_between(hours.0, before_end);
 
         let mut colorer = ColorNetwork::new(app);
 
@@ -336,6 +782,10 @@ impl CompareThroughput {
                 "Relative Throughput".draw_text(ctx),
                 Btn::close(ctx),
             ]),
+            Text::from(Line(describe_hours("This compares people crossing", hours)).secondary())
+                .wrap_to_pct(ctx, 15)
+                .draw(ctx),
+            hour_controls(ctx, hours),
             Checkbox::switch(ctx, "Compare before proposal", None, true),
             scale.make_legend(ctx, vec!["less traffic", "same", "more"]),
         ]))
@@ -345,6 +795,7 @@ impl CompareThroughput {
 
         CompareThroughput {
             time: app.primary.sim.time(),
+            hours,
             unzoomed,
             zoomed,
             panel,
@@ -352,6 +803,35 @@ impl CompareThroughput {
     }
 }
 
+fn describe_hours(prefix: &str, (start, end): (usize, usize)) -> String {
+    if start == 0 && end >= 24 {
+        format!("{} since midnight", prefix)
+    } else {
+        format!(
+            "{} from {} to {}",
+            prefix,
+            (Time::START_OF_DAY + Duration::hours(start)).ampm_tostring(),
+            (Time::START_OF_DAY + Duration::hours(end)).ampm_tostring()
+        )
+    }
+}
+
+fn hour_controls(ctx: &mut EventCtx, (start, end): (usize, usize)) -> Widget {
+    Widget::row(vec![
+        "From hour".draw_text(ctx).centered_vert(),
+        Spinner::new(ctx, (0, 23), start as isize).named("start hour"),
+        "to".draw_text(ctx).centered_vert(),
+        Spinner::new(ctx, (1, 24), end as isize).named("end hour"),
+    ])
+}
+
+fn hours_from_controls(panel: &Panel) -> (usize, usize) {
+    let start = panel.spinner("start hour") as usize;
+    // An empty window isn't useful; keep at least one hour
+    let end = (panel.spinner("end hour") as usize).max(start + 1);
+    (start, end)
+}
+
 pub struct TrafficJams {
     time: Time,
     unzoomed: Drawable,
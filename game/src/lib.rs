@@ -6,7 +6,7 @@ use map_gui::options::Options;
 use sim::SimFlags;
 use widgetry::{EventCtx, State};
 
-use crate::app::{App, Flags};
+use crate::app::{App, Flags, Transition};
 use crate::pregame::TitleScreen;
 use crate::sandbox::{GameplayMode, SandboxMode};
 
@@ -85,6 +85,9 @@ pub fn main() {
         ));
     }
     let start_with_edits = args.optional("--edits");
+    let record_video = args.optional("--record_video").map(|path| {
+        abstutil::read_json::<sandbox::CameraScript>(path, &mut abstutil::Timer::throwaway())
+    });
 
     args.done();
 
@@ -96,6 +99,7 @@ pub fn main() {
             start_with_edits,
             mode,
             initialize_tutorial,
+            record_video,
         )
     });
 }
@@ -107,11 +111,13 @@ fn setup_app(
     start_with_edits: Option<String>,
     maybe_mode: Option<GameplayMode>,
     initialize_tutorial: bool,
+    record_video: Option<sandbox::CameraScript>,
 ) -> (App, Vec<Box<dyn State<App>>>) {
     let title = !opts.dev
         && !flags.sim_flags.load.contains("player/save")
         && !flags.sim_flags.load.contains("/scenarios/")
-        && maybe_mode.is_none();
+        && maybe_mode.is_none()
+        && record_video.is_none();
     let mut app = App::new(flags, opts, ctx, title);
 
     // Handle savestates
@@ -155,7 +161,20 @@ fn setup_app(
     } else {
         let mode = maybe_mode
             .unwrap_or_else(|| GameplayMode::Freeform(app.primary.map.get_name().clone()));
-        vec![SandboxMode::simple_new(ctx, &mut app, mode)]
+        if let Some(script) = record_video {
+            vec![SandboxMode::async_new(
+                ctx,
+                &mut app,
+                mode,
+                Box::new(move |ctx, app| {
+                    vec![Transition::Push(sandbox::RecordVideo::new(
+                        ctx, app, script,
+                    ))]
+                }),
+            )]
+        } else {
+            vec![SandboxMode::simple_new(ctx, &mut app, mode)]
+        }
     };
     if let Some(ss) = savestate {
         // TODO This is weird, we're left in Freeform mode with the wrong UI. Can't instantiate
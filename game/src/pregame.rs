@@ -35,7 +35,11 @@ impl TitleScreen {
         let mut opts = SimOptions::new("screensaver");
         opts.alerts = AlertHandler::Silence;
         app.primary.sim = Sim::new(&app.primary.map, opts, &mut timer);
-        ScenarioGenerator::small_run(&app.primary.map)
+        let generator: ScenarioGenerator = abstutil::read_json(
+            abstutil::path("system/assets/pregame/screensaver.json"),
+            &mut timer,
+        );
+        generator
             .generate(&app.primary.map, &mut rng, &mut timer)
             .instantiate(&mut app.primary.sim, &app.primary.map, &mut rng, &mut timer);
 
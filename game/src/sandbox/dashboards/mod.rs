@@ -10,6 +10,7 @@ use crate::app::Transition;
 mod commuter;
 mod generic_trip_table;
 mod misc;
+mod neighborhoods;
 mod parking_overhead;
 mod summaries;
 mod table;
@@ -28,6 +29,7 @@ pub enum DashTab {
     TransitRoutes,
     CommuterPatterns,
     TrafficSignals,
+    Neighborhoods,
 }
 
 impl DashTab {
@@ -40,6 +42,7 @@ impl DashTab {
             Choice::new("Transit Routes", DashTab::TransitRoutes),
             Choice::new("Commuter Patterns", DashTab::CommuterPatterns),
             Choice::new("Traffic Signal Demand", DashTab::TrafficSignals),
+            Choice::new("Neighborhoods", DashTab::Neighborhoods),
         ];
         if app.has_prebaked().is_none() {
             choices.remove(1);
@@ -82,6 +85,7 @@ impl DashTab {
             DashTab::TransitRoutes => misc::TransitRoutes::new(ctx, app),
             DashTab::CommuterPatterns => CommuterPatterns::new(ctx, app),
             DashTab::TrafficSignals => TrafficSignalDemand::new(ctx, app),
+            DashTab::Neighborhoods => neighborhoods::Neighborhoods::new(ctx, app),
             DashTab::CancelledTripTable | DashTab::UnfinishedTripTable => unreachable!(),
         }))
     }
@@ -0,0 +1,405 @@
+//! Neighborhoods are named polygons that a player draws over the map. They're stored in
+//! data/player/neighborhoods/<city>/<map>/, and this dashboard summarizes the simulation within
+//! each one.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{prettyprint_usize, Parallelism, Timer};
+use geom::{Circle, Distance, Duration, LonLat, Polygon, Pt2D, Ring, Speed};
+use map_gui::tools::PopupMsg;
+use map_model::{Map, PathStep, RoadID, Traversable};
+use sim::{TripEndpoint, TripMode};
+use widgetry::{
+    Btn, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line, Outcome,
+    Panel, State, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::sandbox::dashboards::DashTab;
+
+const POINT_RADIUS: Distance = Distance::const_meters(10.0);
+
+#[derive(Serialize, Deserialize)]
+pub struct Neighborhood {
+    pub name: String,
+    /// The first point isn't repeated at the end
+    pub boundary: Vec<LonLat>,
+}
+
+impl Neighborhood {
+    fn load_all(map: &Map, timer: &mut Timer) -> Vec<Neighborhood> {
+        let mut results = Vec::new();
+        for path in abstutil::list_dir(abstutil::path_all_neighborhoods(map.get_name())) {
+            match abstutil::maybe_read_json::<Neighborhood>(path.clone(), timer) {
+                Ok(n) => results.push(n),
+                Err(err) => warn!("Skipping neighborhood {}: {}", path, err),
+            }
+        }
+        results
+    }
+
+    fn save(&self, map: &Map) {
+        abstutil::write_json(
+            abstutil::path_neighborhood(map.get_name(), &self.name),
+            self,
+        );
+    }
+
+    fn polygon(&self, map: &Map) -> Option<Polygon> {
+        make_polygon(map.get_gps_bounds().convert(&self.boundary))
+    }
+}
+
+fn make_polygon(mut pts: Vec<Pt2D>) -> Option<Polygon> {
+    if pts.len() < 3 {
+        return None;
+    }
+    pts.push(pts[0]);
+    Ring::new(pts).ok().map(|ring| ring.to_polygon())
+}
+
+#[derive(Default)]
+struct Stats {
+    trips_originating: usize,
+    /// Driving trips whose predicted route uses a road in the neighborhood
+    driving_trips: usize,
+    /// The subset of driving_trips that neither start nor end inside
+    through_trips: usize,
+    /// Over the steps inside the neighborhood of finished driving trips
+    total_distance: Distance,
+    total_duration: Duration,
+}
+
+impl Stats {
+    fn through_share(&self) -> String {
+        if self.driving_trips == 0 {
+            return "no driving trips".to_string();
+        }
+        format!(
+            "{}% of {} driving trips",
+            (100.0 * self.through_trips as f64 / self.driving_trips as f64).round(),
+            prettyprint_usize(self.driving_trips)
+        )
+    }
+
+    fn avg_speed(&self, app: &App) -> String {
+        if self.total_duration == Duration::ZERO {
+            return "no finished driving trips".to_string();
+        }
+        Speed::meters_per_second(
+            self.total_distance.inner_meters() / self.total_duration.inner_seconds(),
+        )
+        .to_string(&app.opts.units)
+    }
+}
+
+/// For every neighborhood, counts the trips starting there, and for driving trips routed through
+/// it, how many are just passing through and how fast they go while inside. Routes are predicted
+/// from each trip's endpoints, so they won't match any rerouting that happened during the
+/// simulation.
+fn calculate_stats(app: &App, zones: &[Polygon], timer: &mut Timer) -> Vec<Stats> {
+    let map = &app.primary.map;
+    let sim = &app.primary.sim;
+
+    let roads_per_zone: Vec<BTreeSet<RoadID>> = zones
+        .iter()
+        .map(|poly| {
+            map.all_roads()
+                .iter()
+                .filter(|r| poly.contains_pt(r.center_pts.middle()))
+                .map(|r| r.id)
+                .collect()
+        })
+        .collect();
+    let durations: HashMap<_, _> = sim
+        .get_analytics()
+        .finished_trips
+        .iter()
+        .filter_map(|(_, id, _, maybe_dt)| maybe_dt.map(|dt| (*id, dt)))
+        .collect();
+
+    let mut stats: Vec<Stats> = zones.iter().map(|_| Stats::default()).collect();
+    let trips = sim.all_trip_info();
+    for (_, trip) in &trips {
+        let pt = endpoint_pt(&trip.start, map);
+        for (zone, s) in zones.iter().zip(stats.iter_mut()) {
+            if zone.contains_pt(pt) {
+                s.trips_originating += 1;
+            }
+        }
+    }
+
+    let paths = timer
+        .parallelize(
+            "predict routes",
+            Parallelism::Fastest,
+            trips
+                .into_iter()
+                .filter(|(_, trip)| trip.mode == TripMode::Drive)
+                .collect(),
+            |(id, trip)| {
+                TripEndpoint::path_req(trip.start, trip.end, trip.mode, map)
+                    .and_then(|req| map.pathfind(req))
+                    .map(|path| (id, trip, path))
+            },
+        )
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    timer.start_iter("summarize neighborhoods", paths.len());
+    for (id, trip, path) in paths {
+        timer.next();
+        let roads: BTreeSet<RoadID> = path
+            .get_steps()
+            .iter()
+            .filter_map(|step| match step {
+                PathStep::Lane(l) | PathStep::ContraflowLane(l) => Some(map.get_l(*l).parent),
+                PathStep::Turn(_) => None,
+            })
+            .collect();
+        // Only the whole trip's duration is known, so split it between steps in proportion to how
+        // long each would take at the speed limit.
+        let steps: Vec<(RoadID, Distance, Duration)> = path
+            .get_steps()
+            .iter()
+            .map(|step| {
+                let t = step.as_traversable();
+                let road = match t {
+                    Traversable::Lane(l) => map.get_l(l).parent,
+                    Traversable::Turn(t) => map.get_l(t.dst).parent,
+                };
+                let length = t.length(map);
+                (road, length, length / t.speed_limit(map))
+            })
+            .collect();
+        let free_flow_total: Duration = steps.iter().map(|(_, _, dt)| *dt).sum();
+        let start = endpoint_pt(&trip.start, map);
+        let end = endpoint_pt(&trip.end, map);
+        for ((zone, zone_roads), s) in zones
+            .iter()
+            .zip(roads_per_zone.iter())
+            .zip(stats.iter_mut())
+        {
+            if roads.is_disjoint(zone_roads) {
+                continue;
+            }
+            s.driving_trips += 1;
+            if !zone.contains_pt(start) && !zone.contains_pt(end) {
+                s.through_trips += 1;
+            }
+            if let Some(dt) = durations.get(&id) {
+                if free_flow_total > Duration::ZERO {
+                    for (road, length, free_flow) in &steps {
+                        if zone_roads.contains(road) {
+                            s.total_distance += *length;
+                            s.total_duration += *dt * (*free_flow / free_flow_total);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    stats
+}
+
+fn endpoint_pt(endpoint: &TripEndpoint, map: &Map) -> Pt2D {
+    match endpoint {
+        TripEndpoint::Bldg(b) => map.get_b(*b).polygon.center(),
+        TripEndpoint::Border(i) => map.get_i(*i).polygon.center(),
+        TripEndpoint::SuddenlyAppear(pos) => pos.pt(map),
+    }
+}
+
+pub struct Neighborhoods {
+    panel: Panel,
+    draw: Drawable,
+}
+
+impl Neighborhoods {
+    pub fn new(ctx: &mut EventCtx, app: &App) -> Box<dyn State<App>> {
+        let (neighborhoods, zones, stats) =
+            ctx.loading_screen("summarize neighborhoods", |_, timer| {
+                let mut neighborhoods = Vec::new();
+                let mut zones = Vec::new();
+                for n in Neighborhood::load_all(&app.primary.map, timer) {
+                    if let Some(poly) = n.polygon(&app.primary.map) {
+                        neighborhoods.push(n);
+                        zones.push(poly);
+                    }
+                }
+                let stats = calculate_stats(app, &zones, timer);
+                (neighborhoods, zones, stats)
+            });
+
+        let mut batch = GeomBatch::new();
+        let mut col = vec![DashTab::Neighborhoods.picker(ctx, app)];
+        if neighborhoods.is_empty() {
+            col.push("No neighborhoods drawn yet".draw_text(ctx));
+        }
+        for ((n, zone), s) in neighborhoods.iter().zip(zones).zip(stats) {
+            batch.push(Color::BLUE.alpha(0.4), zone.clone());
+            if let Ok(outline) = zone.to_outline(Distance::meters(3.0)) {
+                batch.push(Color::BLUE, outline);
+            }
+            batch.append(
+                Text::from(Line(&n.name))
+                    .render_autocropped(ctx)
+                    .scale(2.0)
+                    .centered_on(zone.center()),
+            );
+
+            col.push(
+                Text::from_multiline(vec![
+                    Line(&n.name).small_heading(),
+                    Line(format!(
+                        "Trips originating: {}",
+                        prettyprint_usize(s.trips_originating)
+                    )),
+                    Line(format!("Through-traffic: {}", s.through_share())),
+                    Line(format!("Average driving speed: {}", s.avg_speed(app))),
+                ])
+                .draw(ctx),
+            );
+        }
+        col.push(Btn::text_fg("draw a new neighborhood").build_def(ctx, Key::N));
+
+        Box::new(Neighborhoods {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+                .build(ctx),
+            draw: ctx.upload(batch),
+        })
+    }
+}
+
+impl State<App> for Neighborhoods {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => Transition::Pop,
+                "draw a new neighborhood" => Transition::Push(DrawNeighborhood::new(ctx)),
+                _ => unreachable!(),
+            },
+            Outcome::Changed => DashTab::Neighborhoods
+                .transition(ctx, app, &self.panel)
+                .unwrap(),
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+struct DrawNeighborhood {
+    panel: Panel,
+    points: Vec<LonLat>,
+}
+
+impl DrawNeighborhood {
+    fn new(ctx: &mut EventCtx) -> Box<dyn State<App>> {
+        Box::new(DrawNeighborhood {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Draw a neighborhood").small_heading().draw(ctx),
+                    Btn::close(ctx),
+                ]),
+                "Click to add points to the boundary".draw_text(ctx),
+                Widget::row(vec![
+                    "Name:".draw_text(ctx).centered_vert(),
+                    Widget::text_entry(ctx, String::new(), true).named("name"),
+                ]),
+                Widget::row(vec![
+                    Btn::text_fg("undo last point").build_def(ctx, Key::Backspace),
+                    Btn::text_bg2("save").build_def(ctx, Key::Enter),
+                ]),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            points: Vec::new(),
+        })
+    }
+}
+
+impl State<App> for DrawNeighborhood {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "undo last point" => {
+                    self.points.pop();
+                }
+                "save" => {
+                    let name = self.panel.text_box("name");
+                    let n = Neighborhood {
+                        name: name.clone(),
+                        boundary: self.points.clone(),
+                    };
+                    if name.is_empty() {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["Name the neighborhood first"],
+                        ));
+                    }
+                    if n.polygon(&app.primary.map).is_none() {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["The boundary needs at least 3 points and can't cross itself"],
+                        ));
+                    }
+                    n.save(&app.primary.map);
+                    return Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::Replace(Neighborhoods::new(ctx, app)),
+                    ]);
+                }
+                _ => unreachable!(),
+            },
+            _ => {}
+        }
+
+        if let Some(pt) = ctx.canvas.get_cursor_in_map_space() {
+            if app.per_obj.left_click(ctx, "add a new point") {
+                self.points
+                    .push(pt.to_gps(app.primary.map.get_gps_bounds()));
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        let pts: Vec<Pt2D> = app.primary.map.get_gps_bounds().convert(&self.points);
+        if pts.len() == 2 {
+            g.draw_polygon(
+                Color::RED,
+                geom::Line::must_new(pts[0], pts[1]).make_polygons(POINT_RADIUS / 2.0),
+            );
+        }
+        if let Some(poly) = make_polygon(pts.clone()) {
+            g.draw_polygon(Color::BLUE.alpha(0.6), poly);
+        }
+        for pt in pts {
+            g.draw_polygon(
+                Color::RED,
+                Circle::new(pt, POINT_RADIUS / g.canvas.cam_zoom).to_polygon(),
+            );
+        }
+
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
@@ -5,7 +5,7 @@ use std::io::Write;
 use abstutil::prettyprint_usize;
 use geom::{Distance, Duration, Polygon, Pt2D};
 use map_gui::tools::PopupMsg;
-use sim::TripMode;
+use sim::{Analytics, TransitTripBreakdown, TripMode};
 use widgetry::{
     Btn, Checkbox, Choice, Color, CompareTimes, DrawBaselayer, DrawWithTooltips, EventCtx,
     GeomBatch, GfxCtx, Line, Outcome, Panel, State, Text, TextExt, Widget,
@@ -54,6 +54,8 @@ impl TripSummaries {
                     Widget::col(filters).padding(16).outline(2.0, Color::WHITE),
                     Widget::col(vec![
                         summary_boxes(ctx, app, &filter),
+                        toll_summary(ctx, app),
+                        transit_summary(ctx, app),
                         Widget::row(vec![
                             contingency_table(ctx, app, &filter),
                             scatter_plot(ctx, app, &filter),
@@ -218,6 +220,120 @@ fn summary_boxes(ctx: &mut EventCtx, app: &App, filter: &Filter) -> Widget {
     .evenly_spaced()
 }
 
+/// Compares traffic inside the toll zones, which only exist in the current proposal, to the
+/// baseline.
+fn toll_summary(ctx: &mut EventCtx, app: &App) -> Widget {
+    let map = &app.primary.map;
+    if app.has_prebaked().is_none() || map.get_toll_zones().is_empty() {
+        return Widget::nothing();
+    }
+    let now = app.primary.sim.time();
+    let analytics = app.primary.sim.get_analytics();
+    let prebaked = app.prebaked();
+
+    let mut roads = BTreeSet::new();
+    for zone in map.get_toll_zones() {
+        roads.extend(zone.roads_inside(map));
+    }
+    let vehicles_before = prebaked.vehicles_on_roads(&roads, now);
+    let vehicles_after = analytics.vehicles_on_roads(&roads, now);
+    let count_driving = |a: &Analytics| {
+        a.finished_trips
+            .iter()
+            .filter(|(t, _, mode, dt)| *t <= now && *mode == TripMode::Drive && dt.is_some())
+            .count()
+    };
+
+    let mut txt = Text::from(
+        Line(format!(
+            "Toll revenue: ${}",
+            prettyprint_usize(analytics.toll_revenue(now).round() as usize)
+        ))
+        .big_heading_plain(),
+    );
+    txt.add(Line(format!(
+        "Vehicles inside the toll zones: {} before, {} now ({})",
+        prettyprint_usize(vehicles_before),
+        prettyprint_usize(vehicles_after),
+        pct_change(vehicles_before, vehicles_after)
+    )));
+    txt.add(Line(format!(
+        "Finished driving trips: {} before, {} now",
+        prettyprint_usize(count_driving(prebaked)),
+        prettyprint_usize(count_driving(analytics))
+    )));
+    txt.add(Line("Vehicle counts only include whole hours").secondary());
+    txt.draw(ctx)
+        .container()
+        .padding(20)
+        .outline(2.0, Color::WHITE)
+}
+
+/// Compares where public transit riders spent their time to the baseline, only counting trips
+/// that finished in both.
+fn transit_summary(ctx: &mut EventCtx, app: &App) -> Widget {
+    if app.has_prebaked().is_none() {
+        return Widget::nothing();
+    }
+    let now = app.primary.sim.time();
+    let before = app.prebaked().transit_trip_breakdowns(now);
+    let mut after = app.primary.sim.get_analytics().transit_trip_breakdowns(now);
+    let pairs: Vec<(TransitTripBreakdown, TransitTripBreakdown)> = before
+        .into_iter()
+        .filter_map(|(id, b)| after.remove(&id).map(|a| (b, a)))
+        .collect();
+    if pairs.is_empty() {
+        return Widget::nothing();
+    }
+
+    let n = pairs.len() as f64;
+    let mut txt = Text::from(
+        Line(format!(
+            "Public transit trips: {}",
+            prettyprint_usize(pairs.len())
+        ))
+        .big_heading_plain(),
+    );
+    let phases: Vec<(&str, fn(&TransitTripBreakdown) -> Duration)> = vec![
+        ("In-vehicle", |x| x.in_vehicle),
+        ("Waiting", |x| x.waiting),
+        ("Walking to and from stops", |x| x.walking),
+    ];
+    for (label, get) in phases {
+        let b = pairs.iter().map(|(b, _)| get(b)).sum::<Duration>() / n;
+        let a = pairs.iter().map(|(_, a)| get(a)).sum::<Duration>() / n;
+        txt.add(Line(format!(
+            "{}: average {} before, {} now ({}{})",
+            label,
+            b.to_string(&app.opts.units),
+            a.to_string(&app.opts.units),
+            if a > b { "+" } else { "" },
+            (a - b).to_string(&app.opts.units)
+        )));
+    }
+    let transfers_before: usize = pairs.iter().map(|(b, _)| b.transfers()).sum();
+    let transfers_after: usize = pairs.iter().map(|(_, a)| a.transfers()).sum();
+    txt.add(Line(format!(
+        "Transfers: {} before, {} now ({})",
+        prettyprint_usize(transfers_before),
+        prettyprint_usize(transfers_after),
+        pct_change(transfers_before, transfers_after)
+    )));
+    txt.add(Line("Only trips that finished in both are compared").secondary());
+    txt.draw(ctx)
+        .container()
+        .padding(20)
+        .outline(2.0, Color::WHITE)
+}
+
+fn pct_change(before: usize, after: usize) -> String {
+    if before == 0 {
+        return "no baseline".to_string();
+    }
+    let pct = 100.0 * ((after as f64) - (before as f64)) / (before as f64);
+    format!("{:+.1}%", pct)
+}
+
 fn scatter_plot(ctx: &mut EventCtx, app: &App, filter: &Filter) -> Widget {
     if app.has_prebaked().is_none() {
         return Widget::nothing();
@@ -0,0 +1,358 @@
+use abstutil::Timer;
+use geom::{Duration, Time};
+use map_gui::tools::PopupMsg;
+use map_gui::ID;
+use map_model::{Direction, RoadID};
+use sim::{CountComparison, Scenario, ScenarioModifier, TrafficCount};
+use widgetry::{
+    Btn, Choice, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Panel, Spinner, State, Text, TextExt, VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+use crate::common::CommonState;
+use crate::sandbox::gameplay::play_scenario::EditScenarioModifiers;
+
+/// Lets the player enter observed hourly traffic counts, or import them from a CSV file, and
+/// check how well the scenario matches them. Calibrating adds a modifier that copies or removes
+/// people to match the counts better.
+pub struct CalibrateToCounts {
+    panel: Panel,
+    draw: Drawable,
+    scenario_name: String,
+    modifiers: Vec<ScenarioModifier>,
+    counts: Vec<TrafficCount>,
+    selected: Option<RoadID>,
+    /// Only up-to-date if the counts haven't changed since checking
+    report: Option<Vec<CountComparison>>,
+}
+
+impl CalibrateToCounts {
+    pub fn new(
+        ctx: &mut EventCtx,
+        app: &App,
+        scenario_name: String,
+        modifiers: Vec<ScenarioModifier>,
+        counts: Vec<TrafficCount>,
+        selected: Option<RoadID>,
+        report: Option<Vec<CountComparison>>,
+    ) -> Box<dyn State<App>> {
+        let map = &app.primary.map;
+        let mut batch = GeomBatch::new();
+        let mut col = vec![
+            Widget::row(vec![
+                Line("Calibrate to traffic counts")
+                    .small_heading()
+                    .draw(ctx),
+                Btn::close(ctx),
+            ]),
+            Text::from_multiline(vec![
+                Line("Enter the number of vehicles counted on roads each hour."),
+                Line("A GEH under 5 is a good match.").secondary(),
+            ])
+            .draw(ctx),
+        ];
+
+        for (idx, count) in counts.iter().enumerate() {
+            let roads = count.roads(map);
+            let mut txt = Text::from(Line(format!(
+                "{} ({}), {}: {} vehicles",
+                roads
+                    .get(0)
+                    .map(|r| map.get_r(*r).get_name(app.opts.language.as_ref()))
+                    .unwrap_or_else(|| count.way.to_string()),
+                match count.direction {
+                    Some(dir) => dir.to_string(),
+                    None => "both directions".to_string(),
+                },
+                describe_hour(count.hour),
+                count.count
+            )));
+            let mut color = Color::BLUE;
+            if let Some(ref report) = report {
+                let geh = report[idx].geh();
+                color = geh_color(geh);
+                txt.add(Line(format!("modeled {}, GEH {:.1}", report[idx].modeled, geh)).fg(color));
+            }
+            for r in roads {
+                batch.push(color.alpha(0.8), map.get_r(r).get_thick_polygon(map));
+            }
+            col.push(Widget::row(vec![
+                txt.draw(ctx),
+                Btn::svg_def("system/assets/tools/delete.svg")
+                    .build(ctx, format!("delete count {}", idx + 1), None)
+                    .align_right(),
+            ]));
+        }
+        if counts.is_empty() {
+            col.push("No counts yet".draw_text(ctx));
+        }
+        if let Some(ref report) = report {
+            let good = report.iter().filter(|c| c.geh() < 5.0).count();
+            col.push(
+                format!("{} of {} counts have a GEH under 5", good, report.len()).draw_text(ctx),
+            );
+        }
+
+        col.push(Widget::horiz_separator(ctx, 0.5));
+        if let Some(r) = selected {
+            batch.push(
+                Color::YELLOW.alpha(0.8),
+                map.get_r(r).get_thick_polygon(map),
+            );
+            col.push(
+                format!(
+                    "Add a count for all of {}",
+                    map.get_r(r).get_name(app.opts.language.as_ref())
+                )
+                .draw_text(ctx),
+            );
+            col.push(Widget::row(vec![
+                "Direction:".draw_text(ctx).centered_vert(),
+                Widget::dropdown(
+                    ctx,
+                    "direction",
+                    None,
+                    vec![
+                        Choice::new("both directions", None),
+                        Choice::new("forwards", Some(Direction::Fwd)),
+                        Choice::new("backwards", Some(Direction::Back)),
+                    ],
+                ),
+            ]));
+            col.push(Widget::row(vec![
+                "Starting at hour:".draw_text(ctx).centered_vert(),
+                Spinner::new(ctx, (0, 23), 7).named("hour"),
+            ]));
+            col.push(Widget::row(vec![
+                "Vehicles:".draw_text(ctx).centered_vert(),
+                Spinner::new(ctx, (0, 10000), 500).named("count"),
+            ]));
+            col.push(Btn::text_bg2("add count").build_def(ctx, Key::Enter));
+        } else {
+            col.push("Click a road to add a count".draw_text(ctx));
+        }
+        col.push(Widget::row(vec![
+            Widget::text_entry(ctx, String::new(), false).named("csv"),
+            Btn::text_bg2("import CSV").build_def(ctx, None),
+        ]));
+        col.push("CSV files need way,direction,hour,count columns".draw_text(ctx));
+        col.push(
+            Line("Ways are OpenStreetMap IDs; directions are fwd, back, or both")
+                .secondary()
+                .draw(ctx),
+        );
+
+        col.push(Widget::horiz_separator(ctx, 0.5));
+        col.push(Widget::row(vec![
+            Btn::text_bg2("check fit").build_def(ctx, None),
+            Btn::text_bg2("calibrate").build_def(ctx, None),
+        ]));
+
+        Box::new(CalibrateToCounts {
+            panel: Panel::new(Widget::col(col))
+                .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+                .build(ctx),
+            draw: ctx.upload(batch),
+            scenario_name,
+            modifiers,
+            counts,
+            selected,
+            report,
+        })
+    }
+
+    fn replace(
+        &self,
+        ctx: &mut EventCtx,
+        app: &App,
+        counts: Vec<TrafficCount>,
+        selected: Option<RoadID>,
+        report: Option<Vec<CountComparison>>,
+    ) -> Transition {
+        Transition::Replace(CalibrateToCounts::new(
+            ctx,
+            app,
+            self.scenario_name.clone(),
+            self.modifiers.clone(),
+            counts,
+            selected,
+            report,
+        ))
+    }
+
+    /// The scenario, with all of the earlier modifiers applied
+    fn load_scenario(&self, app: &App, timer: &mut Timer) -> Result<Scenario, String> {
+        let map = &app.primary.map;
+        let path = abstutil::path_scenario(map.get_name(), &self.scenario_name);
+        let mut scenario: Scenario = abstutil::maybe_read_versioned_binary(path, timer)
+            .map_err(|_| format!("Can't calibrate the {} scenario", self.scenario_name))?;
+        for m in &self.modifiers {
+            scenario = m.apply(map, scenario);
+        }
+        Ok(scenario)
+    }
+}
+
+impl State<App> for CalibrateToCounts {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.primary.current_selection = app.mouseover_unzoomed_roads_and_intersections(ctx);
+        }
+        let road = match app.primary.current_selection {
+            Some(ID::Road(r)) => Some(r),
+            Some(ID::Lane(l)) => Some(app.primary.map.get_l(l).parent),
+            _ => None,
+        };
+        if let Some(r) = road {
+            if Some(r) != self.selected && app.per_obj.left_click(ctx, "add a count here") {
+                return self.replace(ctx, app, self.counts.clone(), Some(r), self.report.clone());
+            }
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    app.primary.current_selection = None;
+                    Transition::Pop
+                }
+                "add count" => {
+                    let mut counts = self.counts.clone();
+                    counts.push(TrafficCount {
+                        way: app
+                            .primary
+                            .map
+                            .get_r(self.selected.unwrap())
+                            .orig_id
+                            .osm_way_id,
+                        direction: self.panel.dropdown_value("direction"),
+                        hour: self.panel.spinner("hour") as usize,
+                        count: self.panel.spinner("count") as usize,
+                    });
+                    self.replace(ctx, app, counts, None, None)
+                }
+                "import CSV" => {
+                    let path = self.panel.text_box("csv");
+                    let result = abstutil::slurp_file(&path)
+                        .and_then(|bytes| String::from_utf8(bytes).map_err(|err| err.to_string()))
+                        .and_then(|contents| sim::parse_counts(&contents));
+                    match result {
+                        Ok(new) => {
+                            let map = &app.primary.map;
+                            if let Some(c) = new.iter().find(|c| c.roads(map).is_empty()) {
+                                return Transition::Push(PopupMsg::new(
+                                    ctx,
+                                    "Error",
+                                    vec![format!("{} isn't part of this map", c.way)],
+                                ));
+                            }
+                            let mut counts = self.counts.clone();
+                            counts.extend(new);
+                            self.replace(ctx, app, counts, self.selected, None)
+                        }
+                        Err(err) => Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec![format!("Couldn't import {}: {}", path, err)],
+                        )),
+                    }
+                }
+                "check fit" => {
+                    let report = ctx.loading_screen("compare to traffic counts", |_, timer| {
+                        self.load_scenario(app, timer).map(|scenario| {
+                            sim::compare_counts(&app.primary.map, &scenario, &self.counts, timer)
+                        })
+                    });
+                    match report {
+                        Ok(report) => {
+                            self.replace(ctx, app, self.counts.clone(), self.selected, Some(report))
+                        }
+                        Err(err) => Transition::Push(PopupMsg::new(ctx, "Error", vec![err])),
+                    }
+                }
+                "calibrate" => {
+                    if self.counts.is_empty() {
+                        return Transition::Push(PopupMsg::new(
+                            ctx,
+                            "Error",
+                            vec!["Add some counts first"],
+                        ));
+                    }
+                    let modifier = ScenarioModifier::CalibrateToCounts(self.counts.clone());
+                    let result = ctx.loading_screen("calibrate to traffic counts", |_, timer| {
+                        let map = &app.primary.map;
+                        let before = self.load_scenario(app, timer)?;
+                        let after = modifier.apply(map, before.clone());
+                        Ok((
+                            sim::compare_counts(map, &before, &self.counts, timer),
+                            sim::compare_counts(map, &after, &self.counts, timer),
+                            before.people.len(),
+                            after.people.len(),
+                        ))
+                    });
+                    let (before, after, people_before, people_after) = match result {
+                        Ok(x) => x,
+                        Err(err) => {
+                            return Transition::Push(PopupMsg::new(ctx, "Error", vec![err]));
+                        }
+                    };
+                    let good = |report: &Vec<CountComparison>| {
+                        report.iter().filter(|c| c.geh() < 5.0).count()
+                    };
+                    let summary = vec![
+                        format!("People: {} before, {} after", people_before, people_after),
+                        format!(
+                            "Counts with a GEH under 5: {} before, {} after, out of {}",
+                            good(&before),
+                            good(&after),
+                            self.counts.len()
+                        ),
+                    ];
+
+                    let scenario_name = self.scenario_name.clone();
+                    let mut modifiers = self.modifiers.clone();
+                    modifiers.push(modifier);
+                    Transition::Multi(vec![
+                        Transition::Pop,
+                        Transition::Replace(EditScenarioModifiers::new(
+                            ctx,
+                            scenario_name,
+                            modifiers,
+                        )),
+                        Transition::Push(PopupMsg::new(ctx, "Calibrated", summary)),
+                    ])
+                }
+                x => {
+                    let idx = x["delete count ".len()..].parse::<usize>().unwrap() - 1;
+                    let mut counts = self.counts.clone();
+                    counts.remove(idx);
+                    self.replace(ctx, app, counts, self.selected, None)
+                }
+            },
+            _ => Transition::Keep,
+        }
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+        CommonState::draw_osd(g, app);
+    }
+}
+
+fn describe_hour(hour: usize) -> String {
+    let start = Time::START_OF_DAY + Duration::hours(hour);
+    let end = start + Duration::hours(1);
+    format!("{} - {}", start.ampm_tostring(), end.ampm_tostring())
+}
+
+fn geh_color(geh: f64) -> Color {
+    if geh < 5.0 {
+        Color::GREEN
+    } else if geh < 10.0 {
+        Color::YELLOW
+    } else {
+        Color::RED
+    }
+}
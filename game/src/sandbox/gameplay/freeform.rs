@@ -168,8 +168,10 @@ pub fn make_change_traffic(
         ),
     );
     choices.push(
-        Choice::new("random unrealistic trips", "random".to_string()).tooltip(
-            "Lots of trips will start at midnight, but not constantly appear through the day.",
+        Choice::new("synthetic population", "synthetic".to_string()).tooltip(
+            "People live in residential buildings, commute to work or school, and run errands to \
+             shops, restaurants, and other places through the day. The same RNG seed always \
+             produces the same people.",
         ),
     );
     choices.push(Choice::new(
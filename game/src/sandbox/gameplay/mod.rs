@@ -19,6 +19,7 @@ use crate::pregame::MainMenu;
 use crate::sandbox::{Actions, SandboxControls, SandboxMode};
 
 // TODO pub so challenges can grab cutscenes and SandboxMode can dispatch to actions. Weird?
+mod calibrate;
 mod census;
 pub mod commute;
 pub mod fix_traffic_signals;
@@ -114,8 +115,10 @@ impl GameplayMode {
             }
             _ => "weekday".to_string(),
         };
-        if name == "random" {
-            LoadScenario::Scenario(ScenarioGenerator::small_run(map).generate(map, &mut rng, timer))
+        if name == "synthetic" {
+            LoadScenario::Scenario(ScenarioGenerator::synthetic_population(
+                map, 1, &mut rng, timer,
+            ))
         } else if name == "home_to_work" {
             LoadScenario::Scenario(ScenarioGenerator::proletariat_robot(map, &mut rng, timer))
         } else if name == "census" {
@@ -124,6 +127,14 @@ impl GameplayMode {
                 popdat::generate_scenario("typical monday", config, map, &mut rng)
                     .expect("unable to build census scenario"),
             )
+        } else if abstutil::file_exists(abstutil::path_scenario_generator(map.get_name(), &name)) {
+            let path = abstutil::path_scenario_generator(map.get_name(), &name);
+            match ScenarioGenerator::load(path.clone(), timer) {
+                Ok((_, generator)) => {
+                    LoadScenario::Scenario(generator.generate(map, &mut rng, timer))
+                }
+                Err(err) => panic!("Couldn't load scenario generator {}: {}", path, err),
+            }
         } else {
             LoadScenario::Path(abstutil::path_scenario(map.get_name(), &name))
         }
@@ -167,7 +178,14 @@ impl GameplayMode {
                     }
                     _ => {}
                 },
-                EditCmd::ChangeRouteSchedule { .. } => {}
+                EditCmd::ChangeRouteSchedule { .. }
+                | EditCmd::ChangeRouteStops { .. }
+                | EditCmd::ChangeNewBusRoutes { .. }
+                | EditCmd::ChangeTollZones { .. }
+                | EditCmd::ChangeBikeRacks { .. }
+                | EditCmd::ChangeParkingZones { .. }
+                | EditCmd::ChangeBikeTreatments { .. }
+                | EditCmd::ChangeGuideways { .. } => {}
             }
         }
         true
@@ -3,7 +3,7 @@ use std::collections::BTreeSet;
 use maplit::btreeset;
 
 use map_gui::tools::{grey_out_map, nice_map_name, ChooseSomething, CityPicker, PopupMsg};
-use sim::{ScenarioModifier, TripMode};
+use sim::{ScenarioModifier, TripMode, Weather, DEFAULT_VALUE_OF_TIME};
 use widgetry::{
     lctrl, Btn, Choice, Color, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel,
     Slider, Spinner, State, Text, TextExt, VerticalAlignment, Widget,
@@ -12,6 +12,7 @@ use widgetry::{
 use crate::app::{App, Transition};
 use crate::common::checkbox_per_mode;
 use crate::edit::EditMode;
+use crate::sandbox::gameplay::calibrate::CalibrateToCounts;
 use crate::sandbox::gameplay::freeform::make_change_traffic;
 use crate::sandbox::gameplay::{GameplayMode, GameplayState};
 use crate::sandbox::{Actions, SandboxControls, SandboxMode};
@@ -152,7 +153,7 @@ impl GameplayState for PlayScenario {
     }
 }
 
-struct EditScenarioModifiers {
+pub struct EditScenarioModifiers {
     scenario_name: String,
     modifiers: Vec<ScenarioModifier>,
     panel: Panel,
@@ -192,10 +193,40 @@ impl EditScenarioModifiers {
         }
         rows.push(Btn::text_bg2("Change trip mode").build_def(ctx, None));
         rows.push(Btn::text_bg2("Add extra new trips").build_def(ctx, None));
+        rows.push(Btn::text_bg2("Calibrate to traffic counts").build_def(ctx, None));
         rows.push(Widget::row(vec![
             Spinner::new(ctx, (2, 14), 2).named("repeat_days"),
             Btn::text_bg2("Repeat schedule multiple days").build_def(ctx, None),
         ]));
+        rows.push(Widget::row(vec![
+            Spinner::new(ctx, (0, 100), 20).named("weekend_pct"),
+            Btn::text_bg2("Only this percent of people travel on weekends").build_def(ctx, None),
+        ]));
+        rows.push(Widget::row(vec![
+            Spinner::new(ctx, (1, 100), DEFAULT_VALUE_OF_TIME as isize).named("value_of_time"),
+            Btn::text_bg2("Drivers avoid tolls, valuing an hour at this many dollars")
+                .build_def(ctx, None),
+        ]));
+        rows.push(Widget::row(vec![
+            Widget::dropdown(
+                ctx,
+                "weather",
+                Weather::Snow,
+                Weather::all()
+                    .into_iter()
+                    .filter(|w| *w != Weather::Clear)
+                    .map(|w| Choice::new(w.describe(), w))
+                    .collect(),
+            ),
+            Btn::text_bg2("Change the weather").build_def(ctx, None),
+        ]));
+        rows.push(Widget::row(vec![
+            Spinner::new(ctx, (0, 100), 20).named("pct_aggressive"),
+            "% aggressive,".draw_text(ctx).centered_vert(),
+            Spinner::new(ctx, (0, 100), 20).named("pct_cautious"),
+            "% cautious".draw_text(ctx).centered_vert(),
+            Btn::text_bg2("Change the mix of drivers").build_def(ctx, None),
+        ]));
         rows.push(Widget::horiz_separator(ctx, 0.5));
         rows.push(
             Widget::row(vec![
@@ -250,6 +281,17 @@ impl State<App> for EditScenarioModifiers {
                         self.modifiers.clone(),
                     ));
                 }
+                "Calibrate to traffic counts" => {
+                    return Transition::Push(CalibrateToCounts::new(
+                        ctx,
+                        app,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                        Vec::new(),
+                        None,
+                        None,
+                    ));
+                }
                 "Add extra new trips" => {
                     return Transition::Push(ChooseSomething::new(
                         ctx,
@@ -285,6 +327,47 @@ impl State<App> for EditScenarioModifiers {
                         self.modifiers.clone(),
                     ));
                 }
+                "Only this percent of people travel on weekends" => {
+                    self.modifiers.push(ScenarioModifier::ReduceWeekendTrips {
+                        pct_ppl: self.panel.spinner("weekend_pct") as usize,
+                    });
+                    return Transition::Replace(EditScenarioModifiers::new(
+                        ctx,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ));
+                }
+                "Drivers avoid tolls, valuing an hour at this many dollars" => {
+                    self.modifiers.push(ScenarioModifier::RespondToTolls {
+                        value_of_time: self.panel.spinner("value_of_time") as usize,
+                    });
+                    return Transition::Replace(EditScenarioModifiers::new(
+                        ctx,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ));
+                }
+                "Change the weather" => {
+                    self.modifiers.push(ScenarioModifier::Weather(
+                        self.panel.dropdown_value("weather"),
+                    ));
+                    return Transition::Replace(EditScenarioModifiers::new(
+                        ctx,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ));
+                }
+                "Change the mix of drivers" => {
+                    self.modifiers.push(ScenarioModifier::DriverMix {
+                        pct_aggressive: self.panel.spinner("pct_aggressive") as usize,
+                        pct_cautious: self.panel.spinner("pct_cautious") as usize,
+                    });
+                    return Transition::Replace(EditScenarioModifiers::new(
+                        ctx,
+                        self.scenario_name.clone(),
+                        self.modifiers.clone(),
+                    ));
+                }
                 x => {
                     if let Some(x) = x.strip_prefix("delete modifier ") {
                         self.modifiers.remove(x.parse::<usize>().unwrap() - 1);
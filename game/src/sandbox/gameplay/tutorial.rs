@@ -7,8 +7,8 @@ use map_gui::ID;
 use map_model::raw::OriginalRoad;
 use map_model::{osm, BuildingID, Map, Position};
 use sim::{
-    AgentID, BorderSpawnOverTime, CarID, IndividTrip, PersonSpec, Scenario, ScenarioGenerator,
-    SpawnOverTime, TripEndpoint, TripMode, TripPurpose, VehicleType,
+    AgentID, BorderSpawnOverTime, CarID, DepartureCurve, IndividTrip, PersonSpec, Scenario,
+    ScenarioGenerator, SpawnOverTime, TripEndpoint, TripMode, TripPurpose, VehicleType,
 };
 use widgetry::{
     hotkeys, lctrl, Btn, Color, EventCtx, GfxCtx, HorizontalAlignment, Key, Line, Outcome, Panel,
@@ -649,7 +649,8 @@ fn make_bike_lane_scenario(map: &Map) -> ScenarioGenerator {
         percent_use_transit: 0.0,
         start_time: Time::START_OF_DAY,
         stop_time: Time::START_OF_DAY + Duration::seconds(10.0),
-        start_from_border: map.find_i_by_osm_id(osm::NodeID(3005680098)).unwrap(),
+        departure_curve: DepartureCurve::Uniform,
+        start_from_border: Some(map.find_i_by_osm_id(osm::NodeID(3005680098)).unwrap()),
         goal: Some(TripEndpoint::Bldg(
             map.find_b_by_osm_id(bldg(217699501)).unwrap(),
         )),
@@ -1155,12 +1156,15 @@ impl TutorialState {
                         num_agents: 1000,
                         start_time: Time::START_OF_DAY,
                         stop_time: Time::START_OF_DAY + Duration::hours(3),
+                        departure_curve: DepartureCurve::Uniform,
+                        origin: None,
                         goal: None,
                         percent_driving: 1.0,
                         percent_biking: 0.0,
                         percent_use_transit: 0.0,
                     }],
                     border_spawn_over_time: Vec::new(),
+                    deliveries: Vec::new(),
                 })
                 .msg(
                     vec![
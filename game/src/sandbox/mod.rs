@@ -1,15 +1,17 @@
 pub use gameplay::{spawn_agents_around, GameplayMode, TutorialPointer, TutorialState};
 use maplit::btreeset;
+pub use record_video::{CameraScript, RecordVideo};
 pub use speed::{SpeedControls, TimePanel};
+pub use time_travel::Snapshots;
 pub use time_warp::TimeWarpScreen;
 
 use abstutil::prettyprint_usize;
-use geom::{Circle, Distance, Pt2D, Time};
+use geom::{Circle, Distance, Duration, Histogram, Pt2D, Statistic, Time};
 use map_gui::load::{FileLoader, MapLoader};
 use map_gui::tools::{ChooseSomething, Minimap, PopupMsg, TurnExplorer};
 use map_gui::AppLike;
 use map_gui::ID;
-use sim::{Analytics, Scenario};
+use sim::{Analytics, Scenario, TripMode};
 use widgetry::{
     lctrl, Btn, Choice, Color, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
     Outcome, Panel, State, Text, TextExt, UpdateType, VerticalAlignment, Widget,
@@ -32,7 +34,9 @@ use map_gui::render::{unzoomed_agent_radius, UnzoomedAgents};
 pub mod dashboards;
 pub mod gameplay;
 mod misc_tools;
+mod record_video;
 mod speed;
+mod time_travel;
 mod time_warp;
 mod uber_turns;
 
@@ -361,45 +365,145 @@ impl AgentMeter {
             prettyprint_usize(counts.cyclists).draw_text(ctx),
         ]));
 
-        row.push(Widget::custom_row(vec![
-            Widget::draw_svg_with_tooltip(
-                ctx,
-                "system/assets/meters/car.svg",
-                Text::from_multiline(vec![
-                    Line("Cars"),
+        let mut cars = Text::from_multiline(vec![
+            Line("Cars"),
+            Line(format!(
+                "Single-occupancy vehicles: {}",
+                prettyprint_usize(counts.sov_drivers)
+            ))
+            .secondary(),
+        ]);
+        let ride_hail = app.primary.sim.get_ride_hail_stats();
+        if ride_hail.vehicles > 0 {
+            cars.add(
+                Line(format!(
+                    "{} passengers in {} ride-hail vehicles",
+                    prettyprint_usize(counts.ride_hail_riders),
+                    prettyprint_usize(counts.ride_hail_vehicles)
+                ))
+                .secondary(),
+            );
+            cars.add(
+                Line(format!(
+                    "{} ride-hail requests waiting for a vehicle",
+                    prettyprint_usize(ride_hail.unassigned_requests)
+                ))
+                .secondary(),
+            );
+            let mut waits = Histogram::new();
+            for (_, dt) in &ride_hail.wait_times {
+                waits.add(*dt);
+            }
+            if let Some(median) = waits.select(Statistic::P50) {
+                cars.add(Line(format!("Median ride-hail wait: {}", median)).secondary());
+            }
+            let total = ride_hail.deadhead_distance + ride_hail.passenger_distance;
+            if total > Distance::ZERO {
+                cars.add(
                     Line(format!(
-                        "Single-occupancy vehicles: {}",
-                        prettyprint_usize(counts.sov_drivers)
+                        "Ride-hail vehicles drove empty for {}% of {}",
+                        (100.0 * (ride_hail.deadhead_distance / total)) as usize,
+                        total.to_string(&app.opts.units)
                     ))
                     .secondary(),
-                ]),
-            )
-            .margin_right(5),
-            prettyprint_usize(counts.sov_drivers).draw_text(ctx),
-        ]));
-
-        row.push(Widget::custom_row(vec![
-            Widget::draw_svg_with_tooltip(
-                ctx,
-                "system/assets/meters/bus.svg",
-                Text::from_multiline(vec![
-                    Line("Public transit"),
+                );
+            }
+        }
+        let rerouting = app.primary.sim.get_rerouting_stats();
+        if rerouting.enabled {
+            cars.add(
+                Line(format!(
+                    "{} congested lanes right now",
+                    prettyprint_usize(rerouting.congested_lanes)
+                ))
+                .secondary(),
+            );
+            if rerouting.checked > 0 {
+                cars.add(
                     Line(format!(
-                        "{} passengers on {} buses",
-                        prettyprint_usize(counts.bus_riders),
-                        prettyprint_usize(counts.buses)
+                        "Rerouted {} of {} drivers heading into congestion ({}%)",
+                        prettyprint_usize(rerouting.rerouted),
+                        prettyprint_usize(rerouting.checked),
+                        100 * rerouting.rerouted / rerouting.checked
                     ))
                     .secondary(),
+                );
+            }
+        }
+        let curb = app.primary.sim.get_curb_stats();
+        if curb.deliveries + curb.no_room > 0 {
+            cars.add(
+                Line(format!(
+                    "{} deliveries stopped in the road, {} lanes blocked right now",
+                    prettyprint_usize(curb.deliveries),
+                    prettyprint_usize(curb.blocked_lanes)
+                ))
+                .secondary(),
+            );
+        }
+        row.push(Widget::custom_row(vec![
+            Widget::draw_svg_with_tooltip(ctx, "system/assets/meters/car.svg", cars)
+                .margin_right(5),
+            prettyprint_usize(counts.sov_drivers + counts.ride_hail_vehicles).draw_text(ctx),
+        ]));
+
+        let mut transit = Text::from_multiline(vec![
+            Line("Public transit"),
+            Line(format!(
+                "{} passengers on {} buses",
+                prettyprint_usize(counts.bus_riders),
+                prettyprint_usize(counts.buses)
+            ))
+            .secondary(),
+            Line(format!(
+                "{} passengers on {} trains",
+                prettyprint_usize(counts.train_riders),
+                prettyprint_usize(counts.trains)
+            ))
+            .secondary(),
+        ]);
+        let microtransit = app.primary.sim.get_microtransit_stats();
+        if microtransit.shuttles > 0 {
+            transit.add(
+                Line(format!(
+                    "{} passengers on {} microtransit shuttles",
+                    prettyprint_usize(counts.microtransit_riders),
+                    prettyprint_usize(counts.microtransit_vehicles)
+                ))
+                .secondary(),
+            );
+            let mut waits = Histogram::new();
+            let mut trips = Histogram::new();
+            for p in &microtransit.passengers {
+                waits.add(p.wait);
+                trips.add(p.wait + p.ride);
+            }
+            let mut fixed_route = Histogram::new();
+            for (_, _, mode, maybe_dt) in &app.primary.sim.get_analytics().finished_trips {
+                if let (TripMode::Transit, Some(dt)) = (mode, maybe_dt) {
+                    fixed_route.add(*dt);
+                }
+            }
+            if let (Some(wait), Some(trip)) =
+                (waits.select(Statistic::P50), trips.select(Statistic::P50))
+            {
+                transit.add(
                     Line(format!(
-                        "{} passengers on {} trains",
-                        prettyprint_usize(counts.train_riders),
-                        prettyprint_usize(counts.trains)
+                        "Median microtransit wait: {}, whole ride: {}",
+                        wait, trip
                     ))
                     .secondary(),
-                ]),
-            )
-            .margin_right(5),
-            prettyprint_usize(counts.bus_riders + counts.train_riders).draw_text(ctx),
+                );
+            }
+            if let Some(dt) = fixed_route.select(Statistic::P50) {
+                transit.add(Line(format!("Median fixed-route transit trip: {}", dt)).secondary());
+            }
+        }
+        row.push(Widget::custom_row(vec![
+            Widget::draw_svg_with_tooltip(ctx, "system/assets/meters/bus.svg", transit)
+                .margin_right(5),
+            prettyprint_usize(counts.bus_riders + counts.train_riders + counts.microtransit_riders)
+                .draw_text(ctx),
         ]));
 
         let mut rows = vec![
@@ -537,6 +641,22 @@ impl ContextualActions for Actions {
                         actions.push((Key::E, "edit lane".to_string()));
                     }
                 }
+                ID::Car(_) | ID::Pedestrian(_) => {
+                    let sim = &app.primary.sim;
+                    if let Some(person) = id.agent_id().and_then(|a| sim.agent_to_person(a)) {
+                        // Only if this person isn't recorded already
+                        let recorded = sim
+                            .get_trace_recording()
+                            .map(|traces| match traces.people {
+                                Some(ref people) => people.contains(&person),
+                                None => true,
+                            })
+                            .unwrap_or(false);
+                        if !recorded {
+                            actions.push((Key::G, "record this person's trace".to_string()));
+                        }
+                    }
+                }
                 _ => {}
             }
         }
@@ -575,6 +695,20 @@ impl ContextualActions for Actions {
             (ID::Intersection(i), "record traffic here") => {
                 Transition::Push(TrafficRecorder::new(ctx, btreeset! {i}))
             }
+            (id, "record this person's trace") => {
+                let sim = &mut app.primary.sim;
+                let person = sim.agent_to_person(id.agent_id().unwrap()).unwrap();
+                if sim.get_trace_recording().is_some() {
+                    sim.also_record_trace(person);
+                } else {
+                    sim.record_traces(
+                        &app.primary.map,
+                        Some(btreeset! {person}),
+                        Duration::seconds(1.0),
+                    );
+                }
+                Transition::Keep
+            }
             (ID::Lane(l), "explore turns from this lane") => {
                 Transition::Push(TurnExplorer::new(ctx, app, l))
             }
@@ -687,7 +821,7 @@ impl State<App> for SandboxLoader {
                                 }
                             }
 
-                            return Transition::Push(FileLoader::<App, Scenario>::new(
+                            return Transition::Push(FileLoader::<App, Scenario>::new_versioned(
                                 ctx,
                                 path,
                                 Box::new(|_, _, _, scenario| {
@@ -741,7 +875,7 @@ impl State<App> for SandboxLoader {
                         continue;
                     }
 
-                    return Transition::Push(FileLoader::<App, Analytics>::new(
+                    return Transition::Push(FileLoader::<App, Analytics>::new_versioned(
                         ctx,
                         abstutil::path_prebaked_results(app.primary.map.get_name(), &scenario_name),
                         Box::new(move |_, _, _, prebaked| {
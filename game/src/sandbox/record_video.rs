@@ -0,0 +1,177 @@
+//! Records a running simulation to a video without any interaction, following a scripted camera
+//! path. Each frame is saved as a PNG file, and optionally encoded into a video with ffmpeg
+//! afterwards.
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::Timer;
+use geom::{Duration, LonLat, Time};
+use map_gui::render::DrawOptions;
+use widgetry::{
+    DrawBaselayer, EventCtx, GfxCtx, HorizontalAlignment, Line, Panel, State, UpdateType,
+    VerticalAlignment,
+};
+
+use crate::app::{App, ShowEverything, Transition};
+
+/// Describes how to record a video. Read from a JSON file passed with `--record_video`.
+#[derive(Serialize, Deserialize)]
+pub struct CameraScript {
+    /// The directory to write numbered PNG frames into
+    pub output_dir: String,
+    /// How much simulation time passes between frames
+    pub sim_time_per_frame: Duration,
+    /// The camera moves smoothly between these, in order. Recording starts at the first
+    /// keyframe's time and stops at the last.
+    pub keyframes: Vec<Keyframe>,
+    /// If present, encode the frames into a video at this path with ffmpeg, playing back this many
+    /// frames per second.
+    pub ffmpeg: Option<(String, usize)>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Keyframe {
+    pub time: Time,
+    /// Where the camera is centered
+    pub center: LonLat,
+    pub zoom: f64,
+}
+
+impl CameraScript {
+    /// Where the camera is centered, and the zoom, at some time. Interpolates linearly between
+    /// keyframes.
+    fn camera_at(&self, time: Time) -> (LonLat, f64) {
+        let first = &self.keyframes[0];
+        if time <= first.time {
+            return (first.center, first.zoom);
+        }
+        for pair in self.keyframes.windows(2) {
+            let (k1, k2) = (&pair[0], &pair[1]);
+            if time <= k2.time {
+                let pct = if k2.time > k1.time {
+                    (time - k1.time) / (k2.time - k1.time)
+                } else {
+                    1.0
+                };
+                let center = LonLat::new(
+                    k1.center.x() + pct * (k2.center.x() - k1.center.x()),
+                    k1.center.y() + pct * (k2.center.y() - k1.center.y()),
+                );
+                return (center, k1.zoom + pct * (k2.zoom - k1.zoom));
+            }
+        }
+        let last = self.keyframes.last().unwrap();
+        (last.center, last.zoom)
+    }
+}
+
+pub struct RecordVideo {
+    script: CameraScript,
+    panel: Panel,
+    frames_captured: usize,
+}
+
+impl RecordVideo {
+    pub fn new(ctx: &mut EventCtx, app: &mut App, script: CameraScript) -> Box<dyn State<App>> {
+        assert!(
+            !script.keyframes.is_empty(),
+            "The camera script needs at least one keyframe"
+        );
+        let start = script.keyframes[0].time;
+        if app.primary.sim.time() < start {
+            ctx.loading_screen("skip to the first keyframe", |_, timer| {
+                let dt = start - app.primary.sim.time();
+                app.primary
+                    .sim
+                    .timed_step(&app.primary.map, dt, &mut app.primary.sim_cb, timer);
+            });
+        }
+        Box::new(RecordVideo {
+            script,
+            panel: Panel::empty(ctx),
+            frames_captured: 0,
+        })
+    }
+
+    fn finish(&self) -> ! {
+        println!(
+            "Recorded {} frames to {}",
+            self.frames_captured, self.script.output_dir
+        );
+        if let Some((ref path, fps)) = self.script.ffmpeg {
+            let ok = std::process::Command::new("ffmpeg")
+                .args(&[
+                    "-y",
+                    "-framerate",
+                    &fps.to_string(),
+                    "-i",
+                    &format!("{}/frame_%05d.png", self.script.output_dir),
+                    "-c:v",
+                    "libx264",
+                    "-pix_fmt",
+                    "yuv420p",
+                    path,
+                ])
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false);
+            if ok {
+                println!("Encoded the video to {}", path);
+            } else {
+                println!("Encoding the video failed; you probably don't have ffmpeg installed");
+            }
+        }
+        std::process::exit(0);
+    }
+}
+
+impl State<App> for RecordVideo {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        if ctx.input.nonblocking_is_update_event().is_none() {
+            ctx.request_update(UpdateType::Game);
+            return Transition::Keep;
+        }
+        ctx.input.use_update_event();
+
+        // The previous frame was captured after the last event, so advance now.
+        if self.frames_captured > 0 {
+            app.primary.sim.timed_step(
+                &app.primary.map,
+                self.script.sim_time_per_frame,
+                &mut app.primary.sim_cb,
+                &mut Timer::throwaway(),
+            );
+        }
+        let now = app.primary.sim.time();
+        if now > self.script.keyframes.last().unwrap().time {
+            self.finish();
+        }
+
+        let (center, zoom) = self.script.camera_at(now);
+        ctx.canvas.cam_zoom = zoom;
+        ctx.canvas
+            .center_on_map_pt(center.to_pt(app.primary.map.get_gps_bounds()));
+        self.panel = Panel::new(Line(now.ampm_tostring()).small_heading().draw(ctx))
+            .aligned(HorizontalAlignment::Left, VerticalAlignment::Top)
+            .build(ctx);
+
+        self.frames_captured += 1;
+        ctx.request_update(UpdateType::ScreenCapture {
+            filename: format!(
+                "{}/frame_{:05}.png",
+                self.script.output_dir, self.frames_captured
+            ),
+        });
+        ctx.request_update(UpdateType::Game);
+        Transition::Keep
+    }
+
+    fn draw_baselayer(&self) -> DrawBaselayer {
+        DrawBaselayer::Custom
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        app.draw(g, DrawOptions::new(), &ShowEverything::new());
+        self.panel.draw(g);
+    }
+}
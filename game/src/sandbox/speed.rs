@@ -9,6 +9,7 @@ use widgetry::{
 
 use crate::app::{App, Transition};
 use crate::common::Warping;
+use crate::sandbox::time_travel::TimeTravel;
 use crate::sandbox::time_warp::JumpToTime;
 use crate::sandbox::{GameplayMode, SandboxMode, TimeWarpScreen};
 
@@ -116,6 +117,10 @@ impl SpeedControls {
                     .build(ctx, "reset to midnight", Key::X)
                     .container()
                     .padding(9),
+                Btn::text_fg("rewind")
+                    .build_def(ctx, None)
+                    .container()
+                    .padding(9),
             ])
             .bg(app.cs.section_bg),
         );
@@ -179,6 +184,10 @@ impl SpeedControls {
                         )));
                     }
                 }
+                "rewind" => {
+                    self.pause(ctx, app);
+                    return Some(Transition::Push(TimeTravel::new(ctx, app)));
+                }
                 "jump to specific time" => {
                     return Some(Transition::Push(JumpToTime::new(
                         ctx,
@@ -267,6 +276,8 @@ impl SpeedControls {
                     Duration::seconds(0.033),
                     &mut app.primary.sim_cb,
                 );
+                let primary = &mut app.primary;
+                primary.snapshots.maybe_capture(&primary.sim);
                 app.recalculate_current_selection(ctx);
             }
         }
@@ -0,0 +1,171 @@
+//! While the simulation runs, keep a copy of it every minute, going back a few minutes. Dragging
+//! a slider restores one of these copies, so players can re-watch how something like a jam formed,
+//! without restarting from midnight.
+//!
+//! The simulation is deterministic, so re-running from a snapshot reproduces the same results.
+//! Snapshots are thrown away whenever the simulation is reset or edited, since they'd no longer
+//! match.
+
+use geom::{Duration, Time};
+use map_gui::tools::PopupMsg;
+use sim::Sim;
+use widgetry::{
+    Btn, EventCtx, GfxCtx, HorizontalAlignment, Line, Outcome, Panel, Slider, State, Text,
+    VerticalAlignment, Widget,
+};
+
+use crate::app::{App, Transition};
+
+/// How often to snapshot the simulation. Each snapshot is a full copy, so this trades memory for
+/// how precisely players can rewind.
+const SNAPSHOT_EVERY: Duration = Duration::const_seconds(60.0);
+/// How far back players can rewind
+pub const TIME_TRAVEL_WINDOW: Duration = Duration::const_seconds(600.0);
+
+/// Periodic in-memory copies of the simulation, sorted by time.
+#[derive(Default)]
+pub struct Snapshots {
+    list: Vec<Sim>,
+}
+
+impl Snapshots {
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    /// Call this after stepping the simulation. Only copies it if enough time has passed since the
+    /// last snapshot.
+    pub fn maybe_capture(&mut self, sim: &Sim) {
+        let now = sim.time();
+        // If we've rewound, anything after now was the old future. It'll be captured again as the
+        // simulation re-runs.
+        self.list.retain(|s| s.time() <= now);
+        if let Some(last) = self.list.last() {
+            if now - last.time() < SNAPSHOT_EVERY {
+                return;
+            }
+        }
+        self.capture(sim);
+    }
+
+    fn capture(&mut self, sim: &Sim) {
+        let now = sim.time();
+        self.list.retain(|s| now - s.time() <= TIME_TRAVEL_WINDOW);
+        if self.list.iter().any(|s| s.time() == now) {
+            return;
+        }
+        let idx = self
+            .list
+            .iter()
+            .position(|s| s.time() > now)
+            .unwrap_or(self.list.len());
+        self.list.insert(idx, sim.clone());
+    }
+
+    fn times(&self) -> Vec<Time> {
+        self.list.iter().map(|s| s.time()).collect()
+    }
+}
+
+/// Drag a slider back through the snapshots. The simulation stays paused while this is open.
+pub struct TimeTravel {
+    panel: Panel,
+    times: Vec<Time>,
+    current: usize,
+}
+
+impl TimeTravel {
+    pub fn new(ctx: &mut EventCtx, app: &mut App) -> Box<dyn State<App>> {
+        // Always include the present, so players can scrub back to it. If they've already rewound
+        // once, the snapshots after the present are still valid.
+        let primary = &mut app.primary;
+        primary.snapshots.capture(&primary.sim);
+        let times = primary.snapshots.times();
+        let now = primary.sim.time();
+        if times.len() < 2 {
+            return PopupMsg::new(
+                ctx,
+                "Time travel",
+                vec![
+                    "There's nothing to rewind to yet.",
+                    "Let the simulation run for a few minutes first.",
+                ],
+            );
+        }
+
+        let current = times.iter().position(|t| *t == now).unwrap();
+        Box::new(TimeTravel {
+            panel: Panel::new(Widget::col(vec![
+                Widget::row(vec![
+                    Line("Time travel").small_heading().draw(ctx),
+                    Btn::close(ctx),
+                ]),
+                Text::from_multiline(vec![
+                    Line(format!(
+                        "Drag back as far as {}, then resume the simulation to re-watch what \
+                         happened.",
+                        times[0].ampm_tostring()
+                    )),
+                    Line(format!(
+                        "Snapshots are taken every {}, going back {}.",
+                        SNAPSHOT_EVERY, TIME_TRAVEL_WINDOW
+                    ))
+                    .secondary(),
+                ])
+                .wrap_to_pct(ctx, 25)
+                .draw(ctx),
+                Slider::area(
+                    ctx,
+                    0.25 * ctx.canvas.window_width,
+                    (current as f64) / ((times.len() - 1) as f64),
+                )
+                .named("time slider"),
+                Line(times[current].ampm_tostring()).draw(ctx).named("time"),
+            ]))
+            .aligned(HorizontalAlignment::Center, VerticalAlignment::Top)
+            .build(ctx),
+            times,
+            current,
+        })
+    }
+}
+
+impl State<App> for TimeTravel {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition {
+        ctx.canvas_movement();
+        if ctx.redo_mouseover() {
+            app.recalculate_current_selection(ctx);
+        }
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                _ => unreachable!(),
+            },
+            _ => {}
+        }
+
+        let percent = self.panel.slider("time slider").get_percent();
+        let idx = (percent * ((self.times.len() - 1) as f64)).round() as usize;
+        if idx != self.current {
+            self.current = idx;
+            app.primary.sim = app.primary.snapshots.list[idx].clone();
+            app.recalculate_current_selection(ctx);
+            self.panel.replace(
+                ctx,
+                "time",
+                Line(self.times[idx].ampm_tostring())
+                    .draw(ctx)
+                    .named("time"),
+            );
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, _: &App) {
+        self.panel.draw(g);
+    }
+}
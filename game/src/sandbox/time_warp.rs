@@ -12,6 +12,7 @@ use widgetry::{
 
 use crate::app::{App, FindDelayedIntersections, ShowEverything, Transition};
 use crate::common::Warping;
+use crate::sandbox::time_travel::TIME_TRAVEL_WINDOW;
 use crate::sandbox::{GameplayMode, SandboxMode};
 
 // TODO Text entry would be great
@@ -305,6 +306,11 @@ impl State<App> for TimeWarpScreen {
                 Duration::seconds(0.033),
                 &mut app.primary.sim_cb,
             );
+            // Don't bother copying the sim when it's far from where players could rewind to
+            if self.target - app.primary.sim.time() <= TIME_TRAVEL_WINDOW {
+                let primary = &mut app.primary;
+                primary.snapshots.maybe_capture(&primary.sim);
+            }
             for (t, maybe_i, alert) in app.primary.sim.clear_alerts() {
                 // TODO Just the first :(
                 return Transition::Replace(PopupMsg::new(
@@ -121,7 +121,8 @@ impl Polygon {
         Bounds::from(&self.points)
     }
 
-    fn transform<F: Fn(&Pt2D) -> Pt2D>(&self, f: F) -> Polygon {
+    /// Moves every point of the polygon, keeping the same triangles.
+    pub fn transform<F: Fn(&Pt2D) -> Pt2D>(&self, f: F) -> Polygon {
         Polygon {
             points: self.points.iter().map(&f).collect(),
             indices: self.indices.clone(),
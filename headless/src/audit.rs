@@ -0,0 +1,180 @@
+//! Scan a map for data that's probably wrong, and write the locations as GeoJSON. Each feature
+//! links to the OpenStreetMap object to check, so the file can be loaded into an editor like JOSM
+//! and worked through.
+//!
+//! > cd headless; cargo run -- audit --map=../data/system/seattle/maps/montlake.bin --output=audit.geojson
+//!
+//! These problems are flagged:
+//!
+//! - `no sidewalks`: a road with driving lanes, but no sidewalks on either side. Highways, service
+//!   roads, and private roads are skipped, since they often really don't have sidewalks.
+//! - `degenerate signal`: a traffic signal with no stages, a stage that lasts no time or lets no
+//!   movement through, or a vehicle movement that no stage ever allows
+//! - `driving blackhole` and `biking blackhole`: a lane that vehicles can enter but never leave,
+//!   or never reach in the first place. Usually a oneway tagged in the wrong direction or a
+//!   disconnected road.
+//! - `short lane`: a lane shorter than a meter, usually from two OSM nodes placed nearly on top of
+//!   each other
+
+use geojson::{Feature, FeatureCollection, GeoJson};
+
+use abstutil::{prettyprint_usize, CmdArgs, Counter, Timer};
+use geom::{Distance, Duration, PolyLine, Ring};
+use map_model::{osm, LaneType, Map, TurnType};
+
+const SHORT_LANE: Distance = Distance::const_meters(1.0);
+
+pub fn run(mut args: CmdArgs) {
+    let map_path = args.required("--map");
+    let output = args.required("--output");
+    args.done();
+
+    let mut timer = Timer::new("audit map");
+    let map = Map::new(map_path, &mut timer);
+    let problems = audit(&map);
+
+    let mut counts = Counter::new();
+    for p in &problems {
+        counts.inc(p.problem);
+    }
+    for (problem, count) in counts.consume() {
+        println!("{}: {}", problem, prettyprint_usize(count));
+    }
+
+    let gps_bounds = Some(map.get_gps_bounds());
+    let features = problems
+        .into_iter()
+        .map(|p| {
+            let mut props = serde_json::Map::new();
+            props.insert("problem".to_string(), p.problem.into());
+            props.insert("description".to_string(), p.description.into());
+            props.insert("osm".to_string(), p.osm.into());
+            Feature {
+                bbox: None,
+                geometry: Some(match p.geometry {
+                    Geom::Line(pl) => pl.to_geojson(gps_bounds),
+                    Geom::Area(ring) => ring.to_geojson(gps_bounds),
+                }),
+                id: None,
+                properties: Some(props),
+                foreign_members: None,
+            }
+        })
+        .collect();
+    abstutil::write_json(
+        output,
+        &GeoJson::from(FeatureCollection {
+            bbox: None,
+            features,
+            foreign_members: None,
+        }),
+    );
+}
+
+struct Problem {
+    problem: &'static str,
+    description: String,
+    /// A link to the OSM object to check
+    osm: String,
+    geometry: Geom,
+}
+
+enum Geom {
+    Line(PolyLine),
+    Area(Ring),
+}
+
+fn audit(map: &Map) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for r in map.all_roads() {
+        if r.get_rank() == osm::RoadRank::Highway
+            || r.is_service()
+            || r.is_private()
+            || r.is_light_rail()
+            || r.is_footway()
+        {
+            continue;
+        }
+        let lts: Vec<LaneType> = r.lanes_ltr().into_iter().map(|(_, _, lt)| lt).collect();
+        if lts.contains(&LaneType::Driving) && !lts.contains(&LaneType::Sidewalk) {
+            problems.push(Problem {
+                problem: "no sidewalks",
+                description: format!("{} has no sidewalks", r.get_name(None)),
+                osm: r.orig_id.osm_way_id.to_string(),
+                geometry: Geom::Line(r.center_pts.clone()),
+            });
+        }
+    }
+
+    for i in map.all_intersections() {
+        if !i.is_traffic_signal() {
+            continue;
+        }
+        let signal = map.get_traffic_signal(i.id);
+        let mut reasons = Vec::new();
+        if signal.stages.is_empty() {
+            reasons.push("has no stages".to_string());
+        }
+        for (idx, stage) in signal.stages.iter().enumerate() {
+            if stage.phase_type.simple_duration() == Duration::ZERO {
+                reasons.push(format!("stage {} lasts no time", idx + 1));
+            }
+            if stage.protected_movements.is_empty() && stage.yield_movements.is_empty() {
+                reasons.push(format!("stage {} doesn't let anything through", idx + 1));
+            }
+        }
+        let never_served = signal
+            .movements
+            .values()
+            .filter(|m| {
+                m.turn_type != TurnType::Crosswalk
+                    && m.turn_type != TurnType::SharedSidewalkCorner
+                    && signal.stages.iter().all(|s| {
+                        !s.protected_movements.contains(&m.id) && !s.yield_movements.contains(&m.id)
+                    })
+            })
+            .count();
+        if never_served > 0 {
+            reasons.push(format!(
+                "{} vehicle movements are never allowed",
+                never_served
+            ));
+        }
+        if !reasons.is_empty() {
+            problems.push(Problem {
+                problem: "degenerate signal",
+                description: format!("The signal at {} {}", i.id, reasons.join(", ")),
+                osm: i.orig_id.to_string(),
+                geometry: Geom::Area(i.polygon.clone().into_ring()),
+            });
+        }
+    }
+
+    for l in map.all_lanes() {
+        let way = map.get_parent(l.id).orig_id.osm_way_id.to_string();
+        for (blackhole, problem) in vec![
+            (l.driving_blackhole, "driving blackhole"),
+            (l.biking_blackhole, "biking blackhole"),
+        ] {
+            if blackhole {
+                problems.push(Problem {
+                    problem,
+                    description: format!("{} is a {}", l.id, problem),
+                    osm: way.clone(),
+                    geometry: Geom::Line(l.lane_center_pts.clone()),
+                });
+            }
+        }
+        if l.length() < SHORT_LANE {
+            problems.push(Problem {
+                problem: "short lane",
+                description: format!("{} is only {} long", l.id, l.length()),
+                osm: way,
+                geometry: Geom::Line(l.lane_center_pts.clone()),
+            });
+        }
+    }
+
+    problems
+}
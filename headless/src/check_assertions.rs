@@ -0,0 +1,296 @@
+//! Run a simulation and check some assertions about how it went, for regression suites of
+//! scenarios. If any assertion fails, the run stops right away, saves a savestate to debug it,
+//! and exits with a failure.
+//!
+//! > cd headless; cargo run -- check_assertions --scenario=../data/system/seattle/scenarios/montlake/weekday.bin --assertions=montlake.txt
+//!
+//! The assertions file has one per line. Blank lines and lines starting with # are ignored.
+//!
+//! ```text
+//! # Times are like 9am, 5:30pm, or 17:30:00. Durations are like 20 minutes or 00:20:00.
+//! by 9am at least 95% of trips complete
+//! no agent waits over 20 minutes at intersection 42
+//! no agent waits over 1 hour at any intersection
+//! ```
+//!
+//! The simulation runs until the latest `by` time, or `--end_time` if it's set. Pass `--edits` to
+//! apply map edits first.
+
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{CmdArgs, Timer};
+use geom::{Duration, Time};
+use map_model::{IntersectionID, Map, MapEdits};
+use sim::{Scenario, Sim, SimFlags, SimOptions};
+
+/// How often the waiting assertions are checked
+const CHECK_EVERY: Duration = Duration::const_seconds(60.0);
+
+pub fn run(mut args: CmdArgs) {
+    let rng_seed = args
+        .optional_parse("--rng_seed", |s| s.parse())
+        .unwrap_or(SimFlags::RNG_SEED);
+    let opts = SimOptions::from_args(&mut args, rng_seed);
+    let scenario_path = args.required("--scenario");
+    let edits_path = args.optional("--edits");
+    let assertions_path = args.required("--assertions");
+    let end_time = args.optional_parse("--end_time", |s| Time::parse(s));
+    args.done();
+
+    let contents = std::fs::read_to_string(&assertions_path)
+        .unwrap_or_else(|err| panic!("Couldn't read {}: {}", assertions_path, err));
+    let assertions = parse(&contents).unwrap_or_else(|err| {
+        println!("{}: {}", assertions_path, err);
+        std::process::exit(2);
+    });
+    let end_time = end_time
+        .or_else(|| assertions.iter().filter_map(|a| a.deadline()).max())
+        .unwrap_or_else(|| {
+            println!("Nothing says when to stop; pass --end_time");
+            std::process::exit(2);
+        });
+
+    let mut timer = Timer::new("check assertions");
+    let scenario: Scenario = abstutil::must_read_versioned_object(scenario_path, &mut timer);
+    let mut map = Map::new(scenario.map_name.path(), &mut timer);
+    if let Some(path) = edits_path {
+        let edits = MapEdits::load(&map, path.clone(), &mut timer)
+            .unwrap_or_else(|err| panic!("Couldn't load edits {}: {}", path, err));
+        map.must_apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+    }
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let mut sim = Sim::new(&map, opts, &mut timer);
+    scenario.instantiate(&mut sim, &map, &mut rng, &mut timer);
+
+    let mut pending: Vec<&Assertion> = assertions.iter().collect();
+    while sim.time() < end_time {
+        // Stop exactly at deadlines, so trips finishing right after don't count
+        let mut next = (sim.time() + CHECK_EVERY).min(end_time);
+        if let Some(t) = pending.iter().filter_map(|a| a.deadline()).min() {
+            next = next.min(t);
+        }
+        sim.timed_step(&map, next - sim.time(), &mut None, &mut Timer::throwaway());
+
+        let mut still_pending = Vec::new();
+        for assertion in pending {
+            match assertion.check(&sim) {
+                Check::Passed => {
+                    println!("Passed: {}", assertion.describe());
+                }
+                Check::Pending => {
+                    still_pending.push(assertion);
+                }
+                Check::Failed(why) => {
+                    let path = sim.save();
+                    println!("Failed at {}: {}", sim.time(), assertion.describe());
+                    println!("  {}", why);
+                    println!("  Savestate to debug: {}", path);
+                    std::process::exit(1);
+                }
+            }
+        }
+        pending = still_pending;
+    }
+    // Waiting assertions hold for the whole run
+    for assertion in pending {
+        println!("Passed: {}", assertion.describe());
+    }
+    println!("All {} assertions passed", assertions.len());
+}
+
+enum Assertion {
+    TripsComplete {
+        by: Time,
+        min_pct: f64,
+    },
+    MaxWait {
+        at: Option<IntersectionID>,
+        max: Duration,
+    },
+}
+
+enum Check {
+    Passed,
+    Pending,
+    Failed(String),
+}
+
+impl Assertion {
+    /// If the assertion is about some particular time, returns it.
+    fn deadline(&self) -> Option<Time> {
+        match self {
+            Assertion::TripsComplete { by, .. } => Some(*by),
+            Assertion::MaxWait { .. } => None,
+        }
+    }
+
+    fn check(&self, sim: &Sim) -> Check {
+        match self {
+            Assertion::TripsComplete { by, min_pct } => {
+                if sim.time() < *by {
+                    return Check::Pending;
+                }
+                let (finished, unfinished) = sim.num_trips();
+                let completed = sim
+                    .get_analytics()
+                    .finished_trips
+                    .iter()
+                    .filter(|(_, _, _, dt)| dt.is_some())
+                    .count();
+                let total = finished + unfinished;
+                let pct = if total == 0 {
+                    100.0
+                } else {
+                    100.0 * (completed as f64) / (total as f64)
+                };
+                if pct >= *min_pct {
+                    Check::Passed
+                } else {
+                    Check::Failed(format!(
+                        "only {} of {} trips ({:.1}%) completed",
+                        completed, total, pct
+                    ))
+                }
+            }
+            Assertion::MaxWait { at: Some(i), max } => {
+                for (agent, _, started) in sim.get_waiting_agents(*i) {
+                    if sim.time() - started > *max {
+                        return Check::Failed(format!(
+                            "{} has been waiting at {} since {}",
+                            agent, i, started
+                        ));
+                    }
+                }
+                // Agents who already made it through, but waited too long
+                if let Some(delays) = sim.get_analytics().intersection_delays.get(i) {
+                    if let Some((_, t, dt, _)) = delays.iter().find(|(_, _, dt, _)| *dt > *max) {
+                        return Check::Failed(format!(
+                            "an agent finished waiting {} at {} at {}",
+                            dt, i, t
+                        ));
+                    }
+                }
+                Check::Pending
+            }
+            Assertion::MaxWait { at: None, max } => {
+                if let Some((i, started)) = sim.delayed_intersections(*max).into_iter().next() {
+                    return Check::Failed(format!(
+                        "an agent has been waiting at {} since {}",
+                        i, started
+                    ));
+                }
+                Check::Pending
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Assertion::TripsComplete { by, min_pct } => format!(
+                "by {} at least {}% of trips complete",
+                by.ampm_tostring(),
+                min_pct
+            ),
+            Assertion::MaxWait { at: Some(i), max } => {
+                format!("no agent waits over {} at {}", max, i)
+            }
+            Assertion::MaxWait { at: None, max } => {
+                format!("no agent waits over {} at any intersection", max)
+            }
+        }
+    }
+}
+
+fn parse(contents: &str) -> Result<Vec<Assertion>, String> {
+    let mut assertions = Vec::new();
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let assertion = parse_assertion(&words)
+            .map_err(|err| format!("line {}: {} ({})", idx + 1, err, line))?;
+        assertions.push(assertion);
+    }
+    Ok(assertions)
+}
+
+fn parse_assertion(words: &[&str]) -> Result<Assertion, String> {
+    match words {
+        ["by", time, "at", "least", pct, "of", "trips", "complete"] => {
+            let min_pct = pct
+                .strip_suffix('%')
+                .and_then(|x| x.parse::<f64>().ok())
+                .ok_or_else(|| format!("bad percentage {}", pct))?;
+            Ok(Assertion::TripsComplete {
+                by: parse_time(time)?,
+                min_pct,
+            })
+        }
+        ["no", "agent", "waits", "over", rest @ ..] => {
+            let at_idx = rest
+                .iter()
+                .position(|w| *w == "at")
+                .ok_or_else(|| "missing at".to_string())?;
+            let max = parse_duration(&rest[..at_idx])?;
+            let at = match &rest[at_idx + 1..] {
+                ["any", "intersection"] => None,
+                ["intersection", id] => Some(IntersectionID(
+                    id.parse::<usize>()
+                        .map_err(|_| format!("bad intersection {}", id))?,
+                )),
+                _ => {
+                    return Err("expected at intersection ID or at any intersection".to_string());
+                }
+            };
+            Ok(Assertion::MaxWait { at, max })
+        }
+        _ => Err("don't understand this assertion".to_string()),
+    }
+}
+
+/// Understands 9am, 5:30pm, and anything Time::parse does
+fn parse_time(x: &str) -> Result<Time, String> {
+    let (clock, pm) = if let Some(clock) = x.strip_suffix("am") {
+        (clock, false)
+    } else if let Some(clock) = x.strip_suffix("pm") {
+        (clock, true)
+    } else {
+        return Time::parse(x).map_err(|_| format!("bad time {}", x));
+    };
+    let mut parts = clock.split(':');
+    let hours = parts.next().and_then(|h| h.parse::<usize>().ok());
+    let minutes = match parts.next() {
+        Some(m) => m.parse::<usize>().ok(),
+        None => Some(0),
+    };
+    match (hours, minutes, parts.next()) {
+        (Some(h), Some(m), None) if (1..=12).contains(&h) && m < 60 => {
+            let h = (h % 12) + if pm { 12 } else { 0 };
+            Ok(Time::START_OF_DAY + Duration::hours(h) + Duration::minutes(m))
+        }
+        _ => Err(format!("bad time {}", x)),
+    }
+}
+
+/// Understands 20 minutes, 1 hour, 90 seconds, and anything Duration::parse does
+fn parse_duration(words: &[&str]) -> Result<Duration, String> {
+    match words {
+        [x] => Duration::parse(x).map_err(|_| format!("bad duration {}", x)),
+        [n, unit] => {
+            let n = n
+                .parse::<f64>()
+                .map_err(|_| format!("bad duration {} {}", n, unit))?;
+            match unit.trim_end_matches('s') {
+                "second" => Ok(Duration::seconds(n)),
+                "minute" => Ok(Duration::seconds(60.0 * n)),
+                "hour" => Ok(Duration::seconds(3600.0 * n)),
+                _ => Err(format!("bad unit {}", unit)),
+            }
+        }
+        _ => Err(format!("bad duration {}", words.join(" "))),
+    }
+}
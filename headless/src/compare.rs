@@ -0,0 +1,212 @@
+//! Run two simulations to the same end time and diff the results, for A/B testing without the GUI.
+//! Each side can use a different scenario and/or different map edits.
+//!
+//! > cd headless; cargo run -- compare --scenario1=../data/system/seattle/scenarios/montlake/weekday.bin --edits2=../data/player/edits/seattle/montlake/my_edits.json --end_time=12:00:00 --output=compare_results
+//!
+//! This writes trip_times.csv, intersection_delays.csv, road_thruput.csv, and summary.json to the
+//! output directory.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use serde::Serialize;
+
+use abstutil::{CmdArgs, Timer};
+use geom::{Duration, Time};
+use map_model::{IntersectionID, Map, MapEdits, RoadID};
+use sim::{Analytics, Scenario, Sim, SimFlags, SimOptions};
+
+pub fn run(mut args: CmdArgs) {
+    let rng_seed = args
+        .optional_parse("--rng_seed", |s| s.parse())
+        .unwrap_or(SimFlags::RNG_SEED);
+    let opts = SimOptions::from_args(&mut args, rng_seed);
+    let scenario1 = args.required("--scenario1");
+    let scenario2 = args
+        .optional("--scenario2")
+        .unwrap_or_else(|| scenario1.clone());
+    let edits1 = args.optional("--edits1");
+    let edits2 = args.optional("--edits2");
+    let end_time = args.required("--end_time");
+    let end_time = Time::parse(&end_time).unwrap_or_else(|_| panic!("Bad --end_time={}", end_time));
+    let output = args.required("--output");
+    args.done();
+
+    let mut timer = Timer::new("compare two runs");
+    let (before, mut toll_roads) =
+        run_side(&scenario1, edits1, rng_seed, &opts, end_time, &mut timer);
+    let (after, toll_roads2) = run_side(&scenario2, edits2, rng_seed, &opts, end_time, &mut timer);
+    toll_roads.extend(toll_roads2);
+
+    std::fs::create_dir_all(&output).unwrap();
+    let summary = write_results(&output, end_time, &before, &after, &toll_roads).unwrap();
+    abstutil::write_json(format!("{}/summary.json", output), &summary);
+    println!("Wrote results to {}", output);
+}
+
+fn run_side(
+    scenario_path: &str,
+    edits_path: Option<String>,
+    rng_seed: u64,
+    opts: &SimOptions,
+    end_time: Time,
+    timer: &mut Timer,
+) -> (Analytics, BTreeSet<RoadID>) {
+    let scenario: Scenario = abstutil::must_read_versioned_object(scenario_path.to_string(), timer);
+    let mut map = Map::new(scenario.map_name.path(), timer);
+    if let Some(path) = edits_path {
+        let edits = MapEdits::load(&map, path.clone(), timer)
+            .unwrap_or_else(|err| panic!("Couldn't load edits {}: {}", path, err));
+        map.must_apply_edits(edits, timer);
+        map.recalculate_pathfinding_after_edits(timer);
+    }
+
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let mut sim = Sim::new(&map, opts.clone(), timer);
+    scenario.instantiate(&mut sim, &map, &mut rng, timer);
+    sim.timed_step(&map, end_time - Time::START_OF_DAY, &mut None, timer);
+
+    let mut toll_roads = BTreeSet::new();
+    for zone in map.get_toll_zones() {
+        toll_roads.extend(zone.roads_inside(&map));
+    }
+    (sim.get_analytics().clone(), toll_roads)
+}
+
+#[derive(Serialize)]
+struct Summary {
+    end_time: Time,
+    trips_finished_before: usize,
+    trips_finished_after: usize,
+    trips_faster: usize,
+    trips_slower: usize,
+    total_trip_time_before: Duration,
+    total_trip_time_after: Duration,
+    total_road_thruput_before: usize,
+    total_road_thruput_after: usize,
+    toll_revenue_before: f64,
+    toll_revenue_after: f64,
+    /// Cars and trucks using roads inside the toll zones of either side
+    toll_zone_vehicles_before: usize,
+    toll_zone_vehicles_after: usize,
+}
+
+fn write_results(
+    dir: &str,
+    end_time: Time,
+    before: &Analytics,
+    after: &Analytics,
+    toll_roads: &BTreeSet<RoadID>,
+) -> Result<Summary, Box<dyn Error>> {
+    let mut summary = Summary {
+        end_time,
+        trips_finished_before: count_finished(before, end_time),
+        trips_finished_after: count_finished(after, end_time),
+        trips_faster: 0,
+        trips_slower: 0,
+        total_trip_time_before: Duration::ZERO,
+        total_trip_time_after: Duration::ZERO,
+        total_road_thruput_before: 0,
+        total_road_thruput_after: 0,
+        toll_revenue_before: before.toll_revenue(end_time),
+        toll_revenue_after: after.toll_revenue(end_time),
+        toll_zone_vehicles_before: before.vehicles_on_roads(toll_roads, end_time),
+        toll_zone_vehicles_after: after.vehicles_on_roads(toll_roads, end_time),
+    };
+
+    {
+        let mut f = File::create(format!("{}/trip_times.csv", dir))?;
+        writeln!(f, "id,mode,seconds_before,seconds_after")?;
+        for (id, b, a, mode) in after.both_finished_trips(end_time, before) {
+            writeln!(
+                f,
+                "{},{:?},{},{}",
+                id.0,
+                mode,
+                b.inner_seconds(),
+                a.inner_seconds()
+            )?;
+            summary.total_trip_time_before += b;
+            summary.total_trip_time_after += a;
+            if a < b {
+                summary.trips_faster += 1;
+            } else if a > b {
+                summary.trips_slower += 1;
+            }
+        }
+    }
+
+    {
+        let delays_before = delays_per_intersection(before);
+        let mut delays_after = delays_per_intersection(after);
+        let mut f = File::create(format!("{}/intersection_delays.csv", dir))?;
+        writeln!(
+            f,
+            "intersection,count_before,mean_seconds_before,count_after,mean_seconds_after"
+        )?;
+        for (i, (cnt1, total1)) in delays_before {
+            let (cnt2, total2) = delays_after.remove(&i).unwrap_or((0, Duration::ZERO));
+            writeln!(
+                f,
+                "{},{},{},{},{}",
+                i.0,
+                cnt1,
+                mean_seconds(cnt1, total1),
+                cnt2,
+                mean_seconds(cnt2, total2)
+            )?;
+        }
+        for (i, (cnt2, total2)) in delays_after {
+            writeln!(f, "{},0,0,{},{}", i.0, cnt2, mean_seconds(cnt2, total2))?;
+        }
+    }
+
+    {
+        let mut f = File::create(format!("{}/road_thruput.csv", dir))?;
+        writeln!(f, "road,thruput_before,thruput_after")?;
+        for (r, cnt1, cnt2) in before
+            .road_thruput
+            .all_total_counts()
+            .compare(after.road_thruput.all_total_counts())
+        {
+            writeln!(f, "{},{},{}", r.0, cnt1, cnt2)?;
+            summary.total_road_thruput_before += cnt1;
+            summary.total_road_thruput_after += cnt2;
+        }
+    }
+
+    Ok(summary)
+}
+
+fn count_finished(analytics: &Analytics, end_time: Time) -> usize {
+    analytics
+        .finished_trips
+        .iter()
+        .filter(|(t, _, _, dt)| *t <= end_time && dt.is_some())
+        .count()
+}
+
+/// Per intersection, the number of delays recorded and their sum
+fn delays_per_intersection(analytics: &Analytics) -> BTreeMap<IntersectionID, (usize, Duration)> {
+    let mut results = BTreeMap::new();
+    for (i, list) in &analytics.intersection_delays {
+        let mut total = Duration::ZERO;
+        for (_, _, dt, _) in list {
+            total += *dt;
+        }
+        results.insert(*i, (list.len(), total));
+    }
+    results
+}
+
+fn mean_seconds(count: usize, total: Duration) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        total.inner_seconds() / (count as f64)
+    }
+}
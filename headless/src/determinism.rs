@@ -0,0 +1,178 @@
+//! Run two copies of the same simulation at once, each in its own thread, and compare checksums of
+//! every agent's position at regular checkpoints. With the same scenario and RNG seed, both copies
+//! should always match, so a mismatch means something nondeterministic crept in, like iterating
+//! over a HashMap. Each copy also remembers the commands it processed since the last checkpoint,
+//! so the first command that differs can be printed.
+//!
+//! > cd headless; cargo run --release -- determinism --scenario=../data/system/seattle/scenarios/montlake/weekday.bin --end_time=12:00:00
+//!
+//! Checkpoints are every 60 seconds of simulation time by default; change with --every_seconds.
+//! Without --end_time, the whole scenario runs. Pass --edits to apply map edits first. Flags for
+//! SimOptions, like --infinite_parking, apply to both copies. If the copies diverge, this exits
+//! with an error after describing where.
+
+use std::sync::mpsc;
+
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{prettyprint_usize, CmdArgs, Timer};
+use geom::{Duration, Time};
+use map_model::{Map, MapEdits};
+use sim::{Scenario, Sim, SimFlags, SimOptions};
+
+/// How many commands before the first difference to print, for context
+const CONTEXT: usize = 5;
+
+struct Checkpoint {
+    time: Time,
+    checksum: u64,
+    commands: Vec<String>,
+    done: bool,
+}
+
+pub fn run(mut args: CmdArgs) {
+    let rng_seed = args
+        .optional_parse("--rng_seed", |s| s.parse())
+        .unwrap_or(SimFlags::RNG_SEED);
+    let opts = SimOptions::from_args(&mut args, rng_seed);
+    let scenario_path = args.required("--scenario");
+    let edits_path = args.optional("--edits");
+    let every = Duration::seconds(
+        args.optional_parse("--every_seconds", |s| s.parse::<f64>())
+            .unwrap_or(60.0),
+    );
+    let end_time = args
+        .optional("--end_time")
+        .map(|t| Time::parse(&t).unwrap_or_else(|_| panic!("Bad --end_time={}", t)));
+    args.done();
+
+    let mut timer = Timer::new("check determinism");
+    let scenario: Scenario = abstutil::must_read_versioned_object(scenario_path, &mut timer);
+    let mut map = Map::new(scenario.map_name.path(), &mut timer);
+    if let Some(path) = edits_path {
+        let edits = MapEdits::load(&map, path.clone(), &mut timer)
+            .unwrap_or_else(|err| panic!("Couldn't load edits {}: {}", path, err));
+        map.must_apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+    }
+
+    let map = &map;
+    let scenario = &scenario;
+    let opts = &opts;
+    let (checkpoint_tx, checkpoint_rx) = mpsc::channel();
+    let mut last_match = Time::START_OF_DAY;
+    let mut diverged = false;
+    scoped_threadpool::Pool::new(2).scoped(|scope| {
+        let mut continue_txs = Vec::new();
+        for copy in 0..2 {
+            let checkpoint_tx = checkpoint_tx.clone();
+            let (continue_tx, continue_rx) = mpsc::channel();
+            continue_txs.push(continue_tx);
+            scope.execute(move || {
+                let mut timer = Timer::throwaway();
+                let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+                let mut sim = Sim::new(map, opts.clone(), &mut timer);
+                scenario.instantiate(&mut sim, map, &mut rng, &mut timer);
+                sim.audit_commands();
+                let end_time = end_time.unwrap_or_else(|| sim.get_end_of_day());
+                loop {
+                    let dt = every.min(end_time - sim.time());
+                    sim.timed_step(map, dt, &mut None, &mut timer);
+                    let done = sim.time() >= end_time;
+                    checkpoint_tx
+                        .send((
+                            copy,
+                            Checkpoint {
+                                time: sim.time(),
+                                checksum: sim.checksum(map),
+                                commands: sim.take_audited_commands(),
+                                done,
+                            },
+                        ))
+                        .unwrap();
+                    // Wait for the other copy to reach the same checkpoint
+                    if done || !continue_rx.recv().unwrap_or(false) {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(checkpoint_tx);
+
+        loop {
+            let mut checkpoints = vec![None, None];
+            for _ in 0..2 {
+                let (copy, checkpoint) = checkpoint_rx.recv().unwrap();
+                checkpoints[copy] = Some(checkpoint);
+            }
+            let copy2 = checkpoints.pop().unwrap().unwrap();
+            let copy1 = checkpoints.pop().unwrap().unwrap();
+            assert_eq!(copy1.time, copy2.time);
+
+            if copy1.checksum != copy2.checksum || copy1.commands != copy2.commands {
+                describe_divergence(last_match, &copy1, &copy2);
+                diverged = true;
+                for tx in &continue_txs {
+                    // The copy might've already stopped
+                    let _ = tx.send(false);
+                }
+                break;
+            }
+            last_match = copy1.time;
+            if copy1.done {
+                break;
+            }
+            for tx in &continue_txs {
+                tx.send(true).unwrap();
+            }
+        }
+    });
+
+    if diverged {
+        std::process::exit(1);
+    }
+    println!(
+        "Both copies matched at every checkpoint through {}",
+        last_match
+    );
+}
+
+fn describe_divergence(last_match: Time, copy1: &Checkpoint, copy2: &Checkpoint) {
+    println!(
+        "The copies diverged between {} and {}",
+        last_match, copy1.time
+    );
+    let (cmds1, cmds2) = (&copy1.commands, &copy2.commands);
+    let idx = match cmds1.iter().zip(cmds2.iter()).position(|(c1, c2)| c1 != c2) {
+        Some(idx) => idx,
+        None if cmds1.len() != cmds2.len() => cmds1.len().min(cmds2.len()),
+        None => {
+            println!(
+                "Both copies processed the same {} commands, but agents wound up in different \
+                 places. Something not driven by a command differs, like the order agents are \
+                 updated in.",
+                prettyprint_usize(cmds1.len())
+            );
+            return;
+        }
+    };
+
+    println!(
+        "The first {} commands since then matched. The last few:",
+        prettyprint_usize(idx)
+    );
+    for cmd in &cmds1[idx.saturating_sub(CONTEXT)..idx] {
+        println!("  {}", cmd);
+    }
+    println!("Then the first copy did:");
+    println!(
+        "  {}",
+        cmds1.get(idx).map(|c| c.as_str()).unwrap_or("nothing")
+    );
+    println!("But the second copy did:");
+    println!(
+        "  {}",
+        cmds2.get(idx).map(|c| c.as_str()).unwrap_or("nothing")
+    );
+}
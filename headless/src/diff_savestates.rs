@@ -0,0 +1,146 @@
+//! Load two savestates from the same map and print a structured diff of their state. Two runs of
+//! the same scenario with the same RNG seed should produce identical savestates, so this helps
+//! track down nondeterminism.
+//!
+//! > cd headless; cargo run -- diff_savestates --savestate1=../data/player/saves/montlake/no_edits_weekday/08h00m00.0s.bin --savestate2=../data/player/saves/montlake/no_edits_weekday_2/08h00m00.0s.bin
+//!
+//! Both savestates must be under data/player/saves/, so the map and edits can be found. The diff is
+//! printed as JSON, or written to --output.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use abstutil::{CmdArgs, Timer};
+use geom::{Distance, Pt2D, Time};
+use map_model::Map;
+use sim::{AgentID, Sim, SimFlags, SimOptions};
+
+pub fn run(mut args: CmdArgs) {
+    let savestate1 = args.required("--savestate1");
+    let savestate2 = args.required("--savestate2");
+    let threshold = Distance::meters(
+        args.optional_parse("--threshold_meters", |s| s.parse::<f64>())
+            .unwrap_or(0.01),
+    );
+    let output = args.optional("--output");
+    args.done();
+
+    let mut timer = Timer::new("diff savestates");
+    let (map1, sim1) = load(savestate1, &mut timer);
+    let (map2, sim2) = load(savestate2, &mut timer);
+    if map1.get_name() != map2.get_name() {
+        panic!(
+            "The savestates are from different maps: {} and {}",
+            map1.get_name().describe(),
+            map2.get_name().describe()
+        );
+    }
+
+    let diff = diff(&map1, &sim1, &map2, &sim2, threshold);
+    if let Some(path) = output {
+        abstutil::write_json(path, &diff);
+    } else {
+        println!("{}", abstutil::to_json(&diff));
+    }
+}
+
+fn load(path: String, timer: &mut Timer) -> (Map, Sim) {
+    let flags = SimFlags {
+        load: path,
+        modifiers: Vec::new(),
+        rng_seed: SimFlags::RNG_SEED,
+        opts: SimOptions::default(),
+    };
+    let (map, sim, _) = flags.load(timer);
+    (map, sim)
+}
+
+#[derive(Serialize)]
+struct SavestateDiff {
+    time1: Time,
+    time2: Time,
+    only_in_1: Vec<AgentID>,
+    only_in_2: Vec<AgentID>,
+    divergent_positions: Vec<DivergentPosition>,
+    only_scheduled_in_1: Vec<(String, Time)>,
+    only_scheduled_in_2: Vec<(String, Time)>,
+    divergent_schedules: Vec<DivergentSchedule>,
+}
+
+#[derive(Serialize)]
+struct DivergentPosition {
+    agent: AgentID,
+    pos1: Option<Pt2D>,
+    pos2: Option<Pt2D>,
+    distance: Option<Distance>,
+}
+
+#[derive(Serialize)]
+struct DivergentSchedule {
+    command: String,
+    time1: Time,
+    time2: Time,
+}
+
+fn diff(map1: &Map, sim1: &Sim, map2: &Map, sim2: &Sim, threshold: Distance) -> SavestateDiff {
+    let agents1: BTreeSet<AgentID> = sim1.active_agents().into_iter().collect();
+    let agents2: BTreeSet<AgentID> = sim2.active_agents().into_iter().collect();
+
+    let mut divergent_positions = Vec::new();
+    for agent in agents1.intersection(&agents2) {
+        let pos1 = sim1.canonical_pt_for_agent(*agent, map1);
+        let pos2 = sim2.canonical_pt_for_agent(*agent, map2);
+        let distance = match (pos1, pos2) {
+            (Some(pt1), Some(pt2)) => {
+                let dist = pt1.dist_to(pt2);
+                if dist <= threshold {
+                    continue;
+                }
+                Some(dist)
+            }
+            (None, None) => {
+                continue;
+            }
+            _ => None,
+        };
+        divergent_positions.push(DivergentPosition {
+            agent: *agent,
+            pos1,
+            pos2,
+            distance,
+        });
+    }
+
+    let schedule1 = sim1.get_scheduled_commands();
+    let mut schedule2 = sim2.get_scheduled_commands();
+    let mut only_scheduled_in_1 = Vec::new();
+    let mut divergent_schedules = Vec::new();
+    for (command, time1) in schedule1 {
+        match schedule2.remove(&command) {
+            Some(time2) => {
+                if time1 != time2 {
+                    divergent_schedules.push(DivergentSchedule {
+                        command,
+                        time1,
+                        time2,
+                    });
+                }
+            }
+            None => {
+                only_scheduled_in_1.push((command, time1));
+            }
+        }
+    }
+
+    SavestateDiff {
+        time1: sim1.time(),
+        time2: sim2.time(),
+        only_in_1: agents1.difference(&agents2).cloned().collect(),
+        only_in_2: agents2.difference(&agents1).cloned().collect(),
+        divergent_positions,
+        only_scheduled_in_1,
+        only_scheduled_in_2: schedule2.into_iter().collect(),
+        divergent_schedules,
+    }
+}
@@ -0,0 +1,241 @@
+//! Apply a list of edit commands from a text file and save the result as a proposal, so many
+//! candidate proposals can be generated by a script instead of clicking through the editor.
+//!
+//! > cd headless; cargo run -- edit --map=../data/system/seattle/maps/montlake.bin --commands=bike_lanes.txt --output=bike_lanes
+//!
+//! This writes data/player/edits/<city>/<map>/bike_lanes.json. Pass --edits to start from an
+//! existing proposal instead of the original map. The commands file has one command per line;
+//! blank lines and lines starting with # are ignored:
+//!
+//! ```text
+//! road 123: convert rightmost Driving to Biking
+//! road 123: convert leftmost Parking to Driving
+//! road 123: convert every Parking to Bus
+//! road 123: speed limit 25 mph
+//! intersection 45: stop sign
+//! intersection 45: traffic signal
+//! intersection 45: load signal plan traffic_signal_data/53086187.json
+//! intersection 45: close
+//! ```
+//!
+//! Lane types are the ones the lane editor offers: Driving, Biking, Bus, Parking, and
+//! Construction. Left and right are from the point of view of a road's lanes listed left to
+//! right. Each command applies to the map as edited by the commands before it, and any command
+//! that would break the map (like a parking lane without a driving lane) stops everything.
+
+use std::collections::BTreeSet;
+
+use abstutil::{CmdArgs, Timer};
+use geom::Speed;
+use map_model::{
+    ControlStopSign, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, LaneType,
+    Map, MapEdits, PathConstraints, RoadID,
+};
+
+pub fn run(mut args: CmdArgs) {
+    let map_path = args.required("--map");
+    let edits_path = args.optional("--edits");
+    let commands_path = args.required("--commands");
+    let output = args.required("--output");
+    args.done();
+
+    let mut timer = Timer::new("apply scripted edits");
+    let mut map = Map::new(map_path, &mut timer);
+    if let Some(path) = edits_path {
+        let edits = MapEdits::load(&map, path.clone(), &mut timer)
+            .unwrap_or_else(|err| panic!("Couldn't load edits {}: {}", path, err));
+        map.must_apply_edits(edits, &mut timer);
+    }
+
+    let contents = String::from_utf8(
+        abstutil::slurp_file(&commands_path)
+            .unwrap_or_else(|err| panic!("Couldn't read {}: {}", commands_path, err)),
+    )
+    .unwrap_or_else(|err| panic!("{} isn't valid UTF-8: {}", commands_path, err));
+    let mut num_cmds = 0;
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cmd = parse_cmd(&map, line, &mut timer)
+            .unwrap_or_else(|err| panic!("{} line {}: {}", commands_path, idx + 1, err));
+        let changed_road = match cmd {
+            EditCmd::ChangeRoad { r, .. } => Some(r),
+            _ => None,
+        };
+        let mut edits = map.get_edits().clone();
+        edits.commands.push(cmd);
+        map.must_apply_edits(edits, &mut Timer::throwaway());
+        if let Some(r) = changed_road {
+            if let Err(err) = validate(&map, r) {
+                panic!("{} line {}: {}", commands_path, idx + 1, err);
+            }
+        }
+        num_cmds += 1;
+    }
+
+    let mut edits = map.get_edits().clone();
+    edits.edits_name = output;
+    let path = abstutil::path_edits(map.get_name(), &edits.edits_name);
+    abstutil::write_json(path.clone(), &edits.to_permanent(&map));
+    println!("Applied {} commands. Wrote {}", num_cmds, path);
+}
+
+fn parse_cmd(map: &Map, line: &str, timer: &mut Timer) -> Result<EditCmd, String> {
+    let (target, action) = split_once(line, ": ")
+        .ok_or_else(|| format!("expected \"road 123: ...\" or \"intersection 45: ...\""))?;
+    if let Some(id) = target.strip_prefix("road ") {
+        let r = RoadID(parse_id(id)?);
+        if r.0 >= map.all_roads().len() {
+            return Err(format!("{} doesn't exist", r));
+        }
+        parse_road_cmd(map, r, action)
+    } else if let Some(id) = target.strip_prefix("intersection ") {
+        let i = IntersectionID(parse_id(id)?);
+        if i.0 >= map.all_intersections().len() {
+            return Err(format!("{} doesn't exist", i));
+        }
+        if map.get_i(i).is_border() {
+            return Err(format!("{} is a border and can't be edited", i));
+        }
+        parse_intersection_cmd(map, i, action, timer)
+    } else {
+        Err(format!("unknown target {}", target))
+    }
+}
+
+fn parse_road_cmd(map: &Map, r: RoadID, action: &str) -> Result<EditCmd, String> {
+    let old = map.get_r_edit(r);
+    let mut new = old.clone();
+
+    if let Some(rest) = action.strip_prefix("convert ") {
+        let words: Vec<&str> = rest.split(' ').collect();
+        if words.len() != 4 || words[2] != "to" {
+            return Err(format!(
+                "expected \"convert leftmost|rightmost|every <lane type> to <lane type>\""
+            ));
+        }
+        let from = parse_lane_type(words[1])?;
+        let to = parse_lane_type(words[3])?;
+        let matches: Vec<usize> = new
+            .lanes_ltr
+            .iter()
+            .enumerate()
+            .filter(|(_, (lt, _))| *lt == from)
+            .map(|(idx, _)| idx)
+            .collect();
+        if matches.is_empty() {
+            return Err(format!("{} has no {:?} lanes", r, from));
+        }
+        let selected = match words[0] {
+            "leftmost" => vec![matches[0]],
+            "rightmost" => vec![*matches.last().unwrap()],
+            "every" => matches,
+            x => {
+                return Err(format!(
+                    "expected leftmost, rightmost, or every, not \"{}\"",
+                    x
+                ));
+            }
+        };
+        for idx in selected {
+            new.lanes_ltr[idx].0 = to;
+        }
+    } else if let Some(rest) = action.strip_prefix("speed limit ") {
+        let mph = rest
+            .strip_suffix(" mph")
+            .and_then(|x| x.parse::<f64>().ok())
+            .ok_or_else(|| format!("expected \"speed limit <number> mph\""))?;
+        new.speed_limit = Speed::miles_per_hour(mph);
+    } else {
+        return Err(format!("unknown road command \"{}\"", action));
+    }
+
+    Ok(EditCmd::ChangeRoad { r, old, new })
+}
+
+fn parse_intersection_cmd(
+    map: &Map,
+    i: IntersectionID,
+    action: &str,
+    timer: &mut Timer,
+) -> Result<EditCmd, String> {
+    let old = map.get_i_edit(i);
+    let new = if action == "stop sign" {
+        EditIntersection::StopSign(ControlStopSign::new(map, i))
+    } else if action == "traffic signal" {
+        EditIntersection::TrafficSignal(ControlTrafficSignal::new(map, i, timer).export(map))
+    } else if action == "close" {
+        EditIntersection::Closed
+    } else if let Some(path) = action.strip_prefix("load signal plan ") {
+        let new =
+            EditIntersection::TrafficSignal(abstutil::maybe_read_json(path.to_string(), timer)?);
+        if let EditIntersection::TrafficSignal(ref ts) = new {
+            if ts.intersection_osm_node_id != map.get_i(i).orig_id.0 {
+                return Err(format!(
+                    "{} is for OSM node {}, but {} is OSM node {}",
+                    path,
+                    ts.intersection_osm_node_id,
+                    i,
+                    map.get_i(i).orig_id.0
+                ));
+            }
+        }
+        new
+    } else {
+        return Err(format!("unknown intersection command \"{}\"", action));
+    };
+    Ok(EditCmd::ChangeIntersection { i, old, new })
+}
+
+/// The same checks the lane editor makes, since nothing else stops a proposal from breaking the
+/// map.
+fn validate(map: &Map, r: RoadID) -> Result<(), String> {
+    let r = map.get_r(r);
+
+    let all_types: BTreeSet<LaneType> = r.lanes_ltr().into_iter().map(|(_, _, lt)| lt).collect();
+    if all_types.contains(&LaneType::Parking) && !all_types.contains(&LaneType::Driving) {
+        return Err(format!(
+            "a parking lane needs a driving lane somewhere on {}",
+            r.id
+        ));
+    }
+    if !r.all_bus_stops(map).is_empty()
+        && !r
+            .lanes_ltr()
+            .into_iter()
+            .any(|(l, _, _)| PathConstraints::Bus.can_use(map.get_l(l), map))
+    {
+        return Err(format!(
+            "the bus stops on {} need a driving or bus lane",
+            r.id
+        ));
+    }
+    Ok(())
+}
+
+fn parse_lane_type(x: &str) -> Result<LaneType, String> {
+    match x {
+        "Driving" => Ok(LaneType::Driving),
+        "Biking" => Ok(LaneType::Biking),
+        "Bus" => Ok(LaneType::Bus),
+        "Parking" => Ok(LaneType::Parking),
+        "Construction" => Ok(LaneType::Construction),
+        _ => Err(format!(
+            "unknown lane type \"{}\"; use Driving, Biking, Bus, Parking, or Construction",
+            x
+        )),
+    }
+}
+
+fn parse_id(x: &str) -> Result<usize, String> {
+    x.trim()
+        .parse::<usize>()
+        .map_err(|_| format!("bad ID \"{}\"", x))
+}
+
+fn split_once<'a>(x: &'a str, delim: &str) -> Option<(&'a str, &'a str)> {
+    let idx = x.find(delim)?;
+    Some((&x[..idx], &x[idx + delim.len()..]))
+}
@@ -0,0 +1,178 @@
+//! Measure how long emergency vehicles take to reach incidents, before and after some map edits.
+//! Incidents happen at random buildings at random times, and the nearest station sends an
+//! emergency vehicle through the scenario's background traffic. Emergency vehicles preempt
+//! traffic signals, and change lanes into gaps to get around stopped traffic.
+//!
+//! > cd headless; cargo run --release -- emergency_response --scenario=../data/system/seattle/scenarios/montlake/weekday.bin --edits=../data/player/edits/seattle/montlake/my_edits.json --incidents=50 --start_time=07:00:00 --end_time=19:00:00
+//!
+//! Stations are buildings tagged as a fire or ambulance station, unless `--stations=123,456` lists
+//! building IDs. The response time is the whole trip, from leaving the station until the crew
+//! reaches the building. Without `--edits`, only the current map is measured.
+
+use std::collections::BTreeSet;
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{CmdArgs, Timer};
+use geom::{Duration, Histogram, Statistic, Time};
+use map_model::{BuildingID, Map, MapEdits};
+use sim::{
+    IndividTrip, PersonSpec, Scenario, Sim, SimFlags, SimOptions, TripEndpoint, TripMode,
+    TripPurpose,
+};
+
+/// Keep simulating this long after the last incident, so the last responses can finish
+const MAX_RESPONSE_TIME: Duration = Duration::const_seconds(3600.0);
+
+pub fn run(mut args: CmdArgs) {
+    let rng_seed = args
+        .optional_parse("--rng_seed", |s| s.parse())
+        .unwrap_or(SimFlags::RNG_SEED);
+    let opts = SimOptions::from_args(&mut args, rng_seed);
+    let scenario_path = args.required("--scenario");
+    let edits_path = args.optional("--edits");
+    let num_incidents = args
+        .optional_parse("--incidents", |s| s.parse::<usize>())
+        .unwrap_or(20);
+    let stations_arg = args.optional("--stations");
+    let start_time = args
+        .optional_parse("--start_time", Time::parse)
+        .unwrap_or(Time::START_OF_DAY);
+    let end_time = args.required("--end_time");
+    let end_time = Time::parse(&end_time).unwrap_or_else(|_| panic!("Bad --end_time={}", end_time));
+    args.done();
+
+    let mut timer = Timer::new("measure emergency response");
+    let mut scenario: Scenario = abstutil::must_read_versioned_object(scenario_path, &mut timer);
+    let map = Map::new(scenario.map_name.path(), &mut timer);
+
+    let stations: Vec<BuildingID> = if let Some(arg) = stations_arg {
+        arg.split(',')
+            .map(|x| {
+                BuildingID(
+                    x.parse::<usize>()
+                        .unwrap_or_else(|_| panic!("Bad --stations={}", arg)),
+                )
+            })
+            .collect()
+    } else {
+        map.all_buildings()
+            .iter()
+            .filter(|b| {
+                b.amenities.iter().any(|a| {
+                    a.amenity_type == "fire_station" || a.amenity_type == "ambulance_station"
+                })
+            })
+            .map(|b| b.id)
+            .collect()
+    };
+    if stations.is_empty() {
+        println!("No fire or ambulance stations on this map; pass --stations");
+        std::process::exit(2);
+    }
+
+    // Each dispatch is a new person, so every incident gets its own vehicle
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    for _ in 0..num_incidents {
+        let incident = map.all_buildings()[rng.gen_range(0, map.all_buildings().len())].id;
+        let pt = map.get_b(incident).polygon.center();
+        let station = *stations
+            .iter()
+            .min_by_key(|b| map.get_b(**b).polygon.center().dist_to(pt))
+            .unwrap();
+        let depart = Time::START_OF_DAY
+            + Duration::seconds(
+                rng.gen_range(start_time.inner_seconds(), end_time.inner_seconds()),
+            );
+        scenario.people.push(PersonSpec {
+            orig_id: None,
+            origin: TripEndpoint::Bldg(station),
+            trips: vec![IndividTrip::new(
+                depart,
+                TripPurpose::Emergency,
+                TripEndpoint::Bldg(incident),
+                TripMode::Drive,
+            )],
+        });
+    }
+    println!(
+        "{} incidents between {} and {}, served by {} stations",
+        num_incidents,
+        start_time,
+        end_time,
+        stations.len()
+    );
+
+    let before = response_times(&scenario, map, rng_seed, &opts, end_time, &mut timer);
+    println!("Response times: {}", before.describe());
+    if let Some(path) = edits_path {
+        let mut map = Map::new(scenario.map_name.path(), &mut timer);
+        let edits = MapEdits::load(&map, path.clone(), &mut timer)
+            .unwrap_or_else(|err| panic!("Couldn't load edits {}: {}", path, err));
+        map.must_apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+        let after = response_times(&scenario, map, rng_seed, &opts, end_time, &mut timer);
+        println!("Response times with {}: {}", path, after.describe());
+
+        for (name, stat) in vec![
+            ("median", Statistic::P50),
+            ("90th percentile", Statistic::P90),
+            ("max", Statistic::Max),
+        ] {
+            if let (Some(x1), Some(x2)) = (before.times.select(stat), after.times.select(stat)) {
+                let delta = if x2 >= x1 {
+                    format!("+{}", x2 - x1)
+                } else {
+                    format!("-{}", x1 - x2)
+                };
+                println!("  {}: {} -> {} ({})", name, x1, x2, delta);
+            }
+        }
+    }
+}
+
+struct ResponseTimes {
+    times: Histogram<Duration>,
+    unfinished: usize,
+}
+
+impl ResponseTimes {
+    fn describe(&self) -> String {
+        format!("{}, {} unfinished", self.times.describe(), self.unfinished)
+    }
+}
+
+fn response_times(
+    scenario: &Scenario,
+    map: Map,
+    rng_seed: u64,
+    opts: &SimOptions,
+    end_time: Time,
+    timer: &mut Timer,
+) -> ResponseTimes {
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let mut sim = Sim::new(&map, opts.clone(), timer);
+    scenario.instantiate(&mut sim, &map, &mut rng, timer);
+    sim.timed_step(
+        &map,
+        end_time + MAX_RESPONSE_TIME - Time::START_OF_DAY,
+        &mut None,
+        timer,
+    );
+
+    let trips: BTreeSet<_> = sim
+        .all_trip_info()
+        .into_iter()
+        .filter(|(_, info)| info.purpose == TripPurpose::Emergency)
+        .map(|(id, _)| id)
+        .collect();
+    let mut times = Histogram::new();
+    for (_, id, _, dt) in &sim.get_analytics().finished_trips {
+        if let (true, Some(dt)) = (trips.contains(id), dt) {
+            times.add(*dt);
+        }
+    }
+    let unfinished = trips.len() - times.count();
+    ResponseTimes { times, unfinished }
+}
@@ -0,0 +1,348 @@
+//! Map edits change how long trips take, and some people respond by switching modes. Simulating
+//! a fixed scenario can't show that, so this searches for a rough equilibrium instead: simulate the
+//! day, feed the travel times people experienced into a simple mode choice model, change some
+//! people's modes, and repeat until almost nobody wants to switch.
+//!
+//! > cd headless; cargo run --release -- equilibrium --scenario=../data/system/seattle/scenarios/montlake/weekday.bin --edits=../data/player/edits/seattle/montlake/road_diet.json
+//!
+//! The model compares everything to the same scenario simulated without the edits, so without
+//! any edits, nobody switches. For every run and mode, trips are measured against how long they'd
+//! take at free-flow speeds, per hour of departure. That estimates how long anybody's day would
+//! take by driving, biking, or walking, even if they didn't use that mode in the run. Somebody
+//! switches if another mode improved compared to theirs by more than --threshold (a percent of
+//! their day's original travel time, 10 by default). Only people making all of their trips by one
+//! of those three modes are considered, and they switch their whole day, since somebody who leaves
+//! their car at home in the morning doesn't have it in the evening.
+//!
+//! To keep everybody from switching back and forth together, only --damping percent (50 by
+//! default) of the people who want to switch actually do each iteration. Iterations stop when
+//! fewer than --tolerance percent of people (0.5 by default) switch, or after --max_iterations
+//! (10 by default). Mode shares from every iteration are printed. Pass --output=report.json to also
+//! save them, and --save_scenario to save the final scenario with "equilibrium" added to its name.
+//! Flags for SimOptions, like --infinite_parking, apply to every run.
+
+use std::collections::BTreeMap;
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::Serialize;
+
+use abstutil::{prettyprint_usize, CmdArgs, Parallelism, Timer};
+use geom::{Duration, Time};
+use map_model::{Map, MapEdits};
+use sim::{PersonSpec, Scenario, Sim, SimFlags, SimOptions, TripEndpoint, TripMode};
+
+/// The modes people can switch between
+const MODES: [TripMode; 3] = [TripMode::Drive, TripMode::Bike, TripMode::Walk];
+
+/// For every person and trip, the free-flow time by each of `MODES`, if that's possible.
+type FreeFlowTimes = Vec<Vec<[Option<Duration>; 3]>>;
+
+/// For each of `MODES` and hour of departure, how many times longer trips took than at free-flow
+/// speeds.
+type Congestion = BTreeMap<(usize, usize), f64>;
+
+#[derive(Serialize)]
+struct Report {
+    baseline_mode_shares: BTreeMap<TripMode, usize>,
+    iterations: Vec<Iteration>,
+    converged: bool,
+}
+
+#[derive(Serialize)]
+struct Iteration {
+    /// The modes used during this iteration
+    mode_shares: BTreeMap<TripMode, usize>,
+    mean_trip_time: Duration,
+    /// How many people switched modes for the next iteration
+    people_switched: usize,
+}
+
+pub fn run(mut args: CmdArgs) {
+    let rng_seed = args
+        .optional_parse("--rng_seed", |s| s.parse())
+        .unwrap_or(SimFlags::RNG_SEED);
+    let opts = SimOptions::from_args(&mut args, rng_seed);
+    let scenario_path = args.required("--scenario");
+    let edits_path = args.required("--edits");
+    let threshold = args
+        .optional_parse("--threshold", |s| s.parse::<f64>())
+        .unwrap_or(10.0)
+        / 100.0;
+    let damping = args
+        .optional_parse("--damping", |s| s.parse::<f64>())
+        .unwrap_or(50.0)
+        / 100.0;
+    let tolerance = args
+        .optional_parse("--tolerance", |s| s.parse::<f64>())
+        .unwrap_or(0.5)
+        / 100.0;
+    let max_iterations = args
+        .optional_parse("--max_iterations", |s| s.parse::<usize>())
+        .unwrap_or(10);
+    let output = args.optional("--output");
+    let save_scenario = args.enabled("--save_scenario");
+    args.done();
+
+    let mut timer = Timer::new("search for a mode choice equilibrium");
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let (baseline_map, mut scenario) = sim::load_scenario(scenario_path, &mut rng, &mut timer);
+    let mut edited_map = Map::new(baseline_map.get_name().path(), &mut timer);
+    let edits = MapEdits::load(&edited_map, edits_path.clone(), &mut timer)
+        .unwrap_or_else(|err| panic!("Couldn't load edits {}: {}", edits_path, err));
+    edited_map.must_apply_edits(edits, &mut timer);
+    edited_map.recalculate_pathfinding_after_edits(&mut timer);
+
+    let baseline_free_flow = free_flow_times(&baseline_map, &scenario, &mut timer);
+    let edited_free_flow = free_flow_times(&edited_map, &scenario, &mut timer);
+
+    let baseline = simulate(&baseline_map, &scenario, &opts, rng_seed, &mut timer);
+    let baseline_congestion = measure_congestion(&baseline, &scenario, &baseline_free_flow);
+    let mut report = Report {
+        baseline_mode_shares: mode_shares(&scenario),
+        iterations: Vec::new(),
+        converged: false,
+    };
+    println!("Without edits:");
+    print_mode_shares(&report.baseline_mode_shares);
+
+    for iteration in 1..=max_iterations {
+        let sim = simulate(&edited_map, &scenario, &opts, rng_seed, &mut timer);
+        let congestion = measure_congestion(&sim, &scenario, &edited_free_flow);
+        let shares = mode_shares(&scenario);
+        let mean_trip_time = mean_trip_time(&sim);
+
+        let mut switch_rng = XorShiftRng::seed_from_u64(rng_seed + iteration as u64);
+        let mut people_switched = 0;
+        for (idx, person) in scenario.people.iter_mut().enumerate() {
+            let current = match single_mode(person) {
+                Some(m) => m,
+                None => continue,
+            };
+            let before = |m| day_time(person, &baseline_free_flow[idx], m, &baseline_congestion);
+            let after = |m| day_time(person, &edited_free_flow[idx], m, &congestion);
+            let (current_before, current_after) = match (before(current), after(current)) {
+                (Some(b), Some(a)) => (b, a),
+                _ => continue,
+            };
+            let current_change = current_after - current_before;
+
+            let mut best: Option<(usize, f64)> = None;
+            for m in 0..MODES.len() {
+                if m == current {
+                    continue;
+                }
+                if let (Some(b), Some(a)) = (before(m), after(m)) {
+                    let gain = current_change - (a - b);
+                    if gain > threshold * current_before
+                        && best.map(|(_, best_gain)| gain > best_gain).unwrap_or(true)
+                    {
+                        best = Some((m, gain));
+                    }
+                }
+            }
+            if let Some((m, _)) = best {
+                if switch_rng.gen_bool(damping) {
+                    for trip in &mut person.trips {
+                        if !trip.cancelled {
+                            trip.mode = MODES[m];
+                            trip.modified = true;
+                        }
+                    }
+                    people_switched += 1;
+                }
+            }
+        }
+
+        println!(
+            "After iteration {}, mean trip time is {}. {} people will switch modes",
+            iteration,
+            mean_trip_time,
+            prettyprint_usize(people_switched)
+        );
+        print_mode_shares(&shares);
+        report.iterations.push(Iteration {
+            mode_shares: shares,
+            mean_trip_time,
+            people_switched,
+        });
+
+        if (people_switched as f64) < tolerance * (scenario.people.len() as f64) {
+            report.converged = true;
+            break;
+        }
+    }
+
+    if report.converged {
+        println!("Converged after {} iterations", report.iterations.len());
+    } else {
+        println!(
+            "Didn't converge after {} iterations. The final mode shares are:",
+            max_iterations
+        );
+        print_mode_shares(&mode_shares(&scenario));
+    }
+    if let Some(path) = output {
+        abstutil::write_json(path, &report);
+    }
+    if save_scenario {
+        scenario.scenario_name = format!("{} equilibrium", scenario.scenario_name);
+        scenario.save();
+        println!(
+            "Saved {}",
+            abstutil::path_scenario(&scenario.map_name, &scenario.scenario_name)
+        );
+    }
+}
+
+fn simulate(
+    map: &Map,
+    scenario: &Scenario,
+    opts: &SimOptions,
+    rng_seed: u64,
+    timer: &mut Timer,
+) -> Sim {
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let mut sim = Sim::new(map, opts.clone(), timer);
+    scenario.instantiate(&mut sim, map, &mut rng, timer);
+    sim.timed_step(
+        map,
+        sim.get_end_of_day() - Time::START_OF_DAY,
+        &mut None,
+        timer,
+    );
+    sim
+}
+
+fn free_flow_times(map: &Map, scenario: &Scenario, timer: &mut Timer) -> FreeFlowTimes {
+    timer.parallelize(
+        "calculate free-flow times",
+        Parallelism::Fastest,
+        scenario.people.iter().collect(),
+        |person| {
+            let mut from = person.origin;
+            let mut times = Vec::new();
+            for trip in &person.trips {
+                let mut per_mode = [None; 3];
+                for (m, mode) in MODES.iter().enumerate() {
+                    per_mode[m] = TripEndpoint::free_flow_time(from, trip.destination, *mode, map);
+                }
+                times.push(per_mode);
+                from = trip.destination;
+            }
+            times
+        },
+    )
+}
+
+/// People are instantiated in order, so the Nth person in the simulation is the Nth in the
+/// scenario.
+fn measure_congestion(sim: &Sim, scenario: &Scenario, free_flow: &FreeFlowTimes) -> Congestion {
+    let mut finished = BTreeMap::new();
+    for (_, trip, _, maybe_dt) in &sim.get_analytics().finished_trips {
+        if let Some(dt) = maybe_dt {
+            finished.insert(*trip, *dt);
+        }
+    }
+
+    // Sum the experienced and free-flow times
+    let mut totals: BTreeMap<(usize, usize), (f64, f64)> = BTreeMap::new();
+    for (p, person) in sim.get_all_people().iter().enumerate() {
+        for (t, id) in person.trips.iter().enumerate() {
+            let trip = &scenario.people[p].trips[t];
+            let m = match MODES.iter().position(|mode| *mode == trip.mode) {
+                Some(m) => m,
+                None => continue,
+            };
+            if let (Some(actual), Some(ideal)) = (finished.get(id), free_flow[p][t][m]) {
+                let sums = totals
+                    .entry((m, trip.depart.get_hours()))
+                    .or_insert((0.0, 0.0));
+                sums.0 += actual.inner_seconds();
+                sums.1 += ideal.inner_seconds();
+            }
+        }
+    }
+
+    let mut congestion = Congestion::new();
+    for ((m, hour), (actual, ideal)) in totals {
+        if ideal > 0.0 {
+            congestion.insert((m, hour), actual / ideal);
+        }
+    }
+    congestion
+}
+
+/// How long all of somebody's trips would take by one mode. `None` if some trip is impossible that
+/// way.
+fn day_time(
+    person: &PersonSpec,
+    free_flow: &[[Option<Duration>; 3]],
+    m: usize,
+    congestion: &Congestion,
+) -> Option<f64> {
+    let mut total = 0.0;
+    for (trip, times) in person.trips.iter().zip(free_flow) {
+        if trip.cancelled {
+            continue;
+        }
+        // If nobody traveled this way during this hour, assume free-flow
+        let factor = congestion
+            .get(&(m, trip.depart.get_hours()))
+            .cloned()
+            .unwrap_or(1.0);
+        total += times[m]?.inner_seconds() * factor;
+    }
+    Some(total)
+}
+
+/// Only people making every trip by the same one of `MODES` can switch.
+fn single_mode(person: &PersonSpec) -> Option<usize> {
+    let mut modes = person.trips.iter().filter(|t| !t.cancelled).map(|t| t.mode);
+    let first = modes.next()?;
+    if modes.all(|m| m == first) {
+        MODES.iter().position(|m| *m == first)
+    } else {
+        None
+    }
+}
+
+fn mode_shares(scenario: &Scenario) -> BTreeMap<TripMode, usize> {
+    let mut shares = BTreeMap::new();
+    for person in &scenario.people {
+        for trip in &person.trips {
+            if !trip.cancelled {
+                *shares.entry(trip.mode).or_insert(0) += 1;
+            }
+        }
+    }
+    shares
+}
+
+fn print_mode_shares(shares: &BTreeMap<TripMode, usize>) {
+    let total: usize = shares.values().sum();
+    for (mode, cnt) in shares {
+        println!(
+            "- {}: {} trips ({:.1}%)",
+            mode.ongoing_verb(),
+            prettyprint_usize(*cnt),
+            100.0 * (*cnt as f64) / (total as f64)
+        );
+    }
+}
+
+fn mean_trip_time(sim: &Sim) -> Duration {
+    let mut total = Duration::ZERO;
+    let mut count = 0;
+    for (_, _, _, maybe_dt) in &sim.get_analytics().finished_trips {
+        if let Some(dt) = maybe_dt {
+            total += *dt;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        Duration::ZERO
+    } else {
+        total / (count as f64)
+    }
+}
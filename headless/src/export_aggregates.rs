@@ -0,0 +1,249 @@
+//! Run a simulation and export only aggregate trip counts, safe to share even when the scenario
+//! comes from sensitive origin/destination data. Trips are grouped by origin and destination grid
+//! cell, mode, and departure hour. Groups with fewer trips than a threshold are suppressed.
+//!
+//! > cd headless; cargo run -- export_aggregates --scenario=../data/system/seattle/scenarios/montlake/weekday.bin --end_time=24:00:00 --output=aggregates --min_count=10 --epsilon=1.0
+//!
+//! This writes od_counts.csv and summary.json to the output directory. Optional flags:
+//!
+//! - `--edits` applies map edits before simulating
+//! - `--grid_meters` sets the size of the square cells that origins and destinations are grouped
+//!   into, 500m by default
+//! - `--min_count` suppresses groups with fewer trips, 10 by default
+//! - `--epsilon` adds Laplace noise to the counts and total durations before applying the
+//!   threshold, scaled to this privacy budget. Smaller is more private and noisier. Without this,
+//!   only the threshold protects individuals. Groups without any trips are never written at all,
+//!   so this isn't a formal differential privacy guarantee, but it keeps any one person's trips
+//!   from being recovered from the published numbers.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::Serialize;
+
+use abstutil::{CmdArgs, Counter, Timer};
+use geom::{Duration, Pt2D, Time};
+use map_model::{Map, MapEdits};
+use sim::{Scenario, Sim, SimFlags, SimOptions, TripEndpoint, TripMode};
+
+/// Longer trips count as this long, so one trip can't shift a group's total duration too much
+const MAX_DURATION: Duration = Duration::const_seconds(3.0 * 3600.0);
+
+pub fn run(mut args: CmdArgs) {
+    let rng_seed = args
+        .optional_parse("--rng_seed", |s| s.parse())
+        .unwrap_or(SimFlags::RNG_SEED);
+    let opts = SimOptions::from_args(&mut args, rng_seed);
+    let scenario_path = args.required("--scenario");
+    let edits_path = args.optional("--edits");
+    let end_time = args.required("--end_time");
+    let end_time = Time::parse(&end_time).unwrap_or_else(|_| panic!("Bad --end_time={}", end_time));
+    let output = args.required("--output");
+    let grid_meters = args
+        .optional_parse("--grid_meters", |s| s.parse::<f64>())
+        .unwrap_or(500.0);
+    let min_count = args
+        .optional_parse("--min_count", |s| s.parse::<usize>())
+        .unwrap_or(10);
+    let epsilon = args.optional_parse("--epsilon", |s| s.parse::<f64>());
+    args.done();
+
+    let mut timer = Timer::new("export aggregate trips");
+    let scenario: Scenario = abstutil::must_read_versioned_object(scenario_path, &mut timer);
+    let mut map = Map::new(scenario.map_name.path(), &mut timer);
+    if let Some(path) = edits_path {
+        let edits = MapEdits::load(&map, path.clone(), &mut timer)
+            .unwrap_or_else(|err| panic!("Couldn't load edits {}: {}", path, err));
+        map.must_apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+    }
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let mut sim = Sim::new(&map, opts, &mut timer);
+    scenario.instantiate(&mut sim, &map, &mut rng, &mut timer);
+    sim.timed_step(&map, end_time - Time::START_OF_DAY, &mut None, &mut timer);
+
+    // Somebody taking many trips could show up in many groups, so the noise has to cover all of
+    // their trips
+    let max_trips_per_person = scenario
+        .people
+        .iter()
+        .map(|p| p.trips.len())
+        .max()
+        .unwrap_or(1);
+
+    let grid = Grid {
+        cell_size: grid_meters,
+    };
+    let mut groups: BTreeMap<Group, (usize, Duration)> = BTreeMap::new();
+    for (_, id, mode, dt) in &sim.get_analytics().finished_trips {
+        // Cancelled trips don't have a duration
+        let dt = match dt {
+            Some(dt) => *dt,
+            None => {
+                continue;
+            }
+        };
+        let info = sim.trip_info(*id);
+        let group = Group {
+            from: grid.cell(endpoint_pt(&info.start, &map)),
+            to: grid.cell(endpoint_pt(&info.end, &map)),
+            mode: *mode,
+            hour: info.departure.get_hours(),
+        };
+        let entry = groups.entry(group).or_insert((0, Duration::ZERO));
+        entry.0 += 1;
+        entry.1 += dt.min(MAX_DURATION);
+    }
+
+    // Half of the privacy budget goes to the counts, and half to the durations
+    let noise = epsilon.map(|eps| Noise {
+        count_scale: (max_trips_per_person as f64) / (eps / 2.0),
+        duration_scale: (max_trips_per_person as f64) * MAX_DURATION.inner_seconds() / (eps / 2.0),
+    });
+    // The simulation's seed is public, so anyone could reproduce noise drawn from it and subtract
+    // it back out
+    let mut noise_rng = XorShiftRng::from_entropy();
+
+    std::fs::create_dir_all(&output).unwrap();
+    let (groups_published, groups_suppressed) = write_csv(
+        &output,
+        &map,
+        &grid,
+        groups,
+        min_count,
+        noise.as_ref(),
+        &mut noise_rng,
+    )
+    .unwrap();
+    abstutil::write_json(
+        format!("{}/summary.json", output),
+        &Summary {
+            end_time,
+            grid_meters,
+            min_count,
+            epsilon,
+            groups_published,
+            groups_suppressed,
+        },
+    );
+    println!("Wrote aggregates to {}", output);
+}
+
+/// Origin and destination cells are numbered from the top-left of the map
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Group {
+    from: (usize, usize),
+    to: (usize, usize),
+    mode: TripMode,
+    hour: usize,
+}
+
+struct Grid {
+    cell_size: f64,
+}
+
+impl Grid {
+    fn cell(&self, pt: Pt2D) -> (usize, usize) {
+        (
+            (pt.x().max(0.0) / self.cell_size) as usize,
+            (pt.y().max(0.0) / self.cell_size) as usize,
+        )
+    }
+
+    fn center(&self, (x, y): (usize, usize)) -> Pt2D {
+        Pt2D::new(
+            (x as f64 + 0.5) * self.cell_size,
+            (y as f64 + 0.5) * self.cell_size,
+        )
+    }
+}
+
+struct Noise {
+    count_scale: f64,
+    duration_scale: f64,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    end_time: Time,
+    grid_meters: f64,
+    min_count: usize,
+    epsilon: Option<f64>,
+    groups_published: usize,
+    /// Only the number of groups is reported; the trips in them aren't counted anywhere
+    groups_suppressed: usize,
+}
+
+/// Returns the number of groups published and suppressed
+fn write_csv(
+    dir: &str,
+    map: &Map,
+    grid: &Grid,
+    groups: BTreeMap<Group, (usize, Duration)>,
+    min_count: usize,
+    noise: Option<&Noise>,
+    rng: &mut XorShiftRng,
+) -> Result<(usize, usize), Box<dyn Error>> {
+    let mut published = 0;
+    let mut suppressed = 0;
+    let mut modes = Counter::new();
+
+    let mut f = File::create(format!("{}/od_counts.csv", dir))?;
+    writeln!(
+        f,
+        "origin_longitude,origin_latitude,destination_longitude,destination_latitude,mode,\
+         departure_hour,trips,mean_duration_seconds"
+    )?;
+    for (group, (count, total)) in groups {
+        let (count, total) = match noise {
+            Some(noise) => (
+                count as f64 + laplace(rng, noise.count_scale),
+                total.inner_seconds() + laplace(rng, noise.duration_scale),
+            ),
+            None => (count as f64, total.inner_seconds()),
+        };
+        let count = count.round().max(0.0) as usize;
+        if count < min_count.max(1) {
+            suppressed += 1;
+            continue;
+        }
+        published += 1;
+        modes.add(group.mode, count);
+
+        let from = grid.center(group.from).to_gps(map.get_gps_bounds());
+        let to = grid.center(group.to).to_gps(map.get_gps_bounds());
+        writeln!(
+            f,
+            "{},{},{},{},{:?},{},{},{}",
+            from.x(),
+            from.y(),
+            to.x(),
+            to.y(),
+            group.mode,
+            group.hour,
+            count,
+            (total / (count as f64)).max(0.0).round()
+        )?;
+    }
+    info!("Published trips per mode: {:?}", modes.borrow());
+
+    Ok((published, suppressed))
+}
+
+fn endpoint_pt(endpt: &TripEndpoint, map: &Map) -> Pt2D {
+    match endpt {
+        TripEndpoint::Bldg(b) => map.get_b(*b).polygon.center(),
+        TripEndpoint::Border(i) => map.get_i(*i).polygon.center(),
+        TripEndpoint::SuddenlyAppear(pos) => pos.pt(map),
+    }
+}
+
+/// Samples from a Laplace distribution centered at 0
+fn laplace(rng: &mut XorShiftRng, scale: f64) -> f64 {
+    let u: f64 = rng.gen_range(-0.5, 0.5);
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
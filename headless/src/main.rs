@@ -2,12 +2,43 @@
 // https://dabreegster.github.io/abstreet/dev/api.html for documentation. To run this:
 //
 // > cd headless; cargo run -- --port=1234
+//
+// By default, the Montlake weekday scenario is loaded. Pass --scenario to use a different scenario
+// or a scenario generator JSON file (see sim/src/make/generator.rs). Pass --event_log=events.csv to
+// write every simulation event to a CSV file; see sim/src/event_log.rs for the columns. Pass
+// --realtime-factor=2 to keep the simulation from running faster than twice real time.
+//
 // > curl http://localhost:1234/sim/get-time
 // 00:00:00.0
 // > curl http://localhost:1234/sim/goto-time?t=01:01:00
 // it's now 01:01:00.0
 // > curl http://localhost:1234/data/get-road-thruput
 // ... huge JSON blob
+//
+// For monitoring long runs, http://localhost:1234/metrics serves Prometheus metrics. To instead run
+// the whole scenario and print memory usage every hour, pass --mem-report.
+//
+// To show the map (with edits) in a web map like MapLibre, point it at
+// http://localhost:1234/map/tiles/style.json, which uses vector tiles from
+// http://localhost:1234/map/tiles/{z}/{x}/{y}.mvt. See tiles.rs.
+//
+// Instead of starting a server, the first argument can name a tool to run. Each one's file
+// describes its flags.
+//
+// - audit: find suspicious map data that should be fixed in OpenStreetMap
+// - check_assertions: check assertions about a run, for regression tests
+// - compare: compare two runs
+// - determinism: run two copies of a simulation side-by-side to find nondeterminism
+// - diff_savestates: diff two savestates to find nondeterminism
+// - edit: generate proposals from a script of edit commands
+// - emergency_response: measure emergency vehicle response times before and after map edits
+// - equilibrium: let people switch modes in response to map edits
+// - export_aggregates: share results from a sensitive scenario as thresholded, optionally noised
+//   trip counts
+// - optimize_signals: tune a group of traffic signals offline
+// - sweep: run many simulations over a range of parameters
+// - warm_start: simulate a warm-up once, so every experiment can start from there by passing
+//   --savestate
 
 #[macro_use]
 extern crate log;
@@ -23,26 +54,62 @@ use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{serialize_btreemap, CmdArgs, MapName, Timer};
-use geom::{Distance, Duration, LonLat, Time};
+use geom::{Duration, Time};
 use map_model::{
-    CompressedMovementID, ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, Map,
-    MovementID, PermanentMapEdits, RoadID, TurnID,
+    BuildingID, CompressedMovementID, ControlTrafficSignal, Crosswalk, EditCmd, EditIntersection,
+    IntersectionID, Map, MovementID, PermanentMapEdits, RoadID, TurnID,
 };
 use sim::{
-    AgentID, AgentType, DelayCause, ExternalPerson, PersonID, Scenario, ScenarioModifier, Sim,
-    SimFlags, SimOptions, TripID, TripMode, VehicleType,
+    AgentID, AgentPosition, DelayCause, ExternalPerson, ExternalTrip, ExternalTripEndpoint,
+    PersonID, Scenario, ScenarioModifier, Sim, SimApi, SimFlags, SimOptions, TripEndpoint, TripID,
+    TripMode, TripStatus,
 };
 
+mod audit;
+mod check_assertions;
+mod compare;
+mod determinism;
+mod diff_savestates;
+mod edit;
+mod emergency_response;
+mod equilibrium;
+mod export_aggregates;
+mod metrics;
+mod optimize_signals;
+mod sweep;
+mod tiles;
+mod warm_start;
+
+/// Everything that can be passed as the first argument, instead of starting the server
+const TOOLS: [&str; 12] = [
+    "audit",
+    "check_assertions",
+    "compare",
+    "determinism",
+    "diff_savestates",
+    "edit",
+    "emergency_response",
+    "equilibrium",
+    "export_aggregates",
+    "optimize_signals",
+    "sweep",
+    "warm_start",
+];
+
 lazy_static::lazy_static! {
     static ref MAP: RwLock<Map> = RwLock::new(Map::blank());
     static ref SIM: RwLock<Sim> = RwLock::new(Sim::new(&Map::blank(), SimOptions::new("tmp"), &mut Timer::throwaway()));
+    static ref METRICS: RwLock<metrics::Metrics> = RwLock::new(metrics::Metrics::new());
     static ref LOAD: RwLock<LoadSim> = RwLock::new({
         LoadSim {
             scenario: abstutil::path_scenario(&MapName::seattle("montlake"), "weekday"),
             modifiers: Vec::new(),
             edits: None,
+            savestate: None,
             rng_seed: SimFlags::RNG_SEED,
             opts: SimOptions::default(),
+            event_log: None,
+            realtime_factor: None,
         }
     });
 }
@@ -50,20 +117,100 @@ lazy_static::lazy_static! {
 #[tokio::main]
 async fn main() {
     let mut args = CmdArgs::new();
+    match args.optional_free().as_deref() {
+        Some("audit") => {
+            audit::run(args);
+            return;
+        }
+        Some("check_assertions") => {
+            check_assertions::run(args);
+            return;
+        }
+        Some("compare") => {
+            compare::run(args);
+            return;
+        }
+        Some("determinism") => {
+            determinism::run(args);
+            return;
+        }
+        Some("diff_savestates") => {
+            diff_savestates::run(args);
+            return;
+        }
+        Some("edit") => {
+            edit::run(args);
+            return;
+        }
+        Some("emergency_response") => {
+            emergency_response::run(args);
+            return;
+        }
+        Some("equilibrium") => {
+            equilibrium::run(args);
+            return;
+        }
+        Some("export_aggregates") => {
+            export_aggregates::run(args);
+            return;
+        }
+        Some("optimize_signals") => {
+            optimize_signals::run(args);
+            return;
+        }
+        Some("sweep") => {
+            sweep::run(args);
+            return;
+        }
+        Some("warm_start") => {
+            warm_start::run(args);
+            return;
+        }
+        Some(x) => {
+            eprintln!(
+                "Unknown command {}. Pass no command to start the server, or one of: {}",
+                x,
+                TOOLS.join(", ")
+            );
+            std::process::exit(1);
+        }
+        None => {}
+    }
+
     let mut timer = Timer::new("setup headless");
     let rng_seed = args
         .optional_parse("--rng_seed", |s| s.parse())
         .unwrap_or(SimFlags::RNG_SEED);
     let opts = SimOptions::from_args(&mut args, rng_seed);
-    let port = args.required("--port").parse::<u16>().unwrap();
+    let mem_report = args.enabled("--mem-report");
+    let port = if mem_report {
+        0
+    } else {
+        args.required("--port").parse::<u16>().unwrap()
+    };
+    let scenario = args.optional("--scenario");
+    let savestate = args.optional("--savestate");
+    let event_log = args.optional("--event_log");
+    let realtime_factor = args.optional_parse("--realtime-factor", |s| s.parse::<f64>());
     args.done();
 
     {
         let mut load = LOAD.write().unwrap();
+        if let Some(path) = scenario {
+            load.scenario = path;
+        }
+        load.savestate = savestate;
         load.rng_seed = rng_seed;
         load.opts = opts;
+        load.event_log = event_log;
+        load.realtime_factor = realtime_factor;
 
         let (map, sim) = load.setup(&mut timer);
+        if mem_report {
+            metrics::report_memory(&map, sim, &mut timer);
+            return;
+        }
+        METRICS.write().unwrap().update(&sim);
         *MAP.write().unwrap() = map;
         *SIM.write().unwrap() = sim;
     }
@@ -80,6 +227,16 @@ async fn main() {
 
 async fn serve_req(req: Request<Body>) -> Result<Response<Body>, hyper::Error> {
     let path = req.uri().path().to_string();
+    // Don't wait for the simulation to be free
+    if path == "/metrics" {
+        return Ok(Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(METRICS.read().unwrap().render()))
+            .unwrap());
+    }
+    if let Some(tile) = path.strip_prefix("/map/tiles/") {
+        return Ok(serve_tile(&req, tile));
+    }
     // Url::parse needs an absolute URL
     let params: HashMap<String, String> =
         url::Url::parse(&format!("http://localhost{}", req.uri()))
@@ -89,25 +246,53 @@ async fn serve_req(req: Request<Body>) -> Result<Response<Body>, hyper::Error> {
             .collect();
     let body = hyper::body::to_bytes(req).await?.to_vec();
     info!("Handling {}", path);
-    Ok(
-        match handle_command(
-            &path,
-            &params,
-            &body,
-            &mut SIM.write().unwrap(),
-            &mut MAP.write().unwrap(),
-            &mut LOAD.write().unwrap(),
-        ) {
-            Ok(resp) => Response::new(Body::from(resp)),
-            Err(err) => {
-                error!("{}: {}", path, err);
-                Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(Body::from(format!("Bad command {}: {}", path, err)))
-                    .unwrap()
-            }
-        },
-    )
+    let resp = match handle_command(
+        &path,
+        &params,
+        &body,
+        &mut SIM.write().unwrap(),
+        &mut MAP.write().unwrap(),
+        &mut LOAD.write().unwrap(),
+    ) {
+        Ok(resp) => Response::new(Body::from(resp)),
+        Err(err) => {
+            error!("{}: {}", path, err);
+            Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from(format!("Bad command {}: {}", path, err)))
+                .unwrap()
+        }
+    };
+    // The command may have reset or reloaded the simulation
+    METRICS.write().unwrap().update(&SIM.read().unwrap());
+    Ok(resp)
+}
+
+/// Tiles are binary, so they're handled separately from the other commands.
+fn serve_tile(req: &Request<Body>, path: &str) -> Response<Body> {
+    let builder = Response::builder().header("Access-Control-Allow-Origin", "*");
+    if path == "style.json" {
+        let host = req
+            .headers()
+            .get(hyper::header::HOST)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or("localhost");
+        let url = format!("http://{}/map/tiles/{{z}}/{{x}}/{{y}}.mvt", host);
+        return builder
+            .header("Content-Type", "application/json")
+            .body(Body::from(tiles::style(url).to_string()))
+            .unwrap();
+    }
+    match tiles::TileID::parse(path) {
+        Ok(id) => builder
+            .header("Content-Type", "application/vnd.mapbox-vector-tile")
+            .body(Body::from(tiles::render(&MAP.read().unwrap(), id)))
+            .unwrap(),
+        Err(err) => builder
+            .status(StatusCode::BAD_REQUEST)
+            .body(Body::from(format!("Bad tile {}: {}", path, err)))
+            .unwrap(),
+    }
 }
 
 fn handle_command(
@@ -132,6 +317,7 @@ fn handle_command(
             load.scenario = args.scenario;
             load.modifiers = args.modifiers;
             load.edits = args.edits;
+            load.savestate = args.savestate;
 
             // Also reset
             let (new_map, new_sim) = load.setup(&mut Timer::new("reset sim"));
@@ -146,32 +332,83 @@ fn handle_command(
             if t <= sim.time() {
                 Err(format!("{} is in the past. call /sim/reset first?", t).into())
             } else {
-                let dt = t - sim.time();
-                sim.timed_step(map, dt, &mut None, &mut Timer::new("goto-time"));
+                // Step in small chunks of real time, so /metrics can report progress
+                while sim.time() < t {
+                    step_with_closures(sim, map, t);
+                    METRICS.write().unwrap().update(sim);
+                }
                 Ok(format!("it's now {}", t))
             }
         }
+        "/sim/set-realtime-factor" => {
+            let factor = match params["factor"].as_str() {
+                "none" => None,
+                x => {
+                    let factor = x.parse::<f64>()?;
+                    if factor <= 0.0 {
+                        return Err(format!("factor {} must be positive", factor).into());
+                    }
+                    Some(factor)
+                }
+            };
+            sim.set_realtime_factor(factor);
+            load.realtime_factor = factor;
+            Ok(match factor {
+                Some(x) => format!("simulating at most {}x real time", x),
+                None => "simulating as fast as possible".to_string(),
+            })
+        }
         "/sim/new-person" => {
             let input: ExternalPerson = abstutil::from_json(body)?;
-            for trip in &input.trips {
-                if trip.departure < sim.time() {
-                    return Err(format!(
-                        "It's {} now, so you can't start a trip at {}",
-                        sim.time(),
-                        trip.departure
-                    )
-                    .into());
+            let person = spawn_person(input, sim, map, load)?;
+            Ok(format!("{} created", person))
+        }
+        "/sim/spawn-trip" => {
+            let input: SpawnTrip = abstutil::from_json(body)?;
+            for b in vec![input.from, input.to] {
+                if b.0 >= map.all_buildings().len() {
+                    return Err(format!("{} doesn't exist", b).into());
                 }
             }
-
-            let mut scenario = Scenario::empty(map, "one-shot");
-            scenario.people = ExternalPerson::import(map, vec![input])?;
-            let mut rng = XorShiftRng::seed_from_u64(load.rng_seed);
-            scenario.instantiate(sim, map, &mut rng, &mut Timer::throwaway());
-            Ok(format!(
-                "{} created",
-                sim.get_all_people().last().unwrap().id
-            ))
+            let person = spawn_person(
+                ExternalPerson {
+                    origin: ExternalTripEndpoint::TripEndpoint(TripEndpoint::Bldg(input.from)),
+                    trips: vec![ExternalTrip {
+                        departure: input.departure,
+                        destination: ExternalTripEndpoint::TripEndpoint(TripEndpoint::Bldg(
+                            input.to,
+                        )),
+                        mode: input.mode,
+                    }],
+                },
+                sim,
+                map,
+                load,
+            )?;
+            Ok(abstutil::to_json(&SpawnedTrip {
+                person,
+                trip: sim.get_person(person).trips[0],
+            }))
+        }
+        "/sim/finish-trip" => {
+            let id = TripID(params["id"].parse::<usize>()?);
+            let limit = match params.get("t") {
+                Some(t) => Time::parse(t)?,
+                None => sim.time() + Duration::hours(24),
+            };
+            loop {
+                let record = SimApi::new(sim, map)
+                    .trip_record(id)
+                    .ok_or_else(|| format!("{} doesn't exist", id))?;
+                if record.status == TripStatus::Finished || record.status == TripStatus::Cancelled {
+                    return Ok(abstutil::to_json(&record));
+                }
+                if sim.time() >= limit {
+                    return Err(format!("{} still isn't done at {}", id, sim.time()).into());
+                }
+                step_with_closures(sim, map, limit);
+                METRICS.write().unwrap().update(sim);
+            }
         }
         // Traffic signals
         "/traffic-signals/get" => {
@@ -277,47 +514,50 @@ fn handle_command(
             }
             Ok(abstutil::to_json(&all_state))
         }
+        // Crosswalks
+        "/crosswalks/get" => {
+            let i = IntersectionID(params["id"].parse::<usize>()?);
+            Ok(abstutil::to_json(&map.get_i(i).crosswalks))
+        }
+        "/crosswalks/set" => {
+            let crosswalk: Crosswalk = abstutil::from_json(body)?;
+            if map
+                .get_i(crosswalk.parent)
+                .crosswalks
+                .iter()
+                .all(|c| c.road != crosswalk.road)
+            {
+                return Err(format!(
+                    "{} doesn't have a crosswalk over {}",
+                    crosswalk.parent, crosswalk.road
+                )
+                .into());
+            }
+            let road = crosswalk.road;
+            map.incremental_edit_crosswalk(crosswalk);
+            Ok(format!("The crosswalk over {} has been updated", road))
+        }
         // Querying data
         "/data/get-finished-trips" => {
-            let mut trips = Vec::new();
-            for (_, id, mode, maybe_duration) in &sim.get_analytics().finished_trips {
-                let info = sim.trip_info(*id);
-                let distance_crossed = if maybe_duration.is_some() {
-                    sim.finished_trip_details(*id).unwrap().2
-                } else {
-                    Distance::ZERO
-                };
-                trips.push(FinishedTrip {
-                    id: *id,
-                    duration: *maybe_duration,
-                    distance_crossed,
-                    mode: *mode,
-                    capped: info.capped,
-                });
+            Ok(abstutil::to_json(&SimApi::new(sim, map).finished_trips()))
+        }
+        "/data/get-trip" => {
+            let id = TripID(params["id"].parse::<usize>()?);
+            match SimApi::new(sim, map).trip_record(id) {
+                Some(record) => Ok(abstutil::to_json(&record)),
+                None => Err(format!("{} doesn't exist", id).into()),
             }
-            Ok(abstutil::to_json(&trips))
         }
         "/data/get-agent-positions" => Ok(abstutil::to_json(&AgentPositions {
-            agents: sim
-                .get_unzoomed_agents(map)
-                .into_iter()
-                .map(|a| AgentPosition {
-                    vehicle_type: a.id.to_vehicle_type(),
-                    pos: a.pos.to_gps(map.get_gps_bounds()),
-                    distance_crossed: sim.agent_properties(a.id).dist_crossed,
-                    person: a.person,
-                })
-                .collect(),
-        })),
-        "/data/get-road-thruput" => Ok(abstutil::to_json(&RoadThroughput {
-            counts: sim
-                .get_analytics()
-                .road_thruput
-                .counts
-                .iter()
-                .map(|((r, a, hr), cnt)| (*r, *a, *hr, *cnt))
-                .collect(),
+            agents: SimApi::new(sim, map).agent_positions(),
         })),
+        "/data/get-road-thruput" => Ok(abstutil::to_json(&SimApi::new(sim, map).road_throughput())),
+        "/data/get-parking-occupancy" => Ok(abstutil::to_json(
+            &SimApi::new(sim, map).parking_occupancy(),
+        )),
+        "/data/get-parking-occupancy-over-time" => Ok(sim
+            .get_analytics()
+            .parking_occupancy_csv(&sim.get_parking_capacity(), map)),
         "/data/get-blocked-by-graph" => Ok(abstutil::to_json(&BlockedByGraph {
             blocked_by: sim.get_blocked_by_graph(map),
         })),
@@ -343,17 +583,32 @@ fn handle_command(
     }
 }
 
-// TODO I think specifying the API with protobufs or similar will be a better idea.
+/// Creates one person with their trips, starting any time from now on.
+fn spawn_person(
+    input: ExternalPerson,
+    sim: &mut Sim,
+    map: &Map,
+    load: &LoadSim,
+) -> Result<PersonID, Box<dyn Error>> {
+    for trip in &input.trips {
+        if trip.departure < sim.time() {
+            return Err(format!(
+                "It's {} now, so you can't start a trip at {}",
+                sim.time(),
+                trip.departure
+            )
+            .into());
+        }
+    }
 
-#[derive(Serialize)]
-struct FinishedTrip {
-    id: TripID,
-    duration: Option<Duration>,
-    distance_crossed: Distance,
-    mode: TripMode,
-    capped: bool,
+    let mut scenario = Scenario::empty(map, "one-shot");
+    scenario.people = ExternalPerson::import(map, vec![input])?;
+    let mut rng = XorShiftRng::seed_from_u64(load.rng_seed);
+    Ok(scenario.instantiate(sim, map, &mut rng, &mut Timer::throwaway())[0])
 }
 
+// TODO I think specifying the API with protobufs or similar will be a better idea.
+
 #[derive(Serialize)]
 struct Delays {
     #[serde(serialize_with = "serialize_btreemap")]
@@ -366,34 +621,24 @@ struct Throughput {
     per_direction: BTreeMap<MovementID, usize>,
 }
 
-#[derive(Serialize)]
-struct AgentPositions {
-    agents: Vec<AgentPosition>,
+/// One trip between two buildings, for probing the network from outside
+#[derive(Deserialize)]
+struct SpawnTrip {
+    from: BuildingID,
+    to: BuildingID,
+    mode: TripMode,
+    departure: Time,
 }
 
 #[derive(Serialize)]
-struct AgentPosition {
-    /// None for pedestrians
-    vehicle_type: Option<VehicleType>,
-    /// The agent's current position. For pedestrians, this is their center. For vehicles, this
-    /// represents the front of the vehicle.
-    pos: LonLat,
-    /// The distance crossed so far by the agent, in meters. There are some caveats to this value:
-    /// - The distance along driveways between buildings/parking lots and the road doesn't count
-    ///   here.
-    /// - The distance will slightly exceed the true value if the agent begins or ends in the
-    ///   middle of a lane.
-    /// - The distance will not change while an agent is travelling along a lane; it'll only
-    ///   increment when they completely cross one step of their path.
-    distance_crossed: Distance,
-    /// None for buses
-    person: Option<PersonID>,
+struct SpawnedTrip {
+    person: PersonID,
+    trip: TripID,
 }
 
 #[derive(Serialize)]
-struct RoadThroughput {
-    // (road, agent type, hour since midnight, throughput for that one hour period)
-    counts: Vec<(RoadID, AgentType, usize, usize)>,
+struct AgentPositions {
+    agents: Vec<AgentPosition>,
 }
 
 #[derive(Serialize)]
@@ -415,37 +660,91 @@ struct BlockedByGraph {
 
 #[derive(Deserialize)]
 struct LoadSim {
+    /// A path to a scenario or a scenario generator
     scenario: String,
     modifiers: Vec<ScenarioModifier>,
     edits: Option<PermanentMapEdits>,
+    /// Start from a savestate made by warm_start, instead of the beginning of the scenario. The
+    /// edits are applied on top of it, and modifiers aren't allowed.
+    #[serde(default)]
+    savestate: Option<String>,
     // These are fixed from the initial command line flags
     #[serde(skip_deserializing)]
     rng_seed: u64,
     #[serde(skip_deserializing)]
     opts: SimOptions,
+    /// Reloading the simulation starts this log over from the beginning.
+    #[serde(skip_deserializing)]
+    event_log: Option<String>,
+    /// Reloading the simulation starts pacing over from the new start time.
+    #[serde(skip_deserializing)]
+    realtime_factor: Option<f64>,
 }
 
 impl LoadSim {
     fn setup(&self, timer: &mut Timer) -> (Map, Sim) {
-        let mut scenario: Scenario = abstutil::must_read_object(self.scenario.clone(), timer);
-
-        let mut map = Map::new(scenario.map_name.path(), timer);
-        if let Some(perma) = self.edits.clone() {
-            let edits = perma.to_edits(&map).unwrap();
-            map.must_apply_edits(edits, timer);
-            map.recalculate_pathfinding_after_edits(timer);
-        }
+        let mut rng = XorShiftRng::seed_from_u64(self.rng_seed);
+        let (mut map, mut scenario) = sim::load_scenario(self.scenario.clone(), &mut rng, timer);
+        let edits = self
+            .edits
+            .clone()
+            .map(|perma| perma.to_edits(&map).unwrap());
+
+        let mut sim = if let Some(ref path) = self.savestate {
+            if !self.modifiers.is_empty() {
+                panic!("Scenario modifiers can't change a savestate");
+            }
+            let edits = edits.unwrap_or_else(|| map.get_edits().clone());
+            let (mut sim, (trips_cancelled, parked_cars)) =
+                Sim::load_savestate_with_edits(path.clone(), &mut map, edits, timer)
+                    .unwrap_or_else(|err| panic!("Can't start from {}: {}", path, err));
+            info!(
+                "Started from {} at {}. The edits cancelled {} trips and displaced {} parked cars",
+                path,
+                sim.time(),
+                trips_cancelled,
+                parked_cars
+            );
+            self.start_event_log(&mut sim);
+            sim
+        } else {
+            if let Some(edits) = edits {
+                map.must_apply_edits(edits, timer);
+                map.recalculate_pathfinding_after_edits(timer);
+            }
 
-        for m in &self.modifiers {
-            scenario = m.apply(&map, scenario);
-        }
+            for m in &self.modifiers {
+                scenario = m.apply(&map, scenario);
+            }
 
-        let mut rng = XorShiftRng::seed_from_u64(self.rng_seed);
-        let mut sim = Sim::new(&map, self.opts.clone(), timer);
-        scenario.instantiate(&mut sim, &map, &mut rng, timer);
+            let mut sim = Sim::new(&map, self.opts.clone(), timer);
+            self.start_event_log(&mut sim);
+            scenario.instantiate(&mut sim, &map, &mut rng, timer);
+            sim.apply_lane_closures(&mut map, timer);
+            sim
+        };
+        sim.set_realtime_factor(self.realtime_factor);
 
         (map, sim)
     }
+
+    fn start_event_log(&self, sim: &mut Sim) {
+        if let Some(ref path) = self.event_log {
+            sim.log_events_to(path.clone())
+                .unwrap_or_else(|err| panic!("Can't write event log {}: {}", path, err));
+        }
+    }
+}
+
+/// Steps towards some time for about a second of real time, stopping early to open or close lanes
+/// as the scenario says.
+fn step_with_closures(sim: &mut Sim, map: &mut Map, until: Time) {
+    let until = sim
+        .next_lane_closure_change()
+        .map(|t| t.min(until))
+        .unwrap_or(until);
+    sim.time_limited_step(map, until - sim.time(), Duration::seconds(1.0), &mut None);
+    sim.apply_lane_closures(map, &mut Timer::throwaway());
 }
 
 fn export_geometry(map: &Map, i: IntersectionID) -> geojson::GeoJson {
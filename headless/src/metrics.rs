@@ -0,0 +1,132 @@
+//! Expose the progress of the simulation in the Prometheus text format, so long runs can be
+//! watched from a dashboard. The simulation is locked while it's stepping forward, so this keeps a
+//! separate snapshot, refreshed between chunks of work.
+//!
+//! With `--mem-report`, the server doesn't start. Instead the scenario runs through the day,
+//! printing how much memory each part of the simulation uses every hour, so regressions are easy to
+//! spot.
+
+use std::time::Instant;
+
+use abstutil::{prettyprint_usize, Timer};
+use geom::{Duration, Time};
+use map_model::Map;
+use sim::Sim;
+
+pub struct Metrics {
+    sim_time: Time,
+    active_agents: usize,
+    events: usize,
+    events_per_second: f64,
+    last_update: Instant,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            sim_time: Time::START_OF_DAY,
+            active_agents: 0,
+            events: 0,
+            events_per_second: 0.0,
+            last_update: Instant::now(),
+        }
+    }
+
+    pub fn update(&mut self, sim: &Sim) {
+        let events = sim.num_events_processed();
+        let elapsed = self.last_update.elapsed().as_secs_f64();
+        // A reset starts the count over
+        if events >= self.events && elapsed > 0.0 {
+            self.events_per_second = ((events - self.events) as f64) / elapsed;
+        } else {
+            self.events_per_second = 0.0;
+        }
+        self.sim_time = sim.time();
+        self.active_agents = sim.num_active_agents();
+        self.events = events;
+        self.last_update = Instant::now();
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        metric(
+            &mut out,
+            "abstreet_sim_time_seconds",
+            "gauge",
+            "Simulated time since midnight",
+            (self.sim_time - Time::START_OF_DAY).inner_seconds(),
+        );
+        metric(
+            &mut out,
+            "abstreet_active_agents",
+            "gauge",
+            "Agents currently moving in the simulation",
+            self.active_agents as f64,
+        );
+        metric(
+            &mut out,
+            "abstreet_events_total",
+            "counter",
+            "Simulation events processed since the simulation was loaded",
+            self.events as f64,
+        );
+        metric(
+            &mut out,
+            "abstreet_events_per_second",
+            "gauge",
+            "Simulation events processed per second of real time, since the previous update",
+            self.events_per_second,
+        );
+        if let Some(bytes) = resident_memory_bytes() {
+            metric(
+                &mut out,
+                "process_resident_memory_bytes",
+                "gauge",
+                "Resident memory size in bytes",
+                bytes as f64,
+            );
+        }
+        out
+    }
+}
+
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, value: f64) {
+    out.push_str(&format!("# HELP {} {}\n", name, help));
+    out.push_str(&format!("# TYPE {} {}\n", name, kind));
+    out.push_str(&format!("{} {}\n", name, value));
+}
+
+/// Runs the whole day, printing a memory report every simulated hour.
+pub fn report_memory(map: &Map, mut sim: Sim, timer: &mut Timer) {
+    let end_of_day = Time::START_OF_DAY + Duration::hours(24);
+    while !sim.is_done() && sim.time() < end_of_day {
+        sim.timed_step(map, Duration::hours(1), &mut None, timer);
+
+        let mut usage = sim.memory_usage();
+        usage.sort_by_key(|(_, bytes)| std::cmp::Reverse(*bytes));
+        println!(
+            "Memory at {} with {} active agents:",
+            sim.time(),
+            prettyprint_usize(sim.num_active_agents())
+        );
+        if let Some(bytes) = resident_memory_bytes() {
+            println!("  {:>16} resident in total", prettyprint_kb(bytes));
+        }
+        for (name, bytes) in usage {
+            println!("  {:>16} {}", prettyprint_kb(bytes), name);
+        }
+    }
+}
+
+fn prettyprint_kb(bytes: usize) -> String {
+    format!("{} kB", prettyprint_usize(bytes / 1024))
+}
+
+/// Only works on Linux
+fn resident_memory_bytes() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    // Like "VmRSS:     1234 kB"
+    let kb = line.split_whitespace().nth(1)?.parse::<usize>().ok()?;
+    Some(kb * 1024)
+}
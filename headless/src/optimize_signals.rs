@@ -0,0 +1,170 @@
+//! Tune the timing of a group of traffic signals (like a corridor) with a simple hill-climber. Each
+//! round nudges one signal's offset or the duration of one of its stages, re-runs the simulation,
+//! and keeps the change if the total delay at traffic signals dropped. The best plan is written as
+//! map edits.
+//!
+//! > cd headless; cargo run --release -- optimize_signals --scenario=../data/system/seattle/scenarios/montlake/weekday.bin --signals=123,124,125 --start_time=07:00:00 --end_time=08:00:00 --rounds=50 --output=optimized_signals
+//!
+//! This writes data/player/edits/<city>/<map>/optimized_signals.json, which can be loaded in the
+//! game or passed to `compare`. Every round simulates from midnight until the end time, so use a
+//! small scenario or an early end time.
+
+use std::collections::BTreeMap;
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{CmdArgs, Timer};
+use geom::{Duration, Time};
+use map_model::{
+    ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, Map, MapEdits, PhaseType,
+};
+use sim::{Scenario, Sim, SimFlags, SimOptions};
+
+/// How much to change an offset or stage duration in one round
+const STEP: Duration = Duration::const_seconds(5.0);
+/// Never shorten a stage past this, even if it has no crosswalks
+const MIN_STAGE_DURATION: Duration = Duration::const_seconds(5.0);
+
+pub fn run(mut args: CmdArgs) {
+    let rng_seed = args
+        .optional_parse("--rng_seed", |s| s.parse())
+        .unwrap_or(SimFlags::RNG_SEED);
+    let opts = SimOptions::from_args(&mut args, rng_seed);
+    let scenario_path = args.required("--scenario");
+    let edits_path = args.optional("--edits");
+    let signals_arg = args.required("--signals");
+    let signals: Vec<IntersectionID> = signals_arg
+        .split(',')
+        .map(|x| {
+            IntersectionID(
+                x.parse::<usize>()
+                    .unwrap_or_else(|_| panic!("Bad --signals={}", signals_arg)),
+            )
+        })
+        .collect();
+    let start_time = args
+        .optional_parse("--start_time", Time::parse)
+        .unwrap_or(Time::START_OF_DAY);
+    let end_time = args.required("--end_time");
+    let end_time = Time::parse(&end_time).unwrap_or_else(|_| panic!("Bad --end_time={}", end_time));
+    let rounds = args
+        .optional_parse("--rounds", |s| s.parse::<usize>())
+        .unwrap_or(50);
+    let output = args.required("--output");
+    args.done();
+
+    let mut timer = Timer::new("optimize traffic signals");
+    let scenario: Scenario = abstutil::must_read_versioned_object(scenario_path, &mut timer);
+    let mut map = Map::new(scenario.map_name.path(), &mut timer);
+    if let Some(path) = edits_path {
+        let edits = MapEdits::load(&map, path.clone(), &mut timer)
+            .unwrap_or_else(|err| panic!("Couldn't load edits {}: {}", path, err));
+        map.must_apply_edits(edits, &mut timer);
+        map.recalculate_pathfinding_after_edits(&mut timer);
+    }
+    for i in &signals {
+        if map.maybe_get_traffic_signal(*i).is_none() {
+            panic!("{} isn't a traffic signal", i);
+        }
+    }
+    let original: BTreeMap<IntersectionID, EditIntersection> =
+        signals.iter().map(|i| (*i, map.get_i_edit(*i))).collect();
+
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let baseline = total_delay(&map, &scenario, rng_seed, &opts, start_time, end_time);
+    let mut best = baseline;
+    info!("Baseline delay is {}", baseline);
+    for round in 1..=rounds {
+        let i = signals[rng.gen_range(0, signals.len())];
+        let current = map.get_traffic_signal(i).clone();
+        map.incremental_edit_traffic_signal(perturb(&current, &mut rng));
+
+        let delay = total_delay(&map, &scenario, rng_seed, &opts, start_time, end_time);
+        if delay < best {
+            info!(
+                "Round {}/{}: changing {} lowers delay to {}",
+                round, rounds, i, delay
+            );
+            best = delay;
+        } else {
+            info!("Round {}/{}: changing {} doesn't help", round, rounds, i);
+            map.incremental_edit_traffic_signal(current);
+        }
+    }
+
+    // The map's edits don't know about the incremental changes, so express them as commands
+    // against the original signals.
+    let mut edits = map.get_edits().clone();
+    edits.edits_name = output;
+    for (i, old) in original {
+        let new = EditIntersection::TrafficSignal(map.get_traffic_signal(i).export(&map));
+        if new != old {
+            edits
+                .commands
+                .push(EditCmd::ChangeIntersection { i, old, new });
+        }
+    }
+    let path = abstutil::path_edits(map.get_name(), &edits.edits_name);
+    abstutil::write_json(path.clone(), &edits.to_permanent(&map));
+    println!(
+        "Total delay at traffic signals went from {} to {}. Wrote {}",
+        baseline, best, path
+    );
+}
+
+/// Simulates from midnight, then sums the delay measured at every traffic signal since the start
+/// time. Signals outside the group count too, so a change can't just push delay somewhere else.
+fn total_delay(
+    map: &Map,
+    scenario: &Scenario,
+    rng_seed: u64,
+    opts: &SimOptions,
+    start_time: Time,
+    end_time: Time,
+) -> Duration {
+    let mut timer = Timer::throwaway();
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let mut sim = Sim::new(map, opts.clone(), &mut timer);
+    scenario.instantiate(&mut sim, map, &mut rng, &mut timer);
+    sim.timed_step(map, end_time - Time::START_OF_DAY, &mut None, &mut timer);
+    sim.get_analytics()
+        .intersection_delays
+        .values()
+        .flatten()
+        .filter(|(_, t, _, _)| *t >= start_time)
+        .map(|(_, _, delay, _)| *delay)
+        .sum()
+}
+
+/// Shifts the offset or one stage's duration by a step in either direction.
+fn perturb(ts: &ControlTrafficSignal, rng: &mut XorShiftRng) -> ControlTrafficSignal {
+    let mut ts = ts.clone();
+    let longer = rng.gen_bool(0.5);
+    let idx = rng.gen_range(0, ts.stages.len() + 1);
+    if idx == ts.stages.len() {
+        // Offsets only matter relative to the cycle length
+        let cycle = ts.simple_cycle_duration();
+        ts.offset = if longer {
+            (ts.offset + STEP) % cycle
+        } else if ts.offset >= STEP {
+            ts.offset - STEP
+        } else {
+            ts.offset + cycle - STEP
+        };
+    } else {
+        let min = ts.get_min_crossing_time(idx).max(MIN_STAGE_DURATION);
+        let change = |d: Duration| {
+            if longer {
+                d + STEP
+            } else {
+                (d - STEP).max(min)
+            }
+        };
+        ts.stages[idx].phase_type = match ts.stages[idx].phase_type {
+            PhaseType::Fixed(d) => PhaseType::Fixed(change(d)),
+            PhaseType::Adaptive(d) => PhaseType::Adaptive(change(d)),
+        };
+    }
+    ts
+}
@@ -0,0 +1,243 @@
+//! Run many simulations at once, trying every combination of some parameters, and write metrics
+//! from each run. This replaces hand-written scripts around the headless binary.
+//!
+//! > cd headless; cargo run --release -- sweep --config=sweep.toml
+//!
+//! The config looks like:
+//!
+//! ```toml
+//! scenario = "../data/system/seattle/scenarios/montlake/weekday.bin"
+//! # Optional; simulates the whole scenario by default
+//! end_time = "12:00:00"
+//! output = "sweep_results"
+//! # Multiplies the number of people in the scenario. Optional; defaults to [1.0]
+//! demand_scale = [0.5, 1.0, 1.5]
+//! # Optional; defaults to the usual seed
+//! rng_seeds = [42, 43, 44]
+//! # Map edits to try, like different traffic signal plans. "none" means no edits. Optional;
+//! # defaults to ["none"]
+//! edits = ["none", "../data/player/edits/seattle/montlake/retimed_signals.json"]
+//! # Optional; also record every event, trip, and hourly throughput from all runs in one SQLite
+//! # database. Needs the sqlite feature, so build with --features sqlite.
+//! results_db = "sweep_results/results.sqlite"
+//! ```
+//!
+//! Every combination is simulated, using all CPUs. Each run writes run_<number>.json in the output
+//! directory, recording the parameters and the resulting metrics. In the results database, the run
+//! is named run_<number>. Flags for SimOptions, like --infinite_parking, apply to all runs.
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use abstutil::{CmdArgs, Parallelism, Timer};
+use geom::{Duration, Time};
+use map_model::{Map, MapEdits};
+use sim::{Scenario, Sim, SimFlags, SimOptions};
+
+#[derive(Deserialize)]
+struct SweepConfig {
+    scenario: String,
+    end_time: Option<String>,
+    output: String,
+    #[serde(default = "default_demand_scale")]
+    demand_scale: Vec<f64>,
+    #[serde(default = "default_rng_seeds")]
+    rng_seeds: Vec<u64>,
+    #[serde(default = "default_edits")]
+    edits: Vec<String>,
+    results_db: Option<String>,
+}
+
+fn default_demand_scale() -> Vec<f64> {
+    vec![1.0]
+}
+fn default_rng_seeds() -> Vec<u64> {
+    vec![SimFlags::RNG_SEED]
+}
+fn default_edits() -> Vec<String> {
+    vec!["none".to_string()]
+}
+
+#[derive(Clone, Serialize)]
+struct RunParams {
+    demand_scale: f64,
+    rng_seed: u64,
+    edits: String,
+}
+
+#[derive(Serialize)]
+struct RunResults {
+    params: RunParams,
+    end_time: Time,
+    num_people: usize,
+    trips_finished: usize,
+    trips_cancelled: usize,
+    total_trip_time: Duration,
+    mean_trip_time: Duration,
+    total_intersection_delay: Duration,
+    total_road_thruput: usize,
+}
+
+pub fn run(mut args: CmdArgs) {
+    let opts = SimOptions::from_args(&mut args, SimFlags::RNG_SEED);
+    let config_path = args.required("--config");
+    args.done();
+
+    let config: SweepConfig =
+        toml::from_str(&String::from_utf8(abstutil::slurp_file(&config_path).unwrap()).unwrap())
+            .unwrap_or_else(|err| panic!("Bad config {}: {}", config_path, err));
+    let end_time = config.end_time.as_ref().map(|t| {
+        Time::parse(t).unwrap_or_else(|_| panic!("Bad end_time {} in {}", t, config_path))
+    });
+
+    if config.results_db.is_some() && !cfg!(feature = "sqlite") {
+        panic!(
+            "results_db in {} needs the sqlite feature compiled in",
+            config_path
+        );
+    }
+
+    let mut timer = Timer::new("parameter sweep");
+    let scenario: Scenario =
+        abstutil::must_read_versioned_object(config.scenario.clone(), &mut timer);
+    std::fs::create_dir_all(&config.output).unwrap();
+
+    let mut run_idx = 0;
+    for edits in &config.edits {
+        // Every run with the same edits can share one map
+        let mut map = Map::new(scenario.map_name.path(), &mut timer);
+        if edits != "none" {
+            let edits = MapEdits::load(&map, edits.clone(), &mut timer)
+                .unwrap_or_else(|err| panic!("Couldn't load edits {}: {}", edits, err));
+            map.must_apply_edits(edits, &mut timer);
+            map.recalculate_pathfinding_after_edits(&mut timer);
+        }
+
+        let mut requests = Vec::new();
+        for demand_scale in &config.demand_scale {
+            for rng_seed in &config.rng_seeds {
+                run_idx += 1;
+                requests.push((
+                    run_idx,
+                    RunParams {
+                        demand_scale: *demand_scale,
+                        rng_seed: *rng_seed,
+                        edits: edits.clone(),
+                    },
+                ));
+            }
+        }
+
+        let map = &map;
+        let scenario = &scenario;
+        let opts = &opts;
+        let output = &config.output;
+        let results_db = &config.results_db;
+        timer.parallelize(
+            &format!("simulate runs with edits {}", edits),
+            Parallelism::Fastest,
+            requests,
+            |(idx, params)| {
+                let results_db = results_db
+                    .as_ref()
+                    .map(|path| (path, format!("run_{}", idx)));
+                let results = simulate(map, scenario, opts, end_time, results_db, params);
+                abstutil::write_json(format!("{}/run_{}.json", output, idx), &results);
+            },
+        );
+    }
+    println!("Wrote {} runs to {}", run_idx, config.output);
+}
+
+#[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+fn simulate(
+    map: &Map,
+    scenario: &Scenario,
+    opts: &SimOptions,
+    end_time: Option<Time>,
+    results_db: Option<(&String, String)>,
+    params: RunParams,
+) -> RunResults {
+    let mut timer = Timer::throwaway();
+    let mut rng = XorShiftRng::seed_from_u64(params.rng_seed);
+    let scenario = scale_demand(scenario.clone(), params.demand_scale, &mut rng);
+    let num_people = scenario.people.len();
+
+    let mut sim = Sim::new(map, opts.clone(), &mut timer);
+    #[cfg(feature = "sqlite")]
+    {
+        if let Some((path, ref run_name)) = results_db {
+            sim.write_results_to(path.clone(), run_name.clone(), map)
+                .unwrap_or_else(|err| panic!("Can't write to {}: {}", path, err));
+        }
+    }
+    scenario.instantiate(&mut sim, map, &mut rng, &mut timer);
+    let end_time = end_time.unwrap_or_else(|| sim.get_end_of_day());
+    sim.timed_step(map, end_time - Time::START_OF_DAY, &mut None, &mut timer);
+    #[cfg(feature = "sqlite")]
+    {
+        if let Some((path, _)) = results_db {
+            sim.finish_results_db()
+                .unwrap_or_else(|err| panic!("Can't write to {}: {}", path, err));
+        }
+    }
+
+    let analytics = sim.get_analytics();
+    let mut trips_finished = 0;
+    let mut trips_cancelled = 0;
+    let mut total_trip_time = Duration::ZERO;
+    for (_, _, _, maybe_dt) in &analytics.finished_trips {
+        if let Some(dt) = maybe_dt {
+            trips_finished += 1;
+            total_trip_time += *dt;
+        } else {
+            trips_cancelled += 1;
+        }
+    }
+    RunResults {
+        params,
+        end_time,
+        num_people,
+        trips_finished,
+        trips_cancelled,
+        total_trip_time,
+        mean_trip_time: if trips_finished == 0 {
+            Duration::ZERO
+        } else {
+            total_trip_time / (trips_finished as f64)
+        },
+        total_intersection_delay: analytics
+            .intersection_delays
+            .values()
+            .flatten()
+            .map(|(_, _, delay, _)| *delay)
+            .sum(),
+        total_road_thruput: analytics.road_thruput.all_total_counts().sum(),
+    }
+}
+
+/// Keeps each person `scale` times on average. Below 1, some people are dropped; above 1, some are
+/// copied.
+fn scale_demand(mut scenario: Scenario, scale: f64, rng: &mut XorShiftRng) -> Scenario {
+    let mut people = Vec::new();
+    // Copies don't join the original's household
+    let mut new_indices = Vec::new();
+    for person in scenario.people.drain(..) {
+        let mut copies = scale.floor() as usize;
+        if rng.gen_bool(scale.fract()) {
+            copies += 1;
+        }
+        new_indices.push(if copies == 0 {
+            None
+        } else {
+            Some(people.len())
+        });
+        for _ in 0..copies {
+            people.push(person.clone());
+        }
+    }
+    scenario.people = people;
+    scenario.renumber_households(&new_indices);
+    scenario
+}
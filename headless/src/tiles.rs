@@ -0,0 +1,370 @@
+//! Renders the map as Mapbox vector tiles, so a proposal can be shown in Leaflet, MapLibre, or
+//! anything else that understands XYZ tiles. Tiles are made on demand from whatever map is loaded,
+//! including edits. There are three layers -- lanes, intersections, and buildings -- with a few
+//! properties each to style them by. `style()` describes a MapLibre style that looks like the
+//! game.
+//!
+//! See https://github.com/mapbox/vector-tile-spec/tree/master/2.1 for the format. It's simple
+//! enough to write the protobuf encoding by hand.
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+use geom::{GPSBounds, LonLat, Pt2D};
+use map_model::Map;
+
+/// Coordinates inside a tile range from 0 to this
+const EXTENT: f64 = 4096.0;
+/// Include objects a bit outside the tile, so clients don't draw seams at tile edges
+const BUFFER: f64 = 64.0;
+
+const POLYGON: u64 = 3;
+
+/// A tile in the usual XYZ scheme, like `/14/2624/5721`
+#[derive(Clone, Copy, Debug)]
+pub struct TileID {
+    pub z: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl TileID {
+    /// Parses `z/x/y.mvt` or `z/x/y.pbf`.
+    pub fn parse(path: &str) -> Result<TileID, String> {
+        let path = path
+            .strip_suffix(".mvt")
+            .or_else(|| path.strip_suffix(".pbf"))
+            .ok_or_else(|| format!("{} should end in .mvt or .pbf", path))?;
+        let parts: Vec<&str> = path.split('/').collect();
+        if parts.len() != 3 {
+            return Err(format!("{} isn't z/x/y", path));
+        }
+        let parse = |x: &str| {
+            x.parse::<u32>()
+                .map_err(|err| format!("bad tile coordinate {}: {}", x, err))
+        };
+        let id = TileID {
+            z: parse(parts[0])?,
+            x: parse(parts[1])?,
+            y: parse(parts[2])?,
+        };
+        if id.z > 24 || id.x >= 1 << id.z || id.y >= 1 << id.z {
+            return Err(format!("{:?} doesn't exist", id));
+        }
+        Ok(id)
+    }
+
+    /// Web Mercator, from WGS84 into this tile's coordinates
+    fn project(self, pt: LonLat) -> (f64, f64) {
+        let n = f64::from(1u32 << self.z);
+        let x = (pt.x() + 180.0) / 360.0 * n;
+        let lat = pt.y().to_radians();
+        let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * n;
+        (
+            (x - f64::from(self.x)) * EXTENT,
+            (y - f64::from(self.y)) * EXTENT,
+        )
+    }
+}
+
+/// Encodes one tile. It's empty if the map doesn't cover it.
+pub fn render(map: &Map, id: TileID) -> Vec<u8> {
+    let gps_bounds = map.get_gps_bounds();
+
+    let mut lanes = Layer::new("lanes");
+    for l in map.all_lanes() {
+        let road = map.get_r(l.parent);
+        lanes.add(
+            l.id.0,
+            id,
+            gps_bounds,
+            l.lane_center_pts.to_thick_ring(l.width).points(),
+            vec![
+                ("type", Value::String(format!("{:?}", l.lane_type))),
+                ("road", Value::Uint(road.id.0 as u64)),
+                ("osm_way_id", Value::Int(road.orig_id.osm_way_id.0)),
+                ("name", Value::String(road.get_name(None))),
+            ],
+        );
+    }
+
+    let mut intersections = Layer::new("intersections");
+    for i in map.all_intersections() {
+        intersections.add(
+            i.id.0,
+            id,
+            gps_bounds,
+            i.polygon.points(),
+            vec![
+                ("type", Value::String(format!("{:?}", i.intersection_type))),
+                ("osm_node_id", Value::Int(i.orig_id.0)),
+            ],
+        );
+    }
+
+    let mut buildings = Layer::new("buildings");
+    for b in map.all_buildings() {
+        buildings.add(
+            b.id.0,
+            id,
+            gps_bounds,
+            b.polygon.points(),
+            vec![("address", Value::String(b.address.clone()))],
+        );
+    }
+
+    let mut tile = Vec::new();
+    for layer in vec![lanes, intersections, buildings] {
+        if !layer.features.is_empty() {
+            write_bytes(&mut tile, 3, &layer.encode());
+        }
+    }
+    tile
+}
+
+/// A MapLibre style for the tiles, with colors from the game's default color scheme.
+/// `tiles_url` looks like `http://localhost:1234/map/tiles/{z}/{x}/{y}.mvt`.
+pub fn style(tiles_url: String) -> serde_json::Value {
+    serde_json::json!({
+        "version": 8,
+        "sources": {
+            "abstreet": {
+                "type": "vector",
+                "tiles": [tiles_url],
+                "minzoom": 12,
+                "maxzoom": 20
+            }
+        },
+        "layers": [
+            {
+                "id": "background",
+                "type": "background",
+                "paint": { "background-color": "#DEDEDE" }
+            },
+            {
+                "id": "lanes",
+                "type": "fill",
+                "source": "abstreet",
+                "source-layer": "lanes",
+                "paint": {
+                    "fill-color": [
+                        "match", ["get", "type"],
+                        "Driving", "#000000",
+                        "SharedLeftTurn", "#000000",
+                        "Bus", "#BE4A4C",
+                        "Biking", "#0F7D4B",
+                        "Parking", "#333333",
+                        "Sidewalk", "#CCCCCC",
+                        "Shoulder", "#CCCCCC",
+                        "Footway", "#CCCCCC",
+                        "LightRail", "#844204",
+                        "Construction", "#FF8C00",
+                        "#000000"
+                    ]
+                }
+            },
+            {
+                "id": "intersections",
+                "type": "fill",
+                "source": "abstreet",
+                "source-layer": "intersections",
+                "paint": { "fill-color": "#333333" }
+            },
+            {
+                "id": "buildings",
+                "type": "fill",
+                "source": "abstreet",
+                "source-layer": "buildings",
+                "paint": { "fill-color": "#C4C1BC", "fill-outline-color": "#938E85" }
+            }
+        ]
+    })
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Value {
+    String(String),
+    Int(i64),
+    Uint(u64),
+}
+
+struct Layer {
+    name: &'static str,
+    keys: Vec<&'static str>,
+    values: Vec<Value>,
+    value_indices: BTreeMap<Value, usize>,
+    /// Encoded features
+    features: Vec<Vec<u8>>,
+}
+
+impl Layer {
+    fn new(name: &'static str) -> Layer {
+        Layer {
+            name,
+            keys: Vec::new(),
+            values: Vec::new(),
+            value_indices: BTreeMap::new(),
+            features: Vec::new(),
+        }
+    }
+
+    /// Adds one polygon, unless it's outside the tile.
+    fn add(
+        &mut self,
+        feature_id: usize,
+        tile: TileID,
+        gps_bounds: &GPSBounds,
+        ring: &[Pt2D],
+        props: Vec<(&'static str, Value)>,
+    ) {
+        let pts: Vec<(f64, f64)> = ring
+            .iter()
+            .map(|pt| tile.project(pt.to_gps(gps_bounds)))
+            .collect();
+        if !pts.iter().any(|(x, _)| *x >= -BUFFER)
+            || !pts.iter().any(|(x, _)| *x <= EXTENT + BUFFER)
+            || !pts.iter().any(|(_, y)| *y >= -BUFFER)
+            || !pts.iter().any(|(_, y)| *y <= EXTENT + BUFFER)
+        {
+            return;
+        }
+        let geometry = match encode_polygon(pts) {
+            Some(x) => x,
+            None => {
+                return;
+            }
+        };
+
+        let mut tags = Vec::new();
+        for (key, value) in props {
+            let key_idx = match self.keys.iter().position(|k| *k == key) {
+                Some(idx) => idx,
+                None => {
+                    self.keys.push(key);
+                    self.keys.len() - 1
+                }
+            };
+            let num_values = self.values.len();
+            let value_idx = *self
+                .value_indices
+                .entry(value.clone())
+                .or_insert(num_values);
+            if value_idx == num_values {
+                self.values.push(value);
+            }
+            tags.push(key_idx as u64);
+            tags.push(value_idx as u64);
+        }
+
+        let mut feature = Vec::new();
+        write_varint_field(&mut feature, 1, feature_id as u64);
+        write_packed(&mut feature, 2, &tags);
+        write_varint_field(&mut feature, 3, POLYGON);
+        write_packed(&mut feature, 4, &geometry);
+        self.features.push(feature);
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_varint_field(&mut buf, 15, 2);
+        write_bytes(&mut buf, 1, self.name.as_bytes());
+        for feature in &self.features {
+            write_bytes(&mut buf, 2, feature);
+        }
+        for key in &self.keys {
+            write_bytes(&mut buf, 3, key.as_bytes());
+        }
+        for value in &self.values {
+            let mut encoded = Vec::new();
+            match value {
+                Value::String(x) => write_bytes(&mut encoded, 1, x.as_bytes()),
+                Value::Int(x) => write_varint_field(&mut encoded, 4, *x as u64),
+                Value::Uint(x) => write_varint_field(&mut encoded, 5, *x),
+            }
+            write_bytes(&mut buf, 4, &encoded);
+        }
+        write_varint_field(&mut buf, 5, EXTENT as u64);
+        buf
+    }
+}
+
+/// Turns one ring into drawing commands. The ring must wind clockwise in tile coordinates, where Y
+/// points down.
+fn encode_polygon(pts: Vec<(f64, f64)>) -> Option<Vec<u64>> {
+    let mut ring: Vec<(i64, i64)> = Vec::new();
+    for (x, y) in pts {
+        let pt = (x.round() as i64, y.round() as i64);
+        if ring.last() != Some(&pt) {
+            ring.push(pt);
+        }
+    }
+    // The ring is implicitly closed
+    if ring.len() > 1 && ring[0] == *ring.last().unwrap() {
+        ring.pop();
+    }
+    if ring.len() < 3 {
+        return None;
+    }
+
+    let mut area = 0;
+    for (idx, (x1, y1)) in ring.iter().enumerate() {
+        let (x2, y2) = ring[(idx + 1) % ring.len()];
+        area += x1 * y2 - x2 * y1;
+    }
+    if area == 0 {
+        return None;
+    }
+    if area < 0 {
+        ring.reverse();
+    }
+
+    let mut cmds = Vec::new();
+    let mut cursor = (0, 0);
+    for (idx, pt) in ring.iter().enumerate() {
+        if idx == 0 {
+            cmds.push(command(1, 1));
+        } else if idx == 1 {
+            cmds.push(command(2, ring.len() - 1));
+        }
+        cmds.push(zigzag(pt.0 - cursor.0));
+        cmds.push(zigzag(pt.1 - cursor.1));
+        cursor = *pt;
+    }
+    cmds.push(command(7, 1));
+    Some(cmds)
+}
+
+fn command(id: u64, count: usize) -> u64 {
+    (id & 0x7) | ((count as u64) << 3)
+}
+
+fn zigzag(x: i64) -> u64 {
+    ((x << 1) ^ (x >> 63)) as u64
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut x: u64) {
+    while x >= 0x80 {
+        buf.push((x as u8) | 0x80);
+        x >>= 7;
+    }
+    buf.push(x as u8);
+}
+
+fn write_varint_field(buf: &mut Vec<u8>, field: u64, x: u64) {
+    write_varint(buf, field << 3);
+    write_varint(buf, x);
+}
+
+fn write_bytes(buf: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(buf, (field << 3) | 2);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn write_packed(buf: &mut Vec<u8>, field: u64, values: &[u64]) {
+    let mut packed = Vec::new();
+    for x in values {
+        write_varint(&mut packed, *x);
+    }
+    write_bytes(buf, field, &packed);
+}
@@ -0,0 +1,48 @@
+//! Experiments that compare different map edits usually simulate the same warm-up first, like the
+//! quiet early morning before the rush hour being studied. This simulates a scenario without any
+//! edits up to some time, then saves the simulation. Afterwards, pass the savestate to the headless
+//! server with --savestate, or as "savestate" in /sim/load, and edits are applied on top of it.
+//!
+//! > cd headless; cargo run --release -- warm_start --scenario=../data/system/seattle/scenarios/montlake/weekday.bin --until=06:00:00
+//!
+//! The savestate is written to the usual place under data/player/saves, unless --output (ending in
+//! .bin) is given. Flags for SimOptions are saved with it, so they're ignored when the server
+//! starts from it later.
+
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{CmdArgs, Timer};
+use geom::Time;
+use sim::{Sim, SimFlags, SimOptions};
+
+pub fn run(mut args: CmdArgs) {
+    let rng_seed = args
+        .optional_parse("--rng_seed", |s| s.parse())
+        .unwrap_or(SimFlags::RNG_SEED);
+    let opts = SimOptions::from_args(&mut args, rng_seed);
+    let scenario = args.required("--scenario");
+    let until = args.required("--until");
+    let output = args.optional("--output");
+    args.done();
+
+    let until = Time::parse(&until).unwrap_or_else(|_| panic!("Bad --until {}", until));
+    let mut timer = Timer::new("warm up the simulation");
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+    let (mut map, scenario) = sim::load_scenario(scenario, &mut rng, &mut timer);
+    let mut sim = Sim::new(&map, opts, &mut timer);
+    scenario.instantiate(&mut sim, &map, &mut rng, &mut timer);
+    sim.apply_lane_closures(&mut map, &mut timer);
+
+    while sim.time() < until {
+        crate::step_with_closures(&mut sim, &mut map, until);
+    }
+
+    let path = if let Some(path) = output {
+        abstutil::write_versioned_binary(path.clone(), &sim);
+        path
+    } else {
+        sim.save()
+    };
+    println!("Saved the simulation at {} to {}", sim.time(), path);
+}
@@ -0,0 +1,12 @@
+use abstutil::{CmdArgs, Timer};
+use map_model::Map;
+
+/// Exports a map to OpenDRIVE XML, for other driving simulators to use.
+fn main() {
+    let mut args = CmdArgs::new();
+    let output = args.required("--output");
+    let map = Map::new(args.required_free(), &mut Timer::throwaway());
+    args.done();
+    map_model::opendrive::export(&map, &output).unwrap();
+    println!("Wrote {}", output);
+}
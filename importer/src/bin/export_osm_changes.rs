@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use std::process::Command;
+
+use abstutil::{CmdArgs, Tags, Timer};
+use map_model::osm_changes::WayChange;
+use map_model::{Map, MapEdits};
+
+/// Turns map edits that correspond to real-world attributes (lane counts, bike lanes, sidewalks,
+/// speed limits) into an OsmChange file, so corrections can be reviewed in JOSM and uploaded to
+/// OpenStreetMap. The latest version of each way is downloaded, so the diff applies cleanly.
+fn main() {
+    let mut args = CmdArgs::new();
+    let map = args.required("--map");
+    let edits = args.required("--edits");
+    let output = args
+        .optional("--output")
+        .unwrap_or_else(|| "diff.osc".to_string());
+    args.done();
+
+    let mut timer = Timer::new("export OSM changes");
+    let mut map = Map::new(map, &mut timer);
+    let edits = MapEdits::load(&map, edits, &mut timer).unwrap();
+    map.must_apply_edits(edits, &mut timer);
+
+    let changes = map_model::osm_changes::find_changes(&map);
+    for way in &changes.conflicting_ways {
+        println!(
+            "Skipping {}: only part of it was edited, so split it in OSM first",
+            way
+        );
+    }
+    if changes.ways.is_empty() {
+        println!("None of the edits change anything that's mapped in OSM");
+        return;
+    }
+    write_osmc(&changes.ways, &output, &mut timer).unwrap();
+    println!("Wrote {} ways to {}", changes.ways.len(), output);
+}
+
+fn write_osmc(
+    ways: &Vec<WayChange>,
+    output: &str,
+    timer: &mut Timer,
+) -> Result<(), Box<dyn Error>> {
+    let mut modified_ways = Vec::new();
+    timer.start_iter("fetch latest OSM data per modified way", ways.len());
+    for change in ways {
+        timer.next();
+        let url = format!("https://api.openstreetmap.org/api/0.6/way/{}", change.way.0);
+        let resp = Command::new("curl")
+            .arg("--fail")
+            .arg("-s")
+            .arg(&url)
+            .output()?;
+        if !resp.status.success() {
+            return Err(format!("Couldn't fetch {}", url).into());
+        }
+        let mut tree = xmltree::Element::parse(resp.stdout.as_slice())?
+            .take_child("way")
+            .ok_or_else(|| format!("No way in {}", url))?;
+
+        let mut osm_tags = Tags::new(BTreeMap::new());
+        let mut other_children = Vec::new();
+        for node in tree.children.drain(..) {
+            if let Some(elem) = node.as_element() {
+                if elem.name == "tag" {
+                    osm_tags.insert(elem.attributes["k"].clone(), elem.attributes["v"].clone());
+                    continue;
+                }
+            }
+            other_children.push(node);
+        }
+        change.apply(&mut osm_tags);
+
+        tree.children = other_children;
+        for (k, v) in osm_tags.inner() {
+            let mut new_elem = xmltree::Element::new("tag");
+            new_elem.attributes.insert("k".to_string(), k.to_string());
+            new_elem.attributes.insert("v".to_string(), v.to_string());
+            tree.children.push(xmltree::XMLNode::Element(new_elem));
+        }
+
+        tree.attributes.remove("timestamp");
+        tree.attributes.remove("changeset");
+        tree.attributes.remove("user");
+        tree.attributes.remove("uid");
+        tree.attributes.remove("visible");
+
+        let mut bytes: Vec<u8> = Vec::new();
+        tree.write(&mut bytes)?;
+        let out = String::from_utf8(bytes)?;
+        let stripped = out.trim_start_matches("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+        modified_ways.push(stripped.to_string());
+    }
+
+    let mut f = File::create(output)?;
+    writeln!(f, "<osmChange version=\"0.6\" generator=\"abst\"><modify>")?;
+    for w in modified_ways {
+        writeln!(f, "  {}", w)?;
+    }
+    writeln!(f, "</modify></osmChange>")?;
+    Ok(())
+}
@@ -0,0 +1,14 @@
+use abstutil::{CmdArgs, Timer};
+use map_model::Map;
+
+/// Exports the timing of every traffic signal in a map, in the format described in
+/// map_model/src/signal_plans.rs.
+fn main() {
+    let mut args = CmdArgs::new();
+    let output = args.required("--output");
+    let map = Map::new(args.required_free(), &mut Timer::throwaway());
+    args.done();
+    let plans = map_model::signal_plans::export_all(&map);
+    abstutil::write_json(output.clone(), &plans);
+    println!("Wrote {} signals to {}", plans.signals.len(), output);
+}
@@ -0,0 +1,181 @@
+//! Turns the output of a regional travel demand model into a scenario. The input is an
+//! origin-destination matrix: a CSV file with one row per (origin zone, destination zone, hour,
+//! mode), counting how many trips happen. Each trip is expanded into one person, starting from a
+//! random building in the origin zone and going to a random building in the destination zone.
+//! Zones that don't contain any buildings on the map are matched to the nearest border
+//! intersection instead.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+
+use geojson::{GeoJson, Value};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::Deserialize;
+
+use abstutil::{prettyprint_usize, CmdArgs, Timer};
+use geom::{Duration, LonLat, Polygon, Ring, Time};
+use map_model::{IntersectionID, Map};
+use sim::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
+
+fn main() {
+    let mut args = CmdArgs::new();
+    let map = args.required("--map");
+    let zones_path = args.required("--zones");
+    let zone_property = args
+        .optional("--zone_property")
+        .unwrap_or_else(|| "name".to_string());
+    let input = args.required("--input");
+    let scenario_name = args.required("--scenario_name");
+    let rng_seed = args
+        .optional_parse("--rng_seed", |s| s.parse::<u64>())
+        .unwrap_or(42);
+    args.done();
+
+    let mut timer = Timer::new("import origin-destination matrix");
+    let map = Map::new(map, &mut timer);
+    let zones = load_zones(&map, &zones_path, &zone_property).unwrap();
+    let mut rng = XorShiftRng::seed_from_u64(rng_seed);
+
+    let mut s = Scenario::empty(&map, &scenario_name);
+    // Include all buses/trains
+    s.only_seed_buses = None;
+    let mut skipped = 0;
+    for rec in csv::Reader::from_reader(File::open(&input).unwrap()).deserialize() {
+        let rec: Record = rec.unwrap();
+        let (from, to) = match (zones.get(&rec.origin), zones.get(&rec.destination)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                timer.warn(format!(
+                    "Skipping trips from zone {} to {}; one of them isn't in {}",
+                    rec.origin, rec.destination, zones_path
+                ));
+                continue;
+            }
+        };
+        // Counts from a model are often fractional; round randomly, so the totals work out.
+        let mut count = rec.count.floor() as usize;
+        if rng.gen_bool(rec.count.fract()) {
+            count += 1;
+        }
+        for _ in 0..count {
+            let origin = from.pick(&mut rng);
+            let destination = to.pick(&mut rng);
+            if origin == destination {
+                skipped += 1;
+                continue;
+            }
+            let depart = Time::START_OF_DAY
+                + Duration::hours(rec.hour)
+                + Duration::seconds(rng.gen_range(0.0, 3600.0));
+            s.people.push(PersonSpec {
+                orig_id: None,
+                origin,
+                trips: vec![IndividTrip::new(
+                    depart,
+                    // Purpose isn't part of the input
+                    TripPurpose::Shopping,
+                    destination,
+                    rec.mode,
+                )],
+            });
+        }
+    }
+    timer.note(format!(
+        "Created {} trips, skipped {} that start and end at the same place",
+        prettyprint_usize(s.people.len()),
+        prettyprint_usize(skipped)
+    ));
+    s.save();
+}
+
+#[derive(Deserialize)]
+struct Record {
+    origin: String,
+    destination: String,
+    /// Trips depart uniformly within this hour, starting from midnight
+    hour: usize,
+    mode: TripMode,
+    count: f64,
+}
+
+/// Where trips to or from one zone can start or end
+struct Zone {
+    endpoints: Vec<TripEndpoint>,
+}
+
+impl Zone {
+    fn pick(&self, rng: &mut XorShiftRng) -> TripEndpoint {
+        *self.endpoints.choose(rng).unwrap()
+    }
+}
+
+fn load_zones(
+    map: &Map,
+    path: &str,
+    zone_property: &str,
+) -> Result<BTreeMap<String, Zone>, String> {
+    let geojson = abstutil::slurp_file(path)?;
+    let geojson = String::from_utf8(geojson)
+        .map_err(|err| err.to_string())?
+        .parse::<GeoJson>()
+        .map_err(|err| err.to_string())?;
+    let features = match geojson {
+        GeoJson::Feature(feature) => vec![feature],
+        GeoJson::FeatureCollection(feature_collection) => feature_collection.features,
+        _ => return Err(format!("Unexpected geojson in {}", path)),
+    };
+
+    let mut zones = BTreeMap::new();
+    for mut feature in features {
+        let name = match feature
+            .properties
+            .as_ref()
+            .and_then(|props| props.get(zone_property))
+        {
+            Some(serde_json::Value::String(name)) => name.clone(),
+            Some(value) => value.to_string(),
+            None => return Err(format!("A zone is missing the {} property", zone_property)),
+        };
+        let points = match feature.geometry.take().map(|g| g.value) {
+            Some(Value::MultiPolygon(multi_polygon)) => multi_polygon[0][0].clone(),
+            Some(Value::Polygon(polygon)) => polygon[0].clone(),
+            _ => {
+                return Err(format!("Zone {} isn't a polygon", name));
+            }
+        };
+        let points: Vec<LonLat> = points
+            .into_iter()
+            .map(|pt| LonLat::new(pt[0], pt[1]))
+            .collect();
+        let polygon = Ring::new(map.get_gps_bounds().convert(&points))
+            .map_err(|err| format!("Zone {} has a bad polygon: {}", name, err))?
+            .to_polygon();
+
+        let mut endpoints: Vec<TripEndpoint> = map
+            .all_buildings()
+            .iter()
+            .filter(|b| polygon.contains_pt(b.label_center))
+            .map(|b| TripEndpoint::Bldg(b.id))
+            .collect();
+        if endpoints.is_empty() {
+            endpoints.push(TripEndpoint::Border(closest_border(map, &polygon)?));
+        }
+        zones.insert(name, Zone { endpoints });
+    }
+    Ok(zones)
+}
+
+/// Zones outside the map still send trips into it, so use the border closest to them. This doesn't
+/// distinguish incoming and outgoing borders; if a border only works in one direction, some trips
+/// will fail to start.
+fn closest_border(map: &Map, zone: &Polygon) -> Result<IntersectionID, String> {
+    let center = zone.center();
+    map.all_intersections()
+        .iter()
+        .filter(|i| i.is_border())
+        .min_by_key(|i| i.polygon.center().dist_to(center))
+        .map(|i| i.id)
+        .ok_or_else(|| "The map doesn't have any borders".to_string())
+}
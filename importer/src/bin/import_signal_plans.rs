@@ -0,0 +1,30 @@
+use abstutil::{CmdArgs, Timer};
+use map_model::signal_plans::SignalPlans;
+use map_model::Map;
+
+/// Imports traffic signal timing plans, in the format described in
+/// map_model/src/signal_plans.rs, and saves them as map edits.
+fn main() {
+    let mut args = CmdArgs::new();
+    let map = args.required("--map");
+    let input = args.required("--input");
+    let edits_name = args.required("--edits_name");
+    args.done();
+
+    let mut timer = Timer::new("import traffic signal plans");
+    let mut map = Map::new(map, &mut timer);
+    let plans: SignalPlans = abstutil::read_json(input, &mut timer);
+    let num_signals = plans.signals.len();
+    let cmds = map_model::signal_plans::import(&map, plans).unwrap();
+
+    let mut edits = map.get_edits().clone();
+    edits.edits_name = edits_name;
+    edits.commands.extend(cmds);
+    map.must_apply_edits(edits, &mut timer);
+    map.save_edits();
+    println!(
+        "Saved edits with {} traffic signals as {}",
+        num_signals,
+        map.get_edits().edits_name
+    );
+}
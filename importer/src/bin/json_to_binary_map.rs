@@ -6,6 +6,6 @@ fn main() {
     // TODO This can't handle the output of dump_map! What?!
     let mut map: Map = abstutil::read_json(args.required("--input"), &mut Timer::throwaway());
     map.map_loaded_directly();
-    abstutil::write_binary(args.required("--output"), &map);
+    abstutil::write_versioned_binary(args.required("--output"), &map);
     args.done();
 }
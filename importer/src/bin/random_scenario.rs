@@ -11,10 +11,17 @@ fn main() {
     let mut rng = XorShiftRng::seed_from_u64(seed);
     let map = Map::new(args.required("--map"), &mut Timer::throwaway());
     let scenario_name = args.required("--scenario_name");
+    let synthetic = args.enabled("--synthetic");
+    let days = args
+        .optional_parse("--days", |s| s.parse::<usize>())
+        .unwrap_or(1);
     args.done();
 
-    let mut scenario =
-        ScenarioGenerator::proletariat_robot(&map, &mut rng, &mut Timer::throwaway());
+    let mut scenario = if synthetic {
+        ScenarioGenerator::synthetic_population(&map, days, &mut rng, &mut Timer::throwaway())
+    } else {
+        ScenarioGenerator::proletariat_robot(&map, &mut rng, &mut Timer::throwaway())
+    };
     scenario.scenario_name = scenario_name;
     scenario.save();
 }
@@ -0,0 +1,24 @@
+use abstutil::{CmdArgs, MapName, Timer};
+use map_model::raw::RawMap;
+use map_model::Map;
+
+/// Combines two adjacent maps in the same city into one, joining the roads cut at their shared
+/// boundary. This works from the RawMaps produced by importing each map, so they must exist first.
+fn main() {
+    let mut args = CmdArgs::new();
+    let city = args.required("--city");
+    let map1 = MapName::new(&city, &args.required("--map1"));
+    let map2 = MapName::new(&city, &args.required("--map2"));
+    let output = MapName::new(&city, &args.required("--output"));
+    args.done();
+
+    let mut timer = Timer::new(format!("stitch {} and {}", map1.map, map2.map));
+    let raw1: RawMap = abstutil::read_binary(abstutil::path_raw_map(&map1), &mut timer);
+    let raw2: RawMap = abstutil::read_binary(abstutil::path_raw_map(&map2), &mut timer);
+    let raw = raw1.stitch(raw2, output, &mut timer);
+    raw.save();
+
+    let map = Map::create_from_raw(raw, true, false, &mut timer);
+    map.save();
+    println!("Wrote {}", map.get_name().path());
+}
@@ -34,7 +34,9 @@ pub fn osm_to_raw(name: &str, timer: &mut abstutil::Timer, config: &ImporterConf
             map_config: map_model::MapConfig {
                 driving_side: map_model::DrivingSide::Left,
                 bikes_can_use_bus_lanes: false,
+                bikes_avoid_stress: false,
                 inferred_sidewalks: true,
+                clean_geometry: false,
             },
 
             onstreet_parking: convert_osm::OnstreetParking::JustOSM,
@@ -28,6 +28,7 @@ struct Job {
 
     skip_ch: bool,
     keep_bldg_tags: bool,
+    incremental: bool,
 
     only_map: Option<String>,
 
@@ -35,6 +36,7 @@ struct Job {
     oneshot_clip: Option<String>,
     oneshot_drive_on_left: bool,
     oneshot_dont_infer_sidewalks: bool,
+    oneshot_clean_geometry: bool,
 }
 
 fn main() {
@@ -54,6 +56,10 @@ fn main() {
         skip_ch: args.enabled("--skip_ch"),
         // Preserve OSM tags for buildings, increasing the file size.
         keep_bldg_tags: args.enabled("--keep_bldg_tags"),
+        // When re-importing a map after its OSM data changes, keep the IDs of roads,
+        // intersections, and buildings that still exist, so existing scenarios and edits stay
+        // valid.
+        incremental: args.enabled("--incremental"),
 
         // Only process one map. If not specified, process all maps defined by clipping polygons in
         // importer/config/$city/.
@@ -64,6 +70,8 @@ fn main() {
         oneshot_clip: args.optional("--oneshot_clip"),
         oneshot_drive_on_left: args.enabled("--oneshot_drive_on_left"),
         oneshot_dont_infer_sidewalks: args.enabled("--oneshot_dont_infer_sidewalks"),
+        // Merge stub roads and smooth jagged road geometry. See `MapConfig::clean_geometry`.
+        oneshot_clean_geometry: args.enabled("--oneshot_clean_geometry"),
     };
     args.done();
     if !job.osm_to_raw
@@ -96,6 +104,7 @@ fn main() {
             job.oneshot_clip,
             !job.oneshot_drive_on_left,
             !job.oneshot_dont_infer_sidewalks,
+            job.oneshot_clean_geometry,
             !job.skip_ch,
             job.keep_bldg_tags,
         );
@@ -160,7 +169,13 @@ fn main() {
         let name = MapName::new(&job.city, &name);
 
         let mut maybe_map = if job.raw_to_map {
-            let mut map = utils::raw_to_map(&name, !job.skip_ch, job.keep_bldg_tags, &mut timer);
+            let mut map = utils::raw_to_map(
+                &name,
+                !job.skip_ch,
+                job.keep_bldg_tags,
+                job.incremental,
+                &mut timer,
+            );
 
             // Another strange step in the pipeline.
             if name == MapName::new("berlin", "center") {
@@ -175,7 +190,7 @@ fn main() {
                 ));
             } else if name.city == "seattle" {
                 timer.start(format!("add GTFS schedules for {}", name.describe()));
-                seattle::add_gtfs_schedules(&mut map);
+                seattle::add_gtfs_schedules(&mut map, &mut timer);
                 timer.stop(format!("add GTFS schedules for {}", name.describe()));
             }
 
@@ -226,6 +241,7 @@ fn oneshot(
     clip: Option<String>,
     drive_on_right: bool,
     inferred_sidewalks: bool,
+    clean_geometry: bool,
     build_ch: bool,
     keep_bldg_tags: bool,
 ) {
@@ -245,11 +261,13 @@ fn oneshot(
                     map_model::DrivingSide::Left
                 },
                 bikes_can_use_bus_lanes: true,
+                bikes_avoid_stress: false,
                 inferred_sidewalks,
+                clean_geometry,
             },
 
             onstreet_parking: convert_osm::OnstreetParking::JustOSM,
-            public_offstreet_parking: convert_osm::PublicOffstreetParking::None,
+            public_offstreet_parking: convert_osm::PublicOffstreetParking::OSM,
             private_offstreet_parking: convert_osm::PrivateOffstreetParking::FixedPerBldg(1),
             elevation: None,
             include_railroads: true,
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use abstutil::{prettyprint_usize, MultiMap, Parallelism, Timer};
 use geom::LonLat;
 use map_model::{osm, BuildingID, IntersectionID, Map, PathConstraints, PathRequest, PathStep};
-use sim::{IndividTrip, OrigPersonID, PersonSpec, Scenario, TripEndpoint, TripMode};
+use sim::{IndividTrip, OrigPersonID, PersonSpec, Scenario, TripEndpoint, TripMode, Weather};
 
 use crate::soundcast::popdat::{Endpoint, OrigTrip, PopDat};
 
@@ -193,12 +193,14 @@ fn clip_trips(map: &Map, popdat: &PopDat, huge_map: &Map, timer: &mut Timer) ->
                     TripMode::Walk | TripMode::Transit => {
                         (&incoming_borders_walking, &outgoing_borders_walking)
                     }
-                    TripMode::Drive => (&incoming_borders_driving, &outgoing_borders_driving),
+                    TripMode::Drive | TripMode::RideHail => {
+                        (&incoming_borders_driving, &outgoing_borders_driving)
+                    }
                     TripMode::Bike => (&incoming_borders_biking, &outgoing_borders_biking),
                 },
                 match orig.mode {
                     TripMode::Walk | TripMode::Transit => PathConstraints::Pedestrian,
-                    TripMode::Drive => PathConstraints::Car,
+                    TripMode::Drive | TripMode::RideHail => PathConstraints::Car,
                     TripMode::Bike => PathConstraints::Bike,
                 },
                 maybe_huge_map.as_ref(),
@@ -290,6 +292,11 @@ pub fn make_weekday_scenario(
         map_name: map.get_name().clone(),
         people,
         only_seed_buses: None,
+        extra_days: 0,
+        households: Vec::new(),
+        weather: Weather::Clear,
+        driver_mix: Vec::new(),
+        lane_closures: Vec::new(),
     }
     .remove_weird_schedules()
 }
@@ -1,7 +1,8 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::process::Command;
 
-use abstutil::{must_run_cmd, MapName, Timer};
+use abstutil::{must_run_cmd, prettyprint_usize, MapName, Timer};
 
 use crate::configuration::ImporterConfiguration;
 
@@ -127,11 +128,41 @@ pub fn raw_to_map(
     name: &MapName,
     build_ch: bool,
     keep_bldg_tags: bool,
+    incremental: bool,
     timer: &mut Timer,
 ) -> map_model::Map {
     timer.start(format!("Raw->Map for {}", name.describe()));
-    let raw: map_model::raw::RawMap = abstutil::read_binary(abstutil::path_raw_map(name), timer);
-    let map = map_model::Map::create_from_raw(raw, build_ch, keep_bldg_tags, timer);
+    let mut raw: map_model::raw::RawMap =
+        abstutil::read_binary(abstutil::path_raw_map(name), timer);
+    raw.apply_consolidation_hints(&map_model::raw::ConsolidationHints::load(name), timer);
+    let previous = if incremental {
+        match abstutil::maybe_read_versioned_binary::<map_model::Map>(name.path(), timer) {
+            Ok(map) => Some(map),
+            Err(err) => {
+                timer.warn(format!(
+                    "Can't load the previous {}, so IDs won't be preserved: {}",
+                    name.path(),
+                    err
+                ));
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let map = if let Some(previous) = previous {
+        let map = map_model::Map::create_from_raw_preserving_ids(
+            raw,
+            &previous,
+            build_ch,
+            keep_bldg_tags,
+            timer,
+        );
+        describe_changes(&previous, &map, timer);
+        map
+    } else {
+        map_model::Map::create_from_raw(raw, build_ch, keep_bldg_tags, timer)
+    };
     timer.start("save map");
     map.save();
     timer.stop("save map");
@@ -149,3 +180,60 @@ pub fn raw_to_map(
 
     map
 }
+
+/// Summarizes what changed between two imports of the same map, to see what scenarios and edits
+/// might need attention.
+fn describe_changes(old: &map_model::Map, new: &map_model::Map, timer: &mut Timer) {
+    fn compare<K: Ord, I: Ord>(
+        timer: &mut Timer,
+        objects: &str,
+        old: BTreeMap<K, I>,
+        new: BTreeMap<K, I>,
+    ) {
+        let removed = old.keys().filter(|k| !new.contains_key(*k)).count();
+        let added = new.keys().filter(|k| !old.contains_key(*k)).count();
+        let renumbered = new
+            .iter()
+            .filter(|(k, id)| old.get(*k).map(|old_id| old_id != *id).unwrap_or(false))
+            .count();
+        timer.note(format!(
+            "{} {} removed, {} added, {} kept the same ID, {} had to be renumbered",
+            prettyprint_usize(removed),
+            objects,
+            prettyprint_usize(added),
+            prettyprint_usize(new.len() - added - renumbered),
+            prettyprint_usize(renumbered)
+        ));
+    }
+
+    compare(
+        timer,
+        "roads",
+        old.all_roads().iter().map(|r| (r.orig_id, r.id)).collect(),
+        new.all_roads().iter().map(|r| (r.orig_id, r.id)).collect(),
+    );
+    compare(
+        timer,
+        "intersections",
+        old.all_intersections()
+            .iter()
+            .map(|i| (i.orig_id, i.id))
+            .collect(),
+        new.all_intersections()
+            .iter()
+            .map(|i| (i.orig_id, i.id))
+            .collect(),
+    );
+    compare(
+        timer,
+        "buildings",
+        old.all_buildings()
+            .iter()
+            .map(|b| (b.orig_id, b.id))
+            .collect(),
+        new.all_buildings()
+            .iter()
+            .map(|b| (b.orig_id, b.id))
+            .collect(),
+    );
+}
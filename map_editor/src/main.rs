@@ -9,6 +9,7 @@ use widgetry::{
     Outcome, Panel, ScreenPt, SharedAppState, Text, Transition, VerticalAlignment, Widget,
 };
 
+mod measure;
 mod model;
 mod world;
 
@@ -44,6 +45,7 @@ enum State {
     MovingBuilding(osm::OsmID),
     MovingRoadPoint(OriginalRoad, usize),
     CreatingRoad(osm::NodeID),
+    PairingIntersections(osm::NodeID),
     PreviewIntersection(Drawable),
 }
 
@@ -76,6 +78,7 @@ impl MainState {
                         Btn::text_fg("quit").build_def(ctx, Key::Escape),
                         Btn::text_fg("export to OSM").build_def(ctx, None),
                         Btn::text_fg("preview all intersections").build_def(ctx, Key::G),
+                        Btn::text_fg("measure").build_def(ctx, Key::M),
                     ]),
                 ]))
                 .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
@@ -133,6 +136,8 @@ impl widgetry::State<App> for MainState {
                             self.state = State::MovingIntersection(i);
                         } else if ctx.input.pressed(Key::R) {
                             self.state = State::CreatingRoad(i);
+                        } else if ctx.input.pressed(Key::C) {
+                            self.state = State::PairingIntersections(i);
                         } else if ctx.input.pressed(Key::Backspace) {
                             app.model.delete_i(i);
                             app.model.world.handle_mouseover(ctx);
@@ -185,6 +190,14 @@ impl widgetry::State<App> for MainState {
                                         self.state = State::PreviewIntersection(draw);
                                     }
                                 }
+                                "measure" => {
+                                    return Transition::Push(measure::Measure::new(
+                                        ctx,
+                                        app,
+                                        Vec::new(),
+                                        true,
+                                    ));
+                                }
                                 _ => unreachable!(),
                             },
                             _ => {
@@ -243,6 +256,21 @@ impl widgetry::State<App> for MainState {
                     }
                 }
             }
+            State::PairingIntersections(i1) => {
+                if ctx.input.pressed(Key::Escape) {
+                    self.state = State::Viewing;
+                    app.model.world.handle_mouseover(ctx);
+                } else if let Some(ID::Intersection(i2)) = app.model.world.get_selection() {
+                    if i1 != i2 && ctx.input.pressed(Key::M) {
+                        app.model.merge_intersections(i1, i2, ctx);
+                        self.state = State::Viewing;
+                        app.model.world.handle_mouseover(ctx);
+                    } else if i1 != i2 && ctx.input.pressed(Key::N) {
+                        app.model.keep_intersections_apart(i1, i2);
+                        self.state = State::Viewing;
+                    }
+                }
+            }
             State::PreviewIntersection(_) => {
                 if ctx.input.pressed(Key::P) {
                     self.state = State::Viewing;
@@ -284,6 +312,18 @@ impl widgetry::State<App> for MainState {
             _ => app.model.world.draw(g, |_| true),
         }
 
+        // Pairs of intersections that won't be merged during import
+        for (i1, i2) in &app.model.hints.dont_merge {
+            if let (Some(i1), Some(i2)) = (
+                app.model.map.intersections.get(i1),
+                app.model.map.intersections.get(i2),
+            ) {
+                if let Some(l) = Line::new(i1.point, i2.point) {
+                    g.draw_polygon(Color::RED, l.make_polygons(Distance::meters(2.0)));
+                }
+            }
+        }
+
         match self.state {
             State::CreatingRoad(i1) => {
                 if let Some(cursor) = g.get_cursor_in_map_space() {
@@ -292,6 +332,13 @@ impl widgetry::State<App> for MainState {
                     }
                 }
             }
+            State::PairingIntersections(i1) => {
+                if let Some(cursor) = g.get_cursor_in_map_space() {
+                    if let Some(l) = Line::new(app.model.map.intersections[&i1].point, cursor) {
+                        g.draw_polygon(Color::PURPLE, l.make_polygons(Distance::meters(5.0)));
+                    }
+                }
+            }
             State::Viewing
             | State::MovingIntersection(_)
             | State::MovingBuilding(_)
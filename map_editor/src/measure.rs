@@ -0,0 +1,166 @@
+//! Click points on the map to measure between them, for checking imported geometry against
+//! reality. Distances are measured in a straight line and along roads, and three or more points
+//! also measure the area they enclose.
+
+use abstutil::prettyprint_usize;
+use geom::{Circle, Distance, PolyLine, Pt2D, Ring, UnitFmt};
+use widgetry::{
+    Btn, Checkbox, Color, Drawable, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
+    Outcome, Panel, State, Text, Transition, VerticalAlignment, Widget,
+};
+
+use crate::App;
+
+const POINT_RADIUS: Distance = Distance::const_meters(3.0);
+const LINE_THICKNESS: Distance = Distance::const_meters(1.5);
+
+pub struct Measure {
+    pts: Vec<Pt2D>,
+    metric: bool,
+    panel: Panel,
+    draw: Drawable,
+}
+
+impl Measure {
+    pub fn new(ctx: &mut EventCtx, app: &App, pts: Vec<Pt2D>, metric: bool) -> Box<dyn State<App>> {
+        let fmt = UnitFmt {
+            round_durations: true,
+            metric,
+        };
+        let mut batch = GeomBatch::new();
+        let mut txt = Text::new();
+
+        if pts.len() < 2 {
+            txt.add(Line("Click at least two points"));
+        } else {
+            let pl = PolyLine::unchecked_new(pts.clone());
+            txt.add(Line(format!(
+                "Straight line: {}",
+                pl.length().to_string(&fmt)
+            )));
+
+            let mut network = Some(Distance::ZERO);
+            for pair in pts.windows(2) {
+                network = network.and_then(|total| {
+                    Some(total + app.model.map.path_dist_between(pair[0], pair[1])?)
+                });
+            }
+            txt.add(Line(match network {
+                Some(dist) => format!("Along roads: {}", dist.to_string(&fmt)),
+                None => "Along roads: no route".to_string(),
+            }));
+
+            if pts.len() >= 3 {
+                let mut closed = pts.clone();
+                closed.push(pts[0]);
+                match Ring::new(closed) {
+                    Ok(ring) => {
+                        let poly = ring.to_polygon();
+                        txt.add(Line(format!(
+                            "Area: {}",
+                            describe_area(poly.area(), metric)
+                        )));
+                        batch.push(Color::CYAN.alpha(0.3), poly);
+                    }
+                    Err(_) => {
+                        txt.add(Line("Area: the points don't form a simple shape").secondary());
+                    }
+                }
+            }
+            batch.push(Color::CYAN, pl.make_polygons(LINE_THICKNESS));
+        }
+        for pt in &pts {
+            batch.push(Color::RED, Circle::new(*pt, POINT_RADIUS).to_polygon());
+        }
+
+        Box::new(Measure {
+            panel: Panel::new(Widget::col(vec![
+                Line("Measure").small_heading().draw(ctx),
+                Text::from_multiline(vec![
+                    Line("Click to add a point, backspace to remove the last one."),
+                    Line("Along-road distances snap each point to the closest road.").secondary(),
+                ])
+                .draw(ctx),
+                txt.draw(ctx),
+                Checkbox::switch(ctx, "metric / imperial units", None, metric),
+                Widget::row(vec![
+                    Btn::text_fg("clear").build_def(ctx, None),
+                    Btn::text_fg("close").build_def(ctx, Key::Escape),
+                ]),
+            ]))
+            .aligned(HorizontalAlignment::Right, VerticalAlignment::Top)
+            .build(ctx),
+            draw: ctx.upload(batch),
+            pts,
+            metric,
+        })
+    }
+}
+
+impl State<App> for Measure {
+    fn event(&mut self, ctx: &mut EventCtx, app: &mut App) -> Transition<App> {
+        ctx.canvas_movement();
+
+        match self.panel.event(ctx) {
+            Outcome::Clicked(x) => match x.as_ref() {
+                "close" => {
+                    return Transition::Pop;
+                }
+                "clear" => {
+                    return Transition::Replace(Measure::new(ctx, app, Vec::new(), self.metric));
+                }
+                _ => unreachable!(),
+            },
+            Outcome::Changed => {
+                let metric = self.panel.is_checked("metric / imperial units");
+                return Transition::Replace(Measure::new(ctx, app, self.pts.clone(), metric));
+            }
+            _ => {}
+        }
+
+        if ctx.input.pressed(Key::Backspace) && !self.pts.is_empty() {
+            let mut pts = self.pts.clone();
+            pts.pop();
+            return Transition::Replace(Measure::new(ctx, app, pts, self.metric));
+        }
+        if let Some(pt) = ctx.canvas.get_cursor_in_map_space() {
+            if ctx.normal_left_click() {
+                let mut pts = self.pts.clone();
+                pts.push(pt);
+                return Transition::Replace(Measure::new(ctx, app, pts, self.metric));
+            }
+        }
+
+        Transition::Keep
+    }
+
+    fn draw(&self, g: &mut GfxCtx, app: &App) {
+        g.clear(Color::BLACK);
+        g.draw_polygon(
+            Color::rgb(242, 239, 233),
+            app.model.map.boundary_polygon.clone(),
+        );
+        app.model.world.draw(g, |_| true);
+
+        g.redraw(&self.draw);
+        self.panel.draw(g);
+    }
+}
+
+/// Areas are in square meters. Large areas are in hectares or acres.
+fn describe_area(square_meters: f64, metric: bool) -> String {
+    if metric {
+        if square_meters < 10_000.0 {
+            format!("{} m²", prettyprint_usize(square_meters.round() as usize))
+        } else {
+            format!("{:.1} hectares", square_meters / 10_000.0)
+        }
+    } else {
+        let square_feet = square_meters * 10.7639;
+        if square_feet < 43_560.0 {
+            format!("{} ft²", prettyprint_usize(square_feet.round() as usize))
+        } else {
+            format!("{:.1} acres", square_feet / 43_560.0)
+        }
+    }
+}
@@ -1,11 +1,13 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io::Write;
 
 use abstutil::{MapName, Tags, Timer};
 use geom::{
     Bounds, Circle, Distance, FindClosest, GPSBounds, HashablePt2D, LonLat, PolyLine, Polygon, Pt2D,
 };
-use map_model::raw::{OriginalRoad, RawBuilding, RawIntersection, RawMap, RawRoad};
+use map_model::raw::{
+    ConsolidationHints, OriginalRoad, RawBuilding, RawIntersection, RawMap, RawRoad,
+};
 use map_model::{osm, IntersectionType};
 use widgetry::{Color, EventCtx, Line, Text};
 
@@ -20,6 +22,7 @@ pub struct Model {
     pub map: RawMap,
     showing_pts: Option<OriginalRoad>,
     pub world: World<ID>,
+    pub hints: ConsolidationHints,
 
     include_bldgs: bool,
     pub intersection_geom: bool,
@@ -37,6 +40,7 @@ impl Model {
 
             include_bldgs: false,
             world: World::new(),
+            hints: ConsolidationHints::default(),
             intersection_geom: false,
         }
     }
@@ -51,6 +55,7 @@ impl Model {
         let mut model = Model::blank();
         model.include_bldgs = include_bldgs;
         model.map = abstutil::read_binary(path, &mut timer);
+        model.hints = ConsolidationHints::load(&model.map.name);
         model.intersection_geom = intersection_geom;
 
         if model.include_bldgs {
@@ -206,8 +211,19 @@ impl Model {
             Circle::new(i.point, INTERSECTION_RADIUS).to_polygon()
         };
 
-        self.world
-            .add(ctx, Object::new(ID::Intersection(id), color, poly));
+        // Like map_model, put the intersection at the lowest level of its roads
+        let layer = self
+            .map
+            .roads_per_intersection(id)
+            .into_iter()
+            .map(|r| self.map.roads[&r].get_zorder())
+            .min()
+            .unwrap_or(0);
+
+        self.world.add(
+            ctx,
+            Object::new(ID::Intersection(id), color, poly).layer(layer),
+        );
     }
 
     pub fn create_i(&mut self, point: Pt2D, ctx: &EventCtx) {
@@ -311,6 +327,7 @@ impl Model {
             Color::grey(0.8),
             center.make_polygons(total_width),
         )
+        .layer(self.map.roads[&id].get_zorder())
     }
 
     pub fn show_r_points(&mut self, id: OriginalRoad, ctx: &EventCtx) {
@@ -332,7 +349,8 @@ impl Model {
                         ID::RoadPoint(id, idx),
                         Color::GREEN,
                         Circle::new(*pt, INTERSECTION_RADIUS / 2.0).to_polygon(),
-                    ),
+                    )
+                    .layer(r.get_zorder()),
                 );
             }
         }
@@ -430,6 +448,71 @@ impl Model {
     }
 }
 
+// Consolidation hints
+impl Model {
+    /// Merges two intersections right away, and remembers to do so the next time this map is
+    /// imported.
+    pub fn merge_intersections(&mut self, i1: osm::NodeID, i2: osm::NodeID, ctx: &EventCtx) {
+        let mut old_roads: BTreeSet<OriginalRoad> =
+            self.map.roads_per_intersection(i1).into_iter().collect();
+        old_roads.extend(self.map.roads_per_intersection(i2));
+        if let Some(r) = self.showing_pts {
+            self.stop_showing_pts(r);
+        }
+        let changed = match self.map.merge_intersections(i1, i2) {
+            Ok((_, changed)) => changed,
+            Err(err) => {
+                println!("Can't merge {} and {}: {}", i1, i2, err);
+                return;
+            }
+        };
+
+        for r in old_roads {
+            self.road_deleted(r);
+        }
+        self.world.delete(ID::Intersection(i1));
+        self.world.delete(ID::Intersection(i2));
+        // Only this area changed, so just redo it. The intersections on the far end of the
+        // stretched roads might look different now too.
+        let mut neighbors = BTreeSet::new();
+        for r in changed {
+            self.road_added(r, ctx);
+            neighbors.insert(r.i1);
+            neighbors.insert(r.i2);
+        }
+        neighbors.remove(&i1);
+        for i in neighbors {
+            self.world.delete(ID::Intersection(i));
+            self.intersection_added(i, ctx);
+        }
+        self.intersection_added(i1, ctx);
+
+        self.hints.mark(i1, i2, true);
+        self.save_hints();
+    }
+
+    /// Remembers to never merge the roads between these intersections during import.
+    pub fn keep_intersections_apart(&mut self, i1: osm::NodeID, i2: osm::NodeID) {
+        if self.map.roads_between(i1, i2).is_empty() {
+            println!("No road connects {} and {}", i1, i2);
+            return;
+        }
+        self.hints.mark(i1, i2, false);
+        self.save_hints();
+    }
+
+    fn save_hints(&self) {
+        if self.map.name.map.is_empty() {
+            return;
+        }
+        self.hints.save(&self.map.name);
+        println!(
+            "Saved {}. Re-import the map to use these hints.",
+            abstutil::path_consolidation_hints(&self.map.name)
+        );
+    }
+}
+
 // Buildings
 impl Model {
     fn bldg_added(&mut self, id: osm::OsmID, ctx: &EventCtx) {
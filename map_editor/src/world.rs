@@ -15,6 +15,7 @@ pub trait ObjectID: Clone + Copy + Debug + Eq + Hash {
 pub struct Object<ID: ObjectID> {
     id: ID,
     geometry: Vec<(Color, Polygon)>,
+    layer: isize,
 }
 
 impl<ID: ObjectID> Object<ID> {
@@ -22,12 +23,21 @@ impl<ID: ObjectID> Object<ID> {
         Object {
             id,
             geometry: vec![(color, poly)],
+            layer: 0,
         }
     }
+
+    /// Objects on a higher layer, like bridges, show up in front of everything on lower layers,
+    /// regardless of their ID's zorder.
+    pub fn layer(mut self, layer: isize) -> Object<ID> {
+        self.layer = layer;
+        self
+    }
 }
 
 struct WorldObject {
     unioned_polygon: Polygon,
+    layer: isize,
     draw: Drawable,
     quadtree_id: ItemId,
 }
@@ -63,7 +73,7 @@ impl<ID: ObjectID> World<ID> {
                 objects.push(*id);
             }
         }
-        objects.sort_by_key(|id| id.zorder());
+        objects.sort_by_key(|id| (self.objects[id].layer, id.zorder()));
 
         for id in objects {
             let obj = &self.objects[&id];
@@ -93,7 +103,7 @@ impl<ID: ObjectID> World<ID> {
         ) {
             objects.push(*id);
         }
-        objects.sort_by_key(|id| id.zorder());
+        objects.sort_by_key(|id| (self.objects[id].layer, id.zorder()));
         objects.reverse();
 
         for id in objects {
@@ -131,6 +141,7 @@ impl<ID: ObjectID> World<ID> {
             obj.id,
             WorldObject {
                 unioned_polygon,
+                layer: obj.layer,
                 draw,
                 quadtree_id,
             },
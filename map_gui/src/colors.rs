@@ -1,6 +1,11 @@
 //! A color scheme groups colors used for different map, dynamic, and UI elements in one place, to
 //! encourage deduplication. The player can also switch between different color schemes.
 
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::Timer;
 use map_model::osm::RoadRank;
 use map_model::LaneType;
 use widgetry::{Choice, Color, EventCtx, Fill, Style, Texture};
@@ -32,6 +37,8 @@ pub enum ColorSchemeChoice {
     MapboxDark,
     FadedZoom,
     NegativeSpace,
+    HighContrast,
+    ColorblindSafe,
 }
 
 impl ColorSchemeChoice {
@@ -49,6 +56,8 @@ impl ColorSchemeChoice {
             Choice::new("mapbox dark", ColorSchemeChoice::MapboxDark),
             Choice::new("faded zoom", ColorSchemeChoice::FadedZoom),
             Choice::new("negative space", ColorSchemeChoice::NegativeSpace),
+            Choice::new("high contrast", ColorSchemeChoice::HighContrast),
+            Choice::new("colorblind safe", ColorSchemeChoice::ColorblindSafe),
         ]
     }
 }
@@ -118,11 +127,13 @@ pub struct ColorScheme {
     pub grass: Fill,
     pub water: Fill,
     pub median_strip: Fill,
+    pub pedestrian_plaza: Fill,
 
     // Unzoomed dynamic elements
     pub unzoomed_car: Color,
     pub unzoomed_bike: Color,
     pub unzoomed_bus: Color,
+    pub unzoomed_ride_hail: Color,
     pub unzoomed_pedestrian: Color,
 
     // Agents
@@ -133,6 +144,7 @@ pub struct ColorScheme {
     pub bus_body: Color,
     pub bus_label: Color,
     pub train_body: Color,
+    pub emergency_vehicle_body: Color,
     pub ped_head: Color,
     pub ped_foot: Color,
     pub ped_preparing_bike_body: Color,
@@ -154,6 +166,38 @@ pub struct ColorScheme {
     pub after_changes: Color,
 }
 
+/// Overrides some colors of whatever scheme is chosen. This is read from `data/player/theme.json`
+/// every time the color scheme is created, so people can tune colors for a presentation or their
+/// own eyes without recompiling.
+#[derive(Serialize, Deserialize)]
+pub struct Theme {
+    /// Keyed by the name of a field in `ColorScheme`, like `bike_lane` or `unzoomed_car`. Values
+    /// are hex colors like `#FF0000`, with an optional alpha channel like `#FF000080`.
+    #[serde(default)]
+    pub colors: BTreeMap<String, String>,
+    /// Colors that come in a list: `agent_colors`, `good_to_bad_red`, and `good_to_bad_green`.
+    #[serde(default)]
+    pub color_lists: BTreeMap<String, Vec<String>>,
+}
+
+impl Theme {
+    /// Returns None if there's no theme file. Mistakes in the file are printed and skipped, so
+    /// the player isn't left without any colors at all.
+    pub fn load() -> Option<Theme> {
+        let path = abstutil::path_player("theme.json");
+        if !abstutil::file_exists(&path) {
+            return None;
+        }
+        match abstutil::maybe_read_json::<Theme>(path.clone(), &mut Timer::throwaway()) {
+            Ok(theme) => Some(theme),
+            Err(err) => {
+                println!("Ignoring {}: {}", path, err);
+                None
+            }
+        }
+    }
+}
+
 impl ColorScheme {
     pub fn new(ctx: &mut EventCtx, scheme: ColorSchemeChoice) -> ColorScheme {
         let mut cs = match scheme {
@@ -169,8 +213,13 @@ impl ColorScheme {
             ColorSchemeChoice::MapboxDark => ColorScheme::mapbox_dark(),
             ColorSchemeChoice::FadedZoom => ColorScheme::faded_zoom(),
             ColorSchemeChoice::NegativeSpace => ColorScheme::negative_space(),
+            ColorSchemeChoice::HighContrast => ColorScheme::high_contrast(),
+            ColorSchemeChoice::ColorblindSafe => ColorScheme::colorblind_safe(),
         };
         cs.scheme = scheme;
+        if let Some(theme) = Theme::load() {
+            cs.apply_theme(theme);
+        }
         ctx.set_style(cs.gui_style.clone());
         cs
     }
@@ -243,11 +292,13 @@ impl ColorScheme {
             grass: hex("#94C84A").into(),
             water: Color::rgb(164, 200, 234).into(),
             median_strip: Color::CYAN.into(),
+            pedestrian_plaza: Color::grey(0.8).into(),
 
             // Unzoomed dynamic elements
             unzoomed_car: hex("#A32015"),
             unzoomed_bike: hex("#5D9630"),
             unzoomed_bus: hex("#12409D"),
+            unzoomed_ride_hail: hex("#9B3FB5"),
             unzoomed_pedestrian: hex("#DF8C3D"),
 
             // Agents
@@ -264,6 +315,7 @@ impl ColorScheme {
             bus_body: Color::rgb(50, 133, 117),
             bus_label: Color::rgb(249, 206, 24),
             train_body: hex("#42B6E9"),
+            emergency_vehicle_body: hex("#E8E8E8"),
             ped_head: Color::rgb(139, 69, 19),
             ped_foot: Color::BLACK,
             ped_preparing_bike_body: Color::rgb(255, 0, 144),
@@ -318,7 +370,7 @@ impl ColorScheme {
     pub fn zoomed_road_surface(&self, lane: LaneType, rank: RoadRank) -> Color {
         match self.scheme {
             ColorSchemeChoice::FadedZoom => match lane {
-                LaneType::Sidewalk | LaneType::Shoulder => match rank {
+                LaneType::Sidewalk | LaneType::Shoulder | LaneType::Footway => match rank {
                     RoadRank::Highway | RoadRank::Arterial => hex("#F2F2F2"),
                     RoadRank::Local => hex("#DBDDE5"),
                 },
@@ -333,7 +385,7 @@ impl ColorScheme {
                 LaneType::Driving => self.driving_lane,
                 LaneType::Bus => self.bus_lane,
                 LaneType::Parking => self.parking_lane,
-                LaneType::Sidewalk | LaneType::Shoulder => self.sidewalk,
+                LaneType::Sidewalk | LaneType::Shoulder | LaneType::Footway => self.sidewalk,
                 LaneType::Biking => self.bike_lane,
                 LaneType::SharedLeftTurn => self.driving_lane,
                 LaneType::Construction => self.parking_lane,
@@ -375,6 +427,175 @@ impl ColorScheme {
     }
 }
 
+// Overrides from a theme file
+impl ColorScheme {
+    fn apply_theme(&mut self, theme: Theme) {
+        for (name, value) in theme.colors {
+            match parse_color(&value) {
+                Ok(color) => {
+                    if !self.set_color(&name, color) {
+                        println!("Theme has an unknown color {}", name);
+                    }
+                }
+                Err(err) => println!("Theme has a bad color for {}: {}", name, err),
+            }
+        }
+        for (name, values) in theme.color_lists {
+            let colors = match values
+                .iter()
+                .map(|x| parse_color(x))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(colors) => colors,
+                Err(err) => {
+                    println!("Theme has a bad color for {}: {}", name, err);
+                    continue;
+                }
+            };
+            match name.as_ref() {
+                "agent_colors" if !values.is_empty() => self.agent_colors = colors,
+                "good_to_bad_red" if values.len() >= 2 => self.good_to_bad_red = ColorScale(colors),
+                "good_to_bad_green" if values.len() >= 2 => {
+                    self.good_to_bad_green = ColorScale(colors)
+                }
+                _ => println!("Theme has an unknown or too short color list {}", name),
+            }
+        }
+
+        // Panels are drawn using the style, not these fields directly
+        self.gui_style.hovering_color = self.hovering;
+        self.gui_style.panel_bg = self.panel_bg;
+    }
+
+    /// Returns false if there's no color with this name.
+    fn set_color(&mut self, name: &str, color: Color) -> bool {
+        match name {
+            // UI
+            "hovering" => self.hovering = color,
+            "panel_bg" => self.panel_bg = color,
+            "section_bg" => self.section_bg = color,
+            "inner_panel" => self.inner_panel = color,
+            "day_time_slider" => self.day_time_slider = color,
+            "night_time_slider" => self.night_time_slider = color,
+            "selected" => self.selected = color,
+            "current_object" => self.current_object = color,
+            "perma_selected_object" => self.perma_selected_object = color,
+            "bottom_bar_id" => self.bottom_bar_id = color,
+            "bottom_bar_name" => self.bottom_bar_name = color,
+            "fade_map_dark" => self.fade_map_dark = color,
+            "dialog_bg" => self.dialog_bg = color,
+            "minimap_cursor_border" => self.minimap_cursor_border = color,
+            "minimap_cursor_bg" => self.minimap_cursor_bg = Some(color),
+            "minimap_selected_zoom" => self.minimap_selected_zoom = color,
+            "minimap_unselected_zoom" => self.minimap_unselected_zoom = color,
+
+            // Roads
+            "driving_lane" => self.driving_lane = color,
+            "bus_lane" => self.bus_lane = color,
+            "parking_lane" => self.parking_lane = color,
+            "bike_lane" => self.bike_lane = color,
+            "sidewalk" => self.sidewalk = color,
+            "sidewalk_lines" => self.sidewalk_lines = Some(color),
+            "general_road_marking" => self.general_road_marking = color,
+            "road_center_line" => self.road_center_line = color,
+            "light_rail_track" => self.light_rail_track = color,
+            "private_road" => self.private_road = color,
+            "unzoomed_highway" => self.unzoomed_highway = color,
+            "unzoomed_arterial" => self.unzoomed_arterial = color,
+            "unzoomed_residential" => self.unzoomed_residential = color,
+
+            // Intersections
+            "normal_intersection" => self.normal_intersection = color,
+            "stop_sign" => self.stop_sign = color,
+            "stop_sign_pole" => self.stop_sign_pole = color,
+            "signal_protected_turn" => self.signal_protected_turn = color,
+            "signal_permitted_turn" => self.signal_permitted_turn = color,
+            "signal_banned_turn" => self.signal_banned_turn = color,
+            "signal_box" => self.signal_box = color,
+            "signal_spinner" => self.signal_spinner = color,
+            "signal_turn_block_bg" => self.signal_turn_block_bg = color,
+
+            // Timeline delay highlighting
+            "very_slow_intersection" => self.very_slow_intersection = color,
+            "slow_intersection" => self.slow_intersection = color,
+            "normal_slow_intersection" => self.normal_slow_intersection = color,
+
+            // Other static elements
+            "void_background" => self.void_background = color,
+            "map_background" => self.map_background = color.into(),
+            "unzoomed_interesting_intersection" => self.unzoomed_interesting_intersection = color,
+            "residential_building" => self.residential_building = color,
+            "commercial_building" => self.commercial_building = color,
+            "building_outline" => self.building_outline = color,
+            "parking_lot" => self.parking_lot = color,
+            "grass" => self.grass = color.into(),
+            "water" => self.water = color.into(),
+            "median_strip" => self.median_strip = color.into(),
+            "pedestrian_plaza" => self.pedestrian_plaza = color.into(),
+
+            // Unzoomed dynamic elements
+            "unzoomed_car" => self.unzoomed_car = color,
+            "unzoomed_bike" => self.unzoomed_bike = color,
+            "unzoomed_bus" => self.unzoomed_bus = color,
+            "unzoomed_ride_hail" => self.unzoomed_ride_hail = color,
+            "unzoomed_pedestrian" => self.unzoomed_pedestrian = color,
+
+            // Agents
+            "route" => self.route = color,
+            "turn_arrow" => self.turn_arrow = color,
+            "brake_light" => self.brake_light = color,
+            "bus_body" => self.bus_body = color,
+            "bus_label" => self.bus_label = color,
+            "train_body" => self.train_body = color,
+            "emergency_vehicle_body" => self.emergency_vehicle_body = color,
+            "ped_head" => self.ped_head = color,
+            "ped_foot" => self.ped_foot = color,
+            "ped_preparing_bike_body" => self.ped_preparing_bike_body = color,
+            "ped_crowd" => self.ped_crowd = color,
+            "bike_frame" => self.bike_frame = color,
+            "parked_car" => self.parked_car = color,
+
+            // Layers
+            "bus_layer" => self.bus_layer = color,
+            "edits_layer" => self.edits_layer = color,
+
+            // Misc
+            "parking_trip" => self.parking_trip = color,
+            "bike_trip" => self.bike_trip = color,
+            "bus_trip" => self.bus_trip = color,
+            "before_changes" => self.before_changes = color,
+            "after_changes" => self.after_changes = color,
+
+            _ => {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Parses `#RRGGBB` or `#RRGGBBAA`.
+fn parse_color(x: &str) -> Result<Color, String> {
+    if !x.starts_with('#') || !(x.len() == 7 || x.len() == 9) {
+        return Err(format!("{} isn't #RRGGBB or #RRGGBBAA", x));
+    }
+    let channel = |i: usize| {
+        x.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .ok_or_else(|| format!("{} isn't a hex color", x))
+    };
+    let color = Color::rgb(
+        channel(1)? as usize,
+        channel(3)? as usize,
+        channel(5)? as usize,
+    );
+    if x.len() == 9 {
+        Ok(color.alpha(channel(7)? as f32 / 255.0))
+    } else {
+        Ok(color)
+    }
+}
+
 fn modulo_color(colors: &Vec<Color>, idx: usize) -> Color {
     colors[idx % colors.len()]
 }
@@ -403,6 +624,7 @@ impl ColorScheme {
         cs.road_center_line = cs.general_road_marking;
 
         cs.parking_lot = cs.sidewalk;
+        cs.pedestrian_plaza = cs.sidewalk.into();
         cs.unzoomed_arterial = cs.sidewalk;
         cs.unzoomed_highway = cs.parking_lane;
         cs.unzoomed_residential = cs.driving_lane;
@@ -577,3 +799,105 @@ impl ColorScheme {
         cs
     }
 }
+
+// Schemes for accessibility and projectors
+impl ColorScheme {
+    // Bright things on a black background, with every lane type and agent clearly apart
+    fn high_contrast() -> ColorScheme {
+        let mut cs = ColorScheme::standard();
+        cs.void_background = Color::BLACK;
+        cs.map_background = Color::BLACK.into();
+        cs.grass = hex("#0B3D0B").into();
+        cs.water = hex("#0A2A6B").into();
+        cs.residential_building = Color::grey(0.3);
+        cs.commercial_building = Color::grey(0.45);
+        cs.building_outline = Color::WHITE;
+        cs.parking_lot = Color::grey(0.2);
+        cs.pedestrian_plaza = Color::grey(0.2).into();
+
+        cs.driving_lane = Color::grey(0.15);
+        cs.parking_lane = Color::grey(0.3);
+        cs.sidewalk = Color::grey(0.75);
+        cs.sidewalk_lines = Some(Color::BLACK);
+        cs.bike_lane = hex("#00C853");
+        cs.bus_lane = hex("#FF1744");
+        cs.general_road_marking = Color::WHITE;
+        cs.road_center_line = hex("#FFEA00");
+        cs.normal_intersection = cs.driving_lane;
+        cs.unzoomed_highway = hex("#FF9100");
+        cs.unzoomed_arterial = hex("#FFEA00");
+        cs.unzoomed_residential = Color::WHITE;
+        cs.unzoomed_interesting_intersection = Color::WHITE;
+
+        cs.unzoomed_car = hex("#FF1744");
+        cs.unzoomed_bike = hex("#00E676");
+        cs.unzoomed_bus = hex("#2979FF");
+        cs.unzoomed_ride_hail = hex("#E040FB");
+        cs.unzoomed_pedestrian = hex("#FFEA00");
+        cs.agent_colors = vec![
+            hex("#FFEA00"),
+            hex("#00E5FF"),
+            hex("#FF4081"),
+            hex("#FFFFFF"),
+            hex("#FF9100"),
+        ];
+        cs.route = hex("#00E5FF").alpha(0.7);
+        cs.selected = hex("#00E5FF").alpha(0.8);
+        cs.current_object = hex("#FFEA00");
+        cs.perma_selected_object = hex("#00E5FF");
+
+        cs.panel_bg = Color::BLACK.alpha(0.95);
+        cs.gui_style.panel_bg = cs.panel_bg;
+        cs.inner_panel = Color::grey(0.15);
+        cs.section_bg = Color::grey(0.25);
+        cs.minimap_cursor_border = Color::WHITE;
+        cs.minimap_selected_zoom = hex("#FFEA00");
+        cs.minimap_unselected_zoom = Color::WHITE.alpha(0.4);
+        cs
+    }
+
+    // Uses the Okabe-Ito palette, which stays distinguishable with the common forms of color
+    // blindness. Nothing relies on telling red from green.
+    fn colorblind_safe() -> ColorScheme {
+        let orange = hex("#E69F00");
+        let sky_blue = hex("#56B4E9");
+        let bluish_green = hex("#009E73");
+        let yellow = hex("#F0E442");
+        let blue = hex("#0072B2");
+        let vermillion = hex("#D55E00");
+        let reddish_purple = hex("#CC79A7");
+
+        let mut cs = ColorScheme::standard();
+        cs.bike_lane = blue;
+        cs.bus_lane = vermillion;
+        cs.bike_trip = blue;
+        cs.bus_trip = vermillion;
+        cs.unzoomed_highway = orange;
+        cs.unzoomed_arterial = yellow;
+
+        cs.signal_protected_turn = bluish_green;
+        cs.signal_permitted_turn = sky_blue;
+        cs.very_slow_intersection = vermillion;
+        cs.slow_intersection = yellow;
+        cs.normal_slow_intersection = blue;
+
+        cs.unzoomed_car = vermillion;
+        cs.unzoomed_bike = blue;
+        cs.unzoomed_bus = bluish_green;
+        cs.unzoomed_ride_hail = reddish_purple;
+        cs.unzoomed_pedestrian = orange;
+        cs.agent_colors = vec![orange, sky_blue, bluish_green, yellow, blue, reddish_purple];
+        cs.bus_body = bluish_green;
+        cs.parking_trip = reddish_purple;
+
+        cs.good_to_bad_red = ColorScale(vec![hex("#FDD0A2"), vermillion]);
+        cs.good_to_bad_green = ColorScale(vec![hex("#C6DBEF"), blue]);
+        cs.bus_layer = sky_blue;
+        cs.edits_layer = blue;
+        cs.before_changes = blue;
+        cs.after_changes = orange;
+        cs.selected = orange.alpha(0.7);
+        cs.perma_selected_object = blue;
+        cs
+    }
+}
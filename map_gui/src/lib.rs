@@ -59,13 +59,17 @@ pub trait AppLike {
             return false;
         }
         self.mut_opts().color_scheme = cs;
+        self.reload_color_scheme(ctx);
+        true
+    }
+
+    /// Recreate the current color scheme, picking up any changes to the theme file.
+    fn reload_color_scheme(&mut self, ctx: &mut EventCtx) {
         *self.mut_cs() = ColorScheme::new(ctx, self.opts().color_scheme);
 
         ctx.loading_screen("rerendering map colors", |ctx, timer| {
             *self.mut_draw_map() = DrawMap::new(ctx, self.map(), self.opts(), self.cs(), timer);
         });
-
-        true
     }
 }
 
@@ -3,7 +3,7 @@
 
 use serde::de::DeserializeOwned;
 
-use abstutil::{MapName, Timer};
+use abstutil::{MapName, Timer, Versioned};
 use widgetry::{Color, EventCtx, GfxCtx, State, Transition};
 
 use crate::tools::PopupMsg;
@@ -31,7 +31,7 @@ impl MapLoader {
         }
 
         // TODO If we want to load montlake on the web, just pull from bundled data.
-        FileLoader::<A, map_model::Map>::new(
+        FileLoader::<A, map_model::Map>::new_versioned(
             ctx,
             name.path(),
             Box::new(move |ctx, app, timer, map| {
@@ -71,6 +71,7 @@ mod native_loader {
 
     pub struct FileLoader<A: AppLike, T> {
         path: String,
+        read: fn(String, &mut Timer) -> Result<T, String>,
         // Wrapped in an Option just to make calling from event() work. Technically this is unsafe
         // if a caller fails to pop the FileLoader state in their transitions!
         on_load: Option<
@@ -88,6 +89,24 @@ mod native_loader {
         ) -> Box<dyn State<A>> {
             Box::new(FileLoader {
                 path,
+                read: abstutil::read_object,
+                on_load: Some(on_load),
+            })
+        }
+    }
+
+    impl<A: AppLike + 'static, T: 'static + Versioned> FileLoader<A, T> {
+        /// Like `new`, but for binary files with a version header
+        pub fn new_versioned(
+            _: &mut EventCtx,
+            path: String,
+            on_load: Box<
+                dyn FnOnce(&mut EventCtx, &mut A, &mut Timer, Result<T, String>) -> Transition<A>,
+            >,
+        ) -> Box<dyn State<A>> {
+            Box::new(FileLoader {
+                path,
+                read: abstutil::maybe_read_versioned_binary,
                 on_load: Some(on_load),
             })
         }
@@ -96,7 +115,7 @@ mod native_loader {
     impl<A: AppLike + 'static, T: 'static + DeserializeOwned> State<A> for FileLoader<A, T> {
         fn event(&mut self, ctx: &mut EventCtx, app: &mut A) -> Transition<A> {
             ctx.loading_screen(format!("load {}", self.path), |ctx, timer| {
-                let file = abstutil::read_object(self.path.clone(), timer);
+                let file = (self.read)(self.path.clone(), timer);
                 (self.on_load.take().unwrap())(ctx, app, timer, file)
             })
         }
@@ -125,6 +144,7 @@ mod wasm_loader {
     // compatible with winit's event loop.
     pub struct FileLoader<A: AppLike, T> {
         response: oneshot::Receiver<Result<Vec<u8>, String>>,
+        parse: fn(&str, Vec<u8>) -> Result<T, String>,
         on_load: Option<
             Box<dyn FnOnce(&mut EventCtx, &mut A, &mut Timer, Result<T, String>) -> Transition<A>>,
         >,
@@ -140,6 +160,17 @@ mod wasm_loader {
             on_load: Box<
                 dyn FnOnce(&mut EventCtx, &mut A, &mut Timer, Result<T, String>) -> Transition<A>,
             >,
+        ) -> Box<dyn State<A>> {
+            FileLoader::new_with_parser(ctx, path, parse_object, on_load)
+        }
+
+        fn new_with_parser(
+            ctx: &mut EventCtx,
+            path: String,
+            parse: fn(&str, Vec<u8>) -> Result<T, String>,
+            on_load: Box<
+                dyn FnOnce(&mut EventCtx, &mut A, &mut Timer, Result<T, String>) -> Transition<A>,
+            >,
         ) -> Box<dyn State<A>> {
             // Note that files are only gzipepd on S3. When running locally, we just symlink the
             // data/ directory, where files aren't compressed.
@@ -189,6 +220,7 @@ mod wasm_loader {
 
             Box::new(FileLoader {
                 response: rx,
+                parse,
                 on_load: Some(on_load),
                 panel: ctx.make_loading_screen(Text::from(Line(format!("Loading {}...", url)))),
                 started: Instant::now(),
@@ -204,20 +236,7 @@ mod wasm_loader {
                 // while. Any way to make it still be nonblockingish? Maybe put some of the work
                 // inside that spawn_local?
                 let mut timer = Timer::new(format!("Loading {}...", self.url));
-                let result = maybe_resp.and_then(|resp| {
-                    if self.url.ends_with(".gz") {
-                        let decoder = flate2::read::GzDecoder::new(&resp[..]);
-                        if self.url.ends_with(".bin.gz") {
-                            abstutil::from_binary_reader(decoder)
-                        } else {
-                            abstutil::from_json_reader(decoder)
-                        }
-                    } else if self.url.ends_with(".bin") {
-                        abstutil::from_binary(&&resp)
-                    } else {
-                        abstutil::from_json(&&resp)
-                    }
-                });
+                let result = maybe_resp.and_then(|resp| (self.parse)(&self.url, resp));
                 return (self.on_load.take().unwrap())(ctx, app, &mut timer, result);
             }
 
@@ -241,4 +260,40 @@ mod wasm_loader {
             self.panel.draw(g);
         }
     }
+
+    impl<A: AppLike + 'static, T: 'static + Versioned> FileLoader<A, T> {
+        /// Like `new`, but for binary files with a version header
+        pub fn new_versioned(
+            ctx: &mut EventCtx,
+            path: String,
+            on_load: Box<
+                dyn FnOnce(&mut EventCtx, &mut A, &mut Timer, Result<T, String>) -> Transition<A>,
+            >,
+        ) -> Box<dyn State<A>> {
+            FileLoader::new_with_parser(ctx, path, parse_versioned, on_load)
+        }
+    }
+
+    fn parse_object<T: DeserializeOwned>(url: &str, resp: Vec<u8>) -> Result<T, String> {
+        if url.ends_with(".gz") {
+            let decoder = flate2::read::GzDecoder::new(&resp[..]);
+            if url.ends_with(".bin.gz") {
+                abstutil::from_binary_reader(decoder)
+            } else {
+                abstutil::from_json_reader(decoder)
+            }
+        } else if url.ends_with(".bin") {
+            abstutil::from_binary(&resp)
+        } else {
+            abstutil::from_json(&resp)
+        }
+    }
+
+    fn parse_versioned<T: Versioned>(url: &str, resp: Vec<u8>) -> Result<T, String> {
+        if url.ends_with(".gz") {
+            abstutil::from_versioned_binary_reader(flate2::read::GzDecoder::new(&resp[..]))
+        } else {
+            abstutil::from_versioned_binary(&resp)
+        }
+    }
 }
@@ -256,6 +256,7 @@ impl OptionsPanel {
                         None,
                         app.opts().debug_all_agents,
                     ),
+                    Btn::text_fg("Reload color theme").build_def(ctx, None),
                 ])
                 .bg(app.cs().section_bg)
                 .padding(8),
@@ -275,6 +276,10 @@ impl<A: AppLike> State<A> for OptionsPanel {
                 "close" => {
                     return widgetry::Transition::Pop;
                 }
+                "Reload color theme" => {
+                    app.reload_color_scheme(ctx);
+                    return widgetry::Transition::Pop;
+                }
                 "Apply" => {
                     let mut opts = app.opts().clone();
                     opts.dev = self.panel.is_checked("Enable developer mode");
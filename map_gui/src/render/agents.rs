@@ -176,7 +176,7 @@ impl UnzoomedAgents {
 
     fn color(&self, agent: &UnzoomedAgent) -> Option<Color> {
         match agent.id.to_vehicle_type() {
-            Some(VehicleType::Car) => {
+            Some(VehicleType::Car) | Some(VehicleType::Truck) | Some(VehicleType::Emergency) => {
                 if self.cars {
                     Some(self.car_color)
                 } else {
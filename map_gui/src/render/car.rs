@@ -110,7 +110,7 @@ impl DrawCar {
 
             if let Some(t) = input.waiting_for_turn {
                 match map.get_t(t).turn_type {
-                    TurnType::Left => {
+                    TurnType::Left | TurnType::UTurn => {
                         let (pos, angle) = input
                             .body
                             .must_dist_along(input.body.length() - Distance::meters(2.5));
@@ -237,6 +237,8 @@ fn zoomed_color_car(input: &DrawCarInput, cs: &ColorScheme) -> Color {
         cs.bus_body
     } else if input.id.1 == VehicleType::Train {
         cs.train_body
+    } else if input.id.1 == VehicleType::Emergency {
+        cs.emergency_vehicle_body
     } else {
         match input.status {
             CarStatus::Moving => cs.rotating_color_agents(input.id.0),
@@ -47,7 +47,7 @@ impl DrawLane {
         }
         let general_road_marking = app.cs().general_road_marking(road.get_rank());
         match lane.lane_type {
-            LaneType::Sidewalk => {
+            LaneType::Sidewalk | LaneType::Footway => {
                 if let Some(c) = app.cs().sidewalk_lines {
                     draw.extend(c, calculate_sidewalk_lines(lane));
                 } else {
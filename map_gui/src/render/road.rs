@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 
-use geom::{Distance, Polygon, Pt2D};
+use geom::{Circle, Distance, Polygon, Pt2D};
 use map_model::{LaneType, Map, Road, RoadID};
 use widgetry::{Drawable, GeomBatch, GfxCtx, Line, Text};
 
@@ -75,6 +75,30 @@ impl Renderable for DrawRoad {
                     }
                 }
             }
+            if r.modal_filter {
+                // Draw a row of bollards across the middle of the road
+                let road_width: Distance = r
+                    .lanes_ltr()
+                    .into_iter()
+                    .map(|(l, _, _)| app.map().get_l(l).width)
+                    .sum();
+                let (pt, angle) = r.center_pts.must_dist_along(r.center_pts.length() / 2.0);
+                let start = pt.project_away(road_width / 2.0, angle.rotate_degs(-90.0));
+                let num_bollards = (road_width / Distance::meters(1.5)).floor() as usize;
+                for idx in 0..=num_bollards {
+                    batch.push(
+                        center_color,
+                        Circle::new(
+                            start.project_away(
+                                road_width * (idx as f64) / (num_bollards as f64).max(1.0),
+                                angle.rotate_degs(90.0),
+                            ),
+                            Distance::meters(0.4),
+                        )
+                        .to_polygon(),
+                    );
+                }
+            }
             *draw_center_line = Some(g.prerender.upload(batch));
         }
         g.redraw(draw_center_line.as_ref().unwrap());
@@ -1,5 +1,5 @@
 use geom::{ArrowCap, Distance};
-use map_model::{LaneID, TurnType};
+use map_model::{ControlTrafficSignal, LaneID, Turn, TurnPriority, TurnType};
 use widgetry::{
     Btn, Color, DrawBaselayer, EventCtx, GeomBatch, GfxCtx, HorizontalAlignment, Key, Line,
     Outcome, Panel, State, Text, TextExt, Transition, VerticalAlignment, Widget,
@@ -162,6 +162,11 @@ impl TurnExplorer {
                     TurnExplorer::color_turn_type(TurnType::Left),
                     "left turn",
                 ));
+                col.push(ColorLegend::row(
+                    ctx,
+                    TurnExplorer::color_turn_type(TurnType::UTurn),
+                    "U-turn",
+                ));
             }
         } else {
             let (lt, lc, slow_lane) = turns[idx - 1].penalty(app.map());
@@ -176,6 +181,9 @@ impl TurnExplorer {
                 )
                 .draw_text(ctx),
             );
+            if let Some(signal) = app.map().maybe_get_traffic_signal(turns[idx - 1].id.parent) {
+                col.push(TurnExplorer::describe_stages(ctx, signal, turns[idx - 1]));
+            }
             col.push(ColorLegend::row(ctx, CURRENT_TURN, "current turn"));
             col.push(ColorLegend::row(ctx, CONFLICTING_TURN, "conflicting turn"));
         }
@@ -185,6 +193,30 @@ impl TurnExplorer {
             .build(ctx)
     }
 
+    /// Lists the traffic signal stages that allow a turn, to help debug signal timing.
+    fn describe_stages(ctx: &EventCtx, signal: &ControlTrafficSignal, turn: &Turn) -> Widget {
+        // Sidewalk corners never conflict with anything, so signals don't manage them
+        if turn.turn_type == TurnType::SharedSidewalkCorner {
+            return "Always allowed by the traffic signal".draw_text(ctx);
+        }
+        let m = signal.turn_to_movement(turn.id);
+        let stages: Vec<String> = signal
+            .stages
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, stage)| match stage.get_priority_of_movement(m) {
+                TurnPriority::Protected => Some(format!("stage {} (protected)", idx + 1)),
+                TurnPriority::Yield => Some(format!("stage {} (yield)", idx + 1)),
+                TurnPriority::Banned => None,
+            })
+            .collect();
+        if stages.is_empty() {
+            "Not allowed during any stage of the traffic signal".draw_text(ctx)
+        } else {
+            format!("Allowed during {}", stages.join(", ")).draw_text(ctx)
+        }
+    }
+
     // Since this is extremely localized and probably changing, not going to put this in
     // ColorScheme.
     pub fn color_turn_type(t: TurnType) -> Color {
@@ -194,6 +226,7 @@ impl TurnExplorer {
             TurnType::Straight => Color::BLUE,
             TurnType::Right => Color::GREEN,
             TurnType::Left => Color::RED,
+            TurnType::UTurn => Color::PURPLE,
         }
     }
 }
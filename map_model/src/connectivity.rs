@@ -7,9 +7,10 @@ use petgraph::graphmap::DiGraphMap;
 use geom::{Distance, Duration, Speed};
 
 pub use crate::pathfind::{
-    build_graph_for_pedestrians, build_graph_for_vehicles, driving_cost, WalkingNode,
+    build_graph_for_pedestrians, build_graph_for_vehicles, build_graph_for_wheelchairs,
+    driving_cost, walking_cost, WalkingNode,
 };
-use crate::{BuildingID, LaneID, Map, PathConstraints};
+use crate::{BuildingID, LaneID, Map, PathConstraints, PathRequest};
 
 /// Calculate the srongy connected components (SCC) of the part of the map accessible by constraints
 /// (ie, the graph of sidewalks or driving+bike lanes). The largest component is the "main" graph;
@@ -48,6 +49,32 @@ pub fn find_scc(map: &Map, constraints: PathConstraints) -> (HashSet<LaneID>, Ha
     (largest_group, disconnected)
 }
 
+/// Finds sidewalks and footways that are usable in a wheelchair by themselves, but cut off from
+/// the main network by inaccessible links around them.
+pub fn find_wheelchair_gaps(map: &Map) -> HashSet<LaneID> {
+    let graph = build_graph_for_wheelchairs(map);
+    let components = petgraph::algo::kosaraju_scc(&graph);
+    let largest_group: HashSet<WalkingNode> = components
+        .into_iter()
+        .max_by_key(|c| c.len())
+        .unwrap_or_else(Vec::new)
+        .into_iter()
+        .collect();
+    map.all_lanes()
+        .iter()
+        .filter_map(|l| {
+            if l.is_walkable()
+                && l.wheelchair_barrier(map).is_none()
+                && !largest_group.contains(&WalkingNode::SidewalkEndpoint(l.id, true))
+            {
+                Some(l.id)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
 /// Starting from one building, calculate the cost to all others. If a destination isn't reachable,
 /// it won't be included in the results. Ignore results greater than the time_limit away.
 pub fn all_costs_from(
@@ -59,21 +86,7 @@ pub fn all_costs_from(
     let mut results = HashMap::new();
 
     if constraints == PathConstraints::Pedestrian {
-        let graph = build_graph_for_pedestrians(map);
-        let start = WalkingNode::closest(map.get_b(start).sidewalk_pos, map);
-        let cost_per_node = petgraph::algo::dijkstra(&graph, start, None, |(_, _, cost)| *cost);
-
-        // Assign every building a cost based on which end of the sidewalk it's closest to
-        // TODO We could try to get a little more accurate by accounting for the distance from that
-        // end of the sidewalk to the building
-        for b in map.all_buildings() {
-            if let Some(seconds) = cost_per_node.get(&WalkingNode::closest(b.sidewalk_pos, map)) {
-                let duration = Duration::seconds(*seconds as f64);
-                if duration <= time_limit {
-                    results.insert(b.id, duration);
-                }
-            }
-        }
+        return all_walking_costs_from(map, start, time_limit, build_graph_for_pedestrians(map));
     } else {
         // TODO We have a graph of LaneIDs, but mapping a building to one isn't straightforward. In
         // the common case it'll be fine, but some buildings are isolated from the graph by some
@@ -101,9 +114,14 @@ pub fn all_costs_from(
                     driving_cost(map.get_l(turn.src), map.get_t(*turn), constraints, map)
                 });
             for (b, lane) in bldg_to_lane {
-                if let Some(meters) = cost_per_lane.get(&lane) {
-                    let distance = Distance::meters(*meters as f64);
-                    let duration = distance / max_bike_speed;
+                if let Some(cost) = cost_per_lane.get(&lane) {
+                    // The cost for biking is a distance, but for driving, it's already the time to
+                    // cross each road at its speed limit.
+                    let duration = if constraints == PathConstraints::Bike {
+                        Distance::meters(*cost as f64) / max_bike_speed
+                    } else {
+                        Duration::seconds(*cost as f64)
+                    };
                     if duration <= time_limit {
                         results.insert(b, duration);
                     }
@@ -114,3 +132,75 @@ pub fn all_costs_from(
 
     results
 }
+
+/// Like `all_costs_from` for pedestrians, but they can also ride buses and trains. Nobody follows
+/// a schedule here, so boarding costs a fixed wait and riding costs the time to travel between
+/// stops at a typical transit speed, including stops along the way. Transferring at the same stop
+/// is free. This is optimistic for infrequent routes.
+pub fn all_transit_costs_from(
+    map: &Map,
+    start: BuildingID,
+    time_limit: Duration,
+) -> HashMap<BuildingID, Duration> {
+    let wait_for_transit = Duration::minutes(5).inner_seconds() as usize;
+    let transit_speed = Speed::miles_per_hour(12.0);
+
+    let mut graph = build_graph_for_pedestrians(map);
+    for stop in map.all_bus_stops().values() {
+        let ride = WalkingNode::RideBus(stop.id);
+        let lane = map.get_l(stop.sidewalk_pos.lane());
+        for endpt in vec![true, false] {
+            let cost = if endpt {
+                walking_cost(lane.length() - stop.sidewalk_pos.dist_along())
+            } else {
+                walking_cost(stop.sidewalk_pos.dist_along())
+            };
+            let sidewalk = WalkingNode::SidewalkEndpoint(lane.id, endpt);
+            graph.add_edge(sidewalk, ride, cost + wait_for_transit);
+            graph.add_edge(ride, sidewalk, cost);
+        }
+    }
+    for route in map.all_bus_routes() {
+        for pair in route.stops.windows(2) {
+            let (stop1, stop2) = (map.get_bs(pair[0]), map.get_bs(pair[1]));
+            if let Some(path) = map.pathfind(PathRequest {
+                start: stop1.driving_pos,
+                end: stop2.driving_pos,
+                constraints: route.route_type,
+            }) {
+                let duration = path.total_length() / transit_speed;
+                graph.add_edge(
+                    WalkingNode::RideBus(stop1.id),
+                    WalkingNode::RideBus(stop2.id),
+                    (duration.inner_seconds().round() as usize).max(1),
+                );
+            }
+        }
+    }
+
+    all_walking_costs_from(map, start, time_limit, graph)
+}
+
+fn all_walking_costs_from(
+    map: &Map,
+    start: BuildingID,
+    time_limit: Duration,
+    graph: DiGraphMap<WalkingNode, usize>,
+) -> HashMap<BuildingID, Duration> {
+    let mut results = HashMap::new();
+    let start = WalkingNode::closest(map.get_b(start).sidewalk_pos, map);
+    let cost_per_node = petgraph::algo::dijkstra(&graph, start, None, |(_, _, cost)| *cost);
+
+    // Assign every building a cost based on which end of the sidewalk it's closest to
+    // TODO We could try to get a little more accurate by accounting for the distance from that
+    // end of the sidewalk to the building
+    for b in map.all_buildings() {
+        if let Some(seconds) = cost_per_node.get(&WalkingNode::closest(b.sidewalk_pos, map)) {
+            let duration = Duration::seconds(*seconds as f64);
+            if duration <= time_limit {
+                results.insert(b.id, duration);
+            }
+        }
+    }
+    results
+}
@@ -0,0 +1,139 @@
+//! Changing lane widths moves the edges of a road, so the lanes, the intersections at either end,
+//! and anything positioned along the road have to be updated. The road's center line stays put,
+//! so nothing else in the map moves.
+
+use std::collections::BTreeSet;
+
+use abstutil::Timer;
+use geom::{Distance, Pt2D, Ring};
+
+use crate::{Direction, IntersectionID, LaneID, Map, Position, RoadID};
+
+/// Intersection polygons are built from the corners of each road. Points this close to a road's
+/// old corner are moved with it.
+const CORNER_THRESHOLD: Distance = Distance::const_meters(0.5);
+
+/// Sets the width of every lane in a road, from left to right, and rebuilds the geometry that
+/// depends on it. Turns aren't updated here.
+pub fn change_lane_widths(map: &mut Map, r: RoadID, widths: &[Distance], timer: &mut Timer) {
+    let old_half_width = map.get_r(r).get_half_width(map);
+    let new_half_width = widths.iter().cloned().sum::<Distance>() / 2.0;
+    let lanes = map.get_r(r).lanes_ltr();
+    assert_eq!(lanes.len(), widths.len());
+
+    let center = map.get_r(r).center_pts.clone();
+    let left = center
+        .shift_left(new_half_width)
+        .unwrap_or_else(|_| center.clone());
+    let mut width_so_far = Distance::ZERO;
+    for ((l, dir, _), width) in lanes.iter().zip(widths) {
+        let pl = if let Ok(pl) = left.shift_right(width_so_far + (*width / 2.0)) {
+            pl
+        } else {
+            timer.error(format!("{} geometry broken; lane not shifted!", l));
+            left.clone()
+        };
+        width_so_far += *width;
+
+        let lane = &mut map.lanes[l.0];
+        lane.width = *width;
+        lane.lane_center_pts = if *dir == Direction::Fwd {
+            pl
+        } else {
+            pl.reversed()
+        };
+    }
+
+    let (src_i, dst_i) = (map.get_r(r).src_i, map.get_r(r).dst_i);
+    for i in vec![src_i, dst_i] {
+        move_corners(map, i, r, old_half_width, new_half_width, timer);
+    }
+    clamp_positions(map, lanes.into_iter().map(|(l, _, _)| l).collect());
+}
+
+/// Moves the two corners where a road meets an intersection, instead of recalculating the whole
+/// polygon from scratch.
+fn move_corners(
+    map: &mut Map,
+    i: IntersectionID,
+    r: RoadID,
+    old_half_width: Distance,
+    new_half_width: Distance,
+    timer: &mut Timer,
+) {
+    // Point the center line into the intersection
+    let road = map.get_r(r);
+    let center = if road.dst_i == i {
+        road.center_pts.clone()
+    } else {
+        road.center_pts.reversed()
+    };
+    let corners = |half_width: Distance| -> Option<(Pt2D, Pt2D)> {
+        Some((
+            center.shift_right(half_width).ok()?.last_pt(),
+            center.shift_left(half_width).ok()?.last_pt(),
+        ))
+    };
+    let (old_right, old_left, new_right, new_left) =
+        match (corners(old_half_width), corners(new_half_width)) {
+            (Some((old_right, old_left)), Some((new_right, new_left))) => {
+                (old_right, old_left, new_right, new_left)
+            }
+            _ => {
+                timer.warn(format!("Can't find the corners of {} at {}", r, i));
+                return;
+            }
+        };
+
+    let mut pts = map.get_i(i).polygon.points().clone();
+    let mut moved = false;
+    for pt in &mut pts {
+        if pt.approx_eq(old_right, CORNER_THRESHOLD) {
+            *pt = new_right;
+            moved = true;
+        } else if pt.approx_eq(old_left, CORNER_THRESHOLD) {
+            *pt = new_left;
+            moved = true;
+        }
+    }
+    if !moved {
+        timer.warn(format!(
+            "{}'s polygon doesn't include the corners of {}; not updating it",
+            i, r
+        ));
+        return;
+    }
+    match Ring::new(pts) {
+        Ok(ring) => {
+            map.intersections[i.0].polygon = ring.to_polygon();
+        }
+        Err(err) => {
+            timer.warn(format!("Can't move the corners of {} at {}: {}", r, i, err));
+        }
+    }
+}
+
+/// Lanes can get a little shorter around curves, so make sure nothing is positioned past the end.
+fn clamp_positions(map: &mut Map, lanes: BTreeSet<LaneID>) {
+    let all_lanes = &map.lanes;
+    let clamp = |pos: &mut Position| {
+        if lanes.contains(&pos.lane()) {
+            let length = all_lanes[pos.lane().0].length();
+            if pos.dist_along() > length {
+                *pos = Position::new(pos.lane(), length);
+            }
+        }
+    };
+
+    for b in &mut map.buildings {
+        clamp(&mut b.sidewalk_pos);
+    }
+    for bs in map.bus_stops.values_mut() {
+        clamp(&mut bs.driving_pos);
+        clamp(&mut bs.sidewalk_pos);
+    }
+    for pl in &mut map.parking_lots {
+        clamp(&mut pl.driving_pos);
+        clamp(&mut pl.sidewalk_pos);
+    }
+}
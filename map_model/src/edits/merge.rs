@@ -0,0 +1,250 @@
+//! Two people may edit the same map separately, then want to combine their proposals. Both
+//! proposals start from the same original map, so only the net change each one makes to a road,
+//! intersection, or other thing matters. When both change the same thing differently, that's a
+//! conflict, and somebody has to pick which side wins.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{BuildingID, BusRouteID, EditCmd, IntersectionID, MapEdits, RoadID};
+
+/// The thing that an `EditCmd` changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EditTarget {
+    Road(RoadID),
+    Intersection(IntersectionID),
+    RouteSchedule(BusRouteID),
+    RouteStops(BusRouteID),
+    /// Every new bus route is edited at once
+    NewBusRoutes,
+    /// Every toll zone is edited at once
+    TollZones,
+    BikeRacks(BuildingID),
+    /// Every parking zone is edited at once
+    ParkingZones,
+    BikeTreatments(IntersectionID),
+    /// Every guideway is edited at once
+    Guideways,
+}
+
+/// Both proposals change the same thing, but differently.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EditConflict {
+    pub target: EditTarget,
+    /// The net change made by the proposal being merged into
+    pub ours: EditCmd,
+    /// The net change made by the other proposal
+    pub theirs: EditCmd,
+}
+
+impl EditCmd {
+    pub fn target(&self) -> EditTarget {
+        match self {
+            EditCmd::ChangeRoad { r, .. } => EditTarget::Road(*r),
+            EditCmd::ChangeIntersection { i, .. } => EditTarget::Intersection(*i),
+            EditCmd::ChangeRouteSchedule { id, .. } => EditTarget::RouteSchedule(*id),
+            EditCmd::ChangeRouteStops { id, .. } => EditTarget::RouteStops(*id),
+            EditCmd::ChangeNewBusRoutes { .. } => EditTarget::NewBusRoutes,
+            EditCmd::ChangeTollZones { .. } => EditTarget::TollZones,
+            EditCmd::ChangeBikeRacks { b, .. } => EditTarget::BikeRacks(*b),
+            EditCmd::ChangeParkingZones { .. } => EditTarget::ParkingZones,
+            EditCmd::ChangeBikeTreatments { i, .. } => EditTarget::BikeTreatments(*i),
+            EditCmd::ChangeGuideways { .. } => EditTarget::Guideways,
+        }
+    }
+}
+
+impl MapEdits {
+    /// Collapses every command touching the same thing into one, going from the state before the
+    /// first command to the state after the last.
+    pub fn net_changes(&self) -> BTreeMap<EditTarget, EditCmd> {
+        let mut changes: BTreeMap<EditTarget, EditCmd> = BTreeMap::new();
+        for cmd in &self.commands {
+            let target = cmd.target();
+            let net = match changes.remove(&target) {
+                Some(first) => span(&first, cmd.clone()),
+                None => cmd.clone(),
+            };
+            changes.insert(target, net);
+        }
+        changes
+    }
+
+    /// Finds everything that both proposals change, but to something different.
+    pub fn find_conflicts(&self, other: &MapEdits) -> Vec<EditConflict> {
+        let theirs = other.net_changes();
+        let mut conflicts = Vec::new();
+        for (target, ours) in self.net_changes() {
+            if let Some(theirs) = theirs.get(&target) {
+                if ours != *theirs {
+                    conflicts.push(EditConflict {
+                        target,
+                        ours,
+                        theirs: theirs.clone(),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Combines two proposals. Every command from this one is kept, then the other proposal's
+    /// changes to anything this one doesn't touch are added as one undoable step. Conflicts go
+    /// this proposal's way, unless the target is in `theirs_wins`. The caller still has to apply
+    /// the result.
+    pub fn merge(&self, other: &MapEdits, theirs_wins: &BTreeSet<EditTarget>) -> MapEdits {
+        let ours = self.net_changes();
+        let mut merged = self.clone();
+        merged.edits_name = format!("{} + {}", self.edits_name, other.edits_name);
+        merged.redo_stack.clear();
+
+        let first = merged.commands.len();
+        for (target, theirs) in other.net_changes() {
+            match ours.get(&target) {
+                None => {
+                    merged.commands.push(theirs);
+                }
+                Some(ours) => {
+                    if *ours != theirs && theirs_wins.contains(&target) {
+                        // Go from whatever this proposal left behind
+                        merged.commands.push(span(&ours.clone().undo(), theirs));
+                    }
+                }
+            }
+        }
+        merged.group_commands_since(first);
+
+        for line in &other.proposal_description {
+            if !merged.proposal_description.contains(line) {
+                merged.proposal_description.push(line.clone());
+            }
+        }
+        if merged.proposal_link.is_none() {
+            merged.proposal_link = other.proposal_link.clone();
+        }
+        merged
+    }
+}
+
+/// Both commands must have the same target. Produces one command with the old state from `first`
+/// and the new state from `last`.
+fn span(first: &EditCmd, last: EditCmd) -> EditCmd {
+    match (first, last) {
+        (EditCmd::ChangeRoad { old, .. }, EditCmd::ChangeRoad { r, new, .. }) => {
+            EditCmd::ChangeRoad {
+                r,
+                old: old.clone(),
+                new,
+            }
+        }
+        (EditCmd::ChangeIntersection { old, .. }, EditCmd::ChangeIntersection { i, new, .. }) => {
+            EditCmd::ChangeIntersection {
+                i,
+                old: old.clone(),
+                new,
+            }
+        }
+        (
+            EditCmd::ChangeRouteSchedule { old, .. },
+            EditCmd::ChangeRouteSchedule { id, new, .. },
+        ) => EditCmd::ChangeRouteSchedule {
+            id,
+            old: old.clone(),
+            new,
+        },
+        (EditCmd::ChangeRouteStops { old, .. }, EditCmd::ChangeRouteStops { id, new, .. }) => {
+            EditCmd::ChangeRouteStops {
+                id,
+                old: old.clone(),
+                new,
+            }
+        }
+        (EditCmd::ChangeNewBusRoutes { old, .. }, EditCmd::ChangeNewBusRoutes { new, .. }) => {
+            EditCmd::ChangeNewBusRoutes {
+                old: old.clone(),
+                new,
+            }
+        }
+        (EditCmd::ChangeTollZones { old, .. }, EditCmd::ChangeTollZones { new, .. }) => {
+            EditCmd::ChangeTollZones {
+                old: old.clone(),
+                new,
+            }
+        }
+        (EditCmd::ChangeBikeRacks { old, .. }, EditCmd::ChangeBikeRacks { b, new, .. }) => {
+            EditCmd::ChangeBikeRacks { b, old: *old, new }
+        }
+        (EditCmd::ChangeParkingZones { old, .. }, EditCmd::ChangeParkingZones { new, .. }) => {
+            EditCmd::ChangeParkingZones {
+                old: old.clone(),
+                new,
+            }
+        }
+        (
+            EditCmd::ChangeBikeTreatments { old, .. },
+            EditCmd::ChangeBikeTreatments { i, new, .. },
+        ) => EditCmd::ChangeBikeTreatments { i, old: *old, new },
+        (EditCmd::ChangeGuideways { old, .. }, EditCmd::ChangeGuideways { new, .. }) => {
+            EditCmd::ChangeGuideways {
+                old: old.clone(),
+                new,
+            }
+        }
+        (first, last) => panic!("Can't combine {:?} and {:?}", first, last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn racks(b: usize, old: usize, new: usize) -> EditCmd {
+        EditCmd::ChangeBikeRacks {
+            b: BuildingID(b),
+            old,
+            new,
+        }
+    }
+
+    fn edits(name: &str, commands: Vec<EditCmd>) -> MapEdits {
+        let mut edits = MapEdits::new();
+        edits.edits_name = name.to_string();
+        edits.commands = commands;
+        edits
+    }
+
+    #[test]
+    fn merge_proposals() {
+        let ours = edits("ours", vec![racks(0, 4, 6), racks(0, 6, 8), racks(1, 4, 2)]);
+        let theirs = edits(
+            "theirs",
+            vec![racks(0, 4, 10), racks(1, 4, 2), racks(2, 0, 5)],
+        );
+
+        assert_eq!(
+            ours.net_changes()[&EditTarget::BikeRacks(BuildingID(0))],
+            racks(0, 4, 8)
+        );
+        // Making the same change isn't a conflict
+        assert_eq!(
+            ours.find_conflicts(&theirs),
+            vec![EditConflict {
+                target: EditTarget::BikeRacks(BuildingID(0)),
+                ours: racks(0, 4, 8),
+                theirs: racks(0, 4, 10),
+            }]
+        );
+
+        let merged = ours.merge(&theirs, &BTreeSet::new());
+        assert_eq!(merged.edits_name, "ours + theirs");
+        assert_eq!(&merged.commands[3..], &[racks(2, 0, 5)]);
+
+        let mut theirs_wins = BTreeSet::new();
+        theirs_wins.insert(EditTarget::BikeRacks(BuildingID(0)));
+        let merged = ours.merge(&theirs, &theirs_wins);
+        assert_eq!(&merged.commands[3..], &[racks(0, 8, 10), racks(2, 0, 5)]);
+        assert_eq!(
+            merged.net_changes()[&EditTarget::BikeRacks(BuildingID(0))],
+            racks(0, 4, 10)
+        );
+    }
+}
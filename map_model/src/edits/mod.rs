@@ -4,20 +4,25 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 
+pub use merge::{EditConflict, EditTarget};
 pub use perma::PermanentMapEdits;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{retain_btreemap, retain_btreeset, Timer};
-use geom::{Speed, Time};
+use geom::{Distance, Speed, Time};
 
 use crate::make::initial::lane_specs::get_lane_specs_ltr;
+use crate::make::transit;
 use crate::{
-    connectivity, AccessRestrictions, BusRouteID, ControlStopSign, ControlTrafficSignal, Direction,
-    IntersectionID, IntersectionType, LaneID, LaneType, Map, MapConfig, PathConstraints,
-    Pathfinder, Road, RoadID, TurnID, Zone,
+    connectivity, AccessRestrictions, BannedTurn, BikeTreatments, BuildingID, BusRouteID,
+    BusStopID, ControlStopSign, ControlTrafficSignal, Crosswalk, Direction, Guideway,
+    IntersectionID, IntersectionType, LaneID, LaneType, Map, MapConfig, NewBusRoute, ParkingZone,
+    PathConstraints, Pathfinder, Road, RoadID, TimedSpeedLimit, TollZone, TurnID, Zone,
 };
 
 mod compat;
+mod geometry;
+mod merge;
 mod perma;
 
 /// Represents changes to a map. Note this isn't serializable -- that's what `PermanentMapEdits`
@@ -28,6 +33,12 @@ pub struct MapEdits {
     /// A stack, oldest edit is first. The same intersection may be edited multiple times in this
     /// stack, until compress() happens.
     pub commands: Vec<EditCmd>,
+    /// Some actions in the UI, like changing every lane on a road, produce many commands that
+    /// should be undone and redone as one step. Each entry is the index of a command that belongs
+    /// to the same step as the command before it.
+    pub grouped_with_previous: BTreeSet<usize>,
+    /// Steps that've been undone, most recently undone last. Making any new edit clears this.
+    pub redo_stack: Vec<Vec<EditCmd>>,
     /// If false, adjacent roads with the same AccessRestrictions will not be merged into the same
     /// Zone; every Road will be its own Zone. This is used to experiment with a per-road cap. Note
     /// this is a map-wide setting.
@@ -36,7 +47,9 @@ pub struct MapEdits {
     /// Derived from commands, kept up to date by update_derived
     pub changed_roads: BTreeSet<RoadID>,
     pub original_intersections: BTreeMap<IntersectionID, EditIntersection>,
+    /// Only routes that came with the map. Routes created by the edits are always different.
     pub changed_routes: BTreeSet<BusRouteID>,
+    pub original_route_stops: BTreeMap<BusRouteID, Vec<BusStopID>>,
 
     /// Some edits are included in the game by default, in data/system/proposals, as "community
     /// proposals." They require a description and may have a link to a write-up.
@@ -56,19 +69,33 @@ pub enum EditIntersection {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EditRoad {
     pub lanes_ltr: Vec<(LaneType, Direction)>,
+    /// Matches up with lanes_ltr. Edits saved before widths could change don't have this.
+    #[serde(default)]
+    pub lane_widths: Vec<Distance>,
     pub speed_limit: Speed,
     pub access_restrictions: AccessRestrictions,
+    #[serde(default)]
+    pub modal_filter: bool,
+    #[serde(default)]
+    pub banned_turns: Vec<BannedTurn>,
+    #[serde(default)]
+    pub timed_speed_limits: Vec<TimedSpeedLimit>,
+    #[serde(default)]
+    pub deliveries_per_hour: usize,
 }
 
 impl EditRoad {
     pub fn get_orig_from_osm(r: &Road, cfg: &MapConfig) -> EditRoad {
+        let specs = get_lane_specs_ltr(&r.osm_tags, cfg);
         EditRoad {
-            lanes_ltr: get_lane_specs_ltr(&r.osm_tags, cfg)
-                .into_iter()
-                .map(|spec| (spec.lt, spec.dir))
-                .collect(),
+            lanes_ltr: specs.iter().map(|spec| (spec.lt, spec.dir)).collect(),
+            lane_widths: specs.iter().map(|spec| spec.width).collect(),
             speed_limit: r.speed_limit_from_osm(),
             access_restrictions: r.access_restrictions_from_osm(),
+            modal_filter: false,
+            banned_turns: Vec::new(),
+            timed_speed_limits: Vec::new(),
+            deliveries_per_hour: 0,
         }
     }
 
@@ -84,6 +111,13 @@ impl EditRoad {
             }
         }
 
+        let width = self
+            .lane_widths
+            .iter()
+            .zip(other.lane_widths.iter())
+            .filter(|(w1, w2)| w1 != w2)
+            .count();
+
         let mut changes = Vec::new();
         if lt == 1 {
             changes.push(format!("1 lane type"));
@@ -95,12 +129,29 @@ impl EditRoad {
         } else if dir > 1 {
             changes.push(format!("{} lane reversal", dir));
         }
+        if width == 1 {
+            changes.push(format!("1 lane width"));
+        } else if width > 1 {
+            changes.push(format!("{} lane widths", width));
+        }
         if self.speed_limit != other.speed_limit {
             changes.push(format!("speed limit"));
         }
         if self.access_restrictions != other.access_restrictions {
             changes.push(format!("access restrictions"));
         }
+        if self.modal_filter != other.modal_filter {
+            changes.push(format!("modal filter"));
+        }
+        if self.banned_turns != other.banned_turns {
+            changes.push(format!("banned turns"));
+        }
+        if self.timed_speed_limits != other.timed_speed_limits {
+            changes.push(format!("speed limits by time of day"));
+        }
+        if self.deliveries_per_hour != other.deliveries_per_hour {
+            changes.push(format!("deliveries"));
+        }
         changes
     }
 }
@@ -122,6 +173,42 @@ pub enum EditCmd {
         old: Vec<Time>,
         new: Vec<Time>,
     },
+    ChangeRouteStops {
+        id: BusRouteID,
+        old: Vec<BusStopID>,
+        new: Vec<BusStopID>,
+    },
+    /// Replaces every bus route created by the edits at once
+    ChangeNewBusRoutes {
+        old: Vec<NewBusRoute>,
+        new: Vec<NewBusRoute>,
+    },
+    /// Replaces every toll zone at once
+    ChangeTollZones {
+        old: Vec<TollZone>,
+        new: Vec<TollZone>,
+    },
+    /// The number of bikes that fit at racks by a building
+    ChangeBikeRacks {
+        b: BuildingID,
+        old: usize,
+        new: usize,
+    },
+    /// Replaces every parking zone at once
+    ChangeParkingZones {
+        old: Vec<ParkingZone>,
+        new: Vec<ParkingZone>,
+    },
+    ChangeBikeTreatments {
+        i: IntersectionID,
+        old: BikeTreatments,
+        new: BikeTreatments,
+    },
+    /// Replaces every guideway at once
+    ChangeGuideways {
+        old: Vec<Guideway>,
+        new: Vec<Guideway>,
+    },
 }
 
 pub struct EditEffects {
@@ -138,11 +225,14 @@ impl MapEdits {
             proposal_description: Vec::new(),
             proposal_link: None,
             commands: Vec::new(),
+            grouped_with_previous: BTreeSet::new(),
+            redo_stack: Vec::new(),
             merge_zones: true,
 
             changed_roads: BTreeSet::new(),
             original_intersections: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            original_route_stops: BTreeMap::new(),
         }
     }
 
@@ -176,6 +266,7 @@ impl MapEdits {
         self.changed_roads.clear();
         self.original_intersections.clear();
         self.changed_routes.clear();
+        self.original_route_stops.clear();
 
         for cmd in &self.commands {
             match cmd {
@@ -190,6 +281,18 @@ impl MapEdits {
                 EditCmd::ChangeRouteSchedule { id, .. } => {
                     self.changed_routes.insert(*id);
                 }
+                EditCmd::ChangeRouteStops { id, ref old, .. } => {
+                    self.changed_routes.insert(*id);
+                    if !self.original_route_stops.contains_key(id) {
+                        self.original_route_stops.insert(*id, old.clone());
+                    }
+                }
+                EditCmd::ChangeTollZones { .. }
+                | EditCmd::ChangeBikeRacks { .. }
+                | EditCmd::ChangeParkingZones { .. }
+                | EditCmd::ChangeNewBusRoutes { .. }
+                | EditCmd::ChangeBikeTreatments { .. }
+                | EditCmd::ChangeGuideways { .. } => {}
             }
         }
 
@@ -199,12 +302,61 @@ impl MapEdits {
         retain_btreemap(&mut self.original_intersections, |i, orig| {
             map.get_i_edit(*i) != orig.clone()
         });
+        retain_btreemap(&mut self.original_route_stops, |br, orig| {
+            map.maybe_get_br(*br)
+                .map(|r| !r.is_from_edits() && r.stops != *orig)
+                .unwrap_or(false)
+        });
+        let original_route_stops = &self.original_route_stops;
         retain_btreeset(&mut self.changed_routes, |br| {
-            let r = map.get_br(*br);
-            r.spawn_times != r.orig_spawn_times
+            map.maybe_get_br(*br)
+                .map(|r| {
+                    !r.is_from_edits()
+                        && (r.spawn_times != r.orig_spawn_times
+                            || original_route_stops.contains_key(br))
+                })
+                .unwrap_or(false)
         });
     }
 
+    /// Makes all commands starting at index `first` undo and redo as one step.
+    pub fn group_commands_since(&mut self, first: usize) {
+        for idx in first + 1..self.commands.len() {
+            self.grouped_with_previous.insert(idx);
+        }
+    }
+
+    /// Removes the most recent step from the commands, remembering it for redo. Returns false if
+    /// there's nothing to undo. The caller still has to apply the edits.
+    pub fn undo(&mut self) -> bool {
+        let mut step = Vec::new();
+        while let Some(cmd) = self.commands.pop() {
+            step.push(cmd);
+            if !self.grouped_with_previous.remove(&self.commands.len()) {
+                break;
+            }
+        }
+        if step.is_empty() {
+            return false;
+        }
+        step.reverse();
+        self.redo_stack.push(step);
+        true
+    }
+
+    /// Restores the most recently undone step. Returns false if there's nothing to redo. The
+    /// caller still has to apply the edits.
+    pub fn redo(&mut self) -> bool {
+        if let Some(step) = self.redo_stack.pop() {
+            let first = self.commands.len();
+            self.commands.extend(step);
+            self.group_commands_since(first);
+            true
+        } else {
+            false
+        }
+    }
+
     /// Assumes update_derived has been called.
     pub fn compress(&mut self, map: &Map) {
         for r in &self.changed_roads {
@@ -223,10 +375,58 @@ impl MapEdits {
         }
         for r in &self.changed_routes {
             let r = map.get_br(*r);
-            self.commands.push(EditCmd::ChangeRouteSchedule {
-                id: r.id,
-                new: r.spawn_times.clone(),
-                old: r.orig_spawn_times.clone(),
+            if r.spawn_times != r.orig_spawn_times {
+                self.commands.push(EditCmd::ChangeRouteSchedule {
+                    id: r.id,
+                    new: r.spawn_times.clone(),
+                    old: r.orig_spawn_times.clone(),
+                });
+            }
+        }
+        for (r, old) in &self.original_route_stops {
+            self.commands.push(EditCmd::ChangeRouteStops {
+                id: *r,
+                old: old.clone(),
+                new: map.get_br(*r).stops.clone(),
+            });
+        }
+        let new_routes = map.get_new_bus_routes();
+        if !new_routes.is_empty() {
+            self.commands.push(EditCmd::ChangeNewBusRoutes {
+                old: Vec::new(),
+                new: new_routes,
+            });
+        }
+        if !map.toll_zones.is_empty() {
+            self.commands.push(EditCmd::ChangeTollZones {
+                old: Vec::new(),
+                new: map.toll_zones.clone(),
+            });
+        }
+        for (b, new) in &map.bike_racks {
+            self.commands.push(EditCmd::ChangeBikeRacks {
+                b: *b,
+                old: map.get_b(*b).osm_bike_rack_capacity(),
+                new: *new,
+            });
+        }
+        if !map.parking_zones.is_empty() {
+            self.commands.push(EditCmd::ChangeParkingZones {
+                old: Vec::new(),
+                new: map.parking_zones.clone(),
+            });
+        }
+        for (i, new) in &map.bike_treatments {
+            self.commands.push(EditCmd::ChangeBikeTreatments {
+                i: *i,
+                old: BikeTreatments::default(),
+                new: *new,
+            });
+        }
+        if !map.guideways.is_empty() {
+            self.commands.push(EditCmd::ChangeGuideways {
+                old: Vec::new(),
+                new: map.guideways.clone(),
             });
         }
     }
@@ -241,6 +441,11 @@ impl MapEdits {
             // What exactly changed?
             if r.speed_limit != orig.speed_limit
                 || r.access_restrictions != orig.access_restrictions
+                || r.modal_filter != orig.modal_filter
+                || r.banned_turns != orig.banned_turns
+                || r.timed_speed_limits != orig.timed_speed_limits
+                || r.deliveries_per_hour != orig.deliveries_per_hour
+                || map.get_r_edit(r.id).lane_widths != orig.lane_widths
             {
                 roads.insert(r.id);
             } else {
@@ -291,6 +496,52 @@ impl EditCmd {
             EditCmd::ChangeRouteSchedule { id, .. } => {
                 format!("reschedule route {}", map.get_br(*id).short_name)
             }
+            EditCmd::ChangeRouteStops { id, old, new } => {
+                details.push(format!("{} stops, instead of {}", new.len(), old.len()));
+                format!("reroute {}", map.get_br(*id).short_name)
+            }
+            EditCmd::ChangeNewBusRoutes { new, .. } => {
+                for r in new {
+                    details.push(format!("{}: {} stops", r.name, r.stops.len()));
+                }
+                format!("{} new bus routes", new.len())
+            }
+            EditCmd::ChangeTollZones { new, .. } => {
+                for zone in new {
+                    details.push(format!("{}: {}", zone.name, zone.describe_schedule()));
+                }
+                format!("{} toll zones", new.len())
+            }
+            EditCmd::ChangeBikeRacks { b, old, new } => {
+                details.push(format!("room for {} bikes, instead of {}", new, old));
+                format!("bike racks at building #{}", b.0)
+            }
+            EditCmd::ChangeParkingZones { new, .. } => {
+                for zone in new {
+                    details.push(format!("{}: {}", zone.name, zone.describe_restriction()));
+                }
+                format!("{} parking zones", new.len())
+            }
+            EditCmd::ChangeBikeTreatments { i, new, .. } => {
+                if new.is_empty() {
+                    details.push("removed".to_string());
+                }
+                for x in new.describe() {
+                    details.push(x.to_string());
+                }
+                format!("bike treatments at {}", i)
+            }
+            EditCmd::ChangeGuideways { new, .. } => {
+                for guideway in new {
+                    details.push(format!(
+                        "{} ({}): {}",
+                        guideway.name,
+                        guideway.kind.describe(),
+                        guideway.describe_service()
+                    ));
+                }
+                format!("{} guideways", new.len())
+            }
         };
         (summary, details)
     }
@@ -306,6 +557,10 @@ impl EditCmd {
                 let road = &mut map.roads[r.0];
                 road.speed_limit = new.speed_limit;
                 road.access_restrictions = new.access_restrictions.clone();
+                road.modal_filter = new.modal_filter;
+                road.banned_turns = new.banned_turns.clone();
+                road.timed_speed_limits = new.timed_speed_limits.clone();
+                road.deliveries_per_hour = new.deliveries_per_hour;
                 assert_eq!(road.lanes_ltr.len(), new.lanes_ltr.len());
                 for (idx, (lt, dir)) in new.lanes_ltr.clone().into_iter().enumerate() {
                     let lane = &mut map.lanes[(road.lanes_ltr[idx].0).0];
@@ -320,8 +575,19 @@ impl EditCmd {
                     }
                 }
 
-                effects.changed_roads.insert(road.id);
-                for i in vec![road.src_i, road.dst_i] {
+                let (src_i, dst_i) = (road.src_i, road.dst_i);
+                let old_widths: Vec<Distance> = map
+                    .get_r(*r)
+                    .lanes_ltr()
+                    .into_iter()
+                    .map(|(l, _, _)| map.get_l(l).width)
+                    .collect();
+                if old_widths != new.lane_widths {
+                    geometry::change_lane_widths(map, *r, &new.lane_widths, timer);
+                }
+
+                effects.changed_roads.insert(*r);
+                for i in vec![src_i, dst_i] {
                     effects.changed_intersections.insert(i);
                     let i = &mut map.intersections[i.0];
                     i.outgoing_lanes.clear();
@@ -379,6 +645,53 @@ impl EditCmd {
             EditCmd::ChangeRouteSchedule { id, new, .. } => {
                 map.bus_routes[id.0].spawn_times = new.clone();
             }
+            EditCmd::ChangeRouteStops { id, new, .. } => {
+                match transit::route_with_stops(map, map.get_br(*id).clone(), new.clone()) {
+                    Ok(route) => {
+                        map.bus_routes[id.0] = route;
+                    }
+                    Err(err) => timer.warn(format!(
+                        "Can't change stops of {}: {}",
+                        map.get_br(*id).full_name,
+                        err
+                    )),
+                }
+            }
+            EditCmd::ChangeNewBusRoutes { new, .. } => {
+                // New routes always come after the ones from the map
+                map.bus_routes.retain(|r| !r.is_from_edits());
+                for (idx, r) in new.iter().enumerate() {
+                    match transit::make_new_route(map, idx, r) {
+                        Ok(route) => {
+                            map.bus_routes.push(route);
+                        }
+                        Err(err) => timer.warn(format!("Can't create route {}: {}", r.name, err)),
+                    }
+                }
+            }
+            EditCmd::ChangeTollZones { new, .. } => {
+                map.toll_zones = new.clone();
+            }
+            EditCmd::ChangeBikeRacks { b, new, .. } => {
+                if *new == map.get_b(*b).osm_bike_rack_capacity() {
+                    map.bike_racks.remove(b);
+                } else {
+                    map.bike_racks.insert(*b, *new);
+                }
+            }
+            EditCmd::ChangeParkingZones { new, .. } => {
+                map.parking_zones = new.clone();
+            }
+            EditCmd::ChangeBikeTreatments { i, new, .. } => {
+                if new.is_empty() {
+                    map.bike_treatments.remove(i);
+                } else {
+                    map.bike_treatments.insert(*i, *new);
+                }
+            }
+            EditCmd::ChangeGuideways { new, .. } => {
+                map.guideways = new.clone();
+            }
         }
     }
 
@@ -399,6 +712,33 @@ impl EditCmd {
                 old: new,
                 new: old,
             },
+            EditCmd::ChangeRouteStops { id, old, new } => EditCmd::ChangeRouteStops {
+                id,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeNewBusRoutes { old, new } => {
+                EditCmd::ChangeNewBusRoutes { old: new, new: old }
+            }
+            EditCmd::ChangeTollZones { old, new } => {
+                EditCmd::ChangeTollZones { old: new, new: old }
+            }
+            EditCmd::ChangeBikeRacks { b, old, new } => EditCmd::ChangeBikeRacks {
+                b,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeParkingZones { old, new } => {
+                EditCmd::ChangeParkingZones { old: new, new: old }
+            }
+            EditCmd::ChangeBikeTreatments { i, old, new } => EditCmd::ChangeBikeTreatments {
+                i,
+                old: new,
+                new: old,
+            },
+            EditCmd::ChangeGuideways { old, new } => {
+                EditCmd::ChangeGuideways { old: new, new: old }
+            }
         }
     }
 }
@@ -441,6 +781,18 @@ fn recalculate_turns(
         map.turns.insert(t.id, t);
     }
 
+    // Keep how each crossing is controlled, as long as the road is still there
+    let mut crosswalks = Crosswalk::make_all(map, map.get_i(id));
+    let i = &mut map.intersections[id.0];
+    for c in &mut crosswalks {
+        if let Some(old) = i.crosswalks.iter().find(|old| old.road == c.road) {
+            c.marked = old.marked;
+            c.leading_interval = old.leading_interval;
+            c.push_button = old.push_button;
+        }
+    }
+    i.crosswalks = crosswalks;
+
     match i.intersection_type {
         IntersectionType::StopSign => {
             // Stop sign policy usually doesn't depend on incoming lane types, except when changing
@@ -489,8 +841,17 @@ impl Map {
                 .into_iter()
                 .map(|(_, dir, lt)| (lt, dir))
                 .collect(),
+            lane_widths: r
+                .lanes_ltr()
+                .into_iter()
+                .map(|(l, _, _)| self.get_l(l).width)
+                .collect(),
             speed_limit: r.speed_limit,
             access_restrictions: r.access_restrictions.clone(),
+            modal_filter: r.modal_filter,
+            banned_turns: r.banned_turns.clone(),
+            timed_speed_limits: r.timed_speed_limits.clone(),
+            deliveries_per_hour: r.deliveries_per_hour,
         }
     }
 
@@ -501,6 +862,41 @@ impl Map {
         EditCmd::ChangeRoad { r, old, new }
     }
 
+    /// The bus routes that only exist in the current edits.
+    pub fn get_new_bus_routes(&self) -> Vec<NewBusRoute> {
+        self.bus_routes
+            .iter()
+            .filter(|r| r.is_from_edits())
+            .map(|r| NewBusRoute {
+                name: r.full_name.clone(),
+                stops: r.stops.clone(),
+                spawn_times: r.spawn_times.clone(),
+            })
+            .collect()
+    }
+
+    /// Checks if buses could serve these stops, before changing an existing route (or creating a
+    /// new one, if `route` is None) in the edits.
+    pub fn check_route_stops(
+        &self,
+        route: Option<BusRouteID>,
+        stops: Vec<BusStopID>,
+    ) -> Result<(), String> {
+        match route {
+            Some(id) => transit::route_with_stops(self, self.get_br(id).clone(), stops),
+            None => transit::make_new_route(
+                self,
+                0,
+                &NewBusRoute {
+                    name: "new route".to_string(),
+                    stops,
+                    spawn_times: Vec::new(),
+                },
+            ),
+        }
+        .map(|_| ())
+    }
+
     /// Panics on borders
     pub fn get_i_edit(&self, i: IntersectionID) -> EditIntersection {
         match self.get_i(i).intersection_type {
@@ -518,6 +914,7 @@ impl Map {
         // in the UI gets messed up.
         let mut edits = self.edits.clone();
         edits.commands.clear();
+        edits.grouped_with_previous.clear();
         edits.compress(self);
         edits.save(self);
     }
@@ -563,6 +960,26 @@ impl Map {
             );
         }
 
+        // Undone steps can't be redone after something new is edited
+        if new_edits.redo_stack == self.edits.redo_stack
+            && new_edits.commands.len() > self.edits.commands.len()
+            && new_edits.commands.starts_with(&self.edits.commands)
+        {
+            new_edits.redo_stack.clear();
+        }
+        let num_commands = new_edits.commands.len();
+        retain_btreeset(&mut new_edits.grouped_with_previous, |idx| {
+            *idx < num_commands
+        });
+
+        // Before changing anything, let the pathfinder note what its graphs look like, so it can
+        // skip rebuilding the ones these edits don't affect.
+        if !self.pathfinder_dirty {
+            let mut pathfinder = std::mem::replace(&mut self.pathfinder, Pathfinder::Dijkstra);
+            pathfinder.remember_input_graphs(self);
+            self.pathfinder = pathfinder;
+        }
+
         let mut effects = EditEffects::new();
 
         // We need to undo() all of the current commands in reverse order, then apply() all of the
@@ -624,6 +1041,27 @@ impl Map {
         if !effects.changed_roads.is_empty() || merge_zones_changed {
             self.zones = Zone::make_all(self);
         }
+        // Which turns enter a zone depends on the zones and on the turns that exist
+        if !self.toll_zones.is_empty() || !self.toll_entrances.is_empty() {
+            self.toll_entrances = TollZone::find_entrances(self);
+        }
+        // Sidewalks near stations might have changed
+        if !self.guideways.is_empty() || !self.guideway_stations.is_empty() {
+            let mut stations = Vec::new();
+            for guideway in &self.guideways {
+                stations.push(match guideway.find_station_positions(self) {
+                    Ok(positions) => positions,
+                    Err(err) => {
+                        timer.warn(err);
+                        Vec::new()
+                    }
+                });
+            }
+            self.guideway_stations = stations;
+        }
+        if !self.parking_zones.is_empty() || !self.parking_zone_roads.is_empty() {
+            self.parking_zone_roads = ParkingZone::find_roads(self);
+        }
 
         (
             effects.changed_roads,
@@ -648,6 +1086,7 @@ impl Map {
         let mut pathfinder = std::mem::replace(&mut self.pathfinder, Pathfinder::Dijkstra);
         pathfinder.apply_edits(self, timer);
         self.pathfinder = pathfinder;
+        self.timed_pathfinding_graphs.clear();
 
         // Also recompute blackholes. This is cheap enough to do from scratch.
         timer.start("recompute blackholes");
@@ -675,4 +1114,15 @@ impl Map {
         );
         self.traffic_signals.insert(signal.id, signal);
     }
+
+    /// Changes how a crosswalk is controlled. Like incremental_edit_traffic_signal, this isn't
+    /// recorded in the map edits.
+    pub fn incremental_edit_crosswalk(&mut self, crosswalk: Crosswalk) {
+        let i = &mut self.intersections[crosswalk.parent.0];
+        if let Some(idx) = i.crosswalks.iter().position(|c| c.road == crosswalk.road) {
+            i.crosswalks[idx] = crosswalk;
+        } else {
+            panic!("{} doesn't cross {}", crosswalk.parent, crosswalk.road);
+        }
+    }
 }
@@ -3,11 +3,14 @@ use std::collections::{BTreeMap, BTreeSet};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap, MapName};
-use geom::Time;
+use geom::{Distance, LonLat, Time};
 
 use crate::edits::{EditCmd, EditIntersection, EditRoad, MapEdits};
 use crate::raw::OriginalRoad;
-use crate::{osm, ControlStopSign, IntersectionID, Map};
+use crate::{
+    osm, BikeTreatments, BusStopID, ControlStopSign, Guideway, IntersectionID, Map, NewBusRoute,
+    ParkingZone, TollZone,
+};
 
 /// MapEdits are converted to this before serializing. Referencing things like LaneID in a Map won't
 /// work if the basemap is rebuilt from new OSM data, so instead we use stabler OSM IDs that're less
@@ -18,6 +21,12 @@ pub struct PermanentMapEdits {
     pub edits_name: String,
     pub version: usize,
     commands: Vec<PermanentEditCmd>,
+    /// Indices of commands that undo and redo in the same step as the command before them
+    #[serde(default)]
+    grouped_with_previous: BTreeSet<usize>,
+    /// Steps that were undone, so they can still be redone in a later session
+    #[serde(default)]
+    redo_stack: Vec<Vec<PermanentEditCmd>>,
     /// If false, adjacent roads with the same AccessRestrictions will not be merged into the same
     /// Zone; every Road will be its own Zone. This is used to experiment with a per-road cap. Note
     /// this is a map-wide setting.
@@ -59,6 +68,45 @@ pub enum PermanentEditCmd {
         old: Vec<Time>,
         new: Vec<Time>,
     },
+    /// Bus stops don't have any OSM ID, so they're identified by where they are.
+    ChangeRouteStops {
+        osm_rel_id: osm::RelationID,
+        old: Vec<LonLat>,
+        new: Vec<LonLat>,
+    },
+    ChangeNewBusRoutes {
+        old: Vec<PermanentNewBusRoute>,
+        new: Vec<PermanentNewBusRoute>,
+    },
+    ChangeTollZones {
+        old: Vec<TollZone>,
+        new: Vec<TollZone>,
+    },
+    ChangeBikeRacks {
+        b: osm::OsmID,
+        old: usize,
+        new: usize,
+    },
+    ChangeParkingZones {
+        old: Vec<ParkingZone>,
+        new: Vec<ParkingZone>,
+    },
+    ChangeBikeTreatments {
+        i: osm::NodeID,
+        old: BikeTreatments,
+        new: BikeTreatments,
+    },
+    ChangeGuideways {
+        old: Vec<Guideway>,
+        new: Vec<Guideway>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PermanentNewBusRoute {
+    name: String,
+    stops: Vec<LonLat>,
+    spawn_times: Vec<Time>,
 }
 
 impl EditCmd {
@@ -81,6 +129,39 @@ impl EditCmd {
                     new: new.clone(),
                 }
             }
+            EditCmd::ChangeRouteStops { id, old, new } => PermanentEditCmd::ChangeRouteStops {
+                osm_rel_id: map.get_br(*id).osm_rel_id,
+                old: stops_to_permanent(old, map),
+                new: stops_to_permanent(new, map),
+            },
+            EditCmd::ChangeNewBusRoutes { old, new } => PermanentEditCmd::ChangeNewBusRoutes {
+                old: old.iter().map(|r| r.to_permanent(map)).collect(),
+                new: new.iter().map(|r| r.to_permanent(map)).collect(),
+            },
+            EditCmd::ChangeTollZones { old, new } => PermanentEditCmd::ChangeTollZones {
+                old: old.clone(),
+                new: new.clone(),
+            },
+            EditCmd::ChangeBikeRacks { b, old, new } => PermanentEditCmd::ChangeBikeRacks {
+                b: map.get_b(*b).orig_id,
+                old: *old,
+                new: *new,
+            },
+            EditCmd::ChangeParkingZones { old, new } => PermanentEditCmd::ChangeParkingZones {
+                old: old.clone(),
+                new: new.clone(),
+            },
+            EditCmd::ChangeBikeTreatments { i, old, new } => {
+                PermanentEditCmd::ChangeBikeTreatments {
+                    i: map.get_i(*i).orig_id,
+                    old: *old,
+                    new: *new,
+                }
+            }
+            EditCmd::ChangeGuideways { old, new } => PermanentEditCmd::ChangeGuideways {
+                old: old.clone(),
+                new: new.clone(),
+            },
         }
     }
 }
@@ -88,8 +169,20 @@ impl EditCmd {
 impl PermanentEditCmd {
     pub fn to_cmd(self, map: &Map) -> Result<EditCmd, String> {
         match self {
-            PermanentEditCmd::ChangeRoad { r, new, old } => {
+            PermanentEditCmd::ChangeRoad {
+                r,
+                mut new,
+                mut old,
+            } => {
                 let id = map.find_r_by_osm_id(r)?;
+                // Older edits didn't change lane widths
+                let orig_widths =
+                    EditRoad::get_orig_from_osm(map.get_r(id), map.get_config()).lane_widths;
+                for edit in vec![&mut new, &mut old] {
+                    if edit.lane_widths.is_empty() {
+                        edit.lane_widths = orig_widths.clone();
+                    }
+                }
                 let num_current = map.get_r(id).lanes_ltr().len();
                 // The basemap changed -- it'd be pretty hard to understand the original
                 // intent of the edit.
@@ -101,6 +194,9 @@ impl PermanentEditCmd {
                         new.lanes_ltr.len()
                     ));
                 }
+                if new.lane_widths.len() != num_current {
+                    return Err(format!("the lane widths in {} don't match the lanes", r));
+                }
                 Ok(EditCmd::ChangeRoad { r: id, new, old })
             }
             PermanentEditCmd::ChangeIntersection { i, new, old } => {
@@ -125,6 +221,49 @@ impl PermanentEditCmd {
                     .ok_or(format!("can't find {}", osm_rel_id))?;
                 Ok(EditCmd::ChangeRouteSchedule { id, old, new })
             }
+            PermanentEditCmd::ChangeRouteStops {
+                osm_rel_id,
+                old,
+                new,
+            } => {
+                let id = map
+                    .find_br(osm_rel_id)
+                    .ok_or(format!("can't find {}", osm_rel_id))?;
+                Ok(EditCmd::ChangeRouteStops {
+                    id,
+                    old: stops_from_permanent(old, map)?,
+                    new: stops_from_permanent(new, map)?,
+                })
+            }
+            PermanentEditCmd::ChangeNewBusRoutes { old, new } => Ok(EditCmd::ChangeNewBusRoutes {
+                old: old
+                    .into_iter()
+                    .map(|r| r.from_permanent(map))
+                    .collect::<Result<Vec<_>, String>>()?,
+                new: new
+                    .into_iter()
+                    .map(|r| r.from_permanent(map))
+                    .collect::<Result<Vec<_>, String>>()?,
+            }),
+            PermanentEditCmd::ChangeTollZones { old, new } => {
+                Ok(EditCmd::ChangeTollZones { old, new })
+            }
+            PermanentEditCmd::ChangeBikeRacks { b, old, new } => {
+                let id = map
+                    .find_b_by_osm_id(b)
+                    .ok_or(format!("can't find building {}", b))?;
+                Ok(EditCmd::ChangeBikeRacks { b: id, old, new })
+            }
+            PermanentEditCmd::ChangeParkingZones { old, new } => {
+                Ok(EditCmd::ChangeParkingZones { old, new })
+            }
+            PermanentEditCmd::ChangeBikeTreatments { i, old, new } => {
+                let id = map.find_i_by_osm_id(i)?;
+                Ok(EditCmd::ChangeBikeTreatments { i: id, old, new })
+            }
+            PermanentEditCmd::ChangeGuideways { old, new } => {
+                Ok(EditCmd::ChangeGuideways { old, new })
+            }
         }
     }
 }
@@ -140,6 +279,12 @@ impl MapEdits {
             proposal_description: self.proposal_description.clone(),
             proposal_link: self.proposal_link.clone(),
             commands: self.commands.iter().map(|cmd| cmd.to_perma(map)).collect(),
+            grouped_with_previous: self.grouped_with_previous.clone(),
+            redo_stack: self
+                .redo_stack
+                .iter()
+                .map(|step| step.iter().map(|cmd| cmd.to_perma(map)).collect())
+                .collect(),
             merge_zones: self.merge_zones,
         }
     }
@@ -158,11 +303,14 @@ impl PermanentMapEdits {
                 .into_iter()
                 .map(|cmd| cmd.to_cmd(map))
                 .collect::<Result<Vec<EditCmd>, String>>()?,
+            grouped_with_previous: self.grouped_with_previous,
+            redo_stack: redo_stack_from_permanent(self.redo_stack, map),
             merge_zones: self.merge_zones,
 
             changed_roads: BTreeSet::new(),
             original_intersections: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            original_route_stops: BTreeMap::new(),
         };
         edits.update_derived(map);
         Ok(edits)
@@ -171,26 +319,58 @@ impl PermanentMapEdits {
     /// Transform permanent edits to MapEdits, looking up the map IDs by the hopefully stabler OSM
     /// IDs. Strip out commands that're broken.
     pub fn to_edits_permissive(self, map: &Map) -> MapEdits {
+        let mut commands = Vec::new();
+        let mut grouped_with_previous = BTreeSet::new();
+        // Does the current step have any commands that weren't stripped out?
+        let mut step_kept = false;
+        for (idx, cmd) in self.commands.into_iter().enumerate() {
+            let same_step = self.grouped_with_previous.contains(&idx);
+            if !same_step {
+                step_kept = false;
+            }
+            if let Ok(cmd) = cmd.to_cmd(map) {
+                if same_step && step_kept {
+                    grouped_with_previous.insert(commands.len());
+                }
+                commands.push(cmd);
+                step_kept = true;
+            }
+        }
         let mut edits = MapEdits {
             edits_name: self.edits_name,
             proposal_description: self.proposal_description,
             proposal_link: self.proposal_link,
-            commands: self
-                .commands
-                .into_iter()
-                .filter_map(|cmd| cmd.to_cmd(map).ok())
-                .collect(),
+            commands,
+            grouped_with_previous,
+            redo_stack: redo_stack_from_permanent(self.redo_stack, map),
             merge_zones: self.merge_zones,
 
             changed_roads: BTreeSet::new(),
             original_intersections: BTreeMap::new(),
             changed_routes: BTreeSet::new(),
+            original_route_stops: BTreeMap::new(),
         };
         edits.update_derived(map);
         edits
     }
 }
 
+/// Redoing only makes sense if every step still matches the map, so drop everything otherwise.
+fn redo_stack_from_permanent(
+    redo_stack: Vec<Vec<PermanentEditCmd>>,
+    map: &Map,
+) -> Vec<Vec<EditCmd>> {
+    redo_stack
+        .into_iter()
+        .map(|step| {
+            step.into_iter()
+                .map(|cmd| cmd.to_cmd(map))
+                .collect::<Result<Vec<EditCmd>, String>>()
+        })
+        .collect::<Result<Vec<Vec<EditCmd>>, String>>()
+        .unwrap_or_else(|_| Vec::new())
+}
+
 impl EditIntersection {
     fn to_permanent(&self, map: &Map) -> PermanentEditIntersection {
         match self {
@@ -242,3 +422,49 @@ impl PermanentEditIntersection {
         }
     }
 }
+
+impl NewBusRoute {
+    fn to_permanent(&self, map: &Map) -> PermanentNewBusRoute {
+        PermanentNewBusRoute {
+            name: self.name.clone(),
+            stops: stops_to_permanent(&self.stops, map),
+            spawn_times: self.spawn_times.clone(),
+        }
+    }
+}
+
+impl PermanentNewBusRoute {
+    fn from_permanent(self, map: &Map) -> Result<NewBusRoute, String> {
+        Ok(NewBusRoute {
+            name: self.name,
+            stops: stops_from_permanent(self.stops, map)?,
+            spawn_times: self.spawn_times,
+        })
+    }
+}
+
+fn stops_to_permanent(stops: &[BusStopID], map: &Map) -> Vec<LonLat> {
+    stops
+        .iter()
+        .map(|bs| {
+            map.get_bs(*bs)
+                .sidewalk_pos
+                .pt(map)
+                .to_gps(map.get_gps_bounds())
+        })
+        .collect()
+}
+
+fn stops_from_permanent(stops: Vec<LonLat>, map: &Map) -> Result<Vec<BusStopID>, String> {
+    stops
+        .into_iter()
+        .map(|gps| {
+            let pt = gps.to_pt(map.get_gps_bounds());
+            map.all_bus_stops()
+                .values()
+                .find(|bs| bs.sidewalk_pos.pt(map).dist_to(pt) < Distance::meters(1.0))
+                .map(|bs| bs.id)
+                .ok_or(format!("can't find a bus stop at {}", gps))
+        })
+        .collect()
+}
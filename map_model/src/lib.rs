@@ -33,28 +33,37 @@ use geom::{Bounds, Distance, GPSBounds, Polygon};
 
 pub use crate::city::City;
 pub use crate::edits::{
-    EditCmd, EditEffects, EditIntersection, EditRoad, MapEdits, PermanentMapEdits,
+    EditCmd, EditConflict, EditEffects, EditIntersection, EditRoad, EditTarget, MapEdits,
+    PermanentMapEdits,
 };
 pub use crate::map::{DrivingSide, MapConfig};
+pub use crate::objects::accessibility::{AccessBarrier, MAX_WHEELCHAIR_GRADE, MIN_SIDEWALK_WIDTH};
 pub use crate::objects::area::{Area, AreaID, AreaType};
+pub use crate::objects::bike_treatment::BikeTreatments;
 pub use crate::objects::building::{
     Amenity, Building, BuildingID, BuildingType, NamePerLanguage, OffstreetParking,
 };
-pub use crate::objects::bus_stop::{BusRoute, BusRouteID, BusStop, BusStopID};
+pub use crate::objects::bus_stop::{BusRoute, BusRouteID, BusStop, BusStopID, NewBusRoute};
+pub use crate::objects::crosswalk::Crosswalk;
+pub use crate::objects::guideway::{Guideway, GuidewayKind, MAX_STATION_DIST_FROM_SIDEWALK};
 pub use crate::objects::intersection::{Intersection, IntersectionID, IntersectionType};
 pub use crate::objects::lane::{
-    Lane, LaneID, LaneType, PARKING_LOT_SPOT_LENGTH, PARKING_SPOT_LENGTH,
+    Lane, LaneID, LaneType, TrafficStress, PARKING_LOT_SPOT_LENGTH, PARKING_SPOT_LENGTH,
 };
 pub use crate::objects::parking_lot::{ParkingLot, ParkingLotID};
-pub use crate::objects::road::{DirectedRoadID, Direction, Road, RoadID};
+pub use crate::objects::parking_zone::{ParkingRestriction, ParkingZone};
+pub use crate::objects::road::{
+    BannedTurn, DirectedRoadID, Direction, Road, RoadID, TimedSpeedLimit,
+};
 pub use crate::objects::stop_signs::{ControlStopSign, RoadWithStopSign};
+pub use crate::objects::toll_zone::TollZone;
 pub use crate::objects::traffic_signals::{ControlTrafficSignal, PhaseType, Stage};
 pub use crate::objects::turn::{
     CompressedMovementID, Movement, MovementID, Turn, TurnID, TurnPriority, TurnType,
 };
 pub use crate::objects::zone::{AccessRestrictions, Zone};
 pub use crate::pathfind::uber_turns::{IntersectionCluster, UberTurn, UberTurnGroup};
-use crate::pathfind::Pathfinder;
+use crate::pathfind::{CachedVehicleGraphs, Pathfinder};
 pub use crate::pathfind::{Path, PathConstraints, PathRequest, PathStep};
 pub use crate::traversable::{Position, Traversable};
 
@@ -64,9 +73,13 @@ mod edits;
 mod make;
 mod map;
 mod objects;
+pub mod opendrive;
 pub mod osm;
+pub mod osm_changes;
 mod pathfind;
 pub mod raw;
+pub mod signal_plans;
+mod stitch;
 mod traversable;
 
 // TODO Minimize uses of these!
@@ -108,8 +121,37 @@ pub struct Map {
 
     pathfinder: Pathfinder,
     pathfinder_dirty: bool,
+    /// Not the source of truth, just cached. Used for pathfinding while turn bans, tolls, or
+    /// other costs that depend on the time of day apply.
+    #[serde(skip_serializing, skip_deserializing)]
+    timed_pathfinding_graphs: CachedVehicleGraphs,
     // Not the source of truth, just cached.
     zones: Vec<Zone>,
+    /// Toll zones only come from map edits
+    #[serde(skip_serializing, skip_deserializing)]
+    toll_zones: Vec<TollZone>,
+    /// Not the source of truth, just cached. Turns entering each toll zone.
+    #[serde(skip_serializing, skip_deserializing)]
+    toll_entrances: BTreeMap<TurnID, usize>,
+    /// Parking zones only come from map edits
+    #[serde(skip_serializing, skip_deserializing)]
+    parking_zones: Vec<ParkingZone>,
+    /// Not the source of truth, just cached. The parking zone each road is inside.
+    #[serde(skip_serializing, skip_deserializing)]
+    parking_zone_roads: BTreeMap<RoadID, usize>,
+    /// Bike rack capacity changed by map edits. Other buildings have whatever OSM says.
+    #[serde(skip_serializing, skip_deserializing)]
+    bike_racks: BTreeMap<BuildingID, usize>,
+    /// Bike treatments only come from map edits
+    #[serde(skip_serializing, skip_deserializing)]
+    bike_treatments: BTreeMap<IntersectionID, BikeTreatments>,
+    /// Guideways only come from map edits
+    #[serde(skip_serializing, skip_deserializing)]
+    guideways: Vec<Guideway>,
+    /// Not the source of truth, just cached. Where people wait at each guideway's stations, or
+    /// nothing if some station is too far from a sidewalk.
+    #[serde(skip_serializing, skip_deserializing)]
+    guideway_stations: Vec<Vec<Position>>,
 
     name: MapName,
     #[serde(skip_serializing, skip_deserializing)]
@@ -4,13 +4,14 @@ use geom::{Bounds, Distance, FindClosest};
 use crate::{Road, RoadID};
 
 /// Look for roads underneath bridges, then lower their z-order. OSM tags bridges and tunnels, but
-/// not the roads that pass under bridges.
+/// not the roads that pass under bridges. Roads already known to be below the bridge, like tunnels
+/// or anything with a lower `layer`, are left alone.
 pub fn find_bridges(roads: &mut Vec<Road>, bounds: &Bounds, timer: &mut Timer) {
     let mut closest: FindClosest<RoadID> = FindClosest::new(bounds);
     let mut bridges = Vec::new();
     for r in roads.iter() {
         closest.add(r.id, r.center_pts.points());
-        if r.osm_tags.contains_key("bridge") {
+        if r.osm_tags.contains_key("bridge") && !r.osm_tags.is("bridge", "no") {
             bridges.push(r.id);
         }
     }
@@ -19,13 +20,19 @@ pub fn find_bridges(roads: &mut Vec<Road>, bounds: &Bounds, timer: &mut Timer) {
     for bridge in bridges {
         timer.next();
         let bridge_pts = roads[bridge.0].center_pts.clone();
+        let bridge_z = roads[bridge.0].zorder;
+        let bridge_ends = [roads[bridge.0].src_i, roads[bridge.0].dst_i];
         for (r, _, _) in closest.all_close_pts(bridge_pts.middle(), Distance::meters(500.0)) {
             if bridge != r
                 && bridge_pts != roads[r.0].center_pts
                 && bridge_pts.intersection(&roads[r.0].center_pts).is_some()
             {
-                if roads[r.0].zorder == 0 {
-                    roads[r.0].zorder = -1;
+                // Roads connected to the bridge, like the next segment of it, are on the same level
+                if roads[r.0].zorder >= bridge_z
+                    && !bridge_ends.contains(&roads[r.0].src_i)
+                    && !bridge_ends.contains(&roads[r.0].dst_i)
+                {
+                    roads[r.0].zorder = bridge_z - 1;
                 }
             }
         }
@@ -6,7 +6,7 @@ use rand_xorshift::XorShiftRng;
 use abstutil::{Tags, Timer};
 use geom::{Distance, HashablePt2D, Line, Polygon};
 
-use crate::make::match_points_to_lanes;
+use crate::make::{match_points_to_lanes, stable_ids};
 use crate::raw::RawBuilding;
 use crate::{
     osm, Amenity, Building, BuildingID, BuildingType, LaneID, Map, NamePerLanguage,
@@ -17,6 +17,7 @@ use crate::{
 pub fn make_all_buildings(
     input: &BTreeMap<osm::OsmID, RawBuilding>,
     map: &Map,
+    previous_ids: &BTreeMap<osm::OsmID, usize>,
     keep_bldg_tags: bool,
     timer: &mut Timer,
 ) -> Vec<Building> {
@@ -119,6 +120,14 @@ pub fn make_all_buildings(
         }
     }
 
+    // Buildings dropped from the middle would shift the IDs of everything after, so try to keep
+    // the IDs from before.
+    let ids = stable_ids(results.iter().map(|b| b.orig_id).collect(), previous_ids);
+    for b in &mut results {
+        b.id = BuildingID(ids[&b.orig_id]);
+    }
+    results.sort_by_key(|b| b.id);
+
     timer.note(format!(
         "Discarded {} buildings that weren't close enough to a sidewalk",
         input.len() - results.len()
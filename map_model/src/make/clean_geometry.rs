@@ -0,0 +1,272 @@
+//! OSM center-lines are often traced roughly, with zig-zags a few meters long, and ways that meet
+//! end-to-end sometimes leave a stub road only a meter or two long. Both produce strange lane and
+//! intersection geometry. This optional pass merges the stubs into their neighbors and cuts the
+//! corners off sharp bends. It only runs when `MapConfig::clean_geometry` is set, so maps imported
+//! without it stay the same. Roads tagged `osm::DONT_MERGE` by `ConsolidationHints` are left alone.
+
+use std::collections::BTreeSet;
+
+use abstutil::Timer;
+use geom::{Distance, Pt2D};
+
+use crate::make::initial::lane_specs::get_lane_specs_ltr;
+use crate::raw::{OriginalRoad, RawMap};
+use crate::{osm, IntersectionType};
+
+/// Roads shorter than this are stubs.
+const MIN_STUB_LENGTH: Distance = Distance::const_meters(3.0);
+/// Bends tighter than this radius get smoothed. Real turns inside one road are much gentler.
+const MIN_RADIUS: Distance = Distance::const_meters(5.0);
+/// How many times to cut the corners off a bend
+const MAX_SMOOTHING_PASSES: usize = 3;
+
+pub fn clean_geometry(raw: &mut RawMap, timer: &mut Timer) {
+    timer.start("clean up road geometry");
+    let (merged, removed) = merge_stubs(raw);
+    timer.note(format!(
+        "Merged {} stub roads into their neighbors and removed {} dead-end stubs",
+        merged, removed
+    ));
+
+    let mut smoothed = 0;
+    for r in raw.roads.values_mut() {
+        if r.osm_tags.is("junction", "roundabout") {
+            // Already idealized
+            continue;
+        }
+        if let Some(pts) = smooth(&r.center_points) {
+            r.center_points = pts;
+            smoothed += 1;
+        }
+    }
+    timer.note(format!("Smoothed sharp bends in {} roads", smoothed));
+    timer.stop("clean up road geometry");
+}
+
+/// Returns the number of stubs merged into a neighbor and the number of dead-end stubs removed.
+fn merge_stubs(raw: &mut RawMap) -> (usize, usize) {
+    let mut queue: BTreeSet<OriginalRoad> = raw
+        .roads
+        .iter()
+        .filter(|(_, r)| {
+            length(&r.center_points) < MIN_STUB_LENGTH && !r.osm_tags.is(osm::DONT_MERGE, "true")
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    let mut merged = 0;
+    let mut removed = 0;
+    while let Some(stub) = queue.iter().next().cloned() {
+        queue.remove(&stub);
+        if !raw.roads.contains_key(&stub) {
+            continue;
+        }
+
+        for i in vec![stub.i1, stub.i2] {
+            if raw.intersections[&i].intersection_type != IntersectionType::StopSign {
+                continue;
+            }
+            let roads = raw.roads_per_intersection(i);
+            if roads.len() == 1 {
+                // A dead-end, but only remove it if the other end still connects somewhere
+                let other_i = if stub.i1 == i { stub.i2 } else { stub.i1 };
+                if raw.roads_per_intersection(other_i).len() >= 3 && !has_bus_stop(raw, stub) {
+                    raw.roads.remove(&stub).unwrap();
+                    raw.intersections.remove(&i).unwrap();
+                    remove_references(raw, stub);
+                    removed += 1;
+                    break;
+                }
+            } else if roads.len() == 2 {
+                let other = if roads[0] == stub { roads[1] } else { roads[0] };
+                if let Some(new_id) = merge_at(raw, i, stub, other) {
+                    merged += 1;
+                    if length(&raw.roads[&new_id].center_points) < MIN_STUB_LENGTH {
+                        queue.insert(new_id);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    (merged, removed)
+}
+
+/// Joins two roads meeting end-to-end at an intersection with nothing else, removing the
+/// intersection. The longer road's tags are kept. Returns the new road's ID, or None if the roads
+/// don't match well enough to join.
+fn merge_at(
+    raw: &mut RawMap,
+    i: osm::NodeID,
+    r1: OriginalRoad,
+    r2: OriginalRoad,
+) -> Option<OriginalRoad> {
+    let (keep, stub) =
+        if length(&raw.roads[&r1].center_points) >= length(&raw.roads[&r2].center_points) {
+            (r1, r2)
+        } else {
+            (r2, r1)
+        };
+    // The roads have to continue in the same direction, with the same lanes
+    let continues = (keep.i2 == i && stub.i1 == i) || (keep.i1 == i && stub.i2 == i);
+    if !continues
+        || raw.roads[&keep].get_zorder() != raw.roads[&stub].get_zorder()
+        || raw.roads[&keep].osm_tags.is("junction", "roundabout")
+        || raw.roads[&stub].osm_tags.is("junction", "roundabout")
+        || raw.roads[&keep].osm_tags.is(osm::DONT_MERGE, "true")
+        || raw.roads[&stub].osm_tags.is(osm::DONT_MERGE, "true")
+        || !same_lanes(raw, keep, stub)
+    {
+        return None;
+    }
+
+    let keep_pts = &raw.roads[&keep].center_points;
+    let stub_pts = &raw.roads[&stub].center_points;
+    let (new_id, pts) = if keep.i2 == i {
+        let mut pts = keep_pts.clone();
+        pts.extend(stub_pts.iter().skip(1).cloned());
+        (
+            OriginalRoad {
+                osm_way_id: keep.osm_way_id,
+                i1: keep.i1,
+                i2: stub.i2,
+            },
+            pts,
+        )
+    } else {
+        let mut pts = stub_pts.clone();
+        pts.pop();
+        pts.extend(keep_pts.iter().cloned());
+        (
+            OriginalRoad {
+                osm_way_id: keep.osm_way_id,
+                i1: stub.i1,
+                i2: keep.i2,
+            },
+            pts,
+        )
+    };
+    if new_id.i1 == new_id.i2 || raw.roads.contains_key(&new_id) {
+        return None;
+    }
+
+    let stub_road = raw.roads.remove(&stub).unwrap();
+    let mut road = raw.roads.remove(&keep).unwrap();
+    road.center_points = pts;
+    road.turn_restrictions.extend(stub_road.turn_restrictions);
+    road.complicated_turn_restrictions
+        .extend(stub_road.complicated_turn_restrictions);
+    raw.roads.insert(new_id, road);
+    raw.intersections.remove(&i).unwrap();
+    rename_references(raw, keep, new_id);
+    rename_references(raw, stub, new_id);
+    Some(new_id)
+}
+
+fn same_lanes(raw: &RawMap, r1: OriginalRoad, r2: OriginalRoad) -> bool {
+    get_lane_specs_ltr(&raw.roads[&r1].osm_tags, &raw.config)
+        == get_lane_specs_ltr(&raw.roads[&r2].osm_tags, &raw.config)
+}
+
+pub(super) fn rename_references(raw: &mut RawMap, from: OriginalRoad, to: OriginalRoad) {
+    for r in raw.roads.values_mut() {
+        for (_, dst) in r.turn_restrictions.iter_mut() {
+            if *dst == from {
+                *dst = to;
+            }
+        }
+        for (via, dst) in r.complicated_turn_restrictions.iter_mut() {
+            if *via == from {
+                *via = to;
+            }
+            if *dst == from {
+                *dst = to;
+            }
+        }
+    }
+    for route in raw.bus_routes.iter_mut() {
+        for stop in route.stops.iter_mut() {
+            if let Some((ref mut r, _)) = stop.matched_road {
+                if *r == from {
+                    *r = to;
+                }
+            }
+        }
+    }
+}
+
+pub(super) fn has_bus_stop(raw: &RawMap, id: OriginalRoad) -> bool {
+    raw.bus_routes.iter().any(|route| {
+        route
+            .stops
+            .iter()
+            .any(|stop| stop.matched_road.map(|(r, _)| r == id).unwrap_or(false))
+    })
+}
+
+pub(super) fn remove_references(raw: &mut RawMap, id: OriginalRoad) {
+    for r in raw.roads.values_mut() {
+        r.turn_restrictions.retain(|(_, dst)| *dst != id);
+        r.complicated_turn_restrictions
+            .retain(|(via, dst)| *via != id && *dst != id);
+    }
+}
+
+/// Cuts the corners off any bend tighter than `MIN_RADIUS`, leaving the endpoints in place.
+/// Returns None if nothing changed.
+fn smooth(orig_pts: &Vec<Pt2D>) -> Option<Vec<Pt2D>> {
+    if orig_pts.len() < 3 {
+        return None;
+    }
+    let mut pts = orig_pts.clone();
+    let mut changed = false;
+    for _ in 0..MAX_SMOOTHING_PASSES {
+        let mut result = vec![pts[0]];
+        let mut any = false;
+        for idx in 1..pts.len() - 1 {
+            let (prev, pt, next) = (pts[idx - 1], pts[idx], pts[idx + 1]);
+            let len_in = prev.dist_to(pt);
+            let len_out = pt.dist_to(next);
+            if len_in == Distance::ZERO || len_out == Distance::ZERO {
+                result.push(pt);
+                continue;
+            }
+            let turn = prev
+                .angle_to(pt)
+                .simple_shortest_rotation_towards(pt.angle_to(next))
+                .abs()
+                .to_radians();
+            // The angle turned per distance travelled approximates 1 / radius
+            let curvature = turn / ((len_in + len_out) / 2.0).inner_meters();
+            if curvature > 1.0 / MIN_RADIUS.inner_meters() {
+                result.push(lerp(pt, prev, 0.25));
+                result.push(lerp(pt, next, 0.25));
+                any = true;
+            } else {
+                result.push(pt);
+            }
+        }
+        result.push(*pts.last().unwrap());
+        pts = result;
+        if !any {
+            break;
+        }
+        changed = true;
+    }
+    if changed {
+        Some(pts)
+    } else {
+        None
+    }
+}
+
+fn lerp(from: Pt2D, to: Pt2D, pct: f64) -> Pt2D {
+    Pt2D::new(
+        from.x() + (to.x() - from.x()) * pct,
+        from.y() + (to.y() - from.y()) * pct,
+    )
+}
+
+fn length(pts: &Vec<Pt2D>) -> Distance {
+    pts.windows(2)
+        .fold(Distance::ZERO, |sum, pair| sum + pair[0].dist_to(pair[1]))
+}
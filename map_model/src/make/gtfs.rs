@@ -0,0 +1,238 @@
+//! Read published transit schedules from a GTFS feed
+//! (<https://developers.google.com/transit/gtfs/reference>), replacing the synthesized spawn
+//! times of bus and train routes. Only departure times are imported; routes keep the stops and
+//! paths from OpenStreetMap, not GTFS stop sequences or shapes.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::File;
+
+use serde::Deserialize;
+
+use abstutil::{MultiMap, Timer};
+use geom::{Distance, Duration, LonLat, Pt2D, Time};
+
+use crate::{BusRouteID, Map};
+
+impl Map {
+    /// Read routes.txt, trips.txt, and stop_times.txt from a GTFS directory. Each trip's first
+    /// departure becomes a spawn time for the matching route. Routes are matched first by
+    /// `gtfs:trip_marker` against the trip's shape, then by short name. When several routes share
+    /// a short name, stops.txt is used to pick the one with the nearest first and last stops, and
+    /// trips that still can't be told apart are skipped. Routes without any matching trips keep
+    /// their old spawn times. Stops and paths aren't changed; the feed's stop sequences are only
+    /// used to match trips to routes, and shapes.txt isn't read at all. Returns the number of
+    /// routes updated.
+    pub fn import_gtfs_schedules(
+        &mut self,
+        dir: &str,
+        timer: &mut Timer,
+    ) -> Result<usize, Box<dyn Error>> {
+        let mut marker_to_route: BTreeMap<String, BusRouteID> = BTreeMap::new();
+        let mut short_name_to_routes: MultiMap<String, BusRouteID> = MultiMap::new();
+        for br in self.all_bus_routes() {
+            if let Some(ref m) = br.gtfs_trip_marker {
+                // Dunno what the :0 thing is
+                marker_to_route.insert(m.split(':').next().unwrap().to_string(), br.id);
+            }
+            short_name_to_routes.insert(br.short_name.clone(), br.id);
+        }
+
+        let mut gtfs_route_to_short_name: HashMap<String, String> = HashMap::new();
+        for rec in
+            csv::Reader::from_reader(File::open(format!("{}/routes.txt", dir))?).deserialize()
+        {
+            let rec: RouteRecord = rec?;
+            if let Some(name) = rec.route_short_name {
+                gtfs_route_to_short_name.insert(rec.route_id, name);
+            }
+        }
+
+        // Figure out which of our routes each GTFS trip might belong to
+        let mut trip_to_routes: HashMap<String, Vec<BusRouteID>> = HashMap::new();
+        for rec in csv::Reader::from_reader(File::open(format!("{}/trips.txt", dir))?).deserialize()
+        {
+            let rec: TripRecord = rec?;
+            let routes = if let Some(br) = rec
+                .shape_id
+                .as_ref()
+                .and_then(|shape| marker_to_route.get(shape))
+            {
+                vec![*br]
+            } else if let Some(name) = gtfs_route_to_short_name.get(&rec.route_id) {
+                short_name_to_routes
+                    .get(name.clone())
+                    .iter()
+                    .cloned()
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            if !routes.is_empty() {
+                trip_to_routes.insert(rec.trip_id, routes);
+            }
+        }
+
+        // The earliest departure of every trip is when a vehicle should spawn. Also remember the
+        // first and last stop, to tell apart routes sharing a short name.
+        let mut trips: HashMap<String, TripStops> = HashMap::new();
+        for rec in
+            csv::Reader::from_reader(File::open(format!("{}/stop_times.txt", dir))?).deserialize()
+        {
+            let rec: StopTimeRecord = rec?;
+            if !trip_to_routes.contains_key(&rec.trip_id) {
+                continue;
+            }
+            let stop = (rec.stop_sequence, rec.stop_id);
+            let trip = trips.entry(rec.trip_id).or_insert_with(|| TripStops {
+                departure: None,
+                first: stop.clone(),
+                last: stop.clone(),
+            });
+            if stop.0 < trip.first.0 {
+                trip.first = stop.clone();
+            }
+            if stop.0 > trip.last.0 {
+                trip.last = stop;
+            }
+            // Intermediate stops may not have times filled out
+            let raw_time = match rec.departure_time.or(rec.arrival_time) {
+                Some(t) if !t.trim().is_empty() => t,
+                _ => continue,
+            };
+            let time = Time::parse(raw_time.trim())?;
+            if trip.departure.map(|t| time < t).unwrap_or(true) {
+                trip.departure = Some(time);
+            }
+        }
+
+        let stops = if trip_to_routes.values().any(|routes| routes.len() > 1) {
+            self.read_gtfs_stops(dir)?
+        } else {
+            HashMap::new()
+        };
+        let mut ambiguous = 0;
+        let mut schedules: BTreeMap<BusRouteID, Vec<Time>> = BTreeMap::new();
+        for (trip_id, trip) in trips {
+            let mut time = match trip.departure {
+                Some(t) => t,
+                None => continue,
+            };
+            let br = match self.pick_gtfs_route(&trip_to_routes[&trip_id], &trip, &stops) {
+                Some(br) => br,
+                None => {
+                    ambiguous += 1;
+                    continue;
+                }
+            };
+            // Trips after midnight are expressed as times past 24:00:00. We only simulate one day,
+            // so wrap these around.
+            // TODO Maybe we should duplicate these to handle beginning and end of the simulation
+            while time >= Time::START_OF_DAY + Duration::hours(24) {
+                time = time - Duration::hours(24);
+            }
+            schedules.entry(br).or_insert_with(Vec::new).push(time);
+        }
+        if ambiguous > 0 {
+            timer.warn(format!(
+                "Skipped {} GTFS trips that could belong to several routes with the same short \
+                 name",
+                ambiguous
+            ));
+        }
+
+        let num_routes = schedules.len();
+        for (br, mut times) in schedules {
+            times.sort();
+            times.dedup();
+            timer.note(format!(
+                "{} has {} departures from GTFS",
+                self.get_br(br).full_name,
+                times.len()
+            ));
+            self.hack_override_orig_spawn_times(br, times);
+        }
+        Ok(num_routes)
+    }
+
+    fn read_gtfs_stops(&self, dir: &str) -> Result<HashMap<String, Pt2D>, Box<dyn Error>> {
+        let mut stops = HashMap::new();
+        for rec in csv::Reader::from_reader(File::open(format!("{}/stops.txt", dir))?).deserialize()
+        {
+            let rec: StopRecord = rec?;
+            stops.insert(
+                rec.stop_id,
+                LonLat::new(rec.stop_lon, rec.stop_lat).to_pt(self.get_gps_bounds()),
+            );
+        }
+        Ok(stops)
+    }
+
+    /// Routes in opposite directions usually share a short name. Pick the one whose first and
+    /// last stops are closest to the trip's. None if that's a tie or the stops are unknown.
+    fn pick_gtfs_route(
+        &self,
+        routes: &Vec<BusRouteID>,
+        trip: &TripStops,
+        stops: &HashMap<String, Pt2D>,
+    ) -> Option<BusRouteID> {
+        if routes.len() == 1 {
+            return Some(routes[0]);
+        }
+        let first = stops.get(&trip.first.1)?;
+        let last = stops.get(&trip.last.1)?;
+        let mut costs: Vec<(Distance, BusRouteID)> = routes
+            .iter()
+            .map(|br| {
+                let route = self.get_br(*br);
+                let route_first = self.get_bs(route.stops[0]).driving_pos.pt(self);
+                let route_last = self
+                    .get_bs(*route.stops.last().unwrap())
+                    .driving_pos
+                    .pt(self);
+                (first.dist_to(route_first) + last.dist_to(route_last), *br)
+            })
+            .collect();
+        costs.sort_by_key(|(cost, _)| *cost);
+        if costs[0].0 == costs[1].0 {
+            return None;
+        }
+        Some(costs[0].1)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RouteRecord {
+    route_id: String,
+    route_short_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TripRecord {
+    route_id: String,
+    trip_id: String,
+    shape_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StopTimeRecord {
+    trip_id: String,
+    arrival_time: Option<String>,
+    departure_time: Option<String>,
+    stop_id: String,
+    stop_sequence: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct StopRecord {
+    stop_id: String,
+    stop_lat: f64,
+    stop_lon: f64,
+}
+
+struct TripStops {
+    departure: Option<Time>,
+    /// (stop_sequence, stop_id)
+    first: (usize, String),
+    last: (usize, String),
+}
@@ -21,7 +21,7 @@ fn fwd(lt: LaneType) -> LaneSpec {
         lt,
         dir: Direction::Fwd,
         width: match lt {
-            LaneType::Sidewalk => SIDEWALK_THICKNESS,
+            LaneType::Sidewalk | LaneType::Footway => SIDEWALK_THICKNESS,
             LaneType::Shoulder => SHOULDER_THICKNESS,
             _ => NORMAL_LANE_THICKNESS,
         },
@@ -33,7 +33,7 @@ fn back(lt: LaneType) -> LaneSpec {
         lt,
         dir: Direction::Back,
         width: match lt {
-            LaneType::Sidewalk => SIDEWALK_THICKNESS,
+            LaneType::Sidewalk | LaneType::Footway => SIDEWALK_THICKNESS,
             LaneType::Shoulder => SHOULDER_THICKNESS,
             _ => NORMAL_LANE_THICKNESS,
         },
@@ -45,11 +45,24 @@ pub fn get_lane_specs_ltr(tags: &Tags, cfg: &MapConfig) -> Vec<LaneSpec> {
     if tags.is_any("railway", vec!["light_rail", "rail"]) {
         return vec![fwd(LaneType::LightRail)];
     }
-    if tags.is_any(
-        osm::HIGHWAY,
-        vec!["cycleway", "footway", "path", "pedestrian", "steps"],
-    ) {
-        return vec![fwd(LaneType::Sidewalk)];
+    // Off-road paths. Cycle tracks and shared-use paths get a bike lane in each direction (unless
+    // they're oneway), plus a footway alongside if pedestrians are allowed. Keeping the bike lanes
+    // and footway separate means bikes and pedestrians get their own turns.
+    let bikes_allowed = tags.is(osm::HIGHWAY, "cycleway")
+        || (tags.is(osm::HIGHWAY, "path") && tags.is_any("bicycle", vec!["yes", "designated"]));
+    if bikes_allowed {
+        let mut fwd_side = vec![fwd(LaneType::Biking)];
+        let mut back_side = Vec::new();
+        if !tags.is("oneway", "yes") {
+            back_side.push(back(LaneType::Biking));
+        }
+        if tags.is(osm::HIGHWAY, "path") || tags.is_any("foot", vec!["yes", "designated"]) {
+            fwd_side.push(fwd(LaneType::Footway));
+        }
+        return assemble_ltr(fwd_side, back_side, cfg.driving_side);
+    }
+    if tags.is_any(osm::HIGHWAY, vec!["footway", "path", "pedestrian", "steps"]) {
+        return vec![fwd(LaneType::Footway)];
     }
 
     // TODO Reversible roads should be handled differently?
@@ -321,6 +334,7 @@ mod tests {
             LaneType::SharedLeftTurn => "C",
             LaneType::Construction => "x",
             LaneType::LightRail => "l",
+            LaneType::Footway => "f",
         }
     }
 
@@ -432,11 +446,27 @@ mod tests {
                 "sdd",
                 "^^^",
             ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:highway%3Dcycleway",
+                vec!["highway=cycleway", "foot=designated"],
+                DrivingSide::Right,
+                "bbf",
+                "v^^",
+            ),
+            (
+                "https://wiki.openstreetmap.org/wiki/Tag:highway%3Dfootway",
+                vec!["highway=footway"],
+                DrivingSide::Right,
+                "f",
+                "^",
+            ),
         ] {
             let cfg = MapConfig {
                 driving_side,
                 bikes_can_use_bus_lanes: true,
+                bikes_avoid_stress: false,
                 inferred_sidewalks: true,
+                clean_geometry: false,
             };
             let actual = get_lane_specs_ltr(&tags(input.clone()), &cfg);
             let actual_lt = actual
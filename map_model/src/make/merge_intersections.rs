@@ -0,0 +1,151 @@
+//! Merging nearby intersections by hand. OSM often maps one real junction as a few intersections
+//! joined by very short roads, like where a divided road crosses another one. Guessing which of
+//! these to collapse goes wrong in both directions, so `ConsolidationHints` record the answer per
+//! map, and map_editor can mark pairs and preview the result.
+
+use std::collections::BTreeMap;
+
+use abstutil::Timer;
+use geom::Pt2D;
+
+use crate::make::clean_geometry::{has_bus_stop, remove_references, rename_references};
+use crate::raw::{ConsolidationHints, OriginalRoad, RawMap};
+use crate::{osm, IntersectionType};
+
+impl RawMap {
+    /// Merges and tags roads following the hints. Hints that no longer apply, like for
+    /// intersections that don't exist anymore, are skipped with a warning.
+    pub fn apply_consolidation_hints(&mut self, hints: &ConsolidationHints, timer: &mut Timer) {
+        // Merging a with b and then b with c should work
+        let mut merged_into: BTreeMap<osm::NodeID, osm::NodeID> = BTreeMap::new();
+        let resolve = |merged_into: &BTreeMap<osm::NodeID, osm::NodeID>, mut i: osm::NodeID| {
+            while let Some(next) = merged_into.get(&i) {
+                i = *next;
+            }
+            i
+        };
+
+        let mut merged = 0;
+        for (i1, i2) in &hints.merge {
+            let i1 = resolve(&merged_into, *i1);
+            let i2 = resolve(&merged_into, *i2);
+            if i1 == i2 {
+                continue;
+            }
+            match self.merge_intersections(i1, i2) {
+                Ok(_) => {
+                    merged_into.insert(i2, i1);
+                    merged += 1;
+                }
+                Err(err) => {
+                    timer.warn(format!("Can't merge {} and {}: {}", i1, i2, err));
+                }
+            }
+        }
+
+        let mut kept = 0;
+        for (i1, i2) in &hints.dont_merge {
+            let roads = self.roads_between(*i1, *i2);
+            if roads.is_empty() {
+                timer.warn(format!(
+                    "Can't keep {} and {} apart, because no road connects them",
+                    i1, i2
+                ));
+            }
+            for r in roads {
+                self.roads
+                    .get_mut(&r)
+                    .unwrap()
+                    .osm_tags
+                    .insert(osm::DONT_MERGE, "true");
+                kept += 1;
+            }
+        }
+
+        timer.note(format!(
+            "Merged {} pairs of intersections and kept {} roads from merging, following hints",
+            merged, kept
+        ));
+    }
+
+    /// Collapses the roads between two intersections, moving `keep` halfway to `remove` and giving
+    /// it all of their other roads. `remove` is deleted. Returns the deleted roads and the new IDs
+    /// of all roads now touching `keep`. Nothing changes if this fails.
+    pub fn merge_intersections(
+        &mut self,
+        keep: osm::NodeID,
+        remove: osm::NodeID,
+    ) -> Result<(Vec<OriginalRoad>, Vec<OriginalRoad>), String> {
+        for i in vec![keep, remove] {
+            match self.intersections.get(&i) {
+                Some(intersection) => {
+                    if intersection.intersection_type == IntersectionType::Border {
+                        return Err(format!("{} is a border", i));
+                    }
+                }
+                None => {
+                    return Err(format!("{} doesn't exist", i));
+                }
+            }
+        }
+        let short_roads = self.roads_between(keep, remove);
+        if short_roads.is_empty() {
+            return Err("no road connects them".to_string());
+        }
+        if short_roads.iter().any(|r| has_bus_stop(self, *r)) {
+            return Err("a bus stop is on the road between them".to_string());
+        }
+
+        // Work out the new IDs first, so nothing changes if one would clash
+        let mut renames = Vec::new();
+        for r in self.roads_per_intersection(remove) {
+            if short_roads.contains(&r) {
+                continue;
+            }
+            let new_id = OriginalRoad {
+                osm_way_id: r.osm_way_id,
+                i1: if r.i1 == remove { keep } else { r.i1 },
+                i2: if r.i2 == remove { keep } else { r.i2 },
+            };
+            if self.roads.contains_key(&new_id) || renames.iter().any(|(_, id)| *id == new_id) {
+                return Err(format!(
+                    "{} would become {}, which already exists",
+                    r, new_id
+                ));
+            }
+            renames.push((r, new_id));
+        }
+
+        for r in &short_roads {
+            self.roads.remove(r).unwrap();
+            remove_references(self, *r);
+        }
+        let removed = self.intersections.remove(&remove).unwrap();
+        let point = {
+            let i = self.intersections.get_mut(&keep).unwrap();
+            if removed.intersection_type == IntersectionType::TrafficSignal {
+                i.intersection_type = IntersectionType::TrafficSignal;
+            }
+            i.point = Pt2D::center(&vec![i.point, removed.point]);
+            i.point
+        };
+        for (from, to) in renames {
+            let road = self.roads.remove(&from).unwrap();
+            self.roads.insert(to, road);
+            rename_references(self, from, to);
+        }
+        for route in &mut self.bus_routes {
+            for (i, pt) in &mut route.all_pts {
+                if *i == remove || *i == keep {
+                    *i = keep;
+                    *pt = point;
+                }
+            }
+            route.all_pts.dedup_by_key(|(i, _)| *i);
+        }
+
+        // Stretch every road to the new point
+        let changed = self.move_intersection(keep, point).unwrap();
+        Ok((short_roads, changed))
+    }
+}
@@ -6,34 +6,71 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use abstutil::{Parallelism, Tags, Timer};
 use geom::{Bounds, Distance, FindClosest, HashablePt2D, Speed, EPSILON_DIST};
 
-use crate::pathfind::Pathfinder;
+use crate::pathfind::{CachedVehicleGraphs, Pathfinder};
 use crate::raw::{OriginalRoad, RawMap};
 use crate::{
     connectivity, osm, AccessRestrictions, Area, AreaID, AreaType, ControlStopSign,
-    ControlTrafficSignal, Direction, Intersection, IntersectionID, IntersectionType, Lane, LaneID,
-    Map, MapEdits, Movement, PathConstraints, Position, Road, RoadID, Zone,
+    ControlTrafficSignal, Crosswalk, Direction, Intersection, IntersectionID, IntersectionType,
+    Lane, LaneID, Map, MapEdits, Movement, PathConstraints, Position, Road, RoadID, Zone,
 };
 
 mod bridges;
 mod buildings;
+mod clean_geometry;
+mod gtfs;
 pub mod initial;
 mod medians;
+mod merge_intersections;
 mod parking_lots;
 mod remove_disconnected;
+mod roundabouts;
 pub mod traffic_signals;
-mod transit;
+pub mod transit;
 pub mod turns;
 mod walking_turns;
 
 impl Map {
     pub fn create_from_raw(
+        raw: RawMap,
+        build_ch: bool,
+        keep_bldg_tags: bool,
+        timer: &mut Timer,
+    ) -> Map {
+        Map::create(raw, PreviousIDs::default(), build_ch, keep_bldg_tags, timer)
+    }
+
+    /// Like `create_from_raw`, but for re-importing a map after its OSM data changes. Roads,
+    /// intersections, and buildings that still exist keep their IDs from `previous` where
+    /// possible, so existing scenarios and edits referring to them stay valid.
+    pub fn create_from_raw_preserving_ids(
+        raw: RawMap,
+        previous: &Map,
+        build_ch: bool,
+        keep_bldg_tags: bool,
+        timer: &mut Timer,
+    ) -> Map {
+        Map::create(
+            raw,
+            PreviousIDs::new(previous),
+            build_ch,
+            keep_bldg_tags,
+            timer,
+        )
+    }
+
+    fn create(
         mut raw: RawMap,
+        previous: PreviousIDs,
         build_ch: bool,
         keep_bldg_tags: bool,
         timer: &mut Timer,
     ) -> Map {
         // Better to defer this and see RawMaps with more debug info in map_editor
         remove_disconnected::remove_disconnected_roads(&mut raw, timer);
+        roundabouts::idealize_roundabouts(&mut raw, timer);
+        if raw.config.clean_geometry {
+            clean_geometry::clean_geometry(&mut raw, timer);
+        }
 
         timer.start("raw_map to InitialMap");
         let gps_bounds = raw.gps_bounds.clone();
@@ -52,6 +89,14 @@ impl Map {
             areas: Vec::new(),
             parking_lots: Vec::new(),
             zones: Vec::new(),
+            toll_zones: Vec::new(),
+            toll_entrances: BTreeMap::new(),
+            parking_zones: Vec::new(),
+            parking_zone_roads: BTreeMap::new(),
+            bike_racks: BTreeMap::new(),
+            bike_treatments: BTreeMap::new(),
+            guideways: Vec::new(),
+            guideway_stations: Vec::new(),
             boundary_polygon: raw.boundary_polygon.clone(),
             stop_signs: BTreeMap::new(),
             traffic_signals: BTreeMap::new(),
@@ -60,20 +105,34 @@ impl Map {
             config: raw.config.clone(),
             pathfinder: Pathfinder::Dijkstra,
             pathfinder_dirty: false,
+            timed_pathfinding_graphs: CachedVehicleGraphs::default(),
             name: raw.name.clone(),
             edits: MapEdits::new(),
         };
         map.edits = map.new_edits();
 
-        let road_id_mapping: BTreeMap<OriginalRoad, RoadID> = initial_map
-            .roads
-            .keys()
-            .enumerate()
-            .map(|(idx, id)| (*id, RoadID(idx)))
-            .collect();
-        let mut intersection_id_mapping: BTreeMap<osm::NodeID, IntersectionID> = BTreeMap::new();
-        for (idx, i) in initial_map.intersections.values().enumerate() {
-            let id = IntersectionID(idx);
+        let road_id_mapping: BTreeMap<OriginalRoad, RoadID> =
+            stable_ids(initial_map.roads.keys().cloned().collect(), &previous.roads)
+                .into_iter()
+                .map(|(id, idx)| (id, RoadID(idx)))
+                .collect();
+        let intersection_id_mapping: BTreeMap<osm::NodeID, IntersectionID> = stable_ids(
+            initial_map.intersections.keys().cloned().collect(),
+            &previous.intersections,
+        )
+        .into_iter()
+        .map(|(id, idx)| (id, IntersectionID(idx)))
+        .collect();
+
+        // Objects are stored in a Vec indexed by their ID, so push them in ID order.
+        let mut intersections_in_order: Vec<&initial::Intersection> =
+            initial_map.intersections.values().collect();
+        intersections_in_order.sort_by_key(|i| intersection_id_mapping[&i.id]);
+        let mut roads_in_order: Vec<&initial::Road> = initial_map.roads.values().collect();
+        roads_in_order.sort_by_key(|r| road_id_mapping[&r.id]);
+
+        for i in intersections_in_order {
+            let id = intersection_id_mapping[&i.id];
             map.intersections.push(Intersection {
                 id,
                 polygon: i.polygon.clone(),
@@ -85,12 +144,12 @@ impl Map {
                 incoming_lanes: Vec::new(),
                 outgoing_lanes: Vec::new(),
                 roads: i.roads.iter().map(|id| road_id_mapping[id]).collect(),
+                crosswalks: Vec::new(),
             });
-            intersection_id_mapping.insert(i.id, id);
         }
 
-        timer.start_iter("expand roads to lanes", initial_map.roads.len());
-        for r in initial_map.roads.values() {
+        timer.start_iter("expand roads to lanes", roads_in_order.len());
+        for r in roads_in_order {
             timer.next();
 
             let road_id = road_id_mapping[&r.id];
@@ -131,19 +190,12 @@ impl Map {
                 src_i: i1,
                 dst_i: i2,
                 speed_limit: Speed::ZERO,
-                zorder: if let Some(layer) = raw.roads[&r.id].osm_tags.get("layer") {
-                    match layer.parse::<f64>() {
-                        // Just drop .5 for now
-                        Ok(l) => l as isize,
-                        Err(_) => {
-                            warn!("Weird layer={} on {}", layer, r.id);
-                            0
-                        }
-                    }
-                } else {
-                    0
-                },
+                zorder: raw.roads[&r.id].get_zorder(),
                 access_restrictions: AccessRestrictions::new(),
+                modal_filter: false,
+                banned_turns: Vec::new(),
+                timed_speed_limits: Vec::new(),
+                deliveries_per_hour: 0,
             };
             road.speed_limit = road.speed_limit_from_osm();
             road.access_restrictions = road.access_restrictions_from_osm();
@@ -252,6 +304,9 @@ impl Map {
             }
             map.turns.insert(t.id, t);
         }
+        for idx in 0..map.intersections.len() {
+            map.intersections[idx].crosswalks = Crosswalk::make_all(&map, &map.intersections[idx]);
+        }
 
         timer.start("find blackholes");
         for l in connectivity::find_scc(&map, PathConstraints::Car).1 {
@@ -262,7 +317,13 @@ impl Map {
         }
         timer.stop("find blackholes");
 
-        map.buildings = buildings::make_all_buildings(&raw.buildings, &map, keep_bldg_tags, timer);
+        map.buildings = buildings::make_all_buildings(
+            &raw.buildings,
+            &map,
+            &previous.buildings,
+            keep_bldg_tags,
+            timer,
+        );
 
         map.parking_lots = parking_lots::make_all_parking_lots(
             &raw.parking_lots,
@@ -402,3 +463,57 @@ fn match_points_to_lanes<F: Fn(&Lane) -> bool>(
         .flatten()
         .collect()
 }
+
+/// The IDs of objects in a previous version of a map, keyed by their original OSM ID.
+#[derive(Default)]
+struct PreviousIDs {
+    roads: BTreeMap<OriginalRoad, usize>,
+    intersections: BTreeMap<osm::NodeID, usize>,
+    buildings: BTreeMap<osm::OsmID, usize>,
+}
+
+impl PreviousIDs {
+    fn new(map: &Map) -> PreviousIDs {
+        PreviousIDs {
+            roads: map
+                .all_roads()
+                .iter()
+                .map(|r| (r.orig_id, r.id.0))
+                .collect(),
+            intersections: map
+                .all_intersections()
+                .iter()
+                .map(|i| (i.orig_id, i.id.0))
+                .collect(),
+            buildings: map
+                .all_buildings()
+                .iter()
+                .map(|b| (b.orig_id, b.id.0))
+                .collect(),
+        }
+    }
+}
+
+/// Assigns every key an index from 0 to the number of keys. Keys that had an index in `previous`
+/// keep it when it's still in range, and the rest fill in the gaps, in order.
+fn stable_ids<K: Ord>(keys: Vec<K>, previous: &BTreeMap<K, usize>) -> BTreeMap<K, usize> {
+    let mut taken = vec![false; keys.len()];
+    let mut result = BTreeMap::new();
+    let mut leftover = Vec::new();
+    for key in keys {
+        match previous.get(&key) {
+            Some(idx) if *idx < taken.len() && !taken[*idx] => {
+                taken[*idx] = true;
+                result.insert(key, *idx);
+            }
+            _ => {
+                leftover.push(key);
+            }
+        }
+    }
+    let mut free = (0..taken.len()).filter(|idx| !taken[*idx]);
+    for key in leftover {
+        result.insert(key, free.next().unwrap());
+    }
+    result
+}
@@ -0,0 +1,140 @@
+//! OSM maps a roundabout as a ring of one-way ways, split wherever another road joins. Traced from
+//! imagery, the ring is rarely round, and the short segments between entries produce messy
+//! intersection geometry. Before any geometry is calculated, snap each roundabout onto a circle.
+
+use std::collections::BTreeSet;
+
+use abstutil::Timer;
+use geom::{Angle, Distance, Pt2D};
+
+use crate::raw::{OriginalRoad, RawMap};
+
+// Anything outside this range probably isn't a real roundabout, or was mapped strangely.
+const MIN_RADIUS: Distance = Distance::const_meters(5.0);
+const MAX_RADIUS: Distance = Distance::const_meters(100.0);
+// How finely to trace the arc of each circulating road
+const DEGREES_PER_SEGMENT: f64 = 10.0;
+
+pub fn idealize_roundabouts(raw: &mut RawMap, timer: &mut Timer) {
+    let mut remaining: BTreeSet<OriginalRoad> = raw
+        .roads
+        .iter()
+        .filter(|(_, r)| r.osm_tags.is("junction", "roundabout"))
+        .map(|(id, _)| *id)
+        .collect();
+
+    let mut count = 0;
+    while let Some(start) = remaining.iter().next().cloned() {
+        remaining.remove(&start);
+        // Follow the one-way ring until it closes
+        let mut ring = vec![start];
+        while ring.last().unwrap().i2 != start.i1 {
+            let current = *ring.last().unwrap();
+            match remaining.iter().find(|r| r.i1 == current.i2).cloned() {
+                Some(next) => {
+                    remaining.remove(&next);
+                    ring.push(next);
+                }
+                None => {
+                    break;
+                }
+            }
+        }
+        if ring.last().unwrap().i2 != start.i1 {
+            timer.warn(format!(
+                "Roundabout starting with {} doesn't form a closed ring",
+                start
+            ));
+            continue;
+        }
+
+        match idealize(raw, &ring) {
+            Ok(()) => {
+                count += 1;
+            }
+            Err(err) => {
+                timer.warn(format!(
+                    "Not idealizing roundabout starting with {}: {}",
+                    start, err
+                ));
+            }
+        }
+    }
+    timer.note(format!("Idealized the geometry of {} roundabouts", count));
+}
+
+/// The ring is given in the direction of travel.
+fn idealize(raw: &mut RawMap, ring: &Vec<OriginalRoad>) -> Result<(), String> {
+    if ring.len() < 3 {
+        return Err(format!("only {} roads in the ring", ring.len()));
+    }
+    let orig_pts: Vec<Pt2D> = ring
+        .iter()
+        .map(|r| raw.intersections[&r.i1].point)
+        .collect();
+    let center = Pt2D::center(&orig_pts);
+    let radius = orig_pts
+        .iter()
+        .map(|pt| center.dist_to(*pt))
+        .fold(Distance::ZERO, |a, b| a + b)
+        / (orig_pts.len() as f64);
+    if radius < MIN_RADIUS || radius > MAX_RADIUS {
+        return Err(format!("radius of {} is unreasonable", radius));
+    }
+
+    // Which way does traffic circulate? Use the sign of the ring's area.
+    let mut signed_area = 0.0;
+    for (pt1, pt2) in orig_pts.iter().zip(orig_pts.iter().cycle().skip(1)) {
+        signed_area += pt1.x() * pt2.y() - pt2.x() * pt1.y();
+    }
+    let increasing_angles = signed_area > 0.0;
+
+    // Move each intersection onto the circle, dragging along the ends of every road touching it
+    for r in ring {
+        let i = r.i1;
+        let angle = center.angle_to(raw.intersections[&i].point);
+        let pt = center.project_away(radius, angle);
+        raw.intersections.get_mut(&i).unwrap().point = pt;
+        for (id, road) in raw.roads.iter_mut() {
+            if id.i1 == i {
+                road.center_points[0] = pt;
+                if road.center_points.len() > 2
+                    && road.center_points[1].approx_eq(pt, radius / 10.0)
+                {
+                    road.center_points.remove(1);
+                }
+            }
+            if id.i2 == i {
+                let last = road.center_points.len() - 1;
+                road.center_points[last] = pt;
+                if last > 1 && road.center_points[last - 1].approx_eq(pt, radius / 10.0) {
+                    road.center_points.remove(last - 1);
+                }
+            }
+        }
+    }
+
+    // Then trace each circulating road along the circle
+    for r in ring {
+        let pt1 = raw.intersections[&r.i1].point;
+        let pt2 = raw.intersections[&r.i2].point;
+        let start = center.angle_to(pt1).normalized_degrees();
+        let end = center.angle_to(pt2).normalized_degrees();
+        let sweep = if increasing_angles {
+            (end - start).rem_euclid(360.0)
+        } else {
+            -(start - end).rem_euclid(360.0)
+        };
+        let segments = ((sweep.abs() / DEGREES_PER_SEGMENT).ceil() as usize).max(1);
+
+        let mut pts = vec![pt1];
+        for idx in 1..segments {
+            let degrees = start + sweep * (idx as f64) / (segments as f64);
+            pts.push(center.project_away(radius, Angle::degrees(degrees)));
+        }
+        pts.push(pt2);
+        raw.roads.get_mut(r).unwrap().center_points = pts;
+    }
+
+    Ok(())
+}
@@ -4,8 +4,8 @@ use abstutil::Timer;
 use geom::Duration;
 
 use crate::{
-    ControlTrafficSignal, IntersectionCluster, IntersectionID, Map, Movement, MovementID,
-    PhaseType, RoadID, Stage, TurnPriority, TurnType,
+    ControlTrafficSignal, DrivingSide, IntersectionCluster, IntersectionID, Map, Movement,
+    MovementID, PhaseType, RoadID, Stage, TurnPriority, TurnType,
 };
 
 /// Applies a bunch of heuristics to a single intersection, returning the valid results in
@@ -142,6 +142,7 @@ fn degenerate(map: &Map, i: IntersectionID) -> Option<ControlTrafficSignal> {
     let mut ts = new(i, map);
     make_stages(
         &mut ts,
+        map.config.driving_side,
         vec![vec![(vec![r1, r2], TurnType::Straight, PROTECTED)]],
     );
     Some(ts)
@@ -189,6 +190,7 @@ fn three_way(map: &Map, i: IntersectionID) -> Option<ControlTrafficSignal> {
     // Two-stage with no protected lefts, right turn on red, turning cars yield to peds
     make_stages(
         &mut ts,
+        map.config.driving_side,
         vec![
             vec![
                 (vec![north, south], TurnType::Straight, PROTECTED),
@@ -224,6 +226,7 @@ fn four_way_four_stage(map: &Map, i: IntersectionID) -> Option<ControlTrafficSig
     let mut ts = new(i, map);
     make_stages(
         &mut ts,
+        map.config.driving_side,
         vec![
             vec![
                 (vec![north, south], TurnType::Straight, PROTECTED),
@@ -257,6 +260,7 @@ fn four_way_two_stage(map: &Map, i: IntersectionID) -> Option<ControlTrafficSign
     let mut ts = new(i, map);
     make_stages(
         &mut ts,
+        map.config.driving_side,
         vec![
             vec![
                 (vec![north, south], TurnType::Straight, PROTECTED),
@@ -296,6 +300,7 @@ fn four_oneways(map: &Map, i: IntersectionID) -> Option<ControlTrafficSignal> {
     let mut ts = new(i, map);
     make_stages(
         &mut ts,
+        map.config.driving_side,
         vec![
             vec![
                 (vec![r1], TurnType::Straight, PROTECTED),
@@ -385,6 +390,7 @@ const YIELD: bool = false;
 
 fn make_stages(
     ts: &mut ControlTrafficSignal,
+    driving_side: DrivingSide,
     stage_specs: Vec<Vec<(Vec<RoadID>, TurnType, bool)>>,
 ) {
     for specs in stage_specs {
@@ -392,7 +398,22 @@ fn make_stages(
 
         for (roads, turn_type, protected) in specs.into_iter() {
             for movement in ts.movements.values() {
-                if !roads.contains(&movement.id.from.id) || turn_type != movement.turn_type {
+                // U-turns happen alongside left turns, but never get protected.
+                let (mut movement_type, protected) = if movement.turn_type == TurnType::UTurn {
+                    (TurnType::Left, false)
+                } else {
+                    (movement.turn_type, protected)
+                };
+                // The specs are written for driving on the right. When driving on the left, it's
+                // right turns that cross oncoming traffic, so swap them.
+                if driving_side == DrivingSide::Left {
+                    movement_type = match movement_type {
+                        TurnType::Left => TurnType::Right,
+                        TurnType::Right => TurnType::Left,
+                        x => x,
+                    };
+                }
+                if !roads.contains(&movement.id.from.id) || turn_type != movement_type {
                     continue;
                 }
 
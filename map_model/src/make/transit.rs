@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 use abstutil::Timer;
 use geom::{Distance, Duration, FindClosest, HashablePt2D, Time};
@@ -6,7 +6,8 @@ use geom::{Distance, Duration, FindClosest, HashablePt2D, Time};
 use crate::make::match_points_to_lanes;
 use crate::raw::{RawBusRoute, RawBusStop};
 use crate::{
-    BusRoute, BusRouteID, BusStop, BusStopID, LaneID, LaneType, Map, PathConstraints, Position,
+    osm, BusRoute, BusRouteID, BusStop, BusStopID, LaneID, LaneType, Map, NewBusRoute,
+    PathConstraints, Position,
 };
 
 /// Construct the final model of bus/train stops and routes. This is quite broken currently, so not
@@ -254,12 +255,11 @@ impl Matcher {
         // We already figured out what side of the road we're on
         let (r, dir) = stop.matched_road.unwrap();
         let r = map.get_r(map.find_r_by_osm_id(r)?);
-        // Prefer the rightmost match. DON'T use find_closest_lane here; we only want one side of
-        // the road.
+        // Prefer the match closest to the curb. DON'T use find_closest_lane here; we only want one
+        // side of the road.
         let l = map.get_l(
-            r.children(dir)
+            r.children_from_curb(dir, map.get_config().driving_side)
                 .iter()
-                .rev()
                 .find(|(l, _)| route_type.can_use(map.get_l(*l), map))
                 .ok_or_else(|| format!("{} {}, doesn't have a bus or driving lane", r.id, dir))?
                 .0,
@@ -310,6 +310,80 @@ impl Matcher {
     }
 }
 
+/// Builds a route that only exists in map edits. It's the `idx`th new route.
+pub fn make_new_route(map: &Map, idx: usize, r: &NewBusRoute) -> Result<BusRoute, String> {
+    let first_stop = r
+        .stops
+        .get(0)
+        .ok_or_else(|| format!("{} has no stops", r.name))?;
+    let route = BusRoute {
+        id: BusRouteID(map.bus_routes.len()),
+        full_name: r.name.clone(),
+        short_name: r.name.clone(),
+        gtfs_trip_marker: None,
+        osm_rel_id: osm::RelationID(-1 - idx as i64),
+        stops: Vec::new(),
+        start: map.get_bs(*first_stop).driving_pos.lane(),
+        end_border: None,
+        route_type: PathConstraints::Bus,
+        spawn_times: r.spawn_times.clone(),
+        orig_spawn_times: r.spawn_times.clone(),
+    };
+    route_with_stops(map, route, r.stops.clone())
+}
+
+/// Changes the stops of a route. If buses entered the map from a border, they still do;
+/// otherwise they appear just before the new first stop. Fails if buses can't drive between the
+/// stops.
+pub fn route_with_stops(
+    map: &Map,
+    mut route: BusRoute,
+    stops: Vec<BusStopID>,
+) -> Result<BusRoute, String> {
+    if stops.len() < 2 {
+        return Err("A route needs at least two stops".to_string());
+    }
+    for bs in &stops {
+        let stop = map.get_bs(*bs);
+        if stop.is_train_stop != (route.route_type == PathConstraints::Train) {
+            return Err(format!(
+                "{} can't stop at {}",
+                route.plural_noun(),
+                stop.name
+            ));
+        }
+    }
+
+    if !map.get_i(map.get_l(route.start).src_i).is_incoming_border() {
+        route.start = pick_start_lane(map.get_bs(stops[0]).driving_pos, route.route_type, map)?;
+    }
+    route.stops = stops;
+
+    // While the player is editing, the pathfinder may not know about the latest lane changes, so
+    // don't use it.
+    for req in route.all_steps(map) {
+        if req.start.lane() == req.end.lane() && req.start.dist_along() > req.end.dist_along() {
+            return Err(format!(
+                "Two stops are out of order along {}",
+                map.get_parent(req.start.lane()).get_name(None)
+            ));
+        }
+        if map
+            .pathfinder
+            .pathfind_avoiding_turns(req.clone(), BTreeSet::new(), map)
+            .is_none()
+        {
+            return Err(format!(
+                "No way for {} to drive from {} to {}",
+                route.plural_noun(),
+                map.get_parent(req.start.lane()).get_name(None),
+                map.get_parent(req.end.lane()).get_name(None)
+            ));
+        }
+    }
+    Ok(route)
+}
+
 fn pick_start_lane(
     first_stop: Position,
     constraints: PathConstraints,
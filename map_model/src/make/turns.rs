@@ -167,6 +167,12 @@ fn does_turn_pass_restrictions(turn: &Turn, i: &Intersection, map: &Map) -> bool
             }
         }
     }
+    // Bans limited to part of the day are handled by pathfinding instead
+    for ban in &src.banned_turns {
+        if ban.window.is_none() && map.get_r(dst).orig_id == ban.to {
+            return false;
+        }
+    }
 
     true
 }
@@ -181,13 +187,19 @@ fn make_vehicle_turns(i: &Intersection, map: &Map, timer: &mut Timer) -> Vec<Tur
         if !src.lane_type.is_for_moving_vehicles() {
             continue;
         }
+        // turn:lanes=reverse explicitly allows a U-turn from this lane
+        let explicit_uturn = src
+            .get_turn_restrictions(map.get_r(src.parent))
+            .map(|types| types.contains(&TurnType::UTurn))
+            .unwrap_or(false);
         for dst in &i.outgoing_lanes {
             let dst = map.get_l(*dst);
             if !dst.lane_type.is_for_moving_vehicles() {
                 continue;
             }
-            // Only allow U-turns at deadends
-            if src.parent == dst.parent && !is_deadend {
+            // Only allow U-turns at deadends or when tagged
+            let is_uturn = src.parent == dst.parent;
+            if is_uturn && !is_deadend && !explicit_uturn {
                 continue;
             }
             // Can't go between light rail and normal roads
@@ -202,8 +214,11 @@ fn make_vehicle_turns(i: &Intersection, map: &Map, timer: &mut Timer) -> Vec<Tur
                 continue;
             }
 
-            let turn_type =
-                TurnType::from_angles(src.last_line().angle(), dst.first_line().angle());
+            let turn_type = if is_uturn {
+                TurnType::UTurn
+            } else {
+                TurnType::from_angles(src.last_line().angle(), dst.first_line().angle())
+            };
             let geom = if turn_type == TurnType::Straight {
                 PolyLine::must_new(vec![src.last_pt(), dst.first_pt()])
             } else {
@@ -164,7 +164,7 @@ pub fn _make_walking_turns_v2(map: &Map, i: &Intersection, timer: &mut Timer) ->
         let mut fwd = None;
         let mut back = None;
         for (l, dir, lt) in r.lanes_ltr() {
-            if lt == LaneType::Sidewalk || lt == LaneType::Shoulder {
+            if lt.is_walkable() {
                 if dir == Direction::Fwd {
                     fwd = Some(&all_lanes[l.0]);
                 } else {
@@ -492,7 +492,7 @@ fn turn_id(parent: IntersectionID, src: LaneID, dst: LaneID) -> TurnID {
 
 fn get_sidewalk<'a>(lanes: &'a Vec<Lane>, children: Vec<(LaneID, LaneType)>) -> Option<&'a Lane> {
     for (id, lt) in children {
-        if lt == LaneType::Sidewalk || lt == LaneType::Shoulder {
+        if lt.is_walkable() {
             return Some(&lanes[id.0]);
         }
     }
@@ -5,15 +5,16 @@ use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
 use petgraph::graphmap::UnGraphMap;
 use serde::{Deserialize, Serialize};
 
-use abstutil::{MapName, Timer};
-use geom::{Bounds, Distance, GPSBounds, Polygon, Pt2D, Ring, Time};
+use abstutil::{MapName, Migration, Timer, Versioned};
+use geom::{Bounds, Distance, Duration, GPSBounds, Polygon, Pt2D, Ring, Time};
 
 use crate::raw::{OriginalRoad, RawMap};
 use crate::{
     osm, Area, AreaID, Building, BuildingID, BuildingType, BusRoute, BusRouteID, BusStop,
-    BusStopID, ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, Lane, LaneID,
-    LaneType, Map, MapEdits, MovementID, OffstreetParking, ParkingLot, ParkingLotID, Path,
-    PathConstraints, PathRequest, Pathfinder, Position, Road, RoadID, Turn, TurnID, TurnType, Zone,
+    BusStopID, CachedVehicleGraphs, ControlStopSign, ControlTrafficSignal, Crosswalk, Intersection,
+    IntersectionID, Lane, LaneID, LaneType, Map, MapEdits, MovementID, OffstreetParking,
+    ParkingLot, ParkingLotID, Path, PathConstraints, PathRequest, Pathfinder, Position, Road,
+    RoadID, Turn, TurnID, TurnType, Zone,
 };
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -22,10 +23,19 @@ pub struct MapConfig {
     /// (Australia).
     pub driving_side: DrivingSide,
     pub bikes_can_use_bus_lanes: bool,
+    /// If true, bike routing weighs each lane by its traffic stress (see `Lane::traffic_stress`),
+    /// preferring calm streets and protected lanes even if they're a bit out of the way.
+    #[serde(with = "crate::map::added_in_v1")]
+    pub bikes_avoid_stress: bool,
     /// If true, roads without explicitly tagged sidewalks may have sidewalks or shoulders. If
     /// false, no sidewalks will be inferred if not tagged in OSM, and separate sidewalks will be
     /// included.
     pub inferred_sidewalks: bool,
+    /// If true, merge stub roads a few meters long into their neighbors and smooth out sharp
+    /// zig-zags in road center-lines while building the map. Off by default, so existing maps can
+    /// be reproduced exactly.
+    #[serde(with = "crate::map::added_in_v4")]
+    pub clean_geometry: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
@@ -37,7 +47,7 @@ pub enum DrivingSide {
 impl Map {
     pub fn new(path: String, timer: &mut Timer) -> Map {
         if path.contains("/maps/") {
-            match abstutil::maybe_read_binary(path.clone(), timer) {
+            match abstutil::maybe_read_versioned_binary(path.clone(), timer) {
                 Ok(map) => {
                     let mut map: Map = map;
                     map.edits = map.new_edits();
@@ -145,6 +155,14 @@ impl Map {
             areas: Vec::new(),
             parking_lots: Vec::new(),
             zones: Vec::new(),
+            toll_zones: Vec::new(),
+            toll_entrances: BTreeMap::new(),
+            parking_zones: Vec::new(),
+            parking_zone_roads: BTreeMap::new(),
+            bike_racks: BTreeMap::new(),
+            bike_treatments: BTreeMap::new(),
+            guideways: Vec::new(),
+            guideway_stations: Vec::new(),
             boundary_polygon: Ring::must_new(vec![
                 Pt2D::new(0.0, 0.0),
                 Pt2D::new(1.0, 0.0),
@@ -159,10 +177,13 @@ impl Map {
             config: MapConfig {
                 driving_side: DrivingSide::Right,
                 bikes_can_use_bus_lanes: true,
+                bikes_avoid_stress: false,
                 inferred_sidewalks: true,
+                clean_geometry: false,
             },
             pathfinder: Pathfinder::Dijkstra,
             pathfinder_dirty: false,
+            timed_pathfinding_graphs: CachedVehicleGraphs::default(),
             name: MapName {
                 city: "blank city".to_string(),
                 map: "blank".to_string(),
@@ -473,7 +494,7 @@ impl Map {
         assert!(self.edits.edits_name.starts_with("Untitled Proposal"));
         assert!(self.edits.commands.is_empty());
         assert!(!self.pathfinder_dirty);
-        abstutil::write_binary(self.name.path(), self);
+        abstutil::write_versioned_binary(self.name.path(), self);
     }
 
     /// Cars trying to park near this building should head for the driving lane returned here, then
@@ -541,6 +562,132 @@ impl Map {
         assert!(!self.pathfinder_dirty);
         self.pathfinder.pathfind(req, self)
     }
+    /// Like `pathfind`, but also avoids turns that map edits ban at this time of day, and accounts
+    /// for speed limits that only apply during part of the day. This is much slower while any of
+    /// those apply.
+    pub fn pathfind_at(&self, req: PathRequest, time: Time) -> Option<Path> {
+        self.pathfind_with_timed_costs(req, time, None, &BTreeMap::new())
+    }
+
+    /// Like `pathfind_at`, but drivers also weigh the price of entering toll zones against the
+    /// time saved, valuing their time at some dollars per hour.
+    pub fn pathfind_with_tolls(
+        &self,
+        req: PathRequest,
+        time: Time,
+        value_of_time: f64,
+    ) -> Option<Path> {
+        self.pathfind_with_timed_costs(req, time, Some(value_of_time), &BTreeMap::new())
+    }
+
+    /// Like `pathfind_with_tolls`, but adds some extra time to crossing some lanes, like the
+    /// congestion measured in a running simulation. Only meaningful for cars and trucks, whose
+    /// costs are in seconds.
+    pub fn pathfind_with_lane_delays(
+        &self,
+        req: PathRequest,
+        time: Time,
+        value_of_time: f64,
+        delays: &BTreeMap<LaneID, Duration>,
+    ) -> Option<Path> {
+        self.pathfind_with_timed_costs(req, time, Some(value_of_time), delays)
+    }
+
+    /// Everything that the contraction hierarchy doesn't know about: turns banned at this time,
+    /// timed speed limits, tolls if drivers care about them, and extra delays on some lanes.
+    fn pathfind_with_timed_costs(
+        &self,
+        req: PathRequest,
+        time: Time,
+        value_of_time: Option<f64>,
+        delays: &BTreeMap<LaneID, Duration>,
+    ) -> Option<Path> {
+        if req.constraints == PathConstraints::Pedestrian {
+            return self.pathfind(req);
+        }
+        let avoid = self.turns_banned_at(time);
+        let mut penalties = self.timed_speed_limit_penalties(req.constraints, time);
+        // Buses and bikes don't pay
+        let pays =
+            req.constraints == PathConstraints::Car || req.constraints == PathConstraints::Truck;
+        if let (true, Some(value_of_time)) = (pays, value_of_time) {
+            for t in self.toll_entrances.keys() {
+                if let Some((_, price)) = self.toll_for_turn(*t, time) {
+                    if price > 0.0 {
+                        // driving_cost is in seconds
+                        *penalties.entry(*t).or_insert(0.0) += 3600.0 * price / value_of_time;
+                    }
+                }
+            }
+        }
+        // The cost of a lane is charged on the turn leaving it
+        for (l, delay) in delays {
+            for turn in self.get_turns_from_lane(*l) {
+                *penalties.entry(turn.id).or_insert(0.0) += delay.inner_seconds();
+            }
+        }
+        if avoid.is_empty() && penalties.is_empty() {
+            return self.pathfind(req);
+        }
+        assert!(!self.pathfinder_dirty);
+        self.timed_pathfinding_graphs
+            .pathfind_with_turn_penalties(req, avoid, penalties, self)
+    }
+
+    /// When map edits change a road's speed limit at this time of day, the extra time (in seconds,
+    /// possibly negative) to cross each of its lanes, charged on the turns leaving the lane. Only
+    /// cars and trucks weigh routes by time.
+    fn timed_speed_limit_penalties(
+        &self,
+        constraints: PathConstraints,
+        time: Time,
+    ) -> BTreeMap<TurnID, f64> {
+        let mut penalties = BTreeMap::new();
+        if constraints != PathConstraints::Car && constraints != PathConstraints::Truck {
+            return penalties;
+        }
+        for r in &self.roads {
+            let speed = r.speed_limit_at(time);
+            if speed == r.speed_limit {
+                continue;
+            }
+            for (l, _, _) in &r.lanes_ltr {
+                let lane = self.get_l(*l);
+                let extra = lane.length() / speed - lane.length() / r.speed_limit;
+                for turn in self.get_turns_from_lane(*l) {
+                    penalties.insert(turn.id, extra.inner_seconds());
+                }
+            }
+        }
+        penalties
+    }
+
+    /// Turns that still exist, but that map edits ban vehicles from using at this time of day.
+    pub fn turns_banned_at(&self, time: Time) -> BTreeSet<TurnID> {
+        let mut banned = BTreeSet::new();
+        for r in &self.roads {
+            for ban in &r.banned_turns {
+                if ban.window.is_none() || !ban.applies_at(time) {
+                    continue;
+                }
+                let to = match self.find_r_by_osm_id(ban.to) {
+                    Ok(to) => to,
+                    Err(_) => {
+                        continue;
+                    }
+                };
+                for l in &r.lanes_ltr {
+                    for turn in self.get_turns_from_lane(l.0) {
+                        if self.get_l(turn.id.dst).parent == to {
+                            banned.insert(turn.id);
+                        }
+                    }
+                }
+            }
+        }
+        banned
+    }
+
     pub fn pathfind_avoiding_lanes(
         &self,
         req: PathRequest,
@@ -550,6 +697,13 @@ impl Map {
         self.pathfinder.pathfind_avoiding_lanes(req, avoid, self)
     }
 
+    /// Finds a walking path avoiding sidewalks and crossings known to be unusable in a
+    /// wheelchair.
+    pub fn pathfind_wheelchair(&self, req: PathRequest) -> Option<Path> {
+        assert!(!self.pathfinder_dirty);
+        self.pathfinder.pathfind_wheelchair(req, self)
+    }
+
     pub fn should_use_transit(
         &self,
         start: Position,
@@ -681,3 +835,61 @@ impl Map {
         )
     }
 }
+
+// Fields added to the map since maps were first imported
+abstutil::added_in_format!(added_in_v1, 1);
+abstutil::added_in_format!(added_in_v2, 2);
+abstutil::added_in_format!(added_in_v3, 3);
+abstutil::added_in_format!(added_in_v4, 4);
+abstutil::added_in_format!(added_in_v5, 5);
+
+impl Versioned for Map {
+    const FORMAT: &'static str = "map";
+    // Bump this after changing anything serialized in the map, and add a migration below.
+    const VERSION: u32 = 5;
+
+    fn migrations() -> Vec<Migration> {
+        vec![
+            Migration {
+                from: 0,
+                description: "add crosswalks, modal filters, banned turns, and truck pathfinding",
+                upgrade: upgrade_unversioned,
+            },
+            Migration {
+                from: 1,
+                description: "add deliveries per hour to roads",
+                upgrade: |bytes| abstutil::upgrade_added_fields::<Map>(bytes, 1),
+            },
+            Migration {
+                from: 2,
+                description: "add curb ramps to crosswalks",
+                upgrade: |bytes| abstutil::upgrade_added_fields::<Map>(bytes, 2),
+            },
+            Migration {
+                from: 3,
+                description: "add the geometry cleanup setting to the map config",
+                upgrade: |bytes| abstutil::upgrade_added_fields::<Map>(bytes, 3),
+            },
+            Migration {
+                from: 4,
+                description: "add timed speed limits to roads",
+                upgrade: |bytes| abstutil::upgrade_added_fields::<Map>(bytes, 4),
+            },
+        ]
+    }
+}
+
+/// Maps from before versioning existed are missing a few things that have to be calculated from
+/// the rest of the map.
+fn upgrade_unversioned(bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    abstutil::upgrade_added_fields_with::<Map, _>(bytes, 0, |map| {
+        for idx in 0..map.intersections.len() {
+            map.intersections[idx].crosswalks = Crosswalk::make_all(map, &map.intersections[idx]);
+        }
+        let mut pathfinder = std::mem::replace(&mut map.pathfinder, Pathfinder::Dijkstra);
+        if let Pathfinder::CH(ref mut ch) = pathfinder {
+            ch.add_truck_graph(map);
+        }
+        map.pathfinder = pathfinder;
+    })
+}
@@ -0,0 +1,159 @@
+//! How usable sidewalks and crossings are by somebody in a wheelchair, from OSM tags. Most of
+//! these tags are missing in most places, so anything unknown is assumed to be fine; only links
+//! known to be a problem are excluded from wheelchair routing.
+
+use geom::Distance;
+
+use crate::{osm, Lane, LaneType, Map, TurnID, TurnType};
+
+/// Narrower sidewalks can't fit a wheelchair. This is the ADA minimum of 36 inches.
+pub const MIN_SIDEWALK_WIDTH: Distance = Distance::const_meters(0.9);
+/// Steeper sidewalks are too hard to climb (or descend safely). This is the ADA maximum for a
+/// ramp, 1:12.
+pub const MAX_WHEELCHAIR_GRADE: f64 = 0.0833;
+
+/// Why a sidewalk, footway, or crossing can't be used in a wheelchair.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AccessBarrier {
+    TooNarrow(Distance),
+    /// The surface or smoothness tag
+    RoughSurface(String),
+    /// The absolute grade, in [0, 1]
+    TooSteep(f64),
+    Steps,
+    /// Explicitly tagged wheelchair=no
+    TaggedInaccessible,
+    /// A crossing with a raised curb at one end
+    NoCurbRamps,
+}
+
+impl AccessBarrier {
+    pub fn describe(&self) -> String {
+        match self {
+            AccessBarrier::TooNarrow(width) => format!("too narrow ({})", width),
+            AccessBarrier::RoughSurface(surface) => format!("rough surface ({})", surface),
+            AccessBarrier::TooSteep(grade) => format!("too steep ({:.1}%)", grade * 100.0),
+            AccessBarrier::Steps => "steps".to_string(),
+            AccessBarrier::TaggedInaccessible => "tagged as inaccessible".to_string(),
+            AccessBarrier::NoCurbRamps => "no curb ramps".to_string(),
+        }
+    }
+
+    /// A short name for the kind of barrier, ignoring details
+    pub fn category(&self) -> &'static str {
+        match self {
+            AccessBarrier::TooNarrow(_) => "too narrow",
+            AccessBarrier::RoughSurface(_) => "rough surface",
+            AccessBarrier::TooSteep(_) => "too steep",
+            AccessBarrier::Steps => "steps",
+            AccessBarrier::TaggedInaccessible => "tagged inaccessible",
+            AccessBarrier::NoCurbRamps => "no curb ramps",
+        }
+    }
+}
+
+impl Lane {
+    /// The width of a sidewalk or footway, if OSM says.
+    pub fn sidewalk_width(&self, map: &Map) -> Option<Distance> {
+        let value = self.sidewalk_tag(map, "width")?;
+        // Values are usually meters, sometimes with the unit
+        value
+            .trim_end_matches('m')
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .map(Distance::meters)
+    }
+
+    /// The surface of a sidewalk or footway, if OSM says.
+    pub fn sidewalk_surface(&self, map: &Map) -> Option<String> {
+        self.sidewalk_tag(map, "surface")
+    }
+
+    /// If this sidewalk or footway can't be used in a wheelchair, explains why. Returns None for
+    /// lanes that aren't walkable at all.
+    pub fn wheelchair_barrier(&self, map: &Map) -> Option<AccessBarrier> {
+        if !self.is_walkable() {
+            return None;
+        }
+        let tags = &map.get_r(self.parent).osm_tags;
+        if self.lane_type == LaneType::Footway {
+            if tags.is(osm::HIGHWAY, "steps") {
+                return Some(AccessBarrier::Steps);
+            }
+            if tags.is("wheelchair", "no") {
+                return Some(AccessBarrier::TaggedInaccessible);
+            }
+        }
+        if let Some(width) = self.sidewalk_width(map) {
+            if width < MIN_SIDEWALK_WIDTH {
+                return Some(AccessBarrier::TooNarrow(width));
+            }
+        }
+        if let Some(surface) = self.sidewalk_surface(map) {
+            if vec![
+                "gravel",
+                "dirt",
+                "grass",
+                "sand",
+                "ground",
+                "mud",
+                "unhewn_cobblestone",
+                "woodchips",
+            ]
+            .contains(&surface.as_str())
+            {
+                return Some(AccessBarrier::RoughSurface(surface));
+            }
+        }
+        if let Some(smoothness) = self.sidewalk_tag(map, "smoothness") {
+            if vec!["bad", "very_bad", "horrible", "very_horrible", "impassable"]
+                .contains(&smoothness.as_str())
+            {
+                return Some(AccessBarrier::RoughSurface(smoothness));
+            }
+        }
+        let grade = self.percent_grade(map).abs();
+        if grade > MAX_WHEELCHAIR_GRADE {
+            return Some(AccessBarrier::TooSteep(grade));
+        }
+        None
+    }
+
+    /// Footways are their own OSM way, so use the plain tag. Sidewalks are described on the road,
+    /// by sidewalk:left:width, sidewalk:both:width, or sidewalk:width.
+    fn sidewalk_tag(&self, map: &Map, key: &str) -> Option<String> {
+        let road = map.get_r(self.parent);
+        let tags = &road.osm_tags;
+        match self.lane_type {
+            LaneType::Footway => tags.get(key).cloned(),
+            LaneType::Sidewalk => {
+                let side = if road.lanes_ltr()[0].0 == self.id {
+                    "left"
+                } else {
+                    "right"
+                };
+                tags.get(&format!("sidewalk:{}:{}", side, key))
+                    .or_else(|| tags.get(&format!("sidewalk:both:{}", key)))
+                    .or_else(|| tags.get(&format!("sidewalk:{}", key)))
+                    .cloned()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Map {
+    /// If this turn can't be used in a wheelchair, explains why.
+    pub fn turn_wheelchair_barrier(&self, t: TurnID) -> Option<AccessBarrier> {
+        if self.get_t(t).turn_type != TurnType::Crosswalk {
+            return None;
+        }
+        match self.get_i(t.parent).get_crosswalk(t) {
+            Some(crosswalk) if crosswalk.curb_ramps == Some(false) => {
+                Some(AccessBarrier::NoCurbRamps)
+            }
+            _ => None,
+        }
+    }
+}
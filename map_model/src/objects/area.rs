@@ -28,6 +28,8 @@ pub enum AreaType {
     Water,
     Island,
     MedianStrip,
+    /// Open space that pedestrians can walk across in any direction, like a town square
+    PedestrianPlaza,
 }
 
 /// Areas are just used for drawing.
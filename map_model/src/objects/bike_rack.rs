@@ -0,0 +1,42 @@
+//! Cyclists lock their bike at a rack by the building they're going to. OSM maps some
+//! `amenity=bicycle_parking` inside buildings; map edits can add or remove racks anywhere.
+
+use crate::{Building, BuildingID, Map};
+
+/// OSM often doesn't say how many bikes fit, so guess a typical rack
+const DEFAULT_CAPACITY: usize = 4;
+
+impl Building {
+    /// How many bikes fit at racks for this building, according to OSM
+    pub fn osm_bike_rack_capacity(&self) -> usize {
+        self.amenities
+            .iter()
+            .filter(|a| a.amenity_type == "bicycle_parking")
+            .map(|a| {
+                a.osm_tags
+                    .get("capacity")
+                    .and_then(|x| x.parse::<usize>().ok())
+                    .unwrap_or(DEFAULT_CAPACITY)
+            })
+            .sum()
+    }
+}
+
+impl Map {
+    /// How many bikes fit at racks for this building, including map edits
+    pub fn get_bike_rack_capacity(&self, b: BuildingID) -> usize {
+        self.bike_racks
+            .get(&b)
+            .cloned()
+            .unwrap_or_else(|| self.get_b(b).osm_bike_rack_capacity())
+    }
+
+    /// Every building with any bike racks, and their capacity
+    pub fn all_bike_racks(&self) -> Vec<(BuildingID, usize)> {
+        self.all_buildings()
+            .iter()
+            .map(|b| (b.id, self.get_bike_rack_capacity(b.id)))
+            .filter(|(_, cap)| *cap > 0)
+            .collect()
+    }
+}
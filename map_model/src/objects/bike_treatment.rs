@@ -0,0 +1,55 @@
+//! Intersections can be redesigned to make them safer and easier to bike through. None of these
+//! come from OSM; map edits add them.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{IntersectionID, Map};
+
+/// Infrastructure for cyclists at one intersection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BikeTreatments {
+    /// Corner islands set cyclists' crossings back from the main intersection, so turning drivers
+    /// meet them at a right angle after finishing most of their turn. Drivers yield to cyclists
+    /// who got there first.
+    pub protected: bool,
+    /// At traffic signals, an advanced stop line lets cyclists wait in front of cars during a red
+    /// light, so they start first when it turns green.
+    pub bike_box: bool,
+    /// At traffic signals, cyclists turn left in two stages instead of crossing oncoming traffic:
+    /// first straight across with the green, then waiting in a queue box at the far corner for
+    /// the cross street's green. Where people drive on the left, this applies to right turns.
+    pub two_stage_left: bool,
+}
+
+impl BikeTreatments {
+    pub fn is_empty(&self) -> bool {
+        *self == BikeTreatments::default()
+    }
+
+    pub fn describe(&self) -> Vec<&'static str> {
+        let mut list = Vec::new();
+        if self.protected {
+            list.push("protected intersection");
+        }
+        if self.bike_box {
+            list.push("bike box");
+        }
+        if self.two_stage_left {
+            list.push("two-stage left turns");
+        }
+        list
+    }
+}
+
+impl Map {
+    pub fn get_bike_treatments(&self, i: IntersectionID) -> BikeTreatments {
+        self.bike_treatments.get(&i).cloned().unwrap_or_default()
+    }
+
+    /// Every intersection with some treatment
+    pub fn all_bike_treatments(&self) -> &BTreeMap<IntersectionID, BikeTreatments> {
+        &self.bike_treatments
+    }
+}
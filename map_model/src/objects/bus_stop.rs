@@ -24,6 +24,13 @@ impl fmt::Display for BusStopID {
     }
 }
 
+impl BusStopID {
+    /// Distinguishes stops on the same sidewalk. Only meaningful together with `sidewalk`.
+    pub fn idx(self) -> usize {
+        self.idx
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BusRouteID(
     #[serde(
@@ -50,25 +57,35 @@ pub struct BusStop {
     pub is_train_stop: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct BusRoute {
     pub id: BusRouteID,
     pub full_name: String,
     pub short_name: String,
     pub gtfs_trip_marker: Option<String>,
     pub osm_rel_id: osm::RelationID,
+    /// In order, from the OpenStreetMap route relation. GTFS stop sequences aren't imported.
     pub stops: Vec<BusStopID>,
     /// May be a border or not. If not, is long enough for buses to spawn fully.
     pub start: LaneID,
     pub end_border: Option<LaneID>,
     pub route_type: PathConstraints,
-    /// Non-empty, times in order for one day when a vehicle should begin at start.
+    /// Non-empty, times in order for one day when a vehicle should begin at start. When a GTFS
+    /// feed is available, these are the published departure times; otherwise they're synthesized.
     pub spawn_times: Vec<Time>,
     /// Explicitly store whatever the original was, since this can't be reconstructed without side
     /// input.
     pub orig_spawn_times: Vec<Time>,
 }
 
+/// A bus route that only exists in map edits. It can only use bus stops that already exist.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewBusRoute {
+    pub name: String,
+    pub stops: Vec<BusStopID>,
+    pub spawn_times: Vec<Time>,
+}
+
 impl BusRoute {
     pub fn all_steps(&self, map: &Map) -> Vec<PathRequest> {
         let mut steps = Vec::new();
@@ -94,6 +111,11 @@ impl BusRoute {
         steps
     }
 
+    /// Routes created by map edits get made-up negative relation IDs.
+    pub fn is_from_edits(&self) -> bool {
+        self.osm_rel_id.0 < 0
+    }
+
     pub fn plural_noun(&self) -> &'static str {
         if self.route_type == PathConstraints::Bus {
             "buses"
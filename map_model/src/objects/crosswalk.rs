@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::Duration;
+
+use crate::{osm, Intersection, IntersectionID, Map, RoadID, TurnID, TurnType};
+
+/// A place where pedestrians cross one road at an intersection. The path itself is a pair of
+/// Crosswalk turns, one in each direction; this describes how the crossing is controlled.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Crosswalk {
+    pub parent: IntersectionID,
+    /// The road being crossed
+    pub road: RoadID,
+    /// The Crosswalk turns in both directions
+    pub turns: Vec<TurnID>,
+    /// At stop signs, pedestrians have the right of way on marked crosswalks. On unmarked ones,
+    /// they yield to vehicles.
+    pub marked: bool,
+    /// At traffic signals, pedestrians may start crossing this long before conflicting vehicles
+    /// get their green, so turning drivers see them already in the crosswalk.
+    pub leading_interval: Duration,
+    /// At traffic signals, pedestrians have to press a button to get a walk signal. Pressing it
+    /// partway through a stage doesn't help; they have to wait for the stage to come around again.
+    pub push_button: bool,
+    /// Whether both ends of the crossing have curb ramps (or a flush curb), from the kerb tag of
+    /// the OSM crossing node. None if unknown.
+    #[serde(default, with = "crate::map::added_in_v3")]
+    pub curb_ramps: Option<bool>,
+}
+
+impl Crosswalk {
+    /// Groups the Crosswalk turns of an intersection by the road they cross.
+    pub(crate) fn make_all(map: &Map, i: &Intersection) -> Vec<Crosswalk> {
+        let mut per_road: BTreeMap<RoadID, Vec<TurnID>> = BTreeMap::new();
+        for t in &i.turns {
+            if map.get_t(*t).turn_type == TurnType::Crosswalk {
+                per_road
+                    .entry(map.get_l(t.src).parent)
+                    .or_insert_with(Vec::new)
+                    .push(*t);
+            }
+        }
+        per_road
+            .into_iter()
+            .map(|(road, turns)| Crosswalk {
+                parent: i.id,
+                road,
+                turns,
+                // OSM rarely says, so guess. Crossings at signals are always painted; at stop
+                // signs, assume only crossings of bigger roads are.
+                marked: i.is_traffic_signal() || map.get_r(road).get_rank() != osm::RoadRank::Local,
+                leading_interval: Duration::ZERO,
+                push_button: false,
+                curb_ramps: curb_ramps(map, i.id, road),
+            })
+            .collect()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![if self.marked { "marked" } else { "unmarked" }.to_string()];
+        if self.leading_interval > Duration::ZERO {
+            parts.push(format!("{} head start", self.leading_interval));
+        }
+        if self.push_button {
+            parts.push("push button".to_string());
+        }
+        match self.curb_ramps {
+            Some(true) => parts.push("curb ramps".to_string()),
+            Some(false) => parts.push("no curb ramps".to_string()),
+            None => {}
+        }
+        parts.join(", ")
+    }
+}
+
+fn curb_ramps(map: &Map, i: IntersectionID, r: RoadID) -> Option<bool> {
+    let road = map.get_r(r);
+    let key = if road.src_i == i {
+        osm::CROSSING_KERB_START
+    } else {
+        osm::CROSSING_KERB_END
+    };
+    // https://wiki.openstreetmap.org/wiki/Key:kerb
+    match road.osm_tags.get(key).map(|x| x.as_str()) {
+        Some("lowered") | Some("flush") | Some("no") => Some(true),
+        Some("raised") | Some("rolled") => Some(false),
+        _ => None,
+    }
+}
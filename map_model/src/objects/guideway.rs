@@ -0,0 +1,192 @@
+//! A fixed guideway is a transit line with its own right-of-way, like an aerial gondola, personal
+//! rapid transit, or a monorail. Guideways only exist as map edits, so a line can be sketched and
+//! its ridership compared against the map without it. Unlike light rail, the vehicles don't use
+//! lanes; they travel in a straight line between stations, above or apart from everything else.
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration, FindClosest, LonLat, Pt2D, Speed};
+
+use crate::{Map, Position};
+
+/// Stations further than this from any sidewalk can't be used.
+pub const MAX_STATION_DIST_FROM_SIDEWALK: Distance = Distance::const_meters(100.0);
+/// How long vehicles stop at each station in the middle of the line
+const DWELL_TIME: Duration = Duration::const_seconds(20.0);
+/// Used to estimate walking to and from stations, as the crow flies
+const WALKING_SPEED: Speed = Speed::const_meters_per_second(1.34);
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GuidewayKind {
+    Gondola,
+    PersonalRapidTransit,
+    Monorail,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Guideway {
+    pub name: String,
+    pub kind: GuidewayKind,
+    /// In GPS, so the stations survive rebuilding the map. Vehicles run back and forth between
+    /// the first and last station, stopping at every one in between.
+    pub stations: Vec<LonLat>,
+    /// How many people fit in one vehicle
+    pub capacity: usize,
+    pub speed: Speed,
+    /// How often a vehicle leaves each end of the line
+    pub headway: Duration,
+}
+
+impl GuidewayKind {
+    pub fn all() -> Vec<GuidewayKind> {
+        vec![
+            GuidewayKind::Gondola,
+            GuidewayKind::PersonalRapidTransit,
+            GuidewayKind::Monorail,
+        ]
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            GuidewayKind::Gondola => "aerial gondola",
+            GuidewayKind::PersonalRapidTransit => "personal rapid transit",
+            GuidewayKind::Monorail => "monorail",
+        }
+    }
+
+    /// What one vehicle is called
+    pub fn vehicle_noun(self) -> &'static str {
+        match self {
+            GuidewayKind::Gondola => "cabin",
+            GuidewayKind::PersonalRapidTransit => "pod",
+            GuidewayKind::Monorail => "train",
+        }
+    }
+
+    /// Typical (capacity, speed, headway) for this kind of line, as a starting point
+    pub fn typical_service(self) -> (usize, Speed, Duration) {
+        match self {
+            GuidewayKind::Gondola => (10, Speed::meters_per_second(6.0), Duration::seconds(30.0)),
+            GuidewayKind::PersonalRapidTransit => {
+                (4, Speed::miles_per_hour(25.0), Duration::seconds(15.0))
+            }
+            GuidewayKind::Monorail => (150, Speed::miles_per_hour(35.0), Duration::minutes(5)),
+        }
+    }
+}
+
+impl Guideway {
+    pub fn station_pts(&self, map: &Map) -> Vec<Pt2D> {
+        map.get_gps_bounds().convert(&self.stations)
+    }
+
+    /// How long a vehicle takes from one station to another, including stops at every station in
+    /// between. Works in either direction.
+    pub fn travel_time(&self, map: &Map, from: usize, to: usize) -> Duration {
+        let pts = self.station_pts(map);
+        let (lo, hi) = if from <= to { (from, to) } else { (to, from) };
+        let mut time = Duration::ZERO;
+        for idx in lo..hi {
+            time += pts[idx].dist_to(pts[idx + 1]) / self.speed;
+        }
+        if hi > lo + 1 {
+            time += (hi - lo - 1) as f64 * DWELL_TIME;
+        }
+        time
+    }
+
+    pub fn describe_service(&self) -> String {
+        format!(
+            "{} stations, {} people per {} every {}",
+            self.stations.len(),
+            self.capacity,
+            self.kind.vehicle_noun(),
+            self.headway
+        )
+    }
+
+    /// Finds the place on a sidewalk to wait at each station. Fails if any station is too far
+    /// from a sidewalk.
+    pub fn find_station_positions(&self, map: &Map) -> Result<Vec<Position>, String> {
+        if self.stations.len() < 2 {
+            return Err(format!("{} needs at least two stations", self.name));
+        }
+        let mut closest = FindClosest::new(map.get_bounds());
+        for l in map.all_lanes() {
+            if l.is_walkable() {
+                closest.add(l.id, l.lane_center_pts.points());
+            }
+        }
+        let mut positions = Vec::new();
+        for (idx, pt) in self.station_pts(map).into_iter().enumerate() {
+            let pos = closest
+                .closest_pt(pt, MAX_STATION_DIST_FROM_SIDEWALK)
+                .and_then(|(l, pt)| {
+                    let (dist, _) = map.get_l(l).lane_center_pts.dist_along_of_point(pt)?;
+                    Some(Position::new(l, dist))
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "Station {} of {} is more than {} from any sidewalk",
+                        idx + 1,
+                        self.name,
+                        MAX_STATION_DIST_FROM_SIDEWALK
+                    )
+                })?;
+            positions.push(pos);
+        }
+        Ok(positions)
+    }
+}
+
+impl Map {
+    pub fn get_guideways(&self) -> &Vec<Guideway> {
+        &self.guideways
+    }
+
+    /// Where people wait at each station of a guideway
+    pub fn get_guideway_stations(&self, idx: usize) -> &Vec<Position> {
+        &self.guideway_stations[idx]
+    }
+
+    /// If riding a guideway is faster than walking the whole way, returns the guideway and the
+    /// stations to board and alight at. Walking and waiting (half of a headway) are estimated as
+    /// the crow flies.
+    pub fn should_use_guideway(
+        &self,
+        start: Position,
+        end: Position,
+    ) -> Option<(usize, usize, usize)> {
+        let start_pt = start.pt(self);
+        let end_pt = end.pt(self);
+        let mut best_time = start_pt.dist_to(end_pt) / WALKING_SPEED;
+        let mut best = None;
+        for (idx, guideway) in self.guideways.iter().enumerate() {
+            let stations = &self.guideway_stations[idx];
+            let nearest = |pt: Pt2D| {
+                stations
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, pos)| pos.pt(self).dist_to(pt))
+                    .map(|(station, pos)| (station, pos.pt(self).dist_to(pt)))
+            };
+            let (board, walk1, alight, walk2) = match (nearest(start_pt), nearest(end_pt)) {
+                (Some((board, walk1)), Some((alight, walk2))) if board != alight => {
+                    (board, walk1, alight, walk2)
+                }
+                // No usable stations, or the same one is closest to both ends
+                _ => {
+                    continue;
+                }
+            };
+            let time = (walk1 + walk2) / WALKING_SPEED
+                + guideway.headway / 2.0
+                + guideway.travel_time(self, board, alight);
+            if time < best_time {
+                best_time = time;
+                best = Some((idx, board, alight));
+            }
+        }
+        best
+    }
+}
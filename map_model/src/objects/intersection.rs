@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use abstutil::{deserialize_usize, serialize_usize};
 use geom::{Distance, Polygon};
 
-use crate::{osm, DirectedRoadID, LaneID, Map, PathConstraints, Road, RoadID, TurnID};
+use crate::{osm, Crosswalk, DirectedRoadID, LaneID, Map, PathConstraints, Road, RoadID, TurnID};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct IntersectionID(
@@ -54,6 +54,9 @@ pub struct Intersection {
 
     // TODO Maybe DirectedRoadIDs
     pub roads: BTreeSet<RoadID>,
+    /// One per road that pedestrians can cross here
+    #[serde(with = "crate::map::added_in_v1")]
+    pub crosswalks: Vec<Crosswalk>,
 }
 
 impl Intersection {
@@ -79,10 +82,20 @@ impl Intersection {
         self.intersection_type == IntersectionType::TrafficSignal
     }
 
+    /// Finds the crosswalk that a Crosswalk turn belongs to.
+    pub fn get_crosswalk(&self, t: TurnID) -> Option<&Crosswalk> {
+        self.crosswalks.iter().find(|c| c.turns.contains(&t))
+    }
+
     pub fn is_light_rail(&self, map: &Map) -> bool {
         self.roads.iter().all(|r| map.get_r(*r).is_light_rail())
     }
 
+    /// Is this where some road joins a roundabout?
+    pub fn is_roundabout(&self, map: &Map) -> bool {
+        self.roads.iter().any(|r| map.get_r(*r).is_roundabout())
+    }
+
     pub fn is_private(&self, map: &Map) -> bool {
         self.roads.iter().all(|r| map.get_r(*r).is_private())
     }
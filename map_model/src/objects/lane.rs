@@ -4,7 +4,7 @@ use std::fmt;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_usize, serialize_usize, wraparound_get};
-use geom::{Distance, Line, PolyLine, Polygon, Pt2D, Ring};
+use geom::{Distance, Line, PolyLine, Polygon, Pt2D, Ring, Speed};
 
 use crate::{
     osm, BusStopID, DirectedRoadID, Direction, IntersectionID, Map, Road, RoadID, TurnType,
@@ -44,6 +44,8 @@ pub enum LaneType {
     SharedLeftTurn,
     Construction,
     LightRail,
+    /// A path only for pedestrians, not running alongside any road
+    Footway,
 }
 
 impl LaneType {
@@ -58,6 +60,7 @@ impl LaneType {
             LaneType::SharedLeftTurn => false,
             LaneType::Construction => false,
             LaneType::LightRail => true,
+            LaneType::Footway => false,
         }
     }
 
@@ -72,9 +75,14 @@ impl LaneType {
             LaneType::SharedLeftTurn => false,
             LaneType::Construction => false,
             LaneType::LightRail => true,
+            LaneType::Footway => true,
         }
     }
 
+    pub fn is_walkable(self) -> bool {
+        self == LaneType::Sidewalk || self == LaneType::Shoulder || self == LaneType::Footway
+    }
+
     pub fn describe(self) -> &'static str {
         match self {
             LaneType::Driving => "a general-purpose driving lane",
@@ -86,6 +94,7 @@ impl LaneType {
             LaneType::SharedLeftTurn => "a shared left-turn lane",
             LaneType::Construction => "a lane that's closed for construction",
             LaneType::LightRail => "a light rail track",
+            LaneType::Footway => "a footway or trail",
         }
     }
 
@@ -100,6 +109,31 @@ impl LaneType {
             LaneType::SharedLeftTurn => "left-turn lane",
             LaneType::Construction => "construction",
             LaneType::LightRail => "light rail track",
+            LaneType::Footway => "footway",
+        }
+    }
+}
+
+/// Level of Traffic Stress for cycling. See `Lane::traffic_stress`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TrafficStress {
+    /// LTS 1: suitable for children
+    Low,
+    /// LTS 2: comfortable for most adults
+    Moderate,
+    /// LTS 3: tolerable for confident cyclists
+    High,
+    /// LTS 4: only for the strong and fearless
+    Extreme,
+}
+
+impl TrafficStress {
+    pub fn describe(self) -> &'static str {
+        match self {
+            TrafficStress::Low => "low stress (LTS 1)",
+            TrafficStress::Moderate => "moderate stress (LTS 2)",
+            TrafficStress::High => "high stress (LTS 3)",
+            TrafficStress::Extreme => "extreme stress (LTS 4)",
         }
     }
 }
@@ -171,6 +205,17 @@ impl Lane {
         self.lane_center_pts.length()
     }
 
+    /// Returns [-1.0, 1.0], the grade when travelling from src_i to dst_i. 0 is flat, positive is
+    /// uphill, negative is downhill. Elevation is sampled at each intersection during import.
+    pub fn percent_grade(&self, map: &Map) -> f64 {
+        let rise = map.get_i(self.dst_i).elevation - map.get_i(self.src_i).elevation;
+        let run = self.length();
+        if run == Distance::ZERO {
+            return 0.0;
+        }
+        (rise / run).max(-1.0).min(1.0)
+    }
+
     pub fn intersections(&self) -> Vec<IntersectionID> {
         // TODO I think we're assuming there are no loop lanes
         vec![self.src_i, self.dst_i]
@@ -202,7 +247,7 @@ impl Lane {
     }
 
     pub fn is_walkable(&self) -> bool {
-        self.lane_type == LaneType::Sidewalk || self.lane_type == LaneType::Shoulder
+        self.lane_type.is_walkable()
     }
 
     pub fn is_sidewalk(&self) -> bool {
@@ -230,6 +275,63 @@ impl Lane {
         }
     }
 
+    /// How stressful it is to bike on this lane, or None if bikes can't use it. This is a
+    /// simplified version of the Level of Traffic Stress from Mekuria, Furth, and Nixon (2012),
+    /// looking at the lane type, speed limit, road class, number of lanes, and adjacent parking.
+    pub fn traffic_stress(&self, map: &Map) -> Option<TrafficStress> {
+        let road = map.get_r(self.parent);
+        let lanes = road.lanes_ltr();
+        let dir = road.dir(self.id);
+        let num_driving_lanes = lanes
+            .iter()
+            .filter(|(_, d, lt)| *d == dir && *lt == LaneType::Driving)
+            .count();
+        let idx = lanes.iter().position(|(l, _, _)| *l == self.id).unwrap();
+        let next_to_parking = (idx > 0 && lanes[idx - 1].2 == LaneType::Parking)
+            || lanes
+                .get(idx + 1)
+                .map(|(_, _, lt)| *lt == LaneType::Parking)
+                .unwrap_or(false);
+        let speed = road.speed_limit;
+
+        match self.lane_type {
+            LaneType::Biking => Some(if num_driving_lanes == 0 {
+                // Separated from cars entirely
+                TrafficStress::Low
+            } else if speed <= Speed::miles_per_hour(25.0) && num_driving_lanes == 1 {
+                if next_to_parking {
+                    TrafficStress::Moderate
+                } else {
+                    TrafficStress::Low
+                }
+            } else if speed <= Speed::miles_per_hour(35.0) && num_driving_lanes <= 2 {
+                if next_to_parking {
+                    TrafficStress::High
+                } else {
+                    TrafficStress::Moderate
+                }
+            } else {
+                TrafficStress::High
+            }),
+            // Mixing with traffic
+            LaneType::Driving | LaneType::Bus => Some(
+                if road.get_rank() == osm::RoadRank::Local
+                    && speed <= Speed::miles_per_hour(25.0)
+                    && num_driving_lanes <= 1
+                {
+                    TrafficStress::Low
+                } else if speed <= Speed::miles_per_hour(25.0) && num_driving_lanes <= 2 {
+                    TrafficStress::Moderate
+                } else if speed <= Speed::miles_per_hour(35.0) && num_driving_lanes <= 2 {
+                    TrafficStress::High
+                } else {
+                    TrafficStress::Extreme
+                },
+            ),
+            _ => None,
+        }
+    }
+
     pub fn get_turn_restrictions(&self, road: &Road) -> Option<BTreeSet<TurnType>> {
         if !self.is_driving() {
             return None;
@@ -281,11 +383,7 @@ impl Lane {
                     "slight_left" | "slight left" | "merge_to_left" | "sharp_left" => {
                         vec![TurnType::Straight, TurnType::Left]
                     }
-                    "reverse" => {
-                        // TODO We need TurnType::UTurn. Until then, u-turns usually show up as
-                        // left turns.
-                        vec![TurnType::Left]
-                    }
+                    "reverse" => vec![TurnType::UTurn],
                     s => {
                         warn!("Unknown turn restriction {}", s);
                         vec![]
@@ -1,11 +1,18 @@
+pub mod accessibility;
 pub mod area;
+pub mod bike_rack;
+pub mod bike_treatment;
 pub mod building;
 pub mod bus_stop;
+pub mod crosswalk;
+pub mod guideway;
 pub mod intersection;
 pub mod lane;
 pub mod parking_lot;
+pub mod parking_zone;
 pub mod road;
 pub mod stop_signs;
+pub mod toll_zone;
 pub mod traffic_signals;
 pub mod turn;
 pub mod zone;
@@ -0,0 +1,131 @@
+//! Parking zones restrict who can use the on-street parking inside them, and for how long. Like
+//! toll zones, they only exist as map edits.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Duration, LonLat, Polygon, Pt2D, Ring, Time};
+
+use crate::{BuildingID, BuildingType, Map, RoadID};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ParkingZone {
+    pub name: String,
+    /// In GPS, so the zone survives rebuilding the map. The first point isn't repeated at the end.
+    pub boundary: Vec<LonLat>,
+    pub restriction: ParkingRestriction,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum ParkingRestriction {
+    /// Only people going home to a residential building inside the zone can park
+    ResidentPermit,
+    /// Anybody can park, but not for longer than this
+    TimeLimit(Duration),
+    /// Anybody can park, but they pay for the time they're parked between these times
+    Paid {
+        start: Time,
+        end: Time,
+        dollars_per_hour: f64,
+    },
+}
+
+impl ParkingZone {
+    pub fn polygon(&self, map: &Map) -> Option<Polygon> {
+        let mut pts: Vec<Pt2D> = map.get_gps_bounds().convert(&self.boundary);
+        if pts.len() < 3 {
+            return None;
+        }
+        pts.push(pts[0]);
+        Ring::new(pts).ok().map(|ring| ring.to_polygon())
+    }
+
+    pub fn describe_restriction(&self) -> String {
+        match self.restriction {
+            ParkingRestriction::ResidentPermit => "residents only".to_string(),
+            ParkingRestriction::TimeLimit(limit) => format!("{} limit", limit),
+            ParkingRestriction::Paid {
+                start,
+                end,
+                dollars_per_hour,
+            } => format!(
+                "${:.2}/hour from {} to {}",
+                dollars_per_hour,
+                start.ampm_tostring(),
+                end.ampm_tostring()
+            ),
+        }
+    }
+
+    /// Can somebody headed to `target` park here at `now`, planning to leave at `leave_at`? If
+    /// they aren't leaving again today, `leave_at` is None.
+    pub fn allows(&self, target: BuildingID, now: Time, leave_at: Option<Time>, map: &Map) -> bool {
+        match self.restriction {
+            ParkingRestriction::ResidentPermit => {
+                let bldg = map.get_b(target);
+                let residential = matches!(
+                    bldg.bldg_type,
+                    BuildingType::Residential { .. } | BuildingType::ResidentialCommercial(_, _)
+                );
+                let home_zone = map
+                    .get_parking_zone(map.get_l(bldg.sidewalk()).parent)
+                    .map(|zone| zone == self);
+                residential && home_zone == Some(true)
+            }
+            ParkingRestriction::TimeLimit(limit) => match leave_at {
+                Some(t) => t - now <= limit,
+                None => false,
+            },
+            ParkingRestriction::Paid { .. } => true,
+        }
+    }
+
+    /// How much somebody parking here at `now` and leaving at `leave_at` pays, in dollars. If
+    /// they aren't leaving again today, they pay until the end of the paid hours.
+    pub fn price(&self, now: Time, leave_at: Option<Time>) -> f64 {
+        match self.restriction {
+            ParkingRestriction::ResidentPermit | ParkingRestriction::TimeLimit(_) => 0.0,
+            ParkingRestriction::Paid {
+                start,
+                end,
+                dollars_per_hour,
+            } => {
+                let from = now.max(start);
+                let to = leave_at.unwrap_or(end).min(end);
+                if from >= to {
+                    return 0.0;
+                }
+                dollars_per_hour * (to - from).inner_seconds() / 3600.0
+            }
+        }
+    }
+
+    /// Maps every road whose middle is inside some zone to that zone. If zones overlap, the first
+    /// one wins.
+    pub(crate) fn find_roads(map: &Map) -> BTreeMap<RoadID, usize> {
+        let mut roads = BTreeMap::new();
+        for (idx, zone) in map.get_parking_zones().iter().enumerate() {
+            if let Some(poly) = zone.polygon(map) {
+                for r in map.all_roads() {
+                    if poly.contains_pt(r.center_pts.middle()) {
+                        roads.entry(r.id).or_insert(idx);
+                    }
+                }
+            }
+        }
+        roads
+    }
+}
+
+impl Map {
+    pub fn get_parking_zones(&self) -> &Vec<ParkingZone> {
+        &self.parking_zones
+    }
+
+    /// The parking zone restricting on-street parking along this road, if there is one
+    pub fn get_parking_zone(&self, r: RoadID) -> Option<&ParkingZone> {
+        let idx = *self.parking_zone_roads.get(&r)?;
+        Some(&self.parking_zones[idx])
+    }
+}
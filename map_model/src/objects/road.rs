@@ -4,11 +4,11 @@ use enumset::EnumSet;
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_usize, serialize_usize, Tags};
-use geom::{Distance, PolyLine, Polygon, Speed};
+use geom::{Distance, PolyLine, Polygon, Speed, Time, UnitFmt};
 
 use crate::raw::{OriginalRoad, RestrictionType};
 use crate::{
-    osm, AccessRestrictions, BusStopID, IntersectionID, Lane, LaneID, LaneType, Map,
+    osm, AccessRestrictions, BusStopID, DrivingSide, IntersectionID, Lane, LaneID, LaneType, Map,
     PathConstraints, Zone,
 };
 
@@ -101,6 +101,22 @@ pub struct Road {
     pub orig_id: OriginalRoad,
     pub speed_limit: Speed,
     pub access_restrictions: AccessRestrictions,
+    /// A modal filter, like a bollard, placed in the middle of the road. Cars and trucks can't
+    /// pass through, but pedestrians, bikes, and buses can. Only map edits add these.
+    #[serde(with = "crate::map::added_in_v1")]
+    pub modal_filter: bool,
+    /// Vehicles can't turn from this road onto some others. Unlike `turn_restrictions`, only map
+    /// edits add these.
+    #[serde(with = "crate::map::added_in_v1")]
+    pub banned_turns: Vec<BannedTurn>,
+    /// Speed limits that only apply during part of the day, overriding `speed_limit`. Only map
+    /// edits add these.
+    #[serde(with = "crate::map::added_in_v5")]
+    pub timed_speed_limits: Vec<TimedSpeedLimit>,
+    /// How many times an hour a delivery vehicle stops in one of this road's travel or bike lanes,
+    /// blocking it for a while. Only map edits set this.
+    #[serde(with = "crate::map::added_in_v2")]
+    pub deliveries_per_hour: usize,
     pub zorder: isize,
 
     /// Invariant: A road must contain at least one child
@@ -114,6 +130,83 @@ pub struct Road {
     pub dst_i: IntersectionID,
 }
 
+/// A map edit banning vehicles from turning from one road onto another, like "no left turn 7-9am".
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BannedTurn {
+    /// The road that can't be turned onto. This refers to OSM IDs, so edits remain valid when the
+    /// map is rebuilt.
+    pub to: OriginalRoad,
+    /// If set, the ban only applies from the first time until the second, and the turns still
+    /// exist; pathfinding avoids them during this window. If the first time is later, the window
+    /// wraps around midnight. Otherwise, the turns are removed entirely.
+    pub window: Option<(Time, Time)>,
+}
+
+impl BannedTurn {
+    pub fn applies_at(&self, time: Time) -> bool {
+        match self.window {
+            Some((start, end)) => {
+                if start <= end {
+                    time >= start && time < end
+                } else {
+                    time >= start || time < end
+                }
+            }
+            None => true,
+        }
+    }
+
+    pub fn describe(&self, map: &Map) -> String {
+        let to = match map.find_r_by_osm_id(self.to) {
+            Ok(r) => map.get_r(r).get_name(None),
+            Err(_) => self.to.to_string(),
+        };
+        match self.window {
+            Some((start, end)) => format!(
+                "no turning onto {} from {} to {}",
+                to,
+                start.ampm_tostring(),
+                end.ampm_tostring()
+            ),
+            None => format!("no turning onto {}", to),
+        }
+    }
+}
+
+/// A map edit changing a road's speed limit during part of the day, like a school zone with
+/// flashing beacons or a lower limit at night.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimedSpeedLimit {
+    pub speed_limit: Speed,
+    /// The limit applies from the first time until the second. If the first time is later, the
+    /// window wraps around midnight.
+    pub window: (Time, Time),
+    /// School zone limits are signed with flashing beacons. This only affects how the limit is
+    /// described.
+    pub school_zone: bool,
+}
+
+impl TimedSpeedLimit {
+    pub fn applies_at(&self, time: Time) -> bool {
+        let (start, end) = self.window;
+        if start <= end {
+            time >= start && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+
+    pub fn describe(&self, units: &UnitFmt) -> String {
+        format!(
+            "{}{} from {} to {}",
+            self.speed_limit.to_string(units),
+            if self.school_zone { " school zone" } else { "" },
+            self.window.0.ampm_tostring(),
+            self.window.1.ampm_tostring()
+        )
+    }
+}
+
 impl Road {
     /// Returns all lanes from the left side of the road to right. Left/right is determined by the
     /// orientation of center_pts.
@@ -145,6 +238,15 @@ impl Road {
         panic!("{} doesn't contain {}", self.id, lane);
     }
 
+    /// The speed limit at this time of day. If several timed limits apply, the first one wins.
+    pub fn speed_limit_at(&self, time: Time) -> Speed {
+        self.timed_speed_limits
+            .iter()
+            .find(|x| x.applies_at(time))
+            .map(|x| x.speed_limit)
+            .unwrap_or(self.speed_limit)
+    }
+
     pub fn parking_to_driving(&self, parking: LaneID, map: &Map) -> Option<LaneID> {
         self.find_closest_lane(parking, |l| l.is_driving(), map)
     }
@@ -328,8 +430,17 @@ impl Road {
         self.lanes_ltr().len() == 1 && self.lanes_ltr()[0].2 == LaneType::LightRail
     }
 
+    /// Is this a path only for pedestrians? Older maps represent these as a single sidewalk.
     pub fn is_footway(&self) -> bool {
-        self.lanes_ltr().len() == 1 && self.lanes_ltr()[0].2 == LaneType::Sidewalk
+        self.lanes_ltr().len() == 1
+            && (self.lanes_ltr()[0].2 == LaneType::Footway
+                || self.lanes_ltr()[0].2 == LaneType::Sidewalk)
+    }
+
+    /// Is this part of the ring of a roundabout? Traffic on it circulates one-way and has the
+    /// right of way over traffic entering.
+    pub fn is_roundabout(&self) -> bool {
+        self.osm_tags.is("junction", "roundabout")
     }
 
     pub fn is_service(&self) -> bool {
@@ -386,8 +497,9 @@ impl Road {
 // seemed to really need to still handle lanes going outward from the "center" line. Should keep
 // whittling this down, probably. These very much don't handle multiple direction changes.
 impl Road {
-    /// These are ordered from closest to center lane (left-most when driving on the right) to
-    /// farthest (sidewalk)
+    /// These are ordered from left to right, from the point of view of somebody traveling along
+    /// them. When driving on the right, that's from the center lane out to the sidewalk; when
+    /// driving on the left, it's the opposite.
     pub(crate) fn children_forwards(&self) -> Vec<(LaneID, LaneType)> {
         let mut result = Vec::new();
         for (l, dir, lt) in self.lanes_ltr() {
@@ -408,8 +520,8 @@ impl Road {
         result
     }
 
-    /// lane must belong to this road. Offset 0 is the centermost lane on each side of a road, then
-    /// it counts up from there.
+    /// lane must belong to this road. Offset 0 is the left-most lane on each side of a road, from
+    /// the point of view of somebody traveling along it, then it counts up from there.
     pub(crate) fn dir_and_offset(&self, lane: LaneID) -> (Direction, usize) {
         for &dir in [Direction::Fwd, Direction::Back].iter() {
             if let Some(idx) = self.children(dir).iter().position(|pair| pair.0 == lane) {
@@ -428,7 +540,20 @@ impl Road {
         }
     }
 
-    /// Returns lanes from the "center" going out
+    /// Lanes in one direction, ordered from the curb to the center line.
+    pub(crate) fn children_from_curb(
+        &self,
+        dir: Direction,
+        driving_side: DrivingSide,
+    ) -> Vec<(LaneID, LaneType)> {
+        let mut lanes = self.children(dir);
+        if driving_side == DrivingSide::Right {
+            lanes.reverse();
+        }
+        lanes
+    }
+
+    /// Returns lanes ordered from left to right, from the point of view of traffic along them
     pub(crate) fn incoming_lanes(&self, i: IntersectionID) -> Vec<(LaneID, LaneType)> {
         if self.src_i == i {
             self.children_backwards()
@@ -439,7 +564,7 @@ impl Road {
         }
     }
 
-    /// Returns lanes from the "center" going out
+    /// Returns lanes ordered from left to right, from the point of view of traffic along them
     pub(crate) fn outgoing_lanes(&self, i: IntersectionID) -> Vec<(LaneID, LaneType)> {
         if self.src_i == i {
             self.children_forwards()
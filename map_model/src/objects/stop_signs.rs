@@ -96,6 +96,14 @@ impl ControlStopSign {
             return ss;
         }
 
+        // Traffic entering a roundabout yields to traffic already circulating.
+        if map.get_i(id).is_roundabout(map) {
+            for (r, cfg) in ss.roads.iter_mut() {
+                cfg.must_stop = !map.get_r(*r).is_roundabout();
+            }
+            return ss;
+        }
+
         // What's the rank of each road?
         let mut rank: HashMap<RoadID, osm::RoadRank> = HashMap::new();
         for r in ss.roads.keys() {
@@ -123,8 +131,21 @@ impl ControlStopSign {
     pub fn get_priority(&self, turn: TurnID, map: &Map) -> TurnPriority {
         match map.get_t(turn).turn_type {
             TurnType::SharedSidewalkCorner => TurnPriority::Protected,
-            // TODO This actually feels like a policy bit that should be flippable.
-            TurnType::Crosswalk => TurnPriority::Protected,
+            // Pedestrians only have the right of way at marked crosswalks
+            TurnType::Crosswalk => {
+                if map
+                    .get_i(turn.parent)
+                    .get_crosswalk(turn)
+                    .map(|c| c.marked)
+                    .unwrap_or(true)
+                {
+                    TurnPriority::Protected
+                } else {
+                    TurnPriority::Yield
+                }
+            }
+            // Turning around always means yielding to everybody else
+            TurnType::UTurn => TurnPriority::Yield,
             _ => {
                 if self.roads[&map.get_l(turn.src).parent].must_stop {
                     TurnPriority::Yield
@@ -0,0 +1,107 @@
+//! A cordon toll charges vehicles each time they drive into part of the map. Toll zones only exist
+//! as map edits.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{LonLat, Polygon, Pt2D, Ring, Time};
+
+use crate::{Map, Path, PathStep, RoadID, TurnID};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TollZone {
+    pub name: String,
+    /// In GPS, so the zone survives rebuilding the map. The first point isn't repeated at the end.
+    pub boundary: Vec<LonLat>,
+    /// Sorted by time. Each price, in dollars, applies from its time until the next entry. Before
+    /// the first entry, entering is free.
+    pub schedule: Vec<(Time, f64)>,
+}
+
+impl TollZone {
+    /// How much a vehicle pays to enter the zone at this time
+    pub fn price_at(&self, time: Time) -> f64 {
+        let mut price = 0.0;
+        for (start, p) in &self.schedule {
+            if *start <= time {
+                price = *p;
+            }
+        }
+        price
+    }
+
+    pub fn polygon(&self, map: &Map) -> Option<Polygon> {
+        let mut pts: Vec<Pt2D> = map.get_gps_bounds().convert(&self.boundary);
+        if pts.len() < 3 {
+            return None;
+        }
+        pts.push(pts[0]);
+        Ring::new(pts).ok().map(|ring| ring.to_polygon())
+    }
+
+    /// Roads whose middle is inside the zone
+    pub fn roads_inside(&self, map: &Map) -> BTreeSet<RoadID> {
+        match self.polygon(map) {
+            Some(poly) => map
+                .all_roads()
+                .iter()
+                .filter(|r| poly.contains_pt(r.center_pts.middle()))
+                .map(|r| r.id)
+                .collect(),
+            None => BTreeSet::new(),
+        }
+    }
+
+    pub fn describe_schedule(&self) -> String {
+        if self.schedule.is_empty() {
+            return "free".to_string();
+        }
+        self.schedule
+            .iter()
+            .map(|(t, p)| format!("${:.2} from {}", p, t.ampm_tostring()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Finds every turn from a road outside of a zone to a road inside, mapping it to the zone.
+    /// If zones overlap, the first one wins.
+    pub(crate) fn find_entrances(map: &Map) -> BTreeMap<TurnID, usize> {
+        let mut entrances = BTreeMap::new();
+        for (idx, zone) in map.get_toll_zones().iter().enumerate() {
+            let inside = zone.roads_inside(map);
+            for t in map.all_turns().keys() {
+                let src = map.get_l(t.src).parent;
+                let dst = map.get_l(t.dst).parent;
+                if !inside.contains(&src) && inside.contains(&dst) {
+                    entrances.entry(*t).or_insert(idx);
+                }
+            }
+        }
+        entrances
+    }
+}
+
+impl Map {
+    pub fn get_toll_zones(&self) -> &Vec<TollZone> {
+        &self.toll_zones
+    }
+
+    /// If this turn enters a toll zone, returns the zone's index and the price at this time.
+    pub fn toll_for_turn(&self, t: TurnID, time: Time) -> Option<(usize, f64)> {
+        let idx = *self.toll_entrances.get(&t)?;
+        Some((idx, self.toll_zones[idx].price_at(time)))
+    }
+
+    /// The total price of all tolls along a path, assuming they're all paid at this time.
+    pub fn tolls_along_path(&self, path: &Path, time: Time) -> f64 {
+        path.get_steps()
+            .iter()
+            .filter_map(|step| match step {
+                PathStep::Turn(t) => self.toll_for_turn(*t, time),
+                _ => None,
+            })
+            .map(|(_, price)| price)
+            .sum()
+    }
+}
@@ -33,6 +33,8 @@ pub enum TurnType {
     Straight,
     Right,
     Left,
+    /// Turning around to head back along the same road
+    UTurn,
 }
 
 impl TurnType {
@@ -119,23 +121,24 @@ impl Turn {
     pub fn penalty(&self, map: &Map) -> (usize, usize, usize) {
         let from = map.get_l(self.id.src);
         let to = map.get_l(self.id.dst);
+        let driving_side = map.get_config().driving_side;
 
-        // Starting from the farthest from the center line (right in the US), where is this travel
-        // lane? Filters by the lane type and ignores lanes that don't go to the target road.
+        // Starting from the curb (right in the US), where is this travel lane? Filters by the lane
+        // type and ignores lanes that don't go to the target road.
         let from_idx = {
             let mut cnt = 0;
             let r = map.get_r(from.parent);
-            for (l, lt) in r.children(r.dir(from.id)).iter().rev() {
-                if from.lane_type != *lt {
+            for (l, lt) in r.children_from_curb(r.dir(from.id), driving_side) {
+                if from.lane_type != lt {
                     continue;
                 }
                 if map
-                    .get_turns_from_lane(*l)
+                    .get_turns_from_lane(l)
                     .into_iter()
                     .any(|t| map.get_l(t.id.dst).parent == to.parent)
                 {
                     cnt += 1;
-                    if from.id == *l {
+                    if from.id == l {
                         break;
                     }
                 }
@@ -143,17 +146,17 @@ impl Turn {
             cnt
         };
 
-        // Starting from the farthest from the center line (right in the US), where is this travel
-        // lane? Filters by the lane type.
+        // Starting from the curb (right in the US), where is this travel lane? Filters by the lane
+        // type.
         let to_idx = {
             let mut cnt = 0;
             let r = map.get_r(to.parent);
-            for (l, lt) in r.children(r.dir(to.id)).iter().rev() {
-                if to.lane_type != *lt {
+            for (l, lt) in r.children_from_curb(r.dir(to.id), driving_side) {
+                if to.lane_type != lt {
                     continue;
                 }
                 cnt += 1;
-                if to.id == *l {
+                if to.id == l {
                     break;
                 }
             }
@@ -172,7 +175,7 @@ impl Turn {
         // matter.
         let lt_cost = if to.is_biking() || to.is_bus() { 0 } else { 1 };
 
-        // Keep right (in the US)
+        // Keep to the curb side (right in the US)
         let slow_lane = if to_idx > 1 { 1 } else { 0 };
 
         (lt_cost, lc_cost, slow_lane)
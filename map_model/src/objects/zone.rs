@@ -73,7 +73,9 @@ impl Zone {
             for l in map.get_r(*r).all_lanes() {
                 if req.constraints.can_use(map.get_l(l), map) {
                     for turn in map.get_turns_for(l, req.constraints) {
-                        if !self.borders.contains(&turn.id.parent) {
+                        if !self.borders.contains(&turn.id.parent)
+                            && !req.constraints.blocked_by_modal_filter(turn.id, map)
+                        {
                             graph.add_edge(turn.id.src, turn.id.dst, turn.id);
                         }
                     }
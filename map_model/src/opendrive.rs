@@ -0,0 +1,369 @@
+//! Export the map to OpenDRIVE 1.4 (<https://www.asam.net/standards/detail/opendrive/>), so other
+//! driving simulators like CARLA and esmini can use the cleaned-up geometry.
+//!
+//! Every Road becomes an OpenDRIVE road, with its reference line between the lanes going in each
+//! direction. Every Intersection with vehicle turns becomes a junction, and each of those turns
+//! becomes a connecting road with a single lane. Sidewalks are exported as lanes, but crosswalks
+//! and other walking turns aren't.
+//!
+//! OpenDRIVE 1.4 assumes right-hand traffic and has no attribute to say otherwise, so on maps
+//! where people drive on the left, every road gets a `<userData code="trafficRule" value="LHT"/>`.
+//!
+//! Coordinates are in meters, relative to the top-left corner of the map's bounds, with y
+//! pointing north. No geoReference is written.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Error, Write};
+
+use geom::{Distance, PolyLine, Pt2D};
+
+use crate::{
+    Direction, DrivingSide, IntersectionID, Lane, LaneID, LaneType, Map, Road, Turn, TurnType,
+};
+
+pub fn export(map: &Map, path: &str) -> Result<(), Error> {
+    let mut f = BufWriter::new(File::create(path)?);
+    let bounds = map.get_bounds();
+
+    writeln!(f, r#"<?xml version="1.0" standalone="yes"?>"#)?;
+    writeln!(f, "<OpenDRIVE>")?;
+    writeln!(
+        f,
+        r#"  <header revMajor="1" revMinor="4" name="{}" version="1.00" north="{}" south="0" east="{}" west="0"/>"#,
+        escape(&map.get_name().describe()),
+        bounds.max_y - bounds.min_y,
+        bounds.max_x - bounds.min_x
+    )?;
+
+    // Every road has to come before any junction, so figure out the connecting roads first.
+    // Connecting roads need IDs that don't collide with any Road.
+    let mut next_id = map.all_roads().len();
+    let mut junctions: BTreeMap<IntersectionID, Vec<(&Turn, usize)>> = BTreeMap::new();
+    for i in map.all_intersections() {
+        for t in &i.turns {
+            let turn = map.get_t(*t);
+            if exported_turn(map, turn) {
+                junctions
+                    .entry(i.id)
+                    .or_insert_with(Vec::new)
+                    .push((turn, next_id));
+                next_id += 1;
+            }
+        }
+    }
+
+    for r in map.all_roads() {
+        write_road(&mut f, map, r, &junctions)?;
+    }
+    for connections in junctions.values() {
+        for (turn, id) in connections {
+            write_connecting_road(&mut f, map, turn, *id)?;
+        }
+    }
+
+    for (i, connections) in junctions {
+        writeln!(
+            f,
+            r#"  <junction id="{}" name="{}">"#,
+            i.0,
+            escape(&i.to_string())
+        )?;
+        for (idx, (turn, id)) in connections.into_iter().enumerate() {
+            writeln!(
+                f,
+                r#"    <connection id="{}" incomingRoad="{}" connectingRoad="{}" contactPoint="start">"#,
+                idx,
+                map.get_l(turn.id.src).parent.0,
+                id
+            )?;
+            writeln!(
+                f,
+                r#"      <laneLink from="{}" to="-1"/>"#,
+                lane_id(map, turn.id.src)
+            )?;
+            writeln!(f, "    </connection>")?;
+        }
+        writeln!(f, "  </junction>")?;
+    }
+
+    writeln!(f, "</OpenDRIVE>")?;
+    Ok(())
+}
+
+fn write_road<W: Write>(
+    f: &mut W,
+    map: &Map,
+    r: &Road,
+    junctions: &BTreeMap<IntersectionID, Vec<(&Turn, usize)>>,
+) -> Result<(), Error> {
+    let lanes = r.lanes_ltr();
+    let split = reference_line_split(map, r);
+    let total_width: Distance = lanes.iter().map(|(l, _, _)| map.get_l(*l).width).sum();
+    let left_width: Distance = lanes[..split]
+        .iter()
+        .map(|(l, _, _)| map.get_l(*l).width)
+        .sum();
+
+    writeln!(
+        f,
+        r#"  <road name="{}" length="{}" id="{}" junction="-1">"#,
+        escape(&r.get_name(None)),
+        r.center_pts.length().inner_meters(),
+        r.id.0
+    )?;
+    writeln!(f, "    <link>")?;
+    // Only intersections with some vehicle turns become junctions, so roads leading to borders or
+    // footway-only intersections have no link on that end
+    if junctions.contains_key(&r.src_i) {
+        writeln!(
+            f,
+            r#"      <predecessor elementType="junction" elementId="{}"/>"#,
+            r.src_i.0
+        )?;
+    }
+    if junctions.contains_key(&r.dst_i) {
+        writeln!(
+            f,
+            r#"      <successor elementType="junction" elementId="{}"/>"#,
+            r.dst_i.0
+        )?;
+    }
+    writeln!(f, "    </link>")?;
+    writeln!(f, r#"    <type s="0" type="town">"#)?;
+    writeln!(
+        f,
+        r#"      <speed max="{}" unit="m/s"/>"#,
+        r.speed_limit.inner_meters_per_second()
+    )?;
+    writeln!(f, "    </type>")?;
+    write_plan_view(f, map, &r.center_pts)?;
+
+    writeln!(f, "    <lanes>")?;
+    // The reference line is the physical center of the road, so shift the center lane to the
+    // boundary between the two directions. Positive offsets are to the left.
+    writeln!(
+        f,
+        r#"      <laneOffset s="0" a="{}" b="0" c="0" d="0"/>"#,
+        (total_width / 2.0 - left_width).inner_meters()
+    )?;
+    writeln!(f, r#"      <laneSection s="0">"#)?;
+    if split > 0 {
+        writeln!(f, "        <left>")?;
+        for (l, _, _) in &lanes[..split] {
+            write_lane(f, map.get_l(*l), lane_id(map, *l))?;
+        }
+        writeln!(f, "        </left>")?;
+    }
+    writeln!(f, "        <center>")?;
+    writeln!(f, r#"          <lane id="0" type="none" level="false"/>"#)?;
+    writeln!(f, "        </center>")?;
+    if split < lanes.len() {
+        writeln!(f, "        <right>")?;
+        for (l, _, _) in &lanes[split..] {
+            write_lane(f, map.get_l(*l), lane_id(map, *l))?;
+        }
+        writeln!(f, "        </right>")?;
+    }
+    writeln!(f, "      </laneSection>")?;
+    writeln!(f, "    </lanes>")?;
+    write_traffic_rule(f, map)?;
+    writeln!(f, "  </road>")?;
+    Ok(())
+}
+
+fn write_connecting_road<W: Write>(
+    f: &mut W,
+    map: &Map,
+    turn: &Turn,
+    id: usize,
+) -> Result<(), Error> {
+    let src = map.get_l(turn.id.src);
+    let dst = map.get_l(turn.id.dst);
+
+    writeln!(
+        f,
+        r#"  <road name="{}" length="{}" id="{}" junction="{}">"#,
+        escape(&turn.id.to_string()),
+        turn.geom.length().inner_meters(),
+        id,
+        turn.id.parent.0
+    )?;
+    writeln!(f, "    <link>")?;
+    // Lanes going against the road's direction end at the start of the road
+    writeln!(
+        f,
+        r#"      <predecessor elementType="road" elementId="{}" contactPoint="{}"/>"#,
+        src.parent.0,
+        if src.dst_i == map.get_r(src.parent).dst_i {
+            "end"
+        } else {
+            "start"
+        }
+    )?;
+    writeln!(
+        f,
+        r#"      <successor elementType="road" elementId="{}" contactPoint="{}"/>"#,
+        dst.parent.0,
+        if dst.src_i == map.get_r(dst.parent).src_i {
+            "start"
+        } else {
+            "end"
+        }
+    )?;
+    writeln!(f, "    </link>")?;
+    write_plan_view(f, map, &turn.geom)?;
+
+    writeln!(f, "    <lanes>")?;
+    // The turn's geometry follows the center of the lane
+    writeln!(
+        f,
+        r#"      <laneOffset s="0" a="{}" b="0" c="0" d="0"/>"#,
+        (src.width / 2.0).inner_meters()
+    )?;
+    writeln!(f, r#"      <laneSection s="0">"#)?;
+    writeln!(f, "        <center>")?;
+    writeln!(f, r#"          <lane id="0" type="none" level="false"/>"#)?;
+    writeln!(f, "        </center>")?;
+    writeln!(f, "        <right>")?;
+    writeln!(
+        f,
+        r#"          <lane id="-1" type="{}" level="false">"#,
+        lane_type(src.lane_type)
+    )?;
+    writeln!(f, "            <link>")?;
+    writeln!(
+        f,
+        r#"              <predecessor id="{}"/>"#,
+        lane_id(map, src.id)
+    )?;
+    writeln!(
+        f,
+        r#"              <successor id="{}"/>"#,
+        lane_id(map, dst.id)
+    )?;
+    writeln!(f, "            </link>")?;
+    writeln!(
+        f,
+        r#"            <width sOffset="0" a="{}" b="0" c="0" d="0"/>"#,
+        src.width.inner_meters()
+    )?;
+    writeln!(f, "          </lane>")?;
+    writeln!(f, "        </right>")?;
+    writeln!(f, "      </laneSection>")?;
+    writeln!(f, "    </lanes>")?;
+    write_traffic_rule(f, map)?;
+    writeln!(f, "  </road>")?;
+    Ok(())
+}
+
+fn write_plan_view<W: Write>(f: &mut W, map: &Map, pl: &PolyLine) -> Result<(), Error> {
+    writeln!(f, "    <planView>")?;
+    let mut s = 0.0;
+    for line in pl.lines() {
+        let (x1, y1) = to_xy(map, line.pt1());
+        let (x2, y2) = to_xy(map, line.pt2());
+        let length = line.length().inner_meters();
+        writeln!(
+            f,
+            r#"      <geometry s="{}" x="{}" y="{}" hdg="{}" length="{}">"#,
+            s,
+            x1,
+            y1,
+            (y2 - y1).atan2(x2 - x1),
+            length
+        )?;
+        writeln!(f, "        <line/>")?;
+        writeln!(f, "      </geometry>")?;
+        s += length;
+    }
+    writeln!(f, "    </planView>")?;
+    Ok(())
+}
+
+fn write_lane<W: Write>(f: &mut W, lane: &Lane, id: isize) -> Result<(), Error> {
+    writeln!(
+        f,
+        r#"          <lane id="{}" type="{}" level="false">"#,
+        id,
+        lane_type(lane.lane_type)
+    )?;
+    writeln!(
+        f,
+        r#"            <width sOffset="0" a="{}" b="0" c="0" d="0"/>"#,
+        lane.width.inner_meters()
+    )?;
+    writeln!(f, "          </lane>")?;
+    Ok(())
+}
+
+/// Map coordinates have y pointing down; OpenDRIVE has y pointing up.
+fn to_xy(map: &Map, pt: Pt2D) -> (f64, f64) {
+    let bounds = map.get_bounds();
+    (pt.x() - bounds.min_x, bounds.max_y - pt.y())
+}
+
+/// How many lanes, counting from the left, are on the left side of the reference line? Lanes on
+/// the left go against the road's direction when driving on the right, and with it when driving on
+/// the left. If lanes have been reversed so the directions are mixed up, the lanes on the wrong
+/// side are exported as if they went the same way as their neighbors.
+fn reference_line_split(map: &Map, r: &Road) -> usize {
+    let left_dir = match map.config.driving_side {
+        DrivingSide::Right => Direction::Back,
+        DrivingSide::Left => Direction::Fwd,
+    };
+    let lanes = r.lanes_ltr();
+    lanes
+        .iter()
+        .position(|(_, dir, _)| *dir != left_dir)
+        .unwrap_or(lanes.len())
+}
+
+/// OpenDRIVE numbers lanes outwards from the reference line: positive to the left, negative to the
+/// right.
+fn lane_id(map: &Map, l: LaneID) -> isize {
+    let r = map.get_parent(l);
+    let split = reference_line_split(map, r) as isize;
+    let idx = r.offset(l) as isize;
+    if idx < split {
+        split - idx
+    } else {
+        -(idx - split + 1)
+    }
+}
+
+fn exported_turn(map: &Map, turn: &Turn) -> bool {
+    turn.turn_type != TurnType::Crosswalk
+        && turn.turn_type != TurnType::SharedSidewalkCorner
+        && map.get_l(turn.id.src).lane_type.is_for_moving_vehicles()
+}
+
+fn lane_type(lt: LaneType) -> &'static str {
+    match lt {
+        LaneType::Driving => "driving",
+        LaneType::Parking => "parking",
+        LaneType::Sidewalk | LaneType::Footway => "sidewalk",
+        LaneType::Shoulder => "shoulder",
+        LaneType::Biking => "biking",
+        // OpenDRIVE 1.4 has no bus lanes
+        LaneType::Bus => "restricted",
+        LaneType::SharedLeftTurn => "bidirectional",
+        LaneType::Construction => "roadWorks",
+        LaneType::LightRail => "tram",
+    }
+}
+
+/// Right-hand traffic is the default, so only left-hand traffic needs to be recorded.
+fn write_traffic_rule<W: Write>(f: &mut W, map: &Map) -> Result<(), Error> {
+    if map.config.driving_side == DrivingSide::Left {
+        writeln!(f, r#"    <userData code="trafficRule" value="LHT"/>"#)?;
+    }
+    Ok(())
+}
+
+fn escape(x: &str) -> String {
+    x.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
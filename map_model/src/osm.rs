@@ -31,6 +31,17 @@ pub const ENDPT_BACK: &str = "abst:endpt_back";
 pub const INFERRED_PARKING: &str = "abst:parking_inferred";
 pub const INFERRED_SIDEWALKS: &str = "abst:sidewalks_inferred";
 
+// Footpaths generated to cut across open spaces have this, with the OSM ID of the area.
+pub const OPEN_SPACE: &str = "abst:open_space";
+
+// The kerb tag of a crossing node along a road, copied to the road. START means the crossing is
+// nearer the first point of the road, END the last.
+pub const CROSSING_KERB_START: &str = "abst:crossing_kerb_start";
+pub const CROSSING_KERB_END: &str = "abst:crossing_kerb_end";
+
+// Roads that `MapConfig::clean_geometry` mustn't merge, from `raw::ConsolidationHints`.
+pub const DONT_MERGE: &str = "abst:dont_merge";
+
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum RoadRank {
     Local,
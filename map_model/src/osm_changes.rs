@@ -0,0 +1,217 @@
+//! Some map edits describe how a road really looks -- how many lanes it has, whether there are bike
+//! lanes or sidewalks, and the speed limit. If somebody notices a road is mapped wrong while
+//! editing, they can fix it in A/B Street, then upstream the correction to OpenStreetMap. This
+//! translates edited roads back into tags on the OSM ways they came from.
+//!
+//! Only tags describing something the edits actually changed are touched. Both the original and
+//! edited road are described the same way, and only the differences are kept, so tags the importer
+//! interpreted loosely aren't rewritten.
+
+use std::collections::BTreeMap;
+
+use abstutil::{MultiMap, Tags};
+use geom::Speed;
+
+use crate::{osm, Direction, DrivingSide, EditRoad, LaneType, Map, Road};
+
+/// New tags for one OSM way.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WayChange {
+    pub way: osm::WayID,
+    /// None means the tag should be removed.
+    pub tags: BTreeMap<String, Option<String>>,
+}
+
+pub struct OsmChanges {
+    pub ways: Vec<WayChange>,
+    /// An OSM way is sometimes split into several roads. If the edits only change some of them,
+    /// the way has to be split in OSM first, so it's left alone here.
+    pub conflicting_ways: Vec<osm::WayID>,
+}
+
+impl WayChange {
+    /// Updates the tags of the way. Shorthand tags that'd contradict the new ones, like
+    /// `cycleway=lane` when `cycleway:left` is set, are removed.
+    pub fn apply(&self, tags: &mut Tags) {
+        for (k, v) in &self.tags {
+            let shorthand: &[&str] = match k.as_ref() {
+                "cycleway:left" | "cycleway:right" => &["cycleway", "cycleway:both"],
+                osm::SIDEWALK => &["sidewalk:left", "sidewalk:right", "sidewalk:both"],
+                _ => &[],
+            };
+            for x in shorthand {
+                tags.remove(x);
+            }
+            match v {
+                Some(v) => tags.insert(k.clone(), v.clone()),
+                None => {
+                    tags.remove(k);
+                }
+            }
+        }
+    }
+}
+
+/// Finds the OSM tags that should change for every road modified by the current edits.
+pub fn find_changes(map: &Map) -> OsmChanges {
+    let mut roads_per_way = MultiMap::new();
+    for r in map.all_roads() {
+        roads_per_way.insert(r.orig_id.osm_way_id, r.id);
+    }
+
+    let mut result = OsmChanges {
+        ways: Vec::new(),
+        conflicting_ways: Vec::new(),
+    };
+    let mut seen = Vec::new();
+    for r in &map.get_edits().changed_roads {
+        let way = map.get_r(*r).orig_id.osm_way_id;
+        // Roads created by the map editor don't exist in OSM
+        if way.0 <= 0 || seen.contains(&way) {
+            continue;
+        }
+        seen.push(way);
+
+        let mut changes: Vec<BTreeMap<String, Option<String>>> = roads_per_way
+            .get(way)
+            .iter()
+            .map(|r| tag_changes(map, map.get_r(*r)))
+            .collect();
+        if changes.iter().any(|x| *x != changes[0]) {
+            result.conflicting_ways.push(way);
+        } else if !changes[0].is_empty() {
+            result.ways.push(WayChange {
+                way,
+                tags: changes.pop().unwrap(),
+            });
+        }
+    }
+    result
+}
+
+fn tag_changes(map: &Map, road: &Road) -> BTreeMap<String, Option<String>> {
+    let cfg = map.get_config();
+    let mph = road
+        .osm_tags
+        .get(osm::MAXSPEED)
+        .map(|x| x.contains("mph"))
+        .unwrap_or(false);
+    let before = describe(
+        &EditRoad::get_orig_from_osm(road, cfg),
+        cfg.driving_side,
+        mph,
+    );
+    let after = describe(&map.get_r_edit(road.id), cfg.driving_side, mph);
+    after
+        .into_iter()
+        .filter(|(k, v)| before[k] != *v)
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
+}
+
+/// Expresses the parts of a road that have a real-world equivalent as OSM tags.
+fn describe(
+    road: &EditRoad,
+    driving_side: DrivingSide,
+    mph: bool,
+) -> BTreeMap<&'static str, Option<String>> {
+    let count = |types: &[LaneType], dir: Direction| {
+        road.lanes_ltr
+            .iter()
+            .filter(|(lt, d)| types.contains(lt) && *d == dir)
+            .count()
+    };
+    let fwd = count(&[LaneType::Driving, LaneType::Bus], Direction::Fwd);
+    let back = count(&[LaneType::Driving, LaneType::Bus], Direction::Back);
+    // Lanes going forwards are on the right side of a way when driving on the right
+    let (right, left) = match driving_side {
+        DrivingSide::Right => (Direction::Fwd, Direction::Back),
+        DrivingSide::Left => (Direction::Back, Direction::Fwd),
+    };
+    let yes_no = |present: bool, value: &str| {
+        Some(if present {
+            value.to_string()
+        } else {
+            "no".to_string()
+        })
+    };
+
+    let mut tags = BTreeMap::new();
+    tags.insert(
+        "lanes",
+        if fwd + back > 0 {
+            Some((fwd + back).to_string())
+        } else {
+            None
+        },
+    );
+    let two_way = fwd > 0 && back > 0;
+    tags.insert(
+        "lanes:forward",
+        if two_way { Some(fwd.to_string()) } else { None },
+    );
+    tags.insert(
+        "lanes:backward",
+        if two_way {
+            Some(back.to_string())
+        } else {
+            None
+        },
+    );
+    tags.insert(
+        "oneway",
+        if two_way || fwd + back == 0 {
+            None
+        } else if back == 0 {
+            Some("yes".to_string())
+        } else {
+            Some("-1".to_string())
+        },
+    );
+
+    let bike_fwd = count(&[LaneType::Biking], Direction::Fwd) > 0;
+    let bike_back = count(&[LaneType::Biking], Direction::Back) > 0;
+    let (bike_right, bike_left) = if right == Direction::Fwd {
+        (bike_fwd, bike_back)
+    } else {
+        (bike_back, bike_fwd)
+    };
+    tags.insert("cycleway:right", yes_no(bike_right, "lane"));
+    tags.insert("cycleway:left", yes_no(bike_left, "lane"));
+    let contraflow = (back == 0 && bike_back) || (fwd == 0 && bike_fwd);
+    tags.insert(
+        "oneway:bicycle",
+        if contraflow && !two_way {
+            Some("no".to_string())
+        } else {
+            None
+        },
+    );
+
+    let sidewalk_right = count(&[LaneType::Sidewalk], right) > 0;
+    let sidewalk_left = count(&[LaneType::Sidewalk], left) > 0;
+    tags.insert(
+        osm::SIDEWALK,
+        Some(
+            match (sidewalk_left, sidewalk_right) {
+                (true, true) => "both",
+                (true, false) => "left",
+                (false, true) => "right",
+                (false, false) => "no",
+            }
+            .to_string(),
+        ),
+    );
+
+    tags.insert(osm::MAXSPEED, Some(format_speed(road.speed_limit, mph)));
+    tags
+}
+
+fn format_speed(speed: Speed, mph: bool) -> String {
+    let mps = speed.inner_meters_per_second();
+    if mph {
+        format!("{} mph", (mps * 2.23694).round())
+    } else {
+        format!("{}", (mps * 3.6).round())
+    }
+}
@@ -15,6 +15,8 @@ pub struct ContractionHierarchyPathfinder {
     bike_graph: VehiclePathfinder,
     bus_graph: VehiclePathfinder,
     train_graph: VehiclePathfinder,
+    #[serde(with = "truck_graph_added_in_v1")]
+    truck_graph: VehiclePathfinder,
     walking_graph: SidewalkPathfinder,
     walking_with_transit_graph: SidewalkPathfinder,
 }
@@ -39,6 +41,10 @@ impl ContractionHierarchyPathfinder {
         let train_graph = VehiclePathfinder::new(map, PathConstraints::Train, None);
         timer.stop("prepare pathfinding for trains");
 
+        timer.start("prepare pathfinding for trucks");
+        let truck_graph = VehiclePathfinder::new(map, PathConstraints::Truck, Some(&car_graph));
+        timer.stop("prepare pathfinding for trucks");
+
         timer.start("prepare pathfinding for pedestrians");
         let walking_graph = SidewalkPathfinder::new(map, false, &bus_graph, &train_graph);
         timer.stop("prepare pathfinding for pedestrians");
@@ -53,11 +59,18 @@ impl ContractionHierarchyPathfinder {
             bike_graph,
             bus_graph,
             train_graph,
+            truck_graph,
             walking_graph,
             walking_with_transit_graph,
         }
     }
 
+    /// Only for upgrading maps from before trucks had their own graph.
+    pub(crate) fn add_truck_graph(&mut self, map: &Map) {
+        self.truck_graph =
+            VehiclePathfinder::new(map, PathConstraints::Truck, Some(&self.car_graph));
+    }
+
     pub fn simple_pathfind(&self, req: &PathRequest, map: &Map) -> Option<Path> {
         match req.constraints {
             PathConstraints::Pedestrian => unreachable!(),
@@ -65,6 +78,7 @@ impl ContractionHierarchyPathfinder {
             PathConstraints::Bike => self.bike_graph.pathfind(req, map).map(|(p, _)| p),
             PathConstraints::Bus => self.bus_graph.pathfind(req, map).map(|(p, _)| p),
             PathConstraints::Train => self.train_graph.pathfind(req, map).map(|(p, _)| p),
+            PathConstraints::Truck => self.truck_graph.pathfind(req, map).map(|(p, _)| p),
         }
     }
 
@@ -82,6 +96,24 @@ impl ContractionHierarchyPathfinder {
             .should_use_transit(map, start, end)
     }
 
+    /// Call this before the map is edited, if the pathfinder is up-to-date with the map. Graphs
+    /// that edits don't affect won't be rebuilt later.
+    pub fn remember_input_graphs(&mut self, map: &Map) {
+        self.car_graph.remember_input_graph(map);
+        self.bike_graph.remember_input_graph(map);
+        self.bus_graph.remember_input_graph(map);
+        self.truck_graph.remember_input_graph(map);
+        self.train_graph.remember_input_graph(map);
+        self.walking_graph
+            .remember_input_graph(map, &self.bus_graph, &self.train_graph);
+        self.walking_with_transit_graph.remember_input_graph(
+            map,
+            &self.bus_graph,
+            &self.train_graph,
+        );
+    }
+
+    /// Only the graphs that the edits actually affect are rebuilt.
     pub fn apply_edits(&mut self, map: &Map, timer: &mut Timer) {
         timer.start("apply edits to car pathfinding");
         self.car_graph.apply_edits(map);
@@ -95,6 +127,10 @@ impl ContractionHierarchyPathfinder {
         self.bus_graph.apply_edits(map);
         timer.stop("apply edits to bus pathfinding");
 
+        timer.start("apply edits to truck pathfinding");
+        self.truck_graph.apply_edits(map);
+        timer.stop("apply edits to truck pathfinding");
+
         // Can't edit anything related to trains
 
         timer.start("apply edits to pedestrian pathfinding");
@@ -108,3 +144,22 @@ impl ContractionHierarchyPathfinder {
         timer.stop("apply edits to pedestrian using transit pathfinding");
     }
 }
+
+/// Maps from before versioning don't have a truck graph. Upgrading them starts with a placeholder
+/// and then calls `add_truck_graph`.
+mod truck_graph_added_in_v1 {
+    use serde::{Deserializer, Serializer};
+
+    use crate::pathfind::driving::VehiclePathfinder;
+    use crate::PathConstraints;
+
+    pub fn serialize<S: Serializer>(value: &VehiclePathfinder, s: S) -> Result<S::Ok, S::Error> {
+        abstutil::serialize_added_field(1, value, s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<VehiclePathfinder, D::Error> {
+        abstutil::deserialize_added_field_or(1, d, || {
+            VehiclePathfinder::placeholder(PathConstraints::Truck)
+        })
+    }
+}
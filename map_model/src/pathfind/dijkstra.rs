@@ -1,18 +1,22 @@
 //! Pathfinding without needing to build a separate contraction hierarchy.
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
 
 use petgraph::graphmap::DiGraphMap;
 
 use crate::pathfind::driving::driving_cost;
-use crate::pathfind::walking::{walking_cost, WalkingNode};
+use crate::pathfind::walking::{
+    one_step_walking_path, walking_cost, walking_cost_with_grade, walking_path_to_steps,
+    WalkingNode,
+};
 use crate::{LaneID, Map, Path, PathConstraints, PathRequest, PathStep, TurnID};
 
 // TODO These should maybe keep the DiGraphMaps as state. It's cheap to recalculate it for edits.
 
 pub fn simple_pathfind(req: &PathRequest, map: &Map) -> Option<Path> {
     let graph = build_graph_for_vehicles(map, req.constraints);
-    calc_path(graph, req, map)
+    calc_path(&graph, req, &BTreeMap::new(), map)
 }
 
 pub fn build_graph_for_vehicles(
@@ -23,7 +27,9 @@ pub fn build_graph_for_vehicles(
     for l in map.all_lanes() {
         if constraints.can_use(l, map) {
             for turn in map.get_turns_for(l.id, constraints) {
-                graph.add_edge(turn.id.src, turn.id.dst, turn.id);
+                if !constraints.blocked_by_modal_filter(turn.id, map) {
+                    graph.add_edge(turn.id.src, turn.id.dst, turn.id);
+                }
             }
         }
     }
@@ -40,20 +46,96 @@ pub fn pathfind_avoiding_lanes(
     for l in map.all_lanes() {
         if req.constraints.can_use(l, map) && !avoid.contains(&l.id) {
             for turn in map.get_turns_for(l.id, req.constraints) {
-                graph.add_edge(turn.id.src, turn.id.dst, turn.id);
+                if !req.constraints.blocked_by_modal_filter(turn.id, map) {
+                    graph.add_edge(turn.id.src, turn.id.dst, turn.id);
+                }
             }
         }
     }
 
-    calc_path(graph, &req, map)
+    calc_path(&graph, &req, &BTreeMap::new(), map)
 }
 
-fn calc_path(graph: DiGraphMap<LaneID, TurnID>, req: &PathRequest, map: &Map) -> Option<Path> {
+/// Like `simple_pathfind`, but without using some turns.
+pub fn pathfind_avoiding_turns(
+    req: PathRequest,
+    avoid: BTreeSet<TurnID>,
+    map: &Map,
+) -> Option<Path> {
+    pathfind_with_turn_penalties(req, avoid, BTreeMap::new(), map)
+}
+
+/// Like `pathfind_avoiding_turns`, but also adds some extra cost to using some turns. The penalty
+/// is in the same units as `driving_cost`.
+pub fn pathfind_with_turn_penalties(
+    req: PathRequest,
+    avoid: BTreeSet<TurnID>,
+    penalties: BTreeMap<TurnID, f64>,
+    map: &Map,
+) -> Option<Path> {
+    let graph = build_graph_avoiding_turns(map, req.constraints, avoid);
+    calc_path(&graph, &req, &penalties, map)
+}
+
+fn build_graph_avoiding_turns(
+    map: &Map,
+    constraints: PathConstraints,
+    avoid: BTreeSet<TurnID>,
+) -> DiGraphMap<LaneID, TurnID> {
+    let mut graph = build_graph_for_vehicles(map, constraints);
+    for t in avoid {
+        graph.remove_edge(t.src, t.dst);
+    }
+    graph
+}
+
+/// Graphs for `pathfind_with_turn_penalties`, kept around for each vehicle type and set of turns
+/// to avoid. Turns are only banned during a few windows of the day, so most requests share a
+/// graph. Penalties don't change the graph, so they can differ for every request.
+#[derive(Default)]
+pub struct CachedVehicleGraphs {
+    graphs: Mutex<BTreeMap<(PathConstraints, BTreeSet<TurnID>), Arc<DiGraphMap<LaneID, TurnID>>>>,
+}
+
+impl CachedVehicleGraphs {
+    pub fn pathfind_with_turn_penalties(
+        &self,
+        req: PathRequest,
+        avoid: BTreeSet<TurnID>,
+        penalties: BTreeMap<TurnID, f64>,
+        map: &Map,
+    ) -> Option<Path> {
+        // Don't hold the lock while pathfinding
+        let graph = self
+            .graphs
+            .lock()
+            .unwrap()
+            .entry((req.constraints, avoid.clone()))
+            .or_insert_with(|| Arc::new(build_graph_avoiding_turns(map, req.constraints, avoid)))
+            .clone();
+        calc_path(&graph, &req, &penalties, map)
+    }
+
+    /// Call this after the map is edited.
+    pub fn clear(&mut self) {
+        self.graphs.get_mut().unwrap().clear();
+    }
+}
+
+fn calc_path(
+    graph: &DiGraphMap<LaneID, TurnID>,
+    req: &PathRequest,
+    penalties: &BTreeMap<TurnID, f64>,
+    map: &Map,
+) -> Option<Path> {
     let (_, path) = petgraph::algo::astar(
-        &graph,
+        graph,
         req.start.lane(),
         |l| l == req.end.lane(),
-        |(_, _, turn)| driving_cost(map.get_l(turn.src), map.get_t(*turn), req.constraints, map),
+        |(_, _, turn)| {
+            driving_cost(map.get_l(turn.src), map.get_t(*turn), req.constraints, map)
+                + penalties.get(turn).cloned().unwrap_or(0.0)
+        },
         |_| 0.0,
     )?;
     let mut steps = Vec::new();
@@ -74,16 +156,32 @@ fn calc_path(graph: DiGraphMap<LaneID, TurnID>, req: &PathRequest, map: &Map) ->
 // TODO Not happy this works so differently
 
 pub fn build_graph_for_pedestrians(map: &Map) -> DiGraphMap<WalkingNode, usize> {
+    build_walking_graph(map, false)
+}
+
+/// Like `build_graph_for_pedestrians`, but without any sidewalks or crossings known to be
+/// unusable in a wheelchair.
+pub fn build_graph_for_wheelchairs(map: &Map) -> DiGraphMap<WalkingNode, usize> {
+    build_walking_graph(map, true)
+}
+
+fn build_walking_graph(map: &Map, wheelchair: bool) -> DiGraphMap<WalkingNode, usize> {
     let mut graph: DiGraphMap<WalkingNode, usize> = DiGraphMap::new();
     for l in map.all_lanes() {
-        if l.is_walkable() {
-            let cost = walking_cost(l.length());
+        if l.is_walkable() && !(wheelchair && l.wheelchair_barrier(map).is_some()) {
+            let grade = l.percent_grade(map);
             let n1 = WalkingNode::SidewalkEndpoint(l.id, true);
             let n2 = WalkingNode::SidewalkEndpoint(l.id, false);
-            graph.add_edge(n1, n2, cost);
-            graph.add_edge(n2, n1, cost);
+            graph.add_edge(n1, n2, walking_cost_with_grade(l.length(), -grade));
+            graph.add_edge(n2, n1, walking_cost_with_grade(l.length(), grade));
 
             for turn in map.get_turns_for(l.id, PathConstraints::Pedestrian) {
+                if wheelchair
+                    && (map.get_l(turn.id.dst).wheelchair_barrier(map).is_some()
+                        || map.turn_wheelchair_barrier(turn.id).is_some())
+                {
+                    continue;
+                }
                 graph.add_edge(
                     WalkingNode::SidewalkEndpoint(l.id, l.dst_i == turn.id.parent),
                     WalkingNode::SidewalkEndpoint(
@@ -112,3 +210,33 @@ pub fn simple_walking_path(req: &PathRequest, map: &Map) -> Option<Vec<WalkingNo
     )?;
     Some(path)
 }
+
+/// Finds a walking path that only uses sidewalks and crossings usable in a wheelchair. Ignores
+/// access-restricted zones.
+pub fn pathfind_wheelchair(req: PathRequest, map: &Map) -> Option<Path> {
+    assert_eq!(req.constraints, PathConstraints::Pedestrian);
+    if map
+        .get_l(req.start.lane())
+        .wheelchair_barrier(map)
+        .is_some()
+        || map.get_l(req.end.lane()).wheelchair_barrier(map).is_some()
+    {
+        return None;
+    }
+    if req.start.lane() == req.end.lane() {
+        return Some(one_step_walking_path(&req, map));
+    }
+
+    let graph = build_graph_for_wheelchairs(map);
+    let closest_start = WalkingNode::closest(req.start, map);
+    let closest_end = WalkingNode::closest(req.end, map);
+    let (_, path) = petgraph::algo::astar(
+        &graph,
+        closest_start,
+        |end| end == closest_end,
+        |(_, _, cost)| *cost,
+        |_| 0,
+    )?;
+    let steps = walking_path_to_steps(path, map);
+    Some(Path::new(map, steps, req.end.dist_along(), Vec::new()))
+}
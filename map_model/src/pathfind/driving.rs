@@ -1,4 +1,4 @@
-//! Pathfinding for cars, bikes, buses, and trains using contraction hierarchies
+//! Pathfinding for cars, bikes, buses, trains, and trucks using contraction hierarchies
 
 use std::cell::RefCell;
 
@@ -8,9 +8,12 @@ use thread_local::ThreadLocal;
 
 use abstutil::MultiMap;
 
+use crate::pathfind::fingerprint;
 use crate::pathfind::node_map::{deserialize_nodemap, NodeMap};
 use crate::pathfind::uber_turns::{IntersectionCluster, UberTurn};
-use crate::{Lane, LaneID, Map, Path, PathConstraints, PathRequest, PathStep, Turn, TurnID};
+use crate::{
+    Lane, LaneID, Map, Path, PathConstraints, PathRequest, PathStep, TrafficStress, Turn, TurnID,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct VehiclePathfinder {
@@ -20,6 +23,10 @@ pub struct VehiclePathfinder {
     nodes: NodeMap<Node>,
     uber_turns: Vec<UberTurn>,
     constraints: PathConstraints,
+    /// The fingerprint of the input graph that `graph` was last prepared from. This isn't saved
+    /// with the map, so it's filled out lazily before the first edit.
+    #[serde(skip_serializing, skip_deserializing)]
+    input_fingerprint: Option<u64>,
 
     #[serde(skip_serializing, skip_deserializing)]
     path_calc: ThreadLocal<RefCell<PathCalculator>>,
@@ -70,6 +77,21 @@ impl VehiclePathfinder {
             nodes,
             uber_turns,
             constraints,
+            input_fingerprint: Some(fingerprint(&input_graph)),
+            path_calc: ThreadLocal::new(),
+        }
+    }
+
+    /// Can't find any paths. Only used briefly while upgrading old maps.
+    pub fn placeholder(constraints: PathConstraints) -> VehiclePathfinder {
+        let mut input_graph = InputGraph::new();
+        input_graph.freeze();
+        VehiclePathfinder {
+            graph: fast_paths::prepare(&input_graph),
+            nodes: NodeMap::new(),
+            uber_turns: Vec::new(),
+            constraints,
+            input_fingerprint: None,
             path_calc: ThreadLocal::new(),
         }
     }
@@ -121,14 +143,33 @@ impl VehiclePathfinder {
         ))
     }
 
+    /// Call this before the map is edited, if the graph is up-to-date with the map.
+    pub fn remember_input_graph(&mut self, map: &Map) {
+        if self.input_fingerprint.is_none() {
+            self.input_fingerprint = Some(fingerprint(&make_input_graph(
+                map,
+                &self.nodes,
+                &self.uber_turns,
+                self.constraints,
+            )));
+        }
+    }
+
     pub fn apply_edits(&mut self, map: &Map) {
         // The NodeMap is just all lanes and uber-turns -- it won't change. So we can also reuse
         // the node ordering.
         // TODO Make sure the result of this is deterministic and equivalent to computing from
         // scratch.
         let input_graph = make_input_graph(map, &self.nodes, &self.uber_turns, self.constraints);
+        // Most edits only affect some types of vehicles. Contracting is the slow part, so skip it
+        // if nothing changed.
+        let new_fingerprint = fingerprint(&input_graph);
+        if self.input_fingerprint == Some(new_fingerprint) {
+            return;
+        }
         let node_ordering = self.graph.get_node_ordering();
         self.graph = fast_paths::prepare_with_order(&input_graph, &node_ordering).unwrap();
+        self.input_fingerprint = Some(new_fingerprint);
     }
 }
 
@@ -147,11 +188,10 @@ fn make_input_graph(
         // vehicle.
         // TODO Need to test editing lanes inside an IntersectionCluster very carefully. See Mercer
         // and Dexter.
-        if ut
-            .path
-            .iter()
-            .all(|t| constraints.can_use(map.get_l(t.dst), map))
-        {
+        if ut.path.iter().all(|t| {
+            constraints.can_use(map.get_l(t.dst), map)
+                && !constraints.blocked_by_modal_filter(*t, map)
+        }) {
             uber_turn_entrances.insert(ut.entry(), idx);
         } else {
             // Similar to the hack below for unused lanes
@@ -179,6 +219,9 @@ fn make_input_graph(
             let indices = uber_turn_entrances.get(l.id);
             if indices.is_empty() {
                 for turn in map.get_turns_for(l.id, constraints) {
+                    if constraints.blocked_by_modal_filter(turn.id, map) {
+                        continue;
+                    }
                     any = true;
                     input_graph.add_edge(
                         from,
@@ -223,7 +266,7 @@ pub fn driving_cost(lane: &Lane, turn: &Turn, constraints: PathConstraints, map:
     // TODO Could cost turns differently.
 
     let base = match constraints {
-        PathConstraints::Car | PathConstraints::Train => {
+        PathConstraints::Car | PathConstraints::Train | PathConstraints::Truck => {
             // Prefer slightly longer route on faster roads
             let t1 = lane.length() / map.get_r(lane.parent).speed_limit;
             let t2 = turn.geom.length() / map.get_parent(turn.id.dst).speed_limit;
@@ -232,13 +275,21 @@ pub fn driving_cost(lane: &Lane, turn: &Turn, constraints: PathConstraints, map:
         PathConstraints::Bike => {
             // Speed limits don't matter, bikes are usually constrained by their own speed limit.
             let dist = lane.length() + turn.geom.length();
-            // TODO Elevation gain is bad, loss is good.
-            // TODO If we're on a driving lane, higher speed limit is worse.
-            // TODO Bike lanes next to parking is dangerous.
+            // Elevation gain is bad; a 10% grade counts like twice the distance. Going downhill
+            // doesn't help much, since bikes are mostly limited by their own speed.
+            let grade_penalty = 1.0 + 10.0 * lane.percent_grade(map).max(0.0);
 
-            // TODO Prefer bike lanes, then bus lanes, then driving lanes. For now, express that as
-            // an extra cost.
-            let lt_penalty = if lane.is_biking() {
+            let lt_penalty = if map.get_config().bikes_avoid_stress {
+                // This accounts for the lane type, speed limit, and parking next to bike lanes.
+                match lane.traffic_stress(map).unwrap() {
+                    TrafficStress::Low => 1.0,
+                    TrafficStress::Moderate => 1.2,
+                    TrafficStress::High => 1.6,
+                    TrafficStress::Extreme => 2.5,
+                }
+            } else if lane.is_biking() {
+                // TODO Prefer bike lanes, then bus lanes, then driving lanes. For now, express
+                // that as an extra cost.
                 1.0
             } else if lane.is_bus() {
                 1.1
@@ -248,7 +299,7 @@ pub fn driving_cost(lane: &Lane, turn: &Turn, constraints: PathConstraints, map:
             };
 
             // 1m resolution is fine
-            (lt_penalty * dist).inner_meters()
+            (lt_penalty * grade_penalty * dist).inner_meters()
         }
         PathConstraints::Bus => {
             // Like Car, but prefer bus lanes.
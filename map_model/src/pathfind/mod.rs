@@ -1,18 +1,24 @@
 //! Everything related to pathfinding through a map for different types of agents.
 
+use std::collections::hash_map::DefaultHasher;
 use std::collections::VecDeque;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
 use enumset::EnumSetType;
+use fast_paths::InputGraph;
 use serde::{Deserialize, Serialize};
 
 use geom::{Distance, PolyLine, EPSILON_DIST};
 
 pub use self::ch::ContractionHierarchyPathfinder;
-pub use self::dijkstra::{build_graph_for_pedestrians, build_graph_for_vehicles};
+pub use self::dijkstra::{
+    build_graph_for_pedestrians, build_graph_for_vehicles, build_graph_for_wheelchairs,
+    CachedVehicleGraphs,
+};
 pub use self::driving::driving_cost;
 pub use self::pathfinder::Pathfinder;
-pub use self::walking::{walking_cost, WalkingNode};
+pub use self::walking::{walking_cost, walking_cost_with_grade, WalkingNode};
 use crate::{
     osm, BuildingID, Lane, LaneID, LaneType, Map, Position, Traversable, TurnID, UberTurn,
 };
@@ -274,6 +280,64 @@ impl Path {
         }
     }
 
+    /// Switch from the current lane to a parallel one, also changing the next turn to start from
+    /// there. Trusting the caller that the turn exists and isn't part of an uber-turn.
+    pub fn change_current_lane(&mut self, lane: LaneID, map: &Map) {
+        assert!(self.currently_inside_ut.is_none());
+        let old_turn = self.steps[1].as_turn();
+        self.modify_step(
+            1,
+            PathStep::Turn(TurnID {
+                parent: old_turn.parent,
+                src: lane,
+                dst: old_turn.dst,
+            }),
+            map,
+        );
+        self.total_length -= self.steps[0].as_traversable().length(map);
+        self.steps[0] = PathStep::Lane(lane);
+        self.total_length += map.get_l(lane).length();
+    }
+
+    /// Replace the remaining steps with a fresh route from the current lane to the same end, using
+    /// the current state of the map. Progress along the original path is kept. Returns false if
+    /// the path can't be changed right now or no route exists.
+    pub fn reroute(&mut self, constraints: PathConstraints, map: &Map) -> bool {
+        self.reroute_with(constraints, |req| map.pathfind(req))
+    }
+
+    /// Like `reroute`, but finds the new route some other way.
+    pub fn reroute_with<F: Fn(PathRequest) -> Option<Path>>(
+        &mut self,
+        constraints: PathConstraints,
+        pathfind: F,
+    ) -> bool {
+        if self.is_last_step() || self.currently_inside_ut.is_some() {
+            return false;
+        }
+        let start = match self.current_step() {
+            PathStep::Lane(l) => Position::start(l),
+            _ => return false,
+        };
+        let end = Position::new(self.last_step().as_lane(), self.end_dist);
+        let new = match pathfind(PathRequest {
+            start,
+            end,
+            constraints,
+        }) {
+            Some(path) => path,
+            None => {
+                return false;
+            }
+        };
+        assert_eq!(new.current_step(), self.current_step());
+
+        self.steps = new.steps;
+        self.total_length = self.crossed_so_far + new.total_length;
+        self.uber_turns = new.uber_turns;
+        true
+    }
+
     pub fn current_step(&self) -> PathStep {
         self.steps[0]
     }
@@ -423,6 +487,8 @@ pub enum PathConstraints {
     Bike,
     Bus,
     Train,
+    /// Heavy goods vehicles. They can go anywhere cars can, except roads tagged `hgv=no`.
+    Truck,
 }
 
 impl PathConstraints {
@@ -433,13 +499,16 @@ impl PathConstraints {
             PathConstraints::Bike,
             PathConstraints::Bus,
             PathConstraints::Train,
+            PathConstraints::Truck,
         ]
     }
 
     /// Not bijective, but this is the best guess of user intent
     pub fn from_lt(lt: LaneType) -> PathConstraints {
         match lt {
-            LaneType::Sidewalk | LaneType::Shoulder => PathConstraints::Pedestrian,
+            LaneType::Sidewalk | LaneType::Shoulder | LaneType::Footway => {
+                PathConstraints::Pedestrian
+            }
             LaneType::Driving => PathConstraints::Car,
             LaneType::Biking => PathConstraints::Bike,
             LaneType::Bus => PathConstraints::Bus,
@@ -468,9 +537,20 @@ impl PathConstraints {
             }
             PathConstraints::Bus => l.is_driving() || l.is_bus(),
             PathConstraints::Train => l.is_light_rail(),
+            PathConstraints::Truck => {
+                l.is_driving() && !map.get_r(l.parent).osm_tags.is("hgv", "no")
+            }
         }
     }
 
+    /// A modal filter sits in the middle of a road. Cars and trucks can still drive into a
+    /// filtered lane to reach buildings along it, but they can't pass the filter to turn out the
+    /// other end.
+    pub fn blocked_by_modal_filter(self, turn: TurnID, map: &Map) -> bool {
+        (self == PathConstraints::Car || self == PathConstraints::Truck)
+            && map.get_parent(turn.src).modal_filter
+    }
+
     /// Strict for bikes. If there are bike lanes, not allowed to use other lanes.
     pub(crate) fn filter_lanes(self, mut choices: Vec<LaneID>, map: &Map) -> Vec<LaneID> {
         choices.retain(|l| self.can_use(map.get_l(*l), map));
@@ -524,7 +604,7 @@ impl PathRequest {
         let (start, end) = match constraints {
             PathConstraints::Pedestrian => (from.sidewalk_pos, to.sidewalk_pos),
             PathConstraints::Bike => (from.biking_connection(map)?.0, to.biking_connection(map)?.0),
-            PathConstraints::Car => (
+            PathConstraints::Car | PathConstraints::Truck => (
                 from.driving_connection(map)?.0,
                 to.driving_connection(map)?.0,
             ),
@@ -541,6 +621,18 @@ impl PathRequest {
     }
 }
 
+/// Summarizes the edges of a graph before it's contracted. If map edits don't change this, then
+/// the contraction hierarchy doesn't need to be rebuilt.
+fn fingerprint(graph: &InputGraph) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for edge in graph.get_edges() {
+        edge.from.hash(&mut hasher);
+        edge.to.hash(&mut hasher);
+        edge.weight.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
 fn validate_continuity(map: &Map, steps: &Vec<PathStep>) {
     if steps.is_empty() {
         panic!("Empty path");
@@ -8,8 +8,8 @@ use crate::pathfind::ch::ContractionHierarchyPathfinder;
 use crate::pathfind::walking::{one_step_walking_path, walking_path_to_steps};
 use crate::pathfind::{dijkstra, WalkingNode};
 use crate::{
-    BusRouteID, BusStopID, Intersection, LaneID, Map, Path, PathConstraints, PathRequest, Position,
-    TurnID, Zone,
+    BusRouteID, BusStopID, Intersection, LaneID, Map, Path, PathConstraints, PathRequest, PathStep,
+    Position, TurnID, Zone,
 };
 
 /// Most of the time, prefer using the faster contraction hierarchies. But sometimes, callers can
@@ -104,6 +104,17 @@ impl Pathfinder {
             let steps = walking_path_to_steps(self.simple_walking_path(&req, map)?, map);
             return Some(Path::new(map, steps, req.end.dist_along(), Vec::new()));
         }
+        if req.start.lane() != req.end.lane()
+            && (req.constraints == PathConstraints::Car
+                || req.constraints == PathConstraints::Truck)
+        {
+            let start = map.get_l(req.start.lane());
+            if map.get_r(start.parent).modal_filter
+                && req.start.dist_along() >= start.length() / 2.0
+            {
+                return self.pathfind_past_modal_filter(req, map);
+            }
+        }
         self.simple_pathfind(&req, map)
     }
 
@@ -116,6 +127,19 @@ impl Pathfinder {
         dijkstra::pathfind_avoiding_lanes(req, avoid, map)
     }
 
+    pub fn pathfind_wheelchair(&self, req: PathRequest, map: &Map) -> Option<Path> {
+        dijkstra::pathfind_wheelchair(req, map)
+    }
+
+    pub fn pathfind_avoiding_turns(
+        &self,
+        req: PathRequest,
+        avoid: BTreeSet<TurnID>,
+        map: &Map,
+    ) -> Option<Path> {
+        dijkstra::pathfind_avoiding_turns(req, avoid, map)
+    }
+
     // TODO Consider returning the walking-only path in the failure case, to avoid wasting work
     pub fn should_use_transit(
         &self,
@@ -130,6 +154,13 @@ impl Pathfinder {
         }
     }
 
+    pub fn remember_input_graphs(&mut self, map: &Map) {
+        match self {
+            Pathfinder::Dijkstra => {}
+            Pathfinder::CH(ref mut p) => p.remember_input_graphs(map),
+        }
+    }
+
     pub fn apply_edits(&mut self, map: &Map, timer: &mut Timer) {
         match self {
             Pathfinder::Dijkstra => {}
@@ -152,6 +183,39 @@ impl Pathfinder {
         }
     }
 
+    /// Vehicles that start past a road's modal filter can't turn around, but they can leave the
+    /// road the normal way, even though the graph doesn't have turns across the filter.
+    fn pathfind_past_modal_filter(&self, mut req: PathRequest, map: &Map) -> Option<Path> {
+        let start = req.start.lane();
+        let mut best: Option<Path> = None;
+        for turn in map.get_turns_for(start, req.constraints) {
+            req.start = Position::start(turn.id.dst);
+            let rest = if turn.id.dst == req.end.lane() {
+                Path::new(
+                    map,
+                    vec![PathStep::Lane(turn.id.dst)],
+                    req.end.dist_along(),
+                    Vec::new(),
+                )
+            } else if let Some(path) = self.simple_pathfind(&req, map) {
+                path
+            } else {
+                continue;
+            };
+            let mut path = Path::one_step(start, map);
+            path.append(rest, map);
+            path.end_dist = req.end.dist_along();
+            if best
+                .as_ref()
+                .map(|p| path.total_length() < p.total_length())
+                .unwrap_or(true)
+            {
+                best = Some(path);
+            }
+        }
+        best
+    }
+
     fn pathfind_from_zone(
         &self,
         i: &Intersection,
@@ -11,6 +11,7 @@ use thread_local::ThreadLocal;
 use geom::{Distance, Speed};
 
 use crate::pathfind::driving::VehiclePathfinder;
+use crate::pathfind::fingerprint;
 use crate::pathfind::node_map::{deserialize_nodemap, NodeMap};
 use crate::{
     BusRoute, BusRouteID, BusStopID, IntersectionID, LaneID, Map, Path, PathConstraints,
@@ -24,6 +25,9 @@ pub struct SidewalkPathfinder {
     #[serde(deserialize_with = "deserialize_nodemap")]
     nodes: NodeMap<WalkingNode>,
     use_transit: bool,
+    /// Like VehiclePathfinder, used to skip rebuilding after edits that don't affect this graph
+    #[serde(skip_serializing, skip_deserializing)]
+    input_fingerprint: Option<u64>,
 
     #[serde(skip_serializing, skip_deserializing)]
     path_calc: ThreadLocal<RefCell<PathCalculator>>,
@@ -87,21 +91,35 @@ impl SidewalkPathfinder {
             }
         }
 
-        let graph = fast_paths::prepare(&make_input_graph(
-            map,
-            &nodes,
-            use_transit,
-            bus_graph,
-            train_graph,
-        ));
+        let input_graph = make_input_graph(map, &nodes, use_transit, bus_graph, train_graph);
+        let graph = fast_paths::prepare(&input_graph);
         SidewalkPathfinder {
             graph,
             nodes,
             use_transit,
+            input_fingerprint: Some(fingerprint(&input_graph)),
             path_calc: ThreadLocal::new(),
         }
     }
 
+    /// Call this before the map is edited, if the graph is up-to-date with the map.
+    pub fn remember_input_graph(
+        &mut self,
+        map: &Map,
+        bus_graph: &VehiclePathfinder,
+        train_graph: &VehiclePathfinder,
+    ) {
+        if self.input_fingerprint.is_none() {
+            self.input_fingerprint = Some(fingerprint(&make_input_graph(
+                map,
+                &self.nodes,
+                self.use_transit,
+                bus_graph,
+                train_graph,
+            )));
+        }
+    }
+
     pub fn apply_edits(
         &mut self,
         map: &Map,
@@ -112,8 +130,13 @@ impl SidewalkPathfinder {
         // reuse the node ordering.
         let input_graph =
             make_input_graph(map, &self.nodes, self.use_transit, bus_graph, train_graph);
+        let new_fingerprint = fingerprint(&input_graph);
+        if self.input_fingerprint == Some(new_fingerprint) {
+            return;
+        }
         let node_ordering = self.graph.get_node_ordering();
         self.graph = fast_paths::prepare_with_order(&input_graph, &node_ordering).unwrap();
+        self.input_fingerprint = Some(new_fingerprint);
     }
 
     /// Returns the raw nodes
@@ -239,15 +262,18 @@ fn make_input_graph(
                 .allow_through_traffic
                 .contains(PathConstraints::Pedestrian)
         {
-            let mut cost = walking_cost(l.length());
+            let grade = l.percent_grade(map);
+            let mut fwd_cost = walking_cost_with_grade(l.length(), grade);
+            let mut back_cost = walking_cost_with_grade(l.length(), -grade);
             // TODO Tune this penalty, along with many others.
             if l.is_shoulder() {
-                cost *= 2;
+                fwd_cost *= 2;
+                back_cost *= 2;
             }
             let n1 = nodes.get(WalkingNode::SidewalkEndpoint(l.id, true));
             let n2 = nodes.get(WalkingNode::SidewalkEndpoint(l.id, false));
-            input_graph.add_edge(n1, n2, cost);
-            input_graph.add_edge(n2, n1, cost);
+            input_graph.add_edge(n1, n2, back_cost);
+            input_graph.add_edge(n2, n1, fwd_cost);
         }
     }
 
@@ -391,6 +417,17 @@ pub fn walking_cost(dist: Distance) -> usize {
     (time.inner_seconds().round() as usize).max(1)
 }
 
+/// Like walking_cost, but slowing down on steep grades, using Tobler's hiking function
+/// (https://en.wikipedia.org/wiki/Tobler%27s_hiking_function). Gentle downhills are slightly
+/// faster than flat ground; steep ones are slower.
+pub fn walking_cost_with_grade(dist: Distance, grade: f64) -> usize {
+    // Relative to walking on flat ground
+    let slowdown = (3.5 * ((grade + 0.05).abs() - 0.05)).exp();
+    let walking_speed = Speed::meters_per_second(1.34);
+    let time = dist / walking_speed;
+    ((slowdown * time.inner_seconds()).round() as usize).max(1)
+}
+
 pub fn walking_path_to_steps(path: Vec<WalkingNode>, map: &Map) -> Vec<PathStep> {
     let mut steps: Vec<PathStep> = Vec::new();
 
@@ -2,19 +2,17 @@
 //! structure is useful to iterate quickly on parts of the map importing pipeline without having to
 //! constantly read .osm files, and to visualize the intermediate state with map_editor.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
-use petgraph::graphmap::DiGraphMap;
+use petgraph::graphmap::{DiGraphMap, UnGraphMap};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap, MapName, Tags, Timer};
 use geom::{Circle, Distance, GPSBounds, PolyLine, Polygon, Pt2D};
 
 use crate::make::initial::lane_specs::get_lane_specs_ltr;
-use crate::{
-    osm, Amenity, AreaType, Direction, DrivingSide, IntersectionType, LaneType, MapConfig,
-};
+use crate::{osm, Amenity, AreaType, Direction, DrivingSide, IntersectionType, MapConfig};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RawMap {
@@ -100,7 +98,9 @@ impl RawMap {
             config: MapConfig {
                 driving_side: DrivingSide::Right,
                 bikes_can_use_bus_lanes: true,
+                bikes_avoid_stress: false,
                 inferred_sidewalks: true,
+                clean_geometry: false,
             },
         }
     }
@@ -216,6 +216,15 @@ impl RawMap {
         Some(fixed)
     }
 
+    /// All roads directly connecting two intersections, in either direction.
+    pub fn roads_between(&self, i1: osm::NodeID, i2: osm::NodeID) -> Vec<OriginalRoad> {
+        self.roads
+            .keys()
+            .filter(|r| (r.i1 == i1 && r.i2 == i2) || (r.i1 == i2 && r.i2 == i1))
+            .cloned()
+            .collect()
+    }
+
     pub fn closest_intersection(&self, pt: Pt2D) -> osm::NodeID {
         self.intersections
             .iter()
@@ -239,6 +248,99 @@ impl RawMap {
         .get(&to)
         .cloned()
     }
+
+    /// Snaps both points to the closest road, then finds the shortest distance between them along
+    /// roads, ignoring oneways.
+    pub fn path_dist_between(&self, pt1: Pt2D, pt2: Pt2D) -> Option<Distance> {
+        let (r1, dist1) = self.closest_pt_on_road(pt1)?;
+        let (r2, dist2) = self.closest_pt_on_road(pt2)?;
+
+        // Only keep the shortest road between two intersections
+        let mut graph: UnGraphMap<osm::NodeID, Distance> = UnGraphMap::new();
+        for (id, r) in &self.roads {
+            let len = PolyLine::unchecked_new(r.center_points.clone()).length();
+            if graph
+                .edge_weight(id.i1, id.i2)
+                .map(|d| *d > len)
+                .unwrap_or(true)
+            {
+                graph.add_edge(id.i1, id.i2, len);
+            }
+        }
+
+        let len1 = PolyLine::unchecked_new(self.roads[&r1].center_points.clone()).length();
+        let len2 = PolyLine::unchecked_new(self.roads[&r2].center_points.clone()).length();
+        let mut best = if r1 == r2 {
+            Some((dist1 - dist2).abs())
+        } else {
+            None
+        };
+        for (start, to_start) in vec![(r1.i1, dist1), (r1.i2, len1 - dist1)] {
+            let costs = petgraph::algo::dijkstra(&graph, start, None, |(_, _, d)| *d);
+            for (end, from_end) in vec![(r2.i1, dist2), (r2.i2, len2 - dist2)] {
+                if let Some(cost) = costs.get(&end) {
+                    let total = to_start + *cost + from_end;
+                    if best.map(|d| total < d).unwrap_or(true) {
+                        best = Some(total);
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Returns the road closest to a point, and the distance along it of the closest position.
+    fn closest_pt_on_road(&self, pt: Pt2D) -> Option<(OriginalRoad, Distance)> {
+        self.roads
+            .iter()
+            .filter_map(|(id, r)| {
+                let pl = PolyLine::unchecked_new(r.center_points.clone());
+                let snapped = pl.project_pt(pt);
+                let (dist, _) = pl.dist_along_of_point(snapped)?;
+                Some((snapped.dist_to(pt), *id, dist))
+            })
+            .min_by_key(|(away, _, _)| *away)
+            .map(|(_, id, dist)| (id, dist))
+    }
+}
+
+/// Hand-made corrections to which nearby intersections get merged during import, stored per map
+/// and applied to the RawMap before it becomes a Map. Pairs are OSM node IDs, so they survive
+/// importing fresh OSM data, but not intersections made up by clipping.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConsolidationHints {
+    /// Collapse the roads between each pair, leaving one intersection
+    pub merge: BTreeSet<(osm::NodeID, osm::NodeID)>,
+    /// Leave the roads between each pair alone, even if they're short enough for
+    /// `MapConfig::clean_geometry` to merge
+    pub dont_merge: BTreeSet<(osm::NodeID, osm::NodeID)>,
+}
+
+impl ConsolidationHints {
+    /// Returns no hints if the map doesn't have any.
+    pub fn load(name: &MapName) -> ConsolidationHints {
+        abstutil::maybe_read_json(
+            abstutil::path_consolidation_hints(name),
+            &mut Timer::throwaway(),
+        )
+        .unwrap_or_else(|_| ConsolidationHints::default())
+    }
+
+    pub fn save(&self, name: &MapName) {
+        abstutil::write_json(abstutil::path_consolidation_hints(name), self);
+    }
+
+    /// Replaces any existing hint about this pair.
+    pub fn mark(&mut self, i1: osm::NodeID, i2: osm::NodeID, merge: bool) {
+        let pair = if i1 < i2 { (i1, i2) } else { (i2, i1) };
+        self.merge.remove(&pair);
+        self.dont_merge.remove(&pair);
+        if merge {
+            self.merge.insert(pair);
+        } else {
+            self.dont_merge.insert(pair);
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -262,7 +364,7 @@ impl RawRoad {
         let mut sidewalk_left = None;
         for l in &lane_specs {
             total_width += l.width;
-            if l.lt == LaneType::Sidewalk || l.lt == LaneType::Shoulder {
+            if l.lt.is_walkable() {
                 if l.dir == Direction::Back {
                     sidewalk_left = Some(l.width);
                 } else {
@@ -301,6 +403,36 @@ impl RawRoad {
     pub fn is_service(&self) -> bool {
         self.osm_tags.is(osm::HIGHWAY, "service")
     }
+
+    /// Which level the road is at, relative to the ground. OSM's `layer` tag takes priority, but
+    /// it's often missing on bridges and tunnels; assume those are one level above or below.
+    pub fn get_zorder(&self) -> isize {
+        if let Some(layer) = self.osm_tags.get("layer") {
+            match layer.parse::<f64>() {
+                // Just drop .5 for now
+                Ok(l) => {
+                    return l as isize;
+                }
+                Err(_) => {
+                    warn!(
+                        "Weird layer={} on way {}",
+                        layer,
+                        self.osm_tags
+                            .get(osm::OSM_WAY_ID)
+                            .map(|x| x.as_str())
+                            .unwrap_or("???")
+                    );
+                }
+            }
+        }
+        if self.osm_tags.contains_key("bridge") && !self.osm_tags.is("bridge", "no") {
+            return 1;
+        }
+        if self.osm_tags.contains_key("tunnel") && !self.osm_tags.is("tunnel", "no") {
+            return -1;
+        }
+        0
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -0,0 +1,281 @@
+//! Import and export traffic signal timing plans as JSON, so a city's real signal timings can be
+//! brought into A/B Street and edited plans can be sent back out. Everything is identified by OSM
+//! IDs, so a plan exported from one import of a map still applies after the map is regenerated.
+//!
+//! The schema, with everything required:
+//!
+//! ```json
+//! {
+//!   "format_version": 1,
+//!   "signals": [
+//!     {
+//!       "intersection_osm_node_id": 53096945,
+//!       "offset_seconds": 0,
+//!       "phases": [
+//!         {
+//!           "duration_seconds": 30,
+//!           "adaptive": false,
+//!           "protected_movements": [
+//!             {
+//!               "from": { "osm_way_id": 6484869, "osm_node1": 53096945, "osm_node2": 53128048, "is_forwards": false },
+//!               "to": { "osm_way_id": 6484869, "osm_node1": 53095580, "osm_node2": 53096945, "is_forwards": false },
+//!               "is_crosswalk": false
+//!             }
+//!           ],
+//!           "permitted_movements": []
+//!         }
+//!       ]
+//!     }
+//!   ],
+//!   "coordination_groups": [
+//!     { "name": "Montlake Blvd", "cycle_seconds": 90, "intersections": [53096945, 53095580] }
+//!   ]
+//! }
+//! ```
+//!
+//! - A signal cycles through its phases in order, starting `offset_seconds` after midnight.
+//! - During a phase, protected movements go first, and permitted movements yield to them. An
+//!   adaptive phase repeats when it would end if there's still demand.
+//! - A movement goes from one directed road to another. Roads are identified by their OSM way and
+//!   the two OSM nodes at their ends; `is_forwards` means moving from `osm_node1` to `osm_node2`.
+//! - A coordination group is a set of signals timed together, like a green wave along a corridor.
+//!   Every member must have a cycle exactly `cycle_seconds` long; offsets are still per signal.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ControlTrafficSignal, EditCmd, EditIntersection, IntersectionID, Map};
+
+/// Increase when the schema changes in a way older readers wouldn't understand
+pub const FORMAT_VERSION: usize = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignalPlans {
+    pub format_version: usize,
+    pub signals: Vec<SignalPlan>,
+    pub coordination_groups: Vec<CoordinationGroup>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignalPlan {
+    pub intersection_osm_node_id: i64,
+    pub offset_seconds: usize,
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Phase {
+    pub duration_seconds: usize,
+    pub adaptive: bool,
+    pub protected_movements: Vec<Movement>,
+    pub permitted_movements: Vec<Movement>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Movement {
+    pub from: DirectedRoad,
+    pub to: DirectedRoad,
+    pub is_crosswalk: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DirectedRoad {
+    pub osm_way_id: i64,
+    pub osm_node1: i64,
+    pub osm_node2: i64,
+    pub is_forwards: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CoordinationGroup {
+    pub name: String,
+    pub cycle_seconds: usize,
+    /// OSM node IDs of the member signals
+    pub intersections: Vec<i64>,
+}
+
+/// Exports the current timing of some traffic signals. Each group is given a name and its members,
+/// which must all be included in `signals`.
+pub fn export(
+    map: &Map,
+    signals: &BTreeSet<IntersectionID>,
+    groups: Vec<(String, BTreeSet<IntersectionID>)>,
+) -> SignalPlans {
+    SignalPlans {
+        format_version: FORMAT_VERSION,
+        signals: signals
+            .iter()
+            .map(|i| export_signal(map.get_traffic_signal(*i), map))
+            .collect(),
+        coordination_groups: groups
+            .into_iter()
+            .map(|(name, members)| {
+                let cycle = map
+                    .get_traffic_signal(*members.iter().next().unwrap())
+                    .simple_cycle_duration();
+                CoordinationGroup {
+                    name,
+                    cycle_seconds: cycle.inner_seconds() as usize,
+                    intersections: members
+                        .into_iter()
+                        .map(|i| map.get_i(i).orig_id.0)
+                        .collect(),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Exports every traffic signal in the map, without any coordination groups.
+pub fn export_all(map: &Map) -> SignalPlans {
+    let signals = map
+        .all_intersections()
+        .iter()
+        .filter(|i| i.is_traffic_signal())
+        .map(|i| i.id)
+        .collect();
+    export(map, &signals, Vec::new())
+}
+
+/// Produces the edits to apply some plans to the map. Fails if any signal or movement doesn't
+/// match the map, or if any coordination group's members don't share its cycle length.
+pub fn import(map: &Map, plans: SignalPlans) -> Result<Vec<EditCmd>, String> {
+    if plans.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "signal plans are format {}, but only {} is understood",
+            plans.format_version, FORMAT_VERSION
+        ));
+    }
+
+    let mut cmds = Vec::new();
+    let mut cycles = Vec::new();
+    for plan in plans.signals {
+        let osm_id = plan.intersection_osm_node_id;
+        let i = map.find_i_by_osm_id(crate::osm::NodeID(osm_id))?;
+        if map.get_i(i).is_border() {
+            return Err(format!("OSM node {} is a border of the map", osm_id));
+        }
+        let raw = to_raw(plan);
+        let ts = ControlTrafficSignal::import(raw.clone(), i, map)
+            .map_err(|err| format!("signal at OSM node {}: {}", osm_id, err))?;
+        cycles.push((osm_id, ts.simple_cycle_duration()));
+        cmds.push(EditCmd::ChangeIntersection {
+            i,
+            old: map.get_i_edit(i),
+            new: EditIntersection::TrafficSignal(raw),
+        });
+    }
+
+    for group in plans.coordination_groups {
+        for osm_id in &group.intersections {
+            let cycle = match cycles.iter().find(|(id, _)| id == osm_id) {
+                Some((_, cycle)) => *cycle,
+                None => {
+                    return Err(format!(
+                        "coordination group {} includes OSM node {}, which has no plan",
+                        group.name, osm_id
+                    ));
+                }
+            };
+            if cycle.inner_seconds() as usize != group.cycle_seconds {
+                return Err(format!(
+                    "coordination group {} has a {}s cycle, but the signal at OSM node {} has a \
+                     {} cycle",
+                    group.name, group.cycle_seconds, osm_id, cycle
+                ));
+            }
+        }
+    }
+
+    Ok(cmds)
+}
+
+fn export_signal(ts: &ControlTrafficSignal, map: &Map) -> SignalPlan {
+    let raw = ts.export(map);
+    SignalPlan {
+        intersection_osm_node_id: raw.intersection_osm_node_id,
+        offset_seconds: raw.offset_seconds,
+        phases: raw
+            .phases
+            .into_iter()
+            .map(|p| {
+                let (duration_seconds, adaptive) = match p.phase_type {
+                    seattle_traffic_signals::PhaseType::Fixed(d) => (d, false),
+                    seattle_traffic_signals::PhaseType::Adaptive(d) => (d, true),
+                };
+                Phase {
+                    duration_seconds,
+                    adaptive,
+                    protected_movements: p.protected_turns.into_iter().map(from_raw_turn).collect(),
+                    permitted_movements: p.permitted_turns.into_iter().map(from_raw_turn).collect(),
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Edits store signals in the same form as the handmapped Seattle data.
+fn to_raw(plan: SignalPlan) -> seattle_traffic_signals::TrafficSignal {
+    let osm_id = plan.intersection_osm_node_id;
+    seattle_traffic_signals::TrafficSignal {
+        intersection_osm_node_id: osm_id,
+        phases: plan
+            .phases
+            .into_iter()
+            .map(|p| seattle_traffic_signals::Phase {
+                protected_turns: p
+                    .protected_movements
+                    .into_iter()
+                    .map(|m| to_raw_turn(m, osm_id))
+                    .collect(),
+                permitted_turns: p
+                    .permitted_movements
+                    .into_iter()
+                    .map(|m| to_raw_turn(m, osm_id))
+                    .collect(),
+                phase_type: if p.adaptive {
+                    seattle_traffic_signals::PhaseType::Adaptive(p.duration_seconds)
+                } else {
+                    seattle_traffic_signals::PhaseType::Fixed(p.duration_seconds)
+                },
+            })
+            .collect(),
+        offset_seconds: plan.offset_seconds,
+    }
+}
+
+fn from_raw_turn(t: seattle_traffic_signals::Turn) -> Movement {
+    Movement {
+        from: from_raw_road(t.from),
+        to: from_raw_road(t.to),
+        is_crosswalk: t.is_crosswalk,
+    }
+}
+
+fn to_raw_turn(m: Movement, intersection_osm_node_id: i64) -> seattle_traffic_signals::Turn {
+    seattle_traffic_signals::Turn {
+        from: to_raw_road(m.from),
+        to: to_raw_road(m.to),
+        intersection_osm_node_id,
+        is_crosswalk: m.is_crosswalk,
+    }
+}
+
+fn from_raw_road(r: seattle_traffic_signals::DirectedRoad) -> DirectedRoad {
+    DirectedRoad {
+        osm_way_id: r.osm_way_id,
+        osm_node1: r.osm_node1,
+        osm_node2: r.osm_node2,
+        is_forwards: r.is_forwards,
+    }
+}
+
+fn to_raw_road(r: DirectedRoad) -> seattle_traffic_signals::DirectedRoad {
+    seattle_traffic_signals::DirectedRoad {
+        osm_way_id: r.osm_way_id,
+        osm_node1: r.osm_node1,
+        osm_node2: r.osm_node2,
+        is_forwards: r.is_forwards,
+    }
+}
@@ -0,0 +1,333 @@
+//! Combines two adjacent RawMaps into one, so a bigger region can be simulated without importing
+//! it from scratch. Each map was clipped to its own boundary, so a road crossing from one into the
+//! other was cut in two, with each half ending at a border intersection. Stitching joins those
+//! halves again and the border intersections disappear. The combined RawMap is then turned into a
+//! Map as usual, which assigns fresh IDs to everything.
+
+use std::collections::BTreeMap;
+
+use abstutil::{MapName, Timer};
+use geom::{Distance, GPSBounds, LonLat, Polygon, Pt2D};
+
+use crate::raw::{OriginalRoad, RawMap, RawRoad};
+use crate::{osm, IntersectionType};
+
+/// When both maps were clipped along the same line, the two halves of a road meet exactly. Allow
+/// some slack for boundaries drawn slightly differently.
+const MAX_GAP: Distance = Distance::const_meters(10.0);
+
+impl RawMap {
+    /// Combines this map with an adjacent one. Where the two maps overlap, roads, buildings, and
+    /// other objects from this map win. The `MapConfig` of this map is used for the result.
+    pub fn stitch(mut self, mut other: RawMap, name: MapName, timer: &mut Timer) -> RawMap {
+        let mut gps_bounds = self.gps_bounds.clone();
+        gps_bounds.update(LonLat::new(
+            other.gps_bounds.min_lon,
+            other.gps_bounds.min_lat,
+        ));
+        gps_bounds.update(LonLat::new(
+            other.gps_bounds.max_lon,
+            other.gps_bounds.max_lat,
+        ));
+        self.change_gps_bounds(&gps_bounds);
+        other.change_gps_bounds(&gps_bounds);
+        other.avoid_synthetic_ids(&self);
+
+        // Join the two halves of roads cut by the shared boundary
+        let mut renames = BTreeMap::new();
+        let mut joined = Vec::new();
+        for id2 in other.roads.keys().cloned().collect::<Vec<_>>() {
+            let (id1, ours_first) = match self.find_other_half(&other, id2) {
+                Some(pair) => pair,
+                None => {
+                    continue;
+                }
+            };
+            let road1 = self.roads.remove(&id1).unwrap();
+            let road2 = other.roads.remove(&id2).unwrap();
+            let (id, road, border1, border2) = if ours_first {
+                let (id, road) = join_halves(id1, road1, id2, road2);
+                (id, road, id1.i2, id2.i1)
+            } else {
+                let (id, road) = join_halves(id2, road2, id1, road1);
+                (id, road, id1.i1, id2.i2)
+            };
+            if self.can_delete_intersection(border1) {
+                self.delete_intersection(border1);
+            }
+            if other.can_delete_intersection(border2) {
+                other.delete_intersection(border2);
+            }
+            renames.insert(id1, id);
+            renames.insert(id2, id);
+            joined.push((id, road));
+        }
+        timer.note(format!(
+            "Joined {} roads crossing between the two maps",
+            joined.len()
+        ));
+
+        for (id, i) in other.intersections {
+            // A border in one map may be a real intersection in the other
+            let replace = self
+                .intersections
+                .get(&id)
+                .map(|existing| {
+                    existing.intersection_type == IntersectionType::Border
+                        && i.intersection_type != IntersectionType::Border
+                })
+                .unwrap_or(true);
+            if replace {
+                self.intersections.insert(id, i);
+            }
+        }
+        for (id, road) in other.roads {
+            self.roads.entry(id).or_insert(road);
+        }
+        self.roads.extend(joined);
+
+        for (id, b) in other.buildings {
+            self.buildings.entry(id).or_insert(b);
+        }
+        // Synthetic objects have negative IDs that may collide between the maps, so only
+        // deduplicate real OSM objects.
+        for area in other.areas {
+            if area.osm_id.inner() < 0 || !self.areas.iter().any(|a| a.osm_id == area.osm_id) {
+                self.areas.push(area);
+            }
+        }
+        for lot in other.parking_lots {
+            if lot.osm_id.inner() < 0 || !self.parking_lots.iter().any(|pl| pl.osm_id == lot.osm_id)
+            {
+                self.parking_lots.push(lot);
+            }
+        }
+        for (id, pts) in other.parking_aisles {
+            if id.0 < 0 || !self.parking_aisles.iter().any(|(x, _)| *x == id) {
+                self.parking_aisles.push((id, pts));
+            }
+        }
+        // A route crossing between the maps stays as two separate partial routes
+        for route in other.bus_routes {
+            if !self
+                .bus_routes
+                .iter()
+                .any(|r| r.osm_rel_id == route.osm_rel_id)
+            {
+                self.bus_routes.push(route);
+            }
+        }
+        let intersections = &self.intersections;
+        for route in &mut self.bus_routes {
+            for border in vec![&mut route.border_start, &mut route.border_end] {
+                let still_border = border
+                    .and_then(|i| intersections.get(&i))
+                    .map(|i| i.intersection_type == IntersectionType::Border)
+                    .unwrap_or(false);
+                if !still_border {
+                    *border = None;
+                }
+            }
+        }
+        self.fix_road_references(&renames);
+
+        // Boundaries sharing an edge usually form a convex shape; anything else gets padded out.
+        self.boundary_polygon =
+            Polygon::convex_hull(vec![self.boundary_polygon, other.boundary_polygon]);
+        self.name = name;
+        self
+    }
+
+    /// Transforms every point into the coordinate system of a different GPSBounds.
+    fn change_gps_bounds(&mut self, gps_bounds: &GPSBounds) {
+        let old = self.gps_bounds.clone();
+        let convert = |pt: &Pt2D| pt.to_gps(&old).to_pt(gps_bounds);
+        let convert_all = |pts: &mut Vec<Pt2D>| {
+            for pt in pts {
+                *pt = convert(pt);
+            }
+        };
+
+        for road in self.roads.values_mut() {
+            convert_all(&mut road.center_points);
+        }
+        for i in self.intersections.values_mut() {
+            i.point = convert(&i.point);
+        }
+        for b in self.buildings.values_mut() {
+            b.polygon = b.polygon.transform(convert);
+        }
+        for a in &mut self.areas {
+            a.polygon = a.polygon.transform(convert);
+        }
+        for pl in &mut self.parking_lots {
+            pl.polygon = pl.polygon.transform(convert);
+        }
+        for (_, pts) in &mut self.parking_aisles {
+            convert_all(pts);
+        }
+        for route in &mut self.bus_routes {
+            for stop in &mut route.stops {
+                stop.vehicle_pos.1 = convert(&stop.vehicle_pos.1);
+                stop.ped_pos = stop.ped_pos.map(|pt| convert(&pt));
+            }
+            for (_, pt) in &mut route.all_pts {
+                *pt = convert(pt);
+            }
+        }
+        self.boundary_polygon = self.boundary_polygon.transform(convert);
+        self.gps_bounds = gps_bounds.clone();
+    }
+
+    /// Intersections created while clipping get negative IDs, so both maps likely have some of
+    /// the same ones. Renumber any in this map that collide.
+    fn avoid_synthetic_ids(&mut self, other: &RawMap) {
+        let collisions: Vec<osm::NodeID> = self
+            .intersections
+            .keys()
+            .filter(|i| i.0 < 0 && other.intersections.contains_key(i))
+            .cloned()
+            .collect();
+        let mut start = -1;
+        for old in collisions {
+            let new = loop {
+                let id = self.new_osm_node_id(start);
+                start = id.0 - 1;
+                if !other.intersections.contains_key(&id) {
+                    break id;
+                }
+            };
+
+            let i = self.intersections.remove(&old).unwrap();
+            self.intersections.insert(new, i);
+            let mut renames = BTreeMap::new();
+            for id in self.roads_per_intersection(old) {
+                let mut new_id = id;
+                if new_id.i1 == old {
+                    new_id.i1 = new;
+                }
+                if new_id.i2 == old {
+                    new_id.i2 = new;
+                }
+                let road = self.roads.remove(&id).unwrap();
+                self.roads.insert(new_id, road);
+                renames.insert(id, new_id);
+            }
+            self.fix_road_references(&renames);
+            for route in &mut self.bus_routes {
+                for i in route
+                    .border_start
+                    .iter_mut()
+                    .chain(route.border_end.iter_mut())
+                    .chain(route.all_pts.iter_mut().map(|(i, _)| i))
+                    .chain(route.stops.iter_mut().map(|stop| &mut stop.vehicle_pos.0))
+                {
+                    if *i == old {
+                        *i = new;
+                    }
+                }
+            }
+        }
+    }
+
+    /// After roads are renamed, update turn restrictions and bus stops pointing to them.
+    fn fix_road_references(&mut self, renames: &BTreeMap<OriginalRoad, OriginalRoad>) {
+        let rename = |id: &mut OriginalRoad| {
+            if let Some(new) = renames.get(&*id) {
+                *id = *new;
+            }
+        };
+        for road in self.roads.values_mut() {
+            for (_, to) in &mut road.turn_restrictions {
+                rename(to);
+            }
+            for (via, to) in &mut road.complicated_turn_restrictions {
+                rename(via);
+                rename(to);
+            }
+        }
+        for route in &mut self.bus_routes {
+            for stop in &mut route.stops {
+                if let Some((r, _)) = stop.matched_road.as_mut() {
+                    rename(r);
+                }
+            }
+        }
+    }
+
+    /// Finds the road in this map that's the other half of a road from the adjacent map, cut by
+    /// the boundary. Both belong to the same OSM way and end at borders near each other. Also
+    /// returns true if this map's half comes first along the way.
+    fn find_other_half(&self, other: &RawMap, id2: OriginalRoad) -> Option<(OriginalRoad, bool)> {
+        let is_border = |map: &RawMap, i: osm::NodeID| {
+            map.intersections[&i].intersection_type == IntersectionType::Border
+        };
+        let pts2 = &other.roads[&id2].center_points;
+        for (id1, road1) in &self.roads {
+            if id1.osm_way_id != id2.osm_way_id {
+                continue;
+            }
+            let pts1 = &road1.center_points;
+            if is_border(self, id1.i2)
+                && is_border(other, id2.i1)
+                && pts1.last().unwrap().approx_eq(pts2[0], MAX_GAP)
+            {
+                return Some((*id1, true));
+            }
+            if is_border(self, id1.i1)
+                && is_border(other, id2.i2)
+                && pts1[0].approx_eq(*pts2.last().unwrap(), MAX_GAP)
+            {
+                return Some((*id1, false));
+            }
+        }
+        None
+    }
+}
+
+/// The first half ends where the second begins.
+fn join_halves(
+    id1: OriginalRoad,
+    first: RawRoad,
+    id2: OriginalRoad,
+    second: RawRoad,
+) -> (OriginalRoad, RawRoad) {
+    let id = OriginalRoad {
+        osm_way_id: id1.osm_way_id,
+        i1: id1.i1,
+        i2: id2.i2,
+    };
+
+    let mut center_points = first.center_points;
+    // If the boundaries didn't quite line up, keep the small gap as part of the road
+    if center_points.last() == second.center_points.first() {
+        center_points.pop();
+    }
+    center_points.extend(second.center_points);
+
+    // Only the second half knows if the joined road reaches the end of the OSM way
+    let mut osm_tags = first.osm_tags;
+    match second.osm_tags.get(osm::ENDPT_FWD) {
+        Some(x) => {
+            osm_tags.insert(osm::ENDPT_FWD, x.clone());
+        }
+        None => {
+            osm_tags.remove(osm::ENDPT_FWD);
+        }
+    }
+
+    let mut turn_restrictions = first.turn_restrictions;
+    turn_restrictions.extend(second.turn_restrictions);
+    let mut complicated_turn_restrictions = first.complicated_turn_restrictions;
+    complicated_turn_restrictions.extend(second.complicated_turn_restrictions);
+
+    (
+        id,
+        RawRoad {
+            center_points,
+            osm_tags,
+            turn_restrictions,
+            complicated_turn_restrictions,
+        },
+    )
+}
@@ -2,7 +2,7 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
-use geom::{Angle, Distance, PolyLine, Pt2D, Speed};
+use geom::{Angle, Distance, PolyLine, Pt2D, Speed, Time};
 
 use crate::{LaneID, Map, TurnID};
 
@@ -208,6 +208,14 @@ impl Traversable {
         }
     }
 
+    /// Like `speed_limit`, but respecting limits that map edits only apply during part of the day.
+    pub fn speed_limit_at(&self, map: &Map, time: Time) -> Speed {
+        match *self {
+            Traversable::Lane(id) => map.get_parent(id).speed_limit_at(time),
+            Traversable::Turn(id) => map.get_parent(id.dst).speed_limit_at(time),
+        }
+    }
+
     pub fn get_zorder(&self, map: &Map) -> isize {
         match *self {
             Traversable::Lane(id) => map.get_parent(id).zorder,
@@ -1,18 +1,22 @@
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 use serde::{Deserialize, Serialize};
 
-use abstutil::Counter;
-use geom::{Distance, Duration, Time};
+use abstutil::{Counter, Migration, Versioned};
+use geom::{Distance, Duration, Histogram, Statistic, Time};
 use map_model::{
-    BusRouteID, BusStopID, CompressedMovementID, IntersectionID, LaneID, Map, MovementID,
-    ParkingLotID, Path, PathRequest, RoadID, Traversable, TurnID,
+    BuildingID, BusRouteID, BusStopID, CompressedMovementID, IntersectionID, LaneID, Map,
+    MovementID, ParkingLotID, Path, PathRequest, RoadID, Traversable, TurnID,
 };
 
 use crate::{
-    AgentID, AgentType, AlertLocation, CarID, Event, ParkingSpot, TripID, TripMode, TripPhaseType,
+    AgentID, AgentType, AlertLocation, CarID, Emissions, Event, ParkingSpot, Sim, TripID, TripMode,
+    TripPhaseType,
 };
 
+/// How often to count filled parking spots everywhere
+pub const PARKING_OCCUPANCY_EVERY: Duration = Duration::const_seconds(15.0 * 60.0);
+
 /// As a simulation runs, different pieces emit Events. The Analytics object listens to these,
 /// organizing and storing some information from them. The UI queries Analytics to draw time-series
 /// and display statistics.
@@ -52,6 +56,21 @@ pub struct Analytics {
     /// If it is over a certain threshold (<95% of max speed)
     /// TripID, [(LaneID, Percent of maximum speed as an integer (0-100)]
     pub lane_speed_percentage: BTreeMap<TripID, BTreeMap<LaneID, u8>>,
+    /// How long each driving trip spent cruising for parking, summed if they had to look more than
+    /// once
+    #[serde(with = "crate::analytics::added_in_v1")]
+    pub parking_cruising: BTreeMap<TripID, Duration>,
+    /// Fuel burned and pollution emitted on each road. Turns count towards the road leading into
+    /// them.
+    #[serde(with = "crate::analytics::added_in_v1")]
+    pub road_emissions: BTreeMap<RoadID, Emissions>,
+    /// The same, summed over each trip. Buses aren't included.
+    #[serde(with = "crate::analytics::added_in_v1")]
+    pub trip_emissions: BTreeMap<TripID, Emissions>,
+    /// Each time a car or truck drives into a toll zone: the time, the index of the zone, and the
+    /// price paid
+    #[serde(with = "crate::analytics::added_in_v1")]
+    pub toll_payments: Vec<(Time, usize, f64)>,
 
     // TODO This subsumes finished_trips
     pub trip_log: Vec<(Time, TripID, Option<PathRequest>, TripPhaseType)>,
@@ -68,6 +87,11 @@ pub struct Analytics {
 
     /// For benchmarking, we may want to disable collecting data.
     record_anything: bool,
+
+    /// Filled spots everywhere, counted every 15 minutes like a parking study. Not counted with
+    /// infinite parking.
+    #[serde(with = "crate::analytics::added_in_v14")]
+    pub parking_occupancy: Vec<(Time, ParkingCounts)>,
 }
 
 impl Analytics {
@@ -84,12 +108,17 @@ impl Analytics {
             finished_trips: Vec::new(),
             trip_intersection_delays: BTreeMap::new(),
             lane_speed_percentage: BTreeMap::new(),
+            parking_cruising: BTreeMap::new(),
+            road_emissions: BTreeMap::new(),
+            trip_emissions: BTreeMap::new(),
+            toll_payments: Vec::new(),
             trip_log: Vec::new(),
             intersection_delays: BTreeMap::new(),
             parking_lane_changes: BTreeMap::new(),
             parking_lot_changes: BTreeMap::new(),
             alerts: Vec::new(),
             record_anything,
+            parking_occupancy: Vec::new(),
         }
     }
 
@@ -116,6 +145,11 @@ impl Analytics {
                 Traversable::Turn(t) => {
                     self.intersection_thruput
                         .record(time, t.parent, a.to_type(), 1);
+                    if a.to_type() == AgentType::Car {
+                        if let Some((zone, price)) = map.toll_for_turn(t, time) {
+                            self.toll_payments.push((time, zone, price));
+                        }
+                    }
                     if let Some(n) = passengers {
                         self.intersection_thruput.record(
                             time,
@@ -223,6 +257,21 @@ impl Analytics {
             }
         }
 
+        if let Event::CruisedForParking(trip, duration) = ev {
+            *self.parking_cruising.entry(trip).or_insert(Duration::ZERO) += duration;
+        }
+
+        if let Event::VehicleEmissions(_, maybe_trip, on, emissions) = ev {
+            let r = match on {
+                Traversable::Lane(l) => map.get_l(l).parent,
+                Traversable::Turn(t) => map.get_l(t.src).parent,
+            };
+            *self.road_emissions.entry(r).or_default() += emissions;
+            if let Some(trip) = maybe_trip {
+                *self.trip_emissions.entry(trip).or_default() += emissions;
+            }
+        }
+
         // Intersection delays
         if let Event::IntersectionDelayMeasured(id, delay, agent) = ev {
             self.intersection_delays
@@ -306,6 +355,28 @@ impl Analytics {
         None
     }
 
+    /// Total dollars paid to enter toll zones until some time
+    pub fn toll_revenue(&self, now: Time) -> f64 {
+        self.toll_payments
+            .iter()
+            .filter(|(t, _, _)| *t <= now)
+            .map(|(_, _, price)| price)
+            .sum()
+    }
+
+    /// How many cars and trucks used any of these roads, only counting whole hours finished before
+    /// some time. Toll zones only exist in one world, so this compares traffic in them.
+    pub fn vehicles_on_roads(&self, roads: &BTreeSet<RoadID>, now: Time) -> usize {
+        self.road_thruput
+            .counts
+            .iter()
+            .filter(|((r, agent_type, hour), _)| {
+                *agent_type == AgentType::Car && *hour < now.get_hours() && roads.contains(r)
+            })
+            .map(|(_, cnt)| *cnt)
+            .sum()
+    }
+
     /// Returns pairs of trip times for finished trips in both worlds. (ID, before, after, mode)
     pub fn both_finished_trips(
         &self,
@@ -433,6 +504,119 @@ impl Analytics {
         pts
     }
 
+    /// Groups the delays measured at an intersection up to `now` into buckets of size `bucket`,
+    /// starting at midnight, and summarizes each one. Empty buckets are skipped.
+    pub fn intersection_delay_percentiles(
+        &self,
+        i: IntersectionID,
+        bucket: Duration,
+        now: Time,
+    ) -> Vec<DelayStats> {
+        assert!(bucket > Duration::ZERO);
+        let mut per_bucket: BTreeMap<usize, Histogram<Duration>> = BTreeMap::new();
+        if let Some(list) = self.intersection_delays.get(&i) {
+            for (_, t, dt, _) in list {
+                if *t > now {
+                    break;
+                }
+                let idx = ((*t - Time::START_OF_DAY) / bucket).floor() as usize;
+                per_bucket
+                    .entry(idx)
+                    .or_insert_with(Histogram::new)
+                    .add(*dt);
+            }
+        }
+        per_bucket
+            .into_iter()
+            .map(|(idx, hgram)| DelayStats {
+                start: Time::START_OF_DAY + (idx as f64) * bucket,
+                count: hgram.count(),
+                p50: hgram.select(Statistic::P50).unwrap(),
+                p90: hgram.select(Statistic::P90).unwrap(),
+                p99: hgram.select(Statistic::P99).unwrap(),
+                max: hgram.select(Statistic::Max).unwrap(),
+            })
+            .collect()
+    }
+
+    /// Measures the headways between vehicles of one route, up to some time. None if no stop has
+    /// been visited twice yet.
+    pub fn headway_regularity(&self, route: BusRouteID, now: Time) -> Option<HeadwayStats> {
+        let mut last_arrival: BTreeMap<BusStopID, Time> = BTreeMap::new();
+        let mut headways: Vec<f64> = Vec::new();
+        for (t, _, r, stop) in &self.bus_arrivals {
+            if *t > now {
+                break;
+            }
+            if *r != route {
+                continue;
+            }
+            if let Some(prev) = last_arrival.insert(*stop, *t) {
+                headways.push((*t - prev).inner_seconds());
+            }
+        }
+        if headways.is_empty() {
+            return None;
+        }
+
+        let count = headways.len();
+        let mean = headways.iter().sum::<f64>() / (count as f64);
+        let variance = headways.iter().map(|h| (h - mean).powi(2)).sum::<f64>() / (count as f64);
+        let std_dev = variance.sqrt();
+        Some(HeadwayStats {
+            count,
+            mean: Duration::seconds(mean),
+            std_dev: Duration::seconds(std_dev),
+            coefficient_of_variation: if mean > 0.0 { std_dev / mean } else { 0.0 },
+        })
+    }
+
+    /// Breaks down where the time went for every public transit trip finished by some time.
+    /// Trips that never boarded a bus, train, or guideway aren't included.
+    pub fn transit_trip_breakdowns(&self, now: Time) -> BTreeMap<TripID, TransitTripBreakdown> {
+        // The phase each trip is currently in, when it started, and the breakdown so far
+        let mut current: BTreeMap<TripID, (Time, TripPhaseType, TransitTripBreakdown)> =
+            BTreeMap::new();
+        let mut results = BTreeMap::new();
+        for (t, id, _, phase_type) in &self.trip_log {
+            if *t > now {
+                break;
+            }
+            let breakdown = if let Some((start, prev, mut breakdown)) = current.remove(id) {
+                let dt = *t - start;
+                match prev {
+                    TripPhaseType::Walking => {
+                        breakdown.walking += dt;
+                    }
+                    TripPhaseType::WaitingForBus(_, _) | TripPhaseType::WaitingForGuideway(_) => {
+                        breakdown.waiting += dt;
+                    }
+                    TripPhaseType::RidingBus(_, _, _) | TripPhaseType::RidingGuideway(_) => {
+                        breakdown.in_vehicle += dt;
+                        breakdown.boardings += 1;
+                    }
+                    _ => {}
+                }
+                breakdown
+            } else {
+                TransitTripBreakdown::default()
+            };
+
+            match phase_type {
+                TripPhaseType::Finished => {
+                    if breakdown.boardings > 0 {
+                        results.insert(*id, breakdown);
+                    }
+                }
+                TripPhaseType::Cancelled => {}
+                _ => {
+                    current.insert(*id, (*t, *phase_type, breakdown));
+                }
+            }
+        }
+        results
+    }
+
     /// Returns the free spots over time
     pub fn parking_lane_availability(
         &self,
@@ -459,6 +643,94 @@ impl Analytics {
         }
     }
 
+    /// When the next count of filled parking spots is due, if it's by `until`.
+    pub(crate) fn next_parking_count(&self, until: Time) -> Option<Time> {
+        if !self.record_anything {
+            return None;
+        }
+        let next = self
+            .parking_occupancy
+            .last()
+            .map(|(t, _)| *t + PARKING_OCCUPANCY_EVERY)
+            .unwrap_or(Time::START_OF_DAY);
+        if next <= until {
+            Some(next)
+        } else {
+            None
+        }
+    }
+
+    /// The latest count of filled parking spots at or before this time
+    pub fn parking_occupancy_at(&self, time: Time) -> Option<&(Time, ParkingCounts)> {
+        self.parking_occupancy
+            .iter()
+            .rev()
+            .find(|(t, _)| *t <= time)
+    }
+
+    /// Every count of filled parking spots as CSV, with one row per count per place with any
+    /// spots. `capacity` is how many spots each place has. Block faces are described by their
+    /// OSM way and which side of it they're on, to match up with parking studies. Time is in
+    /// seconds since midnight.
+    pub fn parking_occupancy_csv(&self, capacity: &ParkingCounts, map: &Map) -> String {
+        let mut out = vec![
+            "time,location_type,location_id,osm,side,filled,capacity,percent_filled".to_string(),
+        ];
+        for (time, filled) in &self.parking_occupancy {
+            let mut row = |location_type: &str,
+                           id: usize,
+                           osm: String,
+                           side: String,
+                           filled: usize,
+                           capacity: usize| {
+                out.push(format!(
+                    "{},{},{},{},{},{},{},{:.1}",
+                    time.inner_seconds(),
+                    location_type,
+                    id,
+                    osm,
+                    side,
+                    filled,
+                    capacity,
+                    100.0 * (filled as f64) / (capacity as f64)
+                ));
+            };
+            for (l, cap) in &capacity.onstreet {
+                let r = map.get_r(map.get_l(*l).parent);
+                row(
+                    "block_face",
+                    l.0,
+                    r.orig_id.osm_way_id.to_string(),
+                    r.dir(*l).to_string(),
+                    filled.onstreet.get(l).cloned().unwrap_or(0),
+                    *cap,
+                );
+            }
+            for (b, cap) in &capacity.offstreet {
+                row(
+                    "building",
+                    b.0,
+                    map.get_b(*b).orig_id.to_string(),
+                    String::new(),
+                    filled.offstreet.get(b).cloned().unwrap_or(0),
+                    *cap,
+                );
+            }
+            for (pl, cap) in &capacity.lots {
+                row(
+                    "lot",
+                    pl.0,
+                    map.get_pl(*pl).osm_id.to_string(),
+                    String::new(),
+                    filled.lots.get(pl).cloned().unwrap_or(0),
+                    *cap,
+                );
+            }
+        }
+        out.push(String::new());
+        out.join("\n")
+    }
+
     fn parking_spot_availability(
         now: Time,
         changes: &Vec<(Time, bool)>,
@@ -494,6 +766,68 @@ impl Analytics {
     }
 }
 
+// Analytics are saved inside savestates too, so fields added later are marked with the savestate
+// format version.
+abstutil::added_in_format!(added_in_v1, 1);
+abstutil::added_in_format!(added_in_v14, 14);
+
+impl Versioned for Analytics {
+    const FORMAT: &'static str = "prebaked results";
+    // Prebaked results share the savestate format versions. Bump that instead.
+    const VERSION: u32 = <Sim as Versioned>::VERSION;
+
+    fn migrations() -> Vec<Migration> {
+        let mut migrations = vec![Migration {
+            from: 0,
+            description: "add parking cruising, emissions, and toll payments",
+            upgrade: |bytes| abstutil::upgrade_added_fields::<Analytics>(bytes, 0),
+        }];
+        // Only other parts of savestates changed in these versions
+        for from in 1..13 {
+            migrations.push(Migration {
+                from,
+                description: "keep analytics as they are",
+                upgrade: Ok,
+            });
+        }
+        migrations.push(Migration {
+            from: 13,
+            description: "add no parking occupancy",
+            upgrade: |bytes| abstutil::upgrade_added_fields::<Analytics>(bytes, 13),
+        });
+        migrations.push(Migration {
+            from: 14,
+            description: "keep analytics as they are",
+            upgrade: Ok,
+        });
+        migrations
+    }
+}
+
+/// How many parking spots along each block face, in each building, and in each parking lot. A
+/// block face is one side of a road between two intersections, so it's just a parking lane. Places
+/// with none are left out.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ParkingCounts {
+    pub onstreet: BTreeMap<LaneID, usize>,
+    pub offstreet: BTreeMap<BuildingID, usize>,
+    pub lots: BTreeMap<ParkingLotID, usize>,
+}
+
+impl ParkingCounts {
+    pub(crate) fn new(spots: Vec<ParkingSpot>) -> ParkingCounts {
+        let mut counts = ParkingCounts::default();
+        for spot in spots {
+            *match spot {
+                ParkingSpot::Onstreet(l, _) => counts.onstreet.entry(l).or_insert(0),
+                ParkingSpot::Offstreet(b, _) => counts.offstreet.entry(b).or_insert(0),
+                ParkingSpot::Lot(pl, _) => counts.lots.entry(pl).or_insert(0),
+            } += 1;
+        }
+        counts
+    }
+}
+
 impl Default for Analytics {
     fn default() -> Analytics {
         Analytics::new(false)
@@ -510,6 +844,50 @@ pub struct TripPhase {
     pub phase_type: TripPhaseType,
 }
 
+/// A summary of the delays measured at one intersection during one time bucket.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DelayStats {
+    /// The start of the bucket
+    pub start: Time,
+    /// How many agents were delayed
+    pub count: usize,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// How evenly spaced a route's vehicles are, measured by the time between consecutive vehicles
+/// arriving at each stop.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadwayStats {
+    /// How many gaps between arrivals were measured
+    pub count: usize,
+    pub mean: Duration,
+    pub std_dev: Duration,
+    /// std_dev / mean. 0 means perfectly regular service; bunching pushes this up.
+    pub coefficient_of_variation: f64,
+}
+
+/// Where the time went during one public transit trip.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TransitTripBreakdown {
+    /// Riding buses or trains
+    pub in_vehicle: Duration,
+    /// Waiting at stops
+    pub waiting: Duration,
+    /// Walking to the first stop, between stops, and from the last stop
+    pub walking: Duration,
+    /// How many vehicles were boarded
+    pub boardings: usize,
+}
+
+impl TransitTripBreakdown {
+    pub fn transfers(&self) -> usize {
+        self.boardings - 1
+    }
+}
+
 /// See https://github.com/dabreegster/abstreet/issues/85
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TimeSeriesCount<X: Ord + Clone> {
@@ -544,10 +922,15 @@ impl<X: Ord + Clone> TimeSeriesCount<X> {
     }
 
     pub fn total_for(&self, id: X) -> usize {
+        self.total_for_between(id, 0, 24)
+    }
+
+    /// Like `total_for`, but only counting hours in [start_hour, end_hour).
+    pub fn total_for_between(&self, id: X, start_hour: usize, end_hour: usize) -> usize {
         let mut cnt = 0;
         for agent_type in AgentType::all() {
             // TODO Hmm
-            for hour in 0..24 {
+            for hour in start_hour..end_hour {
                 cnt += self
                     .counts
                     .get(&(id.clone(), agent_type, hour))
@@ -566,6 +949,17 @@ impl<X: Ord + Clone> TimeSeriesCount<X> {
         cnt
     }
 
+    /// Like `all_total_counts`, but only counting hours in [start_hour, end_hour).
+    pub fn total_counts_between(&self, start_hour: usize, end_hour: usize) -> Counter<X> {
+        let mut cnt = Counter::new();
+        for ((id, _, hour), value) in &self.counts {
+            if *hour >= start_hour && *hour < end_hour {
+                cnt.add(id.clone(), *value);
+            }
+        }
+        cnt
+    }
+
     pub fn count_per_hour(&self, id: X, time: Time) -> Vec<(AgentType, Vec<(Time, usize)>)> {
         let hour = time.get_hours();
         let mut results = Vec::new();
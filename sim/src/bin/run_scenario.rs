@@ -1,14 +1,75 @@
 //! A simple tool that just runs a simulation for the specified number of hours. Use for profiling
 //! and benchmarking.
+//!
+//! Pass `--record_replay=path` to write a replay log of the run, and `--replay=path` to reproduce
+//! a run from one of those logs. Recording only works when starting from a scenario file. A replay
+//! runs until the end of the recorded run, or for `--hours`, if that's shorter.
 
 fn main() {
     let mut args = abstutil::CmdArgs::new();
     let interruptible = args.enabled("--interruptible");
-    let hours = geom::Duration::hours(args.required("--hours").parse::<usize>().unwrap());
-    let (mut map, mut sim, _) =
-        sim::SimFlags::from_args(&mut args).load(&mut abstutil::Timer::new("setup"));
+    let hours = args
+        .optional_parse("--hours", |s| s.parse::<usize>())
+        .map(geom::Duration::hours);
+    let record_replay = args.optional("--record_replay");
+    let replay = args.optional("--replay");
+    let flags = sim::SimFlags::from_args(&mut args);
     args.done();
 
+    if let Some(path) = replay {
+        let mut timer = abstutil::Timer::new("replay");
+        let log = sim::ReplayLog::load(path, &mut timer).unwrap();
+        let mut map = map_model::Map::new(log.map_name.path(), &mut timer);
+        if log.edits_name != map.get_edits().edits_name {
+            let edits = map_model::MapEdits::load(
+                &map,
+                abstutil::path_edits(map.get_name(), &log.edits_name),
+                &mut timer,
+            )
+            .unwrap();
+            map.must_apply_edits(edits, &mut timer);
+            map.recalculate_pathfinding_after_edits(&mut timer);
+        }
+        let mut end_time = log.end_time().unwrap_or(geom::Time::START_OF_DAY);
+        if let Some(hours) = hours {
+            end_time = end_time.min(geom::Time::START_OF_DAY + hours);
+        }
+        let mut sim = sim::Sim::replay(log, &map, &mut timer);
+        sim.timed_step(&map, end_time - sim.time(), &mut None, &mut timer);
+        println!("Replay matched the original run through {}", sim.time());
+        return;
+    }
+
+    let hours = hours.unwrap_or_else(|| {
+        eprintln!("Pass --hours to say how long to run the simulation");
+        std::process::exit(1);
+    });
+    // The log stores the scenario, so it can't be recorded when resuming from a savestate or
+    // generating a scenario on the fly.
+    if record_replay.is_some() && !flags.load.contains("/scenarios/") {
+        eprintln!(
+            "--record_replay only works when starting from a scenario file, not {}",
+            flags.load
+        );
+        std::process::exit(1);
+    }
+
+    let (mut map, mut sim, _) = flags.load(&mut abstutil::Timer::new("setup"));
+    if let Some(ref path) = record_replay {
+        let mut scenario: sim::Scenario =
+            abstutil::must_read_object(flags.load.clone(), &mut abstutil::Timer::throwaway());
+        for m in &flags.modifiers {
+            scenario = m.apply(&map, scenario);
+        }
+        println!("Recording a replay log to {}", path);
+        sim.record_replay(sim::ReplayLog::new(
+            &map,
+            &scenario,
+            flags.rng_seed,
+            &flags.opts,
+        ));
+    }
+
     if interruptible {
         // Pressing ^C will savestate. This needs a more complex loop to check for the interrupt.
         // This is guarded by the --interruptible flag to keep the benchmarking case simple.
@@ -38,6 +99,9 @@ fn main() {
                 &mut None,
             );
             if sim.time() == goal_time {
+                if let Some(path) = record_replay {
+                    sim.take_replay_log().unwrap().save(path);
+                }
                 return;
             }
         }
@@ -52,4 +116,8 @@ fn main() {
             &mut abstutil::Timer::new("run simulation"),
         );
     }
+
+    if let Some(path) = record_replay {
+        sim.take_replay_log().unwrap().save(path);
+    }
 }
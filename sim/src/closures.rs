@@ -0,0 +1,124 @@
+//! Work zones close lanes for part of the day. A scenario can list closures, and while one is
+//! active, the lane becomes `LaneType::Construction`. The simulation can't change the map itself,
+//! so whoever's running it calls `Sim::apply_lane_closures` as time passes. That applies the
+//! change as a live map edit, so agents whose routes used the lane reroute, just like when somebody
+//! edits the map in the middle of a simulation.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use geom::Time;
+use map_model::{EditCmd, LaneID, LaneType, Map, MapEdits, RoadID};
+
+/// A lane closed between two times of day.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LaneClosure {
+    pub lane: LaneID,
+    pub start: Time,
+    pub end: Time,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ClosureSimState {
+    schedule: Vec<LaneClosure>,
+    /// Lanes closed right now, with their type before the closure
+    closed: BTreeMap<LaneID, LaneType>,
+}
+
+impl ClosureSimState {
+    pub fn new(schedule: Vec<LaneClosure>) -> ClosureSimState {
+        ClosureSimState {
+            schedule,
+            closed: BTreeMap::new(),
+        }
+    }
+
+    /// Closes everything scheduled again on the next update, with the lane types the map has then.
+    pub fn forget_closed(&mut self) {
+        self.closed.clear();
+    }
+
+    /// The next time after `now` that some lane opens or closes.
+    pub fn next_change(&self, now: Time) -> Option<Time> {
+        self.schedule
+            .iter()
+            .flat_map(|c| vec![c.start, c.end])
+            .filter(|t| *t > now)
+            .min()
+    }
+
+    /// If some lanes have to open or close at this time, returns the map edits to do it.
+    pub fn update(&mut self, now: Time, map: &Map) -> Option<MapEdits> {
+        let mut changes: BTreeMap<RoadID, Vec<(LaneID, LaneType)>> = BTreeMap::new();
+        let lanes: BTreeSet<LaneID> = self.schedule.iter().map(|c| c.lane).collect();
+        for l in lanes {
+            let active = self
+                .schedule
+                .iter()
+                .any(|c| c.lane == l && c.start <= now && now < c.end);
+            let lane_type = map.get_l(l).lane_type;
+            if active && !self.closed.contains_key(&l) {
+                // Already closed some other way
+                if lane_type == LaneType::Construction {
+                    continue;
+                }
+                self.closed.insert(l, lane_type);
+                changes
+                    .entry(map.get_l(l).parent)
+                    .or_insert_with(Vec::new)
+                    .push((l, LaneType::Construction));
+            } else if !active {
+                if let Some(orig) = self.closed.remove(&l) {
+                    changes
+                        .entry(map.get_l(l).parent)
+                        .or_insert_with(Vec::new)
+                        .push((l, orig));
+                }
+            }
+        }
+        if changes.is_empty() {
+            return None;
+        }
+
+        let mut edits = map.get_edits().clone();
+        for (r, mut lanes) in changes {
+            let road = map.get_r(r);
+            let edit_cmd = |lanes: &Vec<(LaneID, LaneType)>| {
+                map.edit_road_cmd(r, |new| {
+                    for (l, lt) in lanes {
+                        new.lanes_ltr[road.offset(*l)].0 = *lt;
+                    }
+                })
+            };
+            let mut cmd = edit_cmd(&lanes);
+            // Bus stops need a lane buses can use. Leave those lanes open, rather than breaking
+            // the map.
+            if let EditCmd::ChangeRoad { ref new, .. } = cmd {
+                if !road.all_bus_stops(map).is_empty()
+                    && !new
+                        .lanes_ltr
+                        .iter()
+                        .any(|(lt, _)| *lt == LaneType::Driving || *lt == LaneType::Bus)
+                {
+                    warn!("Can't close all lanes on {}; its bus stops need them", r);
+                    for (l, lt) in &lanes {
+                        if *lt == LaneType::Construction {
+                            self.closed.remove(l);
+                        }
+                    }
+                    lanes.retain(|(_, lt)| *lt != LaneType::Construction);
+                    if lanes.is_empty() {
+                        continue;
+                    }
+                    cmd = edit_cmd(&lanes);
+                }
+            }
+            edits.commands.push(cmd);
+        }
+        if edits.commands.len() == map.get_edits().commands.len() {
+            return None;
+        }
+        Some(edits)
+    }
+}
@@ -0,0 +1,226 @@
+//! Delivery vehicles stop in travel or bike lanes to load and unload, blocking everybody behind
+//! them for a while. Map edits set how often this happens on each road, so different loading zone
+//! policies can be compared. Drivers and cyclists headed for a lane with a stopped delivery vehicle
+//! pick another lane at the previous intersection if they can; otherwise they wait behind it.
+//!
+//! Each delivery vehicle appears at the start of a lane, drives to a random point along it, stops,
+//! then continues to the end of the lane and leaves the road. Where it came from and where it goes
+//! afterwards isn't modelled.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Duration, Time};
+use map_model::{LaneID, Map, Path, PathConstraints, PathRequest, Position, RoadID};
+
+use crate::{
+    CarID, Command, CreateCar, Router, Scheduler, SimOptions, VehicleSpec, VehicleType,
+    FOLLOWING_DISTANCE, MAX_CAR_LENGTH,
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+struct DeliveryVehicle {
+    lane: LaneID,
+    /// Once the vehicle is done stopping, it heads for the end of the lane
+    delivered: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct CurbSimState {
+    /// Roads with their next delivery already scheduled
+    scheduled: BTreeSet<RoadID>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    vehicles: BTreeMap<CarID, DeliveryVehicle>,
+    /// Lanes with a delivery vehicle stopped in them right now
+    blocked: BTreeMap<LaneID, CarID>,
+    dwell_time: Duration,
+    /// Seeds the randomness for each delivery, so nothing stateful needs to be serialized
+    num_deliveries: usize,
+    stats: CurbStats,
+}
+
+/// A summary of deliveries so far.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct CurbStats {
+    /// Delivery vehicles that stopped in a lane
+    pub deliveries: usize,
+    /// Deliveries that didn't happen, because the lane was full when the vehicle tried to appear
+    pub no_room: usize,
+    /// Lanes currently blocked by a stopped delivery vehicle
+    pub blocked_lanes: usize,
+}
+
+impl CurbSimState {
+    pub fn new(opts: &SimOptions) -> CurbSimState {
+        CurbSimState {
+            scheduled: BTreeSet::new(),
+            vehicles: BTreeMap::new(),
+            blocked: BTreeMap::new(),
+            dwell_time: opts.delivery_dwell_time,
+            num_deliveries: 0,
+            stats: CurbStats::default(),
+        }
+    }
+
+    /// Schedule the first delivery for every road that should have them, but doesn't have one
+    /// coming up yet. Call this at the start of the day and after live map edits.
+    pub fn schedule_deliveries(&mut self, now: Time, map: &Map, scheduler: &mut Scheduler) {
+        for r in map.all_roads() {
+            if r.deliveries_per_hour > 0 && !self.scheduled.contains(&r.id) {
+                let mut rng = self.rng(r.id);
+                scheduler.push(
+                    now + next_delivery(&mut rng, r.deliveries_per_hour),
+                    Command::StartDelivery(r.id),
+                );
+                self.scheduled.insert(r.id);
+                self.num_deliveries += 1;
+            }
+        }
+    }
+
+    /// Time for a delivery on some road. Schedules the next one, and returns the delivery vehicle
+    /// to start, if the road has a lane long enough to stop in.
+    pub fn start_delivery(
+        &mut self,
+        now: Time,
+        r: RoadID,
+        id: CarID,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> Option<CreateCar> {
+        self.scheduled.remove(&r);
+        let road = map.get_r(r);
+        // Live map edits may have stopped deliveries here
+        if road.deliveries_per_hour == 0 {
+            return None;
+        }
+        let mut rng = self.rng(r);
+        self.num_deliveries += 1;
+        scheduler.push(
+            now + next_delivery(&mut rng, road.deliveries_per_hour),
+            Command::StartDelivery(r),
+        );
+        self.scheduled.insert(r);
+
+        let vehicle = VehicleSpec {
+            vehicle_type: VehicleType::Car,
+            length: MAX_CAR_LENGTH,
+            max_speed: None,
+        }
+        .make(id, None);
+        // Leave room to drive in, stop, and leave again
+        let min_length = vehicle.length * 3.0 + FOLLOWING_DISTANCE;
+        let lanes: Vec<LaneID> = road
+            .all_lanes()
+            .into_iter()
+            .filter(|l| {
+                let lane = map.get_l(*l);
+                (PathConstraints::Car.can_use(lane, map) || lane.is_biking())
+                    && !self.blocked.contains_key(l)
+                    && lane.length() > min_length
+            })
+            .collect();
+        if lanes.is_empty() {
+            return None;
+        }
+        let lane = map.get_l(lanes[rng.gen_range(0, lanes.len())]);
+        let start_dist = vehicle.length;
+        let stop_dist =
+            start_dist + (lane.length() - vehicle.length - start_dist) * rng.gen_range(0.1, 0.9);
+
+        let req = PathRequest {
+            start: Position::new(lane.id, start_dist),
+            end: Position::new(lane.id, stop_dist),
+            constraints: PathConstraints::Car,
+        };
+        let router = Router::delivery(id, Path::one_step(lane.id, map), stop_dist);
+        self.vehicles.insert(
+            id,
+            DeliveryVehicle {
+                lane: lane.id,
+                delivered: false,
+            },
+        );
+        Some(CreateCar {
+            vehicle,
+            router,
+            req,
+            start_dist,
+            maybe_parked_car: None,
+            trip_and_person: None,
+            maybe_route: None,
+        })
+    }
+
+    /// The lane was too full for the delivery vehicle to appear.
+    pub fn no_room(&mut self, id: CarID) {
+        self.vehicles.remove(&id).unwrap();
+        self.stats.no_room += 1;
+    }
+
+    pub fn is_delivery_vehicle(&self, id: CarID) -> bool {
+        self.vehicles.contains_key(&id)
+    }
+
+    /// The vehicle reached the end of its path. If Some, the vehicle should stop for this long,
+    /// then call `vehicle_departing`. If None, the vehicle is done and should vanish from the
+    /// road.
+    pub fn vehicle_reached_end(&mut self, id: CarID) -> Option<Duration> {
+        let v = self.vehicles.get_mut(&id).unwrap();
+        if v.delivered {
+            self.vehicles.remove(&id);
+            return None;
+        }
+        self.blocked.insert(v.lane, id);
+        self.stats.deliveries += 1;
+        Some(self.dwell_time)
+    }
+
+    /// The vehicle is done stopping, so head for the end of the lane.
+    pub fn vehicle_departing(&mut self, id: CarID, map: &Map) -> Router {
+        let v = self.vehicles.get_mut(&id).unwrap();
+        v.delivered = true;
+        self.blocked.remove(&v.lane);
+        Router::delivery(id, Path::one_step(v.lane, map), map.get_l(v.lane).length())
+    }
+
+    pub fn is_blocked(&self, l: LaneID) -> bool {
+        self.blocked.contains_key(&l)
+    }
+
+    pub fn describe_vehicle(&self, id: CarID) -> Option<String> {
+        let v = self.vehicles.get(&id)?;
+        Some(if v.delivered {
+            "Done delivering, leaving".to_string()
+        } else if self.blocked.get(&v.lane) == Some(&id) {
+            format!("Stopped in {} to make a delivery", v.lane)
+        } else {
+            format!("Looking for somewhere in {} to stop", v.lane)
+        })
+    }
+
+    pub fn get_stats(&self) -> CurbStats {
+        let mut stats = self.stats.clone();
+        stats.blocked_lanes = self.blocked.len();
+        stats
+    }
+
+    fn rng(&self, r: RoadID) -> XorShiftRng {
+        XorShiftRng::seed_from_u64(((r.0 as u64) << 32) ^ (self.num_deliveries as u64))
+    }
+}
+
+/// Deliveries on a road are a Poisson process, so the time between them is exponential.
+fn next_delivery(rng: &mut XorShiftRng, per_hour: usize) -> Duration {
+    let mean = Duration::hours(1) / (per_hour as f64);
+    // Avoid ln(0)
+    let x: f64 = rng.gen_range(0.0001, 1.0);
+    -x.ln() * mean
+}
@@ -0,0 +1,199 @@
+//! Not everybody drives the same way. Some people go over the speed limit, some wait longer for a
+//! gap before pulling out, and some take a moment to notice traffic ahead of them started moving.
+//! A scenario can describe a mix of driver types; each car or truck gets a driver drawn from it.
+//!
+//! Drivers are drawn from the vehicle's ID, so the same vehicle always has the same driver, and
+//! nothing per-vehicle has to be remembered. The simulation doesn't model acceleration, so a slow
+//! reaction just means taking longer to finish crossing the lane.
+
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use geom::Duration;
+
+use crate::{CarID, VehicleType};
+
+/// One kind of driver in a mix.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DriverType {
+    pub name: String,
+    /// How common this type is, relative to the others in the mix.
+    pub weight: f64,
+    /// How much faster than the speed limit these drivers like to go, as a fraction of the limit.
+    /// Negative means slower. Vehicles still never exceed their own max speed.
+    pub speed_offset: Spread,
+    /// Scales how long these drivers wait at stop signs and before yielding at signals, and how
+    /// much room they want before entering a roundabout. Less than 1 is aggressive.
+    pub gap_acceptance: Spread,
+    /// How many seconds these drivers take to start moving again after the way ahead clears.
+    pub reaction_time: Spread,
+}
+
+/// A normal distribution, clamped to a range.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Spread {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// The behavior of one particular driver.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct DriverProfile {
+    pub speed_offset: f64,
+    pub gap_acceptance: f64,
+    pub reaction_time: Duration,
+}
+
+/// The mix of driver types used in a simulation. With no types, everybody drives the same way.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DriverMix {
+    types: Vec<DriverType>,
+}
+
+impl Spread {
+    fn sample(&self, rng: &mut XorShiftRng) -> f64 {
+        let x = if self.std_dev > 0.0 {
+            Normal::new(self.mean, self.std_dev).unwrap().sample(rng)
+        } else {
+            self.mean
+        };
+        x.max(self.min).min(self.max)
+    }
+}
+
+impl DriverType {
+    pub fn aggressive(weight: f64) -> DriverType {
+        DriverType {
+            name: "aggressive".to_string(),
+            weight,
+            speed_offset: Spread {
+                mean: 0.15,
+                std_dev: 0.05,
+                min: 0.0,
+                max: 0.3,
+            },
+            gap_acceptance: Spread {
+                mean: 0.5,
+                std_dev: 0.1,
+                min: 0.2,
+                max: 1.0,
+            },
+            reaction_time: Spread {
+                mean: 0.5,
+                std_dev: 0.2,
+                min: 0.0,
+                max: 1.0,
+            },
+        }
+    }
+
+    pub fn typical(weight: f64) -> DriverType {
+        DriverType {
+            name: "typical".to_string(),
+            weight,
+            speed_offset: Spread {
+                mean: 0.0,
+                std_dev: 0.05,
+                min: -0.1,
+                max: 0.1,
+            },
+            gap_acceptance: Spread {
+                mean: 1.0,
+                std_dev: 0.1,
+                min: 0.7,
+                max: 1.3,
+            },
+            reaction_time: Spread {
+                mean: 1.0,
+                std_dev: 0.3,
+                min: 0.5,
+                max: 2.0,
+            },
+        }
+    }
+
+    pub fn cautious(weight: f64) -> DriverType {
+        DriverType {
+            name: "cautious".to_string(),
+            weight,
+            speed_offset: Spread {
+                mean: -0.1,
+                std_dev: 0.05,
+                min: -0.25,
+                max: 0.0,
+            },
+            gap_acceptance: Spread {
+                mean: 2.0,
+                std_dev: 0.4,
+                min: 1.2,
+                max: 3.0,
+            },
+            reaction_time: Spread {
+                mean: 1.5,
+                std_dev: 0.4,
+                min: 0.8,
+                max: 3.0,
+            },
+        }
+    }
+
+    /// Aggressive and cautious drivers make up the given percentages, and everybody else is
+    /// typical.
+    pub fn mix(pct_aggressive: usize, pct_cautious: usize) -> Vec<DriverType> {
+        let pct_typical = 100 - (pct_aggressive + pct_cautious).min(100);
+        vec![
+            DriverType::aggressive(pct_aggressive as f64),
+            DriverType::typical(pct_typical as f64),
+            DriverType::cautious(pct_cautious as f64),
+        ]
+        .into_iter()
+        .filter(|t| t.weight > 0.0)
+        .collect()
+    }
+}
+
+/// Drives exactly at the speed limit, waits the usual time for gaps, and reacts instantly.
+impl Default for DriverProfile {
+    fn default() -> DriverProfile {
+        DriverProfile {
+            speed_offset: 0.0,
+            gap_acceptance: 1.0,
+            reaction_time: Duration::ZERO,
+        }
+    }
+}
+
+impl DriverMix {
+    pub fn new(types: Vec<DriverType>) -> DriverMix {
+        DriverMix { types }
+    }
+
+    /// Only people driving cars and trucks vary. Buses, trains, bikes, and emergency vehicles
+    /// always behave the same.
+    pub fn profile(&self, car: CarID) -> DriverProfile {
+        let total: f64 = self.types.iter().map(|t| t.weight).sum();
+        if total <= 0.0 || !(car.1 == VehicleType::Car || car.1 == VehicleType::Truck) {
+            return DriverProfile::default();
+        }
+
+        let mut rng = XorShiftRng::seed_from_u64(car.0 as u64);
+        let mut pick = rng.gen_range(0.0, total);
+        let driver_type = self
+            .types
+            .iter()
+            .find(|t| {
+                pick -= t.weight;
+                pick < 0.0
+            })
+            .unwrap_or_else(|| self.types.last().unwrap());
+        DriverProfile {
+            speed_offset: driver_type.speed_offset.sample(&mut rng),
+            gap_acceptance: driver_type.gap_acceptance.sample(&mut rng),
+            reaction_time: Duration::seconds(driver_type.reaction_time.sample(&mut rng)),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! A rough estimate of the fuel burned and pollution emitted by vehicles. Agents in this
+//! simulation accelerate instantly, so there's no real acceleration profile to use. Instead, the
+//! cost of stop-and-go driving is captured by the average speed over each lane or turn (slow
+//! crossings cost more per kilometer) and by the time spent idling in queues and at intersections.
+//!
+//! The curves loosely follow published averages (like COPERT) for a typical gasoline car and a
+//! diesel bus or truck. They're meant to compare the same city before and after some change, not
+//! to produce an emissions inventory.
+
+use std::ops;
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Distance, Duration};
+
+use crate::VehicleType;
+
+/// Tailpipe emissions and the fuel burned to produce them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Emissions {
+    pub fuel_liters: f64,
+    pub co2_grams: f64,
+    pub nox_grams: f64,
+}
+
+struct Profile {
+    /// The CO2 emitted per kilometer at speed v (in km/h) is `base + stop_and_go / v + drag * v^2`
+    base: f64,
+    stop_and_go: f64,
+    drag: f64,
+    /// CO2 emitted per second while idling
+    idle: f64,
+    /// CO2 emitted per liter of fuel burned
+    co2_per_liter: f64,
+    nox_per_liter: f64,
+}
+
+const GASOLINE_CAR: Profile = Profile {
+    base: 100.0,
+    stop_and_go: 3500.0,
+    drag: 0.01,
+    idle: 0.4,
+    co2_per_liter: 2310.0,
+    nox_per_liter: 0.4,
+};
+const DIESEL_TRUCK: Profile = Profile {
+    base: 350.0,
+    stop_and_go: 9000.0,
+    drag: 0.03,
+    idle: 0.9,
+    co2_per_liter: 2680.0,
+    nox_per_liter: 16.0,
+};
+const DIESEL_BUS: Profile = Profile {
+    base: 450.0,
+    stop_and_go: 12000.0,
+    drag: 0.03,
+    idle: 1.9,
+    co2_per_liter: 2680.0,
+    nox_per_liter: 16.0,
+};
+
+/// Below this, the per-kilometer curve explodes; treat anything slower as crawling at this speed.
+const MIN_SPEED_KMH: f64 = 5.0;
+
+impl Emissions {
+    pub const ZERO: Emissions = Emissions {
+        fuel_liters: 0.0,
+        co2_grams: 0.0,
+        nox_grams: 0.0,
+    };
+
+    /// Emissions from covering some distance over some time.
+    pub fn driving(vehicle_type: VehicleType, dist: Distance, time: Duration) -> Emissions {
+        let profile = match Emissions::profile(vehicle_type) {
+            Some(p) => p,
+            None => {
+                return Emissions::ZERO;
+            }
+        };
+        if dist == Distance::ZERO || time == Duration::ZERO {
+            return Emissions::ZERO;
+        }
+        let km = dist.inner_meters() / 1000.0;
+        let kmh = (km / (time.inner_seconds() / 3600.0)).max(MIN_SPEED_KMH);
+        let co2_per_km = profile.base + profile.stop_and_go / kmh + profile.drag * kmh * kmh;
+        Emissions::from_co2(profile, co2_per_km * km)
+    }
+
+    /// Emissions from waiting with the engine running.
+    pub fn idling(vehicle_type: VehicleType, time: Duration) -> Emissions {
+        match Emissions::profile(vehicle_type) {
+            Some(profile) => Emissions::from_co2(profile, profile.idle * time.inner_seconds()),
+            None => Emissions::ZERO,
+        }
+    }
+
+    fn profile(vehicle_type: VehicleType) -> Option<&'static Profile> {
+        match vehicle_type {
+            VehicleType::Car => Some(&GASOLINE_CAR),
+            VehicleType::Truck | VehicleType::Emergency => Some(&DIESEL_TRUCK),
+            VehicleType::Bus => Some(&DIESEL_BUS),
+            // Trains are electric, and bikes are people-powered
+            VehicleType::Train | VehicleType::Bike => None,
+        }
+    }
+
+    fn from_co2(profile: &Profile, co2_grams: f64) -> Emissions {
+        let fuel_liters = co2_grams / profile.co2_per_liter;
+        Emissions {
+            fuel_liters,
+            co2_grams,
+            nox_grams: fuel_liters * profile.nox_per_liter,
+        }
+    }
+}
+
+impl ops::Add for Emissions {
+    type Output = Emissions;
+
+    fn add(self, other: Emissions) -> Emissions {
+        Emissions {
+            fuel_liters: self.fuel_liters + other.fuel_liters,
+            co2_grams: self.co2_grams + other.co2_grams,
+            nox_grams: self.nox_grams + other.nox_grams,
+        }
+    }
+}
+
+impl ops::AddAssign for Emissions {
+    fn add_assign(&mut self, other: Emissions) {
+        *self = *self + other;
+    }
+}
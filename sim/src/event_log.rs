@@ -0,0 +1,379 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+
+use geom::{Duration, Time};
+use map_model::{BusStopID, Traversable};
+
+use crate::{
+    AgentID, AlertLocation, CarID, Event, ParkingSpot, PersonID, SafetyEvent, TripID, TripMode,
+    VehicleType,
+};
+
+/// How many rows to buffer before appending to the file.
+const FLUSH_EVERY: usize = 10_000;
+
+/// The columns of the event log. Every row has every column; unused ones are empty.
+///
+/// - time: seconds since midnight
+/// - event: the name of the Event variant, like TripFinished
+/// - trip, person: numeric IDs
+/// - agent_type: car, bike, bus, train, truck, emergency, pedestrian, or bus_passenger. Ride-hail
+///   vehicles and shuttles are cars, and people riding in them are bus_passenger.
+/// - agent_id: numeric ID of the car or pedestrian. For bus_passenger, the person.
+/// - mode: Walk, Bike, Transit, Drive, or RideHail
+/// - location_type: lane, turn, intersection, building, bus_stop, parking_lot, or onstreet_spot
+/// - location_id: numeric ID. Turns are "src_lane-dst_lane", bus stops are "sidewalk-idx", and
+///   on-street parking spots are "lane-idx".
+/// - duration: seconds, meaning depends on the event
+/// - details: anything else, meaning depends on the event
+pub const EVENT_LOG_HEADER: &str = "time,event,trip,person,agent_type,agent_id,mode,location_type,\
+                                    location_id,duration,details";
+
+/// Writes every Event to a CSV file as the simulation runs, for analysis outside of the UI. Rows
+/// are buffered in memory and appended to the file periodically, and when explicitly flushed.
+#[derive(Clone)]
+pub(crate) struct EventLogger {
+    path: String,
+    rows: Vec<String>,
+}
+
+impl EventLogger {
+    /// Creates the file, overwriting anything existing, and writes the header.
+    pub fn new(path: String) -> Result<EventLogger, String> {
+        let mut f = File::create(&path).map_err(|err| err.to_string())?;
+        writeln!(f, "{}", EVENT_LOG_HEADER).map_err(|err| err.to_string())?;
+        Ok(EventLogger {
+            path,
+            rows: Vec::new(),
+        })
+    }
+
+    pub fn handle_event(&mut self, time: Time, ev: &Event) {
+        self.rows.push(Row::new(time, ev).to_csv());
+        if self.rows.len() >= FLUSH_EVERY {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let f = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .unwrap_or_else(|err| panic!("Can't append to event log {}: {}", self.path, err));
+        let mut f = BufWriter::new(f);
+        for row in self.rows.drain(..) {
+            writeln!(f, "{}", row).unwrap();
+        }
+    }
+}
+
+/// One event, flattened into the columns described by `EVENT_LOG_HEADER`. The results database
+/// stores the same rows.
+#[derive(Clone, Default)]
+pub(crate) struct Row {
+    pub time: f64,
+    pub event: &'static str,
+    pub trip: Option<TripID>,
+    pub person: Option<PersonID>,
+    pub agent: Option<AgentID>,
+    pub mode: Option<TripMode>,
+    pub location: Option<(&'static str, String)>,
+    pub duration: Option<Duration>,
+    pub details: String,
+}
+
+impl Row {
+    pub fn new(time: Time, ev: &Event) -> Row {
+        let mut row = Row {
+            time: time.inner_seconds(),
+            ..Default::default()
+        };
+        match ev {
+            Event::CarReachedParkingSpot(car, spot) => {
+                row.event = "CarReachedParkingSpot";
+                row.agent = Some(AgentID::Car(*car));
+                row.location = Some(spot_location(*spot));
+            }
+            Event::CarLeftParkingSpot(car, spot) => {
+                row.event = "CarLeftParkingSpot";
+                row.agent = Some(AgentID::Car(*car));
+                row.location = Some(spot_location(*spot));
+            }
+            Event::BusArrivedAtStop(car, route, stop) => {
+                row.event = "BusArrivedAtStop";
+                row.agent = Some(AgentID::Car(*car));
+                row.location = Some(stop_location(*stop));
+                row.details = format!("route {}", route.0);
+            }
+            Event::BusDepartedFromStop(car, route, stop) => {
+                row.event = "BusDepartedFromStop";
+                row.agent = Some(AgentID::Car(*car));
+                row.location = Some(stop_location(*stop));
+                row.details = format!("route {}", route.0);
+            }
+            Event::PassengerBoardsTransit(person, car, route, stop, wait) => {
+                row.event = "PassengerBoardsTransit";
+                row.person = Some(*person);
+                row.agent = Some(AgentID::Car(*car));
+                row.location = Some(stop_location(*stop));
+                row.duration = Some(*wait);
+                row.details = format!("route {}", route.0);
+            }
+            Event::PassengerAlightsTransit(person, car, route, stop) => {
+                row.event = "PassengerAlightsTransit";
+                row.person = Some(*person);
+                row.agent = Some(AgentID::Car(*car));
+                row.location = Some(stop_location(*stop));
+                row.details = format!("route {}", route.0);
+            }
+            Event::PersonEntersBuilding(person, b) => {
+                row.event = "PersonEntersBuilding";
+                row.person = Some(*person);
+                row.location = Some(("building", b.0.to_string()));
+            }
+            Event::PersonLeavesBuilding(person, b) => {
+                row.event = "PersonLeavesBuilding";
+                row.person = Some(*person);
+                row.location = Some(("building", b.0.to_string()));
+            }
+            Event::PersonLeavesMap(person, agent, i) => {
+                row.event = "PersonLeavesMap";
+                row.person = Some(*person);
+                row.agent = *agent;
+                row.location = Some(("intersection", i.0.to_string()));
+                if agent.is_none() {
+                    row.details = "cancelled".to_string();
+                }
+            }
+            Event::PersonEntersMap(person, agent, i) => {
+                row.event = "PersonEntersMap";
+                row.person = Some(*person);
+                row.agent = Some(*agent);
+                row.location = Some(("intersection", i.0.to_string()));
+            }
+            Event::PedReachedParkingSpot(ped, spot) => {
+                row.event = "PedReachedParkingSpot";
+                row.agent = Some(AgentID::Pedestrian(*ped));
+                row.location = Some(spot_location(*spot));
+            }
+            Event::BikeStoppedAtSidewalk(car, l) => {
+                row.event = "BikeStoppedAtSidewalk";
+                row.agent = Some(AgentID::Car(*car));
+                row.location = Some(("lane", l.0.to_string()));
+            }
+            Event::AgentEntersTraversable(agent, on, passengers) => {
+                row.event = "AgentEntersTraversable";
+                row.agent = Some(*agent);
+                row.location = Some(traversable_location(*on));
+                if let Some(n) = passengers {
+                    row.details = format!("{} passengers", n);
+                }
+            }
+            Event::IntersectionDelayMeasured(movement, delay, agent) => {
+                row.event = "IntersectionDelayMeasured";
+                row.agent = Some(*agent);
+                row.location = Some(("intersection", movement.i.0.to_string()));
+                row.duration = Some(*delay);
+                row.details = format!("movement {}", movement.idx);
+            }
+            Event::TripFinished {
+                trip,
+                mode,
+                total_time,
+                blocked_time,
+            } => {
+                row.event = "TripFinished";
+                row.trip = Some(*trip);
+                row.mode = Some(*mode);
+                row.duration = Some(*total_time);
+                row.details = format!("blocked for {}s", blocked_time.inner_seconds());
+            }
+            Event::TripCancelled(trip, mode) => {
+                row.event = "TripCancelled";
+                row.trip = Some(*trip);
+                row.mode = Some(*mode);
+            }
+            Event::TripPhaseStarting(trip, person, _, phase) => {
+                row.event = "TripPhaseStarting";
+                row.trip = Some(*trip);
+                row.person = Some(*person);
+                row.details = format!("{:?}", phase);
+            }
+            Event::TripIntersectionDelay(trip, turn, agent, delay) => {
+                row.event = "TripIntersectionDelay";
+                row.trip = Some(*trip);
+                row.agent = Some(*agent);
+                row.location = Some(traversable_location(Traversable::Turn(*turn)));
+                row.duration = Some(*delay);
+            }
+            Event::LaneSpeedPercentage(trip, l, avg, max) => {
+                row.event = "LaneSpeedPercentage";
+                row.trip = Some(*trip);
+                row.location = Some(("lane", l.0.to_string()));
+                row.details = format!(
+                    "average {} m/s, max {} m/s",
+                    avg.inner_meters_per_second(),
+                    max.inner_meters_per_second()
+                );
+            }
+            Event::CruisedForParking(trip, duration) => {
+                row.event = "CruisedForParking";
+                row.trip = Some(*trip);
+                row.duration = Some(*duration);
+            }
+            Event::PaidForParking(person, spot, price) => {
+                row.event = "PaidForParking";
+                row.person = Some(*person);
+                row.location = Some(spot_location(*spot));
+                row.details = format!("${:.2}", price);
+            }
+            Event::VehicleEmissions(car, trip, on, emissions) => {
+                row.event = "VehicleEmissions";
+                row.trip = *trip;
+                row.agent = Some(AgentID::Car(*car));
+                row.location = Some(traversable_location(*on));
+                row.details = format!(
+                    "{} L fuel, {} g CO2, {} g NOx",
+                    emissions.fuel_liters, emissions.co2_grams, emissions.nox_grams
+                );
+            }
+            Event::PathAmended(_) => {
+                row.event = "PathAmended";
+            }
+            Event::Alert(loc, msg) => {
+                row.event = "Alert";
+                match loc {
+                    AlertLocation::Nil => {}
+                    AlertLocation::Intersection(i) => {
+                        row.location = Some(("intersection", i.0.to_string()));
+                    }
+                    AlertLocation::Person(p) => {
+                        row.person = Some(*p);
+                    }
+                    AlertLocation::Building(b) => {
+                        row.location = Some(("building", b.0.to_string()));
+                    }
+                }
+                row.details = msg.clone();
+            }
+            Event::Safety(ev) => {
+                row.event = "Safety";
+                match ev {
+                    SafetyEvent::HardBraking {
+                        car,
+                        on,
+                        deceleration,
+                    } => {
+                        row.agent = Some(AgentID::Car(*car));
+                        row.location = Some(traversable_location(*on));
+                        row.details = format!("hard braking at {} m/s^2", deceleration);
+                    }
+                    SafetyEvent::ShortPostEncroachment {
+                        intersection,
+                        first,
+                        second,
+                        pet,
+                    } => {
+                        row.agent = Some(*second);
+                        row.location = Some(("intersection", intersection.0.to_string()));
+                        row.duration = Some(*pet);
+                        row.details = format!("short post-encroachment time after {}", first);
+                    }
+                    SafetyEvent::ClosePass {
+                        car,
+                        bike,
+                        lane,
+                        clearance,
+                    } => {
+                        row.agent = Some(AgentID::Car(*car));
+                        row.location = Some(("lane", lane.0.to_string()));
+                        row.details = format!(
+                            "passed {} with {} m clearance",
+                            bike,
+                            clearance.inner_meters()
+                        );
+                    }
+                }
+            }
+        }
+        row
+    }
+
+    /// The agent_type and agent_id columns
+    pub fn agent_columns(&self) -> Option<(&'static str, usize)> {
+        match self.agent? {
+            AgentID::Car(car) => Some((car_type(car), car.0)),
+            AgentID::Pedestrian(ped) => Some(("pedestrian", ped.0)),
+            AgentID::BusPassenger(person, _) => Some(("bus_passenger", person.0)),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let (agent_type, agent_id) = match self.agent_columns() {
+            Some((t, id)) => (t, id.to_string()),
+            None => ("", String::new()),
+        };
+        let (location_type, location_id) = match self.location {
+            Some((t, ref id)) => (t, id.clone()),
+            None => ("", String::new()),
+        };
+        [
+            self.time.to_string(),
+            self.event.to_string(),
+            self.trip.map(|t| t.0.to_string()).unwrap_or_default(),
+            self.person.map(|p| p.0.to_string()).unwrap_or_default(),
+            agent_type.to_string(),
+            agent_id,
+            self.mode.map(|m| format!("{:?}", m)).unwrap_or_default(),
+            location_type.to_string(),
+            location_id,
+            self.duration
+                .map(|d| d.inner_seconds().to_string())
+                .unwrap_or_default(),
+            escape(&self.details),
+        ]
+        .join(",")
+    }
+}
+
+fn car_type(car: CarID) -> &'static str {
+    match car.1 {
+        VehicleType::Car => "car",
+        VehicleType::Bike => "bike",
+        VehicleType::Bus => "bus",
+        VehicleType::Train => "train",
+        VehicleType::Truck => "truck",
+        VehicleType::Emergency => "emergency",
+    }
+}
+
+fn traversable_location(on: Traversable) -> (&'static str, String) {
+    match on {
+        Traversable::Lane(l) => ("lane", l.0.to_string()),
+        Traversable::Turn(t) => ("turn", format!("{}-{}", t.src.0, t.dst.0)),
+    }
+}
+
+fn stop_location(stop: BusStopID) -> (&'static str, String) {
+    ("bus_stop", format!("{}-{}", stop.sidewalk.0, stop.idx()))
+}
+
+fn spot_location(spot: ParkingSpot) -> (&'static str, String) {
+    match spot {
+        ParkingSpot::Onstreet(l, idx) => ("onstreet_spot", format!("{}-{}", l.0, idx)),
+        ParkingSpot::Offstreet(b, _) => ("building", b.0.to_string()),
+        ParkingSpot::Lot(pl, _) => ("parking_lot", pl.0.to_string()),
+    }
+}
+
+/// Quote a free-form field if needed.
+fn escape(x: &str) -> String {
+    if x.contains(',') || x.contains('"') || x.contains('\n') {
+        format!("\"{}\"", x.replace('"', "\"\""))
+    } else {
+        x.to_string()
+    }
+}
@@ -6,7 +6,9 @@ use map_model::{
     PathRequest, Traversable, TurnID,
 };
 
-use crate::{AgentID, CarID, ParkingSpot, PedestrianID, PersonID, TripID, TripMode};
+use crate::{
+    AgentID, CarID, Emissions, ParkingSpot, PedestrianID, PersonID, SafetyEvent, TripID, TripMode,
+};
 
 /// As a simulation runs, different systems emit Events. This cleanly separates the internal
 /// mechanics of the simulation from consumers that just want to know what's happening.
@@ -55,11 +57,26 @@ pub enum Event {
     /// TripID, LaneID (Where the delay was encountered), Average Speed, Max Speed
     LaneSpeedPercentage(TripID, LaneID, Speed, Speed),
 
+    /// How long a driver spent cruising for parking, counted once they find a spot. Only happens
+    /// with `SimOptions::cruise_for_parking`.
+    CruisedForParking(TripID, Duration),
+
+    /// Fuel burned by a vehicle while crossing or idling on some lane or turn. The trip is None for
+    /// buses.
+    VehicleEmissions(CarID, Option<TripID>, Traversable, Emissions),
+
     /// Just use for parking replanning. Not happy about copying the full path in here, but the way
     /// to plumb info into Analytics is Event.
     PathAmended(Path),
 
     Alert(AlertLocation, String),
+
+    /// A near miss, counted as a surrogate for crashes.
+    Safety(SafetyEvent),
+
+    /// A driver parked in a paid parking zone. The price, in dollars, covers the whole stay and is
+    /// charged up front.
+    PaidForParking(PersonID, ParkingSpot, f64),
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -82,6 +99,11 @@ pub enum TripPhaseType {
     Cancelled,
     Finished,
     DelayedStart,
+    WaitingForRideHail,
+    RidingRideHail(CarID),
+    /// Which guideway
+    WaitingForGuideway(usize),
+    RidingGuideway(usize),
 }
 
 impl TripPhaseType {
@@ -98,6 +120,16 @@ impl TripPhaseType {
             TripPhaseType::Cancelled => "Trip was cancelled due to some bug".to_string(),
             TripPhaseType::Finished => "Trip finished".to_string(),
             TripPhaseType::DelayedStart => "Delayed by a previous trip taking too long".to_string(),
+            TripPhaseType::WaitingForRideHail => "Waiting for a ride-hail pickup".to_string(),
+            TripPhaseType::RidingRideHail(_) => "Riding in a ride-hail vehicle".to_string(),
+            TripPhaseType::WaitingForGuideway(idx) => match map.get_guideways().get(idx) {
+                Some(guideway) => format!("Waiting for {}", guideway.name),
+                None => "Waiting for a removed guideway".to_string(),
+            },
+            TripPhaseType::RidingGuideway(idx) => match map.get_guideways().get(idx) {
+                Some(guideway) => format!("Riding {}", guideway.name),
+                None => "Riding a removed guideway".to_string(),
+            },
         }
     }
 }
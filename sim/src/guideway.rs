@@ -0,0 +1,169 @@
+//! Riders on fixed guideways (gondolas, personal rapid transit, monorails) sketched in map edits.
+//!
+//! The vehicles aren't simulated as agents. Each line follows a fixed schedule: starting at
+//! midnight, a vehicle leaves each end of the line every headway and stops at every station along
+//! the way. Somebody arriving at a station boards the first vehicle heading their way that has
+//! room for them on every segment of their ride, then reappears as a pedestrian at the station
+//! where they get off. A vehicle that's full for part of somebody's ride passes them by.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Duration, Time};
+use map_model::Map;
+
+use crate::{Command, Scheduler, TripID};
+
+/// Don't look further ahead than this many vehicles for one with room
+const MAX_VEHICLES_TO_WAIT_FOR: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct GuidewaySimState {
+    /// How many people are aboard each vehicle on each segment between two stations, keyed by
+    /// (guideway, heading towards the last station, which departure from the end of the line)
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    loads: BTreeMap<(usize, bool, usize), Vec<usize>>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    riders: BTreeMap<TripID, Rider>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    ridership: BTreeMap<usize, GuidewayRidership>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Rider {
+    guideway: usize,
+    boarded: bool,
+    arrive: Time,
+}
+
+/// Ridership on one guideway so far.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GuidewayRidership {
+    pub riders: usize,
+    /// Indexed by station
+    pub boardings: Vec<usize>,
+    /// Indexed by station
+    pub alightings: Vec<usize>,
+    /// From reaching the station to boarding, summed over everybody
+    pub total_wait: Duration,
+    /// How many times a full vehicle passed somebody by
+    pub left_behind: usize,
+}
+
+impl GuidewaySimState {
+    /// Somebody reached a station. Finds the first vehicle with room and schedules them to board
+    /// it. Returns false if the guideway or the stations don't exist anymore.
+    pub fn ped_reached_station(
+        &mut self,
+        now: Time,
+        trip: TripID,
+        guideway: usize,
+        board: usize,
+        alight: usize,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        let line = match map.get_guideways().get(guideway) {
+            Some(line) => line,
+            None => {
+                return false;
+            }
+        };
+        let num_stations = line.stations.len();
+        if board >= num_stations || alight >= num_stations || board == alight {
+            return false;
+        }
+        let forward = alight > board;
+        let segments = if forward {
+            board..alight
+        } else {
+            alight..board
+        };
+        // When the first vehicle heading this way reaches the boarding station
+        let offset = if forward {
+            line.travel_time(map, 0, board)
+        } else {
+            line.travel_time(map, num_stations - 1, board)
+        };
+        let since_first = now.inner_seconds() - offset.inner_seconds();
+        let mut run = if since_first <= 0.0 {
+            0
+        } else {
+            (since_first / line.headway.inner_seconds()).ceil() as usize
+        };
+
+        let mut left_behind = 0;
+        for _ in 0..MAX_VEHICLES_TO_WAIT_FOR {
+            let loads = self
+                .loads
+                .entry((guideway, forward, run))
+                .or_insert_with(|| vec![0; num_stations - 1]);
+            if segments.clone().all(|idx| loads[idx] < line.capacity) {
+                for idx in segments.clone() {
+                    loads[idx] += 1;
+                }
+                break;
+            }
+            left_behind += 1;
+            run += 1;
+        }
+
+        let depart = Time::START_OF_DAY + offset + (run as f64) * line.headway;
+        let arrive = depart + line.travel_time(map, board, alight);
+        self.riders.insert(
+            trip,
+            Rider {
+                guideway,
+                boarded: false,
+                arrive,
+            },
+        );
+        scheduler.push(depart.max(now), Command::UpdateGuidewayRider(trip));
+
+        let stats = self
+            .ridership
+            .entry(guideway)
+            .or_insert_with(Default::default);
+        stats
+            .boardings
+            .resize(num_stations.max(stats.boardings.len()), 0);
+        stats
+            .alightings
+            .resize(num_stations.max(stats.alightings.len()), 0);
+        stats.riders += 1;
+        stats.boardings[board] += 1;
+        stats.alightings[alight] += 1;
+        stats.total_wait += depart.max(now) - now;
+        stats.left_behind += left_behind;
+        true
+    }
+
+    /// The vehicle somebody's waiting for has arrived, or the one they're riding reached their
+    /// stop. Returns the guideway, and true if they just got off.
+    pub fn update_rider(&mut self, trip: TripID, scheduler: &mut Scheduler) -> (usize, bool) {
+        let rider = self.riders.get_mut(&trip).unwrap();
+        if rider.boarded {
+            let rider = self.riders.remove(&trip).unwrap();
+            (rider.guideway, true)
+        } else {
+            rider.boarded = true;
+            scheduler.push(rider.arrive, Command::UpdateGuidewayRider(trip));
+            (rider.guideway, false)
+        }
+    }
+
+    pub fn get_ridership(&self) -> &BTreeMap<usize, GuidewayRidership> {
+        &self.ridership
+    }
+}
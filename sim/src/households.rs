@@ -0,0 +1,79 @@
+//! Households share a limited number of cars. Every member plans their day as if they had their
+//! own car, but only as many cars as the household owns start the day parked. When a member goes
+//! to drive and their own car isn't parked, they take any other household car parked nearby
+//! instead. If every car is taken, they ride transit or walk.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::Distance;
+
+use crate::PersonID;
+
+/// Members only borrow cars parked this close to where they're starting
+pub(crate) const MAX_DIST_TO_HOUSEHOLD_CAR: Distance = Distance::const_meters(500.0);
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct HouseholdSimState {
+    /// The members of each household
+    members: Vec<Vec<PersonID>>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    household_of: BTreeMap<PersonID, usize>,
+    stats: HouseholdStats,
+}
+
+/// A summary of how households have shared their cars so far.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct HouseholdStats {
+    pub households: usize,
+    /// Driving trips that used a car another member had left parked
+    pub borrowed_cars: usize,
+    /// Driving trips that rode transit or walked instead, because every car was taken
+    pub switched_mode: usize,
+}
+
+impl HouseholdSimState {
+    pub fn add_household(&mut self, members: Vec<PersonID>) {
+        let id = self.members.len();
+        for p in &members {
+            if let Some(other) = self.household_of.insert(*p, id) {
+                panic!("{} belongs to households {} and {}", p, other, id);
+            }
+        }
+        self.members.push(members);
+        self.stats.households += 1;
+    }
+
+    pub fn shares_cars(&self, person: PersonID) -> bool {
+        self.household_of.contains_key(&person)
+    }
+
+    /// Everybody else in the same household as this person
+    pub fn other_members(&self, person: PersonID) -> Vec<PersonID> {
+        match self.household_of.get(&person) {
+            Some(id) => self.members[*id]
+                .iter()
+                .filter(|p| **p != person)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn borrowed_car(&mut self) {
+        self.stats.borrowed_cars += 1;
+    }
+
+    pub fn switched_mode(&mut self) {
+        self.stats.switched_mode += 1;
+    }
+
+    pub fn get_stats(&self) -> &HouseholdStats {
+        &self.stats
+    }
+}
@@ -7,7 +7,7 @@
 //!
 //! Helpful terminology:
 //! - sov = single occupancy vehicle, a car with just a driver and no passengers. (Car passengers
-//!   are not currently modelled)
+//!   are only modelled for ride-hail vehicles)
 
 #[macro_use]
 extern crate log;
@@ -28,42 +28,106 @@ pub use crate::render::{
     UnzoomedAgent,
 };
 
-pub use self::analytics::{Analytics, TripPhase};
+pub use self::analytics::{
+    Analytics, DelayStats, HeadwayStats, ParkingCounts, TransitTripBreakdown, TripPhase,
+    PARKING_OCCUPANCY_EVERY,
+};
 pub(crate) use self::cap::CapSimState;
+pub(crate) use self::closures::ClosureSimState;
+pub use self::closures::LaneClosure;
+pub(crate) use self::curb::CurbSimState;
+pub use self::curb::CurbStats;
+pub(crate) use self::drivers::{DriverMix, DriverProfile};
+pub use self::drivers::{DriverType, Spread};
+pub use self::emissions::Emissions;
+pub(crate) use self::event_log::EventLogger;
+pub use self::event_log::EVENT_LOG_HEADER;
 pub(crate) use self::events::Event;
 pub use self::events::{AlertLocation, TripPhaseType};
+pub use self::guideway::GuidewayRidership;
+pub(crate) use self::guideway::GuidewaySimState;
+pub(crate) use self::households::HouseholdSimState;
+pub use self::households::HouseholdStats;
 pub(crate) use self::make::TripSpec;
 pub use self::make::{
-    fork_rng, BorderSpawnOverTime, ExternalPerson, ExternalTrip, ExternalTripEndpoint, IndividTrip,
-    PersonSpec, Scenario, ScenarioGenerator, ScenarioModifier, SimFlags, SpawnOverTime,
-    TripEndpoint, TripPurpose,
+    calibrate_to_counts, compare_counts, fork_rng, geh, load_scenario, parse_counts,
+    BorderSpawnOverTime, CountComparison, DeliverySpawnOverTime, DepartureCurve, ExternalPerson,
+    ExternalTrip, ExternalTripEndpoint, HouseholdSpec, IndividTrip, PersonSpec, Scenario,
+    ScenarioGenerator, ScenarioModifier, SimFlags, SpawnOverTime, TrafficCount, TripEndpoint,
+    TripPurpose,
+};
+pub use self::mechanics::{
+    ActuatedController, BikeRackOccupancy, IntersectionView, SignalController, StageDecision,
+    IMPROVISED_BIKE_PARKING_TIME,
 };
 pub(crate) use self::mechanics::{
-    DrivingSimState, IntersectionSimState, ParkingSim, ParkingSimState, WalkingSimState,
+    BikeParkingSimState, DrivingSimState, IntersectionSimState, ParkingSearch, ParkingSim,
+    ParkingSimState, WalkingSimState,
 };
+pub(crate) use self::microtransit::MicrotransitSimState;
+pub use self::microtransit::{MicrotransitPassenger, MicrotransitStats};
 pub(crate) use self::pandemic::PandemicModel;
 pub(crate) use self::recorder::TrafficRecorder;
+pub use self::replay::ReplayLog;
+pub(crate) use self::replay::ReplayState;
+pub(crate) use self::rerouting::ReroutingSimState;
+pub use self::rerouting::ReroutingStats;
+#[cfg(feature = "sqlite")]
+pub(crate) use self::results_db::ResultsDb;
+pub use self::ride_hail::RideHailStats;
+pub(crate) use self::ride_hail::{RideHailRequest, RideHailSimState};
 pub(crate) use self::router::{ActionAtEnd, Router};
-pub(crate) use self::scheduler::{Command, Scheduler};
-pub use self::sim::{AgentProperties, AlertHandler, DelayCause, Sim, SimCallback, SimOptions};
+pub(crate) use self::safety::MAX_PET;
+pub use self::safety::{SafetyEvent, SafetyLocation, SafetyStats};
+pub(crate) use self::scheduler::{Command, CommandType, Scheduler};
+pub use self::sim::{
+    AgentProperties, AlertHandler, CarConstraint, CarIntent, DelayCause, Sim, SimCallback,
+    SimOptions, DEFAULT_VALUE_OF_TIME,
+};
+pub use self::sim_api::{
+    AgentPosition, FinishedTrip, ParkingOccupancy, RoadThroughput, SimApi, TripRecord, TripStatus,
+};
+pub use self::traces::{AgentTraces, Ghost};
 pub(crate) use self::transit::TransitSimState;
 pub use self::trips::TripMode;
 pub use self::trips::{CommutersVehiclesCounts, Person, PersonState, TripInfo, TripResult};
 pub(crate) use self::trips::{TripLeg, TripManager};
+pub use self::turn_counts::{
+    Approach, TurnCountColumn, TurnCountTable, TurnCounts, TurnMovement, TURN_COUNT_INTERVAL,
+};
+pub use self::weather::Weather;
 
 mod analytics;
 mod cap;
+mod closures;
+mod curb;
+mod drivers;
+mod emissions;
+mod event_log;
 mod events;
+mod guideway;
+mod households;
 mod make;
 mod mechanics;
+mod microtransit;
 mod pandemic;
 mod recorder;
 mod render;
+mod replay;
+mod rerouting;
+#[cfg(feature = "sqlite")]
+mod results_db;
+mod ride_hail;
 mod router;
+mod safety;
 mod scheduler;
 mod sim;
+mod sim_api;
+mod traces;
 mod transit;
 mod trips;
+mod turn_counts;
+mod weather;
 
 // http://pccsc.net/bicycle-parking-info/ says 68 inches, which is 1.73m
 pub(crate) const BIKE_LENGTH: Distance = Distance::const_meters(1.8);
@@ -73,6 +137,10 @@ pub(crate) const MAX_CAR_LENGTH: Distance = Distance::const_meters(6.5);
 // Note this is more than MAX_CAR_LENGTH
 pub(crate) const BUS_LENGTH: Distance = Distance::const_meters(12.5);
 pub(crate) const LIGHT_RAIL_LENGTH: Distance = Distance::const_meters(60.0);
+// Also more than PARKING_SPOT_LENGTH, so trucks only park offstreet or in lots
+pub(crate) const TRUCK_LENGTH: Distance = Distance::const_meters(10.0);
+// An ambulance still fits in a regular parking spot
+pub(crate) const EMERGENCY_VEHICLE_LENGTH: Distance = Distance::const_meters(6.0);
 
 /// At all speeds (including at rest), cars must be at least this far apart, measured from front of
 /// one car to the back of the other.
@@ -102,6 +170,8 @@ impl fmt::Display for CarID {
             VehicleType::Bus => write!(f, "Bus #{}", self.0),
             VehicleType::Train => write!(f, "Train #{}", self.0),
             VehicleType::Bike => write!(f, "Bike #{}", self.0),
+            VehicleType::Truck => write!(f, "Truck #{}", self.0),
+            VehicleType::Emergency => write!(f, "Emergency vehicle #{}", self.0),
         }
     }
 }
@@ -140,7 +210,8 @@ impl AgentID {
     pub fn to_type(self) -> AgentType {
         match self {
             AgentID::Car(c) => match c.1 {
-                VehicleType::Car => AgentType::Car,
+                // Trucks and emergency vehicles are counted with cars
+                VehicleType::Car | VehicleType::Truck | VehicleType::Emergency => AgentType::Car,
                 VehicleType::Bike => AgentType::Bike,
                 VehicleType::Bus => AgentType::Bus,
                 VehicleType::Train => AgentType::Train,
@@ -274,6 +345,10 @@ pub enum VehicleType {
     Bus,
     Train,
     Bike,
+    Truck,
+    /// Ambulances and fire engines. They preempt traffic signals, and change lanes into gaps to
+    /// get around stopped traffic.
+    Emergency,
 }
 
 impl fmt::Display for VehicleType {
@@ -283,6 +358,8 @@ impl fmt::Display for VehicleType {
             VehicleType::Bus => write!(f, "bus"),
             VehicleType::Train => write!(f, "train"),
             VehicleType::Bike => write!(f, "bike"),
+            VehicleType::Truck => write!(f, "truck"),
+            VehicleType::Emergency => write!(f, "emergency vehicle"),
         }
     }
 }
@@ -294,6 +371,8 @@ impl VehicleType {
             VehicleType::Bus => PathConstraints::Bus,
             VehicleType::Train => PathConstraints::Train,
             VehicleType::Bike => PathConstraints::Bike,
+            VehicleType::Truck => PathConstraints::Truck,
+            VehicleType::Emergency => PathConstraints::Car,
         }
     }
 
@@ -303,6 +382,8 @@ impl VehicleType {
             VehicleType::Bus => true,
             VehicleType::Train => true,
             VehicleType::Bike => false,
+            VehicleType::Truck => false,
+            VehicleType::Emergency => false,
         }
     }
 }
@@ -362,7 +443,7 @@ impl DrivingGoal {
     pub fn goal_pos(&self, constraints: PathConstraints, map: &Map) -> Option<Position> {
         match self {
             DrivingGoal::ParkNear(b) => match constraints {
-                PathConstraints::Car => {
+                PathConstraints::Car | PathConstraints::Truck => {
                     Some(Position::start(map.find_driving_lane_near_building(*b)))
                 }
                 PathConstraints::Bike => Some(map.get_b(*b).biking_connection(map)?.0),
@@ -409,6 +490,8 @@ pub(crate) enum SidewalkPOI {
     /// The bikeable position
     BikeRack(Position),
     SuddenlyAppear,
+    /// A guideway and one of its stations
+    GuidewayStation(usize, usize),
 }
 
 impl SidewalkSpot {
@@ -456,6 +539,18 @@ impl SidewalkSpot {
         }
     }
 
+    /// None if the guideway or station doesn't exist anymore
+    pub fn guideway_station(guideway: usize, station: usize, map: &Map) -> Option<SidewalkSpot> {
+        let pos = map
+            .get_guideways()
+            .get(guideway)
+            .and_then(|_| map.get_guideway_stations(guideway).get(station))?;
+        Some(SidewalkSpot {
+            sidewalk_pos: *pos,
+            connection: SidewalkPOI::GuidewayStation(guideway, station),
+        })
+    }
+
     // Recall sidewalks are bidirectional.
     pub fn start_at_border(i: IntersectionID, map: &Map) -> Option<SidewalkSpot> {
         let lanes = map
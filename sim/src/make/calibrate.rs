@@ -0,0 +1,282 @@
+//! Calibrate a scenario's demand to match observed traffic counts. Every driving trip is routed
+//! along its free-flow shortest path, which tells us which counted OpenStreetMap ways it passes
+//! and roughly when. People are then reweighted until the modeled counts match the observed ones
+//! as well as they can, and copied or removed according to their weights.
+//!
+//! The fit at each count location is measured with the GEH statistic. A GEH under 5 is usually
+//! considered a good match.
+
+use std::collections::BTreeMap;
+
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use abstutil::{Parallelism, Timer};
+use geom::{Duration, Time};
+use map_model::{osm, Direction, Map, PathStep, RoadID};
+
+use crate::{PersonSpec, Scenario, TripEndpoint, TripMode};
+
+/// How many rounds of reweighting to do
+const ITERATIONS: usize = 20;
+/// Nobody is copied more than this many times
+const MAX_WEIGHT: f64 = 5.0;
+
+/// The number of vehicles observed on an OpenStreetMap way during one hour. Counts are keyed by
+/// way, not RoadID, so they still apply after the map is reimported. A way may be split into
+/// several roads; a vehicle driving along more than one of them is only counted once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TrafficCount {
+    pub way: osm::WayID,
+    /// Relative to the order of the way's nodes. None means vehicles in both directions were
+    /// counted together.
+    pub direction: Option<Direction>,
+    /// Counted from midnight of the first day, so 7 means 7-8am
+    pub hour: usize,
+    pub count: usize,
+}
+
+/// How well the modeled demand matches one count.
+#[derive(Clone, Debug)]
+pub struct CountComparison {
+    pub count: TrafficCount,
+    pub modeled: usize,
+}
+
+impl TrafficCount {
+    /// The roads that the counted way became. Empty if the way isn't part of this map.
+    pub fn roads(&self, map: &Map) -> Vec<RoadID> {
+        map.all_roads()
+            .iter()
+            .filter(|r| r.orig_id.osm_way_id == self.way)
+            .map(|r| r.id)
+            .collect()
+    }
+}
+
+impl CountComparison {
+    pub fn geh(&self) -> f64 {
+        geh(self.modeled as f64, self.count.count as f64)
+    }
+}
+
+/// The GEH statistic compares a modeled and observed hourly count, penalizing the same absolute
+/// difference less when there's more traffic.
+pub fn geh(modeled: f64, observed: f64) -> f64 {
+    if modeled + observed == 0.0 {
+        return 0.0;
+    }
+    (2.0 * (modeled - observed).powi(2) / (modeled + observed)).sqrt()
+}
+
+/// Parses counts from CSV with `way,direction,hour,count` columns. The way is an OpenStreetMap
+/// way ID, and the direction is `fwd`, `back`, or `both`. A header row is optional.
+pub fn parse_counts(contents: &str) -> Result<Vec<TrafficCount>, String> {
+    let mut counts = Vec::new();
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (idx == 0 && line.starts_with("way")) {
+            continue;
+        }
+        let cols: Vec<&str> = line.split(',').map(|x| x.trim()).collect();
+        let parse = |x: &str| {
+            x.parse::<usize>()
+                .map_err(|_| format!("line {}: bad number {}", idx + 1, x))
+        };
+        if cols.len() != 4 {
+            return Err(format!(
+                "line {}: expected way,direction,hour,count but got {}",
+                idx + 1,
+                line
+            ));
+        }
+        let way = cols[0]
+            .parse::<i64>()
+            .map_err(|_| format!("line {}: bad way {}", idx + 1, cols[0]))?;
+        let direction = match cols[1] {
+            "fwd" => Some(Direction::Fwd),
+            "back" => Some(Direction::Back),
+            "both" => None,
+            x => {
+                return Err(format!(
+                    "line {}: direction should be fwd, back, or both, not {}",
+                    idx + 1,
+                    x
+                ));
+            }
+        };
+        counts.push(TrafficCount {
+            way: osm::WayID(way),
+            direction,
+            hour: parse(cols[2])?,
+            count: parse(cols[3])?,
+        });
+    }
+    Ok(counts)
+}
+
+/// Compares the scenario's demand to the counts, without changing anything.
+pub fn compare_counts(
+    map: &Map,
+    scenario: &Scenario,
+    counts: &[TrafficCount],
+    timer: &mut Timer,
+) -> Vec<CountComparison> {
+    let crossings = all_crossings(map, scenario, counts, timer);
+    let weights = vec![1.0; scenario.people.len()];
+    modeled_counts(&crossings, &weights, counts.len())
+        .into_iter()
+        .zip(counts)
+        .map(|(modeled, count)| CountComparison {
+            count: *count,
+            modeled: modeled.round() as usize,
+        })
+        .collect()
+}
+
+/// Copies or removes people, so the scenario's demand matches the counts better. People whose
+/// trips don't pass any of the counts are left alone.
+pub fn calibrate_to_counts(
+    map: &Map,
+    mut scenario: Scenario,
+    counts: &[TrafficCount],
+    rng: &mut XorShiftRng,
+    timer: &mut Timer,
+) -> Scenario {
+    let crossings = all_crossings(map, &scenario, counts, timer);
+
+    // Iterative proportional fitting. Each round, every person's weight is scaled by the geometric
+    // mean of how far off the counts they pass are.
+    let mut weights = vec![1.0; scenario.people.len()];
+    for _ in 0..ITERATIONS {
+        let modeled = modeled_counts(&crossings, &weights, counts.len());
+        let ratios: Vec<f64> = counts
+            .iter()
+            .zip(modeled)
+            .map(|(count, modeled)| {
+                if modeled == 0.0 {
+                    1.0
+                } else {
+                    count.count as f64 / modeled
+                }
+            })
+            .collect();
+        for (weight, idxs) in weights.iter_mut().zip(&crossings) {
+            if idxs.is_empty() {
+                continue;
+            }
+            // A zero count removes everybody passing it
+            if idxs.iter().any(|idx| ratios[*idx] == 0.0) {
+                *weight = 0.0;
+                continue;
+            }
+            let log_mean =
+                idxs.iter().map(|idx| ratios[*idx].ln()).sum::<f64>() / (idxs.len() as f64);
+            *weight = (*weight * log_mean.exp()).min(MAX_WEIGHT);
+        }
+    }
+
+    let mut people = Vec::new();
+    // Copies don't join the original's household
+    let mut new_indices = Vec::new();
+    for (person, weight) in scenario.people.drain(..).zip(weights) {
+        let mut copies = weight.floor() as usize;
+        if rng.gen_bool(weight.fract()) {
+            copies += 1;
+        }
+        new_indices.push(if copies == 0 {
+            None
+        } else {
+            Some(people.len())
+        });
+        for copy in 0..copies {
+            let mut person = person.clone();
+            if copy > 0 || weight != 1.0 {
+                for trip in &mut person.trips {
+                    trip.modified = true;
+                }
+            }
+            people.push(person);
+        }
+    }
+    scenario.people = people;
+    scenario.renumber_households(&new_indices);
+    scenario
+}
+
+fn modeled_counts(crossings: &[Vec<usize>], weights: &[f64], num_counts: usize) -> Vec<f64> {
+    let mut modeled = vec![0.0; num_counts];
+    for (idxs, weight) in crossings.iter().zip(weights) {
+        for idx in idxs {
+            modeled[*idx] += weight;
+        }
+    }
+    modeled
+}
+
+/// For each person, the counts their driving trips contribute to. If a person passes the same
+/// count twice, it's listed twice.
+fn all_crossings(
+    map: &Map,
+    scenario: &Scenario,
+    counts: &[TrafficCount],
+    timer: &mut Timer,
+) -> Vec<Vec<usize>> {
+    let lookup: BTreeMap<CountKey, usize> = counts
+        .iter()
+        .enumerate()
+        .map(|(idx, c)| ((c.way, c.direction, c.hour), idx))
+        .collect();
+    timer.parallelize(
+        "route trips past traffic counts",
+        Parallelism::Fastest,
+        scenario.people.iter().collect(),
+        |person| person_crossings(map, person, &lookup),
+    )
+}
+
+/// (way, direction, hour)
+type CountKey = (osm::WayID, Option<Direction>, usize);
+
+fn person_crossings(
+    map: &Map,
+    person: &PersonSpec,
+    lookup: &BTreeMap<CountKey, usize>,
+) -> Vec<usize> {
+    let mut results = Vec::new();
+    let mut from = person.origin;
+    for trip in &person.trips {
+        let to = trip.destination;
+        if !trip.cancelled && (trip.mode == TripMode::Drive || trip.mode == TripMode::RideHail) {
+            if let Some(path) =
+                TripEndpoint::path_req(from, to, trip.mode, map).and_then(|req| map.pathfind(req))
+            {
+                // Estimate when each way is reached, moving at the speed limit
+                let mut time = trip.depart;
+                let mut last_way = None;
+                for step in path.get_steps() {
+                    if let PathStep::Lane(l) = step {
+                        let road = map.get_parent(*l);
+                        let way = (road.orig_id.osm_way_id, road.dir(*l));
+                        // Continuing along the same way through an intersection doesn't pass the
+                        // count again
+                        if last_way != Some(way) {
+                            let hour = ((time - Time::START_OF_DAY) / Duration::hours(1)) as usize;
+                            for direction in &[Some(way.1), None] {
+                                if let Some(idx) = lookup.get(&(way.0, *direction, hour)) {
+                                    results.push(*idx);
+                                }
+                            }
+                        }
+                        last_way = Some(way);
+                    }
+                    let t = step.as_traversable();
+                    time += t.length(map) / t.speed_limit(map);
+                }
+            }
+        }
+        from = to;
+    }
+    results
+}
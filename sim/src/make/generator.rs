@@ -1,16 +1,21 @@
 //! This is a much more primitive way to randomly generate trips. activity_model.rs has something
 //! more realistic.
+//!
+//! A ScenarioGenerator can also be written by hand as JSON and placed in
+//! `data/system/$city/scenario_generators/$map/$name.json`. The headless server, run_scenario, and
+//! the game can all load these directly.
 
 use std::collections::BTreeSet;
 
 use rand::seq::SliceRandom;
 use rand::Rng;
+use rand_distr::{Distribution, Normal};
 use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
-use abstutil::Timer;
+use abstutil::{MapName, Timer};
 use geom::{Duration, Time};
-use map_model::{IntersectionID, Map};
+use map_model::{BuildingID, IntersectionID, Map};
 
 use crate::{IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose};
 
@@ -23,6 +28,8 @@ pub struct ScenarioGenerator {
     pub only_seed_buses: Option<BTreeSet<String>>,
     pub spawn_over_time: Vec<SpawnOverTime>,
     pub border_spawn_over_time: Vec<BorderSpawnOverTime>,
+    #[serde(default)]
+    pub deliveries: Vec<DeliverySpawnOverTime>,
 }
 
 // SpawnOverTime and BorderSpawnOverTime should be kept separate. Agents in SpawnOverTime pick
@@ -31,9 +38,14 @@ pub struct ScenarioGenerator {
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct SpawnOverTime {
     pub num_agents: usize,
-    // TODO use https://docs.rs/rand/0.5.5/rand/distributions/struct.Normal.html
     pub start_time: Time,
     pub stop_time: Time,
+    #[serde(default)]
+    pub departure_curve: DepartureCurve,
+    /// If unspecified, agents start from a random building.
+    #[serde(default)]
+    pub origin: Option<TripEndpoint>,
+    /// If unspecified, agents go to a random building.
     pub goal: Option<TripEndpoint>,
     pub percent_driving: f64,
     pub percent_biking: f64,
@@ -46,13 +58,66 @@ pub struct BorderSpawnOverTime {
     pub num_cars: usize,
     pub num_bikes: usize,
     pub percent_use_transit: f64,
-    // TODO use https://docs.rs/rand/0.5.5/rand/distributions/struct.Normal.html
     pub start_time: Time,
     pub stop_time: Time,
-    pub start_from_border: IntersectionID,
+    #[serde(default)]
+    pub departure_curve: DepartureCurve,
+    /// If unspecified, this many agents spawn from every incoming border.
+    #[serde(default)]
+    pub start_from_border: Option<IntersectionID>,
     pub goal: Option<TripEndpoint>,
 }
 
+/// Trucks that enter the map from a border, deliver to a random shop, and leave through a random
+/// border. Shops are buildings with an amenity that `is_shop` recognizes.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct DeliverySpawnOverTime {
+    pub num_trucks: usize,
+    pub start_time: Time,
+    pub stop_time: Time,
+    #[serde(default)]
+    pub departure_curve: DepartureCurve,
+    /// How long a truck stays at the shop before leaving
+    pub unloading_time: Duration,
+    /// If unspecified, trucks start from a random border.
+    #[serde(default)]
+    pub start_from_border: Option<IntersectionID>,
+}
+
+/// How departure times are distributed between the start and stop time.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum DepartureCurve {
+    /// Every time in the window is equally likely.
+    Uniform,
+    /// Departures cluster around a peak, like rush hour. Samples outside the window are clamped
+    /// to it.
+    Normal { peak: Time, std_dev: Duration },
+}
+
+impl Default for DepartureCurve {
+    fn default() -> DepartureCurve {
+        DepartureCurve::Uniform
+    }
+}
+
+impl DepartureCurve {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            DepartureCurve::Uniform => Ok(()),
+            DepartureCurve::Normal { std_dev, .. } => {
+                if *std_dev < Duration::ZERO {
+                    Err(format!(
+                        "A normal departure curve can't have a negative std_dev of {}",
+                        std_dev
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
 impl ScenarioGenerator {
     // TODO may need to fork the RNG a bit more
     pub fn generate(&self, map: &Map, rng: &mut XorShiftRng, timer: &mut Timer) -> Scenario {
@@ -72,19 +137,49 @@ impl ScenarioGenerator {
         timer.start_iter("BorderSpawnOverTime", self.border_spawn_over_time.len());
         for s in &self.border_spawn_over_time {
             timer.next();
-            for _ in 0..s.num_peds {
-                let mode = if rng.gen_bool(s.percent_use_transit) {
-                    TripMode::Transit
-                } else {
-                    TripMode::Walk
-                };
-                s.spawn(rng, &mut scenario, mode, map);
-            }
-            for _ in 0..s.num_cars {
-                s.spawn(rng, &mut scenario, TripMode::Drive, map);
+            // If there are no sidewalks/driving lanes at a border, scenario instantiation will
+            // just warn and skip them.
+            let borders = match s.start_from_border {
+                Some(i) => vec![i],
+                None => map
+                    .all_incoming_borders()
+                    .into_iter()
+                    .map(|i| i.id)
+                    .collect(),
+            };
+            for border in borders {
+                for _ in 0..s.num_peds {
+                    let mode = if rng.gen_bool(s.percent_use_transit) {
+                        TripMode::Transit
+                    } else {
+                        TripMode::Walk
+                    };
+                    s.spawn(rng, &mut scenario, border, mode, map);
+                }
+                for _ in 0..s.num_cars {
+                    s.spawn(rng, &mut scenario, border, TripMode::Drive, map);
+                }
+                for _ in 0..s.num_bikes {
+                    s.spawn(rng, &mut scenario, border, TripMode::Bike, map);
+                }
             }
-            for _ in 0..s.num_bikes {
-                s.spawn(rng, &mut scenario, TripMode::Bike, map);
+        }
+
+        if !self.deliveries.is_empty() {
+            let shops: Vec<BuildingID> = map
+                .all_buildings()
+                .iter()
+                .filter(|b| b.amenities.iter().any(|a| is_shop(&a.amenity_type)))
+                .map(|b| b.id)
+                .collect();
+            if shops.is_empty() {
+                timer.warn("No shops on this map, so no deliveries".to_string());
+            } else {
+                for s in &self.deliveries {
+                    for _ in 0..s.num_trucks {
+                        s.spawn(rng, &mut scenario, &shops, map);
+                    }
+                }
             }
         }
 
@@ -92,48 +187,29 @@ impl ScenarioGenerator {
         scenario.remove_weird_schedules()
     }
 
-    pub fn small_run(map: &Map) -> ScenarioGenerator {
-        let mut s = ScenarioGenerator {
-            scenario_name: "small_run".to_string(),
-            only_seed_buses: None,
-            spawn_over_time: vec![SpawnOverTime {
-                num_agents: 100,
-                start_time: Time::START_OF_DAY,
-                stop_time: Time::START_OF_DAY + Duration::seconds(5.0),
-                goal: None,
-                percent_driving: 0.5,
-                percent_biking: 0.5,
-                percent_use_transit: 0.5,
-            }],
-            // If there are no sidewalks/driving lanes at a border, scenario instantiation will
-            // just warn and skip them.
-            border_spawn_over_time: map
-                .all_incoming_borders()
-                .into_iter()
-                .map(|i| BorderSpawnOverTime {
-                    num_peds: 10,
-                    num_cars: 10,
-                    num_bikes: 10,
-                    start_time: Time::START_OF_DAY,
-                    stop_time: Time::START_OF_DAY + Duration::seconds(5.0),
-                    start_from_border: i.id,
-                    goal: None,
-                    percent_use_transit: 0.5,
-                })
-                .collect(),
-        };
-        for i in map.all_outgoing_borders() {
-            s.spawn_over_time.push(SpawnOverTime {
-                num_agents: 10,
-                start_time: Time::START_OF_DAY,
-                stop_time: Time::START_OF_DAY + Duration::seconds(5.0),
-                goal: Some(TripEndpoint::Border(i.id)),
-                percent_driving: 0.5,
-                percent_biking: 0.5,
-                percent_use_transit: 0.5,
-            });
+    /// Reads a generator from a JSON file in
+    /// `data/system/$city/scenario_generators/$map/$name.json`. The map it applies to comes from
+    /// the path.
+    pub fn load(path: String, timer: &mut Timer) -> Result<(MapName, ScenarioGenerator), String> {
+        let (map_name, _) = abstutil::parse_scenario_path(&path);
+        let generator: ScenarioGenerator = abstutil::maybe_read_json(path, timer)?;
+        generator.validate()?;
+        Ok((map_name, generator))
+    }
+
+    /// Checks everything that JSON parsing alone doesn't catch. Generators read from a file
+    /// should be validated before generating anything.
+    pub fn validate(&self) -> Result<(), String> {
+        for s in &self.spawn_over_time {
+            s.departure_curve.validate()?;
+        }
+        for s in &self.border_spawn_over_time {
+            s.departure_curve.validate()?;
         }
-        s
+        for s in &self.deliveries {
+            s.departure_curve.validate()?;
+        }
+        Ok(())
     }
 
     pub fn empty(name: &str) -> ScenarioGenerator {
@@ -142,16 +218,20 @@ impl ScenarioGenerator {
             only_seed_buses: Some(BTreeSet::new()),
             spawn_over_time: Vec::new(),
             border_spawn_over_time: Vec::new(),
+            deliveries: Vec::new(),
         }
     }
 }
 
 impl SpawnOverTime {
     fn spawn_agent(&self, rng: &mut XorShiftRng, scenario: &mut Scenario, map: &Map) {
-        let depart = rand_time(rng, self.start_time, self.stop_time);
+        let depart = rand_time(rng, self.start_time, self.stop_time, &self.departure_curve);
         // Note that it's fine for agents to start/end at the same building. Later we might
         // want a better assignment of people per household, or workers per office building.
-        let from_bldg = map.all_buildings().choose(rng).unwrap().id;
+        let origin = self
+            .origin
+            .clone()
+            .unwrap_or_else(|| TripEndpoint::Bldg(map.all_buildings().choose(rng).unwrap().id));
         let mode = if rng.gen_bool(self.percent_driving) {
             TripMode::Drive
         } else if rng.gen_bool(self.percent_biking) {
@@ -163,7 +243,7 @@ impl SpawnOverTime {
         };
         scenario.people.push(PersonSpec {
             orig_id: None,
-            origin: TripEndpoint::Bldg(from_bldg),
+            origin,
             trips: vec![IndividTrip::new(
                 depart,
                 TripPurpose::Shopping,
@@ -177,11 +257,18 @@ impl SpawnOverTime {
 }
 
 impl BorderSpawnOverTime {
-    fn spawn(&self, rng: &mut XorShiftRng, scenario: &mut Scenario, mode: TripMode, map: &Map) {
-        let depart = rand_time(rng, self.start_time, self.stop_time);
+    fn spawn(
+        &self,
+        rng: &mut XorShiftRng,
+        scenario: &mut Scenario,
+        border: IntersectionID,
+        mode: TripMode,
+        map: &Map,
+    ) {
+        let depart = rand_time(rng, self.start_time, self.stop_time, &self.departure_curve);
         scenario.people.push(PersonSpec {
             orig_id: None,
-            origin: TripEndpoint::Border(self.start_from_border),
+            origin: TripEndpoint::Border(border),
             trips: vec![IndividTrip::new(
                 depart,
                 TripPurpose::Shopping,
@@ -194,7 +281,116 @@ impl BorderSpawnOverTime {
     }
 }
 
-fn rand_time(rng: &mut XorShiftRng, low: Time, high: Time) -> Time {
+impl DeliverySpawnOverTime {
+    fn spawn(
+        &self,
+        rng: &mut XorShiftRng,
+        scenario: &mut Scenario,
+        shops: &Vec<BuildingID>,
+        map: &Map,
+    ) {
+        let start = match self.start_from_border {
+            Some(i) => i,
+            None => match map.all_incoming_borders().choose(rng) {
+                Some(i) => i.id,
+                None => {
+                    return;
+                }
+            },
+        };
+        let end = match map.all_outgoing_borders().choose(rng) {
+            Some(i) => i.id,
+            None => {
+                return;
+            }
+        };
+        let shop = *shops.choose(rng).unwrap();
+        let depart = rand_time(rng, self.start_time, self.stop_time, &self.departure_curve);
+        scenario.people.push(PersonSpec {
+            orig_id: None,
+            origin: TripEndpoint::Border(start),
+            trips: vec![
+                IndividTrip::new(
+                    depart,
+                    TripPurpose::Delivery,
+                    TripEndpoint::Bldg(shop),
+                    TripMode::Drive,
+                ),
+                IndividTrip::new(
+                    depart + self.unloading_time,
+                    TripPurpose::Delivery,
+                    TripEndpoint::Border(end),
+                    TripMode::Drive,
+                ),
+            ],
+        });
+    }
+}
+
+/// Recognizes common values of OSM's shop tag. The importer usually drops the OSM tags of buildings
+/// and amenities to save space, so this only looks at the amenity type.
+pub(crate) fn is_shop(amenity_type: &str) -> bool {
+    matches!(
+        amenity_type,
+        "supermarket"
+            | "convenience"
+            | "marketplace"
+            | "mall"
+            | "department_store"
+            | "variety_store"
+            | "general"
+            | "bakery"
+            | "pastry"
+            | "confectionery"
+            | "greengrocer"
+            | "butcher"
+            | "deli"
+            | "beverages"
+            | "alcohol"
+            | "kiosk"
+            | "newsagent"
+            | "clothes"
+            | "shoes"
+            | "second_hand"
+            | "jewelry"
+            | "gift"
+            | "florist"
+            | "books"
+            | "stationery"
+            | "furniture"
+            | "hardware"
+            | "doityourself"
+            | "electronics"
+            | "computer"
+            | "mobile_phone"
+            | "sports"
+            | "bicycle"
+            | "pet"
+            | "car"
+            | "car_parts"
+            | "tyres"
+            | "chemist"
+            | "hairdresser"
+            | "beauty"
+    )
+}
+
+pub(crate) fn rand_time(
+    rng: &mut XorShiftRng,
+    low: Time,
+    high: Time,
+    curve: &DepartureCurve,
+) -> Time {
     assert!(high > low);
-    Time::START_OF_DAY + Duration::seconds(rng.gen_range(low.inner_seconds(), high.inner_seconds()))
+    let secs = match curve {
+        DepartureCurve::Uniform => rng.gen_range(low.inner_seconds(), high.inner_seconds()),
+        DepartureCurve::Normal { peak, std_dev } => {
+            Normal::new(peak.inner_seconds(), std_dev.inner_seconds())
+                .expect("DepartureCurve::validate should reject this")
+                .sample(rng)
+                .max(low.inner_seconds())
+                .min(high.inner_seconds())
+        }
+    };
+    Time::START_OF_DAY + Duration::seconds(secs)
 }
@@ -1,10 +1,10 @@
 use rand::SeedableRng;
 use rand_xorshift::XorShiftRng;
 
-use abstutil::{CmdArgs, MapName};
+use abstutil::{CmdArgs, MapName, Timer};
 use map_model::{Map, MapEdits};
 
-use crate::{Scenario, ScenarioModifier, Sim, SimOptions};
+use crate::{Scenario, ScenarioGenerator, ScenarioModifier, Sim, SimOptions};
 
 /// SimFlags specifies a simulation to setup.
 #[derive(Clone)]
@@ -12,6 +12,7 @@ pub struct SimFlags {
     /// A path to some file.
     /// - a savestate: restore the simulation exactly from some savestate
     /// - a scenario
+    /// - a scenario generator, in JSON, that'll be used to generate a scenario
     /// - some kind of map: start an empty simulation on the map
     pub load: String,
     pub modifiers: Vec<ScenarioModifier>,
@@ -55,7 +56,7 @@ impl SimFlags {
         XorShiftRng::seed_from_u64(self.rng_seed)
     }
 
-    pub fn load(&self, timer: &mut abstutil::Timer) -> (Map, Sim, XorShiftRng) {
+    pub fn load(&self, timer: &mut Timer) -> (Map, Sim, XorShiftRng) {
         let mut rng = self.make_rng();
 
         let mut opts = self.opts.clone();
@@ -81,15 +82,13 @@ impl SimFlags {
             }
 
             (map, sim, rng)
-        } else if self.load.contains("/scenarios/") {
+        } else if self.load.contains("/scenarios/") || self.load.contains("/scenario_generators/") {
             timer.note(format!(
                 "Seeding the simulation from scenario {}",
                 self.load
             ));
 
-            let mut scenario: Scenario = abstutil::must_read_object(self.load.clone(), timer);
-
-            let map = Map::new(scenario.map_name.path(), timer);
+            let (map, mut scenario) = load_scenario(self.load.clone(), &mut rng, timer);
 
             for m in &self.modifiers {
                 scenario = m.apply(&map, scenario);
@@ -117,3 +116,19 @@ impl SimFlags {
         }
     }
 }
+
+/// Loads a scenario and its map from a path. The path can point to a scenario or to a scenario
+/// generator, which is run using the RNG.
+pub fn load_scenario(path: String, rng: &mut XorShiftRng, timer: &mut Timer) -> (Map, Scenario) {
+    if path.contains("/scenario_generators/") {
+        let (map_name, generator) = ScenarioGenerator::load(path.clone(), timer)
+            .unwrap_or_else(|err| panic!("Couldn't load scenario generator {}: {}", path, err));
+        let map = Map::new(map_name.path(), timer);
+        let scenario = generator.generate(&map, rng, timer);
+        (map, scenario)
+    } else {
+        let scenario: Scenario = abstutil::must_read_versioned_object(path, timer);
+        let map = Map::new(scenario.map_name.path(), timer);
+        (map, scenario)
+    }
+}
@@ -4,19 +4,26 @@
 use rand::{RngCore, SeedableRng};
 use rand_xorshift::XorShiftRng;
 
+pub use self::calibrate::{
+    calibrate_to_counts, compare_counts, geh, parse_counts, CountComparison, TrafficCount,
+};
 pub use self::external::{ExternalPerson, ExternalTrip, ExternalTripEndpoint};
-pub use self::generator::{BorderSpawnOverTime, ScenarioGenerator, SpawnOverTime};
-pub use self::load::SimFlags;
+pub use self::generator::{
+    BorderSpawnOverTime, DeliverySpawnOverTime, DepartureCurve, ScenarioGenerator, SpawnOverTime,
+};
+pub use self::load::{load_scenario, SimFlags};
 pub use self::modifier::ScenarioModifier;
-pub use self::scenario::{IndividTrip, PersonSpec, Scenario, TripPurpose};
+pub use self::scenario::{HouseholdSpec, IndividTrip, PersonSpec, Scenario, TripPurpose};
 pub use self::spawner::TripEndpoint;
 pub(crate) use self::spawner::TripSpec;
 
 mod activity_model;
+mod calibrate;
 mod external;
 mod generator;
 mod load;
 mod modifier;
+mod population;
 mod scenario;
 mod spawner;
 
@@ -1,12 +1,17 @@
 use std::collections::BTreeSet;
 
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
 use abstutil::Timer;
 use geom::{Duration, Time};
 use map_model::Map;
 
-use crate::{Scenario, TripMode};
+use crate::make::calibrate::{calibrate_to_counts, TrafficCount};
+use crate::{
+    DriverType, IndividTrip, PersonSpec, Scenario, TripEndpoint, TripMode, TripPurpose, Weather,
+};
 
 /// Transforms an existing Scenario before instantiating it.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
@@ -21,6 +26,26 @@ pub enum ScenarioModifier {
     },
     /// Scenario name
     AddExtraTrips(String),
+    /// Assuming the scenario starts on a Monday and has been repeated over multiple days, cancel
+    /// all trips on Saturdays and Sundays, except for this percentage of people.
+    ReduceWeekendTrips {
+        pct_ppl: usize,
+    },
+    /// People whose driving trips would pay to enter a toll zone bike or walk instead, if the
+    /// extra time is worth less than the tolls. Time is valued at this many dollars per hour.
+    RespondToTolls {
+        value_of_time: usize,
+    },
+    /// Copy or remove people, so driving demand matches observed traffic counts.
+    CalibrateToCounts(Vec<TrafficCount>),
+    /// Everybody moves slower and drivers leave longer gaps. Some people who'd bike or walk take
+    /// transit instead.
+    Weather(Weather),
+    /// Some percent of drivers are aggressive and some are cautious. Everybody else is typical.
+    DriverMix {
+        pct_aggressive: usize,
+        pct_cautious: usize,
+    },
 }
 
 impl ScenarioModifier {
@@ -71,16 +96,72 @@ impl ScenarioModifier {
                 s
             }
             ScenarioModifier::AddExtraTrips(name) => {
-                let other: Scenario = abstutil::must_read_object(
+                let other: Scenario = abstutil::must_read_versioned_object(
                     abstutil::path_scenario(map.get_name(), name),
                     &mut Timer::throwaway(),
                 );
+                let offset = s.people.len();
                 for mut p in other.people {
                     for trip in &mut p.trips {
                         trip.modified = true;
                     }
                     s.people.push(p);
                 }
+                for mut household in other.households {
+                    for idx in &mut household.members {
+                        *idx += offset;
+                    }
+                    s.households.push(household);
+                }
+                s
+            }
+            ScenarioModifier::ReduceWeekendTrips { pct_ppl } => {
+                for (idx, person) in s.people.iter_mut().enumerate() {
+                    // Stable as the percentage changes, just like ChangeMode
+                    if idx % 100 < *pct_ppl {
+                        continue;
+                    }
+                    // Everybody returns home at the end of a repeated day, so cancelling whole
+                    // days doesn't leave anyone stranded.
+                    for trip in &mut person.trips {
+                        if is_weekend(trip.depart) {
+                            trip.modified = true;
+                            trip.cancelled = true;
+                        }
+                    }
+                }
+                s
+            }
+            ScenarioModifier::RespondToTolls { value_of_time } => {
+                for person in &mut s.people {
+                    respond_to_tolls(map, person, *value_of_time as f64);
+                }
+                s
+            }
+            ScenarioModifier::CalibrateToCounts(counts) => {
+                // Modifiers don't get an RNG, but the result should still be deterministic
+                let mut rng = XorShiftRng::seed_from_u64(42);
+                calibrate_to_counts(map, s, counts, &mut rng, &mut Timer::throwaway())
+            }
+            ScenarioModifier::Weather(weather) => {
+                s.weather = *weather;
+                for (idx, person) in s.people.iter_mut().enumerate() {
+                    for trip in &mut person.trips {
+                        // Stable as the weather gets worse, just like ChangeMode. Switch all of
+                        // someone's trips with a mode, so they don't leave their bike somewhere.
+                        if idx % 100 < weather.pct_switching_from(trip.mode) {
+                            trip.mode = TripMode::Transit;
+                            trip.modified = true;
+                        }
+                    }
+                }
+                s
+            }
+            ScenarioModifier::DriverMix {
+                pct_aggressive,
+                pct_cautious,
+            } => {
+                s.driver_mix = DriverType::mix(*pct_aggressive, *pct_cautious);
                 s
             }
         }
@@ -104,33 +185,141 @@ impl ScenarioModifier {
                 to_mode.map(|m| m.verb())
             ),
             ScenarioModifier::AddExtraTrips(name) => format!("Add extra trips from {}", name),
+            ScenarioModifier::ReduceWeekendTrips { pct_ppl } => {
+                format!("only {}% of people travel on weekends", pct_ppl)
+            }
+            ScenarioModifier::RespondToTolls { value_of_time } => format!(
+                "drivers avoid tolls by biking or walking, valuing time at ${}/hour",
+                value_of_time
+            ),
+            ScenarioModifier::CalibrateToCounts(counts) => {
+                format!(
+                    "calibrate driving demand to {} traffic counts",
+                    counts.len()
+                )
+            }
+            ScenarioModifier::Weather(weather) => format!(
+                "{}: everybody slows down, and some people bike or walk less",
+                weather.describe()
+            ),
+            ScenarioModifier::DriverMix {
+                pct_aggressive,
+                pct_cautious,
+            } => format!(
+                "{}% of drivers are aggressive and {}% are cautious",
+                pct_aggressive, pct_cautious
+            ),
         }
     }
 }
 
-// Utter hack. Blindly repeats all trips taken by each person every day.
+// Repeats all trips taken by each person every day. Nothing is reset overnight, so cars stay
+// parked wherever they wound up, and buses keep running on the same schedule.
 //
-// What happens if the last place a person winds up in a day isn't the same as where their
-// first trip the next starts? Will crash as soon as the scenario is instantiated, through
-// check_schedule().
+// If somebody doesn't end the day where they started, they head back there an hour after their
+// last trip, using the same mode, so the next day starts from the right place. If there's no time
+// for that before the next day begins, check_schedule() may complain.
 //
 // The bigger problem is that any people that seem to require multiple cars... will wind up
 // needing LOTS of cars.
 fn repeat_days(mut s: Scenario, days: usize) -> Scenario {
     s.scenario_name = format!("{} (repeated {} days)", s.scenario_name, days);
+    s.extra_days = days.max(1) - 1;
     for person in &mut s.people {
+        let return_home = match (person.trips.first(), person.trips.last()) {
+            (Some(first), Some(last)) if last.destination != person.origin => {
+                let depart = last.depart + Duration::hours(1);
+                if depart < first.depart + Duration::hours(24) {
+                    Some(IndividTrip::new(
+                        depart,
+                        TripPurpose::Home,
+                        person.origin,
+                        last.mode,
+                    ))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
         let mut trips = Vec::new();
         let mut offset = Duration::ZERO;
-        for _ in 0..days {
+        for day in 0..days {
             for trip in &person.trips {
                 let mut new = trip.clone();
                 new.depart += offset;
                 new.modified = true;
                 trips.push(new);
             }
+            if day != days - 1 {
+                if let Some(ref trip) = return_home {
+                    let mut new = trip.clone();
+                    new.depart += offset;
+                    new.modified = true;
+                    trips.push(new);
+                }
+            }
             offset += Duration::hours(24);
         }
         person.trips = trips;
     }
     s
 }
+
+fn is_weekend(t: Time) -> bool {
+    let day = ((t - Time::START_OF_DAY) / Duration::hours(24)) as usize;
+    day % 7 >= 5
+}
+
+// Somebody who leaves their car at home in the morning doesn't have it in the evening, so decide
+// for the whole day at once: compare all of the person's driving, tolls included, to biking or
+// walking those trips instead. Times are estimated at free-flow speeds, and drivers are assumed to
+// take the route they'd pick with the tolls in place, which may detour around the zone.
+fn respond_to_tolls(map: &Map, person: &mut PersonSpec, value_of_time: f64) {
+    let mut tolls = 0.0;
+    let mut drive_time = Duration::ZERO;
+    // Either alternative may be impossible for some trip
+    let mut bike_time = Some(Duration::ZERO);
+    let mut walk_time = Some(Duration::ZERO);
+
+    let mut from = person.origin;
+    for trip in &person.trips {
+        let to = trip.destination;
+        if trip.mode == TripMode::Drive && !trip.cancelled {
+            if let Some(path) = TripEndpoint::path_req(from, to, TripMode::Drive, map)
+                .and_then(|req| map.pathfind_with_tolls(req, trip.depart, value_of_time))
+            {
+                tolls += map.tolls_along_path(&path, trip.depart);
+            }
+            drive_time += TripEndpoint::free_flow_time(from, to, TripMode::Drive, map)
+                .unwrap_or(Duration::ZERO);
+            bike_time = bike_time.and_then(|t| {
+                Some(t + TripEndpoint::free_flow_time(from, to, TripMode::Bike, map)?)
+            });
+            walk_time = walk_time.and_then(|t| {
+                Some(t + TripEndpoint::free_flow_time(from, to, TripMode::Walk, map)?)
+            });
+        }
+        from = to;
+    }
+    if tolls == 0.0 {
+        return;
+    }
+
+    let toll_time = Duration::seconds(3600.0 * tolls / value_of_time);
+    let best = vec![(TripMode::Bike, bike_time), (TripMode::Walk, walk_time)]
+        .into_iter()
+        .filter_map(|(mode, time)| time.map(|t| (mode, t)))
+        .min_by_key(|(_, t)| *t);
+    if let Some((mode, time)) = best {
+        if time < drive_time + toll_time {
+            for trip in &mut person.trips {
+                if trip.mode == TripMode::Drive && !trip.cancelled {
+                    trip.mode = mode;
+                    trip.modified = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,527 @@
+//! Generates a synthetic population from what OSM says about buildings, without any city-specific
+//! data. People live in residential buildings, picked in proportion to how many residents each
+//! one has. Some of them have a workplace or school they always commute to; workers sometimes walk
+//! out for lunch near work, making a home→work→lunch→work→home tour in one mode. Everybody might
+//! also run errands to buildings with matching amenities. Departures for each purpose follow a
+//! daily curve. Each person keeps the same home, workplace, school, and commute mode every day, so
+//! multiple days can be compared. The whole population is reproducible from one RNG seed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+
+use abstutil::{prettyprint_usize, Counter, Parallelism, Timer};
+use geom::{Distance, Duration, Time};
+use map_model::{BuildingID, BuildingType, Map, PathConstraints, PathRequest};
+
+use crate::make::activity_model::select_trip_mode;
+use crate::make::fork_rng;
+use crate::make::generator::{is_shop, rand_time, DepartureCurve};
+use crate::{
+    IndividTrip, PersonSpec, Scenario, ScenarioGenerator, TripEndpoint, TripMode, TripPurpose,
+};
+
+const PERCENT_WORKERS: f64 = 0.55;
+const PERCENT_STUDENTS: f64 = 0.15;
+/// On any day, this many workers go out for lunch
+const PERCENT_LUNCH_OUT: f64 = 0.3;
+/// Workers only walk to restaurants this close to work for lunch
+const LUNCH_RADIUS: Distance = Distance::const_meters(800.0);
+/// Leave at least this much time at home between two outings
+const MIN_TIME_AT_HOME: Duration = Duration::const_seconds(15.0 * 60.0);
+
+impl ScenarioGenerator {
+    /// Generates `days` consecutive days of trips. Everybody keeps the same home, workplace or
+    /// school, and commute mode every day, but the rest of each day varies.
+    pub fn synthetic_population(
+        map: &Map,
+        days: usize,
+        rng: &mut XorShiftRng,
+        timer: &mut Timer,
+    ) -> Scenario {
+        let mut s = Scenario::empty(map, "synthetic population");
+        // Include all buses/trains
+        s.only_seed_buses = None;
+
+        let mut homes = Vec::new();
+        let mut workplaces = Vec::new();
+        for b in map.all_buildings() {
+            match b.bldg_type {
+                BuildingType::Residential { num_residents, .. } => {
+                    homes.push((b.id, num_residents));
+                }
+                BuildingType::ResidentialCommercial(resident_cap, worker_cap) => {
+                    homes.push((b.id, resident_cap));
+                    workplaces.push((b.id, worker_cap));
+                }
+                BuildingType::Commercial(worker_cap) => {
+                    workplaces.push((b.id, worker_cap));
+                }
+                BuildingType::Empty => {}
+            }
+        }
+        let homes = Destinations::new(homes);
+        let workplaces = Destinations::new(workplaces);
+        let errands = errands();
+        let destinations: Vec<Option<Destinations>> = errands
+            .iter()
+            .map(|errand| Destinations::for_purpose(map, errand.purpose))
+            .collect();
+        let schools = Destinations::for_purpose(map, TripPurpose::School);
+
+        let homes = match homes {
+            Some(homes) => homes,
+            None => {
+                timer
+                    .warn("No residential buildings on this map, so nobody lives here".to_string());
+                return s;
+            }
+        };
+
+        // Everybody's home, workplace, and school is decided up-front, then the rest of each
+        // person's days come from their own RNG, so the result doesn't depend on how the work is
+        // parallelized.
+        let num_people = homes.total;
+        let person_params: Vec<(BuildingID, Role, XorShiftRng)> = (0..num_people)
+            .map(|_| {
+                let home = homes.choose(rng);
+                let role = match (workplaces.as_ref(), schools.as_ref()) {
+                    (Some(workplaces), _) if rng.gen_bool(PERCENT_WORKERS) => {
+                        Role::Worker(workplaces.choose(rng))
+                    }
+                    (_, Some(schools)) if rng.gen_bool(PERCENT_STUDENTS) => {
+                        Role::Student(schools.choose(rng))
+                    }
+                    _ => Role::Other,
+                };
+                (home, role, fork_rng(rng))
+            })
+            .collect();
+
+        // Many people share a workplace, so find the restaurants near each one just once
+        let mut lunch_spots: BTreeMap<BuildingID, Destinations> = BTreeMap::new();
+        let meals = errands
+            .iter()
+            .zip(destinations.iter())
+            .find(|(errand, _)| errand.purpose == TripPurpose::Meal)
+            .and_then(|(_, candidates)| candidates.as_ref());
+        if let Some(meals) = meals {
+            let used_workplaces: BTreeSet<BuildingID> = person_params
+                .iter()
+                .filter_map(|(_, role, _)| match role {
+                    Role::Worker(b) => Some(*b),
+                    _ => None,
+                })
+                .collect();
+            for b in used_workplaces {
+                if let Some(nearby) = meals.near(map, b, LUNCH_RADIUS) {
+                    lunch_spots.insert(b, nearby);
+                }
+            }
+        }
+
+        let results = timer.parallelize(
+            "create synthetic people",
+            Parallelism::Fastest,
+            person_params,
+            |(home, mut role, mut rng)| {
+                // People commute the same way every day. If the commute isn't possible, probably
+                // a bug in importing; they just stay home instead.
+                let mut commute_mode = None;
+                let commute_to = match role {
+                    Role::Worker(b) | Role::Student(b) => Some(b),
+                    Role::Other => None,
+                };
+                if let Some(b) = commute_to {
+                    match walking_distance(map, home, b) {
+                        Some(dist) if b != home => {
+                            commute_mode = Some(select_trip_mode(dist, &mut rng));
+                        }
+                        _ => {
+                            role = Role::Other;
+                        }
+                    }
+                }
+                let person = Person {
+                    home,
+                    role,
+                    commute_mode,
+                    lunch_spots: match role {
+                        Role::Worker(b) => lunch_spots.get(&b),
+                        _ => None,
+                    },
+                };
+
+                let mut trips = Vec::new();
+                for day in 0..days {
+                    trips.extend(plan_day(
+                        map,
+                        &person,
+                        &errands,
+                        &destinations,
+                        day,
+                        &mut rng,
+                    ));
+                }
+                if trips.is_empty() {
+                    return None;
+                }
+                Some(PersonSpec {
+                    orig_id: None,
+                    origin: TripEndpoint::Bldg(home),
+                    trips,
+                })
+            },
+        );
+        let mut purposes = Counter::new();
+        for person in results.into_iter().flatten() {
+            for trip in &person.trips {
+                purposes.inc(format!("{:?}", trip.purpose));
+            }
+            s.people.push(person);
+        }
+
+        info!(
+            "Synthetic population: {} people living here, {} of them leave home over {} days. \
+             Trips by purpose: {:?}",
+            prettyprint_usize(num_people),
+            prettyprint_usize(s.people.len()),
+            days,
+            purposes.borrow()
+        );
+        s
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    /// Commutes to this building
+    Worker(BuildingID),
+    /// Goes to school at this building
+    Student(BuildingID),
+    Other,
+}
+
+/// Everything about somebody that stays the same from one day to the next
+struct Person<'a> {
+    home: BuildingID,
+    role: Role,
+    /// Only set for workers and students
+    commute_mode: Option<TripMode>,
+    /// Restaurants within walking distance of a worker's workplace
+    lunch_spots: Option<&'a Destinations>,
+}
+
+/// Buildings that people might go to, each weighted by how many people it has room for
+struct Destinations {
+    candidates: Vec<(BuildingID, usize)>,
+    weights: WeightedIndex<usize>,
+    total: usize,
+}
+
+impl Destinations {
+    fn new(candidates: Vec<(BuildingID, usize)>) -> Option<Destinations> {
+        let candidates: Vec<(BuildingID, usize)> =
+            candidates.into_iter().filter(|(_, n)| *n > 0).collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        let total = candidates.iter().map(|(_, n)| *n).sum();
+        Some(Destinations {
+            weights: WeightedIndex::new(candidates.iter().map(|(_, n)| *n)).unwrap(),
+            candidates,
+            total,
+        })
+    }
+
+    /// Buildings with amenities serving some purpose, weighted by how many matching amenities they
+    /// have. A mall with many shops draws more people than a corner store.
+    fn for_purpose(map: &Map, purpose: TripPurpose) -> Option<Destinations> {
+        let mut candidates = Vec::new();
+        for b in map.all_buildings() {
+            let count = b
+                .amenities
+                .iter()
+                .filter(|a| amenity_purpose(&a.amenity_type) == Some(purpose))
+                .count();
+            candidates.push((b.id, count));
+        }
+        Destinations::new(candidates)
+    }
+
+    /// The subset of these buildings within some straight-line distance of another building
+    fn near(&self, map: &Map, center: BuildingID, radius: Distance) -> Option<Destinations> {
+        let pt = map.get_b(center).polygon.center();
+        Destinations::new(
+            self.candidates
+                .iter()
+                .filter(|(b, _)| map.get_b(*b).polygon.center().dist_to(pt) <= radius)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    fn choose(&self, rng: &mut XorShiftRng) -> BuildingID {
+        self.candidates[self.weights.sample(rng)].0
+    }
+}
+
+/// What kind of errand can be run at some OSM amenity?
+fn amenity_purpose(amenity: &str) -> Option<TripPurpose> {
+    match amenity {
+        "restaurant" | "cafe" | "fast_food" | "food_court" | "ice_cream" | "pub" | "bar"
+        | "biergarten" => Some(TripPurpose::Meal),
+        "doctors" | "dentist" | "clinic" | "hospital" | "pharmacy" | "optician" => {
+            Some(TripPurpose::Medical)
+        }
+        "bank" | "post_office" | "townhall" | "library" => Some(TripPurpose::PersonalBusiness),
+        "school" | "kindergarten" | "college" | "university" => Some(TripPurpose::School),
+        "cinema" | "theatre" | "arts_centre" | "museum" | "nightclub" | "fitness_centre" => {
+            Some(TripPurpose::Recreation)
+        }
+        "community_centre" | "place_of_worship" | "social_centre" => Some(TripPurpose::Social),
+        x if is_shop(x) => Some(TripPurpose::Shopping),
+        _ => None,
+    }
+}
+
+/// How often people run some kind of errand, when they leave home for it, and how long they stay
+struct Errand {
+    purpose: TripPurpose,
+    daily_probability: f64,
+    /// People leave around one of these peaks, given as (hour, standard deviation in hours)
+    peaks: Vec<(f64, f64)>,
+    /// Nobody leaves outside of these hours
+    window: (f64, f64),
+    /// How long people stay, in minutes
+    stay: (f64, f64),
+}
+
+fn errands() -> Vec<Errand> {
+    vec![
+        Errand {
+            purpose: TripPurpose::Shopping,
+            daily_probability: 0.3,
+            peaks: vec![(14.0, 3.0)],
+            window: (8.0, 21.0),
+            stay: (20.0, 60.0),
+        },
+        Errand {
+            purpose: TripPurpose::Meal,
+            daily_probability: 0.25,
+            peaks: vec![(12.25, 0.75), (19.0, 1.0)],
+            window: (11.0, 22.0),
+            stay: (45.0, 90.0),
+        },
+        Errand {
+            purpose: TripPurpose::Recreation,
+            daily_probability: 0.15,
+            peaks: vec![(18.5, 2.0)],
+            window: (9.0, 23.0),
+            stay: (60.0, 180.0),
+        },
+        Errand {
+            purpose: TripPurpose::Social,
+            daily_probability: 0.1,
+            peaks: vec![(17.0, 3.0)],
+            window: (9.0, 22.0),
+            stay: (60.0, 180.0),
+        },
+        Errand {
+            purpose: TripPurpose::PersonalBusiness,
+            daily_probability: 0.1,
+            peaks: vec![(11.0, 2.0)],
+            window: (9.0, 17.0),
+            stay: (15.0, 45.0),
+        },
+        Errand {
+            purpose: TripPurpose::Medical,
+            daily_probability: 0.05,
+            peaks: vec![(11.0, 2.0)],
+            window: (8.0, 17.0),
+            stay: (30.0, 90.0),
+        },
+    ]
+}
+
+/// One tour away from home and back
+struct Outing {
+    purpose: TripPurpose,
+    destination: BuildingID,
+    depart: Time,
+    return_home: Time,
+    /// Workers might walk from work to a restaurant and back in the middle of the day. This is
+    /// the restaurant, when they leave work, and when they leave the restaurant.
+    lunch: Option<(BuildingID, Time, Time)>,
+}
+
+impl Outing {
+    fn overlaps(&self, other: &Outing) -> bool {
+        self.depart < other.return_home + MIN_TIME_AT_HOME
+            && other.depart < self.return_home + MIN_TIME_AT_HOME
+    }
+}
+
+/// Plans one day of trips. The first day starts at midnight; later days are shifted by 24 hours.
+fn plan_day(
+    map: &Map,
+    person: &Person,
+    errands: &Vec<Errand>,
+    destinations: &Vec<Option<Destinations>>,
+    day: usize,
+    rng: &mut XorShiftRng,
+) -> Vec<IndividTrip> {
+    let home = person.home;
+    let mut outings: Vec<Outing> = Vec::new();
+
+    // Commuting takes priority over anything else
+    match person.role {
+        Role::Worker(workplace) => {
+            let depart = hour_curve(rng, (8.0, 1.0), (5.0, 11.0));
+            let return_home = rand_time(
+                rng,
+                depart + Duration::hours(4),
+                hours(23.0),
+                &normal(17.5, 1.25),
+            );
+            // Always draw the same numbers, so going out for lunch doesn't affect the rest of the
+            // day
+            let go_out = rng.gen_bool(PERCENT_LUNCH_OUT);
+            let lunch_depart = hour_curve(rng, (12.25, 0.5), (11.0, 14.0));
+            let lunch_stay = Duration::seconds(60.0 * rng.gen_range(30.0, 60.0));
+            let restaurant = person.lunch_spots.map(|spots| spots.choose(rng));
+            let lunch = match (go_out, restaurant) {
+                (true, Some(restaurant))
+                    if restaurant != workplace
+                        && depart + Duration::hours(1) < lunch_depart
+                        && lunch_depart + lunch_stay + Duration::hours(1) < return_home =>
+                {
+                    Some((restaurant, lunch_depart, lunch_depart + lunch_stay))
+                }
+                _ => None,
+            };
+            outings.push(Outing {
+                purpose: TripPurpose::Work,
+                destination: workplace,
+                depart,
+                return_home,
+                lunch,
+            });
+        }
+        Role::Student(school) => {
+            let depart = hour_curve(rng, (7.75, 0.5), (6.0, 9.5));
+            let return_home = rand_time(
+                rng,
+                depart + Duration::hours(3),
+                hours(19.0),
+                &normal(15.0, 0.75),
+            );
+            outings.push(Outing {
+                purpose: TripPurpose::School,
+                destination: school,
+                depart,
+                return_home,
+                lunch: None,
+            });
+        }
+        Role::Other => {}
+    }
+
+    for (errand, candidates) in errands.iter().zip(destinations.iter()) {
+        // Always draw the same numbers, so one errand's candidates don't affect the others
+        let go = rng.gen_bool(errand.daily_probability);
+        let peak = errand.peaks[rng.gen_range(0, errand.peaks.len())];
+        let depart = hour_curve(rng, peak, errand.window);
+        let stay = Duration::seconds(60.0 * rng.gen_range(errand.stay.0, errand.stay.1));
+        let destination = candidates.as_ref().map(|c| c.choose(rng));
+        if let (true, Some(destination)) = (go, destination) {
+            let outing = Outing {
+                purpose: errand.purpose,
+                destination,
+                depart,
+                return_home: depart + stay,
+                lunch: None,
+            };
+            if outing.return_home < hours(24.0) && !outings.iter().any(|o| o.overlaps(&outing)) {
+                outings.push(outing);
+            }
+        }
+    }
+    outings.sort_by_key(|o| o.depart);
+
+    let offset = Duration::hours(24 * day);
+    let mut trips = Vec::new();
+    for outing in outings {
+        if outing.destination == home {
+            continue;
+        }
+        // Use the same mode for the whole tour, so cars and bikes get home
+        let mode = match outing.purpose {
+            TripPurpose::Work | TripPurpose::School => person.commute_mode.unwrap(),
+            // If the buildings aren't connected, probably a bug in importing; skip this outing.
+            _ => match walking_distance(map, home, outing.destination) {
+                Some(dist) => select_trip_mode(dist, rng),
+                None => {
+                    continue;
+                }
+            },
+        };
+        trips.push(IndividTrip::new(
+            outing.depart + offset,
+            outing.purpose,
+            TripEndpoint::Bldg(outing.destination),
+            mode,
+        ));
+        // Any vehicle stays parked at work during lunch
+        if let Some((restaurant, leave_work, leave_restaurant)) = outing.lunch {
+            trips.push(IndividTrip::new(
+                leave_work + offset,
+                TripPurpose::Meal,
+                TripEndpoint::Bldg(restaurant),
+                TripMode::Walk,
+            ));
+            trips.push(IndividTrip::new(
+                leave_restaurant + offset,
+                outing.purpose,
+                TripEndpoint::Bldg(outing.destination),
+                TripMode::Walk,
+            ));
+        }
+        trips.push(IndividTrip::new(
+            outing.return_home + offset,
+            TripPurpose::Home,
+            TripEndpoint::Bldg(home),
+            mode,
+        ));
+    }
+    trips
+}
+
+fn walking_distance(map: &Map, from: BuildingID, to: BuildingID) -> Option<Distance> {
+    PathRequest::between_buildings(map, from, to, PathConstraints::Pedestrian)
+        .and_then(|req| map.pathfind(req))
+        .map(|path| path.total_length())
+}
+
+fn hours(h: f64) -> Time {
+    Time::START_OF_DAY + Duration::seconds(h * 3600.0)
+}
+
+fn normal(peak: f64, std_dev: f64) -> DepartureCurve {
+    DepartureCurve::Normal {
+        peak: hours(peak),
+        std_dev: Duration::seconds(std_dev * 3600.0),
+    }
+}
+
+/// Samples a time from a normal curve, clamped to a window. Everything is in hours.
+fn hour_curve(rng: &mut XorShiftRng, (peak, std_dev): (f64, f64), window: (f64, f64)) -> Time {
+    rand_time(
+        rng,
+        hours(window.0),
+        hours(window.1),
+        &normal(peak, std_dev),
+    )
+}
@@ -6,17 +6,19 @@ use rand::{Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
-use abstutil::{prettyprint_usize, Counter, MapName, Parallelism, Timer};
+use abstutil::{prettyprint_usize, Counter, MapName, Migration, Parallelism, Timer, Versioned};
 use geom::{Distance, Speed, Time};
 use map_model::{BuildingID, Map, OffstreetParking, RoadID};
 
 use crate::make::fork_rng;
 use crate::{
-    OrigPersonID, ParkingSpot, Sim, TripEndpoint, TripInfo, TripMode, TripSpec, Vehicle,
-    VehicleSpec, VehicleType, BIKE_LENGTH, MAX_CAR_LENGTH, MIN_CAR_LENGTH,
+    DriverType, LaneClosure, OrigPersonID, ParkingSearch, ParkingSpot, PersonID, Sim, TripEndpoint,
+    TripInfo, TripMode, TripSpec, Vehicle, VehicleSpec, VehicleType, Weather, BIKE_LENGTH,
+    EMERGENCY_VEHICLE_LENGTH, MAX_CAR_LENGTH, MIN_CAR_LENGTH, TRUCK_LENGTH,
 };
 
-/// A Scenario describes all the input to a simulation. Usually a scenario covers one day.
+/// A Scenario describes all the input to a simulation. Usually a scenario covers one day, but
+/// ScenarioModifier::RepeatDays can stretch it over several.
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Scenario {
     pub scenario_name: String,
@@ -25,6 +27,25 @@ pub struct Scenario {
     pub people: Vec<PersonSpec>,
     /// None means seed all buses. Otherwise the route name must be present here.
     pub only_seed_buses: Option<BTreeSet<String>>,
+    /// People who share a limited number of cars. Anybody not in a household has as many cars as
+    /// their trips need.
+    #[serde(default)]
+    pub households: Vec<HouseholdSpec>,
+    /// Slows everybody down for the whole day. Set by ScenarioModifier::Weather, which also
+    /// shifts some people's trips to other modes.
+    #[serde(default)]
+    pub weather: Weather,
+    /// The kinds of people driving cars and trucks. If empty, everybody drives the same way.
+    #[serde(default)]
+    pub driver_mix: Vec<DriverType>,
+    /// Lanes closed for part of the day, like for construction. The simulation doesn't apply these
+    /// by itself; see `Sim::apply_lane_closures`.
+    #[serde(default)]
+    pub lane_closures: Vec<LaneClosure>,
+    /// Bus and train schedules repeat for this many days after the first. Set by
+    /// ScenarioModifier::RepeatDays.
+    #[serde(default)]
+    pub extra_days: usize,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -37,6 +58,16 @@ pub struct PersonSpec {
     pub trips: Vec<IndividTrip>,
 }
 
+/// A group of people sharing some cars. When a member wants to drive and every car is already
+/// taken, they ride transit or walk instead.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct HouseholdSpec {
+    /// Indices into the scenario's people. Each person belongs to at most one household.
+    pub members: Vec<usize>,
+    /// How many cars the household owns
+    pub cars: usize,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct IndividTrip {
     pub depart: Time,
@@ -67,7 +98,7 @@ impl IndividTrip {
 }
 
 /// Lifted from Seattle's Soundcast model, but seems general enough to use anyhere.
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum TripPurpose {
     Home,
     Work,
@@ -80,6 +111,30 @@ pub enum TripPurpose {
     Recreation,
     Medical,
     ParkAndRideTransfer,
+    /// A commercial vehicle delivering goods. Drivers on these trips use a truck instead of a car.
+    Delivery,
+    /// Responding to an incident. Drivers on these trips use an emergency vehicle.
+    Emergency,
+}
+
+impl TripPurpose {
+    pub fn all() -> Vec<TripPurpose> {
+        vec![
+            TripPurpose::Home,
+            TripPurpose::Work,
+            TripPurpose::School,
+            TripPurpose::Escort,
+            TripPurpose::PersonalBusiness,
+            TripPurpose::Shopping,
+            TripPurpose::Meal,
+            TripPurpose::Social,
+            TripPurpose::Recreation,
+            TripPurpose::Medical,
+            TripPurpose::ParkAndRideTransfer,
+            TripPurpose::Delivery,
+            TripPurpose::Emergency,
+        ]
+    }
 }
 
 impl fmt::Display for TripPurpose {
@@ -100,18 +155,28 @@ impl fmt::Display for TripPurpose {
                 TripPurpose::Recreation => "recreation",
                 TripPurpose::Medical => "medical",
                 TripPurpose::ParkAndRideTransfer => "park-and-ride transfer",
+                TripPurpose::Delivery => "delivery",
+                TripPurpose::Emergency => "emergency response",
             }
         )
     }
 }
 
 impl Scenario {
-    pub fn instantiate(&self, sim: &mut Sim, map: &Map, rng: &mut XorShiftRng, timer: &mut Timer) {
-        self.instantiate_without_retries(sim, map, rng, true, timer);
+    /// Returns the new people, in the same order as `self.people`.
+    pub fn instantiate(
+        &self,
+        sim: &mut Sim,
+        map: &Map,
+        rng: &mut XorShiftRng,
+        timer: &mut Timer,
+    ) -> Vec<PersonID> {
+        self.instantiate_without_retries(sim, map, rng, true, timer)
     }
 
     /// If retry_if_no_room is false, any vehicles that fail to spawn because of something else in
-    /// the way will just wind up as cancelled trips.
+    /// the way will just wind up as cancelled trips. Returns the new people, in the same order as
+    /// `self.people`.
     pub fn instantiate_without_retries(
         &self,
         sim: &mut Sim,
@@ -119,27 +184,31 @@ impl Scenario {
         rng: &mut XorShiftRng,
         retry_if_no_room: bool,
         timer: &mut Timer,
-    ) {
+    ) -> Vec<PersonID> {
         // Any case where map edits could change the calls to the RNG, we have to fork.
         sim.set_name(self.scenario_name.clone());
+        sim.set_weather(self.weather);
+        sim.set_driver_mix(self.driver_mix.clone());
+        sim.set_lane_closures(self.lane_closures.clone());
 
         timer.start(format!("Instantiating {}", self.scenario_name));
 
         if let Some(ref routes) = self.only_seed_buses {
             for route in map.all_bus_routes() {
                 if routes.contains(&route.full_name) {
-                    sim.seed_bus_route(route);
+                    sim.seed_bus_route(route, self.extra_days);
                 }
             }
         } else {
             // All of them
             for route in map.all_bus_routes() {
-                sim.seed_bus_route(route);
+                sim.seed_bus_route(route, self.extra_days);
             }
         }
 
         timer.start_iter("trips for People", self.people.len());
-        let mut parked_cars: Vec<(Vehicle, BuildingID)> = Vec::new();
+        let mut parked_cars_per_person: Vec<Vec<(Vehicle, BuildingID)>> = Vec::new();
+        let mut person_ids = Vec::new();
         let mut schedule_trips = Vec::new();
         for p in &self.people {
             timer.next();
@@ -151,9 +220,13 @@ impl Scenario {
             let (vehicle_specs, cars_initially_parked_at, vehicle_foreach_trip) =
                 p.get_vehicles(rng);
             let person = sim.new_person(p.orig_id, Scenario::rand_ped_speed(rng), vehicle_specs);
-            for (idx, b) in cars_initially_parked_at {
-                parked_cars.push((person.vehicles[idx].clone(), b));
-            }
+            person_ids.push(person.id);
+            parked_cars_per_person.push(
+                cars_initially_parked_at
+                    .into_iter()
+                    .map(|(idx, b)| (person.vehicles[idx].clone(), b))
+                    .collect(),
+            );
             let mut from = p.origin.clone();
             for (t, maybe_idx) in p.trips.iter().zip(vehicle_foreach_trip) {
                 // The RNG call might change over edits for picking the spawning lane from a border
@@ -196,6 +269,31 @@ impl Scenario {
             }
         }
 
+        // Every member plans their day as if they had their own car, but a household only starts
+        // the day with as many cars as it owns. Members borrow each other's cars during the
+        // simulation.
+        for household in &self.households {
+            sim.new_household(
+                household
+                    .members
+                    .iter()
+                    .map(|idx| person_ids[*idx])
+                    .collect(),
+            );
+            let mut cars = 0;
+            for idx in &household.members {
+                parked_cars_per_person[*idx].retain(|(vehicle, _)| {
+                    if vehicle.vehicle_type != VehicleType::Car {
+                        return true;
+                    }
+                    cars += 1;
+                    cars <= household.cars
+                });
+            }
+        }
+        let mut parked_cars: Vec<(Vehicle, BuildingID)> =
+            parked_cars_per_person.into_iter().flatten().collect();
+
         let results = timer.parallelize(
             "schedule trips",
             Parallelism::Fastest,
@@ -209,10 +307,11 @@ impl Scenario {
 
         sim.spawn_trips(results, map, timer);
         timer.stop(format!("Instantiating {}", self.scenario_name));
+        person_ids
     }
 
     pub fn save(&self) {
-        abstutil::write_binary(
+        abstutil::write_versioned_binary(
             abstutil::path_scenario(&self.map_name, &self.scenario_name),
             self,
         );
@@ -224,6 +323,11 @@ impl Scenario {
             map_name: map.get_name().clone(),
             people: Vec::new(),
             only_seed_buses: Some(BTreeSet::new()),
+            extra_days: 0,
+            households: Vec::new(),
+            weather: Weather::Clear,
+            driver_mix: Vec::new(),
+            lane_closures: Vec::new(),
         }
     }
 
@@ -236,6 +340,24 @@ impl Scenario {
         }
     }
 
+    fn truck() -> VehicleSpec {
+        // The simulation doesn't model acceleration, so express sluggishness with a lower top
+        // speed.
+        VehicleSpec {
+            vehicle_type: VehicleType::Truck,
+            length: TRUCK_LENGTH,
+            max_speed: Some(Speed::miles_per_hour(50.0)),
+        }
+    }
+
+    fn emergency_vehicle() -> VehicleSpec {
+        VehicleSpec {
+            vehicle_type: VehicleType::Emergency,
+            length: EMERGENCY_VEHICLE_LENGTH,
+            max_speed: None,
+        }
+    }
+
     fn rand_bike(rng: &mut XorShiftRng) -> VehicleSpec {
         let max_speed = Some(Scenario::rand_speed(
             rng,
@@ -287,13 +409,22 @@ impl Scenario {
 
     pub fn remove_weird_schedules(mut self) -> Scenario {
         let orig = self.people.len();
-        self.people.retain(|person| match person.check_schedule() {
-            Ok(()) => true,
-            Err(err) => {
-                println!("{}", err);
-                false
+        let mut new_indices = Vec::new();
+        let mut people = Vec::new();
+        for person in self.people.drain(..) {
+            match person.check_schedule() {
+                Ok(()) => {
+                    new_indices.push(Some(people.len()));
+                    people.push(person);
+                }
+                Err(err) => {
+                    println!("{}", err);
+                    new_indices.push(None);
+                }
             }
-        });
+        }
+        self.people = people;
+        self.renumber_households(&new_indices);
         warn!(
             "{} of {} people have nonsense schedules",
             prettyprint_usize(orig - self.people.len()),
@@ -301,6 +432,72 @@ impl Scenario {
         );
         self
     }
+
+    /// After removing or reordering people, fix up the households. `new_indices` maps each
+    /// original person to their new index, if they're still around. Households without anybody
+    /// left are removed.
+    pub fn renumber_households(&mut self, new_indices: &[Option<usize>]) {
+        for household in &mut self.households {
+            household.members = household
+                .members
+                .iter()
+                .filter_map(|idx| new_indices[*idx])
+                .collect();
+        }
+        self.households.retain(|h| !h.members.is_empty());
+    }
+}
+
+impl Versioned for Scenario {
+    const FORMAT: &'static str = "scenario";
+    // Bump this after changing anything serialized in a scenario, and add a migration below.
+    const VERSION: u32 = 5;
+
+    fn migrations() -> Vec<Migration> {
+        // Every field so far was added at the end, so upgrading just appends the default.
+        vec![
+            Migration {
+                from: 0,
+                description: "add no households",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&Vec::<HouseholdSpec>::new()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 1,
+                description: "add clear weather",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&Weather::Clear));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 2,
+                description: "add no driver mix",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&Vec::<DriverType>::new()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 3,
+                description: "add no lane closures",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&Vec::<LaneClosure>::new()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 4,
+                description: "cover a single day",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&0_usize));
+                    Ok(bytes)
+                },
+            },
+        ]
+    }
 }
 
 fn seed_parked_cars(
@@ -405,9 +602,15 @@ fn find_spot_near_building(
             {
                 return Some(spots.remove(idx).0);
             }
+            // Cars seeded overnight have to respect parking zones too
+            let search = ParkingSearch {
+                target: b,
+                now: Time::START_OF_DAY,
+                leave_at: None,
+            };
             if let Some(idx) = spots
                 .iter()
-                .position(|(_, restriction)| restriction.is_none())
+                .position(|(spot, restriction)| restriction.is_none() && search.allows(*spot, map))
             {
                 return Some(spots.remove(idx).0);
             }
@@ -465,12 +668,16 @@ impl PersonSpec {
         let mut bike_idx = None;
         // For each indexed car, is it parked somewhere, or off-map?
         let mut car_locations: Vec<(usize, Option<BuildingID>)> = Vec::new();
+        // Same for trucks, which are only used for deliveries
+        let mut truck_locations: Vec<(usize, Option<BuildingID>)> = Vec::new();
+        // And emergency vehicles, only used to respond to incidents
+        let mut emergency_locations: Vec<(usize, Option<BuildingID>)> = Vec::new();
 
         // TODO If the trip is cancelled, this should be affected...
         let mut from = self.origin.clone();
         for trip in &self.trips {
             let use_for_trip = match trip.mode {
-                TripMode::Walk | TripMode::Transit => None,
+                TripMode::Walk | TripMode::Transit | TripMode::RideHail => None,
                 TripMode::Bike => {
                     if bike_idx.is_none() {
                         bike_idx = Some(vehicle_specs.len());
@@ -479,13 +686,18 @@ impl PersonSpec {
                     bike_idx
                 }
                 TripMode::Drive => {
+                    let locations = match trip.purpose {
+                        TripPurpose::Delivery => &mut truck_locations,
+                        TripPurpose::Emergency => &mut emergency_locations,
+                        _ => &mut car_locations,
+                    };
                     let need_parked_at = match from {
                         TripEndpoint::Bldg(b) => Some(b),
                         _ => None,
                     };
 
                     // Any available cars in the right spot?
-                    let idx = if let Some(idx) = car_locations
+                    let idx = if let Some(idx) = locations
                         .iter()
                         .find(|(_, parked_at)| *parked_at == need_parked_at)
                         .map(|(idx, _)| *idx)
@@ -494,7 +706,11 @@ impl PersonSpec {
                     } else {
                         // Need a new car, starting in the right spot
                         let idx = vehicle_specs.len();
-                        vehicle_specs.push(Scenario::rand_car(rng));
+                        vehicle_specs.push(match trip.purpose {
+                            TripPurpose::Delivery => Scenario::truck(),
+                            TripPurpose::Emergency => Scenario::emergency_vehicle(),
+                            _ => Scenario::rand_car(rng),
+                        });
                         if let Some(b) = need_parked_at {
                             cars_initially_parked_at.push((idx, b));
                         }
@@ -502,13 +718,13 @@ impl PersonSpec {
                     };
 
                     // Where does this car wind up?
-                    car_locations.retain(|(i, _)| idx != *i);
+                    locations.retain(|(i, _)| idx != *i);
                     match trip.destination {
                         TripEndpoint::Bldg(b) => {
-                            car_locations.push((idx, Some(b)));
+                            locations.push((idx, Some(b)));
                         }
                         TripEndpoint::Border(_) | TripEndpoint::SuddenlyAppear(_) => {
-                            car_locations.push((idx, None));
+                            locations.push((idx, None));
                         }
                     }
 
@@ -5,13 +5,13 @@ use rand::seq::SliceRandom;
 use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
+use geom::{Distance, Duration};
 use map_model::{
     BuildingID, BusRouteID, BusStopID, IntersectionID, Map, PathConstraints, PathRequest, Position,
 };
 
 use crate::{
-    CarID, DrivingGoal, PersonID, SidewalkSpot, TripInfo, TripLeg, TripMode, VehicleType,
-    SPAWN_DIST,
+    CarID, DrivingGoal, PersonID, Scenario, SidewalkSpot, TripInfo, TripLeg, TripMode, SPAWN_DIST,
 };
 
 // TODO Some of these fields are unused now that we separately pass TripEndpoint
@@ -52,6 +52,16 @@ pub(crate) enum TripSpec {
         stop1: BusStopID,
         maybe_stop2: Option<BusStopID>,
     },
+    /// Walk to one station of a guideway, ride to another, then walk the rest of the way
+    UsingGuideway {
+        start: SidewalkSpot,
+        goal: SidewalkSpot,
+        guideway: usize,
+        board: usize,
+        alight: usize,
+    },
+    /// Wait inside the building for a ride-hail vehicle
+    UsingRideHail { start: BuildingID, goal: BuildingID },
 }
 
 impl TripSpec {
@@ -84,11 +94,7 @@ impl TripSpec {
                     }
                 }
 
-                let constraints = if use_vehicle.1 == VehicleType::Bike {
-                    PathConstraints::Bike
-                } else {
-                    PathConstraints::Car
-                };
+                let constraints = use_vehicle.1.to_constraints();
 
                 legs.push(TripLeg::Drive(*use_vehicle, goal.clone()));
                 if let DrivingGoal::ParkNear(b) = goal {
@@ -206,6 +212,31 @@ impl TripSpec {
                     ];
                 }
             }
+            TripSpec::UsingGuideway {
+                goal,
+                guideway,
+                board,
+                alight,
+                ..
+            } => match SidewalkSpot::guideway_station(*guideway, *board, map) {
+                Some(walk_to) => {
+                    legs = vec![
+                        TripLeg::Walk(walk_to),
+                        TripLeg::RideGuideway(*guideway, *board, *alight),
+                        TripLeg::Walk(goal.clone()),
+                    ];
+                }
+                None => {
+                    return TripSpec::SpawningFailure {
+                        use_vehicle: None,
+                        error: format!("guideway {} has no station {}", guideway, board),
+                    }
+                    .to_plan(person, info, map);
+                }
+            },
+            TripSpec::UsingRideHail { goal, .. } => {
+                legs = vec![TripLeg::RideHail(*goal)];
+            }
         };
 
         (person, info, self, legs)
@@ -225,7 +256,10 @@ impl TripSpec {
         Ok(match mode {
             TripMode::Drive | TripMode::Bike => {
                 let constraints = if mode == TripMode::Drive {
-                    PathConstraints::Car
+                    // Trucks can't use every road that cars can
+                    use_vehicle
+                        .map(|car| car.1.to_constraints())
+                        .unwrap_or(PathConstraints::Car)
                 } else {
                     PathConstraints::Bike
                 };
@@ -273,27 +307,50 @@ impl TripSpec {
                 start: from.start_sidewalk_spot(map)?,
                 goal: to.end_sidewalk_spot(map)?,
             },
-            TripMode::Transit => {
-                let start = from.start_sidewalk_spot(map)?;
-                let goal = to.end_sidewalk_spot(map)?;
-                if let Some((stop1, maybe_stop2, route)) =
-                    map.should_use_transit(start.sidewalk_pos, goal.sidewalk_pos)
-                {
-                    TripSpec::UsingTransit {
-                        start,
-                        goal,
-                        route,
-                        stop1,
-                        maybe_stop2,
-                    }
-                } else {
-                    //timer.warn(format!("{:?} not actually using transit, because pathfinding
-                    // didn't find any useful route", trip));
-                    TripSpec::JustWalking { start, goal }
+            TripMode::Transit => TripSpec::transit_or_walk(
+                from.start_sidewalk_spot(map)?,
+                to.end_sidewalk_spot(map)?,
+                map,
+            ),
+            TripMode::RideHail => match (from, to) {
+                (TripEndpoint::Bldg(start), TripEndpoint::Bldg(goal)) => {
+                    TripSpec::UsingRideHail { start, goal }
                 }
-            }
+                _ => {
+                    return Err("ride-hail trips must start and end at buildings".to_string());
+                }
+            },
         })
     }
+
+    /// Ride a guideway or transit if there's a useful route, otherwise just walk.
+    pub fn transit_or_walk(start: SidewalkSpot, goal: SidewalkSpot, map: &Map) -> TripSpec {
+        if let Some((guideway, board, alight)) =
+            map.should_use_guideway(start.sidewalk_pos, goal.sidewalk_pos)
+        {
+            TripSpec::UsingGuideway {
+                start,
+                goal,
+                guideway,
+                board,
+                alight,
+            }
+        } else if let Some((stop1, maybe_stop2, route)) =
+            map.should_use_transit(start.sidewalk_pos, goal.sidewalk_pos)
+        {
+            TripSpec::UsingTransit {
+                start,
+                goal,
+                route,
+                stop1,
+                maybe_stop2,
+            }
+        } else {
+            //timer.warn(format!("{:?} not actually using transit, because pathfinding
+            // didn't find any useful route", trip));
+            TripSpec::JustWalking { start, goal }
+        }
+    }
 }
 
 /// Specifies where a trip begins or ends.
@@ -317,12 +374,46 @@ impl TripEndpoint {
             end: to.clone().pos(mode, false, map)?,
             constraints: match mode {
                 TripMode::Walk | TripMode::Transit => PathConstraints::Pedestrian,
-                TripMode::Drive => PathConstraints::Car,
+                TripMode::Drive | TripMode::RideHail => PathConstraints::Car,
                 TripMode::Bike => PathConstraints::Bike,
             },
         })
     }
 
+    /// How long a trip would take along the shortest path, always moving at the speed limit.
+    /// Transit isn't handled; it's treated like walking.
+    pub fn free_flow_time(
+        from: TripEndpoint,
+        to: TripEndpoint,
+        mode: TripMode,
+        map: &Map,
+    ) -> Option<Duration> {
+        let path = map.pathfind(TripEndpoint::path_req(from, to, mode, map)?)?;
+        let mut total_dist = Distance::ZERO;
+        let mut total_time = Duration::ZERO;
+        for step in path.get_steps() {
+            let t = step.as_traversable();
+            let mut speed = t.speed_limit(map);
+            match mode {
+                TripMode::Walk | TripMode::Transit => {
+                    speed = speed.min(Scenario::max_ped_speed());
+                }
+                TripMode::Bike => {
+                    speed = speed.min(Scenario::max_bike_speed());
+                }
+                TripMode::Drive | TripMode::RideHail => {}
+            }
+            let dist = t.length(map);
+            total_dist += dist;
+            total_time += dist / speed;
+        }
+        if total_dist == Distance::ZERO {
+            return Some(Duration::ZERO);
+        }
+        // The path starts and ends partway along its first and last lanes
+        Some(total_time * (path.total_length() / total_dist))
+    }
+
     fn start_sidewalk_spot(&self, map: &Map) -> Result<SidewalkSpot, String> {
         match self {
             TripEndpoint::Bldg(b) => Ok(SidewalkSpot::building(*b, map)),
@@ -370,7 +461,7 @@ impl TripEndpoint {
             })
             .ok()
             .map(|spot| spot.sidewalk_pos),
-            TripMode::Drive | TripMode::Bike => {
+            TripMode::Drive | TripMode::Bike | TripMode::RideHail => {
                 if from {
                     match self {
                         // Fall through and use DrivingGoal also to start.
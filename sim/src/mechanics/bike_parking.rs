@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::Duration;
+use map_model::{BuildingID, Map};
+
+use crate::CarID;
+
+/// Locking a bike to a railing or signpost takes longer than using a rack.
+pub const IMPROVISED_BIKE_PARKING_TIME: Duration = Duration::const_seconds(120.0);
+
+/// Tracks where cyclists lock up their bikes at the end of a trip. Each building has some number
+/// of rack spots; once those fill up, cyclists improvise nearby.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct BikeParkingSimState {
+    /// Where each bike is locked up, and whether it's at a rack
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    parked: BTreeMap<CarID, (BuildingID, bool)>,
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    per_bldg: BTreeMap<BuildingID, BikeRackOccupancy>,
+}
+
+/// How bike parking at one building is used
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct BikeRackOccupancy {
+    /// Bikes currently locked at racks
+    pub at_racks: usize,
+    /// Bikes currently locked up somewhere else nearby, because the racks were full
+    pub improvised: usize,
+    /// The most bikes ever at the racks at once
+    pub peak_at_racks: usize,
+    /// How many cyclists so far couldn't find a free rack
+    pub total_improvised: usize,
+}
+
+impl BikeParkingSimState {
+    /// Locks up a bike by a building. Returns the extra time spent if there wasn't a free rack.
+    pub fn park(&mut self, bike: CarID, b: BuildingID, map: &Map) -> Duration {
+        let occupancy = self.per_bldg.entry(b).or_insert_with(Default::default);
+        let at_rack = occupancy.at_racks < map.get_bike_rack_capacity(b);
+        if at_rack {
+            occupancy.at_racks += 1;
+            occupancy.peak_at_racks = occupancy.peak_at_racks.max(occupancy.at_racks);
+        } else {
+            occupancy.improvised += 1;
+            occupancy.total_improvised += 1;
+        }
+        self.parked.insert(bike, (b, at_rack));
+        if at_rack {
+            Duration::ZERO
+        } else {
+            IMPROVISED_BIKE_PARKING_TIME
+        }
+    }
+
+    /// Bikes that start the day somewhere aren't tracked, so this might do nothing.
+    pub fn unpark(&mut self, bike: CarID) {
+        if let Some((b, at_rack)) = self.parked.remove(&bike) {
+            let occupancy = self.per_bldg.get_mut(&b).unwrap();
+            if at_rack {
+                occupancy.at_racks -= 1;
+            } else {
+                occupancy.improvised -= 1;
+            }
+        }
+    }
+
+    pub fn get_occupancy(&self, b: BuildingID) -> BikeRackOccupancy {
+        self.per_bldg.get(&b).cloned().unwrap_or_default()
+    }
+
+    pub fn all_occupancy(&self) -> &BTreeMap<BuildingID, BikeRackOccupancy> {
+        &self.per_bldg
+    }
+}
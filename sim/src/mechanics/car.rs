@@ -2,12 +2,13 @@ use std::collections::VecDeque;
 
 use serde::{Deserialize, Serialize};
 
-use geom::{Distance, Duration, PolyLine, Time};
+use geom::{Distance, Duration, PolyLine, Speed, Time};
 use map_model::{Direction, Map, Traversable};
 
+use crate::sim::Ctx;
 use crate::{
-    CarStatus, DistanceInterval, DrawCarInput, ParkingSpot, PersonID, Router, TimeInterval,
-    TransitSimState, TripID, Vehicle, VehicleType,
+    CarStatus, DistanceInterval, DrawCarInput, Emissions, Event, ParkingSpot, PersonID, Router,
+    TimeInterval, TransitSimState, TripID, Vehicle, VehicleType,
 };
 
 /// Represents a single vehicle. Note "car" is a misnomer; it could also be a bus or bike.
@@ -25,35 +26,82 @@ pub(crate) struct Car {
     /// In reverse order -- most recently left is first. The sum length of these must be >=
     /// vehicle.length.
     pub last_steps: VecDeque<Traversable>,
+
+    /// When the car started moving at each speed, oldest first. Only for debugging, so it's not
+    /// preserved in savestates.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub speed_history: VecDeque<(Time, Speed)>,
 }
 
+/// How many changes in speed each car remembers
+const SPEED_HISTORY_LENGTH: usize = 20;
+
 impl Car {
+    /// Remembers the speed of the current state, if it hasn't been recorded yet.
+    pub fn record_speed(&mut self) {
+        let (since, speed) = self.state.speed_since();
+        if self.speed_history.back().map(|(t, _)| *t) == Some(since) {
+            return;
+        }
+        self.speed_history.push_back((since, speed));
+        if self.speed_history.len() > SPEED_HISTORY_LENGTH {
+            self.speed_history.pop_front();
+        }
+    }
+
     /// Assumes the current head of the path is the thing to cross.
-    pub fn crossing_state(&self, start_dist: Distance, start_time: Time, map: &Map) -> CarState {
+    pub fn crossing_state(&self, start_dist: Distance, start_time: Time, ctx: &Ctx) -> CarState {
         let dist_int = DistanceInterval::new_driving(
             start_dist,
             if self.router.last_step() {
                 self.router.get_end_dist()
             } else {
-                self.router.head().length(map)
+                self.router.head().length(ctx.map)
             },
         );
-        self.crossing_state_with_end_dist(dist_int, start_time, map)
+        self.crossing_state_with_end_dist(dist_int, start_time, ctx)
     }
 
     pub fn crossing_state_with_end_dist(
         &self,
         dist_int: DistanceInterval,
         start_time: Time,
-        map: &Map,
+        ctx: &Ctx,
     ) -> CarState {
-        let on = self.router.head();
-        let mut speed = on.speed_limit(map);
+        let dt =
+            (dist_int.end - dist_int.start) / self.speed_on(self.router.head(), start_time, ctx);
+        CarState::Crossing(TimeInterval::new(start_time, start_time + dt), dist_int)
+    }
+
+    /// Like crossing_state, but for a driver who was stuck behind somebody. They take a moment to
+    /// react once the way ahead clears, so they finish crossing a little later.
+    pub fn crossing_state_after_stopping(
+        &self,
+        start_dist: Distance,
+        start_time: Time,
+        ctx: &Ctx,
+    ) -> CarState {
+        match self.crossing_state(start_dist, start_time, ctx) {
+            CarState::Crossing(time_int, dist_int) => CarState::Crossing(
+                TimeInterval::new(
+                    time_int.start,
+                    time_int.end + ctx.drivers.profile(self.vehicle.id).reaction_time,
+                ),
+                dist_int,
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    /// How fast this vehicle goes when nothing's in the way. Drivers may go a bit over or under
+    /// the speed limit in effect when they start, but never faster than the vehicle can.
+    pub fn speed_on(&self, on: Traversable, now: Time, ctx: &Ctx) -> Speed {
+        let offset = ctx.drivers.profile(self.vehicle.id).speed_offset;
+        let mut speed = on.speed_limit_at(ctx.map, now) * (1.0 + offset);
         if let Some(s) = self.vehicle.max_speed {
             speed = speed.min(s);
         }
-        let dt = (dist_int.end - dist_int.start) / speed;
-        CarState::Crossing(TimeInterval::new(start_time, start_time + dt), dist_int)
+        speed * ctx.weather.speed_factor()
     }
 
     pub fn get_draw_car(
@@ -241,6 +289,19 @@ impl Car {
         }
     }
 
+    /// Records fuel burned on some lane or turn, unless this vehicle doesn't burn any.
+    pub fn emissions(&self, on: Traversable, emissions: Emissions) -> Option<Event> {
+        if emissions == Emissions::ZERO {
+            return None;
+        }
+        Some(Event::VehicleEmissions(
+            self.vehicle.id,
+            self.trip_and_person.map(|(trip, _)| trip),
+            on,
+            emissions,
+        ))
+    }
+
     pub fn is_parking(&self) -> bool {
         if let CarState::Parking(_, _, _) = self.state {
             return true;
@@ -278,6 +339,27 @@ impl CarState {
         }
     }
 
+    /// When did the car enter this state, and how fast is it moving?
+    pub fn speed_since(&self) -> (Time, Speed) {
+        match self {
+            CarState::Crossing(ref time_int, ref dist_int) => {
+                let dt = time_int.end - time_int.start;
+                let speed = if dt == Duration::ZERO {
+                    Speed::ZERO
+                } else {
+                    Speed::from_dist_time(dist_int.end - dist_int.start, dt)
+                };
+                (time_int.start, speed)
+            }
+            CarState::Queued { blocked_since } | CarState::WaitingToAdvance { blocked_since } => {
+                (*blocked_since, Speed::ZERO)
+            }
+            CarState::Unparking(_, _, ref time_int)
+            | CarState::Parking(_, _, ref time_int)
+            | CarState::IdlingAtStop(_, ref time_int) => (time_int.start, Speed::ZERO),
+        }
+    }
+
     pub fn time_spent_waiting(&self, now: Time) -> Duration {
         match self {
             CarState::Queued { blocked_since } | CarState::WaitingToAdvance { blocked_since } => {
@@ -4,20 +4,19 @@ use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_hashmap, serialize_hashmap, FixedMap, IndexableKey};
 use geom::{Distance, Duration, PolyLine, Speed, Time};
-use map_model::{IntersectionID, LaneID, Map, Path, Traversable};
+use map_model::{IntersectionID, LaneID, Map, Path, PathStep, Position, Traversable};
 
 use crate::mechanics::car::{Car, CarState};
 use crate::mechanics::Queue;
 use crate::sim::Ctx;
 use crate::{
-    ActionAtEnd, AgentID, AgentProperties, CarID, Command, CreateCar, DelayCause, DistanceInterval,
-    DrawCarInput, Event, IntersectionSimState, ParkedCar, ParkingSim, ParkingSpot, PersonID,
-    SimOptions, TimeInterval, TransitSimState, TripID, TripManager, UnzoomedAgent, Vehicle,
-    WalkingSimState, FOLLOWING_DISTANCE,
+    ActionAtEnd, AgentID, AgentProperties, CarConstraint, CarID, CarIntent, Command, CreateCar,
+    DelayCause, DistanceInterval, DrawCarInput, Emissions, Event, IntersectionSimState,
+    MicrotransitSimState, ParkedCar, ParkingSim, ParkingSpot, PersonID, RideHailSimState,
+    SafetyEvent, SimOptions, TimeInterval, TransitSimState, TripID, TripManager, UnzoomedAgent,
+    Vehicle, VehicleType, WalkingSimState, Weather, FOLLOWING_DISTANCE,
 };
 
-const TIME_TO_WAIT_AT_BUS_STOP: Duration = Duration::const_seconds(10.0);
-
 // TODO Do something else.
 pub const BLIND_RETRY_TO_CREEP_FORWARDS: Duration = Duration::const_seconds(0.1);
 pub const BLIND_RETRY_TO_REACH_END_DIST: Duration = Duration::const_seconds(5.0);
@@ -41,7 +40,10 @@ pub(crate) struct DrivingSimState {
     events: Vec<Event>,
 
     recalc_lanechanging: bool,
+    lane_change_lookahead: Distance,
     handle_uber_turns: bool,
+    exact_end_dist_wakeups: bool,
+    cruise_for_parking: bool,
 
     time_to_unpark_onstreet: Duration,
     time_to_park_onstreet: Duration,
@@ -57,7 +59,10 @@ impl DrivingSimState {
             queues: HashMap::new(),
             events: Vec::new(),
             recalc_lanechanging: opts.recalc_lanechanging,
+            lane_change_lookahead: opts.lane_change_lookahead,
             handle_uber_turns: opts.handle_uber_turns,
+            exact_end_dist_wakeups: opts.exact_end_dist_wakeups,
+            cruise_for_parking: opts.cruise_for_parking,
 
             time_to_unpark_onstreet: Duration::seconds(10.0),
             time_to_park_onstreet: Duration::seconds(15.0),
@@ -91,6 +96,7 @@ impl DrivingSimState {
         now: Time,
         mut params: CreateCar,
         ctx: &mut Ctx,
+        trips: &TripManager,
     ) -> Option<CreateCar> {
         let first_lane = params.router.head().as_lane();
 
@@ -113,6 +119,7 @@ impl DrivingSimState {
                 // Temporary
                 state: CarState::Queued { blocked_since: now },
                 last_steps: VecDeque::new(),
+                speed_history: VecDeque::new(),
                 started_at: now,
                 total_blocked_time: Duration::ZERO,
                 trip_and_person: params.trip_and_person,
@@ -133,11 +140,15 @@ impl DrivingSimState {
                 // Have to do this early
                 if car.router.last_step() {
                     match car.router.maybe_handle_end(
+                        now,
                         params.start_dist,
                         &car.vehicle,
                         ctx.parking,
                         ctx.map,
                         car.trip_and_person,
+                        car.trip_and_person
+                            .and_then(|(_, person)| trips.leaving_at(person, now)),
+                        self.cruise_for_parking,
                         &mut self.events,
                     ) {
                         None | Some(ActionAtEnd::GotoLaneEnd) => {}
@@ -166,7 +177,7 @@ impl DrivingSimState {
                     }
                 }
 
-                car.state = car.crossing_state(params.start_dist, now, ctx.map);
+                car.state = car.crossing_state(params.start_dist, now, ctx);
             }
             ctx.scheduler
                 .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -175,7 +186,7 @@ impl DrivingSimState {
                 queue.cars.insert(idx, car.vehicle.id);
                 // Don't use try_to_reserve_entry -- it's overly conservative.
                 // get_idx_to_insert_car does a more detailed check of the current space usage.
-                queue.reserved_length += car.vehicle.length + FOLLOWING_DISTANCE;
+                queue.reserved_length += car.vehicle.length + ctx.weather.following_distance();
             }
             self.cars.insert(car.vehicle.id, car);
             return None;
@@ -211,7 +222,13 @@ impl DrivingSimState {
         trips: &mut TripManager,
         transit: &mut TransitSimState,
         walking: &mut WalkingSimState,
+        ride_hail: &mut RideHailSimState,
+        microtransit: &mut MicrotransitSimState,
     ) {
+        // The state being finished might've started without an update, like when a leader
+        // releases a queued follower
+        self.cars.get_mut(&id).unwrap().record_speed();
+
         let mut need_distances = {
             let car = &self.cars[&id];
             match car.state {
@@ -226,8 +243,21 @@ impl DrivingSimState {
             // checker, temporarily move one of them out of the map.
             let mut car = self.cars.remove(&id).unwrap();
             // Responsibility of update_car to manage scheduling stuff!
-            need_distances = self.update_car_without_distances(&mut car, now, ctx, transit);
+            need_distances = self.update_car_without_distances(
+                &mut car,
+                now,
+                ctx,
+                trips,
+                transit,
+                ride_hail,
+                microtransit,
+            );
+            let stuck = matches!(car.state, CarState::Queued { .. })
+                && (self.recalc_lanechanging || car.vehicle.vehicle_type == VehicleType::Emergency);
             self.cars.insert(id, car);
+            if stuck && !ctx.handling_live_edits {
+                self.change_lanes_around_queue(id, now, ctx);
+            }
         }
 
         if need_distances {
@@ -243,14 +273,27 @@ impl DrivingSimState {
             // checker, temporarily move one of them out of the map.
             let mut car = self.cars.remove(&id).unwrap();
             // Responsibility of update_car_with_distances to manage scheduling stuff!
-            if self
-                .update_car_with_distances(&mut car, &dists, idx, now, ctx, trips, transit, walking)
-            {
+            if self.update_car_with_distances(
+                &mut car,
+                &dists,
+                idx,
+                now,
+                ctx,
+                trips,
+                transit,
+                walking,
+                ride_hail,
+                microtransit,
+            ) {
                 self.cars.insert(id, car);
             } else {
                 self.delete_car_internal(&mut car, dists, idx, now, ctx);
             }
         }
+
+        if let Some(car) = self.cars.get_mut(&id) {
+            car.record_speed();
+        }
     }
 
     // If this returns true, we need to immediately run update_car_with_distances. If we don't,
@@ -261,7 +304,10 @@ impl DrivingSimState {
         car: &mut Car,
         now: Time,
         ctx: &mut Ctx,
+        trips: &mut TripManager,
         transit: &mut TransitSimState,
+        ride_hail: &mut RideHailSimState,
+        microtransit: &mut MicrotransitSimState,
     ) -> bool {
         match car.state {
             CarState::Crossing(time_int, dist_int) => {
@@ -270,7 +316,7 @@ impl DrivingSimState {
                     let avg_speed = Speed::from_dist_time(dist_int.length(), time_cross);
 
                     let route = car.router.head();
-                    let max_speed = route.speed_limit(ctx.map).min(
+                    let max_speed = route.speed_limit_at(ctx.map, now).min(
                         car.vehicle
                             .max_speed
                             .unwrap_or(Speed::meters_per_second(100.0)),
@@ -282,6 +328,11 @@ impl DrivingSimState {
                                 .push(Event::LaneSpeedPercentage(trip, lane, avg_speed, max_speed));
                         }
                     }
+                    self.events.extend(car.emissions(
+                        route,
+                        Emissions::driving(car.vehicle.vehicle_type, dist_int.length(), time_cross),
+                    ));
+                    self.detect_safety_events(car, time_int, dist_int, avg_speed, ctx.map);
                 }
 
                 car.state = CarState::Queued { blocked_since: now };
@@ -296,8 +347,10 @@ impl DrivingSimState {
                     if self.recalc_lanechanging {
                         car.router.opportunistically_lanechange(
                             &self.queues,
+                            ctx.curb,
                             ctx.map,
                             self.handle_uber_turns,
+                            self.lane_change_lookahead,
                         );
                     }
                     ctx.scheduler.push(now, Command::UpdateCar(car.vehicle.id));
@@ -310,23 +363,38 @@ impl DrivingSimState {
                     // the next loop will pick that up. Just trigger the side effect of choosing an
                     // end_dist.
                     car.router.maybe_handle_end(
+                        now,
                         front,
                         &car.vehicle,
                         ctx.parking,
                         ctx.map,
                         car.trip_and_person,
+                        car.trip_and_person
+                            .and_then(|(_, person)| trips.leaving_at(person, now)),
+                        self.cruise_for_parking,
                         &mut self.events,
                     );
                 }
-                car.state = car.crossing_state(front, now, ctx.map);
+                car.state = car.crossing_state(front, now, ctx);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
             }
             CarState::IdlingAtStop(dist, _) => {
-                car.router = transit.bus_departed_from_stop(car.vehicle.id, ctx.map);
+                car.router = if car.vehicle.vehicle_type.is_transit() {
+                    transit.bus_departed_from_stop(car.vehicle.id, ctx.map)
+                } else if ctx.curb.is_delivery_vehicle(car.vehicle.id) {
+                    ctx.curb.vehicle_departing(car.vehicle.id, ctx.map)
+                } else {
+                    let pos = Position::new(car.router.head().as_lane(), dist);
+                    if microtransit.is_shuttle(car.vehicle.id) {
+                        microtransit.vehicle_departing(now, car.vehicle.id, pos, trips, ctx)
+                    } else {
+                        ride_hail.vehicle_departing(now, car.vehicle.id, pos, trips, ctx)
+                    }
+                };
                 self.events
                     .push(Event::PathAmended(car.router.get_path().clone()));
-                car.state = car.crossing_state(dist, now, ctx.map);
+                car.state = car.crossing_state(dist, now, ctx);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
 
@@ -345,11 +413,18 @@ impl DrivingSimState {
                             // right behind us.
                             if !follower.router.last_step() {
                                 follower.total_blocked_time += now - blocked_since;
-                                follower.state = follower.crossing_state(
+                                self.events.extend(follower.emissions(
+                                    follower.router.head(),
+                                    Emissions::idling(
+                                        follower.vehicle.vehicle_type,
+                                        now - blocked_since,
+                                    ),
+                                ));
+                                follower.state = follower.crossing_state_after_stopping(
                                     // Since the follower was Queued, this must be where they are.
                                     dist - car.vehicle.length - FOLLOWING_DISTANCE,
                                     now,
-                                    ctx.map,
+                                    ctx,
                                 );
                                 ctx.scheduler.update(
                                     follower.state.get_end_time(),
@@ -375,10 +450,7 @@ impl DrivingSimState {
                 assert!(from != goto);
 
                 if let Traversable::Turn(t) = goto {
-                    let mut speed = goto.speed_limit(ctx.map);
-                    if let Some(s) = car.vehicle.max_speed {
-                        speed = speed.min(s);
-                    }
+                    let speed = car.speed_on(goto, now, ctx);
                     if !ctx.intersections.maybe_start_turn(
                         AgentID::Car(car.vehicle.id),
                         t,
@@ -386,7 +458,13 @@ impl DrivingSimState {
                         now,
                         ctx.map,
                         ctx.scheduler,
-                        Some((&car, &self.cars, &mut self.queues)),
+                        Some((
+                            &car,
+                            &self.cars,
+                            &mut self.queues,
+                            ctx.weather.following_distance(),
+                            ctx.drivers.profile(car.vehicle.id).gap_acceptance,
+                        )),
                     ) {
                         // Don't schedule a retry here.
                         return false;
@@ -411,14 +489,22 @@ impl DrivingSimState {
                 // way, until laggy_head is None.
 
                 let last_step = car.router.advance(
+                    now,
                     &car.vehicle,
                     ctx.parking,
                     ctx.map,
                     car.trip_and_person,
+                    car.trip_and_person
+                        .and_then(|(_, person)| trips.leaving_at(person, now)),
+                    self.cruise_for_parking,
                     &mut self.events,
                 );
                 car.total_blocked_time += now - blocked_since;
-                car.state = car.crossing_state(Distance::ZERO, now, ctx.map);
+                self.events.extend(car.emissions(
+                    from,
+                    Emissions::idling(car.vehicle.vehicle_type, now - blocked_since),
+                ));
+                car.state = car.crossing_state(Distance::ZERO, now, ctx);
                 ctx.scheduler
                     .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                 self.events.push(Event::AgentEntersTraversable(
@@ -437,18 +523,18 @@ impl DrivingSimState {
                 // Optimistically assume we'll be out of the way ASAP.
                 // This is update, not push, because we might've scheduled a blind retry too late,
                 // and the car actually crosses an entire new traversable in the meantime.
-                ctx.scheduler.update(
-                    car.crossing_state_with_end_dist(
+                let clear_at = car
+                    .crossing_state_with_end_dist(
                         DistanceInterval::new_driving(
                             Distance::ZERO,
                             car.vehicle.length + FOLLOWING_DISTANCE,
                         ),
                         now,
-                        ctx.map,
+                        ctx,
                     )
-                    .get_end_time(),
-                    Command::UpdateLaggyHead(car.vehicle.id),
-                );
+                    .get_end_time();
+                ctx.scheduler
+                    .update(clear_at, Command::UpdateLaggyHead(car.vehicle.id));
 
                 self.queues
                     .get_mut(&goto)
@@ -461,6 +547,235 @@ impl DrivingSimState {
         false
     }
 
+    /// A car stuck behind others looks for a gap in a lane next to it going the same direction,
+    /// where it could still make its next turn and would have fewer cars ahead. Emergency vehicles
+    /// take any gap that gets them ahead; other drivers only bother if it'd get them past at least
+    /// two cars.
+    fn change_lanes_around_queue(&mut self, id: CarID, now: Time, ctx: &mut Ctx) {
+        let (current, constraints, min_gain) = {
+            let car = &self.cars[&id];
+            let min_gain = if car.vehicle.vehicle_type == VehicleType::Emergency {
+                1
+            } else {
+                2
+            };
+            match car.router.head() {
+                Traversable::Lane(l) => (l, car.vehicle.vehicle_type.to_constraints(), min_gain),
+                Traversable::Turn(_) => {
+                    return;
+                }
+            }
+        };
+        let cars_ahead = self.queues[&Traversable::Lane(current)]
+            .cars
+            .iter()
+            .position(|c| *c == id)
+            .unwrap();
+        if cars_ahead < min_gain {
+            return;
+        }
+        let lanes = ctx.map.get_parent(current).lanes_ltr();
+        let idx = lanes.iter().position(|(l, _, _)| *l == current).unwrap();
+        let dir = lanes[idx].1;
+        let mut neighbors = Vec::new();
+        if idx > 0 {
+            neighbors.push(lanes[idx - 1]);
+        }
+        if idx + 1 < lanes.len() {
+            neighbors.push(lanes[idx + 1]);
+        }
+        for (l, d, _) in neighbors {
+            if d == dir
+                && constraints.can_use(ctx.map.get_l(l), ctx.map)
+                && self.try_to_change_lanes(id, l, cars_ahead - min_gain, now, ctx)
+            {
+                return;
+            }
+        }
+    }
+
+    /// Move a car partway along its lane over to a parallel one, keeping its distance along the
+    /// road. Only happens if there's a gap in the target lane big enough for the car with no more
+    /// than `max_cars_ahead` in front of it, and the car could still make its next turn from
+    /// there. Returns true if the car changed lanes.
+    fn try_to_change_lanes(
+        &mut self,
+        id: CarID,
+        target: LaneID,
+        max_cars_ahead: usize,
+        now: Time,
+        ctx: &mut Ctx,
+    ) -> bool {
+        let from = self.cars[&id].router.head();
+        let to = Traversable::Lane(target);
+        let following_dist = ctx.weather.following_distance();
+        {
+            let car = &self.cars[&id];
+            let ok_state = matches!(
+                car.state,
+                CarState::Queued { .. } | CarState::Crossing(_, _)
+            );
+            if !ok_state
+                || !car.last_steps.is_empty()
+                || car
+                    .router
+                    .turn_from_parallel_lane(target, ctx.map)
+                    .is_none()
+                || !self.queues[&to].room_for_car(car, following_dist)
+                || !ctx
+                    .intersections
+                    .nobody_headed_towards(target, ctx.map.get_l(target).src_i)
+            {
+                return false;
+            }
+        }
+
+        let dists = self.queues[&from].get_car_positions(now, &self.cars, &self.queues);
+        let idx = dists.iter().position(|(c, _)| *c == id).unwrap();
+        // Parallel lanes can have slightly different lengths
+        let to_len = to.length(ctx.map);
+        let start_dist = (dists[idx].1 * (to_len / from.length(ctx.map)))
+            .max(self.cars[&id].vehicle.length)
+            .min(to_len);
+        let new_idx = match self.queues[&to].get_idx_to_insert_car(
+            start_dist,
+            self.cars[&id].vehicle.length,
+            now,
+            &self.cars,
+            &self.queues,
+        ) {
+            Some(idx) if idx <= max_cars_ahead => idx,
+            _ => {
+                return false;
+            }
+        };
+
+        {
+            let car = &self.cars[&id];
+            let queue = self.queues.get_mut(&from).unwrap();
+            assert_eq!(queue.cars.remove(idx).unwrap(), id);
+            queue.free_reserved_space(car, following_dist);
+            let queue = self.queues.get_mut(&to).unwrap();
+            queue.cars.insert(new_idx, id);
+            // Like spawning, get_idx_to_insert_car already checked the space.
+            queue.reserved_length += car.vehicle.length + following_dist;
+        }
+        if !ctx.handling_live_edits {
+            ctx.intersections.space_freed(
+                now,
+                ctx.map.get_l(from.as_lane()).src_i,
+                ctx.scheduler,
+                ctx.map,
+            );
+        }
+        self.release_follower(&dists, idx, now, ctx);
+
+        let car = self.cars.get_mut(&id).unwrap();
+        car.router.change_current_lane(target, ctx.map);
+        car.state = match car.state {
+            CarState::Queued { blocked_since } => {
+                car.total_blocked_time += now - blocked_since;
+                self.events.extend(car.emissions(
+                    from,
+                    Emissions::idling(car.vehicle.vehicle_type, now - blocked_since),
+                ));
+                car.crossing_state_after_stopping(start_dist, now, ctx)
+            }
+            _ => car.crossing_state(start_dist, now, ctx),
+        };
+        ctx.scheduler
+            .update(car.state.get_end_time(), Command::UpdateCar(id));
+        self.events
+            .push(Event::PathAmended(car.router.get_path().clone()));
+        true
+    }
+
+    /// When a vehicle finishes crossing some distance, look for near misses along the way. See
+    /// the safety module.
+    fn detect_safety_events(
+        &mut self,
+        car: &Car,
+        time_int: TimeInterval,
+        dist_int: DistanceInterval,
+        speed: Speed,
+        map: &Map,
+    ) {
+        let on = car.router.head();
+        let queue = &self.queues[&on];
+
+        // Did the vehicle just come up on somebody already stopped?
+        if let Some(idx) = queue.cars.iter().position(|c| *c == car.vehicle.id) {
+            if idx > 0 {
+                let leader_stopped = match self.cars[&queue.cars[idx - 1]].state {
+                    CarState::Queued { blocked_since }
+                    | CarState::WaitingToAdvance { blocked_since } => Some(blocked_since),
+                    _ => None,
+                };
+                if let Some(t) = leader_stopped {
+                    let time_to_stop = time_int.end - t.max(time_int.start);
+                    self.events.extend(
+                        SafetyEvent::maybe_hard_braking(
+                            car.vehicle.id,
+                            on,
+                            speed.inner_meters_per_second(),
+                            time_to_stop,
+                        )
+                        .map(Event::Safety),
+                    );
+                }
+            }
+        }
+
+        // Did the vehicle overtake a cyclist in the next lane over? Only count vehicles that
+        // crossed the whole lane, so they definitely started behind anybody still on it.
+        let l = match on {
+            Traversable::Lane(l) => l,
+            Traversable::Turn(_) => {
+                return;
+            }
+        };
+        if car.vehicle.vehicle_type == VehicleType::Bike
+            || dist_int.start != Distance::ZERO
+            || dist_int.end != map.get_l(l).length()
+        {
+            return;
+        }
+        let lanes = map.get_parent(l).lanes_ltr();
+        let idx = lanes.iter().position(|(id, _, _)| *id == l).unwrap();
+        let dir = lanes[idx].1;
+        let mut neighbors = Vec::new();
+        if idx > 0 {
+            neighbors.push(lanes[idx - 1]);
+        }
+        if let Some(next) = lanes.get(idx + 1) {
+            neighbors.push(*next);
+        }
+        for (other, other_dir, _) in neighbors {
+            if other_dir != dir {
+                continue;
+            }
+            let queue = match self.queues.get(&Traversable::Lane(other)) {
+                Some(q) => q,
+                None => {
+                    continue;
+                }
+            };
+            for bike in &queue.cars {
+                if bike.1 != VehicleType::Bike {
+                    continue;
+                }
+                if let CarState::Crossing(bike_time, _) = self.cars[bike].state {
+                    if bike_time.start <= time_int.start {
+                        self.events.extend(
+                            SafetyEvent::maybe_close_pass(car.vehicle.id, l, *bike, other, map)
+                                .map(Event::Safety),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     // Returns true if the car survives.
     fn update_car_with_distances(
         &mut self,
@@ -472,6 +787,8 @@ impl DrivingSimState {
         trips: &mut TripManager,
         transit: &mut TransitSimState,
         walking: &mut WalkingSimState,
+        ride_hail: &mut RideHailSimState,
+        microtransit: &mut MicrotransitSimState,
     ) -> bool {
         let our_dist = dists[idx].1;
 
@@ -482,11 +799,15 @@ impl DrivingSimState {
             | CarState::WaitingToAdvance { .. } => unreachable!(),
             CarState::Queued { blocked_since } => {
                 match car.router.maybe_handle_end(
+                    now,
                     our_dist,
                     &car.vehicle,
                     ctx.parking,
                     ctx.map,
                     car.trip_and_person,
+                    car.trip_and_person
+                        .and_then(|(_, person)| trips.leaving_at(person, now)),
+                    self.cruise_for_parking,
                     &mut self.events,
                 ) {
                     Some(ActionAtEnd::VanishAtBorder(i)) => {
@@ -537,7 +858,7 @@ impl DrivingSimState {
                     }
                     Some(ActionAtEnd::GotoLaneEnd) => {
                         car.total_blocked_time += now - blocked_since;
-                        car.state = car.crossing_state(our_dist, now, ctx.map);
+                        car.state = car.crossing_state(our_dist, now, ctx);
                         ctx.scheduler
                             .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
                         true
@@ -556,10 +877,12 @@ impl DrivingSimState {
                     }
                     Some(ActionAtEnd::BusAtStop) => {
                         car.total_blocked_time += now - blocked_since;
-                        if transit.bus_arrived_at_stop(now, car.vehicle.id, trips, walking, ctx) {
+                        if let Some(dwell_time) =
+                            transit.bus_arrived_at_stop(now, car.vehicle.id, trips, walking, ctx)
+                        {
                             car.state = CarState::IdlingAtStop(
                                 our_dist,
-                                TimeInterval::new(now, now + TIME_TO_WAIT_AT_BUS_STOP),
+                                TimeInterval::new(now, now + dwell_time),
                             );
                             ctx.scheduler
                                 .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
@@ -569,30 +892,72 @@ impl DrivingSimState {
                             false
                         }
                     }
-                    None => {
-                        ctx.scheduler.push(
-                            now + BLIND_RETRY_TO_REACH_END_DIST,
-                            Command::UpdateCar(car.vehicle.id),
-                        );
-
-                        // TODO For now, always use BLIND_RETRY_TO_REACH_END_DIST. Measured things
-                        // to be slower otherwise. :(
-                        /*
-                        // If this car wasn't blocked at all, when would it reach its goal?
-                        let ideal_end_time = match car.crossing_state(our_dist, now, map) {
-                            CarState::Crossing(time_int, _) => time_int.end,
-                            _ => unreachable!(),
+                    Some(ActionAtEnd::RideHailAtEnd) => {
+                        car.total_blocked_time += now - blocked_since;
+                        let pos = Position::new(car.router.head().as_lane(), our_dist);
+                        let dist_driven = car.router.get_path().total_length();
+                        let result = if microtransit.is_shuttle(car.vehicle.id) {
+                            microtransit.vehicle_reached_end(
+                                now,
+                                car.vehicle.id,
+                                pos,
+                                dist_driven,
+                                trips,
+                                ctx,
+                            )
+                        } else {
+                            ride_hail.vehicle_reached_end(
+                                now,
+                                car.vehicle.id,
+                                pos,
+                                dist_driven,
+                                trips,
+                                ctx,
+                            )
                         };
-                        if ideal_end_time == now {
-                            // Haha, no such luck. We're super super close to the goal, but not
-                            // quite there yet.
-                            scheduler.push(now + BLIND_RETRY_TO_REACH_END_DIST, Command::UpdateCar(car.vehicle.id));
+                        if let Some(dwell_time) = result {
+                            car.state = CarState::IdlingAtStop(
+                                our_dist,
+                                TimeInterval::new(now, now + dwell_time),
+                            );
+                            ctx.scheduler
+                                .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                            true
+                        } else {
+                            // Pulling over
+                            false
+                        }
+                    }
+                    Some(ActionAtEnd::DeliveryAtEnd) => {
+                        car.total_blocked_time += now - blocked_since;
+                        if let Some(dwell_time) = ctx.curb.vehicle_reached_end(car.vehicle.id) {
+                            car.state = CarState::IdlingAtStop(
+                                our_dist,
+                                TimeInterval::new(now, now + dwell_time),
+                            );
+                            ctx.scheduler
+                                .push(car.state.get_end_time(), Command::UpdateCar(car.vehicle.id));
+                            true
                         } else {
-                            scheduler.push(ideal_end_time, Command::UpdateCar(car.vehicle.id));
+                            // Leaving the road
+                            false
                         }
-                        // TODO For cars stuck on their last step, this will spam a fair bit. But
-                        // that should be pretty rare.
-                        */
+                    }
+                    None => {
+                        // Blindly retrying measured faster, but the car may sit a few seconds
+                        // longer than it should.
+                        let mut retry_at = now + BLIND_RETRY_TO_REACH_END_DIST;
+                        if self.exact_end_dist_wakeups {
+                            // If this car wasn't blocked at all, when would it reach its goal? If
+                            // it's super close but not quite there, fall back to the blind retry.
+                            let ideal_end_time =
+                                car.crossing_state(our_dist, now, ctx).get_end_time();
+                            if ideal_end_time > now {
+                                retry_at = ideal_end_time;
+                            }
+                        }
+                        ctx.scheduler
+                            .push(retry_at, Command::UpdateCar(car.vehicle.id));
 
                         true
                     }
@@ -631,7 +996,7 @@ impl DrivingSimState {
         // Hacks to delete cars that're mid-turn
         if let Traversable::Turn(t) = car.router.head() {
             let queue = self.queues.get_mut(&car.router.head()).unwrap();
-            queue.reserved_length += car.vehicle.length + FOLLOWING_DISTANCE;
+            queue.reserved_length += car.vehicle.length + ctx.weather.following_distance();
             ctx.intersections.agent_deleted_mid_turn(AgentID::Car(c), t);
         }
         if let Some(Traversable::Turn(t)) = car.router.maybe_next() {
@@ -656,7 +1021,7 @@ impl DrivingSimState {
             let queue = self.queues.get_mut(&car.router.head()).unwrap();
             assert_eq!(queue.cars.remove(idx).unwrap(), car.vehicle.id);
             // trim_last_steps doesn't actually include the current queue!
-            queue.free_reserved_space(car);
+            queue.free_reserved_space(car, ctx.weather.following_distance());
             let i = match queue.id {
                 Traversable::Lane(l) => ctx.map.get_l(l).src_i,
                 Traversable::Turn(t) => t.parent,
@@ -676,7 +1041,18 @@ impl DrivingSimState {
         ctx.scheduler
             .cancel(Command::UpdateLaggyHead(car.vehicle.id));
 
-        // Update the follower so that they don't suddenly jump forwards.
+        self.release_follower(&dists, idx, now, ctx);
+    }
+
+    /// When the car at `idx` leaves a queue, update the follower so that they don't suddenly jump
+    /// forwards.
+    fn release_follower(
+        &mut self,
+        dists: &[(CarID, Distance)],
+        idx: usize,
+        now: Time,
+        ctx: &mut Ctx,
+    ) {
         if idx != dists.len() - 1 {
             let (follower_id, follower_dist) = dists[idx + 1];
             let mut follower = self.cars.get_mut(&follower_id).unwrap();
@@ -687,7 +1063,12 @@ impl DrivingSimState {
                 CarState::Queued { blocked_since } => {
                     // Prevent them from jumping forwards.
                     follower.total_blocked_time += now - blocked_since;
-                    follower.state = follower.crossing_state(follower_dist, now, ctx.map);
+                    self.events.extend(follower.emissions(
+                        follower.router.head(),
+                        Emissions::idling(follower.vehicle.vehicle_type, now - blocked_since),
+                    ));
+                    follower.state =
+                        follower.crossing_state_after_stopping(follower_dist, now, ctx);
                     ctx.scheduler.update(
                         follower.state.get_end_time(),
                         Command::UpdateCar(follower_id),
@@ -696,7 +1077,7 @@ impl DrivingSimState {
                 CarState::Crossing(_, _) => {
                     // If the follower was still Crossing, they might not've been blocked by leader
                     // yet. In that case, recalculating their Crossing state is a no-op.
-                    follower.state = follower.crossing_state(follower_dist, now, ctx.map);
+                    follower.state = follower.crossing_state(follower_dist, now, ctx);
                     ctx.scheduler.update(
                         follower.state.get_end_time(),
                         Command::UpdateCar(follower_id),
@@ -757,7 +1138,7 @@ impl DrivingSimState {
                         self.cars[&id].vehicle.length + FOLLOWING_DISTANCE,
                     ),
                     now,
-                    ctx.map,
+                    ctx,
                 )
                 .get_end_time();
             // Sometimes due to rounding, retry_at will be exactly time, but we really need to
@@ -797,7 +1178,7 @@ impl DrivingSimState {
                     );
                 }
                 Traversable::Lane(l) => {
-                    old_queue.free_reserved_space(car);
+                    old_queue.free_reserved_space(car, ctx.weather.following_distance());
                     if !ctx.handling_live_edits {
                         ctx.intersections.space_freed(
                             now,
@@ -821,17 +1202,20 @@ impl DrivingSimState {
                             if !follower.router.last_step() {
                                 // The follower has been smoothly following while the laggy head
                                 // gets out of the way. So immediately promote them to
-                                // WaitingToAdvance.
+                                // WaitingToAdvance, though they take a moment to react.
                                 follower.state = CarState::WaitingToAdvance { blocked_since };
                                 if self.recalc_lanechanging && !ctx.handling_live_edits {
                                     follower.router.opportunistically_lanechange(
                                         &self.queues,
+                                        ctx.curb,
                                         ctx.map,
                                         self.handle_uber_turns,
+                                        self.lane_change_lookahead,
                                     );
                                 }
+                                let react = ctx.drivers.profile(follower.vehicle.id).reaction_time;
                                 ctx.scheduler
-                                    .push(now, Command::UpdateCar(follower.vehicle.id));
+                                    .push(now + react, Command::UpdateCar(follower.vehicle.id));
                             }
                         }
                         CarState::WaitingToAdvance { .. } => unreachable!(),
@@ -987,6 +1371,59 @@ impl DrivingSimState {
         }
     }
 
+    pub fn car_intent(
+        &self,
+        id: CarID,
+        now: Time,
+        map: &Map,
+        intersections: &IntersectionSimState,
+        weather: Weather,
+    ) -> Option<CarIntent> {
+        let car = self.cars.get(&id)?;
+        let on = car.router.head();
+        let (decision, constraint) = match car.state {
+            CarState::Crossing(ref time_int, _) => (
+                format!("crossing {} until {}", on, time_int.end),
+                Some(CarConstraint::SpeedLimit(car.state.speed_since().1)),
+            ),
+            CarState::Queued { blocked_since } | CarState::WaitingToAdvance { blocked_since } => {
+                let decision = if matches!(car.state, CarState::Queued { .. }) {
+                    format!("queued on {} since {}", on, blocked_since)
+                } else {
+                    format!("waiting to leave {} since {}", on, blocked_since)
+                };
+                // Expensive, but this is just for debugging one car
+                let constraint = match self
+                    .get_blocked_by_graph(now, map, intersections, weather)
+                    .remove(&AgentID::Car(id))
+                {
+                    Some((_, DelayCause::Agent(a))) => CarConstraint::LeadVehicle(a),
+                    Some((_, DelayCause::Intersection(i))) => CarConstraint::Intersection(i),
+                    None => CarConstraint::EndOfLane,
+                };
+                (decision, Some(constraint))
+            }
+            CarState::Unparking(_, spot, ref time_int) => (
+                format!("unparking from {:?} until {}", spot, time_int.end),
+                None,
+            ),
+            CarState::Parking(_, spot, ref time_int) => (
+                format!("parking at {:?} until {}", spot, time_int.end),
+                None,
+            ),
+            CarState::IdlingAtStop(_, ref time_int) => {
+                (format!("idling at a stop until {}", time_int.end), None)
+            }
+        };
+
+        Some(CarIntent {
+            remaining_path: car.router.get_path().get_steps().iter().cloned().collect(),
+            decision,
+            constraint,
+            speed_history: car.speed_history.iter().cloned().collect(),
+        })
+    }
+
     pub fn debug_lane(&self, id: LaneID) {
         if let Some(ref queue) = self.queues.get(&Traversable::Lane(id)) {
             println!("{}", abstutil::to_json(queue));
@@ -1090,6 +1527,88 @@ impl DrivingSimState {
         affected
     }
 
+    /// After live map edits, try to find a new path for a car whose upcoming route crosses
+    /// something edited. Only cars in the middle of crossing an unedited lane can be rerouted;
+    /// anybody queued at an intersection may have already asked to make a turn. Returns false if
+    /// the car's trip has to be cancelled instead.
+    pub fn reroute_after_live_edits(
+        &mut self,
+        id: CarID,
+        closed_intersections: &HashSet<IntersectionID>,
+        edited_lanes: &BTreeSet<LaneID>,
+        map: &Map,
+    ) -> bool {
+        let car = self.cars.get_mut(&id).unwrap();
+        if !matches!(car.state, CarState::Crossing(_, _)) {
+            return false;
+        }
+        let on_edited_step = |step: &Traversable| match step {
+            Traversable::Lane(l) => edited_lanes.contains(l),
+            Traversable::Turn(t) => {
+                closed_intersections.contains(&t.parent)
+                    || edited_lanes.contains(&t.src)
+                    || edited_lanes.contains(&t.dst)
+            }
+        };
+        if on_edited_step(&car.router.head()) || car.last_steps.iter().any(on_edited_step) {
+            return false;
+        }
+        if edited_lanes.contains(&car.router.get_path().last_step().as_lane()) {
+            return false;
+        }
+        if !car.router.reroute_after_live_edits(&car.vehicle, map) {
+            return false;
+        }
+        self.events
+            .push(Event::PathAmended(car.router.get_path().clone()));
+        true
+    }
+
+    /// Let every car whose remaining route crosses one of these delayed lanes look for a faster
+    /// route. Like rerouting after live edits, only cars in the middle of crossing a lane can
+    /// change their path. Returns (cars checked, cars rerouted).
+    pub fn reroute_around_congestion(
+        &mut self,
+        now: Time,
+        value_of_time: f64,
+        delays: &BTreeMap<LaneID, Duration>,
+        map: &Map,
+    ) -> (usize, usize) {
+        if delays.is_empty() {
+            return (0, 0);
+        }
+        let candidates: Vec<CarID> = self
+            .cars
+            .values()
+            .filter(|car| {
+                car.vehicle.vehicle_type == VehicleType::Car
+                    && matches!(car.state, CarState::Crossing(_, _))
+                    && car
+                        .router
+                        .get_path()
+                        .get_steps()
+                        .iter()
+                        .skip(1)
+                        .any(|step| matches!(step, PathStep::Lane(l) if delays.contains_key(l)))
+            })
+            .map(|car| car.vehicle.id)
+            .collect();
+
+        let mut rerouted = 0;
+        for id in &candidates {
+            let car = self.cars.get_mut(id).unwrap();
+            if car
+                .router
+                .reroute_around_congestion(&car.vehicle, now, value_of_time, delays, map)
+            {
+                rerouted += 1;
+                self.events
+                    .push(Event::PathAmended(car.router.get_path().clone()));
+            }
+        }
+        (candidates.len(), rerouted)
+    }
+
     pub fn all_waiting_people(&self, now: Time, delays: &mut BTreeMap<PersonID, Duration>) {
         for c in self.cars.values() {
             if let Some((_, person)) = c.trip_and_person {
@@ -1106,11 +1625,24 @@ impl DrivingSimState {
         Some((queue.reserved_length, queue.geom_len))
     }
 
+    pub fn queue_occupancy(&self) -> BTreeMap<LaneID, f64> {
+        let mut result = BTreeMap::new();
+        for queue in self.queues.values() {
+            if let Traversable::Lane(l) = queue.id {
+                if queue.reserved_length > Distance::ZERO {
+                    result.insert(l, (queue.reserved_length / queue.geom_len).min(1.0));
+                }
+            }
+        }
+        result
+    }
+
     pub fn get_blocked_by_graph(
         &self,
         now: Time,
         map: &Map,
         intersections: &IntersectionSimState,
+        weather: Weather,
     ) -> BTreeMap<AgentID, (Duration, DelayCause)> {
         let mut graph = BTreeMap::new();
 
@@ -1139,7 +1671,14 @@ impl DrivingSimState {
             }
         }
 
-        intersections.populate_blocked_by(now, &mut graph, map, &self.cars, &self.queues);
+        intersections.populate_blocked_by(
+            now,
+            &mut graph,
+            map,
+            &self.cars,
+            &self.queues,
+            weather.following_distance(),
+        );
         graph
     }
 }
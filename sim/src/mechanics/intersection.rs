@@ -5,20 +5,33 @@ use serde::{Deserialize, Serialize};
 use abstutil::{
     deserialize_btreemap, prettyprint_usize, retain_btreeset, serialize_btreemap, FixedMap,
 };
-use geom::{Duration, Time};
+use geom::{Distance, Duration, Time};
 use map_model::{
-    ControlStopSign, ControlTrafficSignal, Intersection, IntersectionID, LaneID, Map, PhaseType,
-    Traversable, TurnID, TurnPriority, TurnType, UberTurn,
+    ControlStopSign, ControlTrafficSignal, DrivingSide, Intersection, IntersectionID, LaneID, Map,
+    PhaseType, Stage, Traversable, TurnID, TurnPriority, TurnType, UberTurn,
 };
 
 use crate::mechanics::car::Car;
+use crate::mechanics::signal_controller::{
+    ActuatedController, IntersectionView, SignalController, StageDecision,
+};
 use crate::mechanics::Queue;
 use crate::{
-    AgentID, AlertLocation, CarID, Command, DelayCause, Event, Scheduler, SimOptions, Speed,
+    AgentID, AlertLocation, CarID, Command, DelayCause, Event, SafetyEvent, Scheduler, SimOptions,
+    Speed, VehicleType, MAX_PET,
 };
 
 const WAIT_AT_STOP_SIGN: Duration = Duration::const_seconds(0.5);
 const WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL: Duration = Duration::const_seconds(0.2);
+// Drivers entering a roundabout yield to circulating vehicles this close to the entry
+const ROUNDABOUT_ENTRY_GAP: Distance = Distance::const_meters(15.0);
+// While yielding, look for a gap again this often
+const ROUNDABOUT_RECHECK: Duration = Duration::const_seconds(0.5);
+// With a bike box, cyclists waiting through a red have this long to start before cars from the
+// same road
+const BIKE_BOX_HEAD_START: Duration = Duration::const_seconds(3.0);
+// At protected intersections, drivers stop yielding to a cyclist who hasn't moved for this long
+const MAX_YIELD_TO_CYCLIST: Duration = Duration::const_seconds(5.0);
 
 /// Manages conflicts at intersections. When an agent has reached the end of a lane, they call
 /// maybe_start_turn to make a Request. Based on the intersection type (stop sign, traffic signal,
@@ -36,10 +49,19 @@ pub(crate) struct IntersectionSimState {
     break_turn_conflict_cycles: bool,
     handle_uber_turns: bool,
     disable_turn_conflicts: bool,
+    actuated_signals: bool,
+    // Signals with an entry here ignore their fixed-time plan. These can't be serialized, so
+    // they're lost in savestates; actuated_signals recreates its controllers lazily.
+    #[serde(skip_serializing, skip_deserializing)]
+    controllers: BTreeMap<IntersectionID, Box<dyn SignalController>>,
     // (x, y) means x is blocked by y. It's a many-to-many relationship. TODO Better data
     // structure.
     blocked_by: BTreeSet<(CarID, CarID)>,
     events: Vec<Event>,
+    // Turns finished in the last few seconds, to measure post-encroachment times. Losing these in
+    // a savestate just misses a few near misses.
+    #[serde(skip_serializing, skip_deserializing)]
+    recent_exits: BTreeMap<IntersectionID, Vec<(Request, Time)>>,
 
     // Count how many calls to maybe_start_turn there are aside from the initial call. Break down
     // failures by those not allowed by the current intersection state vs those blocked by a
@@ -71,6 +93,7 @@ struct State {
 #[derive(Clone, Serialize, Deserialize)]
 struct SignalState {
     current_stage: usize,
+    current_stage_started: Time,
     stage_ends_at: Time,
 }
 
@@ -90,8 +113,11 @@ impl IntersectionSimState {
             break_turn_conflict_cycles: opts.break_turn_conflict_cycles,
             handle_uber_turns: opts.handle_uber_turns,
             disable_turn_conflicts: opts.disable_turn_conflicts,
+            actuated_signals: opts.actuated_signals,
+            controllers: BTreeMap::new(),
             blocked_by: BTreeSet::new(),
             events: Vec::new(),
+            recent_exits: BTreeMap::new(),
 
             total_repeat_requests: 0,
             not_allowed_requests: 0,
@@ -131,6 +157,10 @@ impl IntersectionSimState {
         state.reserved.remove(&Request { agent, turn });
         if !handling_live_edits && map.get_t(turn).turn_type != TurnType::SharedSidewalkCorner {
             self.wakeup_waiting(now, turn.parent, scheduler, map);
+            self.recent_exits
+                .entry(turn.parent)
+                .or_insert_with(Vec::new)
+                .push((Request { agent, turn }, now));
         }
         if self.break_turn_conflict_cycles {
             if let AgentID::Car(car) = agent {
@@ -172,6 +202,58 @@ impl IntersectionSimState {
         state.reserved.remove(&Request { agent, turn });
     }
 
+    /// An emergency vehicle waiting for a banned turn cuts the current stage short, switching to
+    /// the first stage that lets it go.
+    fn preempt_signal(
+        &mut self,
+        agent: AgentID,
+        turn: TurnID,
+        signal: &ControlTrafficSignal,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) {
+        let signal_state = self
+            .state
+            .get_mut(&turn.parent)
+            .unwrap()
+            .signal
+            .as_mut()
+            .unwrap();
+        if signal.stages[signal_state.current_stage].get_priority_of_turn(turn, signal)
+            != TurnPriority::Banned
+        {
+            return;
+        }
+        // Prefer a stage where nobody else has right-of-way over the turn
+        let stage = match signal
+            .stages
+            .iter()
+            .position(|s| s.get_priority_of_turn(turn, signal) == TurnPriority::Protected)
+            .or_else(|| {
+                signal
+                    .stages
+                    .iter()
+                    .position(|s| s.get_priority_of_turn(turn, signal) == TurnPriority::Yield)
+            }) {
+            Some(stage) => stage,
+            None => {
+                return;
+            }
+        };
+
+        signal_state.current_stage = stage;
+        signal_state.current_stage_started = now;
+        signal_state.stage_ends_at = now + signal.stages[stage].phase_type.simple_duration();
+        scheduler.update(
+            signal_state.stage_ends_at,
+            Command::UpdateIntersection(turn.parent),
+        );
+        self.wakeup_waiting(now, turn.parent, scheduler, map);
+        // The emergency vehicle is already deciding what to do right now
+        scheduler.cancel(Command::update_agent(agent));
+    }
+
     fn wakeup_waiting(&self, now: Time, i: IntersectionID, scheduler: &mut Scheduler, map: &Map) {
         let mut all: Vec<(Request, Time)> = self.state[&i]
             .waiting
@@ -193,8 +275,11 @@ impl IntersectionSimState {
             }
         } else if let Some(ref signal) = map.maybe_get_traffic_signal(i) {
             let stage = &signal.stages[self.state[&i].signal.as_ref().unwrap().current_stage];
-            for (req, _) in all {
-                match stage.get_priority_of_turn(req.turn, signal) {
+            for (req, since) in all {
+                match self
+                    .two_stage_left_priority(&req, since, signal, map)
+                    .unwrap_or_else(|| stage.get_priority_of_turn(req.turn, signal))
+                {
                     TurnPriority::Protected => {
                         protected.push(req);
                     }
@@ -240,6 +325,19 @@ impl IntersectionSimState {
         }
     }
 
+    pub fn set_signal_controller(
+        &mut self,
+        i: IntersectionID,
+        controller: Box<dyn SignalController>,
+    ) {
+        assert!(
+            self.state[&i].signal.is_some(),
+            "{} isn't a traffic signal",
+            i
+        );
+        self.controllers.insert(i, controller);
+    }
+
     /// This is only triggered for traffic signals.
     pub fn update_intersection(
         &mut self,
@@ -254,6 +352,42 @@ impl IntersectionSimState {
 
         // Switch to a new stage?
         assert_eq!(now, signal_state.stage_ends_at);
+
+        if self.actuated_signals && !self.controllers.contains_key(&id) {
+            self.controllers
+                .insert(id, Box::new(ActuatedController::default()));
+        }
+        if let Some(controller) = self.controllers.get_mut(&id) {
+            let StageDecision { stage, duration } = controller.decide(&IntersectionView {
+                id,
+                now,
+                signal,
+                current_stage: signal_state.current_stage,
+                current_stage_started: signal_state.current_stage_started,
+                waiting: state
+                    .waiting
+                    .iter()
+                    .map(|(req, t)| (req.agent, req.turn, *t))
+                    .collect(),
+            });
+            if stage >= signal.stages.len() {
+                panic!(
+                    "Signal controller for {} picked stage {}, but there are only {}",
+                    id,
+                    stage,
+                    signal.stages.len()
+                );
+            }
+            if stage != signal_state.current_stage {
+                signal_state.current_stage = stage;
+                signal_state.current_stage_started = now;
+            }
+            signal_state.stage_ends_at = now + duration;
+            scheduler.push(signal_state.stage_ends_at, Command::UpdateIntersection(id));
+            self.wakeup_waiting(now, id, scheduler, map);
+            return;
+        }
+
         let old_stage = &signal.stages[signal_state.current_stage];
         match old_stage.phase_type {
             PhaseType::Fixed(_) => {
@@ -281,6 +415,7 @@ impl IntersectionSimState {
             signal_state.current_stage = 0;
         }
 
+        signal_state.current_stage_started = now;
         signal_state.stage_ends_at = now
             + signal.stages[signal_state.current_stage]
                 .phase_type
@@ -308,6 +443,8 @@ impl IntersectionSimState {
             &Car,
             &FixedMap<CarID, Car>,
             &mut HashMap<Traversable, Queue>,
+            Distance,
+            f64,
         )>,
     ) -> bool {
         let req = Request { agent, turn };
@@ -330,7 +467,14 @@ impl IntersectionSimState {
         let shared_sidewalk_corner =
             map.get_t(req.turn).turn_type == TurnType::SharedSidewalkCorner;
 
-        let readonly_pair = maybe_cars_and_queues.as_ref().map(|(_, c, q)| (*c, &**q));
+        let readonly_pair = maybe_cars_and_queues
+            .as_ref()
+            .map(|(_, c, q, _, _)| (*c, &**q));
+        // Pedestrians don't vary in how they judge gaps
+        let gap_acceptance = maybe_cars_and_queues
+            .as_ref()
+            .map(|(_, _, _, _, gap)| *gap)
+            .unwrap_or(1.0);
         let allowed = if shared_sidewalk_corner {
             // SharedSidewalkCorner doesn't conflict with anything -- fastpath!
             true
@@ -339,7 +483,7 @@ impl IntersectionSimState {
             false
         } else if maybe_cars_and_queues
             .as_ref()
-            .map(|(car, _, _)| {
+            .map(|(car, _, _, _, _)| {
                 self.handle_uber_turns && car.router.get_path().currently_inside_ut().is_some()
             })
             .unwrap_or(false)
@@ -347,7 +491,9 @@ impl IntersectionSimState {
             // If we started an uber-turn, then finish it! But alert if we're running a red light.
             if let Some(ref signal) = map.maybe_get_traffic_signal(turn.parent) {
                 // Don't pass in the scheduler, aka, don't pause before yielding.
-                if !self.traffic_signal_policy(&req, map, signal, speed, now, None) && false {
+                if !self.traffic_signal_policy(&req, map, signal, speed, gap_acceptance, now, None)
+                    && false
+                {
                     self.events.push(Event::Alert(
                         AlertLocation::Intersection(req.turn.parent),
                         format!("Running a red light inside an uber-turn: {:?}", req),
@@ -356,13 +502,30 @@ impl IntersectionSimState {
             }
 
             true
+        } else if !self.yield_to_cyclists(&req, now, map, scheduler) {
+            false
         } else if self.use_freeform_policy_everywhere {
             // If we made it this far, we don't conflict with an accepted turn
             true
         } else if let Some(ref signal) = map.maybe_get_traffic_signal(turn.parent) {
-            self.traffic_signal_policy(&req, map, signal, speed, now, Some(scheduler))
+            if let AgentID::Car(CarID(_, VehicleType::Emergency)) = agent {
+                self.preempt_signal(agent, turn, signal, now, map, scheduler);
+            }
+            self.traffic_signal_policy(
+                &req,
+                map,
+                signal,
+                speed,
+                gap_acceptance,
+                now,
+                Some(scheduler),
+            )
+        } else if let (true, Some(cars_and_queues)) =
+            (map.get_i(turn.parent).is_roundabout(map), readonly_pair)
+        {
+            self.roundabout_policy(&req, map, cars_and_queues, gap_acceptance, now, scheduler)
         } else if let Some(ref sign) = map.maybe_get_stop_sign(turn.parent) {
-            self.stop_sign_policy(&req, map, sign, now, scheduler)
+            self.stop_sign_policy(&req, map, sign, gap_acceptance, now, scheduler)
         } else {
             unreachable!()
         };
@@ -377,7 +540,7 @@ impl IntersectionSimState {
         if self.handle_uber_turns {
             if let Some(ut) = maybe_cars_and_queues
                 .as_ref()
-                .and_then(|(car, _, _)| car.router.get_path().about_to_start_ut())
+                .and_then(|(car, _, _, _, _)| car.router.get_path().about_to_start_ut())
             {
                 // If there's a problem up ahead, don't start.
                 for t in &ut.path {
@@ -401,7 +564,7 @@ impl IntersectionSimState {
         }
 
         // Don't block the box.
-        if let Some((car, _, queues)) = maybe_cars_and_queues {
+        if let Some((car, _, queues, following_dist, _)) = maybe_cars_and_queues {
             assert_eq!(agent, AgentID::Car(car.vehicle.id));
             let inside_ut = self.handle_uber_turns
                 && (car.router.get_path().currently_inside_ut().is_some()
@@ -409,6 +572,7 @@ impl IntersectionSimState {
             let queue = queues.get_mut(&Traversable::Lane(turn.dst)).unwrap();
             if !queue.try_to_reserve_entry(
                 car,
+                following_dist,
                 !self.dont_block_the_box
                     || allow_block_the_box(map.get_i(turn.parent))
                     || inside_ut,
@@ -445,6 +609,10 @@ impl IntersectionSimState {
             }
         }
 
+        if !shared_sidewalk_corner {
+            self.detect_short_pet(&req, now, map);
+        }
+
         // TODO For now, we're only interested in signals, and there's too much raw data to store
         // for stop signs too.
         let state = self.state.get_mut(&turn.parent).unwrap();
@@ -469,6 +637,45 @@ impl IntersectionSimState {
         true
     }
 
+    /// Somebody's starting a turn. Did anybody just finish a conflicting turn?
+    fn detect_short_pet(&mut self, req: &Request, now: Time, map: &Map) {
+        let exits = match self.recent_exits.get_mut(&req.turn.parent) {
+            Some(x) => x,
+            None => {
+                return;
+            }
+        };
+        exits.retain(|(_, t)| now - *t < MAX_PET);
+        let turn = map.get_t(req.turn);
+        let protected = map.get_bike_treatments(req.turn.parent).protected;
+        for (prev, t) in exits.iter() {
+            if prev.agent == req.agent {
+                continue;
+            }
+            // Corner islands move the spot where turning drivers cross cyclists back from the
+            // intersection, after drivers have slowed down and can see them. Turn geometry doesn't
+            // capture that, so don't count those conflicts.
+            if protected
+                && matches!((prev.agent, req.agent), (AgentID::Car(_), AgentID::Car(_)))
+                && is_bike(prev.agent) != is_bike(req.agent)
+            {
+                continue;
+            }
+            // Turns may have disappeared due to live edits
+            if let Some(other) = map.maybe_get_t(prev.turn) {
+                if turn.conflicts_with(other) {
+                    self.events
+                        .push(Event::Safety(SafetyEvent::ShortPostEncroachment {
+                            intersection: req.turn.parent,
+                            first: prev.agent,
+                            second: req.agent,
+                            pet: now - *t,
+                        }));
+                }
+            }
+        }
+    }
+
     pub fn collect_events(&mut self) -> Vec<Event> {
         std::mem::replace(&mut self.events, Vec::new())
     }
@@ -501,12 +708,32 @@ impl IntersectionSimState {
                 (None, Some(_)) => {
                     state.signal = None;
                     scheduler.cancel(Command::UpdateIntersection(state.id));
+                    self.controllers.remove(&state.id);
                 }
                 (None, None) => {}
             }
         }
     }
 
+    /// Start these signals over from the stage their offset dictates right now, instead of
+    /// finishing the current stage first.
+    pub fn restart_traffic_signals(
+        &mut self,
+        now: Time,
+        signals: &BTreeSet<IntersectionID>,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) {
+        for i in signals {
+            if let Some(state) = self.state.get_mut(i) {
+                if state.signal.is_some() {
+                    scheduler.cancel(Command::UpdateIntersection(*i));
+                    state.signal = Some(SignalState::new(*i, now, map, scheduler));
+                }
+            }
+        }
+    }
+
     pub fn handle_live_edits(&self, map: &Map) {
         // Just sanity check that we don't have any references to deleted turns
         let mut errors = Vec::new();
@@ -638,6 +865,7 @@ impl IntersectionSimState {
         map: &Map,
         cars: &FixedMap<CarID, Car>,
         queues: &HashMap<Traversable, Queue>,
+        following_dist: Distance,
     ) {
         // Don't use self.blocked_by -- that gets complicated with uber-turns and such.
         //
@@ -657,7 +885,7 @@ impl IntersectionSimState {
                 } else if let AgentID::Car(car) = req.agent {
                     let queue = &queues[&Traversable::Lane(req.turn.dst)];
                     let car = cars.get(&car).unwrap();
-                    if !queue.room_for_car(car) {
+                    if !queue.room_for_car(car, following_dist) {
                         // TODO Or it's reserved due to an uber turn or something
                         let blocker = queue.cars.back().cloned().or(queue.laggy_head).unwrap();
                         cause = DelayCause::Agent(AgentID::Car(blocker));
@@ -695,6 +923,7 @@ impl IntersectionSimState {
         req: &Request,
         map: &Map,
         sign: &ControlStopSign,
+        gap_acceptance: f64,
         now: Time,
         scheduler: &mut Scheduler,
     ) -> bool {
@@ -702,13 +931,11 @@ impl IntersectionSimState {
         assert!(our_priority != TurnPriority::Banned);
         let our_time = self.state[&req.turn.parent].waiting[req];
 
-        if our_priority == TurnPriority::Yield && now < our_time + WAIT_AT_STOP_SIGN {
+        let wait = WAIT_AT_STOP_SIGN * gap_acceptance;
+        if our_priority == TurnPriority::Yield && now < our_time + wait {
             // Since we have "ownership" of scheduling for req.agent, don't need to use
             // scheduler.update.
-            scheduler.push(
-                our_time + WAIT_AT_STOP_SIGN,
-                Command::update_agent(req.agent),
-            );
+            scheduler.push(our_time + wait, Command::update_agent(req.agent));
             return false;
         }
 
@@ -731,12 +958,62 @@ impl IntersectionSimState {
         true
     }
 
+    /// Vehicles circulating in a roundabout never stop; conflicts with turns already accepted were
+    /// checked before this. Vehicles entering yield to any circulating vehicle about to cross
+    /// their path, but don't have to come to a full stop when the way is clear.
+    fn roundabout_policy(
+        &mut self,
+        req: &Request,
+        map: &Map,
+        (cars, queues): (&FixedMap<CarID, Car>, &HashMap<Traversable, Queue>),
+        gap_acceptance: f64,
+        now: Time,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        if map.get_parent(req.turn.src).is_roundabout()
+            || !map.get_parent(req.turn.dst).is_roundabout()
+        {
+            return true;
+        }
+
+        let turn = map.get_t(req.turn);
+        for l in &map.get_i(req.turn.parent).incoming_lanes {
+            if !map.get_parent(*l).is_roundabout()
+                || !map
+                    .get_turns_from_lane(*l)
+                    .into_iter()
+                    .any(|t| t.conflicts_with(turn))
+            {
+                continue;
+            }
+            // Sidewalks don't have queues
+            let queue = match queues.get(&Traversable::Lane(*l)) {
+                Some(q) => q,
+                None => {
+                    continue;
+                }
+            };
+            if let Some((_, dist)) = queue
+                .get_car_positions(now, cars, queues)
+                .into_iter()
+                .next()
+            {
+                if map.get_l(*l).length() - dist <= ROUNDABOUT_ENTRY_GAP * gap_acceptance {
+                    scheduler.update(now + ROUNDABOUT_RECHECK, Command::update_agent(req.agent));
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
     fn traffic_signal_policy(
         &mut self,
         req: &Request,
         map: &Map,
         signal: &ControlTrafficSignal,
         speed: Speed,
+        gap_acceptance: f64,
         now: Time,
         scheduler: Option<&mut Scheduler>,
     ) -> bool {
@@ -750,25 +1027,75 @@ impl IntersectionSimState {
         let our_time = state.waiting[req];
 
         // Can't go at all this stage.
-        let our_priority = stage.get_priority_of_turn(req.turn, signal);
+        let mut our_priority = self
+            .two_stage_left_priority(req, our_time, signal, map)
+            .unwrap_or_else(|| stage.get_priority_of_turn(req.turn, signal));
         if our_priority == TurnPriority::Banned {
             return false;
         }
+        // Even if a signal is configured to protect a U-turn, drivers still treat it like a yield.
+        if turn.turn_type == TurnType::UTurn {
+            our_priority = TurnPriority::Yield;
+        }
 
-        if our_priority == TurnPriority::Yield
-            && now < our_time + WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL
-        {
+        let wait = WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL * gap_acceptance;
+        if our_priority == TurnPriority::Yield && now < our_time + wait {
             // Since we have "ownership" of scheduling for req.agent, don't need to use
             // scheduler.update.
             if let Some(s) = scheduler {
-                s.push(
-                    our_time + WAIT_BEFORE_YIELD_AT_TRAFFIC_SIGNAL,
-                    Command::update_agent(req.agent),
-                );
+                s.push(our_time + wait, Command::update_agent(req.agent));
             }
             return false;
         }
 
+        if turn.turn_type == TurnType::Crosswalk {
+            // With a push button, pedestrians only get the walk signal if somebody pressed it
+            // before this stage started. wakeup_waiting will retry when the stage changes.
+            let push_button = map
+                .get_i(turn.id.parent)
+                .get_crosswalk(turn.id)
+                .map(|c| c.push_button)
+                .unwrap_or(false);
+            if push_button && our_time >= signal_state.current_stage_started {
+                return false;
+            }
+        } else {
+            // Give pedestrians their head start on any crosswalk that this turn crosses
+            for c in &map.get_i(turn.id.parent).crosswalks {
+                let walk_until = signal_state.current_stage_started + c.leading_interval;
+                if now < walk_until
+                    && stage.get_priority_of_turn(c.turns[0], signal) == TurnPriority::Protected
+                    && c.turns.iter().any(|t| map.get_t(*t).conflicts_with(turn))
+                {
+                    if let Some(s) = scheduler {
+                        // Pedestrians finishing their crossing might wake us up earlier, so use
+                        // update.
+                        s.update(walk_until, Command::update_agent(req.agent));
+                    }
+                    return false;
+                }
+            }
+
+            // Cyclists waiting through the red in a bike box start first
+            let box_until = signal_state.current_stage_started + BIKE_BOX_HEAD_START;
+            if now < box_until
+                && !is_bike(req.agent)
+                && map.get_bike_treatments(req.turn.parent).bike_box
+            {
+                let src = map.get_l(req.turn.src).parent;
+                if state.waiting.iter().any(|(other, since)| {
+                    is_bike(other.agent)
+                        && *since < signal_state.current_stage_started
+                        && map.get_l(other.turn.src).parent == src
+                }) {
+                    if let Some(s) = scheduler {
+                        s.update(box_until, Command::update_agent(req.agent));
+                    }
+                    return false;
+                }
+            }
+        }
+
         // Previously: A yield loses to a conflicting Priority turn.
         // But similar to the description in stop_sign_policy, this caused unnecessary gridlock.
         // Priority vehicles getting scheduled first just requires a little tweak in
@@ -799,6 +1126,100 @@ impl IntersectionSimState {
         true
     }
 
+    /// Cyclists making a two-stage left turn cross with through traffic from their road, then wait
+    /// at the far corner for through traffic onto the road they're turning onto. The first stage
+    /// isn't simulated; they just have to wait through at least one change of stage. Where people
+    /// drive on the left, this applies to right turns instead. Returns None if this isn't a
+    /// two-stage turn.
+    fn two_stage_left_priority(
+        &self,
+        req: &Request,
+        since: Time,
+        signal: &ControlTrafficSignal,
+        map: &Map,
+    ) -> Option<TurnPriority> {
+        let far_side_turn = match map.get_config().driving_side {
+            DrivingSide::Right => TurnType::Left,
+            DrivingSide::Left => TurnType::Right,
+        };
+        if !is_bike(req.agent)
+            || map.get_t(req.turn).turn_type != far_side_turn
+            || !map.get_bike_treatments(req.turn.parent).two_stage_left
+        {
+            return None;
+        }
+        let dst = map.get_l(req.turn.dst).parent;
+        let straight_onto_dst = |stage: &Stage| {
+            map.get_i(req.turn.parent).turns.iter().any(|t| {
+                map.get_t(*t).turn_type == TurnType::Straight
+                    && map.get_l(t.dst).parent == dst
+                    && stage.get_priority_of_turn(*t, signal) != TurnPriority::Banned
+            })
+        };
+        // If traffic never goes straight onto that road, turn like normal
+        if !signal.stages.iter().any(|stage| straight_onto_dst(stage)) {
+            return None;
+        }
+
+        let signal_state = self.state[&req.turn.parent].signal.as_ref().unwrap();
+        if since < signal_state.current_stage_started
+            && straight_onto_dst(&signal.stages[signal_state.current_stage])
+        {
+            Some(TurnPriority::Protected)
+        } else {
+            Some(TurnPriority::Banned)
+        }
+    }
+
+    /// At protected intersections, drivers yield to cyclists who got there first and are about to
+    /// cross their path. If true, the request can go.
+    fn yield_to_cyclists(
+        &self,
+        req: &Request,
+        now: Time,
+        map: &Map,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        if !matches!(req.agent, AgentID::Car(_))
+            || is_bike(req.agent)
+            || !map.get_bike_treatments(req.turn.parent).protected
+        {
+            return true;
+        }
+        let state = &self.state[&req.turn.parent];
+        let our_time = state.waiting[req];
+        let turn = map.get_t(req.turn);
+        for (other, since) in &state.waiting {
+            // Don't wait forever for a cyclist who's stuck
+            if !is_bike(other.agent) || *since > our_time || now - *since >= MAX_YIELD_TO_CYCLIST {
+                continue;
+            }
+            // Cyclists with a red light aren't about to go
+            if let (Some(signal), Some(signal_state)) = (
+                map.maybe_get_traffic_signal(req.turn.parent),
+                state.signal.as_ref(),
+            ) {
+                let priority = self
+                    .two_stage_left_priority(other, *since, signal, map)
+                    .unwrap_or_else(|| {
+                        signal.stages[signal_state.current_stage]
+                            .get_priority_of_turn(other.turn, signal)
+                    });
+                if priority == TurnPriority::Banned {
+                    continue;
+                }
+            }
+            if map.get_t(other.turn).conflicts_with(turn) {
+                scheduler.update(
+                    *since + MAX_YIELD_TO_CYCLIST,
+                    Command::update_agent(req.agent),
+                );
+                return false;
+            }
+        }
+        true
+    }
+
     // If true, the request can go.
     fn handle_accepted_conflicts(
         &mut self,
@@ -899,6 +1320,7 @@ impl SignalState {
     fn new(id: IntersectionID, now: Time, map: &Map, scheduler: &mut Scheduler) -> SignalState {
         let mut state = SignalState {
             current_stage: 0,
+            current_stage_started: now,
             stage_ends_at: now,
         };
 
@@ -916,6 +1338,12 @@ impl SignalState {
                     state.current_stage = 0;
                 }
             } else {
+                // The signal's offset might mean this stage started before the simulation did
+                state.current_stage_started = if now - Time::START_OF_DAY >= offset {
+                    now - offset
+                } else {
+                    Time::START_OF_DAY
+                };
                 state.stage_ends_at = now + dt - offset;
                 break;
             }
@@ -925,6 +1353,10 @@ impl SignalState {
     }
 }
 
+fn is_bike(agent: AgentID) -> bool {
+    matches!(agent, AgentID::Car(CarID(_, VehicleType::Bike)))
+}
+
 fn allow_block_the_box(i: &Intersection) -> bool {
     // Degenerate intersections are often just artifacts of how roads are split up in OSM. Allow
     // vehicles to get stuck in them, since the only possible thing they could block is pedestrians
@@ -1,12 +1,19 @@
+pub(crate) use self::bike_parking::BikeParkingSimState;
+pub use self::bike_parking::{BikeRackOccupancy, IMPROVISED_BIKE_PARKING_TIME};
 pub(crate) use self::driving::DrivingSimState;
 pub(crate) use self::intersection::IntersectionSimState;
-pub(crate) use self::parking::{ParkingSim, ParkingSimState};
+pub(crate) use self::parking::{ParkingSearch, ParkingSim, ParkingSimState};
 pub(crate) use self::queue::Queue;
+pub use self::signal_controller::{
+    ActuatedController, IntersectionView, SignalController, StageDecision,
+};
 pub(crate) use self::walking::WalkingSimState;
 
+mod bike_parking;
 mod car;
 mod driving;
 mod intersection;
 mod parking;
 mod queue;
+mod signal_controller;
 mod walking;
@@ -7,7 +7,7 @@ use abstutil::{
     deserialize_btreemap, deserialize_multimap, retain_btreemap, serialize_btreemap,
     serialize_multimap, MultiMap, Timer,
 };
-use geom::{Distance, PolyLine, Pt2D};
+use geom::{Distance, PolyLine, Pt2D, Time};
 use map_model::{
     BuildingID, Lane, LaneID, LaneType, Map, OffstreetParking, ParkingLotID, PathConstraints,
     PathStep, Position, Traversable, TurnID,
@@ -44,9 +44,7 @@ pub trait ParkingSim {
         &self,
         driving_pos: Position,
         vehicle: &Vehicle,
-        // Either the building where a seeded car starts or the target of a trip. For filtering
-        // private spots.
-        target: BuildingID,
+        search: ParkingSearch,
         map: &Map,
     ) -> Vec<(ParkingSpot, Position)>;
     fn spot_to_driving_pos(&self, spot: ParkingSpot, vehicle: &Vehicle, map: &Map) -> Position;
@@ -63,7 +61,7 @@ pub trait ParkingSim {
         &self,
         start: LaneID,
         vehicle: &Vehicle,
-        target: BuildingID,
+        search: ParkingSearch,
         map: &Map,
     ) -> Option<(Vec<PathStep>, ParkingSpot, Position)>;
     fn collect_events(&mut self) -> Vec<Event>;
@@ -71,6 +69,30 @@ pub trait ParkingSim {
     fn bldg_to_parked_cars(&self, b: BuildingID) -> Vec<CarID>;
 }
 
+/// Describes a driver looking for parking, so spots they can't use are skipped.
+#[derive(Clone, Copy, Debug)]
+pub struct ParkingSearch {
+    /// Either the building where a seeded car starts or the target of a trip. For filtering
+    /// private spots and spots in residential permit zones.
+    pub target: BuildingID,
+    pub now: Time,
+    /// When the driver will move the car again. None if they're staying the rest of the day.
+    pub leave_at: Option<Time>,
+}
+
+impl ParkingSearch {
+    /// Parking zones from map edits restrict some on-street spots.
+    pub fn allows(&self, spot: ParkingSpot, map: &Map) -> bool {
+        match spot {
+            ParkingSpot::Onstreet(l, _) => match map.get_parking_zone(map.get_l(l).parent) {
+                Some(zone) => zone.allows(self.target, self.now, self.leave_at, map),
+                None => true,
+            },
+            ParkingSpot::Offstreet(_, _) | ParkingSpot::Lot(_, _) => true,
+        }
+    }
+}
+
 #[enum_dispatch]
 #[derive(Serialize, Deserialize, Clone)]
 pub enum ParkingSimState {
@@ -412,27 +434,29 @@ impl ParkingSim for NormalParkingSimState {
         &self,
         driving_pos: Position,
         vehicle: &Vehicle,
-        // Either the building where a seeded car starts or the target of a trip. For filtering
-        // private spots.
-        target: BuildingID,
+        search: ParkingSearch,
         map: &Map,
     ) -> Vec<(ParkingSpot, Position)> {
         let mut candidates = Vec::new();
 
-        for l in self.driving_to_parking_lanes.get(driving_pos.lane()) {
-            for spot in self.onstreet_lanes[l].spots() {
-                if self.is_free(spot)
-                    && driving_pos.dist_along()
-                        < self.spot_to_driving_pos(spot, vehicle, map).dist_along()
-                {
-                    candidates.push(spot);
+        // Long vehicles like trucks don't fit in on-street spots.
+        if vehicle.length <= map_model::PARKING_SPOT_LENGTH {
+            for l in self.driving_to_parking_lanes.get(driving_pos.lane()) {
+                for spot in self.onstreet_lanes[l].spots() {
+                    if self.is_free(spot)
+                        && driving_pos.dist_along()
+                            < self.spot_to_driving_pos(spot, vehicle, map).dist_along()
+                        && search.allows(spot, map)
+                    {
+                        candidates.push(spot);
+                    }
                 }
             }
         }
 
         for (b, bldg_dist) in self.driving_to_offstreet.get(driving_pos.lane()) {
             if let OffstreetParking::Private(_, _) = map.get_b(*b).parking {
-                if target != *b {
+                if search.target != *b {
                     continue;
                 }
             }
@@ -534,7 +558,7 @@ impl ParkingSim for NormalParkingSimState {
         &self,
         start: LaneID,
         vehicle: &Vehicle,
-        target: BuildingID,
+        search: ParkingSearch,
         map: &Map,
     ) -> Option<(Vec<PathStep>, ParkingSpot, Position)> {
         let mut backrefs: HashMap<LaneID, TurnID> = HashMap::new();
@@ -552,7 +576,7 @@ impl ParkingSim for NormalParkingSimState {
                 // Pick the closest to the start of the lane, since that's closest to where we came
                 // from
                 if let Some((spot, pos)) = self
-                    .get_all_free_spots(Position::start(current), vehicle, target, map)
+                    .get_all_free_spots(Position::start(current), vehicle, search, map)
                     .into_iter()
                     .min_by_key(|(_, pos)| pos.dist_along())
                 {
@@ -572,7 +596,12 @@ impl ParkingSim for NormalParkingSimState {
                     }
                 }
             }
-            for turn in map.get_turns_for(current, PathConstraints::Car) {
+            let constraints = vehicle.vehicle_type.to_constraints();
+            for turn in map.get_turns_for(current, constraints) {
+                // The car is at the end of the start lane, past any modal filter
+                if current != start && constraints.blocked_by_modal_filter(turn.id, map) {
+                    continue;
+                }
                 if !backrefs.contains_key(&turn.id.dst) {
                     let dist_this_step = turn.geom.length() + map.get_l(current).length();
                     backrefs.insert(turn.id.dst, turn.id);
@@ -865,7 +894,7 @@ impl ParkingSim for InfiniteParkingSimState {
         &self,
         driving_pos: Position,
         vehicle: &Vehicle,
-        target: BuildingID,
+        search: ParkingSearch,
         map: &Map,
     ) -> Vec<(ParkingSpot, Position)> {
         // The target building may be blackholed, so fallback to a building on one of the
@@ -875,8 +904,8 @@ impl ParkingSim for InfiniteParkingSimState {
             if driving_pos.dist_along() >= *bldg_dist {
                 continue;
             }
-            if target == *b {
-                bldg = Some(target);
+            if search.target == *b {
+                bldg = Some(search.target);
                 break;
             } else if bldg.is_none() {
                 // Backup option
@@ -920,7 +949,7 @@ impl ParkingSim for InfiniteParkingSimState {
         &self,
         start: LaneID,
         vehicle: &Vehicle,
-        target: BuildingID,
+        search: ParkingSearch,
         map: &Map,
     ) -> Option<(Vec<PathStep>, ParkingSpot, Position)> {
         // TODO This impl is copied from NormalParkingSimState. Instead, we already know the
@@ -940,7 +969,7 @@ impl ParkingSim for InfiniteParkingSimState {
                 // Pick the closest to the start of the lane, since that's closest to where we came
                 // from
                 if let Some((spot, pos)) = self
-                    .get_all_free_spots(Position::start(current), vehicle, target, map)
+                    .get_all_free_spots(Position::start(current), vehicle, search, map)
                     .into_iter()
                     .min_by_key(|(_, pos)| pos.dist_along())
                 {
@@ -960,7 +989,12 @@ impl ParkingSim for InfiniteParkingSimState {
                     }
                 }
             }
-            for turn in map.get_turns_for(current, PathConstraints::Car) {
+            let constraints = vehicle.vehicle_type.to_constraints();
+            for turn in map.get_turns_for(current, constraints) {
+                // The car is at the end of the start lane, past any modal filter
+                if current != start && constraints.blocked_by_modal_filter(turn.id, map) {
+                    continue;
+                }
                 if !backrefs.contains_key(&turn.id.dst) {
                     let dist_this_step = turn.geom.length() + map.get_l(current).length();
                     backrefs.insert(turn.id.dst, turn.id);
@@ -20,11 +20,12 @@ pub(crate) struct Queue {
     pub laggy_head: Option<CarID>,
 
     pub geom_len: Distance,
-    /// When a car's turn is accepted, reserve the vehicle length + FOLLOWING_DISTANCE for the
+    /// When a car's turn is accepted, reserve the vehicle length + following distance for the
     /// target lane. When the car completely leaves (stops being the laggy_head), free up that
-    /// space. To prevent blocking the box for possibly scary amounts of time, allocate some of
-    /// this length first. This is unused for turns themselves. This value can exceed geom_len
-    /// (for the edge case of ONE long car on a short queue).
+    /// space. The following distance depends on the weather, and may be longer than the minimum
+    /// FOLLOWING_DISTANCE used to position cars. To prevent blocking the box for possibly scary
+    /// amounts of time, allocate some of this length first. This is unused for turns themselves.
+    /// This value can exceed geom_len (for the edge case of ONE long car on a short queue).
     pub reserved_length: Distance,
 }
 
@@ -239,25 +240,30 @@ impl Queue {
 
     /// If true, there's room and the car must actually start the turn (because the space is
     /// reserved).
-    pub fn try_to_reserve_entry(&mut self, car: &Car, force_entry: bool) -> bool {
-        // Sometimes a car + FOLLOWING_DISTANCE might be longer than the geom_len entirely. In that
-        // case, it just means the car won't totally fit on the queue at once, which is fine.
+    pub fn try_to_reserve_entry(
+        &mut self,
+        car: &Car,
+        following_dist: Distance,
+        force_entry: bool,
+    ) -> bool {
+        // Sometimes a car + following distance might be longer than the geom_len entirely. In
+        // that case, it just means the car won't totally fit on the queue at once, which is fine.
         // Reserve the normal amount of space; the next car trying to enter will get rejected.
         // Also allow this don't-block-the-box prevention to be disabled.
-        if self.room_for_car(car) || force_entry {
-            self.reserved_length += car.vehicle.length + FOLLOWING_DISTANCE;
+        if self.room_for_car(car, following_dist) || force_entry {
+            self.reserved_length += car.vehicle.length + following_dist;
             return true;
         }
         false
     }
 
-    pub fn room_for_car(&self, car: &Car) -> bool {
+    pub fn room_for_car(&self, car: &Car, following_dist: Distance) -> bool {
         self.reserved_length == Distance::ZERO
-            || self.reserved_length + car.vehicle.length + FOLLOWING_DISTANCE < self.geom_len
+            || self.reserved_length + car.vehicle.length + following_dist < self.geom_len
     }
 
-    pub fn free_reserved_space(&mut self, car: &Car) {
-        self.reserved_length -= car.vehicle.length + FOLLOWING_DISTANCE;
+    pub fn free_reserved_space(&mut self, car: &Car, following_dist: Distance) {
+        self.reserved_length -= car.vehicle.length + following_dist;
         assert!(
             self.reserved_length >= Distance::ZERO,
             "invalid reserved length: {:?}, car: {:?}",
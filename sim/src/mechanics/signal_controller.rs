@@ -0,0 +1,118 @@
+//! Traffic signals normally follow the fixed-time plan configured in the map: each stage lasts for
+//! its PhaseType's duration, then the signal moves to the next one. A SignalController instead
+//! decides at runtime which stage comes next and for how long, based on who's waiting at the
+//! intersection. This is a plugin point for actuated or adaptive control strategies.
+
+use geom::{Duration, Time};
+use map_model::{ControlTrafficSignal, IntersectionID, TurnID, TurnPriority};
+
+use crate::AgentID;
+
+/// What a SignalController can see when it's asked to make a decision.
+pub struct IntersectionView<'a> {
+    pub id: IntersectionID,
+    pub now: Time,
+    pub signal: &'a ControlTrafficSignal,
+    /// The stage that's just ending
+    pub current_stage: usize,
+    /// When the current stage started. If it's been extended by the controller, this is when the
+    /// stage first started, not the last extension.
+    pub current_stage_started: Time,
+    /// Every agent at the end of a lane, ready to start a turn, and when they started waiting
+    pub waiting: Vec<(AgentID, TurnID, Time)>,
+}
+
+impl<'a> IntersectionView<'a> {
+    /// How many waiting agents could make a protected turn during this stage?
+    pub fn protected_demand(&self, stage: usize) -> usize {
+        let stage = &self.signal.stages[stage];
+        self.waiting
+            .iter()
+            .filter(|(_, t, _)| {
+                stage.get_priority_of_turn(*t, self.signal) == TurnPriority::Protected
+            })
+            .count()
+    }
+
+    /// How many waiting agents could make any turn, protected or yield, during this stage?
+    pub fn demand(&self, stage: usize) -> usize {
+        let stage = &self.signal.stages[stage];
+        self.waiting
+            .iter()
+            .filter(|(_, t, _)| stage.get_priority_of_turn(*t, self.signal) != TurnPriority::Banned)
+            .count()
+    }
+}
+
+/// The stage a signal should switch to (or stay in) and how long until the controller is asked
+/// again.
+pub struct StageDecision {
+    pub stage: usize,
+    pub duration: Duration,
+}
+
+/// Controls one traffic signal, overriding its fixed-time plan. `decide` is called every time the
+/// previous decision expires.
+///
+/// Controllers aren't part of savestates or replay logs.
+pub trait SignalController: Send + Sync {
+    fn decide(&mut self, view: &IntersectionView) -> StageDecision;
+
+    /// The simulation needs to be cloneable.
+    fn clone_box(&self) -> Box<dyn SignalController>;
+}
+
+impl Clone for Box<dyn SignalController> {
+    fn clone(&self) -> Box<dyn SignalController> {
+        self.clone_box()
+    }
+}
+
+/// A simple vehicle-actuated controller. A stage is held for at least `min_green`. While agents
+/// are still waiting to make protected turns, the stage is extended in steps of `extension`, up to
+/// `max_green`. Then the signal skips ahead to the next stage with anybody waiting. If nobody is
+/// waiting anywhere, it just cycles through the stages.
+#[derive(Clone)]
+pub struct ActuatedController {
+    pub min_green: Duration,
+    pub extension: Duration,
+    pub max_green: Duration,
+}
+
+impl std::default::Default for ActuatedController {
+    fn default() -> ActuatedController {
+        ActuatedController {
+            min_green: Duration::seconds(10.0),
+            extension: Duration::seconds(3.0),
+            max_green: Duration::seconds(60.0),
+        }
+    }
+}
+
+impl SignalController for ActuatedController {
+    fn decide(&mut self, view: &IntersectionView) -> StageDecision {
+        let elapsed = view.now - view.current_stage_started;
+        if elapsed + self.extension <= self.max_green
+            && view.protected_demand(view.current_stage) > 0
+        {
+            return StageDecision {
+                stage: view.current_stage,
+                duration: self.extension,
+            };
+        }
+
+        let num_stages = view.signal.stages.len();
+        let next = (1..=num_stages)
+            .map(|offset| (view.current_stage + offset) % num_stages)
+            .find(|idx| view.demand(*idx) > 0)
+            .unwrap_or((view.current_stage + 1) % num_stages);
+        StageDecision {
+            stage: next,
+            duration: self.min_green,
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn SignalController> {
+        Box::new(self.clone())
+    }
+}
@@ -5,24 +5,25 @@ use serde::{Deserialize, Serialize};
 use abstutil::{deserialize_multimap, serialize_multimap, FixedMap, IndexableKey, MultiMap};
 use geom::{Distance, Duration, Line, PolyLine, Speed, Time};
 use map_model::{
-    BuildingID, BusRouteID, DrivingSide, Map, ParkingLotID, Path, PathStep, Traversable,
-    SIDEWALK_THICKNESS,
+    BuildingID, BusRouteID, DrivingSide, Lane, LaneID, Map, ParkingLotID, Path, PathStep,
+    Traversable, SIDEWALK_THICKNESS,
 };
 
 use crate::sim::Ctx;
 use crate::{
     AgentID, AgentProperties, Command, CommutersVehiclesCounts, CreatePedestrian, DistanceInterval,
     DrawPedCrowdInput, DrawPedestrianInput, Event, IntersectionSimState, ParkedCar, ParkingSpot,
-    PedCrowdLocation, PedestrianID, PersonID, Scheduler, SidewalkPOI, SidewalkSpot, TimeInterval,
-    TransitSimState, TripID, TripManager, UnzoomedAgent,
+    PedCrowdLocation, PedestrianID, PersonID, Scheduler, SidewalkPOI, SidewalkSpot, SimOptions,
+    TimeInterval, TransitSimState, TripID, TripManager, UnzoomedAgent,
 };
 
 const TIME_TO_START_BIKING: Duration = Duration::const_seconds(30.0);
 const TIME_TO_FINISH_BIKING: Duration = Duration::const_seconds(45.0);
 
 /// Simulates pedestrians. Unlike vehicles, pedestrians can move bidirectionally on sidewalks and
-/// just "ghost" through each other. There's no queueing or slowdown when many people are
-/// overlapping. They're simply grouped together into a DrawPedCrowdInput for rendering.
+/// just "ghost" through each other. There's no queueing when many people are overlapping. They're
+/// simply grouped together into a DrawPedCrowdInput for rendering. If `pedestrian_crowding` is
+/// enabled, people starting to walk along a crowded sidewalk slow down.
 #[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct WalkingSimState {
     peds: FixedMap<PedestrianID, Pedestrian>,
@@ -32,14 +33,16 @@ pub(crate) struct WalkingSimState {
     )]
     peds_per_traversable: MultiMap<Traversable, PedestrianID>,
     events: Vec<Event>,
+    crowding: bool,
 }
 
 impl WalkingSimState {
-    pub fn new() -> WalkingSimState {
+    pub fn new(opts: &SimOptions) -> WalkingSimState {
         WalkingSimState {
             peds: FixedMap::new(),
             peds_per_traversable: MultiMap::new(),
             events: Vec::new(),
+            crowding: opts.pedestrian_crowding,
         }
     }
 
@@ -92,7 +95,12 @@ impl WalkingSimState {
                 Line::must_new(driving_pos.pt(map), params.start.sidewalk_pos.pt(map)),
                 TimeInterval::new(now, now + TIME_TO_FINISH_BIKING),
             ),
-            _ => ped.crossing_state(params.start.sidewalk_pos.dist_along(), now, map),
+            _ => ped.crossing_state(
+                params.start.sidewalk_pos.dist_along(),
+                now,
+                map,
+                self.crowd(),
+            ),
         };
 
         scheduler.push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
@@ -103,6 +111,25 @@ impl WalkingSimState {
         );
     }
 
+    fn crowd(&self) -> Option<&MultiMap<Traversable, PedestrianID>> {
+        if self.crowding {
+            Some(&self.peds_per_traversable)
+        } else {
+            None
+        }
+    }
+
+    /// The current density of pedestrians on every occupied sidewalk, in people per square meter.
+    pub fn get_sidewalk_densities(&self, map: &Map) -> BTreeMap<LaneID, f64> {
+        let mut results = BTreeMap::new();
+        for (on, peds) in self.peds_per_traversable.borrow() {
+            if let Traversable::Lane(l) = on {
+                results.insert(*l, (peds.len() as f64) / sidewalk_area(map.get_l(*l)));
+            }
+        }
+        results
+    }
+
     pub fn get_draw_ped(
         &self,
         id: PedestrianID,
@@ -128,6 +155,11 @@ impl WalkingSimState {
         transit: &mut TransitSimState,
     ) {
         let mut ped = self.peds.get_mut(&id).unwrap();
+        let crowd = if self.crowding {
+            Some(&self.peds_per_traversable)
+        } else {
+            None
+        };
         match ped.state {
             PedState::Crossing(ref dist_int, _) => {
                 if ped.path.is_last_step() {
@@ -185,6 +217,20 @@ impl WalkingSimState {
                                 self.peds.remove(&id);
                             }
                         }
+                        SidewalkPOI::GuidewayStation(guideway, station) => {
+                            self.peds_per_traversable
+                                .remove(ped.path.current_step().as_traversable(), ped.id);
+                            trips.ped_reached_guideway_station(
+                                now,
+                                ped.id,
+                                guideway,
+                                station,
+                                ped.total_blocked_time,
+                                ped.path.total_length(),
+                                ctx,
+                            );
+                            self.peds.remove(&id);
+                        }
                         SidewalkPOI::Border(i) => {
                             self.peds_per_traversable
                                 .remove(ped.path.current_step().as_traversable(), ped.id);
@@ -230,6 +276,7 @@ impl WalkingSimState {
                         ctx.map,
                         ctx.intersections,
                         &mut self.peds_per_traversable,
+                        self.crowding,
                         &mut self.events,
                         ctx.scheduler,
                     ) {
@@ -247,6 +294,7 @@ impl WalkingSimState {
                     ctx.map,
                     ctx.intersections,
                     &mut self.peds_per_traversable,
+                    self.crowding,
                     &mut self.events,
                     ctx.scheduler,
                 ) {
@@ -262,8 +310,12 @@ impl WalkingSimState {
                 }
             }
             PedState::LeavingBuilding(b, _) => {
-                ped.state =
-                    ped.crossing_state(ctx.map.get_b(b).sidewalk_pos.dist_along(), now, ctx.map);
+                ped.state = ped.crossing_state(
+                    ctx.map.get_b(b).sidewalk_pos.dist_along(),
+                    now,
+                    ctx.map,
+                    crowd,
+                );
                 ctx.scheduler
                     .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
             }
@@ -281,8 +333,12 @@ impl WalkingSimState {
                 self.peds.remove(&id);
             }
             PedState::LeavingParkingLot(pl, _) => {
-                ped.state =
-                    ped.crossing_state(ctx.map.get_pl(pl).sidewalk_pos.dist_along(), now, ctx.map);
+                ped.state = ped.crossing_state(
+                    ctx.map.get_pl(pl).sidewalk_pos.dist_along(),
+                    now,
+                    ctx.map,
+                    crowd,
+                );
                 ctx.scheduler
                     .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
             }
@@ -316,7 +372,7 @@ impl WalkingSimState {
                 self.peds.remove(&id);
             }
             PedState::FinishingBiking(ref spot, _, _) => {
-                ped.state = ped.crossing_state(spot.sidewalk_pos.dist_along(), now, ctx.map);
+                ped.state = ped.crossing_state(spot.sidewalk_pos.dist_along(), now, ctx.map, crowd);
                 ctx.scheduler
                     .push(ped.state.get_end_time(), Command::UpdatePed(ped.id));
             }
@@ -556,7 +612,7 @@ impl WalkingSimState {
                 SidewalkPOI::ParkingSpot(_) | SidewalkPOI::DeferredParkingSpot => {
                     cnts.walking_to_from_car += 1;
                 }
-                SidewalkPOI::BusStop(_) => {
+                SidewalkPOI::BusStop(_) | SidewalkPOI::GuidewayStation(_, _) => {
                     cnts.walking_to_from_transit += 1;
                 }
                 SidewalkPOI::BikeRack(_) => {
@@ -566,7 +622,7 @@ impl WalkingSimState {
                     SidewalkPOI::ParkingSpot(_) | SidewalkPOI::DeferredParkingSpot => {
                         cnts.walking_to_from_car += 1;
                     }
-                    SidewalkPOI::BusStop(_) => {
+                    SidewalkPOI::BusStop(_) | SidewalkPOI::GuidewayStation(_, _) => {
                         cnts.walking_to_from_transit += 1;
                     }
                     SidewalkPOI::BikeRack(_) => {
@@ -598,7 +654,14 @@ struct Pedestrian {
 }
 
 impl Pedestrian {
-    fn crossing_state(&self, start_dist: Distance, start_time: Time, map: &Map) -> PedState {
+    /// If `crowd` is specified, slow down based on how many people are on the current sidewalk.
+    fn crossing_state(
+        &self,
+        start_dist: Distance,
+        start_time: Time,
+        map: &Map,
+        crowd: Option<&MultiMap<Traversable, PedestrianID>>,
+    ) -> PedState {
         let end_dist = if self.path.is_last_step() {
             self.goal.sidewalk_pos.dist_along()
         } else {
@@ -609,8 +672,19 @@ impl Pedestrian {
                 PathStep::Turn(t) => map.get_t(t).geom.length(),
             }
         };
+        let speed = match (crowd, self.path.current_step()) {
+            (Some(peds), PathStep::Lane(l)) | (Some(peds), PathStep::ContraflowLane(l)) => {
+                let others = peds
+                    .get(Traversable::Lane(l))
+                    .iter()
+                    .filter(|p| **p != self.id)
+                    .count();
+                self.speed * crowding_factor(others + 1, map.get_l(l))
+            }
+            _ => self.speed,
+        };
         let dist_int = DistanceInterval::new_walking(start_dist, end_dist);
-        let time_int = TimeInterval::new(start_time, start_time + dist_int.length() / self.speed);
+        let time_int = TimeInterval::new(start_time, start_time + dist_int.length() / speed);
         PedState::Crossing(dist_int, time_int)
     }
 
@@ -745,6 +819,7 @@ impl Pedestrian {
         map: &Map,
         intersections: &mut IntersectionSimState,
         peds_per_traversable: &mut MultiMap<Traversable, PedestrianID>,
+        crowding: bool,
         events: &mut Vec<Event>,
         scheduler: &mut Scheduler,
     ) -> bool {
@@ -769,7 +844,16 @@ impl Pedestrian {
             PathStep::ContraflowLane(l) => map.get_l(l).length(),
             PathStep::Turn(_) => Distance::ZERO,
         };
-        self.state = self.crossing_state(start_dist, now, map);
+        self.state = self.crossing_state(
+            start_dist,
+            now,
+            map,
+            if crowding {
+                Some(peds_per_traversable)
+            } else {
+                None
+            },
+        );
         peds_per_traversable.insert(self.path.current_step().as_traversable(), self.id);
         events.push(Event::AgentEntersTraversable(
             AgentID::Pedestrian(self.id),
@@ -818,6 +902,22 @@ impl PedState {
     }
 }
 
+fn sidewalk_area(lane: &Lane) -> f64 {
+    (lane.length() * lane.width.inner_meters())
+        .inner_meters()
+        .max(1.0)
+}
+
+/// A multiplier for walking speed on a sidewalk with this many people, using Weidmann's
+/// fundamental diagram for pedestrian flow.
+fn crowding_factor(num_peds: usize, lane: &Lane) -> f64 {
+    // People per square meter when everybody is stuck
+    const JAM_DENSITY: f64 = 5.4;
+    let density = (num_peds as f64) / sidewalk_area(lane);
+    // Never totally stop
+    (1.0 - (-1.913 * (1.0 / density - 1.0 / JAM_DENSITY)).exp()).max(0.1)
+}
+
 // The crowds returned here may have low/high values extending up to radius past the real geometry.
 fn find_crowds(
     input: Vec<(PedestrianID, Distance)>,
@@ -0,0 +1,607 @@
+//! Microtransit is a demand-responsive shuttle service covering one zone of the map. Somebody
+//! requesting a ride-hail trip that starts and ends inside the zone gets picked up by a shuttle
+//! instead, and might share it with other passengers along the way.
+//!
+//! Each shuttle has a list of upcoming stops to pick up or drop off passengers. A new request is
+//! inserted into whichever shuttle's stops it delays everybody the least, as long as the shuttle
+//! never carries more than its capacity and nobody's ride takes longer than `max_delay` times the
+//! direct ride. If no shuttle can fit the request in, it waits until somebody is dropped off. Times
+//! are estimated as the crow flies; finding real routes for every possible insertion would be too
+//! expensive. Idle shuttles pull over at the end of the lane and leave the road.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Distance, Duration, LonLat, Polygon, Pt2D, Ring, Speed, Time};
+use map_model::{BuildingID, LaneID, Map, Path, PathConstraints, PathRequest, Position};
+
+use crate::sim::Ctx;
+use crate::{
+    CarID, Command, CreateCar, RideHailRequest, Router, SimOptions, TripID, TripManager, Vehicle,
+};
+
+const BOARDING_TIME: Duration = Duration::const_seconds(30.0);
+const ALIGHTING_TIME: Duration = Duration::const_seconds(20.0);
+/// Used to estimate how long a shuttle takes between two points, as the crow flies
+const ESTIMATED_SPEED: Speed = Speed::const_meters_per_second(5.0);
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Shuttle {
+    vehicle: Vehicle,
+    state: ShuttleState,
+    /// Where the current path ends, or where the shuttle is pulled over
+    pos: Position,
+    /// Upcoming pickups and dropoffs, in order
+    stops: Vec<Stop>,
+    /// Everybody with an upcoming stop, whether they've been picked up yet or not
+    passengers: Vec<Passenger>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+enum ShuttleState {
+    /// Idle and off the road
+    PulledOver,
+    /// Idle, driving to the end of the lane to pull over
+    PullingOver,
+    /// Driving to the first stop. The route is already fixed, so new stops can't go before it.
+    ToStop,
+    /// Stopped for passengers to get in or out
+    Dwelling,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Stop {
+    trip: TripID,
+    pickup: bool,
+    pos: Position,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Passenger {
+    req: RideHailRequest,
+    /// The estimated time to get in and ride directly to the dropoff
+    direct: Duration,
+    boarded_at: Option<Time>,
+    /// How far the passenger has been carried so far
+    dist: Distance,
+}
+
+/// Manages the microtransit shuttles and batches requests onto them.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub(crate) struct MicrotransitSimState {
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    shuttles: BTreeMap<CarID, Shuttle>,
+    /// If None, the shuttles serve the whole map
+    zone: Option<Polygon>,
+    capacity: usize,
+    max_delay: f64,
+    /// Requests no shuttle could fit in yet, oldest first
+    waiting: Vec<Passenger>,
+    finished: Vec<MicrotransitPassenger>,
+}
+
+/// One passenger's finished ride on a shuttle.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MicrotransitPassenger {
+    pub trip: TripID,
+    pub requested_at: Time,
+    /// From requesting a ride to the shuttle arriving
+    pub wait: Duration,
+    /// From the shuttle arriving to getting out, including any detours to serve other passengers
+    pub ride: Duration,
+    /// The estimated time to ride directly, without any detours
+    pub direct: Duration,
+}
+
+/// A summary of the microtransit service so far.
+pub struct MicrotransitStats {
+    pub shuttles: usize,
+    pub idle_shuttles: usize,
+    /// Requests still waiting for a shuttle to fit them in
+    pub unassigned_requests: usize,
+    pub passengers: Vec<MicrotransitPassenger>,
+}
+
+impl MicrotransitSimState {
+    pub fn new(opts: &SimOptions, map: &Map) -> MicrotransitSimState {
+        MicrotransitSimState {
+            shuttles: BTreeMap::new(),
+            zone: opts
+                .microtransit_zone
+                .as_ref()
+                .map(|boundary| zone_polygon(boundary, map)),
+            capacity: opts.microtransit_capacity,
+            max_delay: opts.microtransit_max_delay,
+            waiting: Vec::new(),
+            finished: Vec::new(),
+        }
+    }
+
+    pub fn in_zone(&self, pt: Pt2D) -> bool {
+        self.zone
+            .as_ref()
+            .map(|zone| zone.contains_pt(pt))
+            .unwrap_or(true)
+    }
+
+    /// Adds an idle shuttle, pulled over at the end of a lane.
+    pub fn add_shuttle(&mut self, vehicle: Vehicle, l: LaneID, map: &Map) {
+        self.shuttles.insert(
+            vehicle.id,
+            Shuttle {
+                vehicle,
+                state: ShuttleState::PulledOver,
+                pos: Position::end(l, map),
+                stops: Vec::new(),
+                passengers: Vec::new(),
+            },
+        );
+    }
+
+    pub fn is_shuttle(&self, id: CarID) -> bool {
+        self.shuttles.contains_key(&id)
+    }
+
+    /// Should the shuttles serve this ride-hail request, instead of the regular fleet?
+    pub fn serves(&self, req: &RideHailRequest, map: &Map) -> bool {
+        !self.shuttles.is_empty()
+            && self.in_zone(map.get_b(req.pickup).polygon.center())
+            && self.in_zone(map.get_b(req.dropoff).polygon.center())
+    }
+
+    /// Somebody inside the zone wants a ride. If a shuttle can't stop at either building, the trip
+    /// is cancelled.
+    pub fn request(
+        &mut self,
+        now: Time,
+        req: RideHailRequest,
+        trips: &mut TripManager,
+        ctx: &mut Ctx,
+    ) {
+        let (pickup, dropoff) = match (curb(req.pickup, ctx.map), curb(req.dropoff, ctx.map)) {
+            (Some(pickup), Some(dropoff)) => (pickup, dropoff),
+            _ => {
+                trips.cancel_trip(
+                    now,
+                    req.trip,
+                    format!("a shuttle can't stop at {} or {}", req.pickup, req.dropoff),
+                    None,
+                    ctx,
+                );
+                return;
+            }
+        };
+        let passenger = Passenger {
+            req,
+            direct: BOARDING_TIME + estimate(pickup, dropoff, ctx.map),
+            boarded_at: None,
+            dist: Distance::ZERO,
+        };
+        if let Some(passenger) = self.assign(now, passenger, trips, ctx) {
+            self.waiting.push(passenger);
+        }
+    }
+
+    /// Insert the passenger's pickup and dropoff into the shuttle where it costs the least extra
+    /// time. If no shuttle can fit them in, hands the passenger back.
+    fn assign(
+        &mut self,
+        now: Time,
+        passenger: Passenger,
+        trips: &mut TripManager,
+        ctx: &mut Ctx,
+    ) -> Option<Passenger> {
+        let pickup = curb(passenger.req.pickup, ctx.map).unwrap();
+        let dropoff = curb(passenger.req.dropoff, ctx.map).unwrap();
+        let mut best: Option<(CarID, usize, usize, Duration)> = None;
+        for (id, shuttle) in &self.shuttles {
+            if let Some((i, j, cost)) = shuttle.best_insertion(
+                now,
+                &passenger,
+                pickup,
+                dropoff,
+                self.capacity,
+                self.max_delay,
+                ctx.map,
+            ) {
+                if best.map(|(_, _, _, c)| cost < c).unwrap_or(true) {
+                    best = Some((*id, i, j, cost));
+                }
+            }
+        }
+        let (id, i, j) = match best {
+            Some((id, i, j, _)) => (id, i, j),
+            None => {
+                return Some(passenger);
+            }
+        };
+
+        let trip = passenger.req.trip;
+        let shuttle = self.shuttles.get_mut(&id).unwrap();
+        shuttle.stops.insert(
+            i,
+            Stop {
+                trip,
+                pickup: true,
+                pos: pickup,
+            },
+        );
+        shuttle.stops.insert(
+            j,
+            Stop {
+                trip,
+                pickup: false,
+                pos: dropoff,
+            },
+        );
+        shuttle.passengers.push(passenger);
+
+        if shuttle.state == ShuttleState::PulledOver {
+            // Get back on the road
+            let path_req = PathRequest {
+                start: shuttle.pos,
+                end: pickup,
+                constraints: PathConstraints::Car,
+            };
+            if let Some(path) = ctx.map.pathfind(path_req.clone()) {
+                let router = Router::ride_hail(id, path, pickup.dist_along());
+                ctx.scheduler.push(
+                    now,
+                    Command::SpawnCar(
+                        CreateCar {
+                            vehicle: shuttle.vehicle.clone(),
+                            router,
+                            req: path_req,
+                            start_dist: shuttle.pos.dist_along(),
+                            maybe_parked_car: None,
+                            trip_and_person: None,
+                            maybe_route: None,
+                        },
+                        true,
+                    ),
+                );
+                shuttle.pos = pickup;
+                shuttle.state = ShuttleState::ToStop;
+            } else {
+                self.abandon(
+                    now,
+                    id,
+                    trip,
+                    format!("no path for a shuttle: {}", path_req),
+                    trips,
+                    ctx,
+                );
+            }
+        }
+        None
+    }
+
+    /// Try to fit in waiting requests again, oldest first.
+    fn retry_waiting(&mut self, now: Time, trips: &mut TripManager, ctx: &mut Ctx) {
+        for passenger in std::mem::take(&mut self.waiting) {
+            if let Some(passenger) = self.assign(now, passenger, trips, ctx) {
+                self.waiting.push(passenger);
+            }
+        }
+    }
+
+    /// The shuttle can't serve somebody, so cancel their trip.
+    fn abandon(
+        &mut self,
+        now: Time,
+        id: CarID,
+        trip: TripID,
+        reason: String,
+        trips: &mut TripManager,
+        ctx: &mut Ctx,
+    ) {
+        let shuttle = self.shuttles.get_mut(&id).unwrap();
+        shuttle.stops.retain(|stop| stop.trip != trip);
+        shuttle.passengers.retain(|p| p.req.trip != trip);
+        trips.cancel_trip(now, trip, reason, None, ctx);
+    }
+
+    /// The shuttle reached the end of its path. If Some, the shuttle should stop for this long,
+    /// then call `vehicle_departing`. If None, the shuttle pulled over and should vanish from the
+    /// road.
+    pub fn vehicle_reached_end(
+        &mut self,
+        now: Time,
+        id: CarID,
+        pos: Position,
+        dist_driven: Distance,
+        trips: &mut TripManager,
+        ctx: &mut Ctx,
+    ) -> Option<Duration> {
+        let shuttle = self.shuttles.get_mut(&id).unwrap();
+        shuttle.pos = pos;
+        for p in &mut shuttle.passengers {
+            if p.boarded_at.is_some() {
+                p.dist += dist_driven;
+            }
+        }
+
+        match shuttle.state {
+            ShuttleState::PullingOver => {
+                if shuttle.stops.is_empty() {
+                    shuttle.state = ShuttleState::PulledOver;
+                    return None;
+                }
+                // Assigned somebody while pulling over, so find the way there after stopping
+                shuttle.state = ShuttleState::Dwelling;
+                return Some(Duration::ZERO);
+            }
+            ShuttleState::ToStop => {}
+            ShuttleState::PulledOver | ShuttleState::Dwelling => unreachable!(),
+        }
+
+        shuttle.state = ShuttleState::Dwelling;
+        let mut dwell = Duration::ZERO;
+        let mut dropped_off = false;
+        while !shuttle.stops.is_empty() && shuttle.stops[0].pos == pos {
+            let stop = shuttle.stops.remove(0);
+            let idx = shuttle
+                .passengers
+                .iter()
+                .position(|p| p.req.trip == stop.trip)
+                .unwrap();
+            if stop.pickup {
+                let p = &mut shuttle.passengers[idx];
+                p.boarded_at = Some(now);
+                let dropoff = shuttle
+                    .stops
+                    .iter()
+                    .find(|s| s.trip == stop.trip)
+                    .unwrap()
+                    .pos;
+                trips.ride_hail_passenger_boarded(
+                    stop.trip,
+                    id,
+                    now - p.req.requested_at,
+                    PathRequest {
+                        start: pos,
+                        end: dropoff,
+                        constraints: PathConstraints::Car,
+                    },
+                );
+                dwell = dwell.max(BOARDING_TIME);
+            } else {
+                let p = shuttle.passengers.remove(idx);
+                let boarded_at = p.boarded_at.unwrap();
+                trips.ride_hail_passenger_alighted(now, stop.trip, id, p.dist, ctx);
+                self.finished.push(MicrotransitPassenger {
+                    trip: stop.trip,
+                    requested_at: p.req.requested_at,
+                    wait: boarded_at - p.req.requested_at,
+                    ride: now - boarded_at,
+                    direct: p.direct,
+                });
+                dwell = dwell.max(ALIGHTING_TIME);
+                dropped_off = true;
+            }
+        }
+        if dropped_off {
+            self.retry_waiting(now, trips, ctx);
+        }
+        Some(dwell)
+    }
+
+    /// The shuttle is done stopping at `pos`, so head to the next stop, or pull over if there
+    /// isn't one.
+    pub fn vehicle_departing(
+        &mut self,
+        now: Time,
+        id: CarID,
+        pos: Position,
+        trips: &mut TripManager,
+        ctx: &mut Ctx,
+    ) -> Router {
+        loop {
+            let shuttle = self.shuttles.get_mut(&id).unwrap();
+            if shuttle.stops.is_empty() {
+                shuttle.pos = Position::end(pos.lane(), ctx.map);
+                shuttle.state = ShuttleState::PullingOver;
+                return Router::ride_hail(
+                    id,
+                    Path::one_step(pos.lane(), ctx.map),
+                    shuttle.pos.dist_along(),
+                );
+            }
+            let next = shuttle.stops[0].clone();
+            if let Some(path) = ctx.map.pathfind(PathRequest {
+                start: pos,
+                end: next.pos,
+                constraints: PathConstraints::Car,
+            }) {
+                shuttle.pos = next.pos;
+                shuttle.state = ShuttleState::ToStop;
+                return Router::ride_hail(id, path, next.pos.dist_along());
+            }
+            self.abandon(
+                now,
+                id,
+                next.trip,
+                format!("no path for a shuttle from {} to {}", pos, next.pos),
+                trips,
+                ctx,
+            );
+        }
+    }
+
+    /// Shuttles currently driving or stopped on the road
+    pub fn num_vehicles_on_road(&self) -> usize {
+        self.shuttles
+            .values()
+            .filter(|s| s.state != ShuttleState::PulledOver)
+            .count()
+    }
+
+    pub fn describe_vehicle(&self, id: CarID) -> Option<String> {
+        let shuttle = self.shuttles.get(&id)?;
+        let onboard = shuttle
+            .passengers
+            .iter()
+            .filter(|p| p.boarded_at.is_some())
+            .count();
+        Some(match shuttle.state {
+            ShuttleState::PulledOver => "Idle, pulled over".to_string(),
+            ShuttleState::PullingOver => "Idle, pulling over".to_string(),
+            ShuttleState::ToStop | ShuttleState::Dwelling => format!(
+                "Carrying {} of {} passengers, with {} stops to go",
+                onboard,
+                self.capacity,
+                shuttle.stops.len()
+            ),
+        })
+    }
+
+    pub fn get_stats(&self) -> MicrotransitStats {
+        MicrotransitStats {
+            shuttles: self.shuttles.len(),
+            idle_shuttles: self
+                .shuttles
+                .values()
+                .filter(|s| s.stops.is_empty())
+                .count(),
+            unassigned_requests: self.waiting.len(),
+            passengers: self.finished.clone(),
+        }
+    }
+}
+
+impl Shuttle {
+    /// Finds the cheapest place to insert a new passenger's pickup and dropoff. Returns the
+    /// indices to insert them at, one after the other, and the extra time it costs everybody.
+    fn best_insertion(
+        &self,
+        now: Time,
+        new: &Passenger,
+        pickup: Position,
+        dropoff: Position,
+        capacity: usize,
+        max_delay: f64,
+        map: &Map,
+    ) -> Option<(usize, usize, Duration)> {
+        let (cost_before, rides_before) = self.simulate(now, &self.stops, usize::MAX, map)?;
+        let first = if self.state == ShuttleState::ToStop {
+            1
+        } else {
+            0
+        };
+
+        let mut best = None;
+        for i in first..=self.stops.len() {
+            for j in (i + 1)..=(self.stops.len() + 1) {
+                let mut stops = self.stops.clone();
+                stops.insert(
+                    i,
+                    Stop {
+                        trip: new.req.trip,
+                        pickup: true,
+                        pos: pickup,
+                    },
+                );
+                stops.insert(
+                    j,
+                    Stop {
+                        trip: new.req.trip,
+                        pickup: false,
+                        pos: dropoff,
+                    },
+                );
+                let (cost, rides) = match self.simulate(now, &stops, capacity, map) {
+                    Some(result) => result,
+                    None => {
+                        continue;
+                    }
+                };
+                let ok = rides.iter().all(|(trip, ride)| {
+                    let limit = if *trip == new.req.trip {
+                        new.direct * max_delay
+                    } else {
+                        let p = self
+                            .passengers
+                            .iter()
+                            .find(|p| p.req.trip == *trip)
+                            .unwrap();
+                        // Estimates are rough, so don't refuse everybody just because somebody
+                        // is already running late
+                        (p.direct * max_delay).max(rides_before[trip])
+                    };
+                    *ride <= limit
+                });
+                let extra = cost - cost_before;
+                if ok && best.map(|(_, _, c)| extra < c).unwrap_or(true) {
+                    best = Some((i, j, extra));
+                }
+            }
+        }
+        best
+    }
+
+    /// Estimates how long each passenger's ride takes, and the total time until each of them is
+    /// dropped off, if the shuttle serves these stops in order. None if the shuttle would carry
+    /// more than its capacity.
+    fn simulate(
+        &self,
+        now: Time,
+        stops: &[Stop],
+        capacity: usize,
+        map: &Map,
+    ) -> Option<(Duration, BTreeMap<TripID, Duration>)> {
+        let mut boarded: BTreeMap<TripID, Time> = self
+            .passengers
+            .iter()
+            .filter_map(|p| Some((p.req.trip, p.boarded_at?)))
+            .collect();
+        let mut load = boarded.len();
+        let mut rides = BTreeMap::new();
+        let mut total = Duration::ZERO;
+
+        // A shuttle driving to its first stop is treated as already being there
+        let mut t = now;
+        let mut at = self.pos;
+        for stop in stops {
+            t += estimate(at, stop.pos, map);
+            at = stop.pos;
+            if stop.pickup {
+                load += 1;
+                if load > capacity {
+                    return None;
+                }
+                boarded.insert(stop.trip, t);
+                t += BOARDING_TIME;
+            } else {
+                load -= 1;
+                rides.insert(stop.trip, t - boarded[&stop.trip]);
+                total += t - now;
+                t += ALIGHTING_TIME;
+            }
+        }
+        Some((total, rides))
+    }
+}
+
+/// Where a shuttle stops to serve a building
+fn curb(b: BuildingID, map: &Map) -> Option<Position> {
+    map.get_b(b).driving_connection(map).map(|(pos, _)| pos)
+}
+
+fn estimate(from: Position, to: Position, map: &Map) -> Duration {
+    from.pt(map).dist_to(to.pt(map)) / ESTIMATED_SPEED
+}
+
+fn zone_polygon(boundary: &Vec<LonLat>, map: &Map) -> Polygon {
+    let mut pts: Vec<Pt2D> = map.get_gps_bounds().convert(boundary);
+    if pts.len() >= 3 && pts[0] != *pts.last().unwrap() {
+        pts.push(pts[0]);
+    }
+    Ring::new(pts)
+        .unwrap_or_else(|err| panic!("Bad --microtransit_zone: {}", err))
+        .to_polygon()
+}
@@ -5,7 +5,7 @@ use map_model::{IntersectionID, Map, PathStep, Position, Traversable};
 
 use crate::{
     AgentID, DrivingSimState, Event, IndividTrip, PersonSpec, Scenario, TripEndpoint, TripID,
-    TripManager, TripMode, TripPurpose, VehicleType,
+    TripManager, TripMode, TripPurpose, VehicleType, Weather,
 };
 
 /// Records trips beginning and ending at a specified set of intersections. This can be used to
@@ -92,6 +92,11 @@ impl TrafficRecorder {
             map_name: map.get_name().clone(),
             people,
             only_seed_buses: None,
+            extra_days: 0,
+            households: Vec::new(),
+            weather: Weather::Clear,
+            driver_mix: Vec::new(),
+            lane_closures: Vec::new(),
         }
         .save();
     }
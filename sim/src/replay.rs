@@ -0,0 +1,230 @@
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use serde::{Deserialize, Serialize};
+
+use abstutil::{MapName, Timer};
+use geom::{Distance, Duration, LonLat, Time};
+use map_model::Map;
+
+use crate::{AlertHandler, Command, CommandType, Scenario, Sim, SimOptions};
+
+/// A compact log of everything needed to reproduce a simulation run exactly, even on another
+/// machine. The only randomness in the simulation comes from one XorShiftRng seeded at the
+/// beginning (all other RNGs are forked from it), so recording the seed captures every RNG draw.
+/// Every command the scheduler hands out is also recorded, so that a replay can detect the exact
+/// moment it diverges from the original run.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayLog {
+    pub map_name: MapName,
+    pub edits_name: String,
+    pub scenario: Scenario,
+    pub rng_seed: u64,
+    opts: ReplayOptions,
+    commands: Vec<(Time, CommandType)>,
+}
+
+/// The subset of SimOptions that affects simulation results.
+#[derive(Clone, Serialize, Deserialize)]
+struct ReplayOptions {
+    run_name: String,
+    use_freeform_policy_everywhere: bool,
+    dont_block_the_box: bool,
+    recalc_lanechanging: bool,
+    lane_change_lookahead: Distance,
+    break_turn_conflict_cycles: bool,
+    handle_uber_turns: bool,
+    enable_pandemic_model: bool,
+    infinite_parking: bool,
+    disable_turn_conflicts: bool,
+    cancel_drivers_delay_threshold: Option<Duration>,
+    delay_trips_instead_of_cancelling: Option<Duration>,
+    skip_analytics: bool,
+    pedestrian_crowding: bool,
+    actuated_signals: bool,
+    exact_end_dist_wakeups: bool,
+    cruise_for_parking: bool,
+    value_of_time: f64,
+    ride_hail_fleet: usize,
+    ride_hail_pull_over_when_idle: bool,
+    live_congestion_routing: Option<Duration>,
+    delivery_dwell_time: Duration,
+    microtransit_shuttles: usize,
+    microtransit_capacity: usize,
+    microtransit_max_delay: f64,
+    microtransit_zone: Option<Vec<LonLat>>,
+}
+
+impl ReplayLog {
+    /// Start a new log. The scenario must be the one about to be instantiated with an RNG seeded
+    /// from `rng_seed`.
+    pub fn new(map: &Map, scenario: &Scenario, rng_seed: u64, opts: &SimOptions) -> ReplayLog {
+        ReplayLog {
+            map_name: map.get_name().clone(),
+            edits_name: map.get_edits().edits_name.clone(),
+            scenario: scenario.clone(),
+            rng_seed,
+            opts: ReplayOptions {
+                run_name: opts.run_name.clone(),
+                use_freeform_policy_everywhere: opts.use_freeform_policy_everywhere,
+                dont_block_the_box: opts.dont_block_the_box,
+                recalc_lanechanging: opts.recalc_lanechanging,
+                lane_change_lookahead: opts.lane_change_lookahead,
+                break_turn_conflict_cycles: opts.break_turn_conflict_cycles,
+                handle_uber_turns: opts.handle_uber_turns,
+                enable_pandemic_model: opts.enable_pandemic_model.is_some(),
+                infinite_parking: opts.infinite_parking,
+                disable_turn_conflicts: opts.disable_turn_conflicts,
+                cancel_drivers_delay_threshold: opts.cancel_drivers_delay_threshold,
+                delay_trips_instead_of_cancelling: opts.delay_trips_instead_of_cancelling,
+                skip_analytics: opts.skip_analytics,
+                pedestrian_crowding: opts.pedestrian_crowding,
+                actuated_signals: opts.actuated_signals,
+                exact_end_dist_wakeups: opts.exact_end_dist_wakeups,
+                cruise_for_parking: opts.cruise_for_parking,
+                value_of_time: opts.value_of_time,
+                ride_hail_fleet: opts.ride_hail_fleet,
+                ride_hail_pull_over_when_idle: opts.ride_hail_pull_over_when_idle,
+                live_congestion_routing: opts.live_congestion_routing,
+                delivery_dwell_time: opts.delivery_dwell_time,
+                microtransit_shuttles: opts.microtransit_shuttles,
+                microtransit_capacity: opts.microtransit_capacity,
+                microtransit_max_delay: opts.microtransit_max_delay,
+                microtransit_zone: opts.microtransit_zone.clone(),
+            },
+            commands: Vec::new(),
+        }
+    }
+
+    /// How many scheduler commands have been recorded.
+    pub fn num_commands(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// The time of the last recorded command, if any.
+    pub fn end_time(&self) -> Option<Time> {
+        self.commands.last().map(|(t, _)| *t)
+    }
+
+    pub fn save(&self, path: String) {
+        abstutil::write_binary(path, self);
+    }
+
+    pub fn load(path: String, timer: &mut Timer) -> Result<ReplayLog, String> {
+        abstutil::maybe_read_binary(path, timer)
+    }
+
+    fn to_sim_options(&self) -> SimOptions {
+        SimOptions {
+            run_name: self.opts.run_name.clone(),
+            use_freeform_policy_everywhere: self.opts.use_freeform_policy_everywhere,
+            dont_block_the_box: self.opts.dont_block_the_box,
+            recalc_lanechanging: self.opts.recalc_lanechanging,
+            lane_change_lookahead: self.opts.lane_change_lookahead,
+            break_turn_conflict_cycles: self.opts.break_turn_conflict_cycles,
+            handle_uber_turns: self.opts.handle_uber_turns,
+            // Matches SimOptions::from_args
+            enable_pandemic_model: if self.opts.enable_pandemic_model {
+                Some(XorShiftRng::seed_from_u64(self.rng_seed))
+            } else {
+                None
+            },
+            alerts: AlertHandler::Print,
+            infinite_parking: self.opts.infinite_parking,
+            disable_turn_conflicts: self.opts.disable_turn_conflicts,
+            cancel_drivers_delay_threshold: self.opts.cancel_drivers_delay_threshold,
+            delay_trips_instead_of_cancelling: self.opts.delay_trips_instead_of_cancelling,
+            skip_analytics: self.opts.skip_analytics,
+            pedestrian_crowding: self.opts.pedestrian_crowding,
+            actuated_signals: self.opts.actuated_signals,
+            exact_end_dist_wakeups: self.opts.exact_end_dist_wakeups,
+            cruise_for_parking: self.opts.cruise_for_parking,
+            value_of_time: self.opts.value_of_time,
+            ride_hail_fleet: self.opts.ride_hail_fleet,
+            ride_hail_pull_over_when_idle: self.opts.ride_hail_pull_over_when_idle,
+            live_congestion_routing: self.opts.live_congestion_routing,
+            delivery_dwell_time: self.opts.delivery_dwell_time,
+            microtransit_shuttles: self.opts.microtransit_shuttles,
+            microtransit_capacity: self.opts.microtransit_capacity,
+            microtransit_max_delay: self.opts.microtransit_max_delay,
+            microtransit_zone: self.opts.microtransit_zone.clone(),
+        }
+    }
+}
+
+/// Attached to a Sim to either record a new ReplayLog or check a run against an existing one.
+#[derive(Clone)]
+pub(crate) enum ReplayState {
+    Recording(ReplayLog),
+    Verifying { log: ReplayLog, idx: usize },
+}
+
+impl ReplayState {
+    pub fn handle_cmd(&mut self, time: Time, cmd: &Command) {
+        match self {
+            ReplayState::Recording(ref mut log) => {
+                log.commands.push((time, cmd.to_type()));
+            }
+            ReplayState::Verifying {
+                ref log,
+                ref mut idx,
+            } => {
+                let actual = (time, cmd.to_type());
+                match log.commands.get(*idx) {
+                    Some(expected) => {
+                        if expected != &actual {
+                            panic!(
+                                "Replay diverged at command #{}: the log has {:?} at {}, but the \
+                                 sim is doing {:?} at {}",
+                                idx, expected.1, expected.0, actual.1, actual.0
+                            );
+                        }
+                    }
+                    None => {
+                        // The original run just stopped earlier; that's fine.
+                    }
+                }
+                *idx += 1;
+            }
+        }
+    }
+}
+
+// Replaying
+impl Sim {
+    /// Start recording a ReplayLog. Call this before running the simulation.
+    pub fn record_replay(&mut self, log: ReplayLog) {
+        assert!(self.replay.is_none());
+        self.replay = Some(ReplayState::Recording(log));
+    }
+
+    /// Stop recording and return the log, if one was being recorded.
+    pub fn take_replay_log(&mut self) -> Option<ReplayLog> {
+        match self.replay.take()? {
+            ReplayState::Recording(log) => Some(log),
+            ReplayState::Verifying { log, idx } => {
+                self.replay = Some(ReplayState::Verifying { log, idx });
+                None
+            }
+        }
+    }
+
+    /// Reproduce a previously recorded run. The map must match the one the log was recorded on,
+    /// including edits. As the returned Sim runs, every command is checked against the log, and
+    /// the simulation will crash at the first divergence.
+    pub fn replay(log: ReplayLog, map: &Map, timer: &mut Timer) -> Sim {
+        assert_eq!(&log.map_name, map.get_name());
+        if log.edits_name != map.get_edits().edits_name {
+            panic!(
+                "Replay log was recorded with edits {}, but the map has {}",
+                log.edits_name,
+                map.get_edits().edits_name
+            );
+        }
+
+        let mut sim = Sim::new(map, log.to_sim_options(), timer);
+        let mut rng = XorShiftRng::seed_from_u64(log.rng_seed);
+        log.scenario.instantiate(&mut sim, map, &mut rng, timer);
+        sim.replay = Some(ReplayState::Verifying { log, idx: 0 });
+        sim
+    }
+}
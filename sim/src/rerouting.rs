@@ -0,0 +1,160 @@
+//! Normally drivers pick a route before they start, assuming every road moves at the speed limit,
+//! and stick with it. With live rerouting, the simulation measures how long cars actually take to
+//! cross each lane. Every few minutes, the extra time beyond free-flow is averaged per lane, and
+//! drivers whose remaining route crosses a congested lane look for a faster path, using A* with
+//! those delays added in.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Duration, Time};
+use map_model::{LaneID, Map, Traversable};
+
+use crate::{AgentID, CarID, Command, DrivingSimState, Event, Scheduler, SimOptions, VehicleType};
+
+/// Smaller delays are mostly just waiting at a traffic signal, which isn't worth avoiding.
+const MIN_DELAY: Duration = Duration::const_seconds(30.0);
+/// Forget about cars that entered a lane this long ago. They probably parked or left the map.
+const FORGET_AFTER: Duration = Duration::const_seconds(3600.0);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct ReroutingSimState {
+    /// If None, drivers never reroute
+    refresh_every: Option<Duration>,
+    /// The lane each car is crossing, and when it started
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    entered_lane: BTreeMap<CarID, (LaneID, Time)>,
+    /// Since the last refresh, the total extra time spent crossing each lane, and the number of
+    /// cars
+    observed: BTreeMap<LaneID, (Duration, usize)>,
+    /// As of the last refresh, the average extra time to cross each congested lane
+    delays: BTreeMap<LaneID, Duration>,
+
+    /// For each refresh, the number of cars that looked for a new route, and how many found one
+    history: Vec<(Time, usize, usize)>,
+}
+
+/// A summary of rerouting around congestion so far.
+pub struct ReroutingStats {
+    pub enabled: bool,
+    /// Lanes currently considered congested
+    pub congested_lanes: usize,
+    /// Cars that looked for a new route, because their remaining route crossed a congested lane
+    pub checked: usize,
+    /// Cars that switched to a different route
+    pub rerouted: usize,
+    /// For each refresh, the number of cars checked and rerouted
+    pub history: Vec<(Time, usize, usize)>,
+}
+
+impl ReroutingSimState {
+    pub fn new(opts: &SimOptions) -> ReroutingSimState {
+        ReroutingSimState {
+            refresh_every: opts.live_congestion_routing,
+            entered_lane: BTreeMap::new(),
+            observed: BTreeMap::new(),
+            delays: BTreeMap::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn schedule_first_refresh(&self, now: Time, scheduler: &mut Scheduler) {
+        if let Some(dt) = self.refresh_every {
+            scheduler.push(now + dt, Command::RefreshTravelTimes);
+        }
+    }
+
+    pub fn handle_event(&mut self, now: Time, ev: &Event, map: &Map) {
+        if self.refresh_every.is_none() {
+            return;
+        }
+        if let Event::AgentEntersTraversable(AgentID::Car(car), on, _) = ev {
+            // Bikes and buses are slower than the speed limit anyway
+            if car.1 != VehicleType::Car {
+                return;
+            }
+            match on {
+                Traversable::Lane(l) => {
+                    self.entered_lane.insert(*car, (*l, now));
+                }
+                Traversable::Turn(t) => {
+                    if let Some((l, start)) = self.entered_lane.remove(car) {
+                        if l == t.src {
+                            self.observe(l, now - start, now, map);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn observe(&mut self, l: LaneID, time_spent: Duration, now: Time, map: &Map) {
+        let delay = (time_spent - free_flow_time(l, now, map)).max(Duration::ZERO);
+        let entry = self.observed.entry(l).or_insert((Duration::ZERO, 0));
+        entry.0 += delay;
+        entry.1 += 1;
+    }
+
+    /// Average the delays measured since the last refresh, then let drivers heading into
+    /// congestion look for a faster route. Like other drivers, they weigh tolls against their
+    /// value of time, in dollars per hour.
+    pub fn refresh(
+        &mut self,
+        now: Time,
+        value_of_time: f64,
+        driving: &mut DrivingSimState,
+        scheduler: &mut Scheduler,
+        map: &Map,
+    ) {
+        // Cars stuck in a lane haven't left it yet, but still count how long they've waited so
+        // far. Otherwise a completely jammed lane would look free. Cars that just started crossing
+        // don't tell us anything yet.
+        self.entered_lane
+            .retain(|_, (_, start)| now - *start < FORGET_AFTER);
+        let stuck: Vec<(LaneID, Duration)> = self
+            .entered_lane
+            .values()
+            .map(|(l, start)| (*l, now - *start))
+            .filter(|(l, time_spent)| *time_spent > free_flow_time(*l, now, map))
+            .collect();
+        for (l, time_spent) in stuck {
+            self.observe(l, time_spent, now, map);
+        }
+
+        self.delays.clear();
+        for (l, (total, count)) in std::mem::replace(&mut self.observed, BTreeMap::new()) {
+            let delay = total / (count as f64);
+            if delay >= MIN_DELAY {
+                self.delays.insert(l, delay);
+            }
+        }
+
+        let (checked, rerouted) =
+            driving.reroute_around_congestion(now, value_of_time, &self.delays, map);
+        self.history.push((now, checked, rerouted));
+
+        scheduler.push(
+            now + self.refresh_every.unwrap(),
+            Command::RefreshTravelTimes,
+        );
+    }
+
+    pub fn get_stats(&self) -> ReroutingStats {
+        ReroutingStats {
+            enabled: self.refresh_every.is_some(),
+            congested_lanes: self.delays.len(),
+            checked: self.history.iter().map(|(_, x, _)| x).sum(),
+            rerouted: self.history.iter().map(|(_, _, x)| x).sum(),
+            history: self.history.clone(),
+        }
+    }
+}
+
+fn free_flow_time(l: LaneID, now: Time, map: &Map) -> Duration {
+    map.get_l(l).length() / map.get_parent(l).speed_limit_at(now)
+}
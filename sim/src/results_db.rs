@@ -0,0 +1,240 @@
+//! An optional SQLite sink for simulation results, so studies with many runs can query everything
+//! at once with SQL. Every run appends to the same file, keyed by a row in the `runs` table:
+//!
+//! - runs: id, name, map, edits, start_time, end_time (NULL until the run is finished)
+//! - events: run, then the same columns as the CSV event log (see `EVENT_LOG_HEADER`)
+//! - trips: run, trip, person, mode, purpose, departure, end_time, duration, cancelled
+//! - road_thruput, intersection_thruput: run, road or intersection, agent_type, hour, count
+//!
+//! Times and durations are in seconds. Only compiled with the `sqlite` feature.
+
+use std::collections::BTreeMap;
+
+use rusqlite::{params, Connection};
+
+use geom::Time;
+use map_model::Map;
+
+use crate::event_log::Row;
+use crate::{Analytics, Event, PersonID, TripID, TripInfo};
+
+/// How many events to buffer before inserting them.
+const FLUSH_EVERY: usize = 10_000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    map TEXT NOT NULL,
+    edits TEXT NOT NULL,
+    start_time REAL NOT NULL,
+    end_time REAL
+);
+CREATE TABLE IF NOT EXISTS events (
+    run INTEGER NOT NULL REFERENCES runs(id),
+    time REAL NOT NULL,
+    event TEXT NOT NULL,
+    trip INTEGER,
+    person INTEGER,
+    agent_type TEXT,
+    agent_id INTEGER,
+    mode TEXT,
+    location_type TEXT,
+    location_id TEXT,
+    duration REAL,
+    details TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS events_by_type ON events (run, event);
+CREATE INDEX IF NOT EXISTS events_by_trip ON events (run, trip);
+CREATE TABLE IF NOT EXISTS trips (
+    run INTEGER NOT NULL REFERENCES runs(id),
+    trip INTEGER NOT NULL,
+    person INTEGER,
+    mode TEXT NOT NULL,
+    purpose TEXT NOT NULL,
+    departure REAL NOT NULL,
+    end_time REAL,
+    duration REAL,
+    cancelled INTEGER NOT NULL,
+    PRIMARY KEY (run, trip)
+);
+CREATE INDEX IF NOT EXISTS trips_by_mode ON trips (run, mode);
+CREATE TABLE IF NOT EXISTS road_thruput (
+    run INTEGER NOT NULL REFERENCES runs(id),
+    road INTEGER NOT NULL,
+    agent_type TEXT NOT NULL,
+    hour INTEGER NOT NULL,
+    count INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS road_thruput_by_road ON road_thruput (run, road, hour);
+CREATE TABLE IF NOT EXISTS intersection_thruput (
+    run INTEGER NOT NULL REFERENCES runs(id),
+    intersection INTEGER NOT NULL,
+    agent_type TEXT NOT NULL,
+    hour INTEGER NOT NULL,
+    count INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS intersection_thruput_by_intersection
+    ON intersection_thruput (run, intersection, hour);
+";
+
+/// Buffers events from one run and inserts them into the database periodically. Like the event
+/// log, the file is only opened while writing, so many runs in parallel can share it.
+#[derive(Clone)]
+pub(crate) struct ResultsDb {
+    path: String,
+    run: i64,
+    rows: Vec<Row>,
+}
+
+impl ResultsDb {
+    /// Creates the tables if needed and records the start of a new run.
+    pub fn new(path: String, run_name: String, map: &Map, now: Time) -> Result<ResultsDb, String> {
+        let conn = open(&path).map_err(|err| err.to_string())?;
+        conn.execute_batch(SCHEMA).map_err(|err| err.to_string())?;
+        conn.execute(
+            "INSERT INTO runs (name, map, edits, start_time) VALUES (?, ?, ?, ?)",
+            params![
+                run_name,
+                map.get_name().describe(),
+                map.get_edits().edits_name,
+                now.inner_seconds()
+            ],
+        )
+        .map_err(|err| err.to_string())?;
+        Ok(ResultsDb {
+            run: conn.last_insert_rowid(),
+            path,
+            rows: Vec::new(),
+        })
+    }
+
+    pub fn handle_event(&mut self, time: Time, ev: &Event) {
+        self.rows.push(Row::new(time, ev));
+        if self.rows.len() >= FLUSH_EVERY {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        self.insert_events()
+            .unwrap_or_else(|err| panic!("Can't write events to {}: {}", self.path, err));
+    }
+
+    fn insert_events(&mut self) -> Result<(), rusqlite::Error> {
+        let mut conn = open(&self.path)?;
+        let tx = conn.transaction()?;
+        {
+            let mut stmt =
+                tx.prepare("INSERT INTO events VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+            for row in self.rows.drain(..) {
+                let agent = row.agent_columns();
+                stmt.execute(params![
+                    self.run,
+                    row.time,
+                    row.event,
+                    row.trip.map(|t| t.0 as i64),
+                    row.person.map(|p| p.0 as i64),
+                    agent.map(|(t, _)| t),
+                    agent.map(|(_, id)| id as i64),
+                    row.mode.map(|m| format!("{:?}", m)),
+                    row.location.as_ref().map(|(t, _)| *t),
+                    row.location.as_ref().map(|(_, id)| id.clone()),
+                    row.duration.map(|d| d.inner_seconds()),
+                    row.details,
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Writes the remaining events, every trip, and throughput over time, then marks the run as
+    /// finished.
+    pub fn finish(
+        &mut self,
+        now: Time,
+        trips: Vec<(TripID, TripInfo, Option<PersonID>)>,
+        analytics: &Analytics,
+    ) -> Result<(), String> {
+        self.flush();
+        self.insert_summaries(now, trips, analytics)
+            .map_err(|err| err.to_string())
+    }
+
+    fn insert_summaries(
+        &self,
+        now: Time,
+        trips: Vec<(TripID, TripInfo, Option<PersonID>)>,
+        analytics: &Analytics,
+    ) -> Result<(), rusqlite::Error> {
+        let mut conn = open(&self.path)?;
+        let tx = conn.transaction()?;
+        {
+            let mut finished = BTreeMap::new();
+            for (time, trip, _, duration) in &analytics.finished_trips {
+                finished.insert(*trip, (*time, *duration));
+            }
+            let mut stmt = tx.prepare("INSERT INTO trips VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)")?;
+            for (id, info, person) in trips {
+                let (end_time, duration, cancelled) = match finished.get(&id) {
+                    Some((time, Some(duration))) => (
+                        Some(time.inner_seconds()),
+                        Some(duration.inner_seconds()),
+                        false,
+                    ),
+                    Some((time, None)) => (Some(time.inner_seconds()), None, true),
+                    None => (None, None, info.cancellation_reason.is_some()),
+                };
+                stmt.execute(params![
+                    self.run,
+                    id.0 as i64,
+                    person.map(|p| p.0 as i64),
+                    format!("{:?}", info.mode),
+                    format!("{:?}", info.purpose),
+                    info.departure.inner_seconds(),
+                    end_time,
+                    duration,
+                    cancelled,
+                ])?;
+            }
+
+            let mut stmt = tx.prepare("INSERT INTO road_thruput VALUES (?, ?, ?, ?, ?)")?;
+            for ((r, agent_type, hour), count) in &analytics.road_thruput.counts {
+                stmt.execute(params![
+                    self.run,
+                    r.0 as i64,
+                    format!("{:?}", agent_type),
+                    *hour as i64,
+                    *count as i64,
+                ])?;
+            }
+
+            let mut stmt = tx.prepare("INSERT INTO intersection_thruput VALUES (?, ?, ?, ?, ?)")?;
+            for ((i, agent_type, hour), count) in &analytics.intersection_thruput.counts {
+                stmt.execute(params![
+                    self.run,
+                    i.0 as i64,
+                    format!("{:?}", agent_type),
+                    *hour as i64,
+                    *count as i64,
+                ])?;
+            }
+
+            tx.execute(
+                "UPDATE runs SET end_time = ? WHERE id = ?",
+                params![now.inner_seconds(), self.run],
+            )?;
+        }
+        tx.commit()
+    }
+}
+
+fn open(path: &str) -> Result<Connection, rusqlite::Error> {
+    let conn = Connection::open(path)?;
+    // Other runs might be writing at the same time
+    conn.busy_timeout(std::time::Duration::from_secs(60))?;
+    Ok(conn)
+}
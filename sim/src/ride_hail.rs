@@ -0,0 +1,470 @@
+//! Ride-hail vehicles, like taxis or TNCs, aren't owned by anybody. A fleet of them drives around
+//! the map. Somebody inside a building requests a ride, waits inside until a vehicle arrives, and
+//! gets dropped off at another building. While the vehicle stops at the curb for somebody to get in
+//! or out, it blocks the lane.
+//!
+//! When a request comes in, the dispatcher assigns the nearest idle vehicle. If every vehicle is
+//! busy, the request waits, and the next vehicle to drop somebody off takes the oldest request.
+//! Idle vehicles either circle the block or pull over at the end of the current lane. Pulled over
+//! vehicles leave the road entirely, so the space they take up isn't modelled.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{deserialize_btreemap, serialize_btreemap};
+use geom::{Distance, Duration, Time};
+use map_model::{BuildingID, LaneID, Map, Path, PathConstraints, PathRequest, PathStep, Position};
+
+use crate::router::next_lane_around_block;
+use crate::sim::Ctx;
+use crate::{
+    CarID, Command, CreateCar, PersonID, Router, SimOptions, TripID, TripManager, Vehicle,
+};
+
+const BOARDING_TIME: Duration = Duration::const_seconds(30.0);
+const ALIGHTING_TIME: Duration = Duration::const_seconds(20.0);
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub(crate) struct RideHailRequest {
+    pub trip: TripID,
+    pub person: PersonID,
+    pub pickup: BuildingID,
+    pub dropoff: BuildingID,
+    pub requested_at: Time,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct RideHailVehicle {
+    vehicle: Vehicle,
+    state: VehicleState,
+    /// Where the current path ends, or where the vehicle is pulled over
+    pos: Position,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+enum VehicleState {
+    /// Idle, circling the block
+    Cruising,
+    /// Idle, driving to the end of the lane to pull over
+    PullingOver,
+    /// Idle and off the road
+    PulledOver,
+    /// A vehicle dispatched while cruising finishes its current lane first
+    ToPickup(RideHailRequest),
+    Boarding(RideHailRequest),
+    ToDropoff(RideHailRequest),
+    Alighting,
+}
+
+/// Manages the fleet of ride-hail vehicles and dispatches them to requests.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct RideHailSimState {
+    #[serde(
+        serialize_with = "serialize_btreemap",
+        deserialize_with = "deserialize_btreemap"
+    )]
+    vehicles: BTreeMap<CarID, RideHailVehicle>,
+    /// Requests without a vehicle assigned yet, oldest first
+    waiting: Vec<RideHailRequest>,
+    pull_over_when_idle: bool,
+
+    /// When each passenger was picked up, and how long they waited
+    wait_times: Vec<(Time, Duration)>,
+    /// Distance driven without a passenger, including cruising while idle
+    deadhead_distance: Distance,
+    passenger_distance: Distance,
+}
+
+/// A summary of the ride-hail fleet so far.
+pub struct RideHailStats {
+    pub vehicles: usize,
+    pub idle_vehicles: usize,
+    /// Requests still waiting for a vehicle to be assigned
+    pub unassigned_requests: usize,
+    /// When each passenger was picked up, and how long they waited
+    pub wait_times: Vec<(Time, Duration)>,
+    /// Distance driven without a passenger, including cruising while idle
+    pub deadhead_distance: Distance,
+    pub passenger_distance: Distance,
+}
+
+impl RideHailSimState {
+    pub fn new(opts: &SimOptions) -> RideHailSimState {
+        RideHailSimState {
+            vehicles: BTreeMap::new(),
+            waiting: Vec::new(),
+            pull_over_when_idle: opts.ride_hail_pull_over_when_idle,
+            wait_times: Vec::new(),
+            deadhead_distance: Distance::ZERO,
+            passenger_distance: Distance::ZERO,
+        }
+    }
+
+    /// Adds an idle vehicle to the fleet, starting at the beginning of a lane. Returns the request
+    /// and router to spawn it with.
+    pub fn add_vehicle(&mut self, vehicle: Vehicle, l: LaneID, map: &Map) -> (PathRequest, Router) {
+        let req = PathRequest {
+            start: Position::new(l, vehicle.length),
+            end: Position::end(l, map),
+            constraints: PathConstraints::Car,
+        };
+        let router = Router::ride_hail(vehicle.id, Path::one_step(l, map), req.end.dist_along());
+        self.vehicles.insert(
+            vehicle.id,
+            RideHailVehicle {
+                state: if self.pull_over_when_idle {
+                    VehicleState::PullingOver
+                } else {
+                    VehicleState::Cruising
+                },
+                pos: req.end,
+                vehicle,
+            },
+        );
+        (req, router)
+    }
+
+    /// Somebody inside a building wants a ride. If no vehicle can ever reach them, the trip is
+    /// cancelled.
+    pub fn request(
+        &mut self,
+        now: Time,
+        req: RideHailRequest,
+        trips: &mut TripManager,
+        ctx: &mut Ctx,
+    ) {
+        if self.vehicles.is_empty() {
+            trips.cancel_trip(
+                now,
+                req.trip,
+                "there are no ride-hail vehicles".to_string(),
+                None,
+                ctx,
+            );
+            return;
+        }
+        if curb(req.pickup, ctx.map).is_none() || curb(req.dropoff, ctx.map).is_none() {
+            trips.cancel_trip(
+                now,
+                req.trip,
+                format!(
+                    "a ride-hail vehicle can't stop at {} or {}",
+                    req.pickup, req.dropoff
+                ),
+                None,
+                ctx,
+            );
+            return;
+        }
+        self.waiting.push(req);
+        self.dispatch(now, trips, ctx);
+    }
+
+    /// Assign the nearest idle vehicle to each waiting request, oldest request first.
+    fn dispatch(&mut self, now: Time, trips: &mut TripManager, ctx: &mut Ctx) {
+        while !self.waiting.is_empty() {
+            let pickup = curb(self.waiting[0].pickup, ctx.map).unwrap();
+            let pt = pickup.pt(ctx.map);
+            // As the crow flies; finding the fastest route from every vehicle would be expensive
+            let nearest = self
+                .vehicles
+                .iter()
+                .filter(|(_, v)| {
+                    matches!(v.state, VehicleState::Cruising | VehicleState::PulledOver)
+                })
+                .min_by_key(|(_, v)| v.pos.pt(ctx.map).dist_to(pt))
+                .map(|(id, _)| *id);
+            let id = if let Some(id) = nearest {
+                id
+            } else {
+                return;
+            };
+            let req = self.waiting.remove(0);
+
+            let v = self.vehicles.get_mut(&id).unwrap();
+            if let VehicleState::PulledOver = v.state {
+                // Get back on the road
+                let path_req = PathRequest {
+                    start: v.pos,
+                    end: pickup,
+                    constraints: PathConstraints::Car,
+                };
+                if let Some(path) = ctx.map.pathfind(path_req.clone()) {
+                    let router = Router::ride_hail(id, path, pickup.dist_along());
+                    ctx.scheduler.push(
+                        now,
+                        Command::SpawnCar(
+                            CreateCar {
+                                vehicle: v.vehicle.clone(),
+                                router,
+                                req: path_req,
+                                start_dist: v.pos.dist_along(),
+                                maybe_parked_car: None,
+                                trip_and_person: None,
+                                maybe_route: None,
+                            },
+                            true,
+                        ),
+                    );
+                    v.pos = pickup;
+                    v.state = VehicleState::ToPickup(req);
+                } else {
+                    trips.cancel_trip(
+                        now,
+                        req.trip,
+                        format!("no path for a ride-hail vehicle: {}", path_req),
+                        None,
+                        ctx,
+                    );
+                }
+            } else {
+                // The vehicle heads to the pickup after finishing its current lane
+                v.state = VehicleState::ToPickup(req);
+            }
+        }
+    }
+
+    /// The vehicle reached the end of its path. If Some, the vehicle should stop for this long,
+    /// then call `vehicle_departing`. If None, the vehicle pulled over and should vanish from the
+    /// road.
+    pub fn vehicle_reached_end(
+        &mut self,
+        now: Time,
+        id: CarID,
+        pos: Position,
+        dist_driven: Distance,
+        trips: &mut TripManager,
+        ctx: &mut Ctx,
+    ) -> Option<Duration> {
+        let v = self.vehicles.get_mut(&id).unwrap();
+        v.pos = pos;
+        if let VehicleState::ToDropoff(_) = v.state {
+            self.passenger_distance += dist_driven;
+        } else {
+            self.deadhead_distance += dist_driven;
+        }
+
+        match v.state {
+            VehicleState::Cruising => {
+                if next_lane(pos.lane(), &v.vehicle, ctx.map).is_some() {
+                    Some(Duration::ZERO)
+                } else {
+                    // A dead-end; nowhere to keep cruising
+                    v.state = VehicleState::PulledOver;
+                    self.dispatch(now, trips, ctx);
+                    None
+                }
+            }
+            VehicleState::PullingOver => {
+                v.state = VehicleState::PulledOver;
+                self.dispatch(now, trips, ctx);
+                None
+            }
+            VehicleState::ToPickup(ref req) => {
+                if Some(pos) != curb(req.pickup, ctx.map) {
+                    // Dispatched while cruising, so find the way there after stopping
+                    return Some(Duration::ZERO);
+                }
+                let req = req.clone();
+                let wait = now - req.requested_at;
+                self.wait_times.push((now, wait));
+                trips.ride_hail_passenger_boarded(
+                    req.trip,
+                    id,
+                    wait,
+                    PathRequest {
+                        start: pos,
+                        end: curb(req.dropoff, ctx.map).unwrap(),
+                        constraints: PathConstraints::Car,
+                    },
+                );
+                v.state = VehicleState::Boarding(req);
+                Some(BOARDING_TIME)
+            }
+            VehicleState::ToDropoff(ref req) => {
+                trips.ride_hail_passenger_alighted(now, req.trip, id, dist_driven, ctx);
+                v.state = VehicleState::Alighting;
+                Some(ALIGHTING_TIME)
+            }
+            VehicleState::PulledOver | VehicleState::Boarding(_) | VehicleState::Alighting => {
+                unreachable!()
+            }
+        }
+    }
+
+    /// The vehicle is done stopping at `pos`, so figure out where it goes next.
+    pub fn vehicle_departing(
+        &mut self,
+        now: Time,
+        id: CarID,
+        pos: Position,
+        trips: &mut TripManager,
+        ctx: &mut Ctx,
+    ) -> Router {
+        let v = self.vehicles.get_mut(&id).unwrap();
+        let state = std::mem::replace(&mut v.state, VehicleState::Cruising);
+        match state {
+            VehicleState::Cruising => {
+                let mut path = Path::one_step(pos.lane(), ctx.map);
+                for step in next_lane(pos.lane(), &v.vehicle, ctx.map).unwrap() {
+                    path.add(step, ctx.map);
+                }
+                let end = Position::end(path.last_step().as_lane(), ctx.map);
+                v.pos = end;
+                Router::ride_hail(id, path, end.dist_along())
+            }
+            VehicleState::ToPickup(req) => {
+                let pickup = curb(req.pickup, ctx.map).unwrap();
+                if let Some(router) = route(id, pos, pickup, ctx.map) {
+                    v.pos = pickup;
+                    v.state = VehicleState::ToPickup(req);
+                    return router;
+                }
+                trips.cancel_trip(
+                    now,
+                    req.trip,
+                    format!("no path for a ride-hail vehicle from {} to {}", pos, pickup),
+                    None,
+                    ctx,
+                );
+                self.become_idle(now, id, pos, trips, ctx)
+            }
+            VehicleState::Boarding(req) => {
+                let dropoff = curb(req.dropoff, ctx.map).unwrap();
+                if let Some(router) = route(id, pos, dropoff, ctx.map) {
+                    v.pos = dropoff;
+                    v.state = VehicleState::ToDropoff(req);
+                    return router;
+                }
+                trips.cancel_trip(
+                    now,
+                    req.trip,
+                    format!(
+                        "no path for a ride-hail vehicle from {} to {}",
+                        pos, dropoff
+                    ),
+                    None,
+                    ctx,
+                );
+                self.become_idle(now, id, pos, trips, ctx)
+            }
+            VehicleState::Alighting => self.become_idle(now, id, pos, trips, ctx),
+            VehicleState::PullingOver | VehicleState::PulledOver | VehicleState::ToDropoff(_) => {
+                unreachable!()
+            }
+        }
+    }
+
+    /// Take the oldest waiting request, or otherwise head to the end of the current lane, and
+    /// cruise or pull over from there.
+    fn become_idle(
+        &mut self,
+        now: Time,
+        id: CarID,
+        pos: Position,
+        trips: &mut TripManager,
+        ctx: &mut Ctx,
+    ) -> Router {
+        while !self.waiting.is_empty() {
+            let req = self.waiting.remove(0);
+            let pickup = curb(req.pickup, ctx.map).unwrap();
+            if let Some(router) = route(id, pos, pickup, ctx.map) {
+                let v = self.vehicles.get_mut(&id).unwrap();
+                v.pos = pickup;
+                v.state = VehicleState::ToPickup(req);
+                return router;
+            }
+            trips.cancel_trip(
+                now,
+                req.trip,
+                format!("no path for a ride-hail vehicle from {} to {}", pos, pickup),
+                None,
+                ctx,
+            );
+        }
+
+        let v = self.vehicles.get_mut(&id).unwrap();
+        v.pos = Position::end(pos.lane(), ctx.map);
+        v.state = if self.pull_over_when_idle {
+            VehicleState::PullingOver
+        } else {
+            VehicleState::Cruising
+        };
+        Router::ride_hail(id, Path::one_step(pos.lane(), ctx.map), v.pos.dist_along())
+    }
+
+    /// Vehicles currently driving or stopped on the road
+    pub fn num_vehicles_on_road(&self) -> usize {
+        self.vehicles
+            .values()
+            .filter(|v| !matches!(v.state, VehicleState::PulledOver))
+            .count()
+    }
+
+    pub fn describe_vehicle(&self, id: CarID) -> Option<String> {
+        let v = self.vehicles.get(&id)?;
+        Some(match v.state {
+            VehicleState::Cruising => "Idle, cruising".to_string(),
+            VehicleState::PullingOver => "Idle, pulling over".to_string(),
+            VehicleState::PulledOver => "Idle, pulled over".to_string(),
+            VehicleState::ToPickup(ref req) => format!("Picking up {}", req.person),
+            VehicleState::Boarding(ref req) => format!("Waiting for {} to get in", req.person),
+            VehicleState::ToDropoff(ref req) => format!("Dropping off {}", req.person),
+            VehicleState::Alighting => "Letting a passenger out".to_string(),
+        })
+    }
+
+    pub fn get_stats(&self) -> RideHailStats {
+        RideHailStats {
+            vehicles: self.vehicles.len(),
+            idle_vehicles: self
+                .vehicles
+                .values()
+                .filter(|v| {
+                    matches!(
+                        v.state,
+                        VehicleState::Cruising
+                            | VehicleState::PullingOver
+                            | VehicleState::PulledOver
+                    )
+                })
+                .count(),
+            unassigned_requests: self.waiting.len(),
+            wait_times: self.wait_times.clone(),
+            deadhead_distance: self.deadhead_distance,
+            passenger_distance: self.passenger_distance,
+        }
+    }
+}
+
+/// Where a ride-hail vehicle stops to serve a building
+fn curb(b: BuildingID, map: &Map) -> Option<Position> {
+    map.get_b(b).driving_connection(map).map(|(pos, _)| pos)
+}
+
+fn route(id: CarID, start: Position, end: Position, map: &Map) -> Option<Router> {
+    let path = map.pathfind(PathRequest {
+        start,
+        end,
+        constraints: PathConstraints::Car,
+    })?;
+    Some(Router::ride_hail(id, path, end.dist_along()))
+}
+
+/// Cruise around the block, without heading towards the edge of the map.
+fn next_lane(current: LaneID, vehicle: &Vehicle, map: &Map) -> Option<Vec<PathStep>> {
+    let steps = next_lane_around_block(current, vehicle, map).or_else(|| {
+        // Not part of a block, so just go anywhere
+        let turn = map
+            .get_turns_for(current, PathConstraints::Car)
+            .into_iter()
+            .find(|t| !map.get_i(map.get_l(t.id.dst).dst_i).is_border())?;
+        Some(vec![PathStep::Turn(turn.id), PathStep::Lane(turn.id.dst)])
+    })?;
+    let dst = steps.last().unwrap().as_lane();
+    if map.get_i(map.get_l(dst).dst_i).is_border() {
+        return None;
+    }
+    Some(steps)
+}
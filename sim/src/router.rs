@@ -1,20 +1,20 @@
 //! For vehicles only, not pedestrians. Follows a Path from map_model, but can opportunistically
 //! lane-change to avoid a slow lane, can can handle re-planning to look for available parking.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use serde::{Deserialize, Serialize};
 
-use geom::Distance;
+use geom::{Distance, Duration, Time};
 use map_model::{
-    BuildingID, IntersectionID, LaneID, Map, Path, PathConstraints, PathRequest, PathStep,
-    Position, Traversable, Turn, TurnID,
+    BuildingID, Direction, DrivingSide, IntersectionID, LaneID, Map, Path, PathRequest, PathStep,
+    Position, RoadID, Traversable, Turn, TurnID, TurnType,
 };
 
 use crate::mechanics::Queue;
 use crate::{
-    AlertLocation, CarID, Event, ParkingSim, ParkingSimState, ParkingSpot, PersonID, SidewalkSpot,
-    TripID, TripPhaseType, Vehicle, VehicleType,
+    AlertLocation, CarID, CurbSimState, Event, ParkingSearch, ParkingSim, ParkingSimState,
+    ParkingSpot, PersonID, SidewalkSpot, TripID, TripPhaseType, Vehicle, VehicleType,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -25,6 +25,12 @@ pub(crate) struct Router {
     owner: CarID,
 }
 
+/// Blocks with lots of sides or one-way roads might never lead back to where cruising started, so
+/// give up after this many turns.
+const MAX_CRUISING_TURNS: usize = 12;
+
+abstutil::added_in_format!(added_in_v15, 15);
+
 #[derive(Debug)]
 pub(crate) enum ActionAtEnd {
     VanishAtBorder(IntersectionID),
@@ -33,6 +39,8 @@ pub(crate) enum ActionAtEnd {
     StopBiking(SidewalkSpot),
     BusAtStop,
     GiveUpOnParking,
+    RideHailAtEnd,
+    DeliveryAtEnd,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -44,6 +52,12 @@ enum Goal {
         /// No parking available at all!
         stuck_end_dist: Option<Distance>,
         started_looking: bool,
+        /// When cruising for parking, when the search started, and the lane it started from.
+        /// Cruising gives up after circling the block back to that lane's road.
+        cruising: Option<(Time, LaneID)>,
+        /// How many turns have been taken while cruising, in case the block never leads back
+        #[serde(with = "crate::router::added_in_v15")]
+        cruising_turns: usize,
     },
     EndAtBorder {
         end_dist: Distance,
@@ -55,6 +69,12 @@ enum Goal {
     FollowBusRoute {
         end_dist: Distance,
     },
+    RideHail {
+        end_dist: Distance,
+    },
+    Delivery {
+        end_dist: Distance,
+    },
 }
 
 impl Router {
@@ -90,6 +110,8 @@ impl Router {
                 spot: None,
                 stuck_end_dist: None,
                 started_looking: false,
+                cruising: None,
+                cruising_turns: 0,
             },
             owner,
         }
@@ -111,6 +133,22 @@ impl Router {
         }
     }
 
+    pub fn ride_hail(owner: CarID, path: Path, end_dist: Distance) -> Router {
+        Router {
+            path,
+            goal: Goal::RideHail { end_dist },
+            owner,
+        }
+    }
+
+    pub fn delivery(owner: CarID, path: Path, end_dist: Distance) -> Router {
+        Router {
+            path,
+            goal: Goal::Delivery { end_dist },
+            owner,
+        }
+    }
+
     pub fn head(&self) -> Traversable {
         self.path.current_step().as_traversable()
     }
@@ -139,6 +177,8 @@ impl Router {
             } => stuck_end_dist.unwrap_or_else(|| spot.unwrap().1),
             Goal::BikeThenStop { ref goal } => goal.sidewalk_pos.dist_along(),
             Goal::FollowBusRoute { end_dist } => end_dist,
+            Goal::RideHail { end_dist } => end_dist,
+            Goal::Delivery { end_dist } => end_dist,
         }
     }
 
@@ -146,24 +186,98 @@ impl Router {
         &self.path
     }
 
+    /// If the car could move over to a parallel lane partway along the current one, the turn it
+    /// would take from there. Not while the car is about to take an uber-turn or finish its path.
+    pub fn turn_from_parallel_lane(&self, lane: LaneID, map: &Map) -> Option<TurnID> {
+        if self.last_step() || self.path.about_to_start_ut().is_some() {
+            return None;
+        }
+        let turn = match self.path.next_step() {
+            PathStep::Turn(t) => t,
+            _ => {
+                return None;
+            }
+        };
+        let from_there = TurnID {
+            parent: turn.parent,
+            src: lane,
+            dst: turn.dst,
+        };
+        map.maybe_get_t(from_there)?;
+        Some(from_there)
+    }
+
+    /// Move over to a parallel lane partway along the current one. Check
+    /// `turn_from_parallel_lane` first.
+    pub fn change_current_lane(&mut self, lane: LaneID, map: &Map) {
+        self.path.change_current_lane(lane, map);
+    }
+
+    /// After live map edits, find a new path from the current lane to the same destination.
+    /// Returns false if that's not possible.
+    pub fn reroute_after_live_edits(&mut self, vehicle: &Vehicle, map: &Map) -> bool {
+        // The path may have been extended while looking for parking, so the original end isn't
+        // meaningful anymore.
+        if let Goal::ParkNearBuilding {
+            started_looking: true,
+            ..
+        } = self.goal
+        {
+            return false;
+        }
+        self.path
+            .reroute(vehicle.vehicle_type.to_constraints(), map)
+    }
+
+    /// Look for a faster path to the same destination, adding some delay to crossing congested
+    /// lanes. Returns true if the path changed.
+    pub fn reroute_around_congestion(
+        &mut self,
+        vehicle: &Vehicle,
+        now: Time,
+        value_of_time: f64,
+        delays: &BTreeMap<LaneID, Duration>,
+        map: &Map,
+    ) -> bool {
+        if let Goal::ParkNearBuilding {
+            started_looking: true,
+            ..
+        } = self.goal
+        {
+            return false;
+        }
+        let before = self.path.get_steps().clone();
+        self.path
+            .reroute_with(vehicle.vehicle_type.to_constraints(), |req| {
+                map.pathfind_with_lane_delays(req, now, value_of_time, delays)
+            })
+            && self.path.get_steps() != &before
+    }
+
     /// Returns the step just finished
     pub fn advance(
         &mut self,
+        now: Time,
         vehicle: &Vehicle,
         parking: &ParkingSimState,
         map: &Map,
         trip_and_person: Option<(TripID, PersonID)>,
+        leave_at: Option<Time>,
+        cruise_for_parking: bool,
         events: &mut Vec<Event>,
     ) -> Traversable {
         let prev = self.path.shift(map).as_traversable();
         if self.last_step() {
             // Do this to trigger the side-effect of looking for parking.
             self.maybe_handle_end(
+                now,
                 Distance::ZERO,
                 vehicle,
                 parking,
                 map,
                 trip_and_person,
+                leave_at,
+                cruise_for_parking,
                 events,
             );
         }
@@ -186,12 +300,16 @@ impl Router {
     /// step.
     pub fn maybe_handle_end(
         &mut self,
+        now: Time,
         front: Distance,
         vehicle: &Vehicle,
         parking: &ParkingSimState,
         map: &Map,
         // TODO Not so nice to plumb all of this here
         trip_and_person: Option<(TripID, PersonID)>,
+        // When the driver will move their car again, for parking restrictions
+        leave_at: Option<Time>,
+        cruise_for_parking: bool,
         events: &mut Vec<Event>,
     ) -> Option<ActionAtEnd> {
         match self.goal {
@@ -207,6 +325,8 @@ impl Router {
                 ref mut stuck_end_dist,
                 target,
                 ref mut started_looking,
+                ref mut cruising,
+                ref mut cruising_turns,
             } => {
                 if let Some(d) = stuck_end_dist {
                     if *d == front {
@@ -223,10 +343,15 @@ impl Router {
                 if need_new_spot {
                     *started_looking = true;
                     let current_lane = self.path.current_step().as_lane();
+                    let search = ParkingSearch {
+                        target,
+                        now,
+                        leave_at,
+                    };
                     let candidates = parking.get_all_free_spots(
                         Position::new(current_lane, front),
                         vehicle,
-                        target,
+                        search,
                         map,
                     );
                     let best =
@@ -257,7 +382,7 @@ impl Router {
                                 Some(PathRequest {
                                     start: Position::new(current_lane, front),
                                     end: new_pos,
-                                    constraints: PathConstraints::Car,
+                                    constraints: vehicle.vehicle_type.to_constraints(),
                                 }),
                                 TripPhaseType::Parking,
                             ));
@@ -266,8 +391,31 @@ impl Router {
                         assert!(new_pos.dist_along() >= front);
                         *spot = Some((new_spot, new_pos.dist_along()));
                     } else {
-                        if let Some((new_path_steps, new_spot, new_pos)) =
-                            parking.path_to_free_parking_spot(current_lane, vehicle, target, map)
+                        // Drivers who can't see parking on this lane circle the block, until they
+                        // make it all the way around. Then they resort to searching farther.
+                        let current_road = map.get_l(current_lane).parent;
+                        let keep_cruising = cruise_for_parking
+                            && *cruising_turns < MAX_CRUISING_TURNS
+                            && cruising
+                                .map(|(_, start)| map.get_l(start).parent != current_road)
+                                .unwrap_or(true);
+                        let cruise_steps = if keep_cruising {
+                            next_lane_around_block(current_lane, vehicle, map)
+                        } else {
+                            None
+                        };
+
+                        if let Some(steps) = cruise_steps {
+                            if cruising.is_none() {
+                                *cruising = Some((now, current_lane));
+                            }
+                            *cruising_turns += 1;
+                            for step in steps {
+                                self.path.add(step, map);
+                            }
+                            events.push(Event::PathAmended(self.path.clone()));
+                        } else if let Some((new_path_steps, new_spot, new_pos)) =
+                            parking.path_to_free_parking_spot(current_lane, vehicle, search, map)
                         {
                             assert!(!new_path_steps.is_empty());
                             for step in new_path_steps {
@@ -283,7 +431,7 @@ impl Router {
                                     Some(PathRequest {
                                         start: Position::new(current_lane, front),
                                         end: new_pos,
-                                        constraints: PathConstraints::Car,
+                                        constraints: vehicle.vehicle_type.to_constraints(),
                                     }),
                                     TripPhaseType::Parking,
                                 ));
@@ -306,6 +454,10 @@ impl Router {
                 }
 
                 if spot.unwrap().1 == front {
+                    *cruising_turns = 0;
+                    if let (Some((start, _)), Some((t, _))) = (cruising.take(), trip_and_person) {
+                        events.push(Event::CruisedForParking(t, now - start));
+                    }
                     Some(ActionAtEnd::StartParking(spot.unwrap().0))
                 } else {
                     None
@@ -325,14 +477,30 @@ impl Router {
                     None
                 }
             }
+            Goal::RideHail { end_dist } => {
+                if end_dist == front {
+                    Some(ActionAtEnd::RideHailAtEnd)
+                } else {
+                    None
+                }
+            }
+            Goal::Delivery { end_dist } => {
+                if end_dist == front {
+                    Some(ActionAtEnd::DeliveryAtEnd)
+                } else {
+                    None
+                }
+            }
         }
     }
 
     pub fn opportunistically_lanechange(
         &mut self,
         queues: &HashMap<Traversable, Queue>,
+        curb: &CurbSimState,
         map: &Map,
         handle_uber_turns: bool,
+        lookahead: Distance,
     ) {
         // if we're already in the uber-turn, we're committed, but if we're about to enter one, lock
         // in the best path through it now.
@@ -359,21 +527,36 @@ impl Router {
             let parent = map.get_parent(orig_target_lane);
             let next_parent = map.get_l(next_lane).src_i;
 
-            let compute_cost = |turn1: &Turn, lane: LaneID| {
+            let compute_cost = |turn1: &Turn, lane: LaneID, turn2: &Turn| {
                 let (lt, lc, mut slow_lane) = turn1.penalty(map);
+                // If the next intersection is close, lanes that need to shift over again there
+                // are worse. Within the lookahead, merging has to happen now.
+                let merges_ahead = if map.get_l(lane).length() <= lookahead {
+                    turn2.penalty(map).1
+                } else {
+                    0
+                };
                 let (vehicles, mut bike) = queues[&Traversable::Lane(lane)].target_lane_penalty();
 
+                // A delivery vehicle stopped in the lane blocks it entirely, so merge around it if
+                // there's any other way.
+                let blocked = if curb.is_blocked(lane) { 1 } else { 0 };
+
                 // The magic happens here. We have different penalties:
                 //
+                // 0) Is a delivery vehicle stopped in the lane?
                 // 1) Are we headed towards a general purpose lane instead of a dedicated bike/bus
                 //    lane?
                 // 2) Are there any bikes in the target lane? This ONLY matters if we're a car. If
                 //    we're another bike, the speed difference won't matter.
-                // 3) IF we're a bike, are we headed to something other than the slow (rightmost in
-                //    the US) lane?
+                // 3) IF we're a bike, are we headed to something other than the slow (curb-side,
+                //    so rightmost in the US) lane?
                 // 4) Are there lots of vehicles stacked up in one lane?
                 // 5) Are we changing lanes?
                 //
+                // Before all of that except the lane type, if the next intersection is within the
+                // lookahead distance, are we lined up for the turn we'll make there?
+                //
                 // A linear combination of these penalties is hard to reason about. We mostly
                 // make our choice based on each penalty in order, breaking ties by moving onto the
                 // next thing. With one exception: To produce more realistic behavior, we combine
@@ -384,7 +567,7 @@ impl Router {
                     slow_lane = 0;
                 }
 
-                (lt, bike, slow_lane, vehicles + lc)
+                (blocked, lt, merges_ahead, bike, slow_lane, vehicles + lc)
             };
 
             // Look for other candidates, and assign a cost to each.
@@ -415,7 +598,7 @@ impl Router {
                     return Some((turn1, l, turn2));
                 })
                 .map(|(turn1, l, turn2)| {
-                    let cost = compute_cost(turn1, l);
+                    let cost = compute_cost(turn1, l, turn2);
                     if turn1.id == current_turn {
                         original_cost = Some(cost);
                     }
@@ -475,3 +658,48 @@ impl Router {
         }
     }
 }
+
+/// Returns the turn and lane to keep circling the block on the curb side, or None if the block
+/// can't be traced or there's no way to stay on it.
+pub(crate) fn next_lane_around_block(
+    current: LaneID,
+    vehicle: &Vehicle,
+    map: &Map,
+) -> Option<Vec<PathStep>> {
+    let road = map.get_parent(current);
+    let lanes = road.lanes_ltr();
+    let driving_side = map.get_config().driving_side;
+    // trace_around_block follows the outside edge of the outermost lane, so start from the one on
+    // the curb side.
+    let curb = match (driving_side, road.dir(current)) {
+        (DrivingSide::Right, Direction::Fwd) | (DrivingSide::Left, Direction::Back) => {
+            lanes.last().unwrap().0
+        }
+        (DrivingSide::Right, Direction::Back) | (DrivingSide::Left, Direction::Fwd) => lanes[0].0,
+    };
+    let (_, block_lanes) = map.get_l(curb).trace_around_block(map)?;
+    let block_roads: BTreeSet<RoadID> = block_lanes
+        .into_iter()
+        .map(|l| map.get_l(l).parent)
+        .collect();
+
+    // Keep the block on the curb side by preferring to turn towards it
+    let preference = |tt: TurnType| match (driving_side, tt) {
+        (DrivingSide::Right, TurnType::Right) | (DrivingSide::Left, TurnType::Left) => 0,
+        (_, TurnType::Straight) => 1,
+        _ => 2,
+    };
+    let turn = map
+        .get_turns_for(current, vehicle.vehicle_type.to_constraints())
+        .into_iter()
+        .filter(|t| {
+            let dst = map.get_l(t.id.dst).parent;
+            // A modal filter would leave the car stuck partway around the block
+            t.turn_type != TurnType::UTurn
+                && dst != road.id
+                && block_roads.contains(&dst)
+                && !map.get_r(dst).modal_filter
+        })
+        .min_by_key(|t| preference(t.turn_type))?;
+    Some(vec![PathStep::Turn(turn.id), PathStep::Lane(turn.id.dst)])
+}
@@ -0,0 +1,196 @@
+//! Crashes are too rare to simulate directly, so instead this counts "surrogate" safety events --
+//! near misses that happen far more often and tend to precede real crashes. Three kinds are
+//! detected:
+//!
+//! - Hard braking: a vehicle comes up on a stopped queue with little room to stop. Agents in this
+//!   simulation stop instantly, so the deceleration is estimated from how fast the vehicle was
+//!   going and how long ago the vehicle ahead of it stopped.
+//! - Short post-encroachment time (PET): an agent starts a turn soon after somebody else finished
+//!   a conflicting turn through the same intersection.
+//! - Close passes: a vehicle overtakes a cyclist in the next lane over, with little room between
+//!   them. Nobody moves sideways within a lane, so the clearance comes from the lane widths.
+//!
+//! Like emissions, the thresholds are meant to compare the same city before and after some change,
+//! not to predict how many crashes will happen.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::Counter;
+use geom::{Distance, Duration, Time};
+use map_model::{IntersectionID, LaneID, Map, RoadID, Traversable};
+
+use crate::{AgentID, CarID, Event, VehicleType};
+
+/// Slowing down faster than this, in m/s^2, is hard braking.
+const HARD_BRAKING: f64 = 3.0;
+/// Conflicting turns closer together than this are near misses.
+pub(crate) const MAX_PET: Duration = Duration::const_seconds(1.5);
+/// Passing a cyclist with less room than this is a close pass. Many places require at least this
+/// much by law.
+const CLOSE_PASS: Distance = Distance::const_meters(1.5);
+
+/// One near miss.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SafetyEvent {
+    HardBraking {
+        car: CarID,
+        on: Traversable,
+        /// In m/s^2
+        deceleration: f64,
+    },
+    ShortPostEncroachment {
+        intersection: IntersectionID,
+        /// The agent who went through the conflict point first
+        first: AgentID,
+        second: AgentID,
+        pet: Duration,
+    },
+    ClosePass {
+        car: CarID,
+        bike: CarID,
+        lane: LaneID,
+        clearance: Distance,
+    },
+}
+
+/// Every near miss so far.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SafetyStats {
+    pub events: Vec<(Time, SafetyEvent)>,
+}
+
+impl SafetyEvent {
+    /// A vehicle going `speed` (in m/s) stops `time_to_stop` after the vehicle ahead of it did.
+    /// Stopping over the distance covered in that time takes `speed / (2 * time_to_stop)`.
+    pub(crate) fn maybe_hard_braking(
+        car: CarID,
+        on: Traversable,
+        speed: f64,
+        time_to_stop: Duration,
+    ) -> Option<SafetyEvent> {
+        if time_to_stop <= Duration::ZERO || car.1 == VehicleType::Train {
+            return None;
+        }
+        let deceleration = speed / (2.0 * time_to_stop.inner_seconds());
+        if deceleration > HARD_BRAKING {
+            Some(SafetyEvent::HardBraking {
+                car,
+                on,
+                deceleration,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// A vehicle in `car_lane` overtook a cyclist in the adjacent `bike_lane`. Both ride in the
+    /// middle of their lane.
+    pub(crate) fn maybe_close_pass(
+        car: CarID,
+        car_lane: LaneID,
+        bike: CarID,
+        bike_lane: LaneID,
+        map: &Map,
+    ) -> Option<SafetyEvent> {
+        let between_centers = (map.get_l(car_lane).width + map.get_l(bike_lane).width) / 2.0;
+        let clearance = (between_centers - (vehicle_width(car.1) + vehicle_width(bike.1)) / 2.0)
+            .max(Distance::ZERO);
+        if clearance < CLOSE_PASS {
+            Some(SafetyEvent::ClosePass {
+                car,
+                bike,
+                lane: bike_lane,
+                clearance,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Where did this happen?
+    pub fn location(&self, map: &Map) -> SafetyLocation {
+        match self {
+            SafetyEvent::HardBraking { on, .. } => match on {
+                Traversable::Lane(l) => SafetyLocation::Road(map.get_l(*l).parent),
+                Traversable::Turn(t) => SafetyLocation::Intersection(t.parent),
+            },
+            SafetyEvent::ShortPostEncroachment { intersection, .. } => {
+                SafetyLocation::Intersection(*intersection)
+            }
+            SafetyEvent::ClosePass { lane, .. } => SafetyLocation::Road(map.get_l(*lane).parent),
+        }
+    }
+
+    /// Was a cyclist involved?
+    pub fn involves_bike(&self) -> bool {
+        let is_bike = |a: &AgentID| matches!(a, AgentID::Car(CarID(_, VehicleType::Bike)));
+        match self {
+            SafetyEvent::HardBraking { car, .. } => car.1 == VehicleType::Bike,
+            SafetyEvent::ShortPostEncroachment { first, second, .. } => {
+                is_bike(first) || is_bike(second)
+            }
+            SafetyEvent::ClosePass { .. } => true,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SafetyEvent::HardBraking { .. } => "hard braking",
+            SafetyEvent::ShortPostEncroachment { .. } => "short post-encroachment time",
+            SafetyEvent::ClosePass { .. } => "close pass of a cyclist",
+        }
+    }
+}
+
+/// Safety events are counted per road or intersection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SafetyLocation {
+    Road(RoadID),
+    Intersection(IntersectionID),
+}
+
+impl SafetyStats {
+    pub(crate) fn handle_event(&mut self, now: Time, ev: &Event) {
+        if let Event::Safety(ev) = ev {
+            self.events.push((now, ev.clone()));
+        }
+    }
+
+    /// How many events have happened on each road and at each intersection.
+    pub fn per_location(&self, map: &Map) -> (Counter<RoadID>, Counter<IntersectionID>) {
+        let mut roads = Counter::new();
+        let mut intersections = Counter::new();
+        for (_, ev) in &self.events {
+            match ev.location(map) {
+                SafetyLocation::Road(r) => {
+                    roads.inc(r);
+                }
+                SafetyLocation::Intersection(i) => {
+                    intersections.inc(i);
+                }
+            }
+        }
+        (roads, intersections)
+    }
+
+    /// How many events of each kind have happened.
+    pub fn per_kind(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for (_, ev) in &self.events {
+            *counts.entry(ev.describe()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+fn vehicle_width(vehicle_type: VehicleType) -> Distance {
+    match vehicle_type {
+        VehicleType::Car => Distance::meters(1.75),
+        VehicleType::Bike => Distance::meters(0.8),
+        VehicleType::Bus | VehicleType::Truck | VehicleType::Train | VehicleType::Emergency => {
+            Distance::meters(2.5)
+        }
+    }
+}
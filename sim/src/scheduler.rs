@@ -1,15 +1,16 @@
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
 
 use serde::{Deserialize, Serialize};
 
 use abstutil::Counter;
 use geom::{Duration, Histogram, Time};
-use map_model::{BusRouteID, IntersectionID};
+use map_model::{BusRouteID, IntersectionID, RoadID};
 
 use crate::{
-    pandemic, AgentID, CarID, CreateCar, CreatePedestrian, PedestrianID, TripID, TripSpec,
+    pandemic, AgentID, CarID, CreateCar, CreatePedestrian, PedestrianID, RideHailRequest, TripID,
+    TripSpec,
 };
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -27,6 +28,13 @@ pub(crate) enum Command {
     Pandemic(pandemic::Cmd),
     /// The Time is redundant, just used to dedupe commands
     StartBus(BusRouteID, Time),
+    RequestRideHail(RideHailRequest),
+    /// Average the live travel times measured recently, and reroute drivers around congestion
+    RefreshTravelTimes,
+    /// A delivery vehicle appears somewhere on this road
+    StartDelivery(RoadID),
+    /// Somebody waiting at a guideway station boards, or somebody riding gets off
+    UpdateGuidewayRider(TripID),
 }
 
 impl Command {
@@ -38,7 +46,7 @@ impl Command {
         }
     }
 
-    fn to_type(&self) -> CommandType {
+    pub(crate) fn to_type(&self) -> CommandType {
         match self {
             Command::SpawnCar(ref create, _) => CommandType::Car(create.vehicle.id),
             Command::SpawnPed(ref create) => CommandType::Ped(create.id),
@@ -50,6 +58,10 @@ impl Command {
             Command::Callback(_) => CommandType::Callback,
             Command::Pandemic(ref p) => CommandType::Pandemic(p.clone()),
             Command::StartBus(r, t) => CommandType::StartBus(*r, *t),
+            Command::RequestRideHail(ref req) => CommandType::RideHail(req.trip),
+            Command::RefreshTravelTimes => CommandType::RefreshTravelTimes,
+            Command::StartDelivery(r) => CommandType::StartDelivery(*r),
+            Command::UpdateGuidewayRider(id) => CommandType::GuidewayRider(*id),
         }
     }
 
@@ -65,6 +77,10 @@ impl Command {
             Command::Callback(_) => SimpleCommandType::Callback,
             Command::Pandemic(_) => SimpleCommandType::Pandemic,
             Command::StartBus(_, _) => SimpleCommandType::StartBus,
+            Command::RequestRideHail(_) => SimpleCommandType::RideHail,
+            Command::RefreshTravelTimes => SimpleCommandType::RefreshTravelTimes,
+            Command::StartDelivery(_) => SimpleCommandType::StartDelivery,
+            Command::UpdateGuidewayRider(_) => SimpleCommandType::GuidewayRider,
         }
     }
 }
@@ -72,7 +88,7 @@ impl Command {
 /// A smaller version of Command that satisfies many more properties. Only one Command per
 /// CommandType may exist at a time.
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Debug)]
-enum CommandType {
+pub(crate) enum CommandType {
     StartTrip(TripID),
     Car(CarID),
     CarLaggyHead(CarID),
@@ -81,6 +97,10 @@ enum CommandType {
     Callback,
     Pandemic(pandemic::Cmd),
     StartBus(BusRouteID, Time),
+    RideHail(TripID),
+    RefreshTravelTimes,
+    StartDelivery(RoadID),
+    GuidewayRider(TripID),
 }
 
 /// A more compressed form of CommandType, just used for keeping stats on event processing.
@@ -94,6 +114,10 @@ enum SimpleCommandType {
     Callback,
     Pandemic,
     StartBus,
+    RideHail,
+    RefreshTravelTimes,
+    StartDelivery,
+    GuidewayRider,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -204,6 +228,20 @@ impl Scheduler {
         self.queued_commands.remove(&cmd.to_type());
     }
 
+    /// Cancels every bus that hasn't started yet, returning their routes and start times.
+    pub fn cancel_bus_starts(&mut self) -> Vec<(BusRouteID, Time)> {
+        let mut cancelled = Vec::new();
+        self.queued_commands.retain(|_, (cmd, _)| {
+            if let Command::StartBus(r, t) = cmd {
+                cancelled.push((*r, *t));
+                false
+            } else {
+                true
+            }
+        });
+        cancelled
+    }
+
     /// This next command might've actually been rescheduled to a later time; the caller won't know
     /// that here.
     pub fn peek_next_time(&self) -> Option<Time> {
@@ -238,6 +276,15 @@ impl Scheduler {
         }
     }
 
+    /// Describes every pending command (just the type, not the full payload) and when it's
+    /// scheduled.
+    pub fn get_queued_commands(&self) -> BTreeMap<String, Time> {
+        self.queued_commands
+            .iter()
+            .map(|(cmd_type, (_, time))| (format!("{:?}", cmd_type), *time))
+            .collect()
+    }
+
     pub fn describe_stats(&self) -> Vec<String> {
         let mut stats = vec![
             format!("delta times for events: {}", self.delta_times.describe()),
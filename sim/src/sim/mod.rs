@@ -1,6 +1,8 @@
 // This file has a jumbled mess of queries, setup, and mutating methods.
 
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
 use std::panic;
 
 use instant::Instant;
@@ -8,27 +10,37 @@ use rand::SeedableRng;
 use rand_xorshift::XorShiftRng;
 use serde::{Deserialize, Serialize};
 
-use abstutil::{prettyprint_usize, serialized_size_bytes, CmdArgs, MapName, Timer};
-use geom::{Distance, Duration, Speed, Time};
+use abstutil::{
+    prettyprint_usize, serialized_size_bytes, CmdArgs, MapName, Migration, Timer, Versioned,
+};
+use geom::{Distance, Duration, LonLat, Speed, Time};
 use map_model::{
-    BuildingID, BusRoute, IntersectionID, LaneID, Map, ParkingLotID, Path, PathConstraints,
-    PathRequest, Position, Traversable,
+    BuildingID, BusRoute, BusRouteID, IntersectionID, LaneID, Map, MapEdits, ParkingLotID, Path,
+    PathConstraints, PathRequest, Position, Traversable,
 };
 
-pub use self::queries::{AgentProperties, DelayCause};
+pub use self::queries::{AgentProperties, CarConstraint, CarIntent, DelayCause};
 use crate::{
-    AgentID, AlertLocation, Analytics, CapSimState, CarID, Command, CreateCar, DrivingSimState,
-    Event, IntersectionSimState, OrigPersonID, PandemicModel, ParkedCar, ParkingSim,
-    ParkingSimState, ParkingSpot, Person, PersonID, Router, Scheduler, SidewalkPOI, SidewalkSpot,
+    AgentID, AgentTraces, AlertLocation, Analytics, BikeParkingSimState, CapSimState, CarID,
+    ClosureSimState, Command, CreateCar, CurbSimState, DriverMix, DriverType, DrivingSimState,
+    Event, EventLogger, GuidewaySimState, HouseholdSimState, IntersectionSimState, LaneClosure,
+    MicrotransitSimState, OrigPersonID, PandemicModel, ParkedCar, ParkingCounts, ParkingSearch,
+    ParkingSim, ParkingSimState, ParkingSpot, Person, PersonID, ReplayState, ReroutingSimState,
+    RideHailSimState, Router, SafetyStats, Scheduler, SidewalkPOI, SidewalkSpot, SignalController,
     TrafficRecorder, TransitSimState, TripID, TripInfo, TripLeg, TripManager, TripPhaseType,
-    TripSpec, Vehicle, VehicleSpec, VehicleType, WalkingSimState, BUS_LENGTH, LIGHT_RAIL_LENGTH,
-    MIN_CAR_LENGTH, SPAWN_DIST,
+    TripSpec, TurnCounts, Vehicle, VehicleSpec, VehicleType, WalkingSimState, Weather, BUS_LENGTH,
+    LIGHT_RAIL_LENGTH, MIN_CAR_LENGTH, SPAWN_DIST,
 };
 
 mod queries;
 
 // TODO Do something else.
 const BLIND_RETRY_TO_SPAWN: Duration = Duration::const_seconds(5.0);
+/// In dollars per hour. Roughly half of a typical wage, a common rule of thumb for commuters.
+pub const DEFAULT_VALUE_OF_TIME: f64 = 15.0;
+const DEFAULT_DELIVERY_DWELL_TIME: Duration = Duration::const_seconds(300.0);
+const DEFAULT_MICROTRANSIT_CAPACITY: usize = 6;
+const DEFAULT_MICROTRANSIT_MAX_DELAY: f64 = 1.5;
 
 /// The Sim ties together all the pieces of the simulation. Its main property is the current time.
 #[derive(Serialize, Deserialize, Clone)]
@@ -52,20 +64,69 @@ pub struct Sim {
     // TODO Maybe get rid of this, now that savestates aren't used
     run_name: String,
     step_count: usize,
+    // Only used for monitoring the speed of the simulation, so don't bother preserving it.
+    #[serde(skip_serializing, skip_deserializing)]
+    events_processed: usize,
 
     analytics: Analytics,
+    ride_hail: RideHailSimState,
+    rerouting: ReroutingSimState,
+    bike_parking: BikeParkingSimState,
+    households: HouseholdSimState,
+    curb: CurbSimState,
+    weather: Weather,
+    microtransit: MicrotransitSimState,
+    drivers: DriverMix,
+    safety: SafetyStats,
+    closures: ClosureSimState,
+    turn_counts: TurnCounts,
+    // This is the last field that's serialized, so older savestates can be upgraded by appending
+    // new state.
+    guideways: GuidewaySimState,
     // This is created interactively, and there's no reason to preserve one for savestates.
     #[serde(skip_serializing, skip_deserializing)]
     recorder: Option<TrafficRecorder>,
+    // Traces are recorded to compare against a later run, so they aren't part of a savestate.
+    #[serde(skip_serializing, skip_deserializing)]
+    traces: Option<AgentTraces>,
+    // Replay logs cover a run from the beginning, so they're never part of a savestate.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub(crate) replay: Option<ReplayState>,
+    // Like the recorder, this is requested per run and writes to a file outside of the savestate.
+    #[serde(skip_serializing, skip_deserializing)]
+    event_logger: Option<EventLogger>,
+    // Only used while checking for nondeterminism, and only holds the commands since the last
+    // check.
+    #[serde(skip_serializing, skip_deserializing)]
+    audit: Option<Vec<String>>,
+    #[cfg(feature = "sqlite")]
+    #[serde(skip_serializing, skip_deserializing)]
+    results_db: Option<crate::ResultsDb>,
+    // Pacing depends on the wall-clock of whoever's running the sim right now.
+    #[serde(skip_serializing, skip_deserializing)]
+    pacing: Option<RealtimePacing>,
 
     #[serde(skip_serializing, skip_deserializing)]
     alerts: AlertHandler,
 }
 
+#[derive(Clone)]
+struct RealtimePacing {
+    factor: f64,
+    real_start: Instant,
+    sim_start: Time,
+}
+
 pub(crate) struct Ctx<'a> {
     pub parking: &'a mut ParkingSimState,
     pub intersections: &'a mut IntersectionSimState,
     pub cap: &'a mut CapSimState,
+    pub bike_parking: &'a mut BikeParkingSimState,
+    pub households: &'a mut HouseholdSimState,
+    pub curb: &'a mut CurbSimState,
+    pub guideways: &'a mut GuidewaySimState,
+    pub weather: Weather,
+    pub drivers: &'a DriverMix,
     pub scheduler: &'a mut Scheduler,
     pub map: &'a Map,
     /// If true, live map edits are being processed. Some regular work should maybe be skipped.
@@ -85,8 +146,12 @@ pub struct SimOptions {
     /// mean they'll get stuck blocking the intersection.
     pub dont_block_the_box: bool,
     /// As a vehicle follows a route, opportunistically make small changes to use a different lane,
-    /// based on some score of "least-loaded" lane.
+    /// based on some score of "least-loaded" lane. Vehicles stuck in a queue also move over to a
+    /// parallel lane with a gap further ahead.
     pub recalc_lanechanging: bool,
+    /// When choosing a lane, if the intersection after it is closer than this, pick a lane that
+    /// already lines up with the next turn, instead of changing lanes again at the last moment.
+    pub lane_change_lookahead: Distance,
     /// If a cycle of vehicles depending on each other to turn is detected, temporarily allow
     /// "blocking the box" to try to break gridlock.
     pub break_turn_conflict_cycles: bool,
@@ -115,6 +180,44 @@ pub struct SimOptions {
     /// Don't collect any analytics. Only useful for benchmarking and debugging gridlock more
     /// quickly.
     pub skip_analytics: bool,
+    /// Slow down pedestrians walking along crowded sidewalks.
+    pub pedestrian_crowding: bool,
+    /// Run every traffic signal with an ActuatedController, ignoring the fixed-time plans.
+    pub actuated_signals: bool,
+    /// When a car is queued short of where it stops on its last lane, like a parking spot or bus
+    /// stop, wake it up exactly when it could next get there, instead of retrying every 5 seconds.
+    /// This is more faithful, but can be slower on busy maps. Other retries still poll: spawning a
+    /// car when there's no room, a car's back end clearing a lane right at a rounding boundary, and
+    /// agents yielding at stop signs, signals, and roundabouts.
+    pub exact_end_dist_wakeups: bool,
+    /// Drivers only see free parking on the lane they're currently on. If it's full, they circle
+    /// the block looking for a spot, before searching farther away.
+    pub cruise_for_parking: bool,
+    /// How many dollars an hour of travel time is worth to drivers. They'll pay a toll if it saves
+    /// them enough time.
+    pub value_of_time: f64,
+    /// How many ride-hail vehicles to spread around the map at the start of the day. Only trips
+    /// using ride-hail need them.
+    pub ride_hail_fleet: usize,
+    /// Idle ride-hail vehicles pull over and wait, instead of circling the block.
+    pub ride_hail_pull_over_when_idle: bool,
+    /// If present, measure how long cars actually take to cross each lane. This often, drivers
+    /// whose remaining route crosses a congested lane look for a faster one, using the live travel
+    /// times instead of free-flow speeds.
+    pub live_congestion_routing: Option<Duration>,
+    /// How long delivery vehicles stop in a lane. Map edits control how often they stop on each
+    /// road.
+    pub delivery_dwell_time: Duration,
+    /// How many microtransit shuttles to spread around the zone. Ride-hail trips starting and
+    /// ending in the zone use them instead of the ride-hail fleet.
+    pub microtransit_shuttles: usize,
+    /// How many passengers fit in a shuttle at once
+    pub microtransit_capacity: usize,
+    /// Shuttles only take a detour to serve somebody else if no passenger's ride takes longer
+    /// than this many times the direct ride.
+    pub microtransit_max_delay: f64,
+    /// The area the shuttles serve. If None, they serve the whole map.
+    pub microtransit_zone: Option<Vec<LonLat>>,
 }
 
 impl std::default::Default for SimOptions {
@@ -132,6 +235,10 @@ impl SimOptions {
             use_freeform_policy_everywhere: args.enabled("--freeform_policy"),
             dont_block_the_box: !args.enabled("--disable_block_the_box"),
             recalc_lanechanging: !args.enabled("--disable_recalc_lc"),
+            lane_change_lookahead: args
+                .optional_parse("--lane_change_lookahead", |s| s.parse::<f64>())
+                .map(Distance::meters)
+                .unwrap_or_else(|| Distance::meters(50.0)),
             break_turn_conflict_cycles: !args.enabled("--disable_break_turn_conflict_cycles"),
             handle_uber_turns: !args.enabled("--disable_handle_uber_turns"),
             enable_pandemic_model: if args.enabled("--pandemic") {
@@ -155,6 +262,35 @@ impl SimOptions {
             delay_trips_instead_of_cancelling: args
                 .optional_parse("--delay_trips_instead_of_cancelling", Duration::parse),
             skip_analytics: args.enabled("--skip_analytics"),
+            pedestrian_crowding: args.enabled("--pedestrian_crowding"),
+            actuated_signals: args.enabled("--actuated_signals"),
+            exact_end_dist_wakeups: args.enabled("--exact_end_dist_wakeups"),
+            cruise_for_parking: args.enabled("--cruise_for_parking"),
+            value_of_time: args
+                .optional_parse("--value_of_time", |s| s.parse::<f64>())
+                .unwrap_or(DEFAULT_VALUE_OF_TIME),
+            ride_hail_fleet: args
+                .optional_parse("--ride_hail_fleet", |s| s.parse::<usize>())
+                .unwrap_or(0),
+            ride_hail_pull_over_when_idle: args.enabled("--ride_hail_pull_over_when_idle"),
+            live_congestion_routing: args
+                .optional_parse("--live_congestion_routing", Duration::parse),
+            delivery_dwell_time: args
+                .optional_parse("--delivery_dwell_time", Duration::parse)
+                .unwrap_or(DEFAULT_DELIVERY_DWELL_TIME),
+            microtransit_shuttles: args
+                .optional_parse("--microtransit_shuttles", |s| s.parse::<usize>())
+                .unwrap_or(0),
+            microtransit_capacity: args
+                .optional_parse("--microtransit_capacity", |s| s.parse::<usize>())
+                .unwrap_or(DEFAULT_MICROTRANSIT_CAPACITY),
+            microtransit_max_delay: args
+                .optional_parse("--microtransit_max_delay", |s| s.parse::<f64>())
+                .unwrap_or(DEFAULT_MICROTRANSIT_MAX_DELAY),
+            microtransit_zone: args.optional("--microtransit_zone").map(|path| {
+                LonLat::read_osmosis_polygon(&path)
+                    .unwrap_or_else(|err| panic!("Couldn't read {}: {}", path, err))
+            }),
         }
     }
 }
@@ -182,6 +318,7 @@ impl SimOptions {
             use_freeform_policy_everywhere: false,
             dont_block_the_box: true,
             recalc_lanechanging: true,
+            lane_change_lookahead: Distance::meters(50.0),
             break_turn_conflict_cycles: true,
             handle_uber_turns: true,
             enable_pandemic_model: None,
@@ -191,6 +328,19 @@ impl SimOptions {
             cancel_drivers_delay_threshold: None,
             delay_trips_instead_of_cancelling: None,
             skip_analytics: false,
+            pedestrian_crowding: false,
+            actuated_signals: false,
+            exact_end_dist_wakeups: false,
+            cruise_for_parking: false,
+            value_of_time: DEFAULT_VALUE_OF_TIME,
+            ride_hail_fleet: 0,
+            ride_hail_pull_over_when_idle: false,
+            live_congestion_routing: None,
+            delivery_dwell_time: DEFAULT_DELIVERY_DWELL_TIME,
+            microtransit_shuttles: 0,
+            microtransit_capacity: DEFAULT_MICROTRANSIT_CAPACITY,
+            microtransit_max_delay: DEFAULT_MICROTRANSIT_MAX_DELAY,
+            microtransit_zone: None,
         }
     }
 }
@@ -199,14 +349,21 @@ impl SimOptions {
 impl Sim {
     pub fn new(map: &Map, opts: SimOptions, timer: &mut Timer) -> Sim {
         let mut scheduler = Scheduler::new();
-        Sim {
+        let ride_hail_fleet = opts.ride_hail_fleet;
+        // Some fields of opts get moved below
+        let ride_hail = RideHailSimState::new(&opts);
+        let rerouting = ReroutingSimState::new(&opts);
+        let curb = CurbSimState::new(&opts);
+        let microtransit_shuttles = opts.microtransit_shuttles;
+        let microtransit = MicrotransitSimState::new(&opts, map);
+        let mut sim = Sim {
             driving: DrivingSimState::new(map, &opts),
             parking: ParkingSimState::new(map, opts.infinite_parking, timer),
-            walking: WalkingSimState::new(),
+            walking: WalkingSimState::new(&opts),
             intersections: IntersectionSimState::new(map, &mut scheduler, &opts),
             transit: TransitSimState::new(map),
             cap: CapSimState::new(map, &opts),
-            trips: TripManager::new(),
+            trips: TripManager::new(&opts),
             pandemic: if let Some(rng) = opts.enable_pandemic_model {
                 Some(PandemicModel::new(rng))
             } else {
@@ -219,10 +376,118 @@ impl Sim {
             edits_name: map.get_edits().edits_name.clone(),
             run_name: opts.run_name,
             step_count: 0,
+            events_processed: 0,
             alerts: opts.alerts,
 
             analytics: Analytics::new(!opts.skip_analytics),
+            ride_hail,
+            rerouting,
+            bike_parking: BikeParkingSimState::default(),
+            households: HouseholdSimState::default(),
+            curb,
+            weather: Weather::Clear,
+            microtransit,
+            drivers: DriverMix::default(),
+            safety: SafetyStats::default(),
+            closures: ClosureSimState::default(),
+            turn_counts: TurnCounts::default(),
+            guideways: GuidewaySimState::default(),
             recorder: None,
+            traces: None,
+            replay: None,
+            event_logger: None,
+            audit: None,
+            #[cfg(feature = "sqlite")]
+            results_db: None,
+            pacing: None,
+        };
+        sim.seed_ride_hail_fleet(ride_hail_fleet, map);
+        sim.seed_microtransit(microtransit_shuttles, map);
+        sim.rerouting
+            .schedule_first_refresh(sim.time, &mut sim.scheduler);
+        sim.curb
+            .schedule_deliveries(sim.time, map, &mut sim.scheduler);
+        sim
+    }
+
+    /// Spread ride-hail vehicles evenly over the driving lanes, starting at the beginning of the
+    /// day.
+    fn seed_ride_hail_fleet(&mut self, num: usize, map: &Map) {
+        if num == 0 {
+            return;
+        }
+        let lanes: Vec<LaneID> = map
+            .all_lanes()
+            .iter()
+            .filter(|l| {
+                PathConstraints::Car.can_use(l, map)
+                    && l.length() > MIN_CAR_LENGTH * 2.0
+                    && !map.get_i(l.dst_i).is_border()
+            })
+            .map(|l| l.id)
+            .collect();
+        if lanes.is_empty() {
+            warn!("No lanes to start ride-hail vehicles on");
+            return;
+        }
+        for idx in 0..num {
+            let l = lanes[idx * lanes.len() / num];
+            let vehicle = VehicleSpec {
+                vehicle_type: VehicleType::Car,
+                length: MIN_CAR_LENGTH,
+                max_speed: None,
+            }
+            .make(CarID(self.trips.new_car_id(), VehicleType::Car), None);
+            let start_dist = vehicle.length;
+            let (req, router) = self.ride_hail.add_vehicle(vehicle.clone(), l, map);
+            self.scheduler.push(
+                self.time,
+                Command::SpawnCar(
+                    CreateCar {
+                        start_dist,
+                        router,
+                        vehicle,
+                        req,
+                        maybe_parked_car: None,
+                        trip_and_person: None,
+                        maybe_route: None,
+                    },
+                    true,
+                ),
+            );
+        }
+    }
+
+    /// Spread microtransit shuttles evenly over the driving lanes in their zone. They start the
+    /// day pulled over, waiting for requests.
+    fn seed_microtransit(&mut self, num: usize, map: &Map) {
+        if num == 0 {
+            return;
+        }
+        let lanes: Vec<LaneID> = map
+            .all_lanes()
+            .iter()
+            .filter(|l| {
+                PathConstraints::Car.can_use(l, map)
+                    && l.length() > MIN_CAR_LENGTH * 2.0
+                    && !map.get_i(l.dst_i).is_border()
+                    && self.microtransit.in_zone(l.lane_center_pts.middle())
+            })
+            .map(|l| l.id)
+            .collect();
+        if lanes.is_empty() {
+            warn!("No lanes in the zone to start microtransit shuttles on");
+            return;
+        }
+        for idx in 0..num {
+            let vehicle = VehicleSpec {
+                vehicle_type: VehicleType::Car,
+                length: MIN_CAR_LENGTH * 1.5,
+                max_speed: None,
+            }
+            .make(CarID(self.trips.new_car_id(), VehicleType::Car), None);
+            self.microtransit
+                .add_shuttle(vehicle, lanes[idx * lanes.len() / num], map);
         }
     }
 
@@ -269,6 +534,13 @@ impl Sim {
         self.parking.get_all_parking_spots()
     }
 
+    /// How many spots every block face, building, and lot has, filled or not
+    pub fn get_parking_capacity(&self) -> ParkingCounts {
+        let (mut spots, avail) = self.parking.get_all_parking_spots();
+        spots.extend(avail);
+        ParkingCounts::new(spots)
+    }
+
     pub fn bldg_to_parked_cars(&self, b: BuildingID) -> Vec<CarID> {
         self.parking.bldg_to_parked_cars(b)
     }
@@ -287,20 +559,25 @@ impl Sim {
             max_speed: None,
         };
         let driving_lane = map.find_driving_lane_near_building(b);
+        let search = ParkingSearch {
+            target: b,
+            now: self.time,
+            leave_at: None,
+        };
 
         // Anything on the current lane? TODO Should find the closest one to the sidewalk, but
         // need a new method in ParkingSimState to make that easy.
         // TODO Refactor the logic in router
         let spot = if let Some((spot, _)) = self
             .parking
-            .get_all_free_spots(Position::start(driving_lane), &vehicle, b, map)
+            .get_all_free_spots(Position::start(driving_lane), &vehicle, search, map)
             .get(0)
         {
             spot.clone()
         } else {
             let (_, spot, _) =
                 self.parking
-                    .path_to_free_parking_spot(driving_lane, &vehicle, b, map)?;
+                    .path_to_free_parking_spot(driving_lane, &vehicle, search, map)?;
             spot
         };
 
@@ -320,7 +597,14 @@ impl Sim {
         ped_speed: Speed,
         vehicle_specs: Vec<VehicleSpec>,
     ) -> &Person {
-        self.trips.new_person(orig_id, ped_speed, vehicle_specs)
+        self.trips.new_person(
+            orig_id,
+            ped_speed * self.weather.speed_factor(),
+            vehicle_specs,
+        )
+    }
+    pub(crate) fn new_household(&mut self, members: Vec<PersonID>) {
+        self.households.add_household(members);
     }
     pub(crate) fn seed_parked_car(&mut self, vehicle: Vehicle, spot: ParkingSpot) {
         self.parking.reserve_spot(spot, vehicle.id);
@@ -331,9 +615,17 @@ impl Sim {
         });
     }
 
-    pub(crate) fn seed_bus_route(&mut self, route: &BusRoute) {
-        for t in &route.spawn_times {
-            self.scheduler.push(*t, Command::StartBus(route.id, *t));
+    /// Schedule every bus on the route that hasn't started yet, repeating the schedule for
+    /// `extra_days` after the first.
+    pub(crate) fn seed_bus_route(&mut self, route: &BusRoute, extra_days: usize) {
+        for day in 0..=extra_days {
+            let offset = Duration::hours(24 * day);
+            for t in &route.spawn_times {
+                let t = *t + offset;
+                if t >= self.time {
+                    self.scheduler.push(t, Command::StartBus(route.id, t));
+                }
+            }
         }
     }
 
@@ -386,6 +678,46 @@ impl Sim {
     pub fn set_name(&mut self, name: String) {
         self.run_name = name;
     }
+
+    /// Weather has to be set before anybody's created, since it affects their walking speed and
+    /// how much room vehicles reserve on lanes.
+    pub fn set_weather(&mut self, weather: Weather) {
+        if weather == self.weather {
+            return;
+        }
+        assert!(
+            self.is_empty(),
+            "Can't change the weather once the simulation has started"
+        );
+        self.weather = weather;
+    }
+
+    /// Like the weather, the mix of drivers has to be set before anybody's created.
+    pub fn set_driver_mix(&mut self, types: Vec<DriverType>) {
+        let drivers = DriverMix::new(types);
+        if drivers == self.drivers {
+            return;
+        }
+        assert!(
+            self.is_empty(),
+            "Can't change the mix of drivers once the simulation has started"
+        );
+        self.drivers = drivers;
+    }
+
+    /// Schedules lanes to close for part of the day. Like the weather, this has to be set before
+    /// anybody's created.
+    pub fn set_lane_closures(&mut self, closures: Vec<LaneClosure>) {
+        let closures = ClosureSimState::new(closures);
+        if closures == self.closures {
+            return;
+        }
+        assert!(
+            self.is_empty(),
+            "Can't change lane closures once the simulation has started"
+        );
+        self.closures = closures;
+    }
 }
 
 // Running
@@ -403,12 +735,16 @@ impl Sim {
         let max_time = if let Some(t) = self.scheduler.peek_next_time() {
             if t > self.time + max_dt {
                 // Next event is after when we want to stop.
+                self.record_traces_until(self.time + max_dt, map);
+                self.count_parking_until(self.time + max_dt);
                 self.time += max_dt;
                 return false;
             }
             t
         } else {
             // No events left at all
+            self.record_traces_until(self.time + max_dt, map);
+            self.count_parking_until(self.time + max_dt);
             self.time += max_dt;
             return false;
         };
@@ -418,6 +754,8 @@ impl Sim {
             if time > max_time {
                 return false;
             }
+            self.record_traces_until(time, map);
+            self.count_parking_until(time);
             if let Some(cmd) = self.scheduler.get_next() {
                 if self.do_step(map, time, cmd, maybe_cb) {
                     halt = true;
@@ -441,10 +779,23 @@ impl Sim {
         let mut events = Vec::new();
         let mut halt = false;
 
+        if let Some(ref mut replay) = self.replay {
+            replay.handle_cmd(time, &cmd);
+        }
+        if let Some(ref mut audit) = self.audit {
+            audit.push(format!("{} {:?}", time, cmd));
+        }
+
         let mut ctx = Ctx {
             parking: &mut self.parking,
             intersections: &mut self.intersections,
             cap: &mut self.cap,
+            bike_parking: &mut self.bike_parking,
+            households: &mut self.households,
+            curb: &mut self.curb,
+            guideways: &mut self.guideways,
+            weather: self.weather,
+            drivers: &self.drivers,
             scheduler: &mut self.scheduler,
             map,
             handling_live_edits: false,
@@ -495,9 +846,9 @@ impl Sim {
                     let maybe_parked_car = create_car.maybe_parked_car.clone();
                     let req = create_car.req.clone();
 
-                    if let Some(create_car) = self
-                        .driving
-                        .start_car_on_lane(self.time, create_car, &mut ctx)
+                    if let Some(create_car) =
+                        self.driving
+                            .start_car_on_lane(self.time, create_car, &mut ctx, &self.trips)
                     {
                         // Starting the car failed for some reason.
                         if retry_if_no_room {
@@ -528,10 +879,10 @@ impl Sim {
                                 trip,
                                 person,
                                 Some(req),
-                                if id.1 == VehicleType::Car {
-                                    TripPhaseType::Driving
-                                } else {
+                                if id.1 == VehicleType::Bike {
                                     TripPhaseType::Biking
+                                } else {
+                                    TripPhaseType::Driving
                                 },
                             ));
                         }
@@ -599,6 +950,8 @@ impl Sim {
                     &mut self.trips,
                     &mut self.transit,
                     &mut self.walking,
+                    &mut self.ride_hail,
+                    &mut self.microtransit,
                 );
             }
             Command::UpdateLaggyHead(car) => {
@@ -633,6 +986,47 @@ impl Sim {
             Command::StartBus(r, _) => {
                 self.start_bus(map.get_br(r), map);
             }
+            Command::RequestRideHail(req) => {
+                if self.microtransit.serves(&req, map) {
+                    self.microtransit
+                        .request(self.time, req, &mut self.trips, &mut ctx);
+                } else {
+                    self.ride_hail
+                        .request(self.time, req, &mut self.trips, &mut ctx);
+                }
+            }
+            Command::RefreshTravelTimes => {
+                self.rerouting.refresh(
+                    self.time,
+                    self.trips.value_of_time(),
+                    &mut self.driving,
+                    &mut self.scheduler,
+                    map,
+                );
+            }
+            Command::UpdateGuidewayRider(trip) => {
+                let (guideway, got_off) = ctx.guideways.update_rider(trip, ctx.scheduler);
+                if got_off {
+                    self.trips.person_left_guideway(self.time, trip, &mut ctx);
+                } else {
+                    self.trips.person_boarded_guideway(trip, guideway);
+                }
+            }
+            Command::StartDelivery(r) => {
+                let id = CarID(self.trips.new_car_id(), VehicleType::Car);
+                if let Some(create_car) =
+                    ctx.curb
+                        .start_delivery(self.time, r, id, ctx.map, ctx.scheduler)
+                {
+                    if self
+                        .driving
+                        .start_car_on_lane(self.time, create_car, &mut ctx, &self.trips)
+                        .is_some()
+                    {
+                        ctx.curb.no_room(id);
+                    }
+                }
+            }
         }
 
         // Record events at precisely the time they occur.
@@ -648,6 +1042,7 @@ impl Sim {
         events.extend(self.walking.collect_events());
         events.extend(self.intersections.collect_events());
         events.extend(self.parking.collect_events());
+        self.events_processed += events.len();
         for ev in events {
             if let Some(ref mut m) = self.pandemic {
                 m.handle_event(self.time, &ev, &mut self.scheduler);
@@ -655,6 +1050,18 @@ impl Sim {
             if let Some(ref mut r) = self.recorder {
                 r.handle_event(self.time, &ev, map, &self.driving, &self.trips);
             }
+            if let Some(ref mut l) = self.event_logger {
+                l.handle_event(self.time, &ev);
+            }
+            #[cfg(feature = "sqlite")]
+            {
+                if let Some(ref mut db) = self.results_db {
+                    db.handle_event(self.time, &ev);
+                }
+            }
+            self.rerouting.handle_event(self.time, &ev, map);
+            self.safety.handle_event(self.time, &ev);
+            self.turn_counts.handle_event(self.time, &ev, map);
 
             self.analytics.event(ev, self.time, map);
         }
@@ -676,6 +1083,7 @@ impl Sim {
             if self.minimal_step(map, end_time - self.time, maybe_cb) {
                 break;
             }
+            self.wait_for_realtime();
             if !self.analytics.alerts.is_empty() {
                 match self.alerts {
                     AlertHandler::Print => {
@@ -705,6 +1113,7 @@ impl Sim {
                 last_update = Instant::now();
             }
         }
+        self.flush_event_log();
         timer.stop(format!("Advance sim to {}", end_time));
     }
     pub fn tiny_step(&mut self, map: &Map, maybe_cb: &mut Option<Box<dyn SimCallback>>) {
@@ -730,6 +1139,7 @@ impl Sim {
             if self.minimal_step(map, end_time - self.time, maybe_cb) {
                 break;
             }
+            self.wait_for_realtime();
             if !self.analytics.alerts.is_empty() {
                 match self.alerts {
                     AlertHandler::Print => {
@@ -749,6 +1159,7 @@ impl Sim {
                 }
             }
         }
+        self.flush_event_log();
     }
 
     pub fn dump_before_abort(&self) {
@@ -799,6 +1210,18 @@ impl Sim {
                 "- transit: {} bytes",
                 prettyprint_usize(serialized_size_bytes(&self.transit))
             );
+            println!(
+                "- ride-hail: {} bytes",
+                prettyprint_usize(serialized_size_bytes(&self.ride_hail))
+            );
+            println!(
+                "- microtransit: {} bytes",
+                prettyprint_usize(serialized_size_bytes(&self.microtransit))
+            );
+            println!(
+                "- guideways: {} bytes",
+                prettyprint_usize(serialized_size_bytes(&self.guideways))
+            );
             println!(
                 "- cap: {} bytes",
                 prettyprint_usize(serialized_size_bytes(&self.cap))
@@ -814,7 +1237,7 @@ impl Sim {
         }
 
         let path = self.save_path(self.time);
-        abstutil::write_binary(path.clone(), self);
+        abstutil::write_versioned_binary(path.clone(), self);
 
         path
     }
@@ -828,7 +1251,178 @@ impl Sim {
     }
 
     pub fn load_savestate(path: String, timer: &mut Timer) -> Result<Sim, String> {
-        abstutil::maybe_read_binary(path, timer)
+        abstutil::maybe_read_versioned_binary(path, timer)
+    }
+
+    /// Loads a savestate made on this map, then applies edits on top of it. This way, a warm-up
+    /// shared by many experiments only has to be simulated once. Agents affected by the edits are
+    /// rerouted or cancelled, just like any other live edit. Also returns the number of (trips
+    /// cancelled, parked cars displaced).
+    pub fn load_savestate_with_edits(
+        path: String,
+        map: &mut Map,
+        edits: MapEdits,
+        timer: &mut Timer,
+    ) -> Result<(Sim, (usize, usize)), String> {
+        let mut sim = Sim::load_savestate(path, timer)?;
+        if &sim.map_name != map.get_name() {
+            return Err(format!(
+                "savestate is for {}, not {}",
+                sim.map_name.describe(),
+                map.get_name().describe()
+            ));
+        }
+        if sim.edits_name != map.get_edits().edits_name {
+            return Err(format!(
+                "savestate was made with edits {}, but the map has {}",
+                sim.edits_name,
+                map.get_edits().edits_name
+            ));
+        }
+
+        // The map doesn't have the lane closures from when the savestate was made
+        sim.closures.forget_closed();
+        map.must_apply_edits(edits, timer);
+        map.recalculate_pathfinding_after_edits(timer);
+        let affected = sim.handle_live_edits(map);
+        sim.apply_lane_closures(map, timer);
+        Ok((sim, affected))
+    }
+}
+
+impl Versioned for Sim {
+    const FORMAT: &'static str = "savestate";
+    // Bump this after changing anything serialized in the simulation, and add a migration below.
+    const VERSION: u32 = 15;
+
+    fn migrations() -> Vec<Migration> {
+        vec![
+            // Driving state changed shape before versioning existed, so unversioned savestates
+            // can't be upgraded.
+            Migration {
+                from: 1,
+                description: "add an empty ride-hail fleet",
+                upgrade: |mut bytes| {
+                    let fleet = RideHailSimState::new(&SimOptions::default());
+                    bytes.extend(abstutil::to_binary(&fleet));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 2,
+                description: "add live rerouting, disabled",
+                upgrade: |mut bytes| {
+                    let rerouting = ReroutingSimState::new(&SimOptions::default());
+                    bytes.extend(abstutil::to_binary(&rerouting));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 3,
+                description: "add empty bike racks",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&BikeParkingSimState::default()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 4,
+                description: "add no households",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&HouseholdSimState::default()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 5,
+                description: "add no deliveries",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&CurbSimState::new(
+                        &SimOptions::default(),
+                    )));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 6,
+                description: "add clear weather",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&Weather::Clear));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 7,
+                description: "add no microtransit shuttles",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&MicrotransitSimState::default()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 8,
+                description: "add a mix of drivers who all behave the same",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&DriverMix::default()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 9,
+                description: "add no safety events",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&SafetyStats::default()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 10,
+                description: "add no lane closures",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&ClosureSimState::default()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 11,
+                description: "add no turning movement counts",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&TurnCounts::default()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 12,
+                description: "add no guideway riders",
+                upgrade: |mut bytes| {
+                    bytes.extend(abstutil::to_binary(&GuidewaySimState::default()));
+                    Ok(bytes)
+                },
+            },
+            Migration {
+                from: 13,
+                description: "add no parking occupancy",
+                upgrade: |bytes| abstutil::upgrade_added_fields::<Sim>(bytes, 13),
+            },
+            Migration {
+                from: 14,
+                description: "count turns taken while cruising for parking",
+                upgrade: |bytes| abstutil::upgrade_added_fields::<Sim>(bytes, 14),
+            },
+        ]
+    }
+}
+
+// Traffic signal control
+impl Sim {
+    /// Replace the fixed-time plan of a traffic signal with a controller that decides stages at
+    /// runtime. The controller takes over when the current stage ends.
+    pub fn set_signal_controller(
+        &mut self,
+        i: IntersectionID,
+        controller: Box<dyn SignalController>,
+    ) {
+        self.intersections.set_signal_controller(i, controller);
     }
 }
 
@@ -839,6 +1433,13 @@ impl Sim {
             .handle_live_edited_traffic_signals(self.time, map, &mut self.scheduler)
     }
 
+    /// Live edits to a signal normally only take effect at the end of its current stage. After
+    /// changing offsets, this makes the signals jump to where their new offset puts them.
+    pub fn restart_traffic_signals(&mut self, signals: &BTreeSet<IntersectionID>, map: &Map) {
+        self.intersections
+            .restart_traffic_signals(self.time, signals, map, &mut self.scheduler)
+    }
+
     /// Respond to arbitrary map edits without resetting the simulation. Returns the number of
     /// (trips cancelled, parked cars displaced).
     pub fn handle_live_edits(&mut self, map: &Map) -> (usize, usize) {
@@ -847,13 +1448,20 @@ impl Sim {
         let (affected, num_parked_cars) = self.find_trips_affected_by_live_edits(map);
         let num_trips_cancelled = affected.len();
 
-        // V1: Just cancel every trip crossing an affected area.
-        // (V2 is probably rerouting everyone, only cancelling when that fails)
+        // Cars that could be rerouted already have been. Cancel every other trip crossing an
+        // affected area.
+        // TODO Reroute pedestrians too
         // TODO If we delete a bus, deal with all its passengers
         let mut ctx = Ctx {
             parking: &mut self.parking,
             intersections: &mut self.intersections,
             cap: &mut self.cap,
+            bike_parking: &mut self.bike_parking,
+            households: &mut self.households,
+            curb: &mut self.curb,
+            guideways: &mut self.guideways,
+            weather: self.weather,
+            drivers: &self.drivers,
             scheduler: &mut self.scheduler,
             map,
             handling_live_edits: true,
@@ -890,28 +1498,54 @@ impl Sim {
 
         self.driving.handle_live_edits(map);
         self.intersections.handle_live_edits(map);
+        // Routes may have been created, deleted, rescheduled, or given new stops. Start over
+        // scheduling the buses that haven't left yet, on routes that were already running or that
+        // the edits touch.
+        self.transit.handle_live_edits(map);
+        let cancelled = self.scheduler.cancel_bus_starts();
+        let extra_days = cancelled
+            .iter()
+            .map(|(_, t)| ((*t - Time::START_OF_DAY) / Duration::hours(24)) as usize)
+            .max()
+            .unwrap_or(0);
+        let running: BTreeSet<BusRouteID> = cancelled.into_iter().map(|(r, _)| r).collect();
+        for route in map.all_bus_routes() {
+            if running.contains(&route.id)
+                || route.is_from_edits()
+                || map.get_edits().changed_routes.contains(&route.id)
+            {
+                self.seed_bus_route(route, extra_days);
+            }
+        }
+        self.curb
+            .schedule_deliveries(self.time, map, &mut self.scheduler);
 
         (num_trips_cancelled, num_parked_cars)
     }
 
-    /// Returns (trips affected, number of parked cars displaced)
+    /// Returns (trips affected, number of parked cars displaced). Cars that can be rerouted around
+    /// the edits get a new path and aren't included.
     fn find_trips_affected_by_live_edits(
         &mut self,
         map: &Map,
     ) -> (BTreeSet<(AgentID, TripID)>, usize) {
         let mut affected: BTreeSet<(AgentID, TripID)> = BTreeSet::new();
 
+        // Cars whose upcoming path crosses something edited, but otherwise aren't affected
+        let mut maybe_reroute: BTreeSet<(AgentID, TripID)> = BTreeSet::new();
+
         // TODO Handle changes to access restrictions
 
+        let (edited_lanes, _) = map.get_edits().changed_lanes(map);
+        let mut closed_intersections = HashSet::new();
+        for i in map.get_edits().original_intersections.keys() {
+            if map.get_i(*i).is_closed() {
+                closed_intersections.insert(*i);
+            }
+        }
+
         {
             // Find every active trip whose path crosses a modified lane or intersection
-            let (edited_lanes, _) = map.get_edits().changed_lanes(map);
-            let mut closed_intersections = HashSet::new();
-            for i in map.get_edits().original_intersections.keys() {
-                if map.get_i(*i).is_closed() {
-                    closed_intersections.insert(*i);
-                }
-            }
             for (a, trip) in self.trips.active_agents_and_trips() {
                 if let Some(path) = self.get_path(*a) {
                     if path
@@ -926,7 +1560,11 @@ impl Sim {
                             }
                         })
                     {
-                        affected.insert((*a, *trip));
+                        if let AgentID::Car(_) = a {
+                            maybe_reroute.insert((*a, *trip));
+                        } else {
+                            affected.insert((*a, *trip));
+                        }
                     }
                 }
             }
@@ -957,8 +1595,46 @@ impl Sim {
             num_evicted
         };
 
+        for (a, trip) in maybe_reroute {
+            if affected.contains(&(a, trip)) {
+                continue;
+            }
+            if let AgentID::Car(car) = a {
+                if self.driving.reroute_after_live_edits(
+                    car,
+                    &closed_intersections,
+                    &edited_lanes,
+                    map,
+                ) {
+                    continue;
+                }
+            }
+            affected.insert((a, trip));
+        }
+
         (affected, num_evicted)
     }
+
+    /// Opens and closes lanes as scheduled by `set_lane_closures`, using live map edits. Call this
+    /// as the simulation runs, ideally stepping no further than `next_lane_closure_change` at a
+    /// time. Returns true if the map changed.
+    pub fn apply_lane_closures(&mut self, map: &mut Map, timer: &mut Timer) -> bool {
+        let edits = match self.closures.update(self.time, map) {
+            Some(edits) => edits,
+            None => {
+                return false;
+            }
+        };
+        map.must_apply_edits(edits, timer);
+        map.recalculate_pathfinding_after_edits(timer);
+        self.handle_live_edits(map);
+        true
+    }
+
+    /// The next time some lane is scheduled to open or close.
+    pub fn next_lane_closure_change(&self) -> Option<Time> {
+        self.closures.next_change(self.time)
+    }
 }
 
 // Invasive debugging
@@ -969,6 +1645,12 @@ impl Sim {
                 parking: &mut self.parking,
                 intersections: &mut self.intersections,
                 cap: &mut self.cap,
+                bike_parking: &mut self.bike_parking,
+                households: &mut self.households,
+                curb: &mut self.curb,
+                guideways: &mut self.guideways,
+                weather: self.weather,
+                drivers: &self.drivers,
                 scheduler: &mut self.scheduler,
                 map,
                 handling_live_edits: false,
@@ -1027,3 +1709,186 @@ impl Sim {
         self.recorder.take().unwrap().save(map);
     }
 }
+
+// Recording agent traces
+impl Sim {
+    /// From now on, sample the position of these people (or everybody, if None) periodically.
+    /// Another run can show them as ghosts; see `AgentTraces`.
+    pub fn record_traces(
+        &mut self,
+        map: &Map,
+        people: Option<BTreeSet<PersonID>>,
+        every: Duration,
+    ) {
+        assert!(self.traces.is_none());
+        self.traces = Some(AgentTraces::new(map, self.time, people, every));
+    }
+
+    /// Also records this person, if some people are already being recorded.
+    pub fn also_record_trace(&mut self, person: PersonID) {
+        if let Some(ref mut traces) = self.traces {
+            traces.add_person(person);
+        }
+    }
+
+    pub fn get_trace_recording(&self) -> Option<&AgentTraces> {
+        self.traces.as_ref()
+    }
+
+    pub fn stop_recording_traces(&mut self) -> Option<AgentTraces> {
+        self.traces.take()
+    }
+
+    /// Takes every sample due up to this time. Nothing happens between the current time and this
+    /// one, so positions can be calculated for any time in between.
+    fn record_traces_until(&mut self, until: Time, map: &Map) {
+        if let Some(ref mut traces) = self.traces {
+            while traces.next_sample() <= until {
+                let time = traces.next_sample();
+                let mut agents = self.driving.get_unzoomed_agents(time, map);
+                agents.extend(self.walking.get_unzoomed_agents(time, map));
+                traces.record(time, agents);
+            }
+        }
+    }
+
+    /// Counts filled parking spots for every count due up to this time. Like traces, nothing
+    /// happens in between, so the current state is the state at each of those times.
+    fn count_parking_until(&mut self, until: Time) {
+        // Infinite parking has a spot for everybody, so occupancy is meaningless
+        if self.parking.is_infinite() {
+            return;
+        }
+        while let Some(time) = self.analytics.next_parking_count(until) {
+            let counts = ParkingCounts::new(self.parking.get_all_parking_spots().0);
+            self.analytics.parking_occupancy.push((time, counts));
+        }
+    }
+}
+
+// Checking for nondeterminism
+impl Sim {
+    /// From now on, remember every command processed, until `take_audited_commands` is called.
+    pub fn audit_commands(&mut self) {
+        self.audit = Some(Vec::new());
+    }
+
+    /// Returns every command processed since the last call, with the time it happened.
+    pub fn take_audited_commands(&mut self) -> Vec<String> {
+        self.audit
+            .as_mut()
+            .map(|audit| std::mem::replace(audit, Vec::new()))
+            .unwrap_or_else(Vec::new)
+    }
+
+    /// Summarizes where every agent is and how many trips are done. Two copies of the same
+    /// simulation should always produce the same checksum at the same time.
+    pub fn checksum(&self, map: &Map) -> u64 {
+        let mut agents = self.get_unzoomed_agents(map);
+        agents.sort_by_key(|a| a.id);
+
+        let mut hasher = DefaultHasher::new();
+        self.time.inner_seconds().to_bits().hash(&mut hasher);
+        for a in agents {
+            a.id.hash(&mut hasher);
+            a.pos.to_hashable().hash(&mut hasher);
+            a.person.hash(&mut hasher);
+        }
+        self.analytics.finished_trips.len().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+// Pacing against the wall-clock
+impl Sim {
+    /// From now on, don't let `timed_step` or `time_limited_step` run faster than `factor` times
+    /// real time; they sleep as needed. This keeps the simulation synchronized with something
+    /// external, like a signal controller driving the sim through an API. Pacing is measured from
+    /// this call, so if the caller stops stepping for a while, the next steps run flat out to
+    /// catch up. Pass `None` to run as fast as possible again.
+    ///
+    /// This sleeps the current thread, so it's only meant for native builds.
+    pub fn set_realtime_factor(&mut self, factor: Option<f64>) {
+        self.pacing = factor.map(|factor| {
+            assert!(factor > 0.0, "realtime factor {} must be positive", factor);
+            RealtimePacing {
+                factor,
+                real_start: Instant::now(),
+                sim_start: self.time,
+            }
+        });
+    }
+
+    pub fn get_realtime_factor(&self) -> Option<f64> {
+        self.pacing.as_ref().map(|p| p.factor)
+    }
+
+    fn wait_for_realtime(&self) {
+        if let Some(ref p) = self.pacing {
+            let ahead =
+                (self.time - p.sim_start) / p.factor - Duration::realtime_elapsed(p.real_start);
+            if ahead > Duration::ZERO {
+                std::thread::sleep(std::time::Duration::from_secs_f64(ahead.inner_seconds()));
+            }
+        }
+    }
+}
+
+// Logging events
+impl Sim {
+    /// From now on, append every event to a CSV file at this path, overwriting anything already
+    /// there. See `EVENT_LOG_HEADER` for the schema. The log is flushed at the end of every
+    /// `timed_step` and `time_limited_step`, and by `flush_event_log`.
+    pub fn log_events_to(&mut self, path: String) -> Result<(), String> {
+        assert!(self.event_logger.is_none());
+        self.event_logger = Some(EventLogger::new(path)?);
+        Ok(())
+    }
+
+    /// Also inserts buffered events into the results database, if there is one.
+    pub fn flush_event_log(&mut self) {
+        if let Some(ref mut l) = self.event_logger {
+            l.flush();
+        }
+        #[cfg(feature = "sqlite")]
+        {
+            if let Some(ref mut db) = self.results_db {
+                db.flush();
+            }
+        }
+    }
+
+    /// From now on, record every event in a SQLite database at this path, creating it if needed.
+    /// Many runs can share one database; each is identified by a row in the `runs` table. Call
+    /// `finish_results_db` at the end of the run to also record trips and throughput. See
+    /// sim/src/results_db.rs for the schema.
+    #[cfg(feature = "sqlite")]
+    pub fn write_results_to(
+        &mut self,
+        path: String,
+        run_name: String,
+        map: &Map,
+    ) -> Result<(), String> {
+        assert!(self.results_db.is_none());
+        self.results_db = Some(crate::ResultsDb::new(path, run_name, map, self.time)?);
+        Ok(())
+    }
+
+    /// Writes everything left to the results database and stops recording to it.
+    #[cfg(feature = "sqlite")]
+    pub fn finish_results_db(&mut self) -> Result<(), String> {
+        let mut db = match self.results_db.take() {
+            Some(db) => db,
+            None => {
+                return Err("write_results_to was never called".to_string());
+            }
+        };
+        let trips = self
+            .trips
+            .all_trip_info()
+            .into_iter()
+            .map(|(id, info)| (id, info, self.trips.trip_to_person(id)))
+            .collect();
+        db.finish(self.time, trips, &self.analytics)
+    }
+}
@@ -3,18 +3,21 @@
 use serde::Serialize;
 use std::collections::BTreeMap;
 
-use abstutil::Counter;
-use geom::{Distance, Duration, PolyLine, Pt2D, Time};
+use abstutil::{serialized_size_bytes, Counter};
+use geom::{Distance, Duration, PolyLine, Pt2D, Speed, Time};
 use map_model::{
-    BuildingID, BusRouteID, BusStopID, IntersectionID, Lane, LaneID, Map, Path, Position,
+    BuildingID, BusRouteID, BusStopID, IntersectionID, Lane, LaneID, Map, Path, PathStep, Position,
     Traversable, TurnID,
 };
 
 use crate::analytics::Window;
 use crate::{
-    AgentID, AgentType, Analytics, CarID, CommutersVehiclesCounts, DrawCarInput, DrawPedCrowdInput,
-    DrawPedestrianInput, OrigPersonID, PandemicModel, ParkedCar, ParkingSim, PedestrianID, Person,
-    PersonID, PersonState, Scenario, Sim, TripID, TripInfo, TripResult, UnzoomedAgent, VehicleType,
+    AgentID, AgentType, Analytics, BikeRackOccupancy, CarID, CommutersVehiclesCounts, CurbStats,
+    DelayStats, DrawCarInput, DrawPedCrowdInput, DrawPedestrianInput, GuidewayRidership,
+    HeadwayStats, HouseholdStats, MicrotransitStats, OrigPersonID, PandemicModel, ParkedCar,
+    ParkingSim, PedestrianID, Person, PersonID, PersonState, ReroutingStats, RideHailStats,
+    SafetyStats, Scenario, Sim, TripID, TripInfo, TripResult, TurnCounts, UnzoomedAgent,
+    VehicleType, Weather,
 };
 
 // TODO Many of these just delegate to an inner piece. This is unorganized and hard to maintain.
@@ -36,11 +39,16 @@ impl Sim {
         self.trips.num_trips()
     }
     pub fn num_agents(&self) -> Counter<AgentType> {
-        self.trips.num_agents(&self.transit)
+        self.trips
+            .num_agents(&self.transit, &self.ride_hail, &self.microtransit)
     }
     pub fn num_commuters_vehicles(&self) -> CommutersVehiclesCounts {
-        self.trips
-            .num_commuters_vehicles(&self.transit, &self.walking)
+        self.trips.num_commuters_vehicles(
+            &self.transit,
+            &self.walking,
+            &self.ride_hail,
+            &self.microtransit,
+        )
     }
     /// (total number of people, just in buildings, just off map)
     pub fn num_ppl(&self) -> (usize, usize, usize) {
@@ -57,6 +65,12 @@ impl Sim {
         self.trips.debug_trip(AgentID::Car(id));
     }
 
+    /// What a moving vehicle is doing and why. None if the car isn't on the map.
+    pub fn car_intent(&self, id: CarID, map: &Map) -> Option<CarIntent> {
+        self.driving
+            .car_intent(id, self.time, map, &self.intersections, self.weather)
+    }
+
     pub fn debug_intersection(&self, id: IntersectionID, map: &Map) {
         self.intersections.debug(id, map);
     }
@@ -93,6 +107,73 @@ impl Sim {
         }
     }
 
+    /// If this car belongs to the ride-hail fleet, describes what it's doing.
+    pub fn describe_ride_hail_vehicle(&self, id: CarID) -> Option<String> {
+        self.ride_hail.describe_vehicle(id)
+    }
+
+    pub fn get_ride_hail_stats(&self) -> RideHailStats {
+        self.ride_hail.get_stats()
+    }
+
+    /// If this car is a microtransit shuttle, describes what it's doing.
+    pub fn describe_shuttle(&self, id: CarID) -> Option<String> {
+        self.microtransit.describe_vehicle(id)
+    }
+
+    pub fn get_microtransit_stats(&self) -> MicrotransitStats {
+        self.microtransit.get_stats()
+    }
+
+    /// If this car is making a delivery, describes what it's doing.
+    pub fn describe_delivery_vehicle(&self, id: CarID) -> Option<String> {
+        self.curb.describe_vehicle(id)
+    }
+
+    pub fn get_curb_stats(&self) -> CurbStats {
+        self.curb.get_stats()
+    }
+
+    pub fn get_weather(&self) -> Weather {
+        self.weather
+    }
+
+    /// How often drivers have rerouted around congestion. Only meaningful when
+    /// `live_congestion_routing` is enabled.
+    pub fn get_rerouting_stats(&self) -> ReroutingStats {
+        self.rerouting.get_stats()
+    }
+
+    /// Near misses so far, as a surrogate for crashes
+    pub fn get_safety_stats(&self) -> &SafetyStats {
+        &self.safety
+    }
+
+    /// How many agents have made each turn, for turning movement counts
+    pub fn get_turn_counts(&self) -> &TurnCounts {
+        &self.turn_counts
+    }
+
+    /// Ridership so far on each guideway with any riders, keyed by the guideway's index in the map
+    pub fn get_guideway_ridership(&self) -> &BTreeMap<usize, GuidewayRidership> {
+        self.guideways.get_ridership()
+    }
+
+    /// How households have shared their cars so far
+    pub fn get_household_stats(&self) -> &HouseholdStats {
+        self.households.get_stats()
+    }
+
+    /// How the bike racks by a building have been used so far
+    pub fn get_bike_rack_occupancy(&self, b: BuildingID) -> BikeRackOccupancy {
+        self.bike_parking.get_occupancy(b)
+    }
+
+    /// Only includes buildings where somebody has parked a bike
+    pub fn all_bike_rack_occupancy(&self) -> &BTreeMap<BuildingID, BikeRackOccupancy> {
+        self.bike_parking.all_occupancy()
+    }
+
     pub fn active_agents(&self) -> Vec<AgentID> {
         self.trips.active_agents()
     }
@@ -187,6 +268,8 @@ impl Sim {
             VehicleType::Bike,
             VehicleType::Bus,
             VehicleType::Train,
+            VehicleType::Truck,
+            VehicleType::Emergency,
         ] {
             let id = CarID(idx, *vt);
             if self.driving.does_car_exist(id) {
@@ -270,7 +353,7 @@ impl Sim {
     pub fn get_blocked_by_graph(&self, map: &Map) -> BTreeMap<AgentID, (Duration, DelayCause)> {
         // Pedestrians can only be blocked at intersections, which is handled inside this call
         self.driving
-            .get_blocked_by_graph(self.time, map, &self.intersections)
+            .get_blocked_by_graph(self.time, map, &self.intersections, self.weather)
     }
 
     /// (bus, stop index it's coming from, percent to next stop, location)
@@ -295,6 +378,22 @@ impl Sim {
         &self.analytics
     }
 
+    /// Percentiles of the delay through an intersection so far, grouped into time buckets.
+    pub fn get_intersection_delay_percentiles(
+        &self,
+        i: IntersectionID,
+        bucket: Duration,
+    ) -> Vec<DelayStats> {
+        self.analytics
+            .intersection_delay_percentiles(i, bucket, self.time)
+    }
+
+    /// How regularly spaced a route's vehicles have been so far. None if no stop has been visited
+    /// twice yet.
+    pub fn get_headway_regularity(&self, route: BusRouteID) -> Option<HeadwayStats> {
+        self.analytics.headway_regularity(route, self.time)
+    }
+
     /// For intersections with an agent waiting beyond some threshold, return when they started
     /// waiting. Sorted by earliest waiting (likely the root cause of gridlock).
     pub fn delayed_intersections(&self, threshold: Duration) -> Vec<(IntersectionID, Time)> {
@@ -383,6 +482,11 @@ impl Sim {
         self.cap.get_cap_counter(l)
     }
 
+    /// The current density of pedestrians on every occupied sidewalk, in people per square meter.
+    pub fn get_sidewalk_densities(&self, map: &Map) -> BTreeMap<LaneID, f64> {
+        self.walking.get_sidewalk_densities(map)
+    }
+
     pub fn infinite_parking(&self) -> bool {
         self.parking.is_infinite()
     }
@@ -404,6 +508,19 @@ impl Sim {
     pub fn debug_queue_lengths(&self, l: LaneID) -> Option<(Distance, Distance)> {
         self.driving.debug_queue_lengths(l)
     }
+
+    /// How much of each lane is claimed by vehicles on it or about to enter it, from 0 to 1. When
+    /// a lane is full, vehicles can't turn onto it, and the queue spills back upstream. Lanes
+    /// without any vehicles are omitted.
+    pub fn get_queue_occupancy(&self) -> BTreeMap<LaneID, f64> {
+        self.driving.queue_occupancy()
+    }
+
+    /// Everything waiting in the scheduler and when it'll happen, keyed by a description of the
+    /// command. Useful for comparing two simulations.
+    pub fn get_scheduled_commands(&self) -> BTreeMap<String, Time> {
+        self.scheduler.get_queued_commands()
+    }
 }
 
 // Drawing
@@ -412,6 +529,37 @@ impl Sim {
         self.step_count
     }
 
+    /// How many events (trips starting, agents entering lanes, etc) have happened since this sim
+    /// was created or loaded. This is reset when loading a savestate.
+    pub fn num_events_processed(&self) -> usize {
+        self.events_processed
+    }
+
+    /// Roughly how many bytes each part of the simulation takes, measured by its size in a
+    /// savestate. Allocations that aren't full yet are missed, so this underestimates, but it
+    /// shows which parts grow over a long run.
+    pub fn memory_usage(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("driving", serialized_size_bytes(&self.driving)),
+            ("parking", serialized_size_bytes(&self.parking)),
+            ("walking", serialized_size_bytes(&self.walking)),
+            ("intersections", serialized_size_bytes(&self.intersections)),
+            ("transit", serialized_size_bytes(&self.transit)),
+            ("cap", serialized_size_bytes(&self.cap)),
+            ("trips", serialized_size_bytes(&self.trips)),
+            ("scheduler", serialized_size_bytes(&self.scheduler)),
+            ("analytics", serialized_size_bytes(&self.analytics)),
+            ("ride_hail", serialized_size_bytes(&self.ride_hail)),
+            ("rerouting", serialized_size_bytes(&self.rerouting)),
+            ("bike_parking", serialized_size_bytes(&self.bike_parking)),
+            ("households", serialized_size_bytes(&self.households)),
+            ("curb", serialized_size_bytes(&self.curb)),
+            ("microtransit", serialized_size_bytes(&self.microtransit)),
+            ("safety", serialized_size_bytes(&self.safety)),
+            ("turn_counts", serialized_size_bytes(&self.turn_counts)),
+        ]
+    }
+
     pub fn get_draw_car(&self, id: CarID, map: &Map) -> Option<DrawCarInput> {
         self.parking.get_draw_car(id, map).or_else(|| {
             self.driving
@@ -486,3 +634,32 @@ pub enum DelayCause {
     /// Waiting on a traffic signal to change, or pausing at a stop sign before proceeding
     Intersection(IntersectionID),
 }
+
+/// What a vehicle is doing right now and why, for debugging the simulation.
+pub struct CarIntent {
+    /// The rest of the path, starting with the lane or turn the vehicle is on now
+    pub remaining_path: Vec<PathStep>,
+    /// What the vehicle decided to do the last time it was updated
+    pub decision: String,
+    /// What's keeping the vehicle from going faster. None while parking, unparking, or idling at
+    /// a bus stop.
+    pub constraint: Option<CarConstraint>,
+    /// When the vehicle started moving at each speed, oldest first. Only recent changes are
+    /// remembered, and none from before loading a savestate.
+    pub speed_history: Vec<(Time, Speed)>,
+}
+
+/// The one thing limiting how fast a vehicle moves. Speeds don't change while crossing a lane or
+/// turn, so a moving vehicle is always limited by the speed limit, the vehicle's top speed, or the
+/// weather.
+#[derive(Debug, PartialEq, Clone)]
+pub enum CarConstraint {
+    /// Moving at the fastest speed allowed right now
+    SpeedLimit(Speed),
+    /// Stopped behind another vehicle, or yielding to someone doing a conflicting turn
+    LeadVehicle(AgentID),
+    /// Waiting on a traffic signal to change, or pausing at a stop sign
+    Intersection(IntersectionID),
+    /// Stopped at the end of the lane or its path with nothing else in the way
+    EndOfLane,
+}
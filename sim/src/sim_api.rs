@@ -0,0 +1,212 @@
+//! A read-only view of a running simulation, answering common questions with plain, serializable
+//! types. The headless API server and anything else exposing the simulation to outside tools
+//! should go through this, instead of each picking apart Sim and Analytics internals differently.
+//! Fields here are meant to stay stable, even as the simulation internals change.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use geom::{Distance, Duration, LonLat, Time};
+use map_model::{BuildingID, LaneID, Map, ParkingLotID, RoadID};
+
+use crate::{
+    AgentID, AgentType, ParkingSpot, PersonID, Sim, TripID, TripMode, TripResult, VehicleType,
+};
+
+pub struct SimApi<'a> {
+    sim: &'a Sim,
+    map: &'a Map,
+}
+
+#[derive(Serialize)]
+pub struct AgentPosition {
+    pub id: AgentID,
+    /// None for pedestrians
+    pub vehicle_type: Option<VehicleType>,
+    /// The agent's current position. For pedestrians, this is their center. For vehicles, this
+    /// represents the front of the vehicle.
+    pub pos: LonLat,
+    /// The distance crossed so far by the agent, in meters. There are some caveats to this value:
+    /// - The distance along driveways between buildings/parking lots and the road doesn't count
+    ///   here.
+    /// - The distance will slightly exceed the true value if the agent begins or ends in the
+    ///   middle of a lane.
+    /// - The distance will not change while an agent is travelling along a lane; it'll only
+    ///   increment when they completely cross one step of their path.
+    pub distance_crossed: Distance,
+    /// None for buses
+    pub person: Option<PersonID>,
+}
+
+#[derive(Serialize)]
+pub struct FinishedTrip {
+    pub id: TripID,
+    /// None if the trip was cancelled
+    pub duration: Option<Duration>,
+    pub distance_crossed: Distance,
+    pub mode: TripMode,
+    pub capped: bool,
+}
+
+/// Everything known about one trip so far. Poll this to find out how a trip turned out.
+#[derive(Serialize)]
+pub struct TripRecord {
+    pub id: TripID,
+    pub person: PersonID,
+    pub mode: TripMode,
+    /// The scheduled departure. The trip may start later, if the person's previous trip runs
+    /// late.
+    pub departure: Time,
+    pub status: TripStatus,
+    /// Only set once the trip finishes
+    pub duration: Option<Duration>,
+    /// How long the trip has spent waiting at intersections or stuck in traffic so far
+    pub blocked_time: Duration,
+    /// Only set once the trip finishes
+    pub distance_crossed: Option<Distance>,
+    /// Only set if the trip was cancelled
+    pub cancellation_reason: Option<String>,
+}
+
+#[derive(Serialize, PartialEq, Clone, Copy, Debug)]
+pub enum TripStatus {
+    Scheduled,
+    InProgress,
+    Finished,
+    Cancelled,
+}
+
+#[derive(Serialize)]
+pub struct RoadThroughput {
+    /// (road, agent type, hour since midnight, throughput for that one hour period)
+    pub counts: Vec<(RoadID, AgentType, usize, usize)>,
+}
+
+#[derive(Serialize)]
+pub struct ParkingOccupancy {
+    /// (lane, filled spots, total spots)
+    pub onstreet: Vec<(LaneID, usize, usize)>,
+    /// (building, filled spots, total spots)
+    pub offstreet: Vec<(BuildingID, usize, usize)>,
+    /// (parking lot, filled spots, total spots)
+    pub lots: Vec<(ParkingLotID, usize, usize)>,
+}
+
+impl<'a> SimApi<'a> {
+    pub fn new(sim: &'a Sim, map: &'a Map) -> SimApi<'a> {
+        SimApi { sim, map }
+    }
+
+    /// Every agent currently on the map
+    pub fn agent_positions(&self) -> Vec<AgentPosition> {
+        self.sim
+            .get_unzoomed_agents(self.map)
+            .into_iter()
+            .map(|a| AgentPosition {
+                id: a.id,
+                vehicle_type: a.id.to_vehicle_type(),
+                pos: a.pos.to_gps(self.map.get_gps_bounds()),
+                distance_crossed: self.sim.agent_properties(a.id).dist_crossed,
+                person: a.person,
+            })
+            .collect()
+    }
+
+    /// Every trip that's finished or been cancelled so far, in the order it happened
+    pub fn finished_trips(&self) -> Vec<FinishedTrip> {
+        let mut trips = Vec::new();
+        for (_, id, mode, maybe_duration) in &self.sim.get_analytics().finished_trips {
+            let info = self.sim.trip_info(*id);
+            let distance_crossed = if maybe_duration.is_some() {
+                self.sim.finished_trip_details(*id).unwrap().2
+            } else {
+                Distance::ZERO
+            };
+            trips.push(FinishedTrip {
+                id: *id,
+                duration: *maybe_duration,
+                distance_crossed,
+                mode: *mode,
+                capped: info.capped,
+            });
+        }
+        trips
+    }
+
+    /// None if the trip doesn't exist
+    pub fn trip_record(&self, id: TripID) -> Option<TripRecord> {
+        let status = match self.sim.trip_to_agent(id) {
+            TripResult::TripDoesntExist => {
+                return None;
+            }
+            TripResult::TripNotStarted => TripStatus::Scheduled,
+            TripResult::TripDone => TripStatus::Finished,
+            TripResult::TripCancelled => TripStatus::Cancelled,
+            TripResult::Ok(_) | TripResult::ModeChange => TripStatus::InProgress,
+        };
+        let info = self.sim.trip_info(id);
+        let details = self.sim.finished_trip_details(id);
+        Some(TripRecord {
+            id,
+            person: self.sim.trip_to_person(id)?,
+            mode: info.mode,
+            departure: info.departure,
+            status,
+            duration: details.map(|(duration, _, _)| duration),
+            blocked_time: self.sim.trip_blocked_time(id),
+            distance_crossed: details.map(|(_, _, dist)| dist),
+            cancellation_reason: info.cancellation_reason,
+        })
+    }
+
+    pub fn road_throughput(&self) -> RoadThroughput {
+        RoadThroughput {
+            counts: self
+                .sim
+                .get_analytics()
+                .road_thruput
+                .counts
+                .iter()
+                .map(|((r, a, hr), cnt)| (*r, *a, *hr, *cnt))
+                .collect(),
+        }
+    }
+
+    /// Only includes places with at least one parking spot
+    pub fn parking_occupancy(&self) -> ParkingOccupancy {
+        let mut onstreet: BTreeMap<LaneID, (usize, usize)> = BTreeMap::new();
+        let mut offstreet: BTreeMap<BuildingID, (usize, usize)> = BTreeMap::new();
+        let mut lots: BTreeMap<ParkingLotID, (usize, usize)> = BTreeMap::new();
+
+        let (filled, available) = self.sim.get_all_parking_spots();
+        for (spots, is_filled) in vec![(filled, true), (available, false)] {
+            for spot in spots {
+                let pair = match spot {
+                    ParkingSpot::Onstreet(l, _) => onstreet.entry(l).or_insert((0, 0)),
+                    ParkingSpot::Offstreet(b, _) => offstreet.entry(b).or_insert((0, 0)),
+                    ParkingSpot::Lot(pl, _) => lots.entry(pl).or_insert((0, 0)),
+                };
+                if is_filled {
+                    pair.0 += 1;
+                }
+                pair.1 += 1;
+            }
+        }
+
+        ParkingOccupancy {
+            onstreet: onstreet
+                .into_iter()
+                .map(|(l, (filled, total))| (l, filled, total))
+                .collect(),
+            offstreet: offstreet
+                .into_iter()
+                .map(|(b, (filled, total))| (b, filled, total))
+                .collect(),
+            lots: lots
+                .into_iter()
+                .map(|(pl, (filled, total))| (pl, filled, total))
+                .collect(),
+        }
+    }
+}
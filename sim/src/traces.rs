@@ -0,0 +1,157 @@
+//! Records where agents go during one run, so a later run -- usually with some map edits -- can
+//! show them as translucent "ghosts" moving alongside the live agents.
+//!
+//! People are identified by their PersonID, which stays the same between runs of the same
+//! scenario, even though their cars and pedestrians might get different IDs. Buses and people
+//! riding them aren't recorded.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use abstutil::{MapName, Timer};
+use geom::{Duration, Line, Pt2D, Time};
+use map_model::Map;
+
+use crate::{AgentType, PersonID, UnzoomedAgent};
+
+/// The position of some agents, sampled periodically.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AgentTraces {
+    pub map_name: MapName,
+    pub edits_name: String,
+    /// How often positions are sampled
+    pub every: Duration,
+    /// If None, everybody is recorded
+    pub people: Option<BTreeSet<PersonID>>,
+    next_sample: Time,
+    /// Sorted by time
+    samples: Vec<(Time, Vec<Ghost>)>,
+}
+
+/// Where somebody was at some point during a recorded run.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Ghost {
+    pub person: PersonID,
+    pub agent_type: AgentType,
+    pub pos: Pt2D,
+}
+
+impl AgentTraces {
+    pub(crate) fn new(
+        map: &Map,
+        start: Time,
+        people: Option<BTreeSet<PersonID>>,
+        every: Duration,
+    ) -> AgentTraces {
+        AgentTraces {
+            map_name: map.get_name().clone(),
+            edits_name: map.get_edits().edits_name.clone(),
+            every,
+            people,
+            next_sample: start,
+            samples: Vec::new(),
+        }
+    }
+
+    pub(crate) fn next_sample(&self) -> Time {
+        self.next_sample
+    }
+
+    pub(crate) fn record(&mut self, time: Time, agents: Vec<UnzoomedAgent>) {
+        let ghosts = agents
+            .into_iter()
+            .filter_map(|a| {
+                let person = a.person?;
+                if let Some(ref people) = self.people {
+                    if !people.contains(&person) {
+                        return None;
+                    }
+                }
+                Some(Ghost {
+                    person,
+                    agent_type: a.id.to_type(),
+                    pos: a.pos,
+                })
+            })
+            .collect();
+        self.samples.push((time, ghosts));
+        self.next_sample = time + self.every;
+    }
+
+    /// Also records this person from now on. Does nothing if everybody is already recorded.
+    pub(crate) fn add_person(&mut self, person: PersonID) {
+        if let Some(ref mut people) = self.people {
+            people.insert(person);
+        }
+    }
+
+    /// The first and last time sampled
+    pub fn time_range(&self) -> Option<(Time, Time)> {
+        Some((self.samples.first()?.0, self.samples.last()?.0))
+    }
+
+    /// Where everybody recorded was at this time. Between two samples, positions are interpolated
+    /// in a straight line, so ghosts cut corners when the samples are far apart. Somebody who
+    /// only appears in one of the two samples is shown there, until halfway to the next sample.
+    pub fn ghosts_at(&self, time: Time) -> Vec<Ghost> {
+        let idx = match self.samples.iter().rposition(|(t, _)| *t <= time) {
+            Some(idx) => idx,
+            None => {
+                return Vec::new();
+            }
+        };
+        let (t1, ref before) = self.samples[idx];
+        let (t2, after) = match self.samples.get(idx + 1) {
+            Some((t2, after)) => (*t2, after),
+            None => {
+                // Past the end of the recording
+                return if time - t1 <= self.every {
+                    before.clone()
+                } else {
+                    Vec::new()
+                };
+            }
+        };
+        let pct = (time - t1) / (t2 - t1);
+
+        let mut after: BTreeMap<PersonID, Ghost> = after.iter().map(|g| (g.person, *g)).collect();
+        let mut ghosts = Vec::new();
+        for g1 in before {
+            match after.remove(&g1.person) {
+                Some(g2) => {
+                    ghosts.push(Ghost {
+                        person: g1.person,
+                        agent_type: if pct < 0.5 {
+                            g1.agent_type
+                        } else {
+                            g2.agent_type
+                        },
+                        pos: Line::new(g1.pos, g2.pos)
+                            .and_then(|l| l.percent_along(pct))
+                            .unwrap_or(g1.pos),
+                    });
+                }
+                None => {
+                    if pct < 0.5 {
+                        ghosts.push(*g1);
+                    }
+                }
+            }
+        }
+        if pct >= 0.5 {
+            ghosts.extend(after.into_iter().map(|(_, g)| g));
+        }
+        ghosts
+    }
+
+    pub fn save(&self, name: &str) -> String {
+        let path = abstutil::path_trace(&self.map_name, name);
+        abstutil::write_binary(path.clone(), self);
+        path
+    }
+
+    pub fn load(path: String, timer: &mut Timer) -> Result<AgentTraces, String> {
+        abstutil::maybe_read_binary(path, timer)
+    }
+}
@@ -3,7 +3,7 @@ use std::collections::{BTreeMap, BTreeSet};
 use serde::{Deserialize, Serialize};
 
 use abstutil::{deserialize_btreemap, serialize_btreemap};
-use geom::Time;
+use geom::{Duration, Time};
 use map_model::{BusRoute, BusRouteID, BusStopID, Map, Path, PathRequest, Position};
 
 use crate::sim::Ctx;
@@ -15,6 +15,11 @@ use crate::{
 // These index stops along a route, not stops along a single sidewalk.
 type StopIdx = usize;
 
+/// Opening and closing the doors takes this long at every stop, even if nobody boards or alights.
+const DWELL_TIME_DOORS: Duration = Duration::const_seconds(5.0);
+const BOARDING_TIME_PER_PASSENGER: Duration = Duration::const_seconds(3.0);
+const ALIGHTING_TIME_PER_PASSENGER: Duration = Duration::const_seconds(2.0);
+
 #[derive(Serialize, Deserialize, Clone)]
 struct Stop {
     id: BusStopID,
@@ -170,8 +175,9 @@ impl TransitSimState {
         );
     }
 
-    /// If true, the bus is idling. If false, the bus actually arrived at a border and should now
-    /// vanish.
+    /// If Some, the bus should idle at the stop for this long, while passengers board and alight.
+    /// A bus running late picks up more passengers and waits longer, so buses bunch up. If None,
+    /// the bus actually arrived at a border and should now vanish.
     pub fn bus_arrived_at_stop(
         &mut self,
         now: Time,
@@ -179,7 +185,7 @@ impl TransitSimState {
         trips: &mut TripManager,
         walking: &mut WalkingSimState,
         ctx: &mut Ctx,
-    ) -> bool {
+    ) -> Option<Duration> {
         let mut bus = self.buses.get_mut(&id).unwrap();
         match bus.state {
             BusState::DrivingToStop(stop_idx) => {
@@ -189,9 +195,12 @@ impl TransitSimState {
                     .push(Event::BusArrivedAtStop(id, bus.route, stop1));
 
                 // Deboard existing passengers.
+                let mut num_alighting = 0;
+                let mut num_boarding = 0;
                 let mut still_riding = Vec::new();
                 for (person, maybe_stop2) in bus.passengers.drain(..) {
                     if Some(stop1) == maybe_stop2 {
+                        num_alighting += 1;
                         trips.person_left_bus(now, person, bus.car, ctx);
                         self.events.push(Event::PassengerAlightsTransit(
                             person, bus.car, bus.route, stop1,
@@ -237,12 +246,17 @@ impl TransitSimState {
                             TripPhaseType::RidingBus(route, stop1, bus.car),
                         ));
                         bus.passengers.push((person, maybe_stop2));
+                        num_boarding += 1;
                     } else {
                         still_waiting.push((ped, route, maybe_stop2, started_waiting));
                     }
                 }
                 self.peds_waiting.insert(stop1, still_waiting);
-                true
+                Some(
+                    DWELL_TIME_DOORS
+                        + (num_boarding as f64) * BOARDING_TIME_PER_PASSENGER
+                        + (num_alighting as f64) * ALIGHTING_TIME_PER_PASSENGER,
+                )
             }
             BusState::DrivingOffMap => {
                 self.routes
@@ -261,7 +275,7 @@ impl TransitSimState {
                     }
                     trips.transit_rider_reached_border(now, person, id, ctx);
                 }
-                false
+                None
             }
             BusState::AtStop(_) | BusState::Done => unreachable!(),
         }
@@ -355,6 +369,18 @@ impl TransitSimState {
         None
     }
 
+    /// Forget about routes whose stops were edited, so the next bus uses the new stops. Buses
+    /// already driving finish their old route first.
+    pub fn handle_live_edits(&mut self, map: &Map) {
+        self.routes.retain(|id, route| {
+            !route.active_vehicles.is_empty()
+                || map
+                    .maybe_get_br(*id)
+                    .map(|r| r.stops.iter().eq(route.stops.iter().map(|s| &s.id)))
+                    .unwrap_or(false)
+        });
+    }
+
     pub fn collect_events(&mut self) -> Vec<Event> {
         self.events.drain(..).collect()
     }
@@ -10,12 +10,14 @@ use map_model::{
 };
 
 use crate::cap::CapResult;
+use crate::households::MAX_DIST_TO_HOUSEHOLD_CAR;
 use crate::sim::Ctx;
 use crate::{
     AgentID, AgentType, AlertLocation, CarID, Command, CreateCar, CreatePedestrian, DrivingGoal,
-    Event, IndividTrip, OrigPersonID, ParkedCar, ParkingSim, ParkingSpot, PedestrianID, PersonID,
-    PersonSpec, Scenario, SidewalkPOI, SidewalkSpot, TransitSimState, TripEndpoint, TripID,
-    TripPhaseType, TripPurpose, TripSpec, Vehicle, VehicleSpec, VehicleType, WalkingSimState,
+    Event, IndividTrip, MicrotransitSimState, OrigPersonID, ParkedCar, ParkingSearch, ParkingSim,
+    ParkingSpot, PedestrianID, PersonID, PersonSpec, RideHailRequest, RideHailSimState, Scenario,
+    SidewalkPOI, SidewalkSpot, SimOptions, TransitSimState, TripEndpoint, TripID, TripPhaseType,
+    TripPurpose, TripSpec, Vehicle, VehicleSpec, VehicleType, WalkingSimState,
 };
 
 /// Manages people, each of which executes some trips through the day. Each trip is further broken
@@ -37,19 +39,22 @@ pub(crate) struct TripManager {
     unfinished_trips: usize,
 
     car_id_counter: usize,
+    /// In dollars per hour, for drivers deciding if a toll is worth paying
+    value_of_time: f64,
 
     events: Vec<Event>,
 }
 
 // Initialization
 impl TripManager {
-    pub fn new() -> TripManager {
+    pub fn new(opts: &SimOptions) -> TripManager {
         TripManager {
             trips: Vec::new(),
             people: Vec::new(),
             active_trip_mode: BTreeMap::new(),
             unfinished_trips: 0,
             car_id_counter: 0,
+            value_of_time: opts.value_of_time,
             events: Vec::new(),
         }
     }
@@ -174,11 +179,7 @@ impl TripManager {
 
                 let vehicle = person.get_vehicle(use_vehicle);
                 assert!(ctx.parking.lookup_parked_car(vehicle.id).is_none());
-                let constraints = if use_vehicle.1 == VehicleType::Bike {
-                    PathConstraints::Bike
-                } else {
-                    PathConstraints::Car
-                };
+                let constraints = use_vehicle.1.to_constraints();
                 let req = PathRequest {
                     start: start_pos,
                     end: goal.goal_pos(constraints, ctx.map).unwrap(),
@@ -211,9 +212,19 @@ impl TripManager {
                 self.cancel_trip(now, trip, error, vehicle, ctx);
             }
             TripSpec::UsingParkedCar {
-                car, start_bldg, ..
+                car,
+                start_bldg,
+                goal,
             } => {
                 assert_eq!(person.state, PersonState::Inside(start_bldg));
+                let person_id = person.id;
+                if ctx.households.shares_cars(person_id)
+                    && !self.find_household_car(person_id, car, start_bldg, ctx)
+                {
+                    self.start_trip_without_car(now, trip, start_bldg, goal, ctx);
+                    return;
+                }
+                let person = &mut self.people[person_id.0];
                 person.state = PersonState::Trip(trip);
 
                 if let Some(parked_car) = ctx.parking.lookup_parked_car(car).cloned() {
@@ -376,7 +387,7 @@ impl TripManager {
                     );
                 }
             }
-            TripSpec::UsingTransit { start, stop1, .. } => {
+            TripSpec::UsingTransit { start, .. } | TripSpec::UsingGuideway { start, .. } => {
                 assert_eq!(
                     person.state,
                     match start.connection {
@@ -404,7 +415,11 @@ impl TripManager {
                 );
                 person.state = PersonState::Trip(trip);
 
-                let walk_to = SidewalkSpot::bus_stop(stop1, ctx.map);
+                // To the bus stop or guideway station
+                let walk_to = match self.trips[trip.0].legs[0] {
+                    TripLeg::Walk(ref to) => to.clone(),
+                    _ => unreachable!(),
+                };
                 let req = PathRequest {
                     start: start.sidewalk_pos,
                     end: walk_to.sidewalk_pos,
@@ -428,12 +443,33 @@ impl TripManager {
                     self.cancel_trip(
                         now,
                         trip,
-                        format!("UsingTransit trip couldn't find the first path {}", req),
+                        format!("Transit trip couldn't find the first path {}", req),
                         None,
                         ctx,
                     );
                 }
             }
+            TripSpec::UsingRideHail { start, goal } => {
+                assert_eq!(person.state, PersonState::Inside(start));
+                person.state = PersonState::Trip(trip);
+                // Wait inside until the vehicle arrives
+                self.events.push(Event::TripPhaseStarting(
+                    trip,
+                    person.id,
+                    None,
+                    TripPhaseType::WaitingForRideHail,
+                ));
+                ctx.scheduler.push(
+                    now,
+                    Command::RequestRideHail(RideHailRequest {
+                        trip,
+                        person: person.id,
+                        pickup: start,
+                        dropoff: goal,
+                        requested_at: now,
+                    }),
+                );
+            }
         }
     }
 
@@ -460,7 +496,18 @@ impl TripManager {
         distance_crossed: Distance,
         ctx: &mut Ctx,
     ) {
-        let trip = &mut self.trips[self.active_trip_mode.remove(&AgentID::Car(car)).unwrap().0];
+        let id = self.active_trip_mode.remove(&AgentID::Car(car)).unwrap();
+        if let ParkingSpot::Onstreet(l, _) = spot {
+            if let Some(zone) = ctx.map.get_parking_zone(ctx.map.get_l(l).parent) {
+                let person = self.trips[id.0].person;
+                let price = zone.price(now, self.leaving_at(person, now));
+                if price > 0.0 {
+                    self.events.push(Event::PaidForParking(person, spot, price));
+                }
+            }
+        }
+
+        let trip = &mut self.trips[id.0];
         trip.total_blocked_time += blocked_time;
         trip.total_distance += distance_crossed;
 
@@ -541,11 +588,12 @@ impl TripManager {
                 start = Position::new(start.lane(), start.dist_along() + parked_car.vehicle.length);
             }
         }
-        let end = drive_to.goal_pos(PathConstraints::Car, ctx.map).unwrap();
+        let constraints = parked_car.vehicle.vehicle_type.to_constraints();
+        let end = drive_to.goal_pos(constraints, ctx.map).unwrap();
         let req = PathRequest {
             start,
             end,
-            constraints: PathConstraints::Car,
+            constraints,
         };
 
         let person = trip.person;
@@ -602,6 +650,7 @@ impl TripManager {
             SidewalkPOI::BikeRack(p) => p,
             _ => unreachable!(),
         };
+        ctx.bike_parking.unpark(bike);
 
         let end = if let Some(end) = drive_to.goal_pos(PathConstraints::Bike, ctx.map) {
             end
@@ -627,7 +676,7 @@ impl TripManager {
             None
         } else {
             ctx.map
-                .pathfind(req.clone())
+                .pathfind_at(req.clone(), now)
                 .map(|path| drive_to.make_router(bike, path, ctx.map))
         };
         if let Some(router) = maybe_router {
@@ -677,15 +726,18 @@ impl TripManager {
         trip.total_blocked_time += blocked_time;
         trip.total_distance += distance_crossed;
 
-        match trip.legs.pop_front() {
-            Some(TripLeg::Drive(c, DrivingGoal::ParkNear(_))) => {
+        let b = match trip.legs.pop_front() {
+            Some(TripLeg::Drive(c, DrivingGoal::ParkNear(b))) => {
                 assert_eq!(c, bike);
+                b
             }
             _ => unreachable!(),
         };
+        // If the racks are full, it takes a while to find somewhere else to lock up
+        let delay = ctx.bike_parking.park(bike, b, ctx.map);
 
         let id = trip.id;
-        self.spawn_ped(now, id, bike_rack, ctx);
+        self.spawn_ped(now + delay, id, bike_rack, ctx);
     }
 
     pub fn ped_reached_building(
@@ -814,6 +866,157 @@ impl TripManager {
         self.spawn_ped(now, id, start, ctx);
     }
 
+    /// The pedestrian disappears until they get off the guideway at another station.
+    pub fn ped_reached_guideway_station(
+        &mut self,
+        now: Time,
+        ped: PedestrianID,
+        guideway: usize,
+        station: usize,
+        blocked_time: Duration,
+        distance_crossed: Distance,
+        ctx: &mut Ctx,
+    ) {
+        let trip = &mut self.trips[self
+            .active_trip_mode
+            .remove(&AgentID::Pedestrian(ped))
+            .unwrap()
+            .0];
+        trip.total_blocked_time += blocked_time;
+        trip.total_distance += distance_crossed;
+
+        match trip.legs.pop_front() {
+            Some(TripLeg::Walk(spot)) => assert_eq!(
+                spot.connection,
+                SidewalkPOI::GuidewayStation(guideway, station)
+            ),
+            _ => unreachable!(),
+        }
+        let alight = match trip.legs[0] {
+            TripLeg::RideGuideway(g, board, alight) => {
+                assert_eq!((g, board), (guideway, station));
+                alight
+            }
+            _ => unreachable!(),
+        };
+
+        let (id, person) = (trip.id, trip.person);
+        if ctx.guideways.ped_reached_station(
+            now,
+            id,
+            guideway,
+            station,
+            alight,
+            ctx.map,
+            ctx.scheduler,
+        ) {
+            self.events.push(Event::TripPhaseStarting(
+                id,
+                person,
+                None,
+                TripPhaseType::WaitingForGuideway(guideway),
+            ));
+        } else {
+            self.cancel_trip(
+                now,
+                id,
+                format!("guideway {} was removed before boarding", guideway),
+                None,
+                ctx,
+            );
+        }
+    }
+
+    pub fn person_boarded_guideway(&mut self, id: TripID, guideway: usize) {
+        let trip = &self.trips[id.0];
+        if trip.info.cancellation_reason.is_some() {
+            return;
+        }
+        self.events.push(Event::TripPhaseStarting(
+            id,
+            trip.person,
+            None,
+            TripPhaseType::RidingGuideway(guideway),
+        ));
+    }
+
+    pub fn person_left_guideway(&mut self, now: Time, id: TripID, ctx: &mut Ctx) {
+        let trip = &mut self.trips[id.0];
+        if trip.info.cancellation_reason.is_some() {
+            return;
+        }
+        let start = match trip.legs.pop_front().unwrap() {
+            TripLeg::RideGuideway(guideway, _, alight) => {
+                SidewalkSpot::guideway_station(guideway, alight, ctx.map)
+            }
+            _ => unreachable!(),
+        };
+        match start {
+            Some(start) => self.spawn_ped(now, id, start, ctx),
+            None => self.cancel_trip(
+                now,
+                id,
+                "the guideway was removed mid-ride".to_string(),
+                None,
+                ctx,
+            ),
+        }
+    }
+
+    /// The wait counts as blocked time.
+    pub fn ride_hail_passenger_boarded(
+        &mut self,
+        id: TripID,
+        car: CarID,
+        wait: Duration,
+        req: PathRequest,
+    ) {
+        let trip = &mut self.trips[id.0];
+        trip.total_blocked_time += wait;
+        self.active_trip_mode
+            .insert(AgentID::BusPassenger(trip.person, car), trip.id);
+        self.people[trip.person.0].on_bus = Some(car);
+
+        let pickup = match trip.info.start {
+            TripEndpoint::Bldg(b) => b,
+            _ => unreachable!(),
+        };
+        self.events
+            .push(Event::PersonLeavesBuilding(trip.person, pickup));
+        self.events.push(Event::TripPhaseStarting(
+            trip.id,
+            trip.person,
+            Some(req),
+            TripPhaseType::RidingRideHail(car),
+        ));
+    }
+
+    pub fn ride_hail_passenger_alighted(
+        &mut self,
+        now: Time,
+        id: TripID,
+        car: CarID,
+        distance_crossed: Distance,
+        ctx: &mut Ctx,
+    ) {
+        let trip = &mut self.trips[id.0];
+        self.active_trip_mode
+            .remove(&AgentID::BusPassenger(trip.person, car))
+            .unwrap();
+        trip.total_distance += distance_crossed;
+        let bldg = match trip.legs.pop_front().unwrap() {
+            TripLeg::RideHail(b) => b,
+            _ => unreachable!(),
+        };
+        self.people[trip.person.0].on_bus.take().unwrap();
+
+        self.people[trip.person.0].state = PersonState::Inside(bldg);
+        self.events
+            .push(Event::PersonEntersBuilding(trip.person, bldg));
+
+        self.trip_finished(now, id, ctx);
+    }
+
     pub fn ped_reached_border(
         &mut self,
         now: Time,
@@ -993,7 +1196,7 @@ impl TripManager {
     ) -> Result<Path, String> {
         let path = ctx
             .map
-            .pathfind(req.clone())
+            .pathfind_with_tolls(req.clone(), now, self.value_of_time)
             .ok_or_else(|| format!("no path for {}", req))?;
         match ctx
             .cap
@@ -1024,6 +1227,117 @@ impl TripManager {
             .push(Event::TripCancelled(trip.id, trip.info.mode));
     }
 
+    /// A household member is about to drive. If their own car isn't parked, they take another
+    /// household car parked nearby, which is swapped for theirs. Returns false if every car is
+    /// taken.
+    fn find_household_car(
+        &mut self,
+        person: PersonID,
+        car: CarID,
+        start_bldg: BuildingID,
+        ctx: &mut Ctx,
+    ) -> bool {
+        if ctx.parking.lookup_parked_car(car).is_some() {
+            return true;
+        }
+
+        let start = ctx.map.get_b(start_bldg).polygon.center();
+        let mut best: Option<(Distance, ParkedCar)> = None;
+        for other in ctx.households.other_members(person) {
+            for vehicle in &self.people[other.0].vehicles {
+                if vehicle.vehicle_type != VehicleType::Car || self.is_heading_to(other, vehicle.id)
+                {
+                    continue;
+                }
+                if let Some(parked_car) = ctx.parking.lookup_parked_car(vehicle.id) {
+                    let dist = ctx
+                        .parking
+                        .spot_to_sidewalk_pos(parked_car.spot, ctx.map)
+                        .pt(ctx.map)
+                        .dist_to(start);
+                    if dist <= MAX_DIST_TO_HOUSEHOLD_CAR
+                        && best.as_ref().map(|(d, _)| dist < *d).unwrap_or(true)
+                    {
+                        best = Some((dist, parked_car.clone()));
+                    }
+                }
+            }
+        }
+        let borrowed = match best {
+            Some((_, parked_car)) => parked_car,
+            None => {
+                return false;
+            }
+        };
+
+        ctx.parking.remove_parked_car(borrowed.clone());
+        ctx.parking.reserve_spot(borrowed.spot, car);
+        ctx.parking.add_parked_car(ParkedCar {
+            vehicle: self.people[person.0].get_vehicle(car),
+            spot: borrowed.spot,
+            parked_since: borrowed.parked_since,
+        });
+        ctx.households.borrowed_car();
+        true
+    }
+
+    /// Is this person currently on a trip that'll drive this car?
+    fn is_heading_to(&self, person: PersonID, car: CarID) -> bool {
+        match self.people[person.0].state {
+            PersonState::Trip(t) => self.trips[t.0]
+                .legs
+                .iter()
+                .any(|leg| matches!(leg, TripLeg::Drive(c, _) if *c == car)),
+            PersonState::Inside(_) | PersonState::OffMap => false,
+        }
+    }
+
+    /// Every car in the household is taken, so ride transit or walk instead.
+    fn start_trip_without_car(
+        &mut self,
+        now: Time,
+        trip: TripID,
+        start_bldg: BuildingID,
+        goal: DrivingGoal,
+        ctx: &mut Ctx,
+    ) {
+        ctx.households.switched_mode();
+        let person = self.trips[trip.0].person;
+        let start = SidewalkSpot::building(start_bldg, ctx.map);
+        let end = match goal {
+            DrivingGoal::ParkNear(b) => Some(SidewalkSpot::building(b, ctx.map)),
+            DrivingGoal::Border(i, _) => SidewalkSpot::end_at_border(i, ctx.map),
+        };
+        let end = match end {
+            Some(end) => end,
+            None => {
+                self.people[person.0].state = PersonState::Trip(trip);
+                self.cancel_trip(
+                    now,
+                    trip,
+                    format!(
+                        "every car in the household is taken, and {:?} can't be reached on foot",
+                        goal
+                    ),
+                    None,
+                    ctx,
+                );
+                return;
+            }
+        };
+
+        let spec = TripSpec::transit_or_walk(start, end, ctx.map);
+        let mut info = self.trips[trip.0].info.clone();
+        info.mode = match spec {
+            TripSpec::UsingTransit { .. } | TripSpec::UsingGuideway { .. } => TripMode::Transit,
+            _ => TripMode::Walk,
+        };
+        let (_, info, spec, legs) = spec.to_plan(person, info, ctx.map);
+        self.trips[trip.0].info = info;
+        self.trips[trip.0].legs = VecDeque::from(legs);
+        self.start_trip(now, trip, spec, ctx);
+    }
+
     /// Cancel a trip after it's started. The person will be magically warped to their destination,
     /// along with their car, as if the trip had completed normally.
     pub fn cancel_trip(
@@ -1034,6 +1348,8 @@ impl TripManager {
         abandoned_vehicle: Option<Vehicle>,
         ctx: &mut Ctx,
     ) {
+        // If the car gets warped to the destination, it has to respect parking restrictions
+        let leave_at = self.leaving_at(self.trips[id.0].person, now);
         let trip = &mut self.trips[id.0];
         self.unfinished_trips -= 1;
         trip.info.cancellation_reason = Some(reason);
@@ -1045,6 +1361,15 @@ impl TripManager {
         if let PersonState::Inside(b) = self.people[person.0].state {
             self.events.push(Event::PersonLeavesBuilding(person, b));
         }
+        if let Some(TripLeg::RideHail(_)) = trip.legs.front() {
+            if let Some(car) = self.people[person.0].on_bus.take() {
+                self.active_trip_mode
+                    .remove(&AgentID::BusPassenger(person, car));
+            } else if let TripEndpoint::Bldg(b) = trip.info.start {
+                // Still waiting inside for a pickup
+                self.events.push(Event::PersonLeavesBuilding(person, b));
+            }
+        }
         // Warp to the destination
         self.people[person.0].state = match trip.info.end {
             TripEndpoint::Bldg(b) => {
@@ -1061,19 +1386,32 @@ impl TripManager {
 
         // Don't forget the car!
         if let Some(vehicle) = abandoned_vehicle {
-            if vehicle.vehicle_type == VehicleType::Car {
+            if matches!(
+                vehicle.vehicle_type,
+                VehicleType::Car | VehicleType::Truck | VehicleType::Emergency
+            ) {
                 if let TripEndpoint::Bldg(b) = trip.info.end {
                     let driving_lane = ctx.map.find_driving_lane_near_building(b);
+                    let search = ParkingSearch {
+                        target: b,
+                        now,
+                        leave_at,
+                    };
                     if let Some(spot) = ctx
                         .parking
-                        .get_all_free_spots(Position::start(driving_lane), &vehicle, b, ctx.map)
+                        .get_all_free_spots(
+                            Position::start(driving_lane),
+                            &vehicle,
+                            search,
+                            ctx.map,
+                        )
                         // TODO Could pick something closer, but meh, cancelled trips are bugs
                         // anyway
                         .get(0)
                         .map(|(spot, _)| spot.clone())
                         .or_else(|| {
                             ctx.parking
-                                .path_to_free_parking_spot(driving_lane, &vehicle, b, ctx.map)
+                                .path_to_free_parking_spot(driving_lane, &vehicle, search, ctx.map)
                                 .map(|(_, spot, _)| spot)
                         })
                     {
@@ -1130,6 +1468,9 @@ impl TripManager {
     pub fn num_active_agents(&self) -> usize {
         self.active_trip_mode.len()
     }
+    pub fn value_of_time(&self) -> f64 {
+        self.value_of_time
+    }
 
     pub fn trip_to_agent(&self, id: TripID) -> TripResult<AgentID> {
         if id.0 >= self.trips.len() {
@@ -1152,6 +1493,17 @@ impl TripManager {
             TripLeg::Walk(_) => AgentID::Pedestrian(person.ped),
             TripLeg::Drive(c, _) => AgentID::Car(*c),
             TripLeg::RideBus(_, _) => AgentID::BusPassenger(person.id, person.on_bus.unwrap()),
+            TripLeg::RideHail(_) => match person.on_bus {
+                Some(car) => AgentID::BusPassenger(person.id, car),
+                // Still waiting for a pickup
+                None => {
+                    return TripResult::ModeChange;
+                }
+            },
+            // Guideway vehicles aren't agents
+            TripLeg::RideGuideway(_, _, _) => {
+                return TripResult::ModeChange;
+            }
         };
         if self.active_trip_mode.get(&a) == Some(&id) {
             TripResult::Ok(a)
@@ -1181,7 +1533,12 @@ impl TripManager {
             self.unfinished_trips,
         )
     }
-    pub fn num_agents(&self, transit: &TransitSimState) -> Counter<AgentType> {
+    pub fn num_agents(
+        &self,
+        transit: &TransitSimState,
+        ride_hail: &RideHailSimState,
+        microtransit: &MicrotransitSimState,
+    ) -> Counter<AgentType> {
         let mut cnt = Counter::new();
         for a in self.active_trip_mode.keys() {
             cnt.inc(a.to_type());
@@ -1189,12 +1546,16 @@ impl TripManager {
         let (buses, trains) = transit.active_vehicles();
         cnt.add(AgentType::Bus, buses);
         cnt.add(AgentType::Train, trains);
+        cnt.add(AgentType::Car, ride_hail.num_vehicles_on_road());
+        cnt.add(AgentType::Car, microtransit.num_vehicles_on_road());
         cnt
     }
     pub fn num_commuters_vehicles(
         &self,
         transit: &TransitSimState,
         walking: &WalkingSimState,
+        ride_hail: &RideHailSimState,
+        microtransit: &MicrotransitSimState,
     ) -> CommutersVehiclesCounts {
         let (buses, trains) = transit.active_vehicles();
         let mut cnt = CommutersVehiclesCounts {
@@ -1211,12 +1572,18 @@ impl TripManager {
             trains,
             bus_riders: 0,
             train_riders: 0,
+
+            ride_hail_vehicles: ride_hail.num_vehicles_on_road(),
+            ride_hail_riders: 0,
+
+            microtransit_vehicles: microtransit.num_vehicles_on_road(),
+            microtransit_riders: 0,
         };
 
         for a in self.active_trip_mode.keys() {
             match a {
                 AgentID::Car(c) => match c.1 {
-                    VehicleType::Car => {
+                    VehicleType::Car | VehicleType::Truck | VehicleType::Emergency => {
                         cnt.sov_drivers += 1;
                     }
                     VehicleType::Bike => {
@@ -1231,7 +1598,16 @@ impl TripManager {
                     VehicleType::Train => {
                         cnt.train_riders += 1;
                     }
-                    VehicleType::Car | VehicleType::Bike => unreachable!(),
+                    VehicleType::Car => {
+                        if microtransit.is_shuttle(*c) {
+                            cnt.microtransit_riders += 1;
+                        } else {
+                            cnt.ride_hail_riders += 1;
+                        }
+                    }
+                    VehicleType::Bike | VehicleType::Truck | VehicleType::Emergency => {
+                        unreachable!()
+                    }
                 },
                 // These're counted separately
                 AgentID::Pedestrian(_) => {}
@@ -1301,6 +1677,17 @@ impl TripManager {
         Some(self.trips.get(id.0)?.person)
     }
 
+    /// When somebody parking now will next start a trip, and so move their car. None if they're
+    /// staying put for the rest of the day.
+    pub fn leaving_at(&self, person: PersonID, now: Time) -> Option<Time> {
+        self.people[person.0]
+            .trips
+            .iter()
+            .map(|t| &self.trips[t.0].info)
+            .find(|info| info.departure > now && info.cancellation_reason.is_none())
+            .map(|info| info.departure)
+    }
+
     pub fn all_arrivals_at_border(&self, at: IntersectionID) -> Vec<(Time, AgentType)> {
         let mut times = Vec::new();
         for t in &self.trips {
@@ -1317,6 +1704,8 @@ impl TripManager {
                         // TODO Not true for long. People will be able to spawn at borders already
                         // on a bus.
                         TripMode::Transit => AgentType::Pedestrian,
+                        // Ride-hail trips always start in buildings
+                        TripMode::RideHail => AgentType::Car,
                     };
                     times.push((t.info.departure, agent_type));
                 }
@@ -1400,6 +1789,10 @@ pub(crate) enum TripLeg {
     Drive(CarID, DrivingGoal),
     /// Maybe get off at a stop, maybe ride off-map
     RideBus(BusRouteID, Option<BusStopID>),
+    /// Get picked up and dropped off at this building
+    RideHail(BuildingID),
+    /// Which guideway, and the stations to board and alight at
+    RideGuideway(usize, usize, usize),
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord)]
@@ -1408,6 +1801,7 @@ pub enum TripMode {
     Bike,
     Transit,
     Drive,
+    RideHail,
 }
 
 impl TripMode {
@@ -1417,6 +1811,7 @@ impl TripMode {
             TripMode::Bike,
             TripMode::Transit,
             TripMode::Drive,
+            TripMode::RideHail,
         ]
     }
 
@@ -1426,6 +1821,7 @@ impl TripMode {
             TripMode::Bike => "bike",
             TripMode::Transit => "use transit",
             TripMode::Drive => "drive",
+            TripMode::RideHail => "take a ride-hail vehicle",
         }
     }
 
@@ -1436,6 +1832,7 @@ impl TripMode {
             TripMode::Bike => "biking",
             TripMode::Transit => "using transit",
             TripMode::Drive => "driving",
+            TripMode::RideHail => "riding in a ride-hail vehicle",
         }
     }
 
@@ -1445,6 +1842,7 @@ impl TripMode {
             TripMode::Bike => "Bike",
             TripMode::Transit => "Bus",
             TripMode::Drive => "Car",
+            TripMode::RideHail => "Ride-hail",
         }
     }
 
@@ -1454,7 +1852,7 @@ impl TripMode {
             TripMode::Bike => PathConstraints::Bike,
             // TODO WRONG
             TripMode::Transit => PathConstraints::Bus,
-            TripMode::Drive => PathConstraints::Car,
+            TripMode::Drive | TripMode::RideHail => PathConstraints::Car,
         }
     }
 
@@ -1464,7 +1862,7 @@ impl TripMode {
             PathConstraints::Bike => TripMode::Bike,
             // TODO The bijection breaks down... transit rider vs train vs bus...
             PathConstraints::Bus | PathConstraints::Train => TripMode::Transit,
-            PathConstraints::Car => TripMode::Drive,
+            PathConstraints::Car | PathConstraints::Truck => TripMode::Drive,
         }
     }
 }
@@ -1543,4 +1941,10 @@ pub struct CommutersVehiclesCounts {
     pub trains: usize,
     pub bus_riders: usize,
     pub train_riders: usize,
+
+    pub ride_hail_vehicles: usize,
+    pub ride_hail_riders: usize,
+
+    pub microtransit_vehicles: usize,
+    pub microtransit_riders: usize,
 }
@@ -0,0 +1,285 @@
+//! Turning movement counts, in the form traffic engineers collect them by hand at an
+//! intersection: in 15-minute intervals, how many vehicles from each approach turned left, went
+//! straight, or turned right, and how many pedestrians crossed each leg. These are the inputs to
+//! signal warrants and most intersection capacity analysis.
+//!
+//! Approaches are named by compass direction of travel (SB means heading south, so arriving from
+//! the north leg). Intersections with more than one road from roughly the same direction combine
+//! them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use geom::{Angle, Duration, Time};
+use map_model::{IntersectionID, Map, RoadID, Traversable, TurnID, TurnType};
+
+use crate::{AgentType, Event};
+
+/// Counts are grouped into intervals this long.
+pub const TURN_COUNT_INTERVAL: Duration = Duration::const_seconds(15.0 * 60.0);
+
+/// Every turn made so far, for every intersection.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TurnCounts {
+    /// Per intersection, (turn, interval index, type) -> count
+    counts: BTreeMap<IntersectionID, BTreeMap<(TurnID, usize, AgentType), usize>>,
+}
+
+/// The direction of travel when arriving at an intersection. The order matches the usual layout of
+/// count sheets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Approach {
+    Southbound,
+    Westbound,
+    Northbound,
+    Eastbound,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TurnMovement {
+    UTurn,
+    Left,
+    Thru,
+    Right,
+}
+
+/// One column of a count sheet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TurnCountColumn {
+    /// Cars, buses, and trains making one movement
+    Vehicles(Approach, TurnMovement),
+    /// Bikes arriving from one approach, however they turn
+    Bikes(Approach),
+    /// Pedestrians crossing the leg that this approach arrives from, in either direction
+    Pedestrians(Approach),
+}
+
+/// The counts at one intersection.
+pub struct TurnCountTable {
+    pub columns: Vec<TurnCountColumn>,
+    /// The start of each interval and the count for each column. Every interval from the first
+    /// to the last with any traffic is included.
+    pub rows: Vec<(Time, Vec<usize>)>,
+}
+
+impl TurnCounts {
+    pub(crate) fn handle_event(&mut self, now: Time, ev: &Event, map: &Map) {
+        if let Event::AgentEntersTraversable(a, Traversable::Turn(t), _) = ev {
+            // Walking around a corner isn't a crossing
+            if map.get_t(*t).turn_type == TurnType::SharedSidewalkCorner {
+                return;
+            }
+            let interval = (now.inner_seconds() / TURN_COUNT_INTERVAL.inner_seconds()) as usize;
+            *self
+                .counts
+                .entry(t.parent)
+                .or_insert_with(BTreeMap::new)
+                .entry((*t, interval, a.to_type()))
+                .or_insert(0) += 1;
+        }
+    }
+
+    /// Builds the count sheet for one intersection. The columns cover every movement possible
+    /// there, even ones nobody made.
+    pub fn table(&self, i: IntersectionID, map: &Map) -> TurnCountTable {
+        let mut columns = BTreeSet::new();
+        for t in &map.get_i(i).turns {
+            match map.get_t(*t).turn_type {
+                TurnType::SharedSidewalkCorner => {}
+                TurnType::Crosswalk => {
+                    columns.insert(TurnCountColumn::Pedestrians(approach(
+                        map,
+                        map.get_l(t.src).parent,
+                        i,
+                    )));
+                }
+                turn_type => {
+                    let from = approach(map, map.get_l(t.src).parent, i);
+                    columns.insert(TurnCountColumn::Vehicles(from, movement(turn_type)));
+                    columns.insert(TurnCountColumn::Bikes(from));
+                }
+            }
+        }
+        let columns: Vec<TurnCountColumn> = columns.into_iter().collect();
+
+        let mut per_interval: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        if let Some(counts) = self.counts.get(&i) {
+            for ((t, interval, agent_type), count) in counts {
+                let col = match column(map, *t, *agent_type) {
+                    Some(col) => col,
+                    None => {
+                        continue;
+                    }
+                };
+                if let Some(idx) = columns.iter().position(|c| *c == col) {
+                    per_interval
+                        .entry(*interval)
+                        .or_insert_with(|| vec![0; columns.len()])[idx] += count;
+                }
+            }
+        }
+
+        let mut rows = Vec::new();
+        if let (Some(first), Some(last)) = (
+            per_interval.keys().next().cloned(),
+            per_interval.keys().last().cloned(),
+        ) {
+            for interval in first..=last {
+                rows.push((
+                    Time::START_OF_DAY + (interval as f64) * TURN_COUNT_INTERVAL,
+                    per_interval
+                        .remove(&interval)
+                        .unwrap_or_else(|| vec![0; columns.len()]),
+                ));
+            }
+        }
+        TurnCountTable { columns, rows }
+    }
+}
+
+impl TurnCountTable {
+    /// The hour with the most vehicles (not counting bikes), made of four consecutive intervals.
+    /// Returns the start of that hour and the total.
+    pub fn peak_hour(&self) -> Option<(Time, usize)> {
+        let vehicles: Vec<usize> = self
+            .rows
+            .iter()
+            .map(|(_, counts)| {
+                self.columns
+                    .iter()
+                    .zip(counts)
+                    .filter(|(col, _)| matches!(col, TurnCountColumn::Vehicles(_, _)))
+                    .map(|(_, count)| *count)
+                    .sum()
+            })
+            .collect();
+        if vehicles.is_empty() {
+            return None;
+        }
+        let mut best: Option<(Time, usize)> = None;
+        for (idx, window) in vehicles.windows(4.min(vehicles.len())).enumerate() {
+            let total = window.iter().sum();
+            if best.map(|(_, x)| total > x).unwrap_or(true) {
+                best = Some((self.rows[idx].0, total));
+            }
+        }
+        best
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        out.push_str("interval_start,interval_end");
+        for col in &self.columns {
+            out.push(',');
+            out.push_str(&col.describe());
+        }
+        out.push('\n');
+        for (start, counts) in &self.rows {
+            out.push_str(&format!(
+                "{},{}",
+                start.ampm_tostring(),
+                (*start + TURN_COUNT_INTERVAL).ampm_tostring()
+            ));
+            for count in counts {
+                out.push_str(&format!(",{}", count));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl TurnCountColumn {
+    pub fn describe(self) -> String {
+        match self {
+            TurnCountColumn::Vehicles(from, movement) => {
+                format!("{} {}", from.abbreviation(), movement.describe())
+            }
+            TurnCountColumn::Bikes(from) => format!("{} Bikes", from.abbreviation()),
+            TurnCountColumn::Pedestrians(from) => format!("{} Leg Peds", from.leg()),
+        }
+    }
+}
+
+impl Approach {
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            Approach::Southbound => "SB",
+            Approach::Westbound => "WB",
+            Approach::Northbound => "NB",
+            Approach::Eastbound => "EB",
+        }
+    }
+
+    /// The leg of the intersection that traffic heading this way arrives from
+    pub fn leg(self) -> &'static str {
+        match self {
+            Approach::Southbound => "North",
+            Approach::Westbound => "East",
+            Approach::Northbound => "South",
+            Approach::Eastbound => "West",
+        }
+    }
+
+    fn from_heading(angle: Angle) -> Approach {
+        // Y increases to the south in map-space
+        let degrees = angle.normalized_degrees();
+        if degrees >= 45.0 && degrees < 135.0 {
+            Approach::Southbound
+        } else if degrees >= 135.0 && degrees < 225.0 {
+            Approach::Westbound
+        } else if degrees >= 225.0 && degrees < 315.0 {
+            Approach::Northbound
+        } else {
+            Approach::Eastbound
+        }
+    }
+}
+
+impl TurnMovement {
+    pub fn describe(self) -> &'static str {
+        match self {
+            TurnMovement::UTurn => "U-Turn",
+            TurnMovement::Left => "Left",
+            TurnMovement::Thru => "Thru",
+            TurnMovement::Right => "Right",
+        }
+    }
+}
+
+/// The direction of travel along a road when arriving at one of its intersections.
+fn approach(map: &Map, r: RoadID, i: IntersectionID) -> Approach {
+    let road = map.get_r(r);
+    let heading = if road.dst_i == i {
+        road.center_pts.last_line().angle()
+    } else {
+        road.center_pts.first_line().angle().opposite()
+    };
+    Approach::from_heading(heading)
+}
+
+fn movement(turn_type: TurnType) -> TurnMovement {
+    match turn_type {
+        TurnType::UTurn => TurnMovement::UTurn,
+        TurnType::Left => TurnMovement::Left,
+        TurnType::Right => TurnMovement::Right,
+        _ => TurnMovement::Thru,
+    }
+}
+
+fn column(map: &Map, t: TurnID, agent_type: AgentType) -> Option<TurnCountColumn> {
+    // Turns may have changed from map edits since they were counted
+    let turn_type = map.maybe_get_t(t)?.turn_type;
+    let from = approach(map, map.get_l(t.src).parent, t.parent);
+    match (turn_type, agent_type) {
+        (TurnType::SharedSidewalkCorner, _) => None,
+        (TurnType::Crosswalk, AgentType::Pedestrian) => Some(TurnCountColumn::Pedestrians(from)),
+        (TurnType::Crosswalk, _) => None,
+        (_, AgentType::Bike) => Some(TurnCountColumn::Bikes(from)),
+        (_, AgentType::Car) | (_, AgentType::Bus) | (_, AgentType::Train) => {
+            Some(TurnCountColumn::Vehicles(from, movement(turn_type)))
+        }
+        (_, AgentType::Pedestrian) | (_, AgentType::TransitRider) => None,
+    }
+}
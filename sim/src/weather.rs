@@ -0,0 +1,77 @@
+//! Bad weather slows everybody down, makes drivers leave longer gaps, and convinces some people to
+//! take transit instead of biking or walking. This is a coarse model meant for comparing how
+//! robust a network design is in winter, not for predicting exact travel times.
+//!
+//! Weather is set once per scenario and lasts the whole day.
+
+use serde::{Deserialize, Serialize};
+
+use geom::Distance;
+
+use crate::{TripMode, FOLLOWING_DISTANCE};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Snow,
+    Ice,
+}
+
+impl Default for Weather {
+    fn default() -> Weather {
+        Weather::Clear
+    }
+}
+
+impl Weather {
+    pub fn all() -> Vec<Weather> {
+        vec![Weather::Clear, Weather::Rain, Weather::Snow, Weather::Ice]
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Weather::Clear => "clear",
+            Weather::Rain => "rain",
+            Weather::Snow => "snow",
+            Weather::Ice => "ice",
+        }
+    }
+
+    /// Everybody moves this fraction of their usual speed. For vehicles, this applies after
+    /// capping to the speed limit.
+    pub fn speed_factor(self) -> f64 {
+        match self {
+            Weather::Clear => 1.0,
+            Weather::Rain => 0.9,
+            Weather::Snow => 0.7,
+            Weather::Ice => 0.5,
+        }
+    }
+
+    /// The gap vehicles leave in front of them. Longer gaps mean fewer vehicles fit on a lane
+    /// before it spills back into the intersection.
+    pub fn following_distance(self) -> Distance {
+        match self {
+            Weather::Clear => FOLLOWING_DISTANCE,
+            Weather::Rain => FOLLOWING_DISTANCE * 2.0,
+            Weather::Snow => FOLLOWING_DISTANCE * 3.0,
+            Weather::Ice => FOLLOWING_DISTANCE * 4.0,
+        }
+    }
+
+    /// What percent of people who'd normally use this mode take transit instead. Transit trips
+    /// without a useful route fall back to walking.
+    pub fn pct_switching_from(self, mode: TripMode) -> usize {
+        match (mode, self) {
+            (_, Weather::Clear) => 0,
+            (TripMode::Bike, Weather::Rain) => 30,
+            (TripMode::Bike, Weather::Snow) => 60,
+            (TripMode::Bike, Weather::Ice) => 80,
+            (TripMode::Walk, Weather::Rain) => 10,
+            (TripMode::Walk, Weather::Snow) => 25,
+            (TripMode::Walk, Weather::Ice) => 40,
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,10 @@
+#!/bin/bash
+# Builds the simulation as a JS module in pkg/, then serves the example worker. The map and
+# scenario are fetched from data/system, so run this from a checkout with data downloaded.
+
+set -e
+wasm-pack build --dev --target web
+cp index.html worker.js pkg/
+ln -sf ../../data pkg/data
+cd pkg
+python3 -m http.server 8000
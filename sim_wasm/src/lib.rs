@@ -0,0 +1,132 @@
+//! Runs the simulation in a browser, without any graphics, so JavaScript can drive it from a web
+//! worker. This is the web equivalent of the headless API server; see
+//! https://dabreegster.github.io/abstreet/dev/api.html. The caller fetches the map and scenario
+//! files (from data/system/) and passes in the raw bytes. Queries return JSON strings with the
+//! same format as the headless server.
+//!
+//! See worker.js for an example, and run_web.sh to build and serve it.
+
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use wasm_bindgen::prelude::*;
+
+use abstutil::Timer;
+use geom::{Duration, Time};
+use map_model::Map;
+use sim::{ExternalPerson, Scenario, Sim, SimApi, SimFlags, SimOptions};
+
+#[wasm_bindgen(start)]
+pub fn run() {
+    abstutil::setup();
+}
+
+#[wasm_bindgen]
+pub struct WebSim {
+    map: Map,
+    sim: Sim,
+    rng: XorShiftRng,
+}
+
+#[wasm_bindgen]
+impl WebSim {
+    /// Expects a map and a scenario for that map, both in the uncompressed binary format.
+    #[wasm_bindgen(constructor)]
+    pub fn new(map_bytes: &[u8], scenario_bytes: &[u8]) -> Result<WebSim, JsValue> {
+        let mut timer = Timer::new("load sim");
+        let mut map: Map = abstutil::from_versioned_binary(map_bytes).map_err(err)?;
+        map.map_loaded_directly();
+        let scenario: Scenario = abstutil::from_versioned_binary(scenario_bytes).map_err(err)?;
+        if &scenario.map_name != map.get_name() {
+            return Err(err(format!(
+                "Scenario {} is for {}, not {}",
+                scenario.scenario_name,
+                scenario.map_name.describe(),
+                map.get_name().describe()
+            )));
+        }
+
+        let mut rng = XorShiftRng::seed_from_u64(SimFlags::RNG_SEED);
+        let mut sim = Sim::new(&map, SimOptions::new("web"), &mut timer);
+        scenario.instantiate(&mut sim, &map, &mut rng, &mut timer);
+        Ok(WebSim { map, sim, rng })
+    }
+
+    /// The current time, formatted like "01:30:00.0"
+    pub fn time(&self) -> String {
+        self.sim.time().to_string()
+    }
+
+    /// Seconds since midnight
+    pub fn time_seconds(&self) -> f64 {
+        self.sim.time().inner_seconds()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.sim.is_done()
+    }
+
+    /// Advances the simulation by some number of seconds.
+    pub fn step(&mut self, seconds: f64) {
+        self.sim.timed_step(
+            &self.map,
+            Duration::seconds(seconds),
+            &mut None,
+            &mut Timer::throwaway(),
+        );
+    }
+
+    /// Advances the simulation to a time like "01:30:00". It's an error to go backwards.
+    pub fn goto_time(&mut self, time: &str) -> Result<(), JsValue> {
+        let t = Time::parse(time).map_err(|e| err(e.to_string()))?;
+        if t <= self.sim.time() {
+            return Err(err(format!("{} is in the past", t)));
+        }
+        self.step((t - self.sim.time()).inner_seconds());
+        Ok(())
+    }
+
+    pub fn get_agent_positions(&self) -> String {
+        abstutil::to_json(&SimApi::new(&self.sim, &self.map).agent_positions())
+    }
+
+    pub fn get_finished_trips(&self) -> String {
+        abstutil::to_json(&SimApi::new(&self.sim, &self.map).finished_trips())
+    }
+
+    pub fn get_road_thruput(&self) -> String {
+        abstutil::to_json(&SimApi::new(&self.sim, &self.map).road_throughput())
+    }
+
+    pub fn get_parking_occupancy(&self) -> String {
+        abstutil::to_json(&SimApi::new(&self.sim, &self.map).parking_occupancy())
+    }
+
+    /// Takes an ExternalPerson as JSON, like the headless server's /sim/new-person, and returns
+    /// the new person's ID.
+    pub fn spawn_person(&mut self, json: &str) -> Result<String, JsValue> {
+        let input: ExternalPerson = abstutil::from_json(&json.as_bytes().to_vec()).map_err(err)?;
+        for trip in &input.trips {
+            if trip.departure < self.sim.time() {
+                return Err(err(format!(
+                    "It's {} now, so you can't start a trip at {}",
+                    self.sim.time(),
+                    trip.departure
+                )));
+            }
+        }
+
+        let mut scenario = Scenario::empty(&self.map, "one-shot");
+        scenario.people = ExternalPerson::import(&self.map, vec![input]).map_err(err)?;
+        let people = scenario.instantiate(
+            &mut self.sim,
+            &self.map,
+            &mut self.rng,
+            &mut Timer::throwaway(),
+        );
+        Ok(people[0].to_string())
+    }
+}
+
+fn err(msg: String) -> JsValue {
+    JsValue::from_str(&msg)
+}
@@ -0,0 +1,33 @@
+// An example of running the simulation in a web worker. The page posts messages like
+// {cmd: "step", seconds: 60} and gets back {time, agents}.
+import { default as init, WebSim } from './sim_wasm.js';
+
+let sim = null;
+
+async function load(mapPath, scenarioPath) {
+    await init('./sim_wasm_bg.wasm');
+    const [map, scenario] = await Promise.all([mapPath, scenarioPath].map(async (path) => {
+        const resp = await fetch(path);
+        return new Uint8Array(await resp.arrayBuffer());
+    }));
+    sim = new WebSim(map, scenario);
+}
+
+onmessage = async (e) => {
+    const msg = e.data;
+    try {
+        if (msg.cmd == "load") {
+            await load(msg.map, msg.scenario);
+        } else if (msg.cmd == "step") {
+            sim.step(msg.seconds);
+        } else if (msg.cmd == "spawn") {
+            postMessage({ spawned: sim.spawn_person(JSON.stringify(msg.person)) });
+        }
+        postMessage({
+            time: sim.time(),
+            agents: JSON.parse(sim.get_agent_positions()),
+        });
+    } catch (err) {
+        postMessage({ error: err.toString() });
+    }
+};
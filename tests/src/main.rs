@@ -49,7 +49,9 @@ fn import_map(path: String) -> Map {
             map_config: map_model::MapConfig {
                 driving_side: map_model::DrivingSide::Right,
                 bikes_can_use_bus_lanes: true,
+                bikes_avoid_stress: false,
                 inferred_sidewalks: true,
+                clean_geometry: false,
             },
             onstreet_parking: convert_osm::OnstreetParking::JustOSM,
             public_offstreet_parking: convert_osm::PublicOffstreetParking::None,
@@ -79,7 +81,7 @@ fn smoke_test() -> Result<(), std::io::Error> {
     for name in MapName::list_all_maps() {
         let map = map_model::Map::new(name.path(), &mut timer);
         let scenario = if map.get_city_name() == "seattle" {
-            abstutil::read_binary(abstutil::path_scenario(&name, "weekday"), &mut timer)
+            abstutil::read_versioned_binary(abstutil::path_scenario(&name, "weekday"), &mut timer)
         } else {
             let mut rng = sim::SimFlags::for_test("smoke_test").make_rng();
             sim::ScenarioGenerator::proletariat_robot(&map, &mut rng, &mut timer)
@@ -362,6 +362,30 @@ impl PrerenderInnards {
     pub fn draw_finished(&self, gfc_ctx_innards: GfxCtxInnards) {
         self.window_adapter.draw_finished(gfc_ctx_innards)
     }
+
+    /// Saves everything drawn so far in this frame as a PNG file. Call this before
+    /// `draw_finished`, which swaps buffers.
+    pub fn save_frame(&self, path: &str) -> Result<(), String> {
+        let size = self.window().inner_size();
+        let mut pixels = vec![0; (size.width * size.height * 4) as usize];
+        unsafe {
+            self.gl.read_pixels(
+                0,
+                0,
+                size.width as i32,
+                size.height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                &mut pixels,
+            );
+        }
+        let img = image::RgbaImage::from_raw(size.width, size.height, pixels)
+            .ok_or_else(|| "wrong number of pixels".to_string())?;
+        // OpenGL's origin is the bottom-left
+        image::imageops::flip_vertical(&img)
+            .save(path)
+            .map_err(|err| err.to_string())
+    }
 }
 
 /// Uploads a sprite sheet of textures to the GPU so they can be used by Fill::Texture and
@@ -21,6 +21,10 @@ pub enum UpdateType {
         max_x: f64,
         max_y: f64,
     },
+    /// Draw the current state and save exactly what's in the window as a PNG file.
+    ScreenCapture {
+        filename: String,
+    },
 }
 
 pub struct EventCtx<'a> {
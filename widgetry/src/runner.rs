@@ -10,7 +10,7 @@ use geom::Duration;
 
 use crate::app_state::App;
 use crate::assets::Assets;
-use crate::tools::screenshot::screenshot_everything;
+use crate::tools::screenshot::{screenshot_current_frame, screenshot_everything};
 use crate::{
     Canvas, Event, EventCtx, GfxCtx, Prerender, SharedAppState, Style, Text, UpdateType, UserInput,
 };
@@ -124,8 +124,14 @@ impl<A: SharedAppState> State<A> {
         }
     }
 
-    // Returns naming hint. Logically consumes the number of uploads.
-    pub(crate) fn draw(&mut self, prerender: &Prerender, screenshot: bool) -> Option<String> {
+    // Returns naming hint. Logically consumes the number of uploads. If capture_to is specified,
+    // the frame is also saved to that file.
+    pub(crate) fn draw(
+        &mut self,
+        prerender: &Prerender,
+        screenshot: bool,
+        capture_to: Option<&str>,
+    ) -> Option<String> {
         let mut g = GfxCtx::new(prerender, &self.canvas, &self.style, screenshot);
 
         self.canvas.start_drawing();
@@ -149,6 +155,11 @@ impl<A: SharedAppState> State<A> {
             );
         }
 
+        if let Some(path) = capture_to {
+            if let Err(err) = prerender.inner.save_frame(path) {
+                error!("Couldn't save frame to {}: {}", path, err);
+            }
+        }
         prerender.inner.draw_finished(g.inner);
         naming_hint
     }
@@ -275,7 +286,7 @@ pub fn run<
                 }
             }
             winit::event::Event::RedrawRequested(_) => {
-                state.draw(&prerender, false);
+                state.draw(&prerender, false, None);
                 prerender.num_uploads.set(0);
                 return;
             }
@@ -335,6 +346,9 @@ pub fn run<
                 } => {
                     screenshot_everything(&mut state, &dir, &prerender, zoom, max_x, max_y);
                 }
+                UpdateType::ScreenCapture { filename } => {
+                    screenshot_current_frame(&mut state, &prerender, &filename);
+                }
             }
         }
     });
@@ -32,7 +32,9 @@ pub(crate) fn screenshot_everything<A: SharedAppState>(
             state.canvas.cam_x = (tile_x as f64) * state.canvas.window_width;
             state.canvas.cam_y = (tile_y as f64) * state.canvas.window_height;
 
-            let suffix = state.draw(prerender, true).unwrap_or_else(String::new);
+            let suffix = state
+                .draw(prerender, true, None)
+                .unwrap_or_else(String::new);
             let filename = format!("{:02}x{:02}{}.gif", tile_x + 1, tile_y + 1, suffix);
 
             // TODO Is vsync or something else causing the above redraw to not actually show up in
@@ -54,6 +56,20 @@ pub(crate) fn screenshot_everything<A: SharedAppState>(
     finish(dir_path, filenames, num_tiles_x, num_tiles_y);
 }
 
+/// Unlike `screenshot_everything`, this reads the pixels back from the GPU, so no external tools
+/// are needed, and it doesn't matter if something else covers the window.
+pub(crate) fn screenshot_current_frame<A: SharedAppState>(
+    state: &mut State<A>,
+    prerender: &Prerender,
+    filename: &str,
+) {
+    if let Some(dir) = std::path::Path::new(filename).parent() {
+        fs::create_dir_all(dir).unwrap();
+    }
+    state.draw(prerender, false, Some(filename));
+    prerender.num_uploads.set(0);
+}
+
 fn screencap(filename: &str) -> bool {
     if !process::Command::new("scrot")
         .args(&[